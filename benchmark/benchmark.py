@@ -98,4 +98,25 @@ def benchmark_call_def_4pos_4name():
     return y
 
 
+def benchmark_small_lists():
+    y = 0
+    for x in range(REPEAT_100M):
+        y = len([x, x])
+    return y
+
+
+def benchmark_small_dicts():
+    y = 0
+    for x in range(REPEAT_100M):
+        y = len({"a": x, "b": x})
+    return y
+
+
+def benchmark_short_strings():
+    y = 0
+    for x in range(REPEAT_100M):
+        y = len("key_" + str(x % 10))
+    return y
+
+
 print(benchmark_call_def_1name())
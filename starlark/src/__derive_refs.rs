@@ -32,6 +32,7 @@ pub mod invoke_macro_error;
 pub mod param_spec;
 pub mod parse_args;
 pub mod sig;
+pub mod unpack_fields;
 
 // Re-exports for vtable registration macro.
 #[cfg(feature = "pagable")]
@@ -15,7 +15,9 @@
  * limitations under the License.
  */
 
+use allocative::Allocative;
 use dupe::Dupe;
+use once_cell::sync::Lazy;
 
 use crate::__derive_refs::param_spec::NativeCallableParam;
 use crate::__derive_refs::param_spec::NativeCallableParamDefaultValue;
@@ -33,12 +35,18 @@ use crate::typing::Ty;
 /// A wrapper for the parameters to `GlobalsBuilder::set_function` and `MethodBuilder::set_method`
 pub struct NativeCallableComponents {
     pub speculative_exec_safe: bool,
+    pub deterministic: bool,
     pub rust_docstring: Option<&'static str>,
     pub param_spec: NativeCallableParamSpec,
     pub return_type: Ty,
 }
 
 impl NativeCallableComponents {
+    /// Can be called by a hermetic evaluator.
+    pub(crate) fn is_deterministic(&self) -> bool {
+        self.deterministic || self.speculative_exec_safe
+    }
+
     fn doc_params(&self) -> DocParams {
         fn doc_param(p: &NativeCallableParam) -> DocParam {
             let NativeCallableParam { name, ty, required } = p;
@@ -65,19 +73,35 @@ impl NativeCallableComponents {
         }
     }
 
-    pub(crate) fn into_docs(self, as_type: Option<(Ty, DocType)>) -> DocItem {
-        let func_docs = DocFunction::from_docstring(
-            DocStringKind::Rust,
-            self.doc_params(),
-            self.return_type.clone(),
-            self.rust_docstring,
-        );
-        match as_type {
-            Some((_, ty_docs)) => DocItem::Type(DocType {
-                constructor: Some(func_docs),
-                ..ty_docs
-            }),
-            None => DocItem::Member(DocMember::Function(func_docs)),
-        }
+    /// Documentation is built on first access: parsing docstrings
+    /// is most of the cost of registering a module.
+    pub(crate) fn into_docs(self, as_type: Option<fn() -> DocType>) -> NativeCallableDocs {
+        NativeCallableDocs(Lazy::new(Box::new(move || {
+            let func_docs = DocFunction::from_docstring(
+                DocStringKind::Rust,
+                self.doc_params(),
+                self.return_type.clone(),
+                self.rust_docstring,
+            );
+            match as_type {
+                Some(ty_docs) => DocItem::Type(DocType {
+                    constructor: Some(func_docs),
+                    ..ty_docs()
+                }),
+                None => DocItem::Member(DocMember::Function(func_docs)),
+            }
+        })))
+    }
+}
+
+/// Documentation of a native function or method, computed when first requested.
+#[derive(Allocative)]
+pub(crate) struct NativeCallableDocs(
+    #[allocative(skip)] Lazy<DocItem, Box<dyn FnOnce() -> DocItem + Send>>,
+);
+
+impl NativeCallableDocs {
+    pub(crate) fn get(&self) -> &DocItem {
+        &self.0
     }
 }
@@ -0,0 +1,110 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Runtime support for `#[derive(FromStarlark)]`.
+
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::Ty;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::dict::DictRef;
+use crate::values::structs::StructRef;
+
+#[derive(Debug, thiserror::Error)]
+enum UnpackFieldsError {
+    #[error("Missing field `{1}` for `{0}`")]
+    Missing(&'static str, &'static str),
+    #[error("Unknown field `{1}` for `{0}`")]
+    Unknown(&'static str, String),
+    #[error("Field `{1}` for `{0}` expected `{2}`, got `{3}`")]
+    WrongType(&'static str, &'static str, Ty, String),
+    #[error("Dict for `{0}` has key `{1}`, but keys must be strings")]
+    NotStringKey(&'static str, String),
+}
+
+/// The fields of a struct or dict being unpacked into the Rust struct `type_name`.
+pub struct UnpackFields<'v> {
+    type_name: &'static str,
+    fields: SmallMap<&'v str, Value<'v>>,
+}
+
+impl<'v> UnpackFields<'v> {
+    /// The fields of `value` if it is a struct, otherwise `None`.
+    pub fn from_struct(type_name: &'static str, value: Value<'v>) -> Option<UnpackFields<'v>> {
+        let s = StructRef::from_value(value)?;
+        Some(UnpackFields {
+            type_name,
+            fields: s.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+        })
+    }
+
+    /// The entries of `value` if it is a dict, otherwise `None`. Keys must be strings.
+    pub fn from_dict(
+        type_name: &'static str,
+        value: Value<'v>,
+    ) -> crate::Result<Option<UnpackFields<'v>>> {
+        let Some(dict) = DictRef::from_value(value) else {
+            return Ok(None);
+        };
+        let fields = dict
+            .iter()
+            .map(|(k, v)| match k.unpack_str() {
+                Some(k) => Ok((k, v)),
+                None => Err(crate::Error::new_value(UnpackFieldsError::NotStringKey(
+                    type_name,
+                    k.to_repr(),
+                ))),
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Some(UnpackFields { type_name, fields }))
+    }
+
+    /// Take the field `name`, or `None` if it is missing.
+    pub fn take<T: UnpackValue<'v>>(&mut self, name: &'static str) -> crate::Result<Option<T>> {
+        let Some(value) = self.fields.shift_remove(&name) else {
+            return Ok(None);
+        };
+        match T::unpack_value(value)? {
+            Some(x) => Ok(Some(x)),
+            None => Err(crate::Error::new_value(UnpackFieldsError::WrongType(
+                self.type_name,
+                name,
+                T::starlark_type_repr(),
+                value.to_string_for_type_error(),
+            ))),
+        }
+    }
+
+    /// Take the field `name`, which must be present.
+    pub fn take_required<T: UnpackValue<'v>>(&mut self, name: &'static str) -> crate::Result<T> {
+        self.take(name)?.ok_or_else(|| {
+            crate::Error::new_value(UnpackFieldsError::Missing(self.type_name, name))
+        })
+    }
+
+    /// Error if any field was not taken.
+    pub fn finish(self) -> crate::Result<()> {
+        match self.fields.into_iter().next() {
+            None => Ok(()),
+            Some((name, _)) => Err(crate::Error::new_value(UnpackFieldsError::Unknown(
+                self.type_name,
+                name.to_owned(),
+            ))),
+        }
+    }
+}
@@ -19,7 +19,12 @@
 
 use std::collections::HashSet;
 
+pub use check::AstModuleCheck;
 pub use lint_message::LintMessage;
+pub use rule::LintContext;
+pub use rule::LintRule;
+pub use rule::Linter;
+pub use rule::apply_lint_fixes;
 pub use types::EvalMessage;
 pub use types::EvalSeverity;
 pub use types::Lint;
@@ -28,13 +33,16 @@ pub use unused_loads::remove::remove_unused_loads;
 use crate::analysis::types::LintT;
 use crate::syntax::AstModule;
 
+mod check;
 mod dubious;
 pub mod find_call_name;
 mod flow;
 mod incompatible;
+pub(crate) mod library;
 mod lint_message;
 mod names;
 mod performance;
+mod rule;
 mod types;
 mod underscore;
 mod unused_loads;
@@ -44,20 +52,30 @@ pub trait AstModuleLint {
     /// Run a static linter over the module. If the complete set of global variables are known
     /// they can be passed as the `globals` argument, resulting in name-resolution lint errors.
     /// The precise checks run by the linter are not considered stable between versions.
+    ///
+    /// To add checks of your own, or configure severities, use a [`Linter`].
     fn lint(&self, globals: Option<&HashSet<String>>) -> Vec<Lint>;
 }
 
 impl AstModuleLint for AstModule {
     fn lint(&self, globals: Option<&HashSet<String>>) -> Vec<Lint> {
-        let mut res = Vec::new();
-        res.extend(flow::lint(self).into_iter().map(LintT::erase));
-        res.extend(incompatible::lint(self).into_iter().map(LintT::erase));
-        res.extend(dubious::lint(self).into_iter().map(LintT::erase));
-        res.extend(names::lint(self, globals).into_iter().map(LintT::erase));
-        res.extend(underscore::lint(self).into_iter().map(LintT::erase));
-        res.extend(performance::lint(self).into_iter().map(LintT::erase));
-        res.retain(|issue| !self.is_suppressed(&issue.short_name, issue.location.span));
-        res
+        Linter::new().lint(self, globals, None)
+    }
+}
+
+/// The lints built into the crate.
+struct BuiltinRules;
+
+impl LintRule for BuiltinRules {
+    fn check(&self, ctx: &mut LintContext) {
+        let module = ctx.module();
+        let globals = ctx.globals();
+        ctx.extend(flow::lint(module).into_iter().map(LintT::erase));
+        ctx.extend(incompatible::lint(module).into_iter().map(LintT::erase));
+        ctx.extend(dubious::lint(module).into_iter().map(LintT::erase));
+        ctx.extend(names::lint(module, globals).into_iter().map(LintT::erase));
+        ctx.extend(underscore::lint(module).into_iter().map(LintT::erase));
+        ctx.extend(performance::lint(module).into_iter().map(LintT::erase));
     }
 }
 
@@ -0,0 +1,117 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Report every error in a module before evaluating it.
+
+use std::collections::HashMap;
+
+use dupe::Dupe;
+use starlark_syntax::syntax::module::AstModuleFields;
+
+use crate::environment::Globals;
+use crate::environment::names::MutableNames;
+use crate::errors::Diagnostic;
+use crate::eval::compiler::scope::ModuleScopes;
+use crate::eval::compiler::scope::scope_resolver_globals::ScopeResolverGlobals;
+use crate::syntax::AstModule;
+use crate::values::FrozenHeap;
+
+/// Check a module for the errors evaluation would stop at, without evaluating it.
+pub trait AstModuleCheck {
+    /// Resolve the names used in the module against `globals`, returning every error found,
+    /// like undefined variables, in source order. Evaluation stops at the first of these.
+    ///
+    /// Together with [`AstModule::parse_all`], which reports every syntax error, this
+    /// finds all the errors in a file which don't depend on running it:
+    ///
+    /// ```
+    /// use starlark::analysis::AstModuleCheck;
+    /// use starlark::environment::Globals;
+    /// use starlark::errors::Diagnostic;
+    /// use starlark::syntax::AstModule;
+    /// use starlark::syntax::Dialect;
+    ///
+    /// let program = "x = a\ny = b\n";
+    /// let diagnostics: Vec<Diagnostic> =
+    ///     match AstModule::parse_all("x.star", program.to_owned(), &Dialect::Standard) {
+    ///         Ok(ast) => ast.check(&Globals::standard()),
+    ///         Err(errors) => errors.iter().map(Diagnostic::from_error).collect(),
+    ///     };
+    /// assert_eq!(diagnostics.len(), 2);
+    /// ```
+    fn check(&self, globals: &Globals) -> Vec<Diagnostic>;
+}
+
+impl AstModuleCheck for AstModule {
+    fn check(&self, globals: &Globals) -> Vec<Diagnostic> {
+        let (codemap, statement, dialect, _) = self.clone().into_parts();
+        let names = MutableNames::new();
+        let frozen_heap = FrozenHeap::new();
+        let (errors, _) = ModuleScopes::check_module(
+            &names,
+            &frozen_heap,
+            &HashMap::new(),
+            statement,
+            ScopeResolverGlobals {
+                globals: Some(frozen_heap.alloc_any(globals.dupe())),
+            },
+            frozen_heap.alloc_any(codemap.dupe()),
+            &dialect,
+        );
+        let mut errors: Vec<crate::Error> = errors.into_iter().map(|e| e.into_error()).collect();
+        errors.sort_by_key(|e| e.span().map(|s| s.span.begin()));
+        errors.iter().map(Diagnostic::from_error).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::AstModuleCheck;
+    use crate::environment::Globals;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_check_reports_all_errors() {
+        let program = r#"
+def f():
+    return undefined_a
+
+x = undefined_b
+y = len([])
+z = undefined_c
+"#;
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+        let diagnostics = ast.check(&Globals::standard());
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.message.clone()).collect();
+        assert_eq!(
+            vec![
+                "Variable `undefined_a` not found",
+                "Variable `undefined_b` not found",
+                "Variable `undefined_c` not found",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_check_ok() {
+        let ast =
+            AstModule::parse("x.star", "x = len([])\n".to_owned(), &Dialect::Standard).unwrap();
+        assert!(ast.check(&Globals::standard()).is_empty());
+    }
+}
@@ -107,7 +107,7 @@ fn has_effect(x: &AstExpr) -> bool {
             matches!(x, AstLiteral::String(_))
         }
         Expr::Lambda(_) => false,
-        Expr::If(_) | Expr::Tuple(_) | Expr::List(_) | Expr::Dict(_) => {
+        Expr::If(_) | Expr::Tuple(_) | Expr::List(_) | Expr::Dict(_) | Expr::Set(_) => {
             let mut res = false;
             x.visit_expr(|x| res = res || has_effect(x));
             res
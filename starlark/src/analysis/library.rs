@@ -0,0 +1,177 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Uses of builtins which are not available in a [`Library`](crate::environment::Library).
+
+use std::collections::HashSet;
+
+use starlark_syntax::syntax::ast::AssignP;
+use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::AstParameter;
+use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::Clause;
+use starlark_syntax::syntax::ast::Expr;
+use starlark_syntax::syntax::ast::ForP;
+use starlark_syntax::syntax::ast::Stmt;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::uniplate::Visit;
+use thiserror::Error;
+
+use crate::analysis::EvalSeverity;
+use crate::analysis::Lint;
+use crate::analysis::types::LintT;
+use crate::analysis::types::LintWarning;
+use crate::codemap::CodeMap;
+use crate::codemap::Span;
+use crate::environment::LibraryVersion;
+use crate::syntax::AstModule;
+
+#[derive(Error, Debug)]
+pub(crate) enum LibraryWarning {
+    #[error("`{0}` is not available in this library")]
+    Disabled(String),
+    #[error("`{0}` was introduced in {1}, but the library is pinned to {2}")]
+    TooNew(String, LibraryVersion, LibraryVersion),
+}
+
+impl LintWarning for LibraryWarning {
+    fn severity(&self) -> EvalSeverity {
+        EvalSeverity::Warning
+    }
+
+    fn short_name(&self) -> &'static str {
+        match self {
+            LibraryWarning::Disabled(..) => "unavailable-builtin",
+            LibraryWarning::TooNew(..) => "builtin-too-new",
+        }
+    }
+}
+
+/// Names bound anywhere in the module, which shadow the builtins.
+fn bound_names<'a>(x: Visit<'a, AstNoPayload>, res: &mut HashSet<&'a str>) {
+    fn params<'a>(xs: &'a [AstParameter], res: &mut HashSet<&'a str>) {
+        for x in xs {
+            if let (Some(name), _, _) = x.split() {
+                res.insert(name.ident.as_str());
+            }
+        }
+    }
+
+    match &x {
+        Visit::Stmt(stmt) => {
+            let stmt: &'a AstStmt = stmt;
+            match &stmt.node {
+                Stmt::Assign(AssignP { lhs, .. }) | Stmt::AssignModify(lhs, _, _) => lhs
+                    .visit_lvalue(|x| {
+                        res.insert(x.ident.as_str());
+                    }),
+                Stmt::For(ForP { var, .. }) => var.visit_lvalue(|x| {
+                    res.insert(x.ident.as_str());
+                }),
+                Stmt::Def(def) => {
+                    res.insert(def.name.ident.as_str());
+                    params(&def.params, res);
+                }
+                Stmt::Load(load) => {
+                    for x in &load.args {
+                        res.insert(x.local.ident.as_str());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Visit::Expr(expr) => {
+            let expr: &'a AstExpr = expr;
+            match &expr.node {
+                Expr::Lambda(lambda) => params(&lambda.params, res),
+                Expr::ListComprehension(_, first, clauses)
+                | Expr::DictComprehension(_, first, clauses) => {
+                    let fors = clauses.iter().filter_map(|x| match x {
+                        Clause::For(x) => Some(x),
+                        Clause::If(_) => None,
+                    });
+                    for x in std::iter::once(&**first).chain(fors) {
+                        x.var.visit_lvalue(|x| {
+                            res.insert(x.ident.as_str());
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    x.visit_children(|x| bound_names(x, res));
+}
+
+/// The dotted path of an expression like `a.b.c`, with the span of each prefix.
+fn dotted_path(x: &AstExpr, res: &mut Vec<(String, Span)>) -> bool {
+    match &x.node {
+        Expr::Identifier(ident) => {
+            res.push((ident.ident.clone(), x.span));
+            true
+        }
+        Expr::Dot(object, attr) => {
+            if !dotted_path(object, res) {
+                return false;
+            }
+            let name = format!("{}.{}", res.last().unwrap().0, attr.node);
+            res.push((name, x.span));
+            true
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn lint(
+    module: &AstModule,
+    check: impl Fn(&str) -> Option<LibraryWarning>,
+) -> Vec<Lint> {
+    fn check_expr(
+        codemap: &CodeMap,
+        x: &AstExpr,
+        bound: &HashSet<&str>,
+        check: &impl Fn(&str) -> Option<LibraryWarning>,
+        res: &mut Vec<LintT<LibraryWarning>>,
+    ) {
+        let mut path = Vec::new();
+        if dotted_path(x, &mut path) {
+            if !bound.contains(path[0].0.as_str()) {
+                // Report only the shortest unavailable prefix, `json` rather than `json.decode`.
+                if let Some((warning, span)) = path
+                    .iter()
+                    .find_map(|(name, span)| Some((check(name)?, *span)))
+                {
+                    res.push(LintT::new(codemap, span, warning));
+                }
+            }
+            return;
+        }
+        x.visit_expr(|x| check_expr(codemap, x, bound, check, res));
+    }
+
+    let mut bound = HashSet::new();
+    bound_names(Visit::Stmt(module.statement()), &mut bound);
+    let mut res = Vec::new();
+    module
+        .statement()
+        .visit_expr(|x| check_expr(module.codemap(), x, &bound, &check, &mut res));
+    res.into_iter()
+        .map(LintT::erase)
+        .filter(|issue| !module.is_suppressed(&issue.short_name, issue.location.span))
+        .collect()
+}
@@ -0,0 +1,297 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Lint checks defined outside this crate, see [`LintRule`].
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::analysis::EvalSeverity;
+use crate::analysis::Lint;
+use crate::codemap::Span;
+use crate::syntax::AstModule;
+use crate::syntax::edit::Edit;
+use crate::typing::TypeMap;
+
+/// A lint check, run by a [`Linter`] alongside the built-in ones.
+///
+/// For example, to ban a builtin:
+///
+/// ```
+/// use starlark::analysis::EvalSeverity;
+/// use starlark::analysis::LintContext;
+/// use starlark::analysis::LintRule;
+/// use starlark::analysis::Linter;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+/// use starlark::syntax::ast::Expr;
+/// use starlark::syntax::edit::Edit;
+///
+/// struct NoPrint;
+///
+/// impl LintRule for NoPrint {
+///     fn check(&self, ctx: &mut LintContext) {
+///         let mut spans = Vec::new();
+///         ctx.module().visit_exprs(|x| {
+///             if let Expr::Identifier(name) = &x.node {
+///                 if name.node.ident == "print" {
+///                     spans.push(x.span);
+///                 }
+///             }
+///         });
+///         for span in spans {
+///             ctx.report_with_fix(
+///                 "banned-print",
+///                 EvalSeverity::Warning,
+///                 span,
+///                 "Use `log` rather than `print`",
+///                 vec![Edit::replace(span, "log")],
+///             );
+///         }
+///     }
+/// }
+///
+/// let ast =
+///     AstModule::parse("x.star", "print(1)\n".to_owned(), &Dialect::Standard).unwrap();
+/// let mut linter = Linter::new();
+/// linter.add_rule(NoPrint);
+/// let lints = linter.lint(&ast, None, None);
+/// assert_eq!("banned-print", lints[0].short_name);
+/// assert_eq!("log(1)\n", ast.apply_edits(lints[0].fixes.clone()).unwrap());
+/// ```
+pub trait LintRule {
+    /// Check the module, reporting problems to `ctx`.
+    fn check(&self, ctx: &mut LintContext);
+}
+
+/// The module being linted, and the lints found so far, given to [`LintRule::check`].
+pub struct LintContext<'a> {
+    module: &'a AstModule,
+    globals: Option<&'a HashSet<String>>,
+    types: Option<&'a TypeMap>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> LintContext<'a> {
+    /// The module being linted.
+    pub fn module(&self) -> &'a AstModule {
+        self.module
+    }
+
+    /// The names of all global variables, if they are known.
+    pub fn globals(&self) -> Option<&'a HashSet<String>> {
+        self.globals
+    }
+
+    /// The types of the bindings of the module, if it was typechecked, see
+    /// [`AstModuleTypecheck`](crate::typing::AstModuleTypecheck).
+    pub fn types(&self) -> Option<&'a TypeMap> {
+        self.types
+    }
+
+    /// Report a problem in `span` of the module, where `short_name` is a kebab-case name
+    /// for this kind of problem, used to configure its severity and suppress it with
+    /// `# starlark-lint-disable short-name` comments.
+    pub fn report(
+        &mut self,
+        short_name: &str,
+        severity: EvalSeverity,
+        span: Span,
+        problem: impl Display,
+    ) {
+        self.report_with_fix(short_name, severity, span, problem, Vec::new())
+    }
+
+    /// Like [`report`](LintContext::report), with edits of the source which fix the problem.
+    pub fn report_with_fix(
+        &mut self,
+        short_name: &str,
+        severity: EvalSeverity,
+        span: Span,
+        problem: impl Display,
+        fixes: Vec<Edit>,
+    ) {
+        let location = self.module.file_span(span);
+        self.lints.push(Lint {
+            original: location.source_span().to_owned(),
+            location,
+            short_name: short_name.to_owned(),
+            severity,
+            problem: problem.to_string(),
+            fixes,
+        });
+    }
+
+    pub(crate) fn extend(&mut self, lints: impl IntoIterator<Item = Lint>) {
+        self.lints.extend(lints);
+    }
+}
+
+/// Runs the built-in lints and any [`LintRule`]s added.
+pub struct Linter<'a> {
+    builtin: bool,
+    rules: Vec<Box<dyn LintRule + 'a>>,
+    severities: HashMap<String, EvalSeverity>,
+}
+
+impl<'a> Linter<'a> {
+    /// A linter which runs the built-in lints, as
+    /// [`AstModuleLint::lint`](crate::analysis::AstModuleLint::lint) does.
+    pub fn new() -> Self {
+        Linter {
+            builtin: true,
+            rules: Vec::new(),
+            severities: HashMap::new(),
+        }
+    }
+
+    /// Only run the rules added with [`add_rule`](Linter::add_rule).
+    pub fn disable_builtin(&mut self) {
+        self.builtin = false;
+    }
+
+    /// Also run `rule`, after the built-in lints and the rules added before it.
+    pub fn add_rule(&mut self, rule: impl LintRule + 'a) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Report lints named `short_name` with `severity`, whichever rule reports them.
+    /// Lints with [`EvalSeverity::Disabled`] are still returned, and usually not shown.
+    pub fn set_severity(&mut self, short_name: &str, severity: EvalSeverity) {
+        self.severities.insert(short_name.to_owned(), severity);
+    }
+
+    /// Lint `module`. Lints suppressed by a `# starlark-lint-disable` comment are dropped.
+    /// The `globals` and `types` are passed on to the rules, and if `globals` are given,
+    /// the built-in lints also report names which can't be resolved.
+    pub fn lint(
+        &self,
+        module: &AstModule,
+        globals: Option<&HashSet<String>>,
+        types: Option<&TypeMap>,
+    ) -> Vec<Lint> {
+        let mut ctx = LintContext {
+            module,
+            globals,
+            types,
+            lints: Vec::new(),
+        };
+        if self.builtin {
+            crate::analysis::BuiltinRules.check(&mut ctx);
+        }
+        for rule in &self.rules {
+            rule.check(&mut ctx);
+        }
+        let mut res = ctx.lints;
+        res.retain(|issue| !module.is_suppressed(&issue.short_name, issue.location.span));
+        for issue in &mut res {
+            if let Some(severity) = self.severities.get(&issue.short_name) {
+                issue.severity = *severity;
+            }
+        }
+        res
+    }
+}
+
+/// Apply the fixes of all of `lints` to the source of `module`, as
+/// [`AstModule::apply_edits`] does. Fails if any fixes overlap.
+pub fn apply_lint_fixes(module: &AstModule, lints: &[Lint]) -> crate::Result<String> {
+    module.apply_edits(
+        lints
+            .iter()
+            .flat_map(|lint| lint.fixes.iter().cloned())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::EvalSeverity;
+    use crate::analysis::LintContext;
+    use crate::analysis::LintRule;
+    use crate::analysis::Linter;
+    use crate::analysis::apply_lint_fixes;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::syntax::ast::Stmt;
+    use crate::syntax::edit::Edit;
+
+    /// Top-level assignments must be to upper case names.
+    struct UpperCaseGlobals;
+
+    impl LintRule for UpperCaseGlobals {
+        fn check(&self, ctx: &mut LintContext) {
+            let module = ctx.module();
+            let Stmt::Statements(stmts) = &module.statement().node else {
+                return;
+            };
+            for stmt in stmts {
+                if let Stmt::Assign(assign) = &stmt.node {
+                    let span = assign.lhs.span;
+                    let name = module.source_span(span);
+                    if name != name.to_uppercase() {
+                        ctx.report_with_fix(
+                            "lower-case-global",
+                            EvalSeverity::Advice,
+                            span,
+                            format!("Global `{name}` should be upper case"),
+                            vec![Edit::replace(span, name.to_uppercase())],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::AllOptionsInternal).unwrap()
+    }
+
+    #[test]
+    fn test_lint_rule() {
+        let m = module(
+            r#"
+x = 1
+Y = 2
+z = 3 # starlark-lint-disable lower-case-global
+def f():
+    a = 1
+"#,
+        );
+        let mut linter = Linter::new();
+        linter.add_rule(UpperCaseGlobals);
+        let res = linter.lint(&m, None, None);
+        assert_eq!(
+            vec!["unused-assign", "lower-case-global"],
+            res.iter()
+                .map(|x| x.short_name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(EvalSeverity::Advice, res[1].severity);
+        assert_eq!(
+            "\nX = 1\nY = 2\nz = 3 # starlark-lint-disable lower-case-global\ndef f():\n    a = 1\n",
+            apply_lint_fixes(&m, &res).unwrap()
+        );
+
+        linter.disable_builtin();
+        linter.set_severity("lower-case-global", EvalSeverity::Warning);
+        let res = linter.lint(&m, None, None);
+        assert_eq!(1, res.len());
+        assert_eq!(EvalSeverity::Warning, res[0].severity);
+    }
+}
@@ -21,11 +21,13 @@ use std::path::Path;
 
 use dupe::Dupe;
 use serde::Serialize;
+use starlark_syntax::span_display::DiagnosticRenderOptions;
 
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
+use crate::syntax::edit::Edit;
 
 pub(crate) trait LintWarning: Display {
     fn severity(&self) -> EvalSeverity;
@@ -55,6 +57,9 @@ pub struct Lint {
     pub problem: String,
     /// The source code at [`location`](Lint::location).
     pub original: String,
+    /// Edits of the source which fix the problem, if any, see
+    /// [`apply_lint_fixes`](crate::analysis::apply_lint_fixes).
+    pub fixes: Vec<Edit>,
 }
 
 impl Display for Lint {
@@ -86,6 +91,7 @@ impl<T: LintWarning> LintT<T> {
             severity: self.problem.severity(),
             problem: self.problem.to_string(),
             original: self.original,
+            fixes: Vec::new(),
         }
     }
 }
@@ -124,7 +130,8 @@ pub struct EvalMessage {
     pub span: Option<ResolvedSpan>,
     /// How severed the problem is.
     pub severity: EvalSeverity,
-    /// The general name of the issue.
+    /// The general name of the issue: the lint short name, or for errors the error code,
+    /// like `E0301`.
     pub name: String,
     /// The details of the issue, generally displayed to the user.
     pub description: String,
@@ -147,10 +154,36 @@ impl Display for EvalMessage {
 impl EvalMessage {
     /// Produce an `EvalMessage` from a `starlark::Error`
     pub fn from_error(file: &Path, err: &crate::Error) -> Self {
-        if let Some(span) = err.span() {
-            return Self::from_diagnostic(span, err.without_diagnostic(), err);
-        }
-        Self::from_any_error(file, err)
+        let mut message = match err.span() {
+            Some(span) => Self::from_diagnostic(span, err.without_diagnostic(), err),
+            None => Self::from_any_error(file, err),
+        };
+        message.name = err.code().to_string();
+        message
+    }
+
+    /// Like `from_error`, but with `full_error_with_span` rendered as configured by
+    /// `options`, including the error code, and the messages of `options.catalog`.
+    pub fn from_error_with_options(
+        file: &Path,
+        err: &crate::Error,
+        options: &DiagnosticRenderOptions,
+    ) -> Self {
+        let mut message = match (err.span(), &options.catalog) {
+            (Some(span), Some(catalog)) => Self::from_diagnostic(
+                span,
+                err.message_from_catalog(&**catalog),
+                err.display_with_options(options),
+            ),
+            (Some(span), None) => Self::from_diagnostic(
+                span,
+                err.without_diagnostic(),
+                err.display_with_options(options),
+            ),
+            (None, _) => Self::from_any_error(file, &err.display_with_options(options)),
+        };
+        message.name = err.code().to_string();
+        message
     }
 
     /// Create an `EvalMessage` from any kind of error
@@ -46,7 +46,7 @@ fn test_unused_loads(name: &str, program: &str) {
                 writeln!(out).unwrap();
 
                 let span = codemap.file_span(span);
-                let display = span_display(Some(span.as_ref()), "Unused load", false);
+                let display = span_display(Some(span.as_ref()), "Unused load", None, false);
                 write!(out, "{display}").unwrap();
             }
         }
@@ -44,3 +44,6 @@ mod assert;
 mod conformance;
 
 pub use assert::*;
+pub use conformance::ConformanceCase;
+pub use conformance::ConformanceOutcome;
+pub use conformance::ConformanceReport;
@@ -108,18 +108,22 @@ enum GcStrategy {
 #[starlark_module]
 // Deliberately qualify the GlobalsBuild type to test that we can
 fn asserts_star(builder: &mut crate::environment::GlobalsBuilder) {
+    #[starlark(deterministic)]
     fn eq<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_equals(a, b)
     }
 
+    #[starlark(deterministic)]
     fn ne<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_different(a, b)
     }
 
+    #[starlark(deterministic)]
     fn lt<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_less_than(a, b)
     }
 
+    #[starlark(deterministic)]
     fn contains<'v>(xs: Value<'v>, x: Value<'v>) -> starlark::Result<NoneType> {
         if !xs.is_in(x)? {
             Err(anyhow::anyhow!("assert.contains: expected {} to be in {}", x, xs).into())
@@ -128,15 +132,18 @@ fn asserts_star(builder: &mut crate::environment::GlobalsBuilder) {
         }
     }
 
+    #[starlark(deterministic)]
     fn r#true(x: Value) -> starlark::Result<NoneType> {
         assert_equals(Value::new_bool(x.to_bool()), Value::new_bool(true))
     }
 
     // We don't allow this at runtime - just to be compatible with the Go Starlark test suite
+    #[starlark(deterministic)]
     fn freeze<'v>(x: Value<'v>) -> anyhow::Result<Value<'v>> {
         Ok(x)
     }
 
+    #[starlark(deterministic)]
     fn fails<'v>(
         f: Value<'v>,
         msg: &str,
@@ -156,22 +163,27 @@ pub(crate) fn test_functions(builder: &mut GlobalsBuilder) {
     const fibonacci: Vec<i32> = vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55, 89];
 
     // Approximate version of a method used by the Go test suite
+    #[starlark(deterministic)]
     fn hasfields<'v>() -> anyhow::Result<impl AllocValue<'v>> {
         Ok(AllocStruct::EMPTY)
     }
 
+    #[starlark(deterministic)]
     fn assert_eq<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_equals(a, b)
     }
 
+    #[starlark(deterministic)]
     fn assert_ne<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_different(a, b)
     }
 
+    #[starlark(deterministic)]
     fn assert_lt<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_less_than(a, b)
     }
 
+    #[starlark(deterministic)]
     fn assert_true(a: Value) -> anyhow::Result<NoneType> {
         if !a.to_bool() {
             Err(anyhow::anyhow!("assertion failed"))
@@ -180,6 +192,7 @@ pub(crate) fn test_functions(builder: &mut GlobalsBuilder) {
         }
     }
 
+    #[starlark(deterministic)]
     fn assert_false(a: Value) -> anyhow::Result<NoneType> {
         if a.to_bool() {
             Err(anyhow::anyhow!("assertion failed"))
@@ -194,6 +207,7 @@ pub(crate) fn test_functions(builder: &mut GlobalsBuilder) {
         Ok(NoneType)
     }
 
+    #[starlark(deterministic)]
     fn assert_type<'v>(v: Value<'v>, ty: Value<'v>, heap: Heap<'v>) -> starlark::Result<NoneType> {
         TypeCompiled::new(ty, heap)?.check_type(v, Some("v"))?;
         Ok(NoneType)
@@ -311,6 +325,14 @@ impl<'a> Assert<'a> {
         eval.eval_module(ast, &self.globals).map_err(Into::into)
     }
 
+    /// Execute `program` once, with the default GC settings, returning any error.
+    pub(super) fn execute_result(&self, program: &str) -> crate::Result<()> {
+        Module::with_temp_heap(|env| {
+            self.execute("assert.bzl", program, &env, GcStrategy::Auto)
+                .map(|_| ())
+        })
+    }
+
     fn execute_fail<'v>(
         &self,
         func: &str,
@@ -595,6 +617,14 @@ Value 2
     }
 }
 
+/// The `asserts.star` module used by the Go Starlark test suite, which defines `asserts`
+/// (with `eq`, `ne`, `lt`, `contains`, `true` and `fails`) and `freeze`.
+///
+/// Useful for running Starlark test files outside of [`Assert`].
+pub fn asserts_module() -> FrozenModule {
+    Lazy::force(&ASSERTS_STAR).dupe()
+}
+
 /// See [`Assert::eq`].
 pub fn eq(lhs: &str, rhs: &str) {
     Assert::new().eq(lhs, rhs)
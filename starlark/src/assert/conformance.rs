@@ -17,6 +17,9 @@
 
 //! Run conformance tests, which are used by the Go starlark.
 //! e.g. <https://github.com/google/skylark/tree/master/testdata>
+//!
+//! A test file is split into test cases by `---` lines. A test case with a line
+//! containing `### message` is expected to fail at that line, all others to succeed.
 
 // `if_then_panic` is only in newer clippy, delete this in future.
 #![allow(unknown_lints)]
@@ -24,6 +27,7 @@
 #![allow(clippy::if_then_panic)]
 
 use itertools::Itertools;
+use serde::Serialize;
 
 use crate::assert::assert::Assert;
 
@@ -55,12 +59,119 @@ impl<'a> Assert<'a> {
             panic!("Exception given but not used, `{missed}`");
         }
     }
+
+    /// Run every test case of the conformance test `code`, from the file `file`,
+    /// with the [dialect](Assert::dialect) and globals of this `Assert`, and report
+    /// the outcome of each rather than panicking at the first failure.
+    ///
+    /// The report is for finding where this implementation diverges from others,
+    /// e.g. under [`Dialect::Standard`](crate::syntax::Dialect::Standard).
+    pub fn conformance_report(&self, file: &str, code: &str) -> ConformanceReport {
+        let cases = ConformanceTest::parse(code)
+            .into_iter()
+            .map(|x| ConformanceCase {
+                line: x.first_line,
+                outcome: x.outcome(self),
+            })
+            .collect();
+        ConformanceReport {
+            file: file.to_owned(),
+            cases,
+        }
+    }
+}
+
+/// The outcome of running a test case, see [`Assert::conformance_report`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ConformanceOutcome {
+    /// Passed, or failed at the line marked with `###`.
+    Pass,
+    /// Expected to pass, but failed with this error.
+    UnexpectedError {
+        /// The error.
+        error: String,
+    },
+    /// Expected to fail at `line` of the file, but passed.
+    MissingError {
+        /// The line of the file with `###`.
+        line: usize,
+        /// The text after `###`, usually the message of the error from Go Starlark.
+        expected: String,
+    },
+    /// Failed, but not at the line marked with `###`.
+    WrongLine {
+        /// The line of the file with `###`.
+        line: usize,
+        /// The line of the file where the error is, if it has a location.
+        got: Option<usize>,
+        /// The error.
+        error: String,
+    },
+}
+
+/// One test case of a conformance report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConformanceCase {
+    /// The first line of the test case in the file, starting at 1.
+    pub line: usize,
+    /// What happened when running it.
+    #[serde(flatten)]
+    pub outcome: ConformanceOutcome,
+}
+
+/// The outcome of every test case of a conformance test file, produced by
+/// [`Assert::conformance_report`]. Serializes to JSON with [`to_json`](ConformanceReport::to_json).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConformanceReport {
+    /// The name of the file, as given.
+    pub file: String,
+    /// The test cases, in the order they occur in the file.
+    pub cases: Vec<ConformanceCase>,
+}
+
+impl ConformanceReport {
+    /// The number of test cases which passed.
+    pub fn passed(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|x| x.outcome == ConformanceOutcome::Pass)
+            .count()
+    }
+
+    /// The test cases which didn't pass.
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCase> {
+        self.cases
+            .iter()
+            .filter(|x| x.outcome != ConformanceOutcome::Pass)
+    }
+
+    /// The report as JSON, with the number of test cases which passed and failed.
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Json<'a> {
+            #[serde(flatten)]
+            report: &'a ConformanceReport,
+            passed: usize,
+            failed: usize,
+        }
+
+        let passed = self.passed();
+        serde_json::to_string_pretty(&Json {
+            report: self,
+            passed,
+            failed: self.cases.len() - passed,
+        })
+        .unwrap()
+    }
 }
 
 /// Describe a conformance test
 struct ConformanceTest {
     /// The code of the test
     code: String,
+    /// The line of the file the test starts at, starting at 1
+    first_line: usize,
     /// If this might throw an error, what is it
     error: Option<(usize, String)>,
 }
@@ -68,11 +179,18 @@ struct ConformanceTest {
 impl ConformanceTest {
     fn parse(code: &str) -> Vec<Self> {
         // First split on "---"
+        let mut first_line = 1;
         code.lines()
             .collect::<Vec<_>>()
             .split(|x| *x == "---")
             .map(|xs| Self {
                 code: xs.join("\n"),
+                first_line: {
+                    let res = first_line;
+                    // Skip the lines of the test and the `---` after it.
+                    first_line += xs.len() + 1;
+                    res
+                },
                 error: xs
                     .iter()
                     .find_position(|x| x.contains("###"))
@@ -86,6 +204,34 @@ impl ConformanceTest {
             .collect()
     }
 
+    fn outcome(&self, assert: &Assert) -> ConformanceOutcome {
+        // Lines in the file rather than the test case.
+        let file_line = |line: usize| line + self.first_line - 1;
+        let res = assert.execute_result(&self.code);
+        match (&self.error, res) {
+            (None, Ok(())) => ConformanceOutcome::Pass,
+            (None, Err(e)) => ConformanceOutcome::UnexpectedError {
+                error: format!("{e:#}"),
+            },
+            (Some((line, expected)), Ok(())) => ConformanceOutcome::MissingError {
+                line: file_line(*line),
+                expected: expected.clone(),
+            },
+            (Some((line, _)), Err(e)) => {
+                let got = e.span().map(|span| span.resolve_span().begin.line + 1);
+                if got == Some(*line) {
+                    ConformanceOutcome::Pass
+                } else {
+                    ConformanceOutcome::WrongLine {
+                        line: file_line(*line),
+                        got: got.map(file_line),
+                        error: format!("{e:#}"),
+                    }
+                }
+            }
+        }
+    }
+
     fn test(&self, assert: &Assert) {
         fn get_line(err: &crate::Error) -> Option<usize> {
             err.span().map(|span| span.resolve_span().begin.line + 1)
@@ -26,6 +26,7 @@ use std::ops::Deref;
 use std::ptr;
 
 pub use starlark_derive::Coerce;
+use starlark_map::small_map::InlineEntries;
 use starlark_map::small_map::SmallMap;
 use starlark_map::small_set::SmallSet;
 
@@ -110,6 +111,15 @@ where
 {
 }
 
+unsafe impl<FromK, FromV, ToK, ToV, const N: usize>
+    Coerce<SmallMap<ToK, ToV, InlineEntries<ToK, ToV, N>>>
+    for SmallMap<FromK, FromV, InlineEntries<FromK, FromV, N>>
+where
+    FromK: CoerceKey<ToK>,
+    FromV: Coerce<ToV>,
+{
+}
+
 unsafe impl<From, To> Coerce<SmallSet<To>> for SmallSet<From> where From: Coerce<To> {}
 
 /// Safely convert between types which have a `Coerce` relationship.
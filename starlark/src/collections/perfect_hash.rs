@@ -0,0 +1,228 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A lookup index over a fixed set of hashes, built once and then only read.
+//!
+//! This is hash-and-displace: hashes are split into small groups, and each
+//! group gets its own seed, chosen so that all hashes of the group land in
+//! slots no earlier group took. Groups are placed largest first, while the
+//! table is still empty, so every group quickly finds a seed and every hash
+//! gets its own slot. A lookup is then two loads and one key comparison.
+//!
+//! Equal hashes can never be separated, so if any two entries share the full
+//! hash we fall back to linear probing, which is still correct, just not perfect.
+
+use std::cmp::Reverse;
+
+use allocative::Allocative;
+
+/// Number of seeds to try for a group before growing the table.
+const SEEDS_PER_GROUP: u32 = 1 << 12;
+/// Number of times to double the table before giving up on a perfect layout.
+const MAX_GROWTH: u32 = 3;
+/// Average number of hashes in a group.
+const GROUP_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Allocative)]
+pub(crate) struct PerfectHashIndex {
+    /// Index of the entry in each slot plus one, or zero for an empty slot.
+    slots: Box<[u32]>,
+    /// Seed of each group.
+    seeds: Box<[u32]>,
+    /// `64 - log2(seeds.len())`, so the top bits of the hash pick the group.
+    group_shift: u32,
+    /// `64 - log2(slots.len())`, so the top bits of the mixed hash pick the slot.
+    slot_shift: u32,
+    /// Every entry is in the slot its hash selects, so lookups never probe.
+    perfect: bool,
+}
+
+impl PerfectHashIndex {
+    /// Build an index over entries with the given hashes, in entry order.
+    pub(crate) fn new(hashes: &[u64]) -> Self {
+        assert!(hashes.len() < u32::MAX as usize);
+        let group_bits = Self::bits(hashes.len() / GROUP_SIZE);
+        // Keep the table at most 80% full.
+        let slot_bits = Self::bits(hashes.len() + hashes.len() / 4);
+        if !Self::has_equal(hashes) {
+            for slot_bits in slot_bits..=slot_bits + MAX_GROWTH {
+                if let Some(index) = Self::displaced(hashes, group_bits, slot_bits) {
+                    return index;
+                }
+            }
+        }
+        PerfectHashIndex {
+            slots: Self::probed(hashes, slot_bits),
+            seeds: vec![0; 1 << group_bits].into_boxed_slice(),
+            group_shift: 64 - group_bits,
+            slot_shift: 64 - slot_bits,
+            perfect: false,
+        }
+    }
+
+    /// Number of bits to index a table of at least `len` elements (and at least two).
+    fn bits(len: usize) -> u32 {
+        len.next_power_of_two().trailing_zeros().max(1)
+    }
+
+    fn has_equal(hashes: &[u64]) -> bool {
+        let mut sorted = hashes.to_vec();
+        sorted.sort_unstable();
+        sorted.windows(2).any(|w| w[0] == w[1])
+    }
+
+    #[inline]
+    fn group(hash: u64, group_shift: u32) -> usize {
+        (hash.wrapping_mul(0x9E3779B97F4A7C15) >> group_shift) as usize
+    }
+
+    #[inline]
+    fn slot(hash: u64, seed: u32, slot_shift: u32) -> usize {
+        let x = (hash ^ (seed as u64).wrapping_mul(0xD6E8FEB86659FD93))
+            .wrapping_mul(0xFF51AFD7ED558CCD);
+        ((x ^ (x >> 32)).wrapping_mul(0xC4CEB9FE1A85EC53) >> slot_shift) as usize
+    }
+
+    fn displaced(hashes: &[u64], group_bits: u32, slot_bits: u32) -> Option<Self> {
+        let group_shift = 64 - group_bits;
+        let slot_shift = 64 - slot_bits;
+        let mut groups: Vec<Vec<u32>> = vec![Vec::new(); 1 << group_bits];
+        for (i, hash) in hashes.iter().enumerate() {
+            groups[Self::group(*hash, group_shift)].push(i as u32);
+        }
+        let mut order: Vec<usize> = (0..groups.len()).collect();
+        order.sort_by_key(|g| Reverse(groups[*g].len()));
+
+        let mut slots = vec![0; 1 << slot_bits].into_boxed_slice();
+        let mut seeds = vec![0; groups.len()].into_boxed_slice();
+        let mut taken = Vec::new();
+        for g in order {
+            let group = &groups[g];
+            if group.is_empty() {
+                break;
+            }
+            let seed = (0..SEEDS_PER_GROUP).find(|seed| {
+                taken.clear();
+                group.iter().all(|i| {
+                    let s = Self::slot(hashes[*i as usize], *seed, slot_shift);
+                    if slots[s] != 0 || taken.contains(&s) {
+                        return false;
+                    }
+                    taken.push(s);
+                    true
+                })
+            })?;
+            for (i, s) in group.iter().zip(&taken) {
+                slots[*s] = i + 1;
+            }
+            seeds[g] = seed;
+        }
+        Some(PerfectHashIndex {
+            slots,
+            seeds,
+            group_shift,
+            slot_shift,
+            perfect: true,
+        })
+    }
+
+    fn probed(hashes: &[u64], slot_bits: u32) -> Box<[u32]> {
+        let mut slots = vec![0; 1 << slot_bits].into_boxed_slice();
+        let mask = slots.len() - 1;
+        for (i, hash) in hashes.iter().enumerate() {
+            let mut s = Self::slot(*hash, 0, 64 - slot_bits);
+            while slots[s] != 0 {
+                s = (s + 1) & mask;
+            }
+            slots[s] = i as u32 + 1;
+        }
+        slots
+    }
+
+    /// Whether every lookup takes a single probe.
+    #[cfg(test)]
+    pub(crate) fn is_perfect(&self) -> bool {
+        self.perfect
+    }
+
+    /// Find the index of the entry with the given hash for which `eq` holds.
+    #[inline]
+    pub(crate) fn find(&self, hash: u64, mut eq: impl FnMut(usize) -> bool) -> Option<usize> {
+        let seed = self.seeds[Self::group(hash, self.group_shift)];
+        let mut s = Self::slot(hash, seed, self.slot_shift);
+        if self.perfect {
+            return match self.slots[s] {
+                0 => None,
+                i if eq(i as usize - 1) => Some(i as usize - 1),
+                _ => None,
+            };
+        }
+        let mask = self.slots.len() - 1;
+        loop {
+            match self.slots[s] {
+                0 => return None,
+                i if eq(i as usize - 1) => return Some(i as usize - 1),
+                _ => s = (s + 1) & mask,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collections::perfect_hash::PerfectHashIndex;
+
+    #[test]
+    fn test_perfect_hash_distinct() {
+        let hashes: Vec<u64> = (0..200u64).map(|x| x.wrapping_mul(0x1234567)).collect();
+        let index = PerfectHashIndex::new(&hashes);
+        assert!(index.is_perfect());
+        for (i, h) in hashes.iter().enumerate() {
+            assert_eq!(Some(i), index.find(*h, |j| hashes[j] == *h));
+        }
+        assert_eq!(None, index.find(7, |j| hashes[j] == 7));
+    }
+
+    #[test]
+    fn test_perfect_hash_many() {
+        // Close hashes, in a table too big to find by trying seeds for the whole table.
+        let hashes: Vec<u64> = (0..5000u64).collect();
+        let index = PerfectHashIndex::new(&hashes);
+        assert!(index.is_perfect());
+        for (i, h) in hashes.iter().enumerate() {
+            assert_eq!(Some(i), index.find(*h, |j| hashes[j] == *h));
+        }
+    }
+
+    #[test]
+    fn test_perfect_hash_collisions() {
+        // Equal hashes can never be separated, so this has to probe.
+        let hashes = [5, 5, 5, 9];
+        let index = PerfectHashIndex::new(&hashes);
+        assert!(!index.is_perfect());
+        for (i, &hash) in hashes.iter().enumerate() {
+            assert_eq!(Some(i), index.find(hash, |j| j == i));
+        }
+        assert_eq!(None, index.find(5, |_| false));
+    }
+
+    #[test]
+    fn test_perfect_hash_empty() {
+        let index = PerfectHashIndex::new(&[]);
+        assert_eq!(None, index.find(0, |_| true));
+    }
+}
@@ -15,5 +15,6 @@
  * limitations under the License.
  */
 
+pub(crate) mod frozen_map;
 pub(crate) mod map;
 pub(crate) mod symbol;
@@ -0,0 +1,127 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A read-only [`SymbolMap`], laid out with a perfect hash once it is built.
+
+use std::fmt;
+use std::fmt::Debug;
+
+use allocative::Allocative;
+use starlark_map::Hashed;
+
+use crate::collections::perfect_hash::PerfectHashIndex;
+use crate::collections::symbol::map::SymbolMap;
+use crate::collections::symbol::symbol::Symbol;
+
+/// Used for tables which are looked up far more often than they are built,
+/// like the globals and the methods of a type.
+#[derive(Clone, Allocative)]
+pub(crate) struct FrozenSymbolMap<T> {
+    entries: Box<[(Symbol, T)]>,
+    index: PerfectHashIndex,
+}
+
+impl<T: Debug> Debug for FrozenSymbolMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|x| (&x.0, &x.1)))
+            .finish()
+    }
+}
+
+impl<T> From<SymbolMap<T>> for FrozenSymbolMap<T> {
+    fn from(map: SymbolMap<T>) -> Self {
+        let entries: Box<[(Symbol, T)]> = map.into_entries().collect();
+        let hashes: Vec<u64> = entries.iter().map(|x| x.0.hash()).collect();
+        FrozenSymbolMap {
+            index: PerfectHashIndex::new(&hashes),
+            entries,
+        }
+    }
+}
+
+impl<T> FrozenSymbolMap<T> {
+    #[inline]
+    fn find(&self, hash: u64, eq: impl Fn(&Symbol) -> bool) -> Option<&T> {
+        let i = self.index.find(hash, |i| eq(&self.entries[i].0))?;
+        Some(&self.entries[i].1)
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, key: &Symbol) -> Option<&T> {
+        self.find(key.hash(), |x| key == x)
+    }
+
+    pub(crate) fn get_str(&self, key: &str) -> Option<&T> {
+        self.get_hashed_str(Hashed::new(key))
+    }
+
+    pub(crate) fn get_hashed_str(&self, key: Hashed<&str>) -> Option<&T> {
+        self.find(key.hash().promote(), |x| x.as_str() == *key.key())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether every lookup takes a single probe.
+    #[cfg(test)]
+    pub(crate) fn is_perfect(&self) -> bool {
+        self.index.is_perfect()
+    }
+
+    pub(crate) fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a (Symbol, T)> + 'a {
+        self.entries.iter()
+    }
+
+    pub(crate) fn keys<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a Symbol> + 'a {
+        self.iter().map(|x| &x.0)
+    }
+
+    pub(crate) fn values<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a T> + 'a {
+        self.iter().map(|x| &x.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark_map::Hashed;
+
+    use crate::collections::symbol::frozen_map::FrozenSymbolMap;
+    use crate::collections::symbol::map::SymbolMap;
+    use crate::collections::symbol::symbol::Symbol;
+
+    #[test]
+    fn test_frozen_symbol_map() {
+        let mut map = SymbolMap::new();
+        for i in 0..100 {
+            map.insert(&format!("name_{i}"), i);
+        }
+        let map = FrozenSymbolMap::from(map);
+        assert_eq!(100, map.len());
+        for i in 0..100 {
+            let name = format!("name_{i}");
+            assert_eq!(Some(&i), map.get_str(&name));
+            assert_eq!(Some(&i), map.get_hashed_str(Hashed::new(&name)));
+            assert_eq!(Some(&i), map.get(&Symbol::new(&name)));
+        }
+        assert_eq!(None, map.get_str("name_100"));
+        assert_eq!(None, map.get_str(""));
+        assert_eq!(100, map.keys().count());
+        assert!(map.is_perfect());
+    }
+}
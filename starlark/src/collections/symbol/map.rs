@@ -94,15 +94,13 @@ impl<T> SymbolMap<T> {
     }
 
     pub(crate) fn get_hashed_string_value(&self, key: Hashed<StringValue>) -> Option<&T> {
-        self.0
-            .find(key.hash().promote(), |x| {
-                x.0.as_aligned_padded_str() == key.key().as_aligned_padded_str()
-            })
-            .map(|x| &x.1)
-    }
-
-    pub(crate) fn len(&self) -> usize {
-        self.0.len()
+        match key.key().as_aligned_padded_str() {
+            Some(s) => self
+                .0
+                .find(key.hash().promote(), |x| x.0.as_aligned_padded_str() == s)
+                .map(|x| &x.1),
+            None => self.get_hashed_str(Hashed::new_unchecked(key.hash(), key.key().as_str())),
+        }
     }
 
     pub(crate) fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a (Symbol, T)> + 'a {
@@ -113,7 +111,7 @@ impl<T> SymbolMap<T> {
         self.iter().map(|x| &x.0)
     }
 
-    pub(crate) fn values<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a T> + 'a {
-        self.iter().map(|x| &x.1)
+    pub(crate) fn into_entries(self) -> impl ExactSizeIterator<Item = (Symbol, T)> {
+        self.0.into_iter()
     }
 }
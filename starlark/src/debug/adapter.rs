@@ -272,6 +272,7 @@ pub struct InspectVariableInfo {
 }
 
 /// Information about expression evaluation result
+#[derive(Clone, Debug)]
 pub struct EvaluateExprInfo {
     /// The value as a String.
     pub result: String,
@@ -281,6 +282,16 @@ pub struct EvaluateExprInfo {
     pub has_children: bool,
 }
 
+/// The value of a watch expression, as of the last time the evaluation stopped.
+#[derive(Clone, Debug)]
+pub struct WatchInfo {
+    /// The watch expression.
+    pub expression: String,
+    /// The value, or the error if the evaluation failed. `None` if the evaluation
+    /// hasn't stopped since the watch was set.
+    pub result: Option<Result<EvaluateExprInfo, String>>,
+}
+
 impl InspectVariableInfo {
     fn try_from_dict<'v>(value_dict: DictRef<'v>) -> crate::Result<Self> {
         let key_segments = value_dict
@@ -366,6 +377,9 @@ pub trait DapAdapter: Debug + Send + 'static {
 
     /// Gets a stacktrace from the current execution state.
     ///
+    /// Frames of native functions, which have no location, are skipped unless enabled
+    /// with [`set_native_frames`](DapAdapter::set_native_frames).
+    ///
     /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_StackTrace>
     fn stack_trace(&self, args: StackTraceArguments) -> anyhow::Result<StackTraceResponseBody>;
 
@@ -399,12 +413,70 @@ pub trait DapAdapter: Debug + Send + 'static {
     ///
     /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Evaluate>
     fn evaluate(&self, expr: &str) -> anyhow::Result<EvaluateExprInfo>;
+
+    /// Sets the watch expressions (and clears existing ones), which are evaluated in the
+    /// context of the top-most frame each time the evaluation stops.
+    fn set_watches(&self, expressions: Vec<String>) -> anyhow::Result<()>;
+
+    /// Gets the watch expressions with their values as of the last stop.
+    fn watches(&self) -> anyhow::Result<Vec<WatchInfo>>;
+
+    /// Whether stack traces include the frames of native functions, such as `map`
+    /// calling back into Starlark.
+    fn set_native_frames(&self, enabled: bool);
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub(crate) struct Breakpoint {
     span: FileSpan,
     condition: Option<String>,
+    hit_condition: Option<HitCondition>,
+}
+
+/// A DAP `hitCondition`, deciding whether a breakpoint stops from the number of times it
+/// has been hit (with its condition true), including the current one.
+///
+/// Written as `N` (the same as `>= N`), `== N`, `> N`, `>= N`, `< N`, `<= N`, or `% N`
+/// to stop every `N`-th hit.
+#[derive(Debug, Clone, Copy, Dupe, Hash, Eq, PartialEq)]
+pub(crate) enum HitCondition {
+    Eq(usize),
+    Gt(usize),
+    Ge(usize),
+    Lt(usize),
+    Le(usize),
+    Multiple(usize),
+}
+
+impl HitCondition {
+    pub(crate) fn parse(s: &str) -> Option<HitCondition> {
+        let s = s.trim();
+        let (op, n) = match s.find(|c: char| c.is_ascii_digit()) {
+            Some(i) => s.split_at(i),
+            None => return None,
+        };
+        let n = n.trim().parse().ok()?;
+        match op.trim() {
+            "" | ">=" => Some(HitCondition::Ge(n)),
+            "=" | "==" => Some(HitCondition::Eq(n)),
+            ">" => Some(HitCondition::Gt(n)),
+            "<" => Some(HitCondition::Lt(n)),
+            "<=" => Some(HitCondition::Le(n)),
+            "%" if n != 0 => Some(HitCondition::Multiple(n)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn matches(self, hits: usize) -> bool {
+        match self {
+            HitCondition::Eq(n) => hits == n,
+            HitCondition::Gt(n) => hits > n,
+            HitCondition::Ge(n) => hits >= n,
+            HitCondition::Lt(n) => hits < n,
+            HitCondition::Le(n) => hits <= n,
+            HitCondition::Multiple(n) => hits.is_multiple_of(n),
+        }
+    }
 }
 
 /// Breakpoints resolved to their spans.
@@ -442,6 +514,7 @@ pub fn dap_capabilities() -> Capabilities {
         supports_set_variable: Some(true),
         supports_step_in_targets_request: Some(true),
         supports_conditional_breakpoints: Some(true),
+        supports_hit_conditional_breakpoints: Some(true),
         ..Capabilities::default()
     }
 }
@@ -19,6 +19,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::Receiver;
@@ -29,11 +30,13 @@ use debugserver_types::*;
 use dupe::Dupe;
 use starlark_syntax::error::StarlarkResultExt;
 use starlark_syntax::slice_vec_ext::SliceExt;
+use starlark_syntax::slice_vec_ext::VecExt;
 
 use super::EvaluateExprInfo;
 use super::InspectVariableInfo;
 use super::PathSegment;
 use super::VariablePath;
+use super::WatchInfo;
 use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
 use crate::codemap::Span;
@@ -45,6 +48,7 @@ use crate::debug::StepKind;
 use crate::debug::Variable;
 use crate::debug::VariablesInfo;
 use crate::debug::adapter::Breakpoint;
+use crate::debug::adapter::HitCondition;
 use crate::debug::adapter::ResolvedBreakpoints;
 use crate::eval::BeforeStmtFuncDyn;
 use crate::eval::Evaluator;
@@ -61,6 +65,8 @@ pub(crate) fn prepare_dap_adapter(
         client,
         breakpoints: Arc::new(Mutex::new(BreakpointConfig::new())),
         disable_breakpoints: Arc::new(0usize.into()),
+        watches: Mutex::new(Vec::new()),
+        native_frames: AtomicBool::new(false),
     });
 
     (
@@ -122,27 +128,7 @@ impl<'e> BeforeStmtFuncDyn<'e> for DapAdapterEvalHookImpl {
             return Ok(());
         }
 
-        let stop = if self.state.disable_breakpoints.load(Ordering::SeqCst) > 0 {
-            false
-        } else {
-            let breaks = self.state.breakpoints.lock().unwrap();
-            let breakpoint = breaks.at(span_loc);
-            match breakpoint {
-                Some(Breakpoint {
-                    condition: Some(condition),
-                    ..
-                }) => match evaluate_expr(&self.state, eval, condition.to_owned()) {
-                    Ok(v) => v.to_bool(),
-                    Err(_) => {
-                        // If failed to evaluate the condition, stop.
-                        // TODO(nga): print the error.
-                        true
-                    }
-                },
-                Some(..) => true,
-                None => false,
-            }
-        };
+        let stop = self.breakpoint_stop(span_loc, eval);
 
         let step_stop = match self.step {
             None => false,
@@ -156,6 +142,7 @@ impl<'e> BeforeStmtFuncDyn<'e> for DapAdapterEvalHookImpl {
 
         if stop || step_stop {
             self.step = None;
+            self.evaluate_watches(eval);
             self.state.client.event_stopped()?;
             loop {
                 let msg = self.receiver.recv();
@@ -191,6 +178,50 @@ impl DapAdapterEvalHookImpl {
             step: None,
         }
     }
+
+    /// Whether a breakpoint at this statement stops, which counts as a hit if its condition holds.
+    fn breakpoint_stop(&self, span_loc: FileSpanRef, eval: &mut Evaluator) -> bool {
+        if self.state.disable_breakpoints.load(Ordering::SeqCst) > 0 {
+            return false;
+        }
+        // Don't hold the lock while evaluating the condition.
+        let condition = match self.state.breakpoints.lock().unwrap().at(span_loc) {
+            Some(breakpoint) => breakpoint.condition.clone(),
+            None => return false,
+        };
+        if let Some(condition) = condition {
+            match evaluate_expr(&self.state, eval, condition) {
+                Ok(v) if !v.to_bool() => return false,
+                Ok(_) => {}
+                // If failed to evaluate the condition, stop.
+                // TODO(nga): print the error.
+                Err(_) => {}
+            }
+        }
+        self.state.breakpoints.lock().unwrap().hit(span_loc)
+    }
+
+    fn evaluate_watches(&self, eval: &mut Evaluator) {
+        let expressions = self
+            .state
+            .watches
+            .lock()
+            .unwrap()
+            .map(|watch| watch.expression.clone());
+        let results = expressions.into_map(|expression| {
+            let result = evaluate_expr(&self.state, eval, expression.clone())
+                .map(|v| EvaluateExprInfo::from_value(&v))
+                .map_err(|e| format!("{e:#}"));
+            (expression, result)
+        });
+        // The watches may have been changed while evaluating, so match them by expression.
+        let mut watches = self.state.watches.lock().unwrap();
+        for (expression, result) in results {
+            if let Some(watch) = watches.iter_mut().find(|w| w.expression == expression) {
+                watch.result = Some(result);
+            }
+        }
+    }
 }
 
 impl DapAdapterEvalHook for DapAdapterEvalHookImpl {
@@ -201,8 +232,8 @@ impl DapAdapterEvalHook for DapAdapterEvalHookImpl {
 
 #[derive(Debug)]
 struct BreakpointConfig {
-    // maps a source filename to the breakpoint spans for the file
-    breakpoints: HashMap<String, HashMap<Span, Breakpoint>>,
+    // maps a source filename to the breakpoint spans for the file, with their hit counts
+    breakpoints: HashMap<String, HashMap<Span, (Breakpoint, usize)>>,
 }
 
 impl BreakpointConfig {
@@ -216,6 +247,23 @@ impl BreakpointConfig {
         self.breakpoints
             .get(span_loc.filename())
             .and_then(|file_breaks| file_breaks.get(&span_loc.span))
+            .map(|(breakpoint, _)| breakpoint)
+    }
+
+    /// Counts a hit of the breakpoint, returning whether it stops.
+    fn hit(&mut self, span_loc: FileSpanRef) -> bool {
+        let Some((breakpoint, hits)) = self
+            .breakpoints
+            .get_mut(span_loc.filename())
+            .and_then(|file_breaks| file_breaks.get_mut(&span_loc.span))
+        else {
+            // Removed since we looked it up.
+            return false;
+        };
+        *hits += 1;
+        breakpoint
+            .hit_condition
+            .is_none_or(|condition| condition.matches(*hits))
     }
 
     fn set_breakpoints(
@@ -232,7 +280,7 @@ impl BreakpointConfig {
                     .0
                     .iter()
                     .filter_map(|x| x.clone())
-                    .map(|x| (x.span.span, x))
+                    .map(|x| (x.span.span, (x, 0)))
                     .collect(),
             );
         }
@@ -248,6 +296,10 @@ struct SharedAdapterState {
     breakpoints: Arc<Mutex<BreakpointConfig>>,
     // Set while we are doing evaluate calls (>= 1 means disable)
     disable_breakpoints: Arc<AtomicUsize>,
+    // Evaluated each time we stop.
+    watches: Mutex<Vec<WatchInfo>>,
+    // Whether stack traces include frames without a location.
+    native_frames: AtomicBool,
 }
 
 #[derive(Debug, Clone, Copy, Dupe)]
@@ -308,15 +360,26 @@ impl DapAdapter for DapAdapterImpl {
         // Our model of a Frame and the debugger model are a bit different.
         // We record the location of the call, but DAP wants the location we are at.
         // We also have them in the wrong order
-        self.with_ctx(Box::new(|span, eval| {
+        let native_frames = self.state.native_frames.load(Ordering::SeqCst);
+        self.with_ctx(Box::new(move |span, eval| {
             let frames = eval.call_stack().into_frames();
             let mut next = Some(span.to_file_span());
             let mut res = Vec::with_capacity(frames.len() + 1);
-            for (i, x) in frames.iter().rev().enumerate() {
-                res.push(convert_frame(i, x.name.clone(), next));
+            for x in frames.iter().rev() {
+                // Native functions don't record where they call back into Starlark,
+                // so theirs are the frames without a location.
+                match next {
+                    Some(_) => res.push(convert_frame(res.len(), x.name.clone(), next)),
+                    None if native_frames => {
+                        let mut frame = convert_frame(res.len(), x.name.clone(), None);
+                        frame.presentation_hint = Some("subtle".to_owned());
+                        res.push(frame);
+                    }
+                    None => {}
+                }
                 next = x.location.dupe();
             }
-            res.push(convert_frame(frames.len(), "Root".to_owned(), next));
+            res.push(convert_frame(res.len(), "Root".to_owned(), next));
             Ok(StackTraceResponseBody {
                 total_frames: Some(res.len() as i64),
                 stack_frames: res,
@@ -388,6 +451,28 @@ impl DapAdapter for DapAdapterImpl {
             }
         }))
     }
+
+    fn set_watches(&self, expressions: Vec<String>) -> anyhow::Result<()> {
+        let mut watches = self.state.watches.lock().unwrap();
+        let new = expressions.into_map(|expression| {
+            // Keep the values of existing watches until the next stop.
+            let result = watches
+                .iter()
+                .find(|w| w.expression == expression)
+                .and_then(|w| w.result.clone());
+            WatchInfo { expression, result }
+        });
+        *watches = new;
+        Ok(())
+    }
+
+    fn watches(&self) -> anyhow::Result<Vec<WatchInfo>> {
+        Ok(self.state.watches.lock().unwrap().clone())
+    }
+
+    fn set_native_frames(&self, enabled: bool) {
+        self.state.native_frames.store(enabled, Ordering::SeqCst);
+    }
 }
 
 impl DapAdapterImpl {
@@ -446,9 +531,15 @@ pub(crate) fn resolve_breakpoints(
         Vec::new(),
         |v| {
             v.map(|x| {
+                // A hit condition we can't parse makes the breakpoint unverified.
+                let hit_condition = match &x.hit_condition {
+                    Some(hit_condition) => Some(HitCondition::parse(hit_condition)?),
+                    None => None,
+                };
                 poss.get(&(x.line as usize - 1)).map(|span| Breakpoint {
                     span: span.clone(),
                     condition: x.condition.clone(),
+                    hit_condition,
                 })
             })
         },
@@ -242,10 +242,6 @@ print(x)
             let eval_result =
                 s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
             controller.wait_for_eval_stopped(1, TIMEOUT);
-            // TODO(cjhopman): we currently hit breakpoints on top-level statements twice (once for the gc bytecode, once for the actual statement).
-            adapter.continue_()?;
-            controller.wait_for_eval_stopped(2, TIMEOUT);
-
             adapter.continue_()?;
 
             join_timeout(eval_result, TIMEOUT)?;
@@ -302,8 +298,54 @@ print(x)
                 s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
             controller.wait_for_eval_stopped(1, TIMEOUT);
             adapter.continue_()?;
-            // TODO(cjhopman): we currently hit breakpoints on top-level statements twice (once for the gc bytecode, once for the actual statement).
+
+            join_timeout(eval_result, TIMEOUT)?;
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_breakpoint_with_hit_condition() -> crate::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let file_contents = "
+def do():
+    for i in range(5):
+        x = i # line 4
+do()
+        ";
+        dap_test_template(|s, controller, adapter, eval_hook| {
+            let ast = AstModule::parse(
+                "test.bzl",
+                file_contents.to_owned(),
+                &Dialect::AllOptionsInternal,
+            )?;
+            let mut args = breakpoints_args("test.bzl", &[(4, Some("i > 0"))]);
+            // Hits are only counted when the condition holds, so stops when `i` is 2 and 4.
+            args.breakpoints.as_mut().unwrap()[0].hit_condition = Some("% 2".to_owned());
+            let breakpoints = resolve_breakpoints(&args, &ast)?;
+            adapter.set_breakpoints("test.bzl", &breakpoints)?;
+            adapter.set_watches(vec!["i * 10".to_owned(), "undefined".to_owned()])?;
+            let eval_result =
+                s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
+            controller.wait_for_eval_stopped(1, TIMEOUT);
+            assert_eq!("2", adapter.evaluate("i")?.result);
+            let watches = adapter.watches()?;
+            assert_eq!(
+                "20",
+                watches[0].result.as_ref().unwrap().as_ref().unwrap().result
+            );
+            assert!(watches[1].result.as_ref().unwrap().is_err());
+            adapter.continue_()?;
             controller.wait_for_eval_stopped(2, TIMEOUT);
+            assert_eq!("4", adapter.evaluate("i")?.result);
+            let watches = adapter.watches()?;
+            assert_eq!(
+                "40",
+                watches[0].result.as_ref().unwrap().as_ref().unwrap().result
+            );
             adapter.continue_()?;
 
             join_timeout(eval_result, TIMEOUT)?;
@@ -311,6 +353,57 @@ print(x)
         })
     }
 
+    #[test]
+    fn test_native_frames() -> crate::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let file_contents = "
+def key(x):
+    return x # line 3
+sorted([2, 1], key = key)
+        ";
+        let result = dap_test_template(|s, controller, adapter, eval_hook| {
+            let ast = AstModule::parse(
+                "test.bzl",
+                file_contents.to_owned(),
+                &Dialect::AllOptionsInternal,
+            )?;
+            let breakpoints =
+                resolve_breakpoints(&breakpoints_args("test.bzl", &[(3, None)]), &ast)?;
+            adapter.set_breakpoints("test.bzl", &breakpoints)?;
+            let eval_result =
+                s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
+            controller.wait_for_eval_stopped(1, TIMEOUT);
+            let args = StackTraceArguments {
+                format: None,
+                levels: None,
+                start_frame: None,
+                thread_id: 0,
+            };
+            let without = adapter.stack_trace(args.clone());
+            adapter.set_native_frames(true);
+            let with = adapter.stack_trace(args);
+            adapter.continue_()?;
+            // `key` is called for each element.
+            controller.wait_for_eval_stopped(2, TIMEOUT);
+            adapter.continue_()?;
+            join_timeout(eval_result, TIMEOUT)?;
+            Ok((without?, with?))
+        })?;
+
+        let names = |body: &StackTraceResponseBody| {
+            body.stack_frames
+                .iter()
+                .map(|f| f.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["key", "Root"], names(&result.0));
+        assert_eq!(vec!["key", "sorted", "Root"], names(&result.1));
+        Ok(())
+    }
+
     #[test]
     fn test_step_over() -> crate::Result<()> {
         if is_wasm() {
@@ -339,24 +432,18 @@ print(x)
             let eval_result =
                 s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
             controller.wait_for_eval_stopped(1, TIMEOUT);
-            // TODO(cjhopman): we currently hit breakpoints on top-level statements twice (once for the gc bytecode, once for the actual statement).
-            adapter.continue_()?;
-            controller.wait_for_eval_stopped(2, TIMEOUT);
 
             assert_eq!("1", adapter.evaluate("x[0]")?.result);
             assert_eq!("2", adapter.evaluate("x[1]")?.result);
             assert_eq!("3", adapter.evaluate("x[2]")?.result);
             adapter.step(StepKind::Over)?;
-            controller.wait_for_eval_stopped(3, TIMEOUT);
+            controller.wait_for_eval_stopped(2, TIMEOUT);
             assert_eq!("2", adapter.evaluate("x[0]")?.result);
             assert_eq!("3", adapter.evaluate("x[1]")?.result);
             assert_eq!("4", adapter.evaluate("x[2]")?.result);
 
-            // TODO(cjhopman): we currently hit breakpoints on top-level statements twice (once for the gc bytecode, once for the actual statement).
-            adapter.step(StepKind::Over)?;
-            controller.wait_for_eval_stopped(4, TIMEOUT);
             adapter.step(StepKind::Over)?;
-            controller.wait_for_eval_stopped(5, TIMEOUT);
+            controller.wait_for_eval_stopped(3, TIMEOUT);
             assert_eq!("3", adapter.evaluate("x[0]")?.result);
             assert_eq!("4", adapter.evaluate("x[1]")?.result);
             assert_eq!("5", adapter.evaluate("x[2]")?.result);
@@ -394,9 +481,6 @@ print(x)
             let eval_result =
                 s.spawn(move || -> crate::Result<_> { eval_with_hook(ast, eval_hook) });
             controller.wait_for_eval_stopped(1, TIMEOUT);
-            // TODO(cjhopman): we currently hit breakpoints on top-level statements twice (once for the gc bytecode, once for the actual statement).
-            adapter.continue_()?;
-            controller.wait_for_eval_stopped(2, TIMEOUT);
 
             assert_eq!("1", adapter.evaluate("x[0]")?.result);
             assert_eq!("2", adapter.evaluate("x[1]")?.result);
@@ -404,34 +488,30 @@ print(x)
 
             // into adjust
             adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(3, TIMEOUT);
+            controller.wait_for_eval_stopped(2, TIMEOUT);
             assert_eq!("1", adapter.evaluate("y[0]")?.result);
             assert_eq!("2", adapter.evaluate("y[1]")?.result);
             assert_eq!("3", adapter.evaluate("y[2]")?.result);
 
             // into should go to next line
             adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(4, TIMEOUT);
+            controller.wait_for_eval_stopped(3, TIMEOUT);
             assert_eq!("2", adapter.evaluate("y[0]")?.result);
             assert_eq!("2", adapter.evaluate("y[1]")?.result);
             assert_eq!("3", adapter.evaluate("y[2]")?.result);
 
             // two more intos should get us out of the function call
             adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(5, TIMEOUT);
+            controller.wait_for_eval_stopped(4, TIMEOUT);
             adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(6, TIMEOUT);
+            controller.wait_for_eval_stopped(5, TIMEOUT);
             assert_eq!("2", adapter.evaluate("x[0]")?.result);
             assert_eq!("3", adapter.evaluate("x[1]")?.result);
             assert_eq!("4", adapter.evaluate("x[2]")?.result);
 
             // and once more back into the function
             adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(7, TIMEOUT);
-
-            // TODO(cjhopman): unfortunately, gc being marked as statements causes us to need to step_into again.
-            adapter.step(StepKind::Into)?;
-            controller.wait_for_eval_stopped(8, TIMEOUT);
+            controller.wait_for_eval_stopped(6, TIMEOUT);
 
             assert_eq!("2", adapter.evaluate("y[0]")?.result);
             assert_eq!("3", adapter.evaluate("y[1]")?.result);
@@ -21,11 +21,16 @@
 #![allow(missing_docs)]
 
 pub mod code;
+pub mod html;
+pub mod json;
+pub mod json_schema;
+pub mod lint;
 pub mod markdown;
 pub mod multipage;
 mod parse;
 #[cfg(test)]
 mod tests;
+pub mod text;
 
 use std::iter;
 
@@ -94,6 +99,19 @@ pub struct DocFunction {
     pub params: DocParams,
     /// Details about what this function returns.
     pub ret: DocReturn,
+    /// Problems found while parsing the sections of the docstring, reported by [`lint`].
+    pub issues: Vec<DocStringIssue>,
+}
+
+/// A problem with the sections of a docstring, found while parsing it.
+#[derive(Debug, Clone, PartialEq, Eq, Allocative)]
+pub enum DocStringIssue {
+    /// A parameter which is documented, but is not in the signature.
+    UnknownParam(String),
+    /// A section, like `Returns:`, with nothing in it.
+    EmptySection(String),
+    /// A line in the arguments section which is neither a parameter nor a continuation.
+    MalformedLine { section: String, line: String },
 }
 
 impl DocFunction {
@@ -0,0 +1,364 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Render documentation as standalone, cross-linked, HTML pages, as an alternative to
+//! [`render_markdown_multipage`](crate::docs::multipage::render_markdown_multipage).
+//!
+//! Every module gets an index page listing its members, with links to the pages of its
+//! types and nested modules, and every type gets a page of its own. Each member has an
+//! anchor, named after it, on the page which documents it.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use dupe::Dupe;
+use itertools::Itertools;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocProperty;
+use crate::docs::DocString;
+use crate::docs::markdown::render_function_prototype;
+use crate::docs::multipage::DocModuleInfo;
+use crate::docs::multipage::DocPageRef;
+use crate::docs::multipage::PageRender;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::ty::TypeRenderConfig;
+
+// Types are rendered to plain text with links marked by these characters, so the text
+// can be escaped before the links are turned into HTML.
+const LINK_START: char = '\u{1}';
+const LINK_MID: char = '\u{2}';
+const LINK_END: char = '\u{3}';
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// The file a page is written to, relative to the root.
+fn page_file(page: &PageRender) -> String {
+    match page.page {
+        DocPageRef::Module(_) if page.path.is_empty() => "index.html".to_owned(),
+        DocPageRef::Module(_) => format!("{}/index.html", page.path),
+        DocPageRef::Type(_) => format!("{}.html", page.path),
+    }
+}
+
+/// Render free text, like a docstring, where blank lines separate paragraphs and
+/// triple backticks delimit code blocks.
+fn render_text(text: &str, out: &mut String) {
+    for (i, part) in text.split("```").enumerate() {
+        if i % 2 == 1 {
+            // Drop the language, as in "```python".
+            let code = match part.split_once('\n') {
+                Some((first, rest)) if !first.contains(' ') => rest,
+                _ => part,
+            };
+            let _ = writeln!(
+                out,
+                "<pre><code>{}</code></pre>",
+                escape(code.trim_end_matches('\n'))
+            );
+        } else {
+            for paragraph in part.split("\n\n") {
+                let paragraph = paragraph.trim();
+                if !paragraph.is_empty() {
+                    let _ = writeln!(out, "<p>{}</p>", escape(paragraph));
+                }
+            }
+        }
+    }
+}
+
+fn render_doc_string(docs: &Option<DocString>, out: &mut String) {
+    let Some(docs) = docs else {
+        return;
+    };
+    render_text(&docs.summary, out);
+    if let Some(details) = &docs.details {
+        render_text(details, out);
+    }
+    if let Some(examples) = &docs.examples {
+        out.push_str("<h4>Examples</h4>\n");
+        if examples.contains("```") {
+            render_text(examples, out);
+        } else {
+            let _ = writeln!(out, "<pre><code>{}</code></pre>", escape(examples));
+        }
+    }
+}
+
+/// A page being rendered.
+struct Page {
+    /// Prefix of the root from this page, like `../`.
+    root: String,
+    /// The file of the page of each type, relative to the root.
+    types: Rc<HashMap<Ty, String>>,
+    out: String,
+}
+
+impl Page {
+    fn type_config(&self) -> TypeRenderConfig {
+        let types = self.types.dupe();
+        TypeRenderConfig::LinkedType {
+            render_linked_ty_starlark_value: Box::new(move |ty| {
+                match types.get(&Ty::basic(TyBasic::StarlarkValue(ty.dupe()))) {
+                    Some(file) => format!("{LINK_START}{file}{LINK_MID}{ty}{LINK_END}"),
+                    None => ty.to_string(),
+                }
+            }),
+        }
+    }
+
+    /// Escape code rendered with [`type_config`](Page::type_config), turning type links into HTML.
+    fn code(&self, code: &str) -> String {
+        // Rendering with links escapes stars for markdown, which HTML doesn't need.
+        let code = escape(code).replace("\\*", "*");
+        let mut res = String::with_capacity(code.len());
+        let mut rest = code.as_str();
+        while let Some((before, link)) = rest.split_once(LINK_START) {
+            res.push_str(before);
+            let (file, link) = link.split_once(LINK_MID).unwrap();
+            let (name, after) = link.split_once(LINK_END).unwrap();
+            let _ = write!(res, r#"<a href="{}{}">{}</a>"#, self.root, file, name);
+            rest = after;
+        }
+        res.push_str(rest);
+        res
+    }
+
+    fn ty(&self, ty: &Ty) -> String {
+        self.code(&ty.display_with(&self.type_config()).to_string())
+    }
+
+    fn member_header(&mut self, name: &str, anchor: &str) {
+        let anchor = escape(anchor);
+        let _ = writeln!(
+            self.out,
+            r##"<section id="{anchor}">
+<h2><a href="#{anchor}">{}</a></h2>"##,
+            escape(name)
+        );
+    }
+
+    fn property(&mut self, name: &str, anchor: &str, property: &DocProperty) {
+        self.member_header(name, anchor);
+        let _ = writeln!(
+            self.out,
+            "<pre><code>{}: {}</code></pre>",
+            escape(name),
+            self.ty(&property.typ)
+        );
+        render_doc_string(&property.docs, &mut self.out);
+        self.out.push_str("</section>\n");
+    }
+
+    fn function(&mut self, name: &str, anchor: &str, function: &DocFunction) {
+        self.member_header(name, anchor);
+        self.function_body(name, function);
+        self.out.push_str("</section>\n");
+    }
+
+    fn function_body(&mut self, name: &str, function: &DocFunction) {
+        let prototype = render_function_prototype(name, function, &self.type_config());
+        let _ = writeln!(
+            self.out,
+            "<pre><code>{}</code></pre>",
+            self.code(&prototype)
+        );
+        render_doc_string(&function.docs, &mut self.out);
+
+        let params: Vec<_> = function
+            .params
+            .doc_params_with_starred_names()
+            .filter(|(_, p)| p.docs.is_some())
+            .collect();
+        if !params.is_empty() {
+            self.out.push_str("<h4>Parameters</h4>\n<dl>\n");
+            for (name, p) in params {
+                let default = match &p.default_value {
+                    Some(v) => format!(" (defaults to <code>{}</code>)", escape(v)),
+                    None => " (required)".to_owned(),
+                };
+                let _ = writeln!(self.out, "<dt><code>{}</code>{default}</dt>", escape(&name));
+                self.out.push_str("<dd>\n");
+                render_doc_string(&p.docs, &mut self.out);
+                self.out.push_str("</dd>\n");
+            }
+            self.out.push_str("</dl>\n");
+        }
+        if function.ret.docs.is_some() {
+            self.out.push_str("<h4>Returns</h4>\n");
+            render_doc_string(&function.ret.docs, &mut self.out);
+        }
+    }
+
+    fn member(&mut self, name: &str, anchor: &str, member: &DocMember) {
+        match member {
+            DocMember::Function(f) => self.function(name, anchor, f),
+            DocMember::Property(p) => self.property(name, anchor, p),
+        }
+    }
+
+    /// Links to the members, then the members themselves.
+    fn members<'a>(
+        &mut self,
+        prefix: &str,
+        members: impl IntoIterator<Item = (&'a str, &'a DocMember)>,
+    ) {
+        let members: Vec<_> = members
+            .into_iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .collect();
+        if members.is_empty() {
+            return;
+        }
+        self.out.push_str("<ul class=\"members\">\n");
+        for (name, _) in &members {
+            let name = escape(name);
+            let _ = writeln!(
+                self.out,
+                r##"<li><a href="#{name}"><code>{prefix}{name}</code></a></li>"##
+            );
+        }
+        self.out.push_str("</ul>\n");
+        for (name, member) in members {
+            self.member(&format!("{prefix}{name}"), name, member);
+        }
+    }
+
+    fn finish(self, title: &str) -> String {
+        let title = escape(title);
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+</head>
+<body>
+<nav><a href="{}index.html">Index</a></nav>
+<main>
+<h1>{title}</h1>
+{}</main>
+</body>
+</html>
+"#,
+            self.root, self.out
+        )
+    }
+}
+
+fn render_page(render: &PageRender, types: &Rc<HashMap<Ty, String>>) -> String {
+    let file = page_file(render);
+    let mut page = Page {
+        root: "../".repeat(file.matches('/').count()),
+        types: types.dupe(),
+        out: String::new(),
+    };
+    match render.page {
+        DocPageRef::Module(module) => {
+            render_doc_string(&module.docs, &mut page.out);
+            // Nested modules and types have pages of their own.
+            let mut links = Vec::new();
+            for (name, item) in &module.members {
+                let path = if render.path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{name}", render.path)
+                };
+                match item {
+                    DocItem::Module(_) => {
+                        links.push((name, "module", format!("{path}/index.html")))
+                    }
+                    DocItem::Type(_) => links.push((name, "type", format!("{path}.html"))),
+                    DocItem::Member(_) => {}
+                }
+            }
+            if !links.is_empty() {
+                links.sort();
+                page.out.push_str("<ul class=\"pages\">\n");
+                for (name, kind, file) in links {
+                    let _ = writeln!(
+                        page.out,
+                        r#"<li><a href="{}{}"><code>{}</code></a> {kind}</li>"#,
+                        page.root,
+                        escape(&file),
+                        escape(name)
+                    );
+                }
+                page.out.push_str("</ul>\n");
+            }
+            page.members(
+                "",
+                module.members.iter().filter_map(|(name, item)| match item {
+                    DocItem::Member(m) => Some((name.as_str(), m)),
+                    _ => None,
+                }),
+            );
+            page.finish(&render.name)
+        }
+        DocPageRef::Type(ty) => {
+            render_doc_string(&ty.docs, &mut page.out);
+            if let Some(constructor) = &ty.constructor {
+                page.function_body(&render.name, constructor);
+            }
+            page.members(
+                &format!("{}.", render.name),
+                ty.members.iter().map(|(name, m)| (name.as_str(), m)),
+            );
+            page.finish(&format!("{} type", render.name))
+        }
+    }
+}
+
+/// Render the modules as HTML pages, returned as a map from file paths, relative to the
+/// root of the documentation, to their contents.
+///
+/// The root module of each [`DocModuleInfo`] is rendered as `index.html` under its page path,
+/// its nested modules likewise under their name, and its types as `name.html`. Types in
+/// signatures link to the page of the type, if one is rendered.
+pub fn render_html_pages(modules_infos: Vec<DocModuleInfo<'_>>) -> HashMap<String, String> {
+    let renders: Vec<PageRender> = modules_infos
+        .iter()
+        .flat_map(|x| x.into_page_renders())
+        .collect();
+    let mut types = HashMap::new();
+    for render in &renders {
+        if let Some(ty) = &render.ty {
+            types.insert(ty.dupe(), page_file(render));
+        }
+    }
+    let types = Rc::new(types);
+    renders
+        .iter()
+        .map(|render| (page_file(render), render_page(render, &types)))
+        .collect()
+}
@@ -0,0 +1,268 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A stable JSON representation of documentation, for tools like IDE plugins and
+//! documentation websites, which shouldn't have to parse the markdown output.
+//!
+//! The schema is described by the types of this module. Fields may be added within a
+//! version, so readers should ignore unknown fields, but any other change increments
+//! [`DOCS_JSON_VERSION`]. Types are given as strings, in Starlark type annotation syntax.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocParam;
+use crate::docs::DocProperty;
+use crate::docs::DocString;
+use crate::docs::DocType;
+
+/// The version of the schema, stored as the `version` of [`JsonDocs`].
+pub const DOCS_JSON_VERSION: u32 = 1;
+
+/// The top-level document: a module, along with the version of the schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocs {
+    /// Always [`DOCS_JSON_VERSION`] when written by this version of Starlark.
+    pub version: u32,
+    pub module: JsonDocModule,
+}
+
+impl JsonDocs {
+    pub fn new(module: &DocModule) -> JsonDocs {
+        JsonDocs {
+            version: DOCS_JSON_VERSION,
+            module: JsonDocModule::from(module),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocString {
+    pub summary: String,
+    pub details: Option<String>,
+    pub examples: Option<String>,
+}
+
+impl From<&DocString> for JsonDocString {
+    fn from(docs: &DocString) -> JsonDocString {
+        JsonDocString {
+            summary: docs.summary.clone(),
+            details: docs.details.clone(),
+            examples: docs.examples.clone(),
+        }
+    }
+}
+
+fn doc_string(docs: &Option<DocString>) -> Option<JsonDocString> {
+    docs.as_ref().map(JsonDocString::from)
+}
+
+/// A module or namespace, whose members are accessed as `module.member`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocModule {
+    pub docs: Option<JsonDocString>,
+    /// Sorted by name.
+    pub members: BTreeMap<String, JsonDocItem>,
+}
+
+impl From<&DocModule> for JsonDocModule {
+    fn from(module: &DocModule) -> JsonDocModule {
+        JsonDocModule {
+            docs: doc_string(&module.docs),
+            members: module
+                .members
+                .iter()
+                .map(|(name, item)| (name.clone(), JsonDocItem::from(item)))
+                .collect(),
+        }
+    }
+}
+
+/// Anything documented, distinguished by its `kind`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JsonDocItem {
+    Module(JsonDocModule),
+    Type(JsonDocType),
+    Function(JsonDocFunction),
+    Property(JsonDocProperty),
+}
+
+impl From<&DocItem> for JsonDocItem {
+    fn from(item: &DocItem) -> JsonDocItem {
+        match item {
+            DocItem::Module(m) => JsonDocItem::Module(JsonDocModule::from(m)),
+            DocItem::Type(t) => JsonDocItem::Type(JsonDocType::from(t)),
+            DocItem::Member(DocMember::Function(f)) => {
+                JsonDocItem::Function(JsonDocFunction::from(f))
+            }
+            DocItem::Member(DocMember::Property(p)) => {
+                JsonDocItem::Property(JsonDocProperty::from(p))
+            }
+        }
+    }
+}
+
+/// A type, whose members are accessed on values of the type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocType {
+    pub docs: Option<JsonDocString>,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub constructor: Option<JsonDocFunction>,
+    /// Sorted by name.
+    pub members: BTreeMap<String, JsonDocMember>,
+}
+
+impl From<&DocType> for JsonDocType {
+    fn from(t: &DocType) -> JsonDocType {
+        JsonDocType {
+            docs: doc_string(&t.docs),
+            ty: t.ty.to_string(),
+            constructor: t.constructor.as_ref().map(JsonDocFunction::from),
+            members: t
+                .members
+                .iter()
+                .map(|(name, member)| (name.clone(), JsonDocMember::from(member)))
+                .collect(),
+        }
+    }
+}
+
+/// A member of a type, distinguished by its `kind`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JsonDocMember {
+    Function(JsonDocFunction),
+    Property(JsonDocProperty),
+}
+
+impl From<&DocMember> for JsonDocMember {
+    fn from(member: &DocMember) -> JsonDocMember {
+        match member {
+            DocMember::Function(f) => JsonDocMember::Function(JsonDocFunction::from(f)),
+            DocMember::Property(p) => JsonDocMember::Property(JsonDocProperty::from(p)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocFunction {
+    pub docs: Option<JsonDocString>,
+    /// In the order of the signature.
+    pub params: Vec<JsonDocParam>,
+    #[serde(rename = "return")]
+    pub ret: JsonDocReturn,
+}
+
+impl From<&DocFunction> for JsonDocFunction {
+    fn from(f: &DocFunction) -> JsonDocFunction {
+        let params = &f.params;
+        let with_kind = |kind: JsonParamKind, xs: &[DocParam]| {
+            xs.iter()
+                .map(move |p| JsonDocParam::new(kind, p))
+                .collect::<Vec<_>>()
+        };
+        let mut res = with_kind(JsonParamKind::PosOnly, &params.pos_only);
+        res.extend(with_kind(JsonParamKind::PosOrNamed, &params.pos_or_named));
+        res.extend(with_kind(JsonParamKind::Args, params.args.as_slice()));
+        res.extend(with_kind(JsonParamKind::NamedOnly, &params.named_only));
+        res.extend(with_kind(JsonParamKind::Kwargs, params.kwargs.as_slice()));
+        JsonDocFunction {
+            docs: doc_string(&f.docs),
+            params: res,
+            ret: JsonDocReturn {
+                ty: f.ret.typ.to_string(),
+                docs: doc_string(&f.ret.docs),
+            },
+        }
+    }
+}
+
+/// How a parameter is passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonParamKind {
+    /// Before the `/`.
+    PosOnly,
+    PosOrNamed,
+    /// `*args`.
+    Args,
+    /// After the `*` or `*args`.
+    NamedOnly,
+    /// `**kwargs`.
+    Kwargs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocParam {
+    /// Without the `*` or `**` of `*args` and `**kwargs`.
+    pub name: String,
+    pub kind: JsonParamKind,
+    /// The type of the values of `*args` and `**kwargs`, rather than a tuple or dict of them.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The source code of the default value, if the parameter is optional.
+    pub default: Option<String>,
+    pub docs: Option<JsonDocString>,
+}
+
+impl JsonDocParam {
+    fn new(kind: JsonParamKind, p: &DocParam) -> JsonDocParam {
+        JsonDocParam {
+            name: p.name.clone(),
+            kind,
+            ty: p.typ.to_string(),
+            default: p.default_value.clone(),
+            docs: doc_string(&p.docs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocReturn {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub docs: Option<JsonDocString>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocProperty {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub docs: Option<JsonDocString>,
+}
+
+impl From<&DocProperty> for JsonDocProperty {
+    fn from(p: &DocProperty) -> JsonDocProperty {
+        JsonDocProperty {
+            ty: p.typ.to_string(),
+            docs: doc_string(&p.docs),
+        }
+    }
+}
+
+/// Render the documentation of a module as pretty-printed [`JsonDocs`].
+pub fn render_docs_json(module: &DocModule) -> String {
+    serde_json::to_string_pretty(&JsonDocs::new(module)).unwrap()
+}
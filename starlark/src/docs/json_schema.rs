@@ -0,0 +1,199 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [JSON Schema](https://json-schema.org) for the values functions accept, generated from
+//! their documentation, so input validation and forms can share a source of truth with the
+//! docs.
+//!
+//! A function is described as an object with a property per parameter which can be passed
+//! by name, as if it was called with `f(**input)`.
+
+use serde_json::Map;
+use serde_json::json;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocParam;
+use crate::docs::DocString;
+use crate::docs::DocType;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::tuple::TyTuple;
+
+/// The JSON Schema dialect of the generated documents.
+const SCHEMA: &str = "https://json-schema.org/draft/2020-12/schema";
+
+fn basic_schema(ty: &TyBasic) -> serde_json::Value {
+    match ty {
+        TyBasic::Any | TyBasic::TypeVar(_) => json!({}),
+        TyBasic::StarlarkValue(x) => match x.as_name() {
+            "int" => json!({"type": "integer"}),
+            "float" => json!({"type": "number"}),
+            "string" => json!({"type": "string"}),
+            "bool" => json!({"type": "boolean"}),
+            "NoneType" => json!({"type": "null"}),
+            "struct" => json!({"type": "object"}),
+            _ => json!({}),
+        },
+        TyBasic::List(item) | TyBasic::Iter(item) => {
+            json!({"type": "array", "items": ty_json_schema(item)})
+        }
+        TyBasic::Set(item) => {
+            json!({"type": "array", "items": ty_json_schema(item), "uniqueItems": true})
+        }
+        TyBasic::Tuple(TyTuple::Of(item)) => {
+            json!({"type": "array", "items": ty_json_schema(item)})
+        }
+        TyBasic::Tuple(TyTuple::Elems(elems)) => json!({
+            "type": "array",
+            "prefixItems": elems.iter().map(ty_json_schema).collect::<Vec<_>>(),
+            "minItems": elems.len(),
+            "maxItems": elems.len(),
+        }),
+        // JSON object keys are always strings, so the key type is not checked.
+        TyBasic::Dict(_, value) => {
+            json!({"type": "object", "additionalProperties": ty_json_schema(value)})
+        }
+        // Functions, types and custom types have no JSON representation.
+        TyBasic::Callable(_) | TyBasic::Type | TyBasic::Custom(_) => json!({}),
+    }
+}
+
+/// The schema of JSON values which convert to Starlark values of type `ty`.
+///
+/// Types without a JSON representation, like functions, allow any value.
+pub fn ty_json_schema(ty: &Ty) -> serde_json::Value {
+    match ty.iter_union() {
+        [] => json!(false),
+        [x] => basic_schema(x),
+        xs => {
+            if xs.iter().any(|x| matches!(x, TyBasic::Any)) {
+                return json!({});
+            }
+            json!({"anyOf": xs.iter().map(basic_schema).collect::<Vec<_>>()})
+        }
+    }
+}
+
+fn description(docs: &Option<DocString>) -> Option<String> {
+    let docs = docs.as_ref()?;
+    Some(match &docs.details {
+        Some(details) => format!("{}\n\n{}", docs.summary, details),
+        None => docs.summary.clone(),
+    })
+}
+
+fn with_description(mut schema: serde_json::Value, docs: &Option<DocString>) -> serde_json::Value {
+    if let (Some(description), Some(schema)) = (description(docs), schema.as_object_mut()) {
+        schema.insert("description".to_owned(), json!(description));
+    }
+    schema
+}
+
+/// Default values are shown as Starlark code, so only the simple ones are representable.
+fn default_value(default: &str) -> Option<serde_json::Value> {
+    match default {
+        "None" => Some(json!(null)),
+        "True" => Some(json!(true)),
+        "False" => Some(json!(false)),
+        _ => serde_json::from_str(default).ok(),
+    }
+}
+
+fn param_schema(param: &DocParam) -> serde_json::Value {
+    let mut schema = with_description(ty_json_schema(&param.typ), &param.docs);
+    if let (Some(default), Some(schema)) = (
+        param.default_value.as_deref().and_then(default_value),
+        schema.as_object_mut(),
+    ) {
+        schema.insert("default".to_owned(), default);
+    }
+    schema
+}
+
+/// The schema of the keyword arguments `function` accepts: an object with a property per
+/// parameter which can be passed by name, requiring those without defaults.
+///
+/// Extra properties are allowed only if the function takes `**kwargs`.
+pub fn function_json_schema(function: &DocFunction) -> serde_json::Value {
+    let params = &function.params;
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for param in params.pos_or_named.iter().chain(&params.named_only) {
+        properties.insert(param.name.clone(), param_schema(param));
+        if param.default_value.is_none() {
+            required.push(param.name.clone());
+        }
+    }
+    let additional = match &params.kwargs {
+        Some(kwargs) => ty_json_schema(&kwargs.typ),
+        None => json!(false),
+    };
+    with_description(
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": additional,
+        }),
+        &function.docs,
+    )
+}
+
+/// The schema of objects with the properties of `doc_type`, its members which are not
+/// methods. Properties without a JSON representation are left out.
+pub fn doc_type_json_schema(doc_type: &DocType) -> serde_json::Value {
+    let mut properties = Map::new();
+    for (name, member) in &doc_type.members {
+        if let DocMember::Property(property) = member {
+            properties.insert(
+                name.clone(),
+                with_description(ty_json_schema(&property.typ), &property.docs),
+            );
+        }
+    }
+    with_description(
+        json!({"type": "object", "properties": properties}),
+        &doc_type.docs,
+    )
+}
+
+/// A schema document with a definition, under `$defs`, for each function and type in the
+/// module, keyed by name. Nested modules are flattened with dotted names.
+pub fn module_json_schema(module: &DocModule) -> serde_json::Value {
+    fn collect(prefix: &str, module: &DocModule, defs: &mut Map<String, serde_json::Value>) {
+        for (name, item) in &module.members {
+            let name = format!("{prefix}{name}");
+            match item {
+                DocItem::Member(DocMember::Function(f)) => {
+                    defs.insert(name, function_json_schema(f));
+                }
+                DocItem::Type(t) => {
+                    defs.insert(name, doc_type_json_schema(t));
+                }
+                DocItem::Module(m) => collect(&format!("{name}."), m, defs),
+                DocItem::Member(DocMember::Property(_)) => {}
+            }
+        }
+    }
+
+    let mut defs = Map::new();
+    collect("", module, &mut defs);
+    with_description(json!({"$schema": SCHEMA, "$defs": defs}), &module.docs)
+}
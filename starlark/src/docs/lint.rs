@@ -0,0 +1,138 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Check that documentation agrees with the signatures it documents.
+//!
+//! Intended to run in tests or CI over the output of `Globals::documentation`, so that
+//! docstrings of `#[starlark_module]` functions don't drift from their parameters.
+
+use std::fmt;
+use std::fmt::Display;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocStringIssue;
+use crate::docs::DocType;
+use crate::typing::Ty;
+
+/// A kind of problem found by [`lint_doc_module`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DocLintKind {
+    #[error("Parameter `{0}` is documented, but is not in the signature")]
+    UnknownParam(String),
+    #[error("Parameter `{0}` is not documented")]
+    UndocumentedParam(String),
+    #[error("Return value is not documented")]
+    MissingReturnDocs,
+    #[error("Section `{0}` is empty")]
+    EmptySection(String),
+    #[error("Line `{line}` in section `{section}` is not a parameter")]
+    MalformedLine { section: String, line: String },
+}
+
+/// A problem with the documentation of the item at `path`, like `module.function`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocLint {
+    pub path: String,
+    pub kind: DocLintKind,
+}
+
+impl Display for DocLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.kind)
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+/// Lint a single function.
+///
+/// The return value must be documented unless the function returns `None`, never returns,
+/// or has no return type, as is common for functions defined in Starlark.
+pub fn lint_doc_function(path: &str, function: &DocFunction) -> Vec<DocLint> {
+    let lint = |kind| DocLint {
+        path: path.to_owned(),
+        kind,
+    };
+    let mut res = Vec::new();
+    for issue in &function.issues {
+        res.push(lint(match issue {
+            DocStringIssue::UnknownParam(name) => DocLintKind::UnknownParam(name.clone()),
+            DocStringIssue::EmptySection(section) => DocLintKind::EmptySection(section.clone()),
+            DocStringIssue::MalformedLine { section, line } => DocLintKind::MalformedLine {
+                section: section.clone(),
+                line: line.clone(),
+            },
+        }));
+    }
+    for (name, param) in function.params.doc_params_with_starred_names() {
+        if param.docs.is_none() {
+            res.push(lint(DocLintKind::UndocumentedParam(name)));
+        }
+    }
+    let ret = &function.ret.typ;
+    if function.ret.docs.is_none() && !ret.is_any() && !ret.is_never() && *ret != Ty::none() {
+        res.push(lint(DocLintKind::MissingReturnDocs));
+    }
+    res
+}
+
+/// Lint the constructor and methods of a type, named `path`.
+pub fn lint_doc_type(path: &str, ty: &DocType) -> Vec<DocLint> {
+    let mut res = Vec::new();
+    if let Some(constructor) = &ty.constructor {
+        res.extend(lint_doc_function(path, constructor));
+    }
+    for (name, member) in &ty.members {
+        if let DocMember::Function(f) = member {
+            res.extend(lint_doc_function(&join(path, name), f));
+        }
+    }
+    res
+}
+
+fn lint_doc_item(path: &str, item: &DocItem, res: &mut Vec<DocLint>) {
+    match item {
+        DocItem::Module(m) => lint_members(path, m, res),
+        DocItem::Type(t) => res.extend(lint_doc_type(path, t)),
+        DocItem::Member(DocMember::Function(f)) => res.extend(lint_doc_function(path, f)),
+        DocItem::Member(DocMember::Property(_)) => {}
+    }
+}
+
+fn lint_members(path: &str, module: &DocModule, res: &mut Vec<DocLint>) {
+    for (name, item) in &module.members {
+        lint_doc_item(&join(path, name), item, res);
+    }
+}
+
+/// Lint every function in the module, including those of nested modules and types.
+///
+/// Paths of the results are relative to the module, like `struct` or `json.encode`.
+pub fn lint_doc_module(module: &DocModule) -> Vec<DocLint> {
+    let mut res = Vec::new();
+    lint_members("", module, &mut res);
+    res
+}
@@ -74,9 +74,14 @@ fn render_property(name: &str, property: &DocProperty, render_config: &RenderCon
             "{name}: {}",
             &property.typ.display_with(&render_config.type_config)
         ),
-        &render_config.type_config,
+        render_config,
+    );
+    let header = format!(
+        "{}\n\n{prototype}",
+        render_config
+            .markdown_options
+            .heading(1, &escape_name(name), Some(name))
     );
-    let header = format!("## {}\n\n{prototype}", escape_name(name));
     let summary = render_doc_string(DSOpts::Summary, &property.docs);
     let details = render_doc_string(DSOpts::Details, &property.docs);
 
@@ -158,10 +163,15 @@ fn render_default_layout(
 ) -> String {
     let prototype = render_code_block(
         &render_function_prototype(name, function, &render_config.type_config),
-        &render_config.type_config,
+        render_config,
     );
     let header = if include_header {
-        format!("## {}\n\n{prototype}", escape_name(name))
+        format!(
+            "{}\n\n{prototype}",
+            render_config
+                .markdown_options
+                .heading(1, &escape_name(name), Some(name))
+        )
     } else {
         prototype
     };
@@ -180,16 +190,16 @@ fn render_default_layout(
         body.push_str(summary);
     }
     if let Some(parameter_docs) = &parameter_docs {
-        body.push_str("\n\n#### Parameters\n\n");
+        body.push_str(&render_config.markdown_options.section(3, "Parameters"));
         body.push_str(parameter_docs);
     }
     if let Some(returns) = &return_docs {
-        body.push_str("\n\n#### Returns\n\n");
+        body.push_str(&render_config.markdown_options.section(3, "Returns"));
         body.push_str(returns);
     }
     if let Some(details) = &details {
         if parameter_docs.is_some() || return_docs.is_some() {
-            body.push_str("\n\n#### Details\n\n");
+            body.push_str(&render_config.markdown_options.section(3, "Details"));
         } else {
             // No need to aggressively separate the defaults from the summary if there
             // was nothing in between them. Just let it flow.
@@ -198,7 +208,7 @@ fn render_default_layout(
         body.push_str(details);
     }
     if let Some(examples) = &examples {
-        body.push_str("\n\n#### Examples\n\n");
+        body.push_str(&render_config.markdown_options.section(3, "Examples"));
         body.push_str(examples);
     }
 
@@ -212,7 +222,7 @@ fn render_signature_at_bottom_layout(
 ) -> String {
     let prototype = render_code_block(
         &render_function_prototype(name, function, &render_config.type_config),
-        &render_config.type_config,
+        render_config,
     );
 
     let summary = render_doc_string(DSOpts::Summary, &function.docs);
@@ -227,21 +237,22 @@ fn render_signature_at_bottom_layout(
         body.push_str(summary);
     }
     if let Some(details) = &details {
-        body.push_str("\n\n### Details\n\n");
+        body.push_str(&render_config.markdown_options.section(2, "Details"));
         body.push_str(details);
     }
-    body.push_str("\n\n");
-    body.push_str(&format!("### Function Signature\n\n{prototype}"));
+    body.push_str(
+        &render_config
+            .markdown_options
+            .section(2, "Function Signature"),
+    );
+    body.push_str(&prototype);
     if let Some(parameter_docs) = &parameter_docs {
-        body.push_str("\n\n### Parameters\n\n");
+        body.push_str(&render_config.markdown_options.section(2, "Parameters"));
         body.push_str(parameter_docs);
     }
     if let Some(examples) = &examples {
-        body.push_str("\n\n### Examples\n\n");
-        body.push_str(&render_strings_with_code_blocks(
-            examples,
-            &render_config.type_config,
-        ));
+        body.push_str(&render_config.markdown_options.section(2, "Examples"));
+        body.push_str(&render_strings_with_code_blocks(examples, render_config));
     }
 
     body
@@ -259,22 +270,34 @@ pub(super) fn render_members<'a>(
         .map(|s| format!("\n\n{s}"))
         .unwrap_or_default();
 
-    let member_details = members
+    let members: Vec<_> = members
         .into_iter()
         .sorted_by(|(l_m, _), (r_m, _)| l_m.cmp(r_m))
-        .map(|(child, member)| {
-            render_doc_member(&format!("{prefix}{child}"), &member, render_config)
-        });
+        .map(|(child, member)| (format!("{prefix}{child}"), member))
+        .collect();
+    let options = &render_config.markdown_options;
+    let toc = if options.table_of_contents && !members.is_empty() {
+        let mut toc = String::new();
+        for (name, _) in &members {
+            let _ = writeln!(toc, "* [{}](#{})", escape_name(name), anchor_id(name));
+        }
+        format!("\n\n{}", toc.trim_end())
+    } else {
+        String::new()
+    };
+    let member_details = members
+        .iter()
+        .map(|(name, member)| render_doc_member(name, member, render_config));
     let member_details: Vec<_> = after_summary.into_iter().chain(member_details).collect();
     let members_details = member_details.join("\n\n---\n\n");
 
     let header = if name == "" {
         "".to_owned()
     } else {
-        format!("# {name}")
+        options.heading(0, name, None)
     };
 
-    format!("{header}{summary}\n\n{members_details}")
+    format!("{header}{summary}{toc}\n\n{members_details}")
 }
 
 pub(super) fn render_doc_type(
@@ -306,6 +329,7 @@ pub fn render_doc_item_no_link(name: &str, item: &DocItem) -> String {
         &RenderConfig {
             type_config: TypeRenderConfig::Default,
             layout_config: LayoutRenderConfig::Default,
+            markdown_options: MarkdownOptions::default(),
         },
     )
 }
@@ -364,7 +388,7 @@ fn raw_type_prefix(prefix: &str, t: &Ty, render_config: &TypeRenderConfig) -> St
     }
 }
 
-fn render_function_prototype(
+pub(super) fn render_function_prototype(
     function_name: &str,
     f: &DocFunction,
     render_config: &TypeRenderConfig,
@@ -384,7 +408,7 @@ fn render_function_prototype(
     }
 }
 
-fn render_strings_with_code_blocks(contents: &str, render_config: &TypeRenderConfig) -> String {
+fn render_strings_with_code_blocks(contents: &str, render_config: &RenderConfig) -> String {
     let re = LazyLock::new(|| Regex::new(r"```([\s\S]*?)```").unwrap());
     re.replace_all(contents, |captures: &regex::Captures| {
         render_code_block(&captures[1], render_config)
@@ -400,13 +424,14 @@ fn render_strings_with_code_blocks(contents: &str, render_config: &TypeRenderCon
 //    def soome_function() -> <Link to="/path/to/type">Artifact</Link>
 //  </code>
 //</pre>
-fn render_code_block(contents: &str, render_config: &TypeRenderConfig) -> String {
-    match render_config {
-        TypeRenderConfig::Default => format!("```python\n{contents}\n```"),
+fn render_code_block(contents: &str, render_config: &RenderConfig) -> String {
+    let language = &render_config.markdown_options.code_fence_language;
+    match render_config.type_config {
+        TypeRenderConfig::Default => format!("```{language}\n{contents}\n```"),
         TypeRenderConfig::LinkedType {
             render_linked_ty_starlark_value: _,
         } => {
-            format!(r#"<pre class="language-python"><code>{contents}</code></pre>"#)
+            format!(r#"<pre class="language-{language}"><code>{contents}</code></pre>"#)
         }
     }
 }
@@ -440,6 +465,75 @@ impl DocType {
     }
 }
 
+/// How the anchors of members, which the table of contents links to, are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorStyle {
+    /// No explicit anchors, relying on renderers like GitHub generating them from headings.
+    Implicit,
+    /// An HTML anchor, `<a id="name"></a>`, before the heading of each member.
+    Html,
+    /// A heading attribute, `## name {#name}`, as supported by Pandoc and many static site
+    /// generators.
+    HeadingAttribute,
+}
+
+/// Options for the markdown output.
+///
+/// The defaults match the output of previous versions.
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// The level of the heading of a module or type, `1` for `#`. Members are one
+    /// level below, and the sections of members, like their parameters, lower still.
+    pub base_heading_level: usize,
+    /// Whether to list the members, linking to them, after the summary of a module or type.
+    pub table_of_contents: bool,
+    pub anchor_style: AnchorStyle,
+    /// The language of code blocks, like prototypes.
+    pub code_fence_language: String,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            base_heading_level: 1,
+            table_of_contents: false,
+            anchor_style: AnchorStyle::Implicit,
+            code_fence_language: "python".to_owned(),
+        }
+    }
+}
+
+impl MarkdownOptions {
+    /// A heading `offset` levels below the base, with an anchor if `anchor` is given.
+    fn heading(&self, offset: usize, text: &str, anchor: Option<&str>) -> String {
+        // Markdown has no headings below level 6.
+        let hashes = "#".repeat((self.base_heading_level + offset).clamp(1, 6));
+        match (anchor, self.anchor_style) {
+            (Some(anchor), AnchorStyle::Html) => {
+                format!("<a id=\"{}\"></a>\n\n{hashes} {text}", anchor_id(anchor))
+            }
+            (Some(anchor), AnchorStyle::HeadingAttribute) => {
+                format!("{hashes} {text} {{#{}}}", anchor_id(anchor))
+            }
+            _ => format!("{hashes} {text}"),
+        }
+    }
+
+    /// A section heading, like the parameters of a function, separated by blank lines.
+    fn section(&self, offset: usize, title: &str) -> String {
+        format!("\n\n{}\n\n", self.heading(offset, title, None))
+    }
+}
+
+/// The anchor of a member, as GitHub generates it from a heading: lowercase, without
+/// punctuation other than `-` and `_`.
+fn anchor_id(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
 /// Configuration for layout rendering.
 pub enum LayoutRenderConfig {
     Default,
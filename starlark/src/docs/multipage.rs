@@ -23,6 +23,7 @@ use crate::docs::DocItem;
 use crate::docs::DocModule;
 use crate::docs::DocType;
 use crate::docs::markdown::LayoutRenderConfig;
+use crate::docs::markdown::MarkdownOptions;
 use crate::typing::Ty;
 use crate::typing::TyBasic;
 use crate::typing::TyStarlarkValue;
@@ -31,6 +32,7 @@ use crate::typing::ty::TypeRenderConfig;
 pub struct RenderConfig {
     pub type_config: TypeRenderConfig,
     pub layout_config: LayoutRenderConfig,
+    pub markdown_options: MarkdownOptions,
 }
 
 pub struct DocModuleInfo<'a> {
@@ -41,7 +43,7 @@ pub struct DocModuleInfo<'a> {
 }
 
 impl<'a> DocModuleInfo<'a> {
-    fn into_page_renders(&self) -> Vec<PageRender<'a>> {
+    pub(super) fn into_page_renders(&self) -> Vec<PageRender<'a>> {
         Self::traverse_inner(&self.module, &self.name, &self.page_path)
     }
 
@@ -87,18 +89,18 @@ impl<'a> DocModuleInfo<'a> {
 /// A reference to a page to render
 /// DocsRender will have all the PageRender it needs to render the docs
 /// Since types and some modules are owned by other modules, we need to use the reference here
-enum DocPageRef<'a> {
+pub(super) enum DocPageRef<'a> {
     Module(&'a DocModule),
     Type(&'a DocType),
 }
 
 /// A single page to render
-struct PageRender<'a> {
-    page: DocPageRef<'a>,
-    path: String,
-    name: String,
+pub(super) struct PageRender<'a> {
+    pub(super) page: DocPageRef<'a>,
+    pub(super) path: String,
+    pub(super) name: String,
     /// The type of the page, if it is a type page. This is used to get the link to the type.
-    ty: Option<Ty>,
+    pub(super) ty: Option<Ty>,
 }
 
 impl<'a> PageRender<'a> {
@@ -132,6 +134,7 @@ impl<'a> MultipageRender<'a> {
         docs: Vec<DocModuleInfo<'a>>,
         linked_ty_mapper: Option<fn(&str, &str) -> String>,
         render_signature_at_bottom: bool,
+        markdown_options: MarkdownOptions,
     ) -> Self {
         let mut res = vec![];
 
@@ -168,6 +171,7 @@ impl<'a> MultipageRender<'a> {
                 } else {
                     LayoutRenderConfig::Default
                 },
+                markdown_options,
             },
         }
     }
@@ -194,7 +198,27 @@ pub fn render_markdown_multipage(
     linked_ty_mapper: Option<fn(&str, &str) -> String>,
     render_signature_at_bottom: bool,
 ) -> HashMap<String, String> {
-    let multipage_render =
-        MultipageRender::new(modules_infos, linked_ty_mapper, render_signature_at_bottom);
+    render_markdown_multipage_with_options(
+        modules_infos,
+        linked_ty_mapper,
+        render_signature_at_bottom,
+        MarkdownOptions::default(),
+    )
+}
+
+/// Same as [`render_markdown_multipage`], with options for the markdown, like the heading
+/// levels or a table of contents for each page.
+pub fn render_markdown_multipage_with_options(
+    modules_infos: Vec<DocModuleInfo<'_>>,
+    linked_ty_mapper: Option<fn(&str, &str) -> String>,
+    render_signature_at_bottom: bool,
+    markdown_options: MarkdownOptions,
+) -> HashMap<String, String> {
+    let multipage_render = MultipageRender::new(
+        modules_infos,
+        linked_ty_mapper,
+        render_signature_at_bottom,
+        markdown_options,
+    );
     multipage_render.render_markdown_pages()
 }
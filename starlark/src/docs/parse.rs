@@ -33,6 +33,7 @@ use crate::docs::DocParam;
 use crate::docs::DocParams;
 use crate::docs::DocReturn;
 use crate::docs::DocString;
+use crate::docs::DocStringIssue;
 use crate::typing::Ty;
 
 /// Controls the formatting to use when parsing `DocString`s from raw docstrings
@@ -337,10 +338,14 @@ impl DocFunction {
             Some(ds) => {
                 let (function_docstring, sections) =
                     ds.parse_and_remove_sections(kind, &["arguments", "args", "returns", "return"]);
-
-                match sections.get("arguments").or_else(|| sections.get("args")) {
-                    Some(args) => {
-                        let entries = Self::parse_params(kind, args);
+                let mut issues = Vec::new();
+
+                let args_section = ["arguments", "args"]
+                    .into_iter()
+                    .find_map(|s| Some((s, sections.get(s)?)));
+                match args_section {
+                    Some((section, args)) => {
+                        let entries = Self::parse_params(kind, section, args, &mut issues);
                         for x in &mut params.doc_params_mut() {
                             let DocParam { name, docs, .. } = x;
                             match entries.get(name) {
@@ -348,14 +353,36 @@ impl DocFunction {
                                 None => {}
                             }
                         }
+                        let mut unknown: Vec<&String> = entries
+                            .keys()
+                            .filter(|k| params.doc_params().all(|p| &p.name != *k))
+                            .collect();
+                        unknown.sort();
+                        issues.extend(
+                            unknown
+                                .into_iter()
+                                .map(|k| DocStringIssue::UnknownParam(k.clone())),
+                        );
+                        if entries.is_empty() && args.trim().is_empty() {
+                            issues.push(DocStringIssue::EmptySection(section.to_owned()));
+                        }
                     }
                     _ => (),
                 }
 
-                let return_docs = sections
-                    .get("return")
-                    .or_else(|| sections.get("returns"))
-                    .and_then(|raw| DocString::from_docstring(kind, raw));
+                let returns_section = ["return", "returns"]
+                    .into_iter()
+                    .find_map(|s| Some((s, sections.get(s)?)));
+                let return_docs = match returns_section {
+                    Some((section, raw)) => {
+                        let docs = DocString::from_docstring(kind, raw);
+                        if docs.is_none() {
+                            issues.push(DocStringIssue::EmptySection(section.to_owned()));
+                        }
+                        docs
+                    }
+                    None => None,
+                };
 
                 DocFunction {
                     docs: Some(function_docstring),
@@ -364,6 +391,7 @@ impl DocFunction {
                         docs: return_docs,
                         typ: return_type,
                     },
+                    issues,
                 }
             }
             None => DocFunction {
@@ -373,6 +401,7 @@ impl DocFunction {
                     docs: None,
                     typ: return_type,
                 },
+                issues: Vec::new(),
             },
         }
     }
@@ -383,7 +412,14 @@ impl DocFunction {
     /// the `DocString::parse_and_remove_sections()` function call. This is done as a
     /// separate function to reduce the number of times that sections are parsed out of
     /// docstring (e.g. if a user wants both the `Args:` and `Returns:` sections)
-    fn parse_params(kind: DocStringKind, args_section: &str) -> HashMap<String, String> {
+    ///
+    /// Lines which are neither a parameter nor a continuation of one are added to `issues`.
+    fn parse_params(
+        kind: DocStringKind,
+        section: &str,
+        args_section: &str,
+        issues: &mut Vec<DocStringIssue>,
+    ) -> HashMap<String, String> {
         static STARLARK_ARG_RE: Lazy<Regex> =
             Lazy::new(|| Regex::new(r"^\*{0,2}(\w+):\s*(.*)").unwrap());
         static RUST_ARG_RE: Lazy<Regex> =
@@ -416,6 +452,11 @@ impl DocFunction {
                 )];
             } else if current_arg.is_some() && INDENTED_RE.is_match(line) {
                 current_text.push(line.to_owned());
+            } else if !line.trim().is_empty() {
+                issues.push(DocStringIssue::MalformedLine {
+                    section: section.to_owned(),
+                    line: line.trim().to_owned(),
+                });
             }
         }
 
@@ -764,6 +805,7 @@ mod tests {
                 docs: DocString::from_docstring(kind, "A value"),
                 typ: return_type.clone(),
             },
+            issues: Vec::new(),
         };
 
         let function_docs = DocFunction::from_docstring(
@@ -833,6 +875,7 @@ mod tests {
                 docs: DocString::from_docstring(kind, "A value"),
                 typ: return_type.clone(),
             },
+            issues: Vec::new(),
         };
 
         let function_docs = DocFunction::from_docstring(
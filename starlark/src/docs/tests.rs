@@ -15,5 +15,10 @@
  * limitations under the License.
  */
 
+mod html;
+mod json;
+mod json_schema;
+mod lint;
 mod markdown;
 mod rustdocs;
+mod text;
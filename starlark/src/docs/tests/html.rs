@@ -0,0 +1,108 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use allocative::Allocative;
+use derive_more::Display;
+use serde::Serialize;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::docs::DocItem;
+use crate::docs::html::render_html_pages;
+use crate::docs::multipage::DocModuleInfo;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::starlark_value_as_type::StarlarkValueAsType;
+
+#[derive(ProvidesStaticType, Debug, Display, Allocative, Serialize)]
+#[display("widget")]
+struct Widget;
+
+starlark_simple_value!(Widget);
+
+#[starlark_value(type = "widget")]
+impl<'v> StarlarkValue<'v> for Widget {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(widget_methods)
+    }
+}
+
+#[starlark_module]
+fn widget_methods(builder: &mut MethodsBuilder) {
+    /// The size of the widget.
+    #[starlark(attribute)]
+    fn size<'v>(this: Value<'v>) -> starlark::Result<i32> {
+        let _ignore = this;
+        Ok(1)
+    }
+}
+
+#[starlark_module]
+fn ui(builder: &mut GlobalsBuilder) {
+    const Widget: StarlarkValueAsType<Widget> = StarlarkValueAsType::new();
+
+    /// Make a widget, if `a < b`.
+    fn new_widget(a: i32, b: i32) -> anyhow::Result<Widget> {
+        let _ignore = (a, b);
+        Ok(Widget)
+    }
+}
+
+#[test]
+fn test_render_html_pages() {
+    let globals = GlobalsBuilder::new().with_namespace("ui", ui).build();
+    let docs = globals.documentation();
+    let pages = render_html_pages(vec![DocModuleInfo {
+        module: &docs,
+        name: "globals".to_owned(),
+        page_path: "".to_owned(),
+    }]);
+
+    let mut files: Vec<&str> = pages.keys().map(|x| x.as_str()).collect();
+    files.sort();
+    assert_eq!(files, &["index.html", "ui/Widget.html", "ui/index.html"]);
+
+    let index = &pages["index.html"];
+    assert!(index.starts_with("<!DOCTYPE html>"));
+    assert!(index.contains(r#"<a href="ui/index.html"><code>ui</code></a> module"#));
+
+    let ui_page = &pages["ui/index.html"];
+    assert!(ui_page.contains(r#"<nav><a href="../index.html">Index</a></nav>"#));
+    assert!(ui_page.contains(r#"<a href="../ui/Widget.html"><code>Widget</code></a> type"#));
+    assert!(ui_page.contains(r##"<a href="#new_widget">"##));
+    assert!(ui_page.contains(r#"<section id="new_widget">"#));
+    assert!(ui_page.contains("Make a widget, if `a &lt; b`."));
+    // The return type links to the page of the type.
+    assert!(ui_page.contains(r#"-&gt; <a href="../ui/Widget.html">widget</a>"#));
+
+    let DocItem::Module(ui_docs) = docs.members.get("ui").unwrap() else {
+        panic!("expected a module");
+    };
+    assert!(ui_docs.members.contains_key("Widget"));
+    let widget_page = &pages["ui/Widget.html"];
+    assert!(widget_page.contains("<title>Widget type</title>"));
+    assert!(widget_page.contains(r#"<section id="size">"#));
+    assert!(widget_page.contains("The size of the widget."));
+}
@@ -0,0 +1,80 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use serde_json::json;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::docs::json::DOCS_JSON_VERSION;
+use crate::docs::json::JsonDocItem;
+use crate::docs::json::JsonDocs;
+use crate::docs::json::JsonParamKind;
+use crate::docs::json::render_docs_json;
+use crate::environment::GlobalsBuilder;
+use crate::values::none::NoneType;
+use crate::values::tuple::UnpackTuple;
+
+#[starlark_module]
+fn tools(builder: &mut GlobalsBuilder) {
+    const VERSION: i32 = 3;
+
+    /// Run a tool.
+    fn run(
+        #[starlark(require = pos)] name: &str,
+        #[starlark(args)] args: UnpackTuple<String>,
+        #[starlark(require = named, default = false)] verbose: bool,
+    ) -> anyhow::Result<NoneType> {
+        let _ignore = (name, args, verbose);
+        Ok(NoneType)
+    }
+}
+
+#[test]
+fn test_render_docs_json() {
+    let globals = GlobalsBuilder::new().with_namespace("tools", tools).build();
+    let rendered = render_docs_json(&globals.documentation());
+    let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+    assert_eq!(DOCS_JSON_VERSION, value["version"]);
+    let tools = &value["module"]["members"]["tools"];
+    assert_eq!("module", tools["kind"]);
+    assert_eq!(
+        json!({"kind": "property", "type": "int", "docs": null}),
+        tools["members"]["VERSION"]
+    );
+    let run = &tools["members"]["run"];
+    assert_eq!("function", run["kind"]);
+    assert_eq!("Run a tool.", run["docs"]["summary"]);
+    assert_eq!("None", run["return"]["type"]);
+    assert_eq!(
+        json!([
+            {"name": "name", "kind": "pos_only", "type": "str", "default": null, "docs": null},
+            {"name": "args", "kind": "args", "type": "str", "default": null, "docs": null},
+            {"name": "verbose", "kind": "named_only", "type": "bool", "default": "False", "docs": null},
+        ]),
+        run["params"]
+    );
+
+    // Tools can read the documents back.
+    let docs: JsonDocs = serde_json::from_str(&rendered).unwrap();
+    let JsonDocItem::Module(tools) = &docs.module.members["tools"] else {
+        panic!("expected a module");
+    };
+    let JsonDocItem::Function(run) = &tools.members["run"] else {
+        panic!("expected a function");
+    };
+    assert_eq!(JsonParamKind::NamedOnly, run.params[2].kind);
+}
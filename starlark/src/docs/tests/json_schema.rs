@@ -0,0 +1,86 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use serde_json::json;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::json_schema::function_json_schema;
+use crate::docs::json_schema::module_json_schema;
+use crate::environment::GlobalsBuilder;
+use crate::values::list::UnpackList;
+use crate::values::none::NoneOr;
+use crate::values::none::NoneType;
+
+#[starlark_module]
+fn deploy(builder: &mut GlobalsBuilder) {
+    /// Deploy a service.
+    fn deploy(
+        #[starlark(require = named)] name: String,
+        #[starlark(require = named, default = 1)] replicas: i32,
+        #[starlark(require = named)] ports: UnpackList<i32>,
+        #[starlark(require = named)] env: NoneOr<(String, bool)>,
+    ) -> anyhow::Result<NoneType> {
+        let _ignore = (name, replicas, ports, env);
+        Ok(NoneType)
+    }
+}
+
+#[test]
+fn test_function_json_schema() {
+    let globals = GlobalsBuilder::new().with(deploy).build();
+    let docs = globals.documentation();
+    let DocItem::Member(DocMember::Function(f)) = docs.members.get("deploy").unwrap() else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        json!({
+            "type": "object",
+            "description": "Deploy a service.",
+            "properties": {
+                "name": {"type": "string"},
+                "replicas": {"type": "integer", "default": 1},
+                "ports": {"type": "array", "items": {"type": "integer"}},
+                "env": {"anyOf": [
+                    {"type": "null"},
+                    {
+                        "type": "array",
+                        "prefixItems": [{"type": "string"}, {"type": "boolean"}],
+                        "minItems": 2,
+                        "maxItems": 2,
+                    },
+                ]},
+            },
+            "required": ["name", "ports", "env"],
+            "additionalProperties": false,
+        }),
+        function_json_schema(f)
+    );
+}
+
+#[test]
+fn test_module_json_schema() {
+    let globals = GlobalsBuilder::new().with(deploy).build();
+    let schema = module_json_schema(&globals.documentation());
+    assert_eq!(
+        "https://json-schema.org/draft/2020-12/schema",
+        schema["$schema"]
+    );
+    assert_eq!("object", schema["$defs"]["deploy"]["type"]);
+}
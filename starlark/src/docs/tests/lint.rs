@@ -0,0 +1,119 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::docs::DocFunction;
+use crate::docs::DocParam;
+use crate::docs::DocParams;
+use crate::docs::DocStringKind;
+use crate::docs::lint::DocLint;
+use crate::docs::lint::DocLintKind;
+use crate::docs::lint::lint_doc_function;
+use crate::docs::lint::lint_doc_module;
+use crate::environment::GlobalsBuilder;
+use crate::typing::Ty;
+use crate::values::none::NoneType;
+
+#[starlark_module]
+fn files(builder: &mut GlobalsBuilder) {
+    /// Remove a file.
+    ///
+    /// # Arguments
+    /// * `path`: The file to remove.
+    fn remove(path: &str) -> anyhow::Result<NoneType> {
+        let _ignore = path;
+        Ok(NoneType)
+    }
+
+    /// Copy a file, returning the destination.
+    ///
+    /// # Arguments
+    /// * `src`: The file to copy.
+    /// * `source`: The old name of `src`.
+    fn copy(src: &str, dst: &str) -> anyhow::Result<String> {
+        let _ignore = src;
+        Ok(dst.to_owned())
+    }
+}
+
+#[test]
+fn test_lint_doc_module() {
+    let globals = GlobalsBuilder::new().with(files).build();
+    let lints = lint_doc_module(&globals.documentation());
+    let lint = |kind| DocLint {
+        path: "copy".to_owned(),
+        kind,
+    };
+    assert_eq!(
+        vec![
+            lint(DocLintKind::UnknownParam("source".to_owned())),
+            lint(DocLintKind::UndocumentedParam("dst".to_owned())),
+            lint(DocLintKind::MissingReturnDocs),
+        ],
+        lints
+    );
+    assert_eq!(
+        "copy: Parameter `source` is documented, but is not in the signature",
+        lints[0].to_string()
+    );
+}
+
+#[test]
+fn test_lint_malformed_sections() {
+    let param = |name: &str| DocParam {
+        name: name.to_owned(),
+        docs: None,
+        typ: Ty::any(),
+        default_value: None,
+    };
+    let docstring = r#"Frobnicate.
+
+    Args:
+        x: The thing.
+        y (int): Not a parameter.
+        *args: More things.
+
+    Returns:
+    "#;
+    let function = DocFunction::from_docstring(
+        DocStringKind::Starlark,
+        DocParams {
+            pos_or_named: vec![param("x")],
+            args: Some(param("args")),
+            ..DocParams::default()
+        },
+        Ty::int(),
+        Some(docstring),
+    );
+    let kinds: Vec<DocLintKind> = lint_doc_function("frobnicate", &function)
+        .into_iter()
+        .map(|l| l.kind)
+        .collect();
+    assert_eq!(
+        vec![
+            DocLintKind::MalformedLine {
+                section: "args".to_owned(),
+                line: "y (int): Not a parameter.".to_owned(),
+            },
+            DocLintKind::EmptySection("returns".to_owned()),
+            DocLintKind::MissingReturnDocs,
+        ],
+        kinds
+    );
+}
@@ -31,14 +31,20 @@ use crate::any::ProvidesStaticType;
 use crate::assert;
 use crate::docs::DocItem;
 use crate::docs::DocType;
+use crate::docs::markdown::AnchorStyle;
+use crate::docs::markdown::LayoutRenderConfig;
+use crate::docs::markdown::MarkdownOptions;
+use crate::docs::markdown::render_doc_item;
 use crate::docs::markdown::render_doc_item_no_link;
 use crate::docs::multipage::DocModuleInfo;
+use crate::docs::multipage::RenderConfig;
 use crate::docs::multipage::render_markdown_multipage;
 use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
 use crate::environment::Methods;
 use crate::environment::MethodsBuilder;
 use crate::environment::MethodsStatic;
+use crate::typing::ty::TypeRenderConfig;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 use crate::values::list::UnpackList;
@@ -342,6 +348,45 @@ fn globals_render_signature_at_bottom_with_linked_type() {
     test_globals_docs_render(true, true);
 }
 
+#[test]
+fn test_markdown_options() {
+    let globals = GlobalsBuilder::new()
+        .with_namespace("submod", submodule)
+        .build();
+    let render_config = RenderConfig {
+        type_config: TypeRenderConfig::Default,
+        layout_config: LayoutRenderConfig::Default,
+        markdown_options: MarkdownOptions {
+            base_heading_level: 2,
+            table_of_contents: true,
+            anchor_style: AnchorStyle::HeadingAttribute,
+            code_fence_language: "starlark".to_owned(),
+        },
+    };
+    let output = render_doc_item(
+        "submod",
+        globals.documentation().members.get("submod").unwrap(),
+        &render_config,
+    );
+    assert!(
+        output.starts_with(
+            r#"## submod
+
+* [new\_obj](#new_obj)
+* [notypes](#notypes)
+* [starlark\_args](#starlark_args)
+* [starlark\_kwargs](#starlark_kwargs)
+
+### new\_obj {#new_obj}
+
+```starlark
+def new_obj() -> obj
+```"#
+        ),
+        "{output}"
+    );
+}
+
 #[test]
 fn golden_docs_object() {
     let docs = DocType::from_starlark_value::<Obj>();
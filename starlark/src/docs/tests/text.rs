@@ -0,0 +1,82 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::docs::text::TextRenderOptions;
+use crate::docs::text::render_doc_item_text;
+use crate::environment::GlobalsBuilder;
+use crate::values::none::NoneType;
+
+#[starlark_module]
+fn files(builder: &mut GlobalsBuilder) {
+    /// Copy files from one place to another, preserving their permissions unless told otherwise.
+    ///
+    /// # Arguments
+    ///     * `src`: The file to copy.
+    ///
+    /// # Returns
+    /// Nothing.
+    fn copy(src: &str, #[starlark(default = false)] force: bool) -> anyhow::Result<NoneType> {
+        let _ignore = (src, force);
+        Ok(NoneType)
+    }
+}
+
+#[test]
+fn test_render_doc_item_text() {
+    let globals = GlobalsBuilder::new().with(files).build();
+    let docs = globals.documentation();
+    let copy = docs.members.get("copy").unwrap();
+
+    let options = TextRenderOptions {
+        width: 40,
+        ansi: false,
+    };
+    let text = render_doc_item_text("copy", copy, &options);
+    assert!(text.starts_with(
+        "copy
+    def copy(src: str, force: bool = False) -> None
+
+    Copy files from one place to
+    another, preserving their
+    permissions unless told otherwise.
+"
+    ));
+    assert!(
+        text.contains("    PARAMETERS\n        src (required)\n            The file to copy.\n")
+    );
+    assert!(text.contains("    RETURNS\n        Nothing.\n"));
+    assert!(!text.contains('`'));
+
+    let options = TextRenderOptions {
+        width: 40,
+        ansi: true,
+    };
+    let text = render_doc_item_text("copy", copy, &options);
+    assert!(text.starts_with("\x1b[1mcopy\x1b[0m\n"));
+    assert!(text.contains("\x1b[4msrc\x1b[0m (required)"));
+
+    // Modules list their members.
+    let text = render_doc_item_text(
+        "files",
+        &crate::docs::DocItem::Module(docs),
+        &TextRenderOptions::default(),
+    );
+    assert!(text.starts_with("files\n\ncopy\n    def copy("));
+}
@@ -0,0 +1,296 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Render documentation as plain text for terminals, like the help of a command line tool.
+//!
+//! Text is wrapped at a configurable width, prototypes are indented rather than fenced,
+//! and headings and parameter names can be highlighted with ANSI escape sequences.
+
+use std::fmt::Write;
+use std::mem;
+
+use itertools::Itertools;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocProperty;
+use crate::docs::DocString;
+use crate::docs::DocType;
+use crate::docs::markdown::render_function_prototype;
+use crate::typing::ty::TypeRenderConfig;
+
+const INDENT: &str = "    ";
+
+/// Options for [`render_doc_item_text`].
+#[derive(Debug, Clone)]
+pub struct TextRenderOptions {
+    /// The column to wrap text at. Prototypes and code are never wrapped.
+    pub width: usize,
+    /// Use ANSI escape sequences for bold headings and underlined parameter names.
+    pub ansi: bool,
+}
+
+impl Default for TextRenderOptions {
+    fn default() -> Self {
+        TextRenderOptions {
+            width: 80,
+            ansi: false,
+        }
+    }
+}
+
+struct TextRender<'a> {
+    options: &'a TextRenderOptions,
+    out: String,
+    /// The last line is a section heading or a parameter name, so the next paragraph goes
+    /// right below it, without a blank line.
+    below_heading: bool,
+}
+
+impl<'a> TextRender<'a> {
+    fn bold(&self, s: &str) -> String {
+        if self.options.ansi {
+            format!("\x1b[1m{s}\x1b[0m")
+        } else {
+            s.to_owned()
+        }
+    }
+
+    fn underline(&self, s: &str) -> String {
+        if self.options.ansi {
+            format!("\x1b[4m{s}\x1b[0m")
+        } else {
+            s.to_owned()
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if mem::take(&mut self.below_heading) {
+            return;
+        }
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Write `s` as is, with every line indented.
+    fn verbatim(&mut self, indent: &str, s: &str) {
+        for line in s.lines() {
+            if line.is_empty() {
+                self.out.push('\n');
+            } else {
+                let _ = writeln!(self.out, "{indent}{line}");
+            }
+        }
+    }
+
+    /// Write free text, wrapping paragraphs at the width. Code blocks, and lines which
+    /// start with whitespace, like nested lists, are written as is.
+    fn text(&mut self, indent: &str, s: &str) {
+        for (i, part) in s.split("```").enumerate() {
+            if i % 2 == 1 {
+                let code = match part.split_once('\n') {
+                    Some((first, rest)) if !first.contains(' ') => rest,
+                    _ => part,
+                };
+                self.blank_line();
+                self.verbatim(&format!("{indent}{INDENT}"), code.trim_end_matches('\n'));
+                continue;
+            }
+            for paragraph in part.split("\n\n") {
+                let paragraph = paragraph.trim_matches('\n');
+                if paragraph.trim().is_empty() {
+                    continue;
+                }
+                self.blank_line();
+                let mut words = Vec::new();
+                for line in paragraph.lines() {
+                    if line.starts_with(char::is_whitespace) {
+                        self.wrap(indent, &words);
+                        words.clear();
+                        let _ = writeln!(self.out, "{indent}{line}");
+                    } else {
+                        words.extend(line.split_whitespace());
+                    }
+                }
+                self.wrap(indent, &words);
+            }
+        }
+    }
+
+    fn wrap(&mut self, indent: &str, words: &[&str]) {
+        let mut column = 0;
+        for word in words {
+            let len = word.chars().count();
+            if column == 0 {
+                self.out.push_str(indent);
+                column = indent.chars().count();
+            } else if column + 1 + len > self.options.width {
+                let _ = write!(self.out, "\n{indent}");
+                column = indent.chars().count();
+            } else {
+                self.out.push(' ');
+                column += 1;
+            }
+            self.out.push_str(word);
+            column += len;
+        }
+        if column != 0 {
+            self.out.push('\n');
+        }
+    }
+
+    fn doc_string(&mut self, indent: &str, docs: &Option<DocString>) {
+        let Some(docs) = docs else {
+            return;
+        };
+        self.text(indent, &docs.summary);
+        if let Some(details) = &docs.details {
+            self.text(indent, details);
+        }
+        if let Some(examples) = &docs.examples {
+            self.blank_line();
+            let _ = writeln!(self.out, "{indent}{}", self.bold("EXAMPLES"));
+            let indent = format!("{indent}{INDENT}");
+            if examples.contains("```") {
+                self.below_heading = true;
+                self.text(&indent, examples);
+                self.below_heading = false;
+            } else {
+                self.verbatim(&indent, examples);
+            }
+        }
+    }
+
+    /// Write the docs right below the last line, which is a heading.
+    fn doc_string_below_heading(&mut self, indent: &str, docs: &Option<DocString>) {
+        self.below_heading = true;
+        self.doc_string(indent, docs);
+        self.below_heading = false;
+    }
+
+    fn heading(&mut self, name: &str) {
+        self.blank_line();
+        let _ = writeln!(self.out, "{}", self.bold(name));
+    }
+
+    fn property(&mut self, name: &str, property: &DocProperty) {
+        self.heading(name);
+        let ty = property.typ.display_with(&TypeRenderConfig::Default);
+        let _ = writeln!(self.out, "{INDENT}{name}: {ty}");
+        self.doc_string(INDENT, &property.docs);
+    }
+
+    fn function(&mut self, name: &str, function: &DocFunction) {
+        self.heading(name);
+        self.function_body(name, function);
+    }
+
+    fn function_body(&mut self, name: &str, function: &DocFunction) {
+        let prototype = render_function_prototype(name, function, &TypeRenderConfig::Default);
+        self.verbatim(INDENT, &prototype);
+        self.doc_string(INDENT, &function.docs);
+
+        let params: Vec<_> = function
+            .params
+            .doc_params_with_starred_names()
+            .filter(|(_, p)| p.docs.is_some())
+            .collect();
+        if !params.is_empty() {
+            self.blank_line();
+            let _ = writeln!(self.out, "{INDENT}{}", self.bold("PARAMETERS"));
+            for (name, p) in params {
+                let default = match &p.default_value {
+                    Some(v) => format!(" (default: {v})"),
+                    None => " (required)".to_owned(),
+                };
+                let _ = writeln!(
+                    self.out,
+                    "{INDENT}{INDENT}{}{default}",
+                    self.underline(&name)
+                );
+                self.doc_string_below_heading(&INDENT.repeat(3), &p.docs);
+            }
+        }
+        if function.ret.docs.is_some() {
+            self.blank_line();
+            let _ = writeln!(self.out, "{INDENT}{}", self.bold("RETURNS"));
+            self.doc_string_below_heading(&INDENT.repeat(2), &function.ret.docs);
+        }
+    }
+
+    fn member(&mut self, name: &str, member: &DocMember) {
+        match member {
+            DocMember::Function(f) => self.function(name, f),
+            DocMember::Property(p) => self.property(name, p),
+        }
+    }
+
+    fn members<'m>(
+        &mut self,
+        prefix: &str,
+        members: impl IntoIterator<Item = (&'m str, DocMember)>,
+    ) {
+        for (name, member) in members.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+            self.member(&format!("{prefix}{name}"), &member);
+        }
+    }
+
+    fn doc_type(&mut self, name: &str, t: &DocType) {
+        self.heading(&format!("{name} type"));
+        self.doc_string(INDENT, &t.docs);
+        if let Some(constructor) = &t.constructor {
+            self.blank_line();
+            self.function_body(name, constructor);
+        }
+        self.members(
+            &format!("{name}."),
+            t.members.iter().map(|(n, m)| (n.as_str(), m.clone())),
+        );
+    }
+}
+
+/// Render the documentation of `name` as plain text, like for `--help`.
+///
+/// Modules list their members, with any nested modules and types collapsed into a property.
+pub fn render_doc_item_text(name: &str, item: &DocItem, options: &TextRenderOptions) -> String {
+    let mut render = TextRender {
+        options,
+        out: String::new(),
+        below_heading: false,
+    };
+    match item {
+        DocItem::Module(m) => {
+            if !name.is_empty() {
+                render.heading(name);
+            }
+            render.doc_string(INDENT, &m.docs);
+            render.members(
+                "",
+                m.members.iter().filter_map(|(n, m)| {
+                    m.try_as_member_with_collapsed_object()
+                        .ok()
+                        .map(|m| (n.as_str(), m))
+                }),
+            );
+        }
+        DocItem::Type(t) => render.doc_type(name, t),
+        DocItem::Member(m) => render.member(name, m),
+    }
+    render.out
+}
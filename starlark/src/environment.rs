@@ -24,14 +24,26 @@
 
 mod globals;
 mod methods;
+mod module_cache;
 mod module_dump;
+mod module_graph;
+mod module_registry;
 mod modules;
 pub(crate) mod names;
 pub(crate) mod slots;
 
 pub use globals::*;
 pub use methods::*;
+pub use module_cache::*;
+pub use module_graph::*;
+pub use module_registry::*;
 pub use modules::*;
+
+pub use crate::stdlib::library::Library;
+pub use crate::stdlib::library::LibraryBuilder;
+pub use crate::stdlib::library::LibraryVersion;
+#[cfg(feature = "fs")]
+pub use crate::stdlib::os::OsPolicy;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -45,4 +57,10 @@ enum EnvironmentError {
     ModuleHasNoSymbolDidYouMean(String, String),
     #[error("Module symbol `{0}` is not exported")]
     ModuleSymbolIsNotExported(String),
+    #[error("Module symbol `{0}` is not exported, it is not listed in `__all__`")]
+    ModuleSymbolIsNotInExportList(String),
+    #[error("`__all__` must be a list of strings, got a value of type `{0}`")]
+    ExportListNotStrings(String),
+    #[error("`__all__` lists `{0}`, which is not defined in the module")]
+    ExportListUnknownName(String),
 }
@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -24,7 +26,10 @@ use once_cell::sync::Lazy;
 use once_cell::sync::OnceCell;
 
 use crate::__derive_refs::components::NativeCallableComponents;
+use crate::collections::Hashed;
 use crate::collections::SmallMap;
+use crate::collections::StarlarkHasher;
+use crate::collections::symbol::frozen_map::FrozenSymbolMap;
 use crate::collections::symbol::map::SymbolMap;
 use crate::docs::DocItem;
 use crate::docs::DocModule;
@@ -57,9 +62,11 @@ type GlobalValue = MaybeDocHiddenValue<'static, FrozenValue>;
 #[derive(Debug, Allocative)]
 struct GlobalsData {
     heap: FrozenHeapRef,
-    variables: SymbolMap<GlobalValue>,
+    variables: FrozenSymbolMap<GlobalValue>,
     variable_names: Vec<FrozenStringValue>,
     docstring: Option<String>,
+    #[allocative(skip)]
+    fingerprint: OnceCell<u64>,
 }
 
 /// Used to build a [`Globals`] value.
@@ -86,8 +93,11 @@ impl Globals {
 
     /// Create a [`Globals`] following the
     /// [Starlark standard](https://github.com/bazelbuild/starlark/blob/master/spec.md#built-in-constants-and-functions).
+    ///
+    /// These are built once per process, and shared by all the callers.
     pub fn standard() -> Self {
-        GlobalsBuilder::standard().build()
+        static STANDARD: GlobalsSnapshot = GlobalsSnapshot::standard();
+        STANDARD.globals().dupe()
     }
 
     /// Create a [`Globals`] combining those functions in the Starlark standard plus
@@ -100,6 +110,25 @@ impl Globals {
         GlobalsBuilder::extended().build()
     }
 
+    /// The same globals without the native functions which are not deterministic,
+    /// including those in namespaces, for an evaluator made hermetic with
+    /// [`Evaluator::set_hermetic`](crate::eval::Evaluator::set_hermetic).
+    ///
+    /// Programs using those functions then fail to compile, rather than when the call
+    /// is reached. Other values, like callable custom values, are still checked when
+    /// they are called.
+    pub fn hermetic(&self) -> Globals {
+        let mut builder = GlobalsBuilder::new();
+        builder.heap.add_reference(self.heap());
+        builder.docstring = self.0.docstring.clone();
+        for (name, value) in self.0.variables.iter() {
+            if let Some(value) = builder.deterministic(value) {
+                builder.variables.insert(name.as_str(), value);
+            }
+        }
+        builder.build()
+    }
+
     /// Empty globals.
     pub(crate) fn empty() -> &'static Globals {
         static EMPTY: Lazy<Globals> = Lazy::new(|| GlobalsBuilder::new().build());
@@ -142,6 +171,25 @@ impl Globals {
         self.0.variables.iter().map(|(n, v)| (n.as_str(), v.value))
     }
 
+    /// A hash of the names and values of the globals.
+    ///
+    /// Globals built the same way have the same fingerprint, even if built separately,
+    /// so it can be used to key caches of evaluation results, like
+    /// [`ModuleCache`](crate::environment::ModuleCache).
+    /// Values are compared by type and `repr`.
+    pub fn fingerprint(&self) -> u64 {
+        *self.0.fingerprint.get_or_init(|| {
+            let mut hasher = StarlarkHasher::new();
+            for name in &self.0.variable_names {
+                let value = self.0.variables.get_str(name.as_str()).unwrap().value;
+                name.as_str().hash(&mut hasher);
+                value.to_value().get_type().hash(&mut hasher);
+                value.to_value().to_repr().hash(&mut hasher);
+            }
+            hasher.finish()
+        })
+    }
+
     pub(crate) fn heap(&self) -> &FrozenHeapRef {
         &self.0.heap
     }
@@ -212,6 +260,8 @@ impl GlobalsBuilder {
 
     /// Add a nested namespace to the builder. If `f` adds the definition `foo`,
     /// it will end up on a namespace `name`, accessible as `name.foo`.
+    ///
+    /// Adding a namespace which already exists adds to its definitions.
     pub fn namespace(&mut self, name: &str, f: impl FnOnce(&mut GlobalsBuilder)) {
         self.namespace_inner(name, false, f)
     }
@@ -227,7 +277,14 @@ impl GlobalsBuilder {
         doc_hidden: bool,
         f: impl FnOnce(&mut GlobalsBuilder),
     ) {
-        self.namespace_fields.push(SmallMap::new());
+        let fields = match self
+            .get_inner(name)
+            .and_then(|x| x.downcast_frozen_ref::<FrozenNamespace>())
+        {
+            Some(existing) => existing.fields().clone(),
+            None => SmallMap::new(),
+        };
+        self.namespace_fields.push(fields);
         f(self);
         let fields = self.namespace_fields.pop().unwrap();
         self.set_inner(
@@ -237,6 +294,30 @@ impl GlobalsBuilder {
         );
     }
 
+    /// The namespace at the dotted `path`, like `ci` or `ci.jobs`, for adding definitions
+    /// accessible as `ci.jobs.foo`. It and its parents are created if needed:
+    ///
+    /// ```
+    /// # use starlark::environment::GlobalsBuilder;
+    /// # fn ci_module(_: &mut GlobalsBuilder) {}
+    /// let mut builder = GlobalsBuilder::standard();
+    /// builder.namespace_at("ci").with(ci_module);
+    /// builder.namespace_at("ci.jobs").set("default_timeout", 60);
+    /// ```
+    pub fn namespace_at(&mut self, path: &str) -> NamespaceBuilder<'_> {
+        NamespaceBuilder {
+            builder: self,
+            path: path.split('.').map(|x| x.to_owned()).collect(),
+        }
+    }
+
+    fn namespace_path(&mut self, path: &[String], f: impl FnOnce(&mut GlobalsBuilder)) {
+        match path {
+            [] => f(self),
+            [name, rest @ ..] => self.namespace(name, |builder| builder.namespace_path(rest, f)),
+        }
+    }
+
     /// A fluent API for modifying [`GlobalsBuilder`] and returning the result.
     pub fn with(mut self, f: impl FnOnce(&mut Self)) -> Self {
         f(&mut self);
@@ -259,18 +340,83 @@ impl GlobalsBuilder {
         variable_names.sort();
         Globals(Arc::new(GlobalsData {
             heap: self.heap.into_ref(),
-            variables: self.variables,
+            variables: self.variables.into(),
             variable_names,
             docstring: self.docstring,
+            fingerprint: OnceCell::new(),
         }))
     }
 
+    /// Remove the definitions, named like `print` or `json.decode`, for which `keep`
+    /// returns `false`. Namespaces are rebuilt without the removed members.
+    pub(crate) fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        let variables = std::mem::replace(&mut self.variables, SymbolMap::new());
+        for (name, value) in variables.into_entries() {
+            if keep(name.as_str()) {
+                let value = self.retain_namespace(name.as_str(), value, &keep);
+                self.variables.insert(name.as_str(), value);
+            }
+        }
+    }
+
+    fn retain_namespace(
+        &self,
+        path: &str,
+        value: GlobalValue,
+        keep: &impl Fn(&str) -> bool,
+    ) -> GlobalValue {
+        let Some(namespace) = value.value.downcast_frozen_ref::<FrozenNamespace>() else {
+            return value;
+        };
+        let mut fields = SmallMap::with_capacity(namespace.fields().len());
+        for (name, field) in namespace.fields() {
+            let path = format!("{path}.{}", name.as_str());
+            if keep(&path) {
+                fields.insert(*name, self.retain_namespace(&path, field.clone(), keep));
+            }
+        }
+        MaybeDocHiddenValue {
+            value: self.heap.alloc(FrozenNamespace::new(fields)),
+            doc_hidden: value.doc_hidden,
+            phantom: Default::default(),
+        }
+    }
+
     /// Set a value in the [`GlobalsBuilder`].
     pub fn set<'v, V: AllocFrozenValue>(&'v mut self, name: &str, value: V) {
         let value = value.alloc_frozen_value(&self.heap);
         self.set_inner(name, value, false)
     }
 
+    /// `value` without the natives which are not deterministic,
+    /// or `None` if it is such a native itself.
+    fn deterministic(&self, value: &GlobalValue) -> Option<GlobalValue> {
+        if let Some(function) = value.value.downcast_frozen_ref::<NativeFunction>() {
+            return function.deterministic.then(|| value.clone());
+        }
+        if let Some(namespace) = value.value.downcast_frozen_ref::<FrozenNamespace>() {
+            let fields = namespace
+                .fields()
+                .iter()
+                .filter_map(|(name, field)| Some((*name, self.deterministic(field)?)))
+                .collect();
+            return Some(MaybeDocHiddenValue {
+                value: self.heap.alloc(FrozenNamespace::new(fields)),
+                doc_hidden: value.doc_hidden,
+                phantom: Default::default(),
+            });
+        }
+        Some(value.clone())
+    }
+
+    /// The value `name` in the innermost namespace being built.
+    fn get_inner(&self, name: &str) -> Option<FrozenValue> {
+        match self.namespace_fields.last() {
+            None => self.variables.get_str(name).map(|x| x.value),
+            Some(fields) => fields.get_hashed(Hashed::new(name)).map(|x| x.value),
+        }
+    }
+
     fn set_inner<'v>(&'v mut self, name: &str, value: FrozenValue, doc_hidden: bool) {
         let value = MaybeDocHiddenValue {
             value,
@@ -296,7 +442,7 @@ impl GlobalsBuilder {
         name: &str,
         components: NativeCallableComponents,
         sig: ParametersSpec<FrozenValue>,
-        as_type: Option<(Ty, DocType)>,
+        as_type: Option<(Ty, fn() -> DocType)>,
         ty: Option<Ty>,
         special_builtin_function: Option<SpecialBuiltinFunction>,
         f: NativeFuncFn,
@@ -307,6 +453,7 @@ impl GlobalsBuilder {
                 function: NativeFunc(f, sig),
                 name: name.to_owned(),
                 speculative_exec_safe: components.speculative_exec_safe,
+                deterministic: components.is_deterministic(),
                 as_type: as_type.as_ref().map(|x| x.0.dupe()),
                 ty: ty.unwrap_or_else(|| {
                     Ty::from_native_callable_components(
@@ -315,7 +462,7 @@ impl GlobalsBuilder {
                     )
                     .unwrap() // TODO(nga): do not unwrap.
                 }),
-                docs: components.into_docs(as_type),
+                docs: components.into_docs(as_type.map(|x| x.1)),
                 special_builtin_function,
             },
         )
@@ -342,6 +489,32 @@ impl GlobalsBuilder {
     }
 }
 
+/// A namespace of a [`GlobalsBuilder`], returned by
+/// [`namespace_at`](GlobalsBuilder::namespace_at).
+pub struct NamespaceBuilder<'a> {
+    builder: &'a mut GlobalsBuilder,
+    path: Vec<String>,
+}
+
+impl<'a> NamespaceBuilder<'a> {
+    /// Add the definitions made by `f` to the namespace.
+    pub fn with(self, f: impl FnOnce(&mut GlobalsBuilder)) -> Self {
+        self.builder.namespace_path(&self.path, f);
+        self
+    }
+
+    /// Set a value in the namespace.
+    pub fn set<V: AllocFrozenValue>(self, name: &str, value: V) -> Self {
+        self.with(|builder| builder.set(name, value))
+    }
+
+    /// The namespace nested in this one at the dotted `path`.
+    pub fn namespace_at(mut self, path: &str) -> Self {
+        self.path.extend(path.split('.').map(|x| x.to_owned()));
+        self
+    }
+}
+
 /// Used to create globals.
 pub struct GlobalsStatic(OnceCell<Globals>);
 
@@ -382,6 +555,62 @@ impl GlobalsStatic {
     }
 }
 
+/// [`Globals`] built on first use, at most once per process, for use in a `static`.
+///
+/// Short-lived processes pay for registering the globals only if they are used,
+/// and long-lived processes only once. The [`documentation`](GlobalsSnapshot::documentation)
+/// is built on first use too, and kept.
+///
+/// ```
+/// use starlark::environment::GlobalsSnapshot;
+/// use starlark::environment::LibraryExtension;
+///
+/// static GLOBALS: GlobalsSnapshot =
+///     GlobalsSnapshot::extended_by(&[LibraryExtension::Json, LibraryExtension::Map]);
+///
+/// assert!(GLOBALS.globals().names().any(|x| x.as_str() == "json"));
+/// ```
+///
+/// Registering the globals is cheap: the documentation of native functions,
+/// which is most of the work, is only built when
+/// [`documentation`](GlobalsSnapshot::documentation) is called.
+///
+/// Globals can't be stored outside the process, for example by a build script,
+/// because they contain pointers to native functions and types.
+pub struct GlobalsSnapshot {
+    extensions: &'static [LibraryExtension],
+    globals: OnceCell<Globals>,
+    documentation: OnceCell<DocModule>,
+}
+
+impl GlobalsSnapshot {
+    /// The [`standard`](Globals::standard) globals.
+    pub const fn standard() -> Self {
+        Self::extended_by(&[])
+    }
+
+    /// The standard globals plus the extensions, like [`Globals::extended_by`].
+    pub const fn extended_by(extensions: &'static [LibraryExtension]) -> Self {
+        GlobalsSnapshot {
+            extensions,
+            globals: OnceCell::new(),
+            documentation: OnceCell::new(),
+        }
+    }
+
+    /// The globals, built on the first call.
+    pub fn globals(&self) -> &Globals {
+        self.globals
+            .get_or_init(|| GlobalsBuilder::extended_by(self.extensions).build())
+    }
+
+    /// The documentation of the globals, built on the first call.
+    pub fn documentation(&self) -> &DocModule {
+        self.documentation
+            .get_or_init(|| self.globals().documentation())
+    }
+}
+
 pub(crate) fn common_documentation<'a, T: IntoIterator<Item = (&'a str, FrozenValue)>>(
     docstring: &Option<String>,
     members: T,
@@ -406,6 +635,7 @@ mod tests {
 
     use super::*;
     use crate as starlark;
+    use crate::assert::Assert;
 
     #[test]
     fn test_send_sync()
@@ -421,6 +651,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_globals_perfect_hash() {
+        assert!(Globals::standard().0.variables.is_perfect());
+        assert!(Globals::extended_internal().0.variables.is_perfect());
+    }
+
+    #[test]
+    fn test_globals_snapshot() {
+        static SNAPSHOT: GlobalsSnapshot = GlobalsSnapshot::extended_by(&[LibraryExtension::Json]);
+        let globals = SNAPSHOT.globals();
+        assert!(globals.get("json").is_some());
+        assert!(Arc::ptr_eq(&globals.0, &SNAPSHOT.globals().0));
+        assert!(SNAPSHOT.documentation().members.contains_key("json"));
+        assert!(Arc::ptr_eq(&Globals::standard().0, &Globals::standard().0));
+    }
+
     #[test]
     fn test_doc_hidden() {
         let mut globals = GlobalsBuilder::new();
@@ -438,4 +684,41 @@ mod tests {
         };
         assert_eq!(&docs.members.into_keys().exactly_one().ok().unwrap(), "x");
     }
+
+    #[starlark_module]
+    fn ci_members(builder: &mut GlobalsBuilder) {
+        fn trigger() -> anyhow::Result<i32> {
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_namespace_at() {
+        let mut a = Assert::new();
+        a.globals_add(|builder| {
+            builder.namespace_at("ci").with(ci_members);
+            builder
+                .namespace_at("ci.jobs")
+                .set("timeout", 60)
+                .namespace_at("linux")
+                .set("arch", "x86_64");
+            // Adds to the existing namespace.
+            builder.namespace("ci", |builder| builder.set("name", "ci"));
+        });
+        a.eq("1", "ci.trigger()");
+        a.eq("60", "ci.jobs.timeout");
+        a.eq("'x86_64'", "ci.jobs.linux.arch");
+        a.eq("'ci'", "ci.name");
+
+        let mut globals = GlobalsBuilder::new();
+        globals.namespace_at("ci.jobs").with(ci_members);
+        let docs = globals.build().documentation();
+        let Some(DocItem::Module(ci)) = docs.members.get("ci") else {
+            unreachable!()
+        };
+        let Some(DocItem::Module(jobs)) = ci.members.get("jobs") else {
+            unreachable!()
+        };
+        assert!(jobs.members.contains_key("trigger"));
+    }
 }
@@ -20,6 +20,7 @@ use once_cell::sync::OnceCell;
 use starlark_map::Hashed;
 
 use crate::__derive_refs::components::NativeCallableComponents;
+use crate::collections::symbol::frozen_map::FrozenSymbolMap;
 use crate::collections::symbol::map::SymbolMap;
 use crate::collections::symbol::symbol::Symbol;
 use crate::docs::DocType;
@@ -44,7 +45,7 @@ pub struct Methods {
     /// This field holds the objects referenced in `members`.
     #[allow(dead_code)]
     heap: FrozenHeapRef,
-    members: SymbolMap<UnboundValue>,
+    members: FrozenSymbolMap<UnboundValue>,
     docstring: Option<String>,
 }
 
@@ -144,7 +145,7 @@ impl MethodsBuilder {
     pub fn build(self) -> Methods {
         Methods {
             heap: self.heap.into_ref(),
-            members: self.members,
+            members: self.members.into(),
             docstring: self.docstring,
         }
     }
@@ -224,6 +225,7 @@ impl MethodsBuilder {
                 function: NativeMeth(f, sig),
                 name: name.to_owned(),
                 speculative_exec_safe: components.speculative_exec_safe,
+                deterministic: components.is_deterministic(),
                 docs: components.into_docs(None),
                 ty,
             })),
@@ -0,0 +1,347 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+
+use crate::collections::StarlarkHasher;
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::syntax::Dialect;
+
+/// Key of a [`ModuleCache`] entry: hashes of the source, dialect and globals
+/// a module was evaluated with.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash)]
+pub struct ModuleCacheKey {
+    /// Hash of the module source code.
+    pub source: u64,
+    /// Hash of the dialect the module was parsed with.
+    pub dialect: u64,
+    /// [`Globals::fingerprint`] of the globals the module was evaluated with.
+    pub globals: u64,
+}
+
+impl ModuleCacheKey {
+    /// Compute the key for a module.
+    pub fn new(source: &str, dialect: &Dialect, globals: &Globals) -> ModuleCacheKey {
+        fn hash(x: impl Hash) -> u64 {
+            let mut hasher = StarlarkHasher::new();
+            x.hash(&mut hasher);
+            hasher.finish()
+        }
+        ModuleCacheKey {
+            source: hash(source),
+            dialect: hash(dialect),
+            globals: globals.fingerprint(),
+        }
+    }
+}
+
+struct ModuleCacheEntry {
+    /// Full source, to not return a wrong module on hash collision.
+    source: String,
+    module: FrozenModule,
+    /// Value of `ModuleCacheInner::tick` when the entry was last used.
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct ModuleCacheInner {
+    entries: HashMap<ModuleCacheKey, ModuleCacheEntry>,
+    tick: u64,
+}
+
+/// Cache of frozen modules, for reuse of the same module across evaluations.
+///
+/// Modules are identified by their source, the [`Dialect`] and the [`Globals`]
+/// they were evaluated with (see [`ModuleCacheKey`]), not by file name.
+/// The cache can be shared between threads.
+///
+/// The key does not include the modules loaded with `load()`, so modules which load
+/// other modules should only be cached if their dependencies don't change,
+/// or the cache should be cleared when they do.
+///
+/// ```
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::environment::ModuleCache;
+/// use starlark::eval::Evaluator;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+///
+/// let cache = ModuleCache::new().with_capacity(100);
+/// let globals = Globals::standard();
+/// let source = "x = 1 + 2";
+/// for _ in 0..3 {
+///     let module = cache
+///         .get_or_try_insert_with(source, &Dialect::Standard, &globals, || {
+///             let ast =
+///                 AstModule::parse("prelude.star", source.to_owned(), &Dialect::Standard)?;
+///             Module::with_temp_heap(|module| {
+///                 Evaluator::new(&module).eval_module(ast, &globals)?;
+///                 module.freeze().map_err(starlark::Error::from)
+///             })
+///         })
+///         .unwrap();
+///     assert_eq!(3, module.get("x").unwrap().unpack_i32().unwrap());
+/// }
+/// assert_eq!(1, cache.len());
+/// ```
+pub struct ModuleCache {
+    inner: Mutex<ModuleCacheInner>,
+    capacity: Option<usize>,
+    on_evict: Option<Box<dyn Fn(&ModuleCacheKey, &FrozenModule) + Send + Sync>>,
+}
+
+impl Default for ModuleCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleCache {
+    /// Create an empty cache, of unlimited capacity.
+    pub fn new() -> Self {
+        ModuleCache {
+            inner: Mutex::new(ModuleCacheInner::default()),
+            capacity: None,
+            on_evict: None,
+        }
+    }
+
+    /// Keep at most `capacity` modules, evicting the least recently used ones.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Call `on_evict` for every module removed from the cache,
+    /// either to respect the capacity, or by [`remove`](ModuleCache::remove),
+    /// [`retain`](ModuleCache::retain) or [`clear`](ModuleCache::clear).
+    pub fn with_on_evict(
+        mut self,
+        on_evict: impl Fn(&ModuleCacheKey, &FrozenModule) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    fn evicted(&self, evicted: Vec<(ModuleCacheKey, ModuleCacheEntry)>) {
+        // Called without holding the lock, so the callback can use the cache.
+        if let Some(on_evict) = &self.on_evict {
+            for (key, entry) in &evicted {
+                on_evict(key, &entry.module);
+            }
+        }
+    }
+
+    /// Get a module evaluated from this source, dialect and globals, if it is cached.
+    pub fn get(&self, source: &str, dialect: &Dialect, globals: &Globals) -> Option<FrozenModule> {
+        let key = ModuleCacheKey::new(source, dialect, globals);
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let entry = inner.entries.get_mut(&key)?;
+        if entry.source != source {
+            return None;
+        }
+        entry.last_used = tick;
+        Some(entry.module.dupe())
+    }
+
+    /// Store a module evaluated from this source, dialect and globals,
+    /// replacing any module previously stored for them.
+    pub fn insert(&self, source: &str, dialect: &Dialect, globals: &Globals, module: FrozenModule) {
+        let key = ModuleCacheKey::new(source, dialect, globals);
+        let mut evicted = Vec::new();
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let entry = ModuleCacheEntry {
+                source: source.to_owned(),
+                module,
+                last_used: inner.tick,
+            };
+            if let Some(old) = inner.entries.insert(key, entry) {
+                evicted.push((key, old));
+            }
+            if let Some(capacity) = self.capacity {
+                while inner.entries.len() > capacity {
+                    let oldest = *inner
+                        .entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.last_used)
+                        .unwrap()
+                        .0;
+                    let entry = inner.entries.remove(&oldest).unwrap();
+                    evicted.push((oldest, entry));
+                }
+            }
+        }
+        self.evicted(evicted);
+    }
+
+    /// Get the cached module, or create it with `f` and cache it.
+    ///
+    /// The lock is not held while `f` runs, so concurrent callers may both evaluate
+    /// the module, and the last one is cached. Errors are not cached.
+    pub fn get_or_try_insert_with<E>(
+        &self,
+        source: &str,
+        dialect: &Dialect,
+        globals: &Globals,
+        f: impl FnOnce() -> Result<FrozenModule, E>,
+    ) -> Result<FrozenModule, E> {
+        if let Some(module) = self.get(source, dialect, globals) {
+            return Ok(module);
+        }
+        let module = f()?;
+        self.insert(source, dialect, globals, module.dupe());
+        Ok(module)
+    }
+
+    /// Remove the module stored for the key, returning it.
+    pub fn remove(&self, key: &ModuleCacheKey) -> Option<FrozenModule> {
+        let entry = self.inner.lock().unwrap().entries.remove(key)?;
+        let module = entry.module.dupe();
+        self.evicted(vec![(*key, entry)]);
+        Some(module)
+    }
+
+    /// Remove the modules for which `f` returns `false`.
+    pub fn retain(&self, mut f: impl FnMut(&ModuleCacheKey, &FrozenModule) -> bool) {
+        let evicted = {
+            let mut inner = self.inner.lock().unwrap();
+            let remove: Vec<ModuleCacheKey> = inner
+                .entries
+                .iter()
+                .filter(|(key, entry)| !f(key, &entry.module))
+                .map(|(key, _)| *key)
+                .collect();
+            remove
+                .into_iter()
+                .map(|key| {
+                    let entry = inner.entries.remove(&key).unwrap();
+                    (key, entry)
+                })
+                .collect()
+        };
+        self.evicted(evicted);
+    }
+
+    /// Remove all the modules.
+    pub fn clear(&self) {
+        let evicted = self.inner.lock().unwrap().entries.drain().collect();
+        self.evicted(evicted);
+    }
+
+    /// Number of cached modules.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether no modules are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+
+    fn eval(source: &str, globals: &Globals) -> crate::Result<FrozenModule> {
+        let ast = AstModule::parse("m.star", source.to_owned(), &Dialect::Standard)?;
+        Module::with_temp_heap(|module| {
+            Evaluator::new(&module).eval_module(ast, globals)?;
+            Ok(module.freeze()?)
+        })
+    }
+
+    #[test]
+    fn test_module_cache_reuses_modules() {
+        let cache = ModuleCache::new();
+        let evals = AtomicUsize::new(0);
+        let get = |source: &str, globals: &Globals| {
+            cache
+                .get_or_try_insert_with(source, &Dialect::Standard, globals, || {
+                    evals.fetch_add(1, Ordering::SeqCst);
+                    eval(source, globals)
+                })
+                .unwrap()
+        };
+        let x = get("x = [1]", &Globals::standard());
+        let y = get("x = [1]", &Globals::standard());
+        assert!(
+            x.get("x")
+                .unwrap()
+                .value()
+                .ptr_eq(y.get("x").unwrap().value())
+        );
+        assert_eq!(1, evals.load(Ordering::SeqCst));
+
+        get("x = [2]", &Globals::standard());
+        get("x = [1]", &Globals::extended_internal());
+        assert_eq!(3, evals.load(Ordering::SeqCst));
+        assert!(
+            cache
+                .get("x = [1]", &Dialect::Extended, &Globals::standard())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_module_cache_evicts() {
+        let evicted = Arc::new(AtomicUsize::new(0));
+        let cache = ModuleCache::new().with_capacity(2).with_on_evict({
+            let evicted = evicted.dupe();
+            move |_, _| {
+                evicted.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let globals = Globals::standard();
+        for source in ["x = 1", "x = 2", "x = 3"] {
+            cache.insert(
+                source,
+                &Dialect::Standard,
+                &globals,
+                eval(source, &globals).unwrap(),
+            );
+            // Keep the first module recently used.
+            cache.get("x = 1", &Dialect::Standard, &globals);
+        }
+        assert_eq!(2, cache.len());
+        assert_eq!(1, evicted.load(Ordering::SeqCst));
+        assert!(cache.get("x = 1", &Dialect::Standard, &globals).is_some());
+        assert!(cache.get("x = 2", &Dialect::Standard, &globals).is_none());
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(3, evicted.load(Ordering::SeqCst));
+    }
+}
@@ -44,6 +44,28 @@ impl FrozenModule {
         }
         w
     }
+
+    /// Disassemble the bytecode of the functions defined in this module,
+    /// including the span of each statement and the constants used by each instruction.
+    ///
+    /// The output is meant for humans investigating what code compiles to,
+    /// and the format is not stable.
+    pub fn dump_bytecode(&self) -> String {
+        let mut w = String::new();
+        for (name, value) in self.all_items() {
+            if let Some(def) = FrozenValueTyped::<FrozenDef>::new(value) {
+                if !w.is_empty() {
+                    writeln!(w).unwrap();
+                }
+                writeln!(w, "def {}:", name.as_str()).unwrap();
+                def.bc()
+                    .dump_debug()
+                    .lines()
+                    .for_each(|line| writeln!(w, "  {line}").unwrap());
+            }
+        }
+        w
+    }
 }
 
 impl FrozenHeapRef {
@@ -54,3 +76,26 @@ impl FrozenHeapRef {
         w
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_dump_bytecode() {
+        let module = Assert::new().pass_module(
+            r#"
+def f(x):
+    return x + 1
+
+y = 1
+"#,
+        );
+        let dump = module.dump_bytecode();
+        assert!(dump.starts_with("def f:\n"), "{dump}");
+        assert!(dump.contains("Max stack size:"), "{dump}");
+        // Adding a small int constant has its own instruction.
+        assert!(dump.contains("AddInt &x "), "{dump}");
+        assert!(!dump.contains("def y:"), "{dump}");
+    }
+}
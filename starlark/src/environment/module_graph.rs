@@ -0,0 +1,662 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+
+use dupe::Dupe;
+
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::environment::ModuleCacheKey;
+use crate::eval::Evaluator;
+use crate::eval::FileLoader;
+use crate::eval::ReturnFileLoader;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Debug, thiserror::Error)]
+enum ModuleGraphError {
+    #[error("Cycle in `load()` statements: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("Module `{0}` failed to load: {1}")]
+    LoadFailed(String, String),
+}
+
+struct ModuleGraphNode {
+    key: ModuleCacheKey,
+    /// Full source, to not reuse a wrong module on hash collision.
+    source: String,
+    /// Paths of the modules loaded by this one, with their versions when this one was evaluated.
+    loads: Vec<(String, u64)>,
+    module: FrozenModule,
+    /// Changed each time the module is evaluated, so modules loading it know to evaluate again.
+    version: u64,
+}
+
+#[derive(Default)]
+struct ModuleGraphInner {
+    nodes: HashMap<String, ModuleGraphNode>,
+    next_version: u64,
+}
+
+/// Modules visited by one call to [`ModuleGraphLoader::load`].
+#[derive(Default)]
+struct ModuleGraphVisit {
+    /// Modules already brought up to date, with their versions.
+    done: HashMap<String, (FrozenModule, u64)>,
+    /// Modules being brought up to date, to detect cycles.
+    stack: Vec<String>,
+}
+
+/// The error for a load of `path` which closes a cycle of modules being loaded, if it does.
+fn cycle(stack: &[String], path: &str) -> Option<crate::Error> {
+    let start = stack.iter().position(|x| x == path)?;
+    let mut cycle = stack[start..].to_vec();
+    cycle.push(path.to_owned());
+    Some(crate::Error::new_other(ModuleGraphError::Cycle(cycle)))
+}
+
+/// A module read, but not yet evaluated.
+struct ModuleGraphRead {
+    key: ModuleCacheKey,
+    source: String,
+    /// Parsed module, if it was parsed to find its loads.
+    ast: Option<AstModule>,
+    /// Paths of the modules it loads.
+    loads: Vec<String>,
+}
+
+/// A module in the graph of [`ModuleGraphLoader::load_parallel`].
+struct ParallelNode {
+    path: String,
+    /// Indices of the modules it loads, in the order of the `load()` statements.
+    loads: Vec<usize>,
+}
+
+#[derive(Default)]
+struct ParallelGraph {
+    nodes: Vec<ParallelNode>,
+    /// The module read for each node, or why it could not be, taken when it is evaluated.
+    reads: Vec<Option<crate::Result<ModuleGraphRead>>>,
+    index: HashMap<String, usize>,
+}
+
+struct ParallelState {
+    /// Modules whose loads are all done.
+    ready: Vec<usize>,
+    /// Number of loads not done yet, for each module.
+    pending: Vec<usize>,
+    results: Vec<Option<crate::Result<(FrozenModule, u64)>>>,
+    reads: Vec<Option<crate::Result<ModuleGraphRead>>>,
+    /// Number of modules not done yet.
+    remaining: usize,
+    panicked: bool,
+}
+
+/// Stop the other workers if a worker panics, so the panic can propagate.
+struct ParallelPanicGuard<'a> {
+    state: &'a Mutex<ParallelState>,
+    changed: &'a Condvar,
+}
+
+impl Drop for ParallelPanicGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .panicked = true;
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// A [`FileLoader`] which reads, evaluates and caches modules and the modules they load,
+/// evaluating again only what changed.
+///
+/// Sources are read with the function given to [`new`](ModuleGraphLoader::new), each time a
+/// module is loaded, and modules are evaluated again when their source changed, or when a
+/// module they load, directly or not, was evaluated again. So a change to one file only
+/// costs evaluating that file and the files which depend on it. Cycles of `load()` statements
+/// are reported as errors listing the cycle.
+///
+/// [`load_parallel`](ModuleGraphLoader::load_parallel) loads many modules at once,
+/// evaluating modules which don't depend on each other on several threads.
+///
+/// The paths of `load()` statements are given to the reader as written, so it should resolve
+/// them if they are relative.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+///
+/// use starlark::environment::Globals;
+/// use starlark::environment::ModuleGraphLoader;
+/// use starlark::syntax::Dialect;
+///
+/// let files = Mutex::new(HashMap::from([
+///     ("a.star", "x = 1"),
+///     ("b.star", "load('a.star', 'x')\ny = x + 1"),
+/// ]));
+/// let loader = ModuleGraphLoader::new(Globals::standard(), Dialect::Standard, move |path| {
+///     Ok(files.lock().unwrap()[path].to_owned())
+/// });
+/// let b = loader.load("b.star").unwrap();
+/// assert_eq!(2, b.get("y").unwrap().unpack_i32().unwrap());
+/// assert_eq!(2, loader.len());
+/// ```
+pub struct ModuleGraphLoader {
+    globals: Globals,
+    dialect: Dialect,
+    read: Box<dyn Fn(&str) -> crate::Result<String> + Send + Sync>,
+    inner: Mutex<ModuleGraphInner>,
+}
+
+impl ModuleGraphLoader {
+    /// Create a loader evaluating modules with `globals` and `dialect`, reading their sources,
+    /// given their path, with `read`.
+    pub fn new(
+        globals: Globals,
+        dialect: Dialect,
+        read: impl Fn(&str) -> crate::Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        ModuleGraphLoader {
+            globals,
+            dialect,
+            read: Box::new(read),
+            inner: Mutex::new(ModuleGraphInner::default()),
+        }
+    }
+
+    /// Get the module for `path`, evaluating it and the modules it loads if they are not cached
+    /// or changed since they were cached.
+    ///
+    /// The lock is not held while modules are evaluated, so concurrent callers may both
+    /// evaluate a module, and the last one is cached. Errors are not cached.
+    pub fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        let (module, _) = self.visit(path, &mut ModuleGraphVisit::default())?;
+        Ok(module)
+    }
+
+    fn visit(
+        &self,
+        path: &str,
+        visit: &mut ModuleGraphVisit,
+    ) -> crate::Result<(FrozenModule, u64)> {
+        if let Some((module, version)) = visit.done.get(path) {
+            return Ok((module.dupe(), *version));
+        }
+        if let Some(cycle) = cycle(&visit.stack, path) {
+            return Err(cycle);
+        }
+
+        let read = self.read_module(path)?;
+        // After an error the visit is abandoned, so the stack needn't be popped.
+        visit.stack.push(path.to_owned());
+        let mut loads = Vec::with_capacity(read.loads.len());
+        for x in &read.loads {
+            let (module, version) = self.visit(x, visit)?;
+            loads.push((x.clone(), module, version));
+        }
+        visit.stack.pop();
+
+        let res = self.reuse_or_eval(path, read, loads)?;
+        visit.done.insert(path.to_owned(), (res.0.dupe(), res.1));
+        Ok(res)
+    }
+
+    /// Read the source of a module and find what it loads.
+    fn read_module(&self, path: &str) -> crate::Result<ModuleGraphRead> {
+        let source = (self.read)(path)?;
+        let key = ModuleCacheKey::new(&source, &self.dialect, &self.globals);
+        let cached_loads: Option<Vec<String>> = {
+            let inner = self.inner.lock().unwrap();
+            inner
+                .nodes
+                .get(path)
+                .filter(|node| node.key == key && node.source == source)
+                .map(|node| node.loads.iter().map(|(x, _)| x.clone()).collect())
+        };
+        // If the source did not change, the loads are known without parsing.
+        let (ast, loads) = match cached_loads {
+            Some(loads) => (None, loads),
+            None => {
+                let ast = AstModule::parse(path, source.clone(), &self.dialect)?;
+                let loads = ast.loads().iter().map(|x| x.module_id.to_owned()).collect();
+                (Some(ast), loads)
+            }
+        };
+        Ok(ModuleGraphRead {
+            key,
+            source,
+            ast,
+            loads,
+        })
+    }
+
+    /// Reuse the cached module if neither its source nor the modules it loads changed,
+    /// otherwise evaluate it and cache it.
+    fn reuse_or_eval(
+        &self,
+        path: &str,
+        read: ModuleGraphRead,
+        loads: Vec<(String, FrozenModule, u64)>,
+    ) -> crate::Result<(FrozenModule, u64)> {
+        let ModuleGraphRead {
+            key, source, ast, ..
+        } = read;
+        let versions: Vec<(String, u64)> = loads.iter().map(|(x, _, v)| (x.clone(), *v)).collect();
+        let reused = {
+            let inner = self.inner.lock().unwrap();
+            inner
+                .nodes
+                .get(path)
+                .filter(|node| node.key == key && node.source == source && node.loads == versions)
+                .map(|node| (node.module.dupe(), node.version))
+        };
+        if let Some(res) = reused {
+            return Ok(res);
+        }
+
+        let ast = match ast {
+            Some(ast) => ast,
+            None => AstModule::parse(path, source.clone(), &self.dialect)?,
+        };
+        let modules: HashMap<&str, &FrozenModule> =
+            loads.iter().map(|(x, m, _)| (x.as_str(), m)).collect();
+        let module = self.eval(ast, &ReturnFileLoader { modules: &modules })?;
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_version += 1;
+        let version = inner.next_version;
+        inner.nodes.insert(
+            path.to_owned(),
+            ModuleGraphNode {
+                key,
+                source,
+                loads: versions,
+                module: module.dupe(),
+                version,
+            },
+        );
+        Ok((module, version))
+    }
+
+    /// Load the modules for `paths`, like [`load`](ModuleGraphLoader::load), evaluating
+    /// modules which don't load each other concurrently, on `threads` threads.
+    /// Returns the results in the order of `paths`.
+    ///
+    /// All the sources are read, and load cycles found, before any module is evaluated.
+    /// The results don't depend on the order the modules happen to be evaluated in:
+    /// a module for which a load failed fails with the error of the first failed load,
+    /// in the order of its `load()` statements.
+    pub fn load_parallel(
+        &self,
+        paths: &[&str],
+        threads: usize,
+    ) -> Vec<crate::Result<FrozenModule>> {
+        let mut graph = ParallelGraph::default();
+        let roots: Vec<usize> = paths
+            .iter()
+            .map(|path| self.discover(path, &mut graph, &mut Vec::new()))
+            .collect();
+        let ParallelGraph { nodes, reads, .. } = graph;
+        let mut dependents = vec![Vec::new(); nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for &j in &node.loads {
+                dependents[j].push(i);
+            }
+        }
+
+        let state = Mutex::new(ParallelState {
+            ready: (0..nodes.len())
+                .filter(|i| nodes[*i].loads.is_empty())
+                .collect(),
+            pending: nodes.iter().map(|x| x.loads.len()).collect(),
+            results: (0..nodes.len()).map(|_| None).collect(),
+            reads,
+            remaining: nodes.len(),
+            panicked: false,
+        });
+        let changed = Condvar::new();
+        let worker = || {
+            let _guard = ParallelPanicGuard {
+                state: &state,
+                changed: &changed,
+            };
+            loop {
+                let (i, read, loads) = {
+                    let mut state = state.lock().unwrap();
+                    let i = loop {
+                        if state.panicked || state.remaining == 0 {
+                            return;
+                        }
+                        if let Some(i) = state.ready.pop() {
+                            break i;
+                        }
+                        state = changed.wait(state).unwrap();
+                    };
+                    let read = state.reads[i].take().unwrap();
+                    let mut loads = Vec::with_capacity(nodes[i].loads.len());
+                    let mut failed = None;
+                    for &j in &nodes[i].loads {
+                        match &state.results[j] {
+                            Some(Ok((module, version))) => {
+                                loads.push((nodes[j].path.clone(), module.dupe(), *version))
+                            }
+                            Some(Err(e)) => {
+                                failed = Some(ModuleGraphError::LoadFailed(
+                                    nodes[j].path.clone(),
+                                    e.to_string(),
+                                ));
+                                break;
+                            }
+                            None => {
+                                unreachable!("modules are only ready when their loads are done")
+                            }
+                        }
+                    }
+                    let loads = match failed {
+                        None => Ok(loads),
+                        Some(e) => Err(crate::Error::new_other(e)),
+                    };
+                    (i, read, loads)
+                };
+                let res = read.and_then(|read| {
+                    loads.and_then(|loads| self.reuse_or_eval(&nodes[i].path, read, loads))
+                });
+                let mut state = state.lock().unwrap();
+                state.results[i] = Some(res);
+                state.remaining -= 1;
+                for &d in &dependents[i] {
+                    state.pending[d] -= 1;
+                    if state.pending[d] == 0 {
+                        state.ready.push(d);
+                    }
+                }
+                changed.notify_all();
+            }
+        };
+        thread::scope(|scope| {
+            for _ in 0..cmp::max(1, cmp::min(threads, nodes.len())) {
+                scope.spawn(worker);
+            }
+        });
+
+        let mut results = state.into_inner().unwrap().results;
+        roots
+            .into_iter()
+            .map(|i| match results[i].take().unwrap() {
+                Ok((module, version)) => {
+                    results[i] = Some(Ok((module.dupe(), version)));
+                    Ok(module)
+                }
+                Err(e) => {
+                    // For when the same path is given again.
+                    results[i] = Some(Err(crate::Error::new_other(ModuleGraphError::LoadFailed(
+                        nodes[i].path.clone(),
+                        e.to_string(),
+                    ))));
+                    Err(e)
+                }
+            })
+            .collect()
+    }
+
+    /// Read `path` and the modules it loads into `graph`, returning its index.
+    fn discover(&self, path: &str, graph: &mut ParallelGraph, stack: &mut Vec<String>) -> usize {
+        if let Some(i) = graph.index.get(path) {
+            return *i;
+        }
+        let i = graph.nodes.len();
+        graph.index.insert(path.to_owned(), i);
+        graph.nodes.push(ParallelNode {
+            path: path.to_owned(),
+            loads: Vec::new(),
+        });
+        graph.reads.push(None);
+
+        let mut read = self.read_module(path);
+        let mut loads = Vec::new();
+        if let Ok(r) = &read {
+            stack.push(path.to_owned());
+            for x in &r.loads {
+                if let Some(e) = cycle(stack, x) {
+                    read = Err(e);
+                    loads.clear();
+                    break;
+                }
+                loads.push(self.discover(x, graph, stack));
+            }
+            stack.pop();
+        }
+        graph.nodes[i].loads = loads;
+        graph.reads[i] = Some(read);
+        i
+    }
+
+    fn eval(&self, ast: AstModule, loader: &dyn FileLoader) -> crate::Result<FrozenModule> {
+        Module::with_temp_heap(|module| {
+            {
+                let mut eval = Evaluator::new(&module);
+                eval.set_loader(loader);
+                eval.eval_module(ast, &self.globals)?;
+            }
+            Ok(module.freeze()?)
+        })
+    }
+
+    /// The paths of the modules loaded by the cached module for `path`.
+    pub fn loads(&self, path: &str) -> Option<Vec<String>> {
+        let inner = self.inner.lock().unwrap();
+        let node = inner.nodes.get(path)?;
+        Some(node.loads.iter().map(|(x, _)| x.clone()).collect())
+    }
+
+    /// Forget the module for `path`, so it is evaluated again when next loaded, along with the
+    /// modules which load it. Returns whether it was cached.
+    pub fn invalidate(&self, path: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.nodes.remove(path).is_none() {
+            return false;
+        }
+        let mut todo = vec![path.to_owned()];
+        while let Some(path) = todo.pop() {
+            let loaders: Vec<String> = inner
+                .nodes
+                .iter()
+                .filter(|(_, node)| node.loads.iter().any(|(x, _)| *x == path))
+                .map(|(x, _)| x.clone())
+                .collect();
+            for x in loaders {
+                inner.nodes.remove(&x);
+                todo.push(x);
+            }
+        }
+        true
+    }
+
+    /// Forget all the modules.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().nodes.clear();
+    }
+
+    /// Number of cached modules.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().nodes.len()
+    }
+
+    /// Whether no modules are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FileLoader for ModuleGraphLoader {
+    fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        ModuleGraphLoader::load(self, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn loader(files: &Arc<Mutex<HashMap<&'static str, &'static str>>>) -> ModuleGraphLoader {
+        let files = files.dupe();
+        ModuleGraphLoader::new(
+            Globals::standard(),
+            Dialect::Standard,
+            move |path| match files.lock().unwrap().get(path) {
+                Some(x) => Ok((*x).to_owned()),
+                None => Err(crate::Error::new_other(anyhow::anyhow!("No file `{path}`"))),
+            },
+        )
+    }
+
+    fn get(module: &FrozenModule, name: &str) -> i32 {
+        module.get(name).unwrap().unpack_i32().unwrap()
+    }
+
+    #[test]
+    fn test_module_graph_reevaluates_changed_subtree() {
+        let files = Arc::new(Mutex::new(HashMap::from([
+            ("a.star", "x = 1"),
+            ("b.star", "load('a.star', 'x')\ny = x * 10"),
+            ("c.star", "z = [1]"),
+            (
+                "d.star",
+                "load('b.star', 'y')\nload('c.star', 'z')\nw = y + len(z)",
+            ),
+        ])));
+        let loader = loader(&files);
+        assert_eq!(11, get(&loader.load("d.star").unwrap(), "w"));
+        assert_eq!(4, loader.len());
+        assert_eq!(
+            Some(vec!["b.star".to_owned(), "c.star".to_owned()]),
+            loader.loads("d.star")
+        );
+        let c = loader.load("c.star").unwrap();
+
+        files.lock().unwrap().insert("a.star", "x = 2");
+        assert_eq!(21, get(&loader.load("d.star").unwrap(), "w"));
+        // `c.star` did not change, so was not evaluated again.
+        assert!(
+            c.get("z")
+                .unwrap()
+                .value()
+                .ptr_eq(loader.load("c.star").unwrap().get("z").unwrap().value())
+        );
+
+        assert!(loader.invalidate("c.star"));
+        assert!(!loader.invalidate("c.star"));
+        // `d.star` loads `c.star`, so was forgotten too.
+        assert!(!loader.invalidate("d.star"));
+        assert_eq!(2, loader.len());
+        assert_eq!(21, get(&loader.load("d.star").unwrap(), "w"));
+        assert!(
+            !c.get("z")
+                .unwrap()
+                .value()
+                .ptr_eq(loader.load("c.star").unwrap().get("z").unwrap().value())
+        );
+    }
+
+    #[test]
+    fn test_module_graph_cycle() {
+        let files = Arc::new(Mutex::new(HashMap::from([
+            ("a.star", "load('b.star', 'y')\nx = 1"),
+            ("b.star", "load('c.star', 'z')\ny = 1"),
+            ("c.star", "load('a.star', 'x')\nz = 1"),
+        ])));
+        let err = loader(&files).load("a.star").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("a.star -> b.star -> c.star -> a.star"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_module_graph_load_parallel() {
+        let mut files = HashMap::from([
+            ("common.star", "x = 1"),
+            ("bad1.star", "fail('one')"),
+            ("bad2.star", "fail('two')"),
+            (
+                "top.star",
+                "load('bad2.star', 'a')\nload('bad1.star', 'b')\nc = 1",
+            ),
+        ]);
+        let leaves = [
+            "leaf0.star",
+            "leaf1.star",
+            "leaf2.star",
+            "leaf3.star",
+            "leaf4.star",
+            "leaf5.star",
+            "leaf6.star",
+            "leaf7.star",
+        ];
+        for leaf in leaves {
+            files.insert(leaf, "load('common.star', 'x')\ny = x + 1");
+        }
+        let files = Arc::new(Mutex::new(files));
+        let loader = loader(&files);
+
+        let mut paths = leaves.to_vec();
+        paths.push("top.star");
+        paths.push("missing.star");
+        let results = loader.load_parallel(&paths, 4);
+        assert_eq!(paths.len(), results.len());
+        for res in &results[..leaves.len()] {
+            assert_eq!(2, get(res.as_ref().unwrap(), "y"));
+        }
+        // The first failed load is reported, whichever failed first.
+        let err = results[leaves.len()].as_ref().unwrap_err().to_string();
+        assert!(err.contains("bad2.star") && err.contains("two"), "{err}");
+        assert!(results[leaves.len() + 1].is_err());
+
+        // Modules loaded in parallel are cached like the others.
+        let leaf0 = results[0].as_ref().unwrap();
+        assert!(
+            leaf0
+                .get("y")
+                .unwrap()
+                .value()
+                .ptr_eq(loader.load("leaf0.star").unwrap().get("y").unwrap().value())
+        );
+    }
+
+    #[test]
+    fn test_module_graph_load_parallel_cycle() {
+        let files = Arc::new(Mutex::new(HashMap::from([
+            ("a.star", "load('b.star', 'y')\nx = 1"),
+            ("b.star", "load('a.star', 'x')\ny = 1"),
+        ])));
+        let results = loader(&files).load_parallel(&["a.star", "b.star"], 2);
+        let err = results[0].as_ref().unwrap_err().to_string();
+        assert!(err.contains("a.star -> b.star -> a.star"), "{err}");
+        assert!(results[1].is_err());
+    }
+}
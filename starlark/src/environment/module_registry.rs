@@ -0,0 +1,331 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+
+use crate::environment::FrozenModule;
+use crate::eval::FileLoader;
+
+#[derive(Debug, thiserror::Error)]
+enum ModuleRegistryError {
+    #[error("Module `{0}` is not in the registry")]
+    NotFound(String),
+}
+
+struct ModuleRegistryEntry {
+    module: FrozenModule,
+    /// Paths of the modules loaded by this one.
+    loads: Vec<String>,
+}
+
+#[derive(Default)]
+struct ModuleRegistryInner {
+    entries: HashMap<String, ModuleRegistryEntry>,
+    generation: u64,
+}
+
+impl ModuleRegistryInner {
+    /// The modules which load `path`, directly or not, ordered so that every module
+    /// comes after the modules it loads.
+    fn dependents(&self, path: &str) -> Vec<String> {
+        let mut found = HashSet::new();
+        let mut todo = vec![path];
+        while let Some(path) = todo.pop() {
+            for (name, entry) in &self.entries {
+                if entry.loads.iter().any(|x| x == path) && found.insert(name.as_str()) {
+                    todo.push(name);
+                }
+            }
+        }
+
+        let mut pending: Vec<&str> = found.iter().copied().collect();
+        pending.sort();
+        let mut res: Vec<String> = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = |name: &&str| {
+                self.entries[*name]
+                    .loads
+                    .iter()
+                    .all(|x| !found.contains(x.as_str()) || res.iter().any(|r| r == x))
+            };
+            // Load cycles fail at evaluation, so can't be in the registry, but don't loop if they are.
+            let next = pending.iter().position(ready).unwrap_or(0);
+            res.push(pending.remove(next).to_owned());
+        }
+        res
+    }
+}
+
+/// Frozen modules by the path they are loaded by, along with the load graph between them,
+/// so modules can be replaced while evaluators keep running.
+///
+/// The registry is a [`FileLoader`], so evaluations can load from it directly.
+/// [`replace`](ModuleRegistry::replace) swaps a module and removes the modules which
+/// load it, directly or not, in a single step, so no evaluation can observe a mix of
+/// the old and new versions. [`reload`](ModuleRegistry::reload) also re-evaluates them.
+///
+/// ```
+/// use starlark::environment::FrozenModule;
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::environment::ModuleRegistry;
+/// use starlark::eval::Evaluator;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+///
+/// let sources = std::cell::RefCell::new(std::collections::HashMap::from([
+///     ("a.star", "x = 1".to_owned()),
+///     ("b.star", "load('a.star', 'x')\ny = x + 1".to_owned()),
+/// ]));
+/// let eval = |path: &str, registry: &ModuleRegistry| -> starlark::Result<(FrozenModule, Vec<String>)> {
+///     let source = sources.borrow()[path].clone();
+///     let ast = AstModule::parse(path, source, &Dialect::Standard)?;
+///     let loads: Vec<String> = ast.loads().iter().map(|x| x.module_id.to_owned()).collect();
+///     let module = Module::with_temp_heap(|module| {
+///         {
+///             let mut eval = Evaluator::new(&module);
+///             eval.set_loader(registry);
+///             eval.eval_module(ast, &Globals::standard())?;
+///         }
+///         module.freeze().map_err(starlark::Error::from)
+///     })?;
+///     Ok((module, loads))
+/// };
+///
+/// let registry = ModuleRegistry::new();
+/// for path in ["a.star", "b.star"] {
+///     let (module, loads) = eval(path, &registry).unwrap();
+///     registry.insert(path, module, loads);
+/// }
+///
+/// // `a.star` was edited.
+/// sources.borrow_mut().insert("a.star", "x = 10".to_owned());
+/// let (module, loads) = eval("a.star", &registry).unwrap();
+/// assert_eq!(vec!["b.star"], registry.reload("a.star", module, loads, eval).unwrap());
+/// let b = registry.get("b.star").unwrap();
+/// assert_eq!(11, b.get("y").unwrap().unpack_i32().unwrap());
+/// ```
+pub struct ModuleRegistry {
+    inner: Mutex<ModuleRegistryInner>,
+}
+
+impl Default for ModuleRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        ModuleRegistry {
+            inner: Mutex::new(ModuleRegistryInner::default()),
+        }
+    }
+
+    /// The module registered for `path`.
+    pub fn get(&self, path: &str) -> Option<FrozenModule> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.get(path).map(|x| x.module.dupe())
+    }
+
+    /// Register the module for `path`, which was evaluated loading the modules `loads`,
+    /// usually those given by [`AstModule::loads`](crate::syntax::AstModule::loads).
+    ///
+    /// Unlike [`replace`](ModuleRegistry::replace), the modules which load `path` are kept.
+    pub fn insert(&self, path: &str, module: FrozenModule, loads: Vec<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        inner
+            .entries
+            .insert(path.to_owned(), ModuleRegistryEntry { module, loads });
+    }
+
+    /// The paths of the registered modules which load `path`, directly or not,
+    /// ordered so that every module comes after the modules it loads.
+    pub fn dependents(&self, path: &str) -> Vec<String> {
+        self.inner.lock().unwrap().dependents(path)
+    }
+
+    /// Replace the module for `path`, and remove the modules which load it, directly or
+    /// not, since they refer to the old one. Returns the paths of the removed modules,
+    /// in the order they should be evaluated again.
+    pub fn replace(&self, path: &str, module: FrozenModule, loads: Vec<String>) -> Vec<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let dependents = inner.dependents(path);
+        for x in &dependents {
+            inner.entries.remove(x);
+        }
+        inner.generation += 1;
+        inner
+            .entries
+            .insert(path.to_owned(), ModuleRegistryEntry { module, loads });
+        dependents
+    }
+
+    /// [`Replace`](ModuleRegistry::replace) the module for `path`, then evaluate again
+    /// the modules which loaded it, with `eval`, which returns the new module and what it
+    /// loads. Returns the paths of the evaluated modules.
+    ///
+    /// The lock is not held while `eval` runs, so it can load from the registry.
+    /// If `eval` fails, the modules which were not evaluated again stay removed.
+    pub fn reload(
+        &self,
+        path: &str,
+        module: FrozenModule,
+        loads: Vec<String>,
+        mut eval: impl FnMut(&str, &ModuleRegistry) -> crate::Result<(FrozenModule, Vec<String>)>,
+    ) -> crate::Result<Vec<String>> {
+        let dependents = self.replace(path, module, loads);
+        for x in &dependents {
+            let (module, loads) = eval(x, self)?;
+            self.insert(x, module, loads);
+        }
+        Ok(dependents)
+    }
+
+    /// Remove the module for `path`, and the modules which load it, directly or not.
+    /// Returns the paths of those other modules.
+    pub fn remove(&self, path: &str) -> Vec<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let dependents = inner.dependents(path);
+        for x in &dependents {
+            inner.entries.remove(x);
+        }
+        inner.entries.remove(path);
+        inner.generation += 1;
+        dependents
+    }
+
+    /// A counter incremented by every change to the registry, so long-running evaluators
+    /// can cheaply check whether anything was replaced.
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Number of registered modules.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether no modules are registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FileLoader for ModuleRegistry {
+    fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        self.get(path)
+            .ok_or_else(|| crate::Error::new_other(ModuleRegistryError::NotFound(path.to_owned())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval(
+        sources: &HashMap<&str, &str>,
+        path: &str,
+        registry: &ModuleRegistry,
+    ) -> crate::Result<(FrozenModule, Vec<String>)> {
+        let ast = AstModule::parse(path, sources[path].to_owned(), &Dialect::Standard)?;
+        let loads = ast.loads().iter().map(|x| x.module_id.to_owned()).collect();
+        let module = Module::with_temp_heap(|module| {
+            {
+                let mut eval = Evaluator::new(&module);
+                eval.set_loader(registry);
+                eval.eval_module(ast, &Globals::standard())?;
+            }
+            Ok::<_, crate::Error>(module.freeze()?)
+        })?;
+        Ok((module, loads))
+    }
+
+    fn get(registry: &ModuleRegistry, path: &str, name: &str) -> i32 {
+        let module = registry.get(path).unwrap();
+        module.get(name).unwrap().unpack_i32().unwrap()
+    }
+
+    #[test]
+    fn test_registry_reload() {
+        let mut sources = HashMap::from([
+            (
+                "c.star",
+                "load('b.star', 'y')\nload('a.star', 'x')\nz = x + y",
+            ),
+            ("b.star", "load('a.star', 'x')\ny = x * 10"),
+            ("a.star", "x = 1"),
+            ("d.star", "w = 5"),
+        ]);
+        let registry = ModuleRegistry::new();
+        for path in ["a.star", "b.star", "c.star", "d.star"] {
+            let (module, loads) = eval(&sources, path, &registry).unwrap();
+            registry.insert(path, module, loads);
+        }
+        assert_eq!(11, get(&registry, "c.star", "z"));
+        assert_eq!(vec!["b.star", "c.star"], registry.dependents("a.star"));
+        let d = registry.get("d.star").unwrap();
+
+        sources.insert("a.star", "x = 2");
+        let generation = registry.generation();
+        let (module, loads) = eval(&sources, "a.star", &registry).unwrap();
+        let reloaded = registry
+            .reload("a.star", module, loads, |path, registry| {
+                eval(&sources, path, registry)
+            })
+            .unwrap();
+        assert_eq!(vec!["b.star", "c.star"], reloaded);
+        assert!(registry.generation() > generation);
+        assert_eq!(22, get(&registry, "c.star", "z"));
+        // Unrelated modules are not evaluated again.
+        assert!(
+            d.get("w")
+                .unwrap()
+                .value()
+                .ptr_eq(registry.get("d.star").unwrap().get("w").unwrap().value())
+        );
+    }
+
+    #[test]
+    fn test_registry_replace_removes_dependents() {
+        let sources = HashMap::from([("a.star", "x = 1"), ("b.star", "load('a.star', 'x')")]);
+        let registry = ModuleRegistry::new();
+        for path in ["a.star", "b.star"] {
+            let (module, loads) = eval(&sources, path, &registry).unwrap();
+            registry.insert(path, module, loads);
+        }
+        let (module, loads) = eval(&sources, "a.star", &registry).unwrap();
+        assert_eq!(vec!["b.star"], registry.replace("a.star", module, loads));
+        assert!(registry.get("b.star").is_none());
+        assert_eq!(1, registry.len());
+        assert!(registry.load("b.star").is_err());
+
+        assert!(registry.remove("a.star").is_empty());
+        assert!(registry.is_empty());
+    }
+}
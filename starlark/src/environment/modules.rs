@@ -23,14 +23,16 @@
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::time::Duration;
-use std::time::Instant;
 
 use allocative::Allocative;
+use derivative::Derivative;
 use dupe::Dupe;
 use itertools::Itertools;
 use starlark_syntax::syntax::ast::Visibility;
 
+use crate::codemap::FileSpan;
 use crate::collections::Hashed;
+use crate::collections::SmallMap;
 use crate::docs::DocModule;
 use crate::docs::DocString;
 use crate::docs::DocStringKind;
@@ -43,8 +45,12 @@ use crate::environment::slots::ModuleSlotId;
 use crate::environment::slots::MutableSlots;
 use crate::errors::did_you_mean::did_you_mean;
 use crate::eval::ProfileData;
+use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
+use crate::typing::Ty;
+use crate::util::instant::Instant;
 use crate::values::Freeze;
+use crate::values::FreezeError;
 use crate::values::FreezeResult;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
@@ -56,16 +62,23 @@ use crate::values::Heap;
 use crate::values::OwnedFrozenValue;
 use crate::values::Trace;
 use crate::values::Tracer;
+use crate::values::UnpackValue;
 use crate::values::Value;
 use crate::values::layout::heap::heap_type::FrozenHeapName;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
 use crate::values::layout::heap::profile::aggregated::RetainedHeapProfile;
+use crate::values::layout::heap::profile::analysis::HeapAnalysis;
+use crate::values::layout::heap::profile::analysis::HeapReferences;
+use crate::values::layout::heap::profile::analysis::HeapReferencesBuilder;
+use crate::values::list_or_tuple::UnpackListOrTuple;
 
 #[derive(Debug, thiserror::Error)]
 enum ModuleError {
     #[error("Retained memory profiling is not enabled")]
     RetainedMemoryProfileNotEnabled,
+    #[error("Heap analysis is not enabled, see `Module::enable_heap_analysis`")]
+    HeapAnalysisNotEnabled,
     #[error("Extra value already set to a value of type `{}`", .0)]
     ExtraValueAlreadySet(&'static str),
 }
@@ -99,6 +112,29 @@ pub(crate) struct FrozenModuleData {
     docstring: Option<String>,
     /// When heap profile enabled, this field stores retained memory info.
     heap_profile: Option<RetainedHeapProfile>,
+    /// When heap analysis enabled, the references between the values of the heap.
+    heap_references: Option<HeapReferences>,
+    /// Whether the exports were restricted by `__all__`.
+    has_export_list: bool,
+    /// Where each symbol was first defined.
+    #[allocative(skip)]
+    definition_spans: SmallMap<FrozenStringValue, FrozenFileSpan>,
+}
+
+/// A symbol exported by a [`FrozenModule`], returned by [`exports`](FrozenModule::exports).
+#[derive(Debug, Clone)]
+pub struct ModuleExport {
+    /// The name the symbol is loaded by.
+    pub name: String,
+    /// The value of the symbol.
+    pub value: OwnedFrozenValue,
+    /// The type of the value, as seen by the typechecker.
+    pub ty: Ty,
+    /// The docstring of the value, for example of a `def`.
+    pub docs: Option<DocString>,
+    /// Where the symbol was first defined, `None` if it was not defined by Starlark code,
+    /// for example with [`Module::set`].
+    pub span: Option<FileSpan>,
 }
 
 /// A container for user values, used during execution.
@@ -107,7 +143,8 @@ pub(crate) struct FrozenModuleData {
 /// You can get references to these heaps with [`frozen_heap`](Module::frozen_heap) and
 /// [`heap`](Module::heap). Be careful not to use these values after the [`Module`] has been
 /// released unless you obtain a reference to the frozen heap.
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct Module<'v> {
     heap: Heap<'v>,
     frozen_heap: FrozenHeap,
@@ -128,8 +165,22 @@ pub struct Module<'v> {
     extra_value: Cell<Option<Value<'v>>>,
     /// When `Some`, heap profile is collected on freeze.
     heap_profile_on_freeze: Cell<Option<RetainedHeapProfileMode>>,
+    /// Whether to record the references between values on freeze, see `enable_heap_analysis`.
+    heap_analysis_on_freeze: Cell<bool>,
+    /// Number of threads to freeze with, see `set_freeze_threads`.
+    #[cfg(feature = "parallel_freeze")]
+    freeze_threads: Cell<usize>,
+    /// Whether the exports were restricted by `__all__`, see `apply_export_list`.
+    has_export_list: Cell<bool>,
+    /// Where each symbol was first defined by Starlark code.
+    definition_spans: RefCell<SmallMap<FrozenStringValue, FrozenFileSpan>>,
+    /// Callbacks run after freezing, see `after_freeze`.
+    #[derivative(Debug = "ignore")]
+    after_freeze: RefCell<Vec<AfterFreezeHook>>,
 }
 
+type AfterFreezeHook = Box<dyn FnOnce(&FrozenModule) -> crate::Result<()>>;
+
 impl FrozenModule {
     /// Convert items in `globals` into a `FrozenModule`.
     /// This function can be used to implement starlark module
@@ -212,6 +263,56 @@ impl FrozenModule {
         self.module.names()
     }
 
+    /// The exported symbols, with their types, docstrings and definitions.
+    pub fn exports(&self) -> Vec<ModuleExport> {
+        self.module
+            .names
+            .symbols()
+            .filter_map(|(name, slot)| {
+                let value = self.module.slots.get_slot(slot)?;
+                Some(ModuleExport {
+                    name: name.as_str().to_owned(),
+                    ty: Ty::of_value(value.to_value()),
+                    docs: value.to_value().documentation().get_doc_string().cloned(),
+                    span: self
+                        .module
+                        .definition_spans
+                        .get(&name)
+                        .map(|x| x.to_file_span()),
+                    // This code is safe because we know the frozen module ref keeps the values alive
+                    value: unsafe { OwnedFrozenValue::new(self.heap.dupe(), value) },
+                })
+            })
+            .collect()
+    }
+
+    /// A view of this module which exports only `symbols`, for example to expose just the
+    /// public API of a library to `load()` by other libraries. The values are shared.
+    ///
+    /// Fails if any of `symbols` is not exported by this module.
+    pub fn restrict(&self, symbols: &[&str]) -> crate::Result<FrozenModule> {
+        for symbol in symbols {
+            self.get(symbol).map_err(crate::Error::new_other)?;
+        }
+        let heap = FrozenHeap::new();
+        heap.add_reference(&self.heap);
+        let module = heap.alloc_any(FrozenModuleData {
+            names: self.module.names.restrict(symbols),
+            slots: self.module.slots.clone(),
+            docstring: self.module.docstring.clone(),
+            heap_profile: None,
+            heap_references: None,
+            has_export_list: false,
+            definition_spans: self.module.definition_spans.clone(),
+        });
+        Ok(FrozenModule {
+            heap: heap.into_ref(),
+            module,
+            extra_value: self.extra_value,
+            eval_duration: self.eval_duration,
+        })
+    }
+
     /// Obtain the [`FrozenHeapRef`] which owns the storage of all values defined in this module.
     pub fn frozen_heap(&self) -> &FrozenHeapRef {
         &self.heap
@@ -254,6 +355,20 @@ impl FrozenModule {
         }
     }
 
+    /// The memory retained by each value of this module, or error if not enabled with
+    /// [`Module::enable_heap_analysis`].
+    pub fn heap_analysis(&self) -> anyhow::Result<HeapAnalysis> {
+        match &self.module.heap_references {
+            None => Err(ModuleError::HeapAnalysisNotEnabled.into()),
+            Some(references) => Ok(HeapAnalysis::new(
+                &self.heap,
+                references,
+                self.all_items()
+                    .map(|(name, value)| (name.as_str().to_owned(), value)),
+            )),
+        }
+    }
+
     /// `extra_value` field from `Module`, frozen.
     pub fn extra_value(&self) -> Option<FrozenValue> {
         self.extra_value
@@ -356,6 +471,12 @@ impl<'v> Module<'v> {
             eval_duration: Cell::new(Duration::ZERO),
             extra_value: Cell::new(None),
             heap_profile_on_freeze: Cell::new(None),
+            heap_analysis_on_freeze: Cell::new(false),
+            #[cfg(feature = "parallel_freeze")]
+            freeze_threads: Cell::new(1),
+            has_export_list: Cell::new(false),
+            definition_spans: RefCell::new(SmallMap::new()),
+            after_freeze: RefCell::new(Vec::new()),
         }
     }
 
@@ -363,6 +484,26 @@ impl<'v> Module<'v> {
         self.heap_profile_on_freeze.set(Some(mode));
     }
 
+    /// Record the references between values when freezing, which makes freezing slower,
+    /// to compute the memory each value keeps alive with
+    /// [`FrozenModule::heap_analysis`]. Freezing then always happens on the calling thread.
+    pub fn enable_heap_analysis(&self) {
+        self.heap_analysis_on_freeze.set(true);
+    }
+
+    /// Freeze this module on `threads` threads, which helps for modules with large heaps.
+    ///
+    /// Each thread freezes some of the top-level variables, and values shared between them
+    /// are frozen once, by whichever thread gets there first. The frozen values are spread
+    /// over a heap per thread, all kept alive by the module. Freezing still happens on the
+    /// calling thread if `threads` is 0 or 1, which is the default.
+    ///
+    /// Requires the `parallel_freeze` feature.
+    #[cfg(feature = "parallel_freeze")]
+    pub fn set_freeze_threads(&self, threads: usize) {
+        self.freeze_threads.set(threads);
+    }
+
     /// Get the heap on which values are allocated by this module.
     pub fn heap(&self) -> Heap<'v> {
         self.heap
@@ -436,6 +577,12 @@ impl<'v> Module<'v> {
         self.freeze_impl(Some(name))
     }
 
+    /// Discard the module, keeping only its frozen heap,
+    /// which values allocated there may still reference.
+    pub(crate) fn into_frozen_heap(self) -> FrozenHeapRef {
+        self.frozen_heap.into_ref()
+    }
+
     fn freeze_impl(self, name: Option<FrozenHeapName>) -> FreezeResult<FrozenModule> {
         let Module {
             names,
@@ -446,19 +593,44 @@ impl<'v> Module<'v> {
             eval_duration,
             extra_value,
             heap_profile_on_freeze,
+            heap_analysis_on_freeze,
+            #[cfg(feature = "parallel_freeze")]
+            freeze_threads,
+            has_export_list,
+            definition_spans,
+            after_freeze,
         } = self;
         let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::info_span!("starlark::freeze", allocated_bytes = heap.allocated_bytes())
+                .entered();
         // This is when we do the GC/freeze, using the module slots as roots
         // Note that we even freeze anonymous slots, since they are accessed by
         // slot-index in the code, and we don't walk into them, so don't know if
         // they are used.
-        let freezer = Freezer::new(&frozen_heap);
+        let mut freezer = Freezer::new(&frozen_heap);
+        if heap_analysis_on_freeze.get() {
+            freezer.references = Some(RefCell::new(HeapReferencesBuilder::default()));
+        }
         // FIXME(JakobDegen): Fix the `Freezer` API to make it impossible to forget this
         for r in heap.referenced_heaps() {
             frozen_heap.add_reference(&r);
         }
+        #[cfg(not(feature = "parallel_freeze"))]
         let slots = slots.freeze(&freezer)?;
+        #[cfg(feature = "parallel_freeze")]
+        let slots = match freeze_threads.get() {
+            // References are only recorded when freezing on one thread.
+            _ if freezer.references.is_some() => slots.freeze(&freezer)?,
+            0 | 1 => slots.freeze(&freezer)?,
+            threads => slots.freeze_parallel(&freezer, threads)?,
+        };
         let extra_value = extra_value.into_inner().freeze(&freezer)?;
+        let heap_references = freezer
+            .references
+            .as_ref()
+            .map(|references| references.borrow_mut().finish());
         let stacks = if let Some(mode) = heap_profile_on_freeze.get() {
             // TODO(nga): retained heap profile does not store information about data
             //   allocated in frozen heap before freeze starts.
@@ -475,18 +647,36 @@ impl<'v> Module<'v> {
             slots,
             docstring: docstring.into_inner(),
             heap_profile: stacks,
+            heap_references,
+            has_export_list: has_export_list.get(),
+            definition_spans: definition_spans.into_inner(),
         };
         let frozen_module_ref = freezer.heap.alloc_any(rest);
         for frozen_def in freezer.frozen_defs.borrow().as_slice() {
-            frozen_def.post_freeze(frozen_module_ref, heap, &freezer.heap);
+            frozen_def.post_freeze(frozen_module_ref);
         }
 
-        Ok(FrozenModule {
+        let frozen = FrozenModule {
             heap: frozen_heap.into_ref_impl(name),
             module: frozen_module_ref,
             extra_value,
             eval_duration: start.elapsed() + eval_duration.get(),
-        })
+        };
+        for hook in after_freeze.into_inner() {
+            hook(&frozen).map_err(|e| FreezeError::new(e.to_string()))?;
+        }
+        Ok(frozen)
+    }
+
+    /// Run `f` on the frozen module once [`freeze`](Module::freeze) succeeds, for example
+    /// to check invariants spanning several values, or to register the module with the host.
+    /// Callbacks run in the order they were added, and an error from one is returned by
+    /// `freeze`.
+    ///
+    /// See [`StarlarkValue::on_freeze`](crate::values::StarlarkValue::on_freeze) for a hook
+    /// on individual values.
+    pub fn after_freeze(&self, f: impl FnOnce(&FrozenModule) -> crate::Result<()> + 'static) {
+        self.after_freeze.borrow_mut().push(Box::new(f));
     }
 
     /// Set the value of a variable in the environment.
@@ -516,6 +706,39 @@ impl<'v> Module<'v> {
         slots.set_slot(slot, value);
     }
 
+    pub(crate) fn add_definition_span(&self, name: FrozenStringValue, span: FrozenFileSpan) {
+        self.definition_spans
+            .borrow_mut()
+            .entry(name)
+            .or_insert(span);
+    }
+
+    /// Make the exported symbols exactly those listed in `__all__`, if it is defined.
+    ///
+    /// Listed symbols imported with `load()` are exported even without
+    /// [`enable_load_reexport`](crate::syntax::Dialect::enable_load_reexport),
+    /// but symbols starting with `_` remain private.
+    pub(crate) fn apply_export_list(&self) -> crate::Result<()> {
+        let Some((exports, _)) = self.get_any_visibility(Hashed::new("__all__")) else {
+            return Ok(());
+        };
+        let Some(exports) = UnpackListOrTuple::<&str>::unpack_value_opt(exports) else {
+            return Err(crate::Error::new_other(
+                EnvironmentError::ExportListNotStrings(exports.get_type().to_owned()),
+            ));
+        };
+        for name in &exports.items {
+            if self.get_any_visibility(Hashed::new(name)).is_none() {
+                return Err(crate::Error::new_other(
+                    EnvironmentError::ExportListUnknownName((*name).to_owned()),
+                ));
+            }
+        }
+        self.names.restrict_exports(&exports.items);
+        self.has_export_list.set(true);
+        Ok(())
+    }
+
     /// Import symbols from a module, similar to what is done during `load()`.
     pub fn import_public_symbols(&self, module: &FrozenModule) {
         self.frozen_heap.add_reference(&module.heap);
@@ -540,6 +763,11 @@ impl<'v> Module<'v> {
         }
         match module.get_any_visibility(symbol)? {
             (v, Visibility::Public) => Ok(self.heap().access_owned_frozen_value(&v)),
+            (_, Visibility::Private) if module.module.has_export_list => {
+                Err(crate::Error::new_other(
+                    EnvironmentError::ModuleSymbolIsNotInExportList(symbol.to_owned()),
+                ))
+            }
             (_, Visibility::Private) => Err(crate::Error::new_other(
                 EnvironmentError::ModuleSymbolIsNotExported(symbol.to_owned()),
             )),
@@ -550,7 +778,6 @@ impl<'v> Module<'v> {
         self.docstring.replace(Some(docstring));
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn add_eval_duration(&self, duration: Duration) {
         self.eval_duration.set(self.eval_duration.get() + duration);
     }
@@ -597,17 +824,29 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use allocative::Allocative;
+    use derive_more::Display;
+    use starlark_derive::NoSerialize;
     use starlark_derive::starlark_module;
+    use starlark_derive::starlark_value;
 
     use crate as starlark;
+    use crate::any::ProvidesStaticType;
+    use crate::assert::Assert;
     use crate::environment::FrozenModule;
     use crate::environment::Globals;
     use crate::environment::GlobalsBuilder;
     use crate::environment::Module;
     use crate::eval::Evaluator;
     use crate::eval::runtime::profile::mode::ProfileMode;
+    use crate::starlark_simple_value;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
+    use crate::values::StarlarkValue;
+    use crate::values::Value;
     use crate::values::list::ListRef;
 
     #[test]
@@ -667,4 +906,165 @@ x = f(1)
                 .len()
         );
     }
+
+    #[cfg(feature = "parallel_freeze")]
+    #[test]
+    fn test_freeze_parallel() {
+        let module = Module::with_temp_heap(|module| {
+            module.set_freeze_threads(4);
+            {
+                let mut eval = Evaluator::new(&module);
+                eval.eval_module(
+                    AstModule::parse(
+                        "x.star",
+                        r"
+shared = {'k': [1, 2, 3]}
+cycle = []
+cycle.append(cycle)
+tables = [[{'i': i, 'shared': shared, 's': 'str' + str(i)} for i in range(100)] for _ in range(20)]
+def f(x):
+    return x + len(tables)
+"
+                        .to_owned(),
+                        &Dialect::AllOptionsInternal,
+                    )
+                    .unwrap(),
+                    &Globals::standard(),
+                )
+                .unwrap();
+            }
+            module.freeze()
+        })
+        .unwrap();
+
+        let mut a = Assert::new();
+        a.module_add("x", module);
+        a.pass(
+            r"
+load('x', 'cycle', 'f', 'shared', 'tables')
+assert_eq(shared['k'], [1, 2, 3])
+assert_eq(cycle[0][0], cycle[0])
+assert_eq(tables[7][42]['s'], 'str42')
+assert_eq(tables[0][0]['shared'], shared)
+assert_eq(f(1), 21)
+",
+        );
+    }
+
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display("unfreezable")]
+    struct Unfreezable;
+
+    starlark_simple_value!(Unfreezable);
+
+    #[starlark_value(type = "unfreezable")]
+    impl<'v> StarlarkValue<'v> for Unfreezable {
+        fn on_freeze(&self) -> crate::Result<()> {
+            Err(crate::Error::new_other(anyhow::anyhow!(
+                "unfreezable values must not escape"
+            )))
+        }
+    }
+
+    #[starlark_module]
+    fn unfreezable_globals(globals: &mut GlobalsBuilder) {
+        fn unfreezable() -> anyhow::Result<Unfreezable> {
+            Ok(Unfreezable)
+        }
+    }
+
+    #[test]
+    fn test_value_on_freeze() {
+        let globals = GlobalsBuilder::standard().with(unfreezable_globals).build();
+        let res = Module::with_temp_heap(|module| {
+            {
+                let mut eval = Evaluator::new(&module);
+                let ast = AstModule::parse(
+                    "x.star",
+                    "x = [unfreezable()]".to_owned(),
+                    &Dialect::Standard,
+                )
+                .unwrap();
+                eval.eval_module(ast, &globals).unwrap();
+            }
+            module.freeze()
+        });
+        let err = res.unwrap_err();
+        assert!(
+            err.err_msg.contains("unfreezable values must not escape"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_after_freeze() {
+        let ran = Rc::new(Cell::new(false));
+        let res = Module::with_temp_heap(|module| {
+            module.set("x", Value::testing_new_int(1));
+            let ran = ran.clone();
+            module.after_freeze(move |frozen| {
+                assert_eq!(Some(1), frozen.get("x").unwrap().value().unpack_i32());
+                ran.set(true);
+                Ok(())
+            });
+            module.after_freeze(|_| Err(crate::Error::new_other(anyhow::anyhow!("rejected"))));
+            module.freeze()
+        });
+        assert!(ran.get());
+        assert!(res.unwrap_err().err_msg.contains("rejected"));
+    }
+
+    #[test]
+    fn test_exports_and_restrict() {
+        let module = Module::with_temp_heap(|module| {
+            module.set("host", Value::testing_new_int(0));
+            {
+                let mut eval = Evaluator::new(&module);
+                let ast = AstModule::parse(
+                    "lib.star",
+                    r#"
+def f(x):
+    """Double x."""
+    return x * 2
+
+x = 1
+_p = 2
+"#
+                    .to_owned(),
+                    &Dialect::Standard,
+                )
+                .unwrap();
+                eval.eval_module(ast, &Globals::standard()).unwrap();
+            }
+            module.freeze()
+        })
+        .unwrap();
+
+        let exports = module.exports();
+        let names: Vec<&str> = exports.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(vec!["host", "f", "x"], names);
+        assert_eq!(None, exports[0].span);
+        assert_eq!(
+            "lib.star:2:5-6",
+            exports[1].span.as_ref().unwrap().to_string()
+        );
+        assert_eq!(
+            Some("Double x."),
+            exports[1].docs.as_ref().map(|d| d.summary.as_str())
+        );
+        assert_eq!("int", exports[2].ty.to_string());
+
+        let view = module.restrict(&["f"]).unwrap();
+        assert_eq!(
+            vec!["f"],
+            view.exports().iter().map(|x| &x.name).collect::<Vec<_>>()
+        );
+        let mut a = Assert::new();
+        a.module_add("lib", view);
+        a.pass("load('lib', 'f')\nassert_eq(f(2), 4)");
+        a.fail("load('lib', 'x')", "Module symbol `x` is not exported");
+
+        assert!(module.restrict(&["_p"]).is_err());
+        assert!(module.restrict(&["missing"]).is_err());
+    }
 }
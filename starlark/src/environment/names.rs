@@ -22,6 +22,8 @@ use starlark_syntax::syntax::ast::Visibility;
 
 use crate::collections::Hashed;
 use crate::collections::SmallMap;
+use crate::collections::perfect_hash::PerfectHashIndex;
+use crate::environment::Module;
 use crate::environment::slots::ModuleSlotId;
 use crate::values::FrozenStringValue;
 
@@ -45,8 +47,13 @@ use crate::values::FrozenStringValue;
 #[derive(Debug)]
 pub(crate) struct MutableNames(RefCell<SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>>);
 
+/// Names of a frozen module, with an index built at freeze time so that
+/// looking up a module export never has to probe.
 #[derive(Debug, Allocative)]
-pub(crate) struct FrozenNames(SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>);
+pub(crate) struct FrozenNames {
+    names: SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>,
+    index: PerfectHashIndex,
+}
 
 impl MutableNames {
     pub(crate) fn new() -> Self {
@@ -100,6 +107,17 @@ impl MutableNames {
         self.add_name_visibility(name, Visibility::Public)
     }
 
+    /// Make public exactly the names in `exports`, except those which are private by default.
+    pub(crate) fn restrict_exports(&self, exports: &[&str]) {
+        for (name, (_slot, vis)) in self.0.borrow_mut().iter_mut() {
+            *vis = if exports.contains(&name.as_str()) {
+                Module::default_visibility(name)
+            } else {
+                Visibility::Private
+            };
+        }
+    }
+
     pub(crate) fn hide_name(&self, name: &str) {
         self.0.borrow_mut().shift_remove(name);
     }
@@ -131,27 +149,60 @@ impl MutableNames {
     }
 
     pub(crate) fn freeze(self) -> FrozenNames {
-        FrozenNames(self.0.into_inner())
+        FrozenNames::new(self.0.into_inner())
     }
 }
 
 impl FrozenNames {
+    fn new(names: SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>) -> FrozenNames {
+        let hashes: Vec<u64> = names
+            .iter_hashed()
+            .map(|(name, _)| name.hash().promote())
+            .collect();
+        FrozenNames {
+            index: PerfectHashIndex::new(&hashes),
+            names,
+        }
+    }
+
+    /// The same names, with only those in `exports` public.
+    pub(crate) fn restrict(&self, exports: &[&str]) -> FrozenNames {
+        let names = self
+            .names
+            .iter()
+            .map(|(name, (slot, vis))| {
+                let vis = match vis {
+                    Visibility::Public if exports.contains(&name.as_str()) => Visibility::Public,
+                    _ => Visibility::Private,
+                };
+                (*name, (*slot, vis))
+            })
+            .collect();
+        FrozenNames::new(names)
+    }
+
     pub(crate) fn get_name(&self, name: &str) -> Option<(ModuleSlotId, Visibility)> {
-        self.0.get(name).copied()
+        let hash = Hashed::new(name).hash().promote();
+        let i = self.index.find(hash, |i| {
+            self.names.get_index(i).unwrap().0.as_str() == name
+        })?;
+        Some(*self.names.get_index(i).unwrap().1)
     }
 
     /// Symbols including private.
     pub(crate) fn all_symbols(
         &self,
     ) -> impl Iterator<Item = (FrozenStringValue, ModuleSlotId)> + '_ {
-        self.0.iter().map(|(name, (slot, _vis))| (*name, *slot))
+        self.names.iter().map(|(name, (slot, _vis))| (*name, *slot))
     }
 
     /// Exported symbols.
     pub(crate) fn symbols(&self) -> impl Iterator<Item = (FrozenStringValue, ModuleSlotId)> + '_ {
-        self.0.iter().filter_map(|(name, (slot, vis))| match vis {
-            Visibility::Private => None,
-            Visibility::Public => Some((*name, *slot)),
-        })
+        self.names
+            .iter()
+            .filter_map(|(name, (slot, vis))| match vis {
+                Visibility::Private => None,
+                Visibility::Public => Some((*name, *slot)),
+            })
     }
 }
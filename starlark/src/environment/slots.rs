@@ -26,6 +26,8 @@ use crate::values::FreezeResult;
 use crate::values::Freezer;
 use crate::values::FrozenValue;
 use crate::values::Value;
+#[cfg(feature = "parallel_freeze")]
+use crate::values::layout::freezer::parallel::freeze_parallel;
 
 #[derive(Clone, Copy, Dupe, Debug, PartialEq, Eq, Allocative, Hash)]
 pub(crate) struct ModuleSlotId(pub(crate) u32);
@@ -41,7 +43,7 @@ impl ModuleSlotId {
 pub(crate) struct MutableSlots<'v>(RefCell<Vec<Option<Value<'v>>>>);
 
 // Indexed slots of a module. May contain unassigned values as `None`.
-#[derive(Debug, Allocative)]
+#[derive(Debug, Clone, Allocative)]
 pub(crate) struct FrozenSlots(Vec<Option<FrozenValue>>);
 
 impl<'v> MutableSlots<'v> {
@@ -91,6 +93,17 @@ impl<'v> MutableSlots<'v> {
         let slots = self.0.into_inner().freeze(freezer)?;
         Ok(FrozenSlots(slots))
     }
+
+    /// Like `freeze`, but spreading the slots over `threads` threads.
+    #[cfg(feature = "parallel_freeze")]
+    pub(crate) fn freeze_parallel(
+        self,
+        freezer: &Freezer,
+        threads: usize,
+    ) -> FreezeResult<FrozenSlots> {
+        let slots = freeze_parallel(freezer, &self.0.into_inner(), threads)?;
+        Ok(FrozenSlots(slots))
+    }
 }
 
 impl FrozenSlots {
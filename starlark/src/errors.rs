@@ -18,9 +18,15 @@
 //! Error types used by Starlark.
 
 pub use starlark_syntax::frame::Frame;
+pub use starlark_syntax::message_catalog::MessageCatalog;
+pub use starlark_syntax::span_display::DiagnosticPaths;
+pub use starlark_syntax::span_display::DiagnosticRenderOptions;
 
 pub use crate::analysis::EvalMessage;
 pub use crate::analysis::EvalSeverity;
 pub use crate::analysis::Lint;
+pub use crate::errors::diagnostic::Diagnostic;
+pub use crate::errors::diagnostic::DiagnosticLabel;
 
+mod diagnostic;
 pub(crate) mod did_you_mean;
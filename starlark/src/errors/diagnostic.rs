@@ -0,0 +1,261 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Structured diagnostics, for hosts which render errors themselves.
+
+use std::fmt;
+
+use dupe::Dupe;
+
+use crate::ErrorKind;
+use crate::analysis::EvalSeverity;
+use crate::analysis::Lint;
+use crate::codemap::CodeMap;
+use crate::codemap::Span;
+
+/// A part of the source a [`Diagnostic`] points at.
+#[derive(Debug, Clone)]
+pub struct DiagnosticLabel {
+    /// The byte range in [`Diagnostic::source`].
+    pub span: Span,
+    /// Text to show next to the span, if any.
+    pub message: Option<String>,
+}
+
+/// An error or lint, split into the parts diagnostic renderers expect, like
+/// [`miette`](https://docs.rs/miette) or [`ariadne`](https://docs.rs/ariadne).
+///
+/// With the `miette` feature this implements `miette::Diagnostic`.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The main message, without location information.
+    pub message: String,
+    /// How bad the problem is.
+    pub severity: EvalSeverity,
+    /// A kebab-case identifier for the kind of problem, e.g. `fail` or `missing-return`.
+    pub code: Option<String>,
+    /// The file the labels refer to. Absent if the problem has no location.
+    pub source: Option<CodeMap>,
+    /// Locations in [`source`](Diagnostic::source), the first being the primary one.
+    pub labels: Vec<DiagnosticLabel>,
+    /// Additional information, like the call stack of an evaluation error.
+    pub help: Option<String>,
+}
+
+fn error_kind_code(kind: &ErrorKind) -> Option<&'static str> {
+    Some(match kind {
+        ErrorKind::Fail(_) => "fail",
+        ErrorKind::StackOverflow(_) => "stack-overflow",
+        ErrorKind::Value(_) => "value",
+        ErrorKind::Function(_) => "function",
+        ErrorKind::Scope(_) => "scope",
+        ErrorKind::Parser(_) => "parser",
+        ErrorKind::Freeze(_) => "freeze",
+        ErrorKind::Internal(_) => "internal",
+        ErrorKind::Native(_) => "native",
+        _ => return None,
+    })
+}
+
+impl Diagnostic {
+    /// The diagnostic for an error.
+    pub fn from_error(err: &crate::Error) -> Diagnostic {
+        let (source, labels) = match err.span() {
+            Some(span) => {
+                let mut labels = vec![DiagnosticLabel {
+                    span: span.span,
+                    message: None,
+                }];
+                // Labels can only point into the file of the primary span.
+                labels.extend(
+                    err.labels()
+                        .iter()
+                        .filter(|label| label.span.file == span.file)
+                        .map(|label| DiagnosticLabel {
+                            span: label.span.span,
+                            message: Some(label.label.clone()),
+                        }),
+                );
+                (Some(span.file.dupe()), labels)
+            }
+            None => (None, Vec::new()),
+        };
+        let call_stack = err.call_stack();
+        Diagnostic {
+            message: format!("{:#}", err.without_diagnostic()),
+            severity: EvalSeverity::Error,
+            code: error_kind_code(err.kind()).map(str::to_owned),
+            source,
+            labels,
+            help: (!call_stack.is_empty()).then(|| call_stack.to_string()),
+        }
+    }
+}
+
+impl From<Lint> for Diagnostic {
+    fn from(lint: Lint) -> Diagnostic {
+        Diagnostic {
+            message: lint.problem,
+            severity: lint.severity,
+            code: Some(lint.short_name),
+            labels: vec![DiagnosticLabel {
+                span: lint.location.span,
+                message: None,
+            }],
+            source: Some(lint.location.file),
+            help: None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(feature = "miette")]
+mod miette_impl {
+    use std::fmt;
+
+    use miette::LabeledSpan;
+    use miette::MietteError;
+    use miette::MietteSpanContents;
+    use miette::Severity;
+    use miette::SourceCode;
+    use miette::SourceSpan;
+    use miette::SpanContents;
+    use ref_cast::RefCast;
+
+    use crate::analysis::EvalSeverity;
+    use crate::codemap::CodeMap;
+    use crate::errors::Diagnostic;
+
+    /// A [`CodeMap`] as miette source code, named after the file.
+    #[derive(RefCast)]
+    #[repr(transparent)]
+    struct MietteSource(CodeMap);
+
+    impl SourceCode for MietteSource {
+        fn read_span<'a>(
+            &'a self,
+            span: &SourceSpan,
+            context_lines_before: usize,
+            context_lines_after: usize,
+        ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+            let contents =
+                self.0
+                    .source()
+                    .read_span(span, context_lines_before, context_lines_after)?;
+            Ok(Box::new(MietteSpanContents::new_named(
+                self.0.filename().to_owned(),
+                contents.data(),
+                *contents.span(),
+                contents.line(),
+                contents.column(),
+                contents.line_count(),
+            )))
+        }
+    }
+
+    impl miette::Diagnostic for Diagnostic {
+        fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+            self.code
+                .as_ref()
+                .map(|code| Box::new(code) as Box<dyn fmt::Display>)
+        }
+
+        fn severity(&self) -> Option<Severity> {
+            Some(match self.severity {
+                EvalSeverity::Error => Severity::Error,
+                EvalSeverity::Warning => Severity::Warning,
+                EvalSeverity::Advice | EvalSeverity::Disabled => Severity::Advice,
+            })
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+            self.help
+                .as_ref()
+                .map(|help| Box::new(help) as Box<dyn fmt::Display>)
+        }
+
+        fn source_code(&self) -> Option<&dyn SourceCode> {
+            self.source
+                .as_ref()
+                .map(|source| MietteSource::ref_cast(source) as &dyn SourceCode)
+        }
+
+        fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+            if self.labels.is_empty() {
+                return None;
+            }
+            Some(Box::new(self.labels.iter().enumerate().map(
+                |(i, label)| {
+                    let begin = label.span.begin().get() as usize;
+                    let len = label.span.end().get() as usize - begin;
+                    if i == 0 {
+                        LabeledSpan::new_primary_with_span(label.message.clone(), (begin, len))
+                    } else {
+                        LabeledSpan::new_with_span(label.message.clone(), (begin, len))
+                    }
+                },
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::EvalSeverity;
+    use crate::errors::Diagnostic;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_from_parse_error() {
+        let err = AstModule::parse("x.star", "x = (\n".to_owned(), &Dialect::Standard).unwrap_err();
+        let diagnostic = Diagnostic::from_error(&err);
+        assert_eq!(EvalSeverity::Error, diagnostic.severity);
+        assert_eq!(Some("parser"), diagnostic.code.as_deref());
+        assert_eq!("x.star", diagnostic.source.unwrap().filename());
+        assert_eq!(1, diagnostic.labels.len());
+        assert!(!diagnostic.message.contains("x.star"));
+    }
+
+    #[test]
+    fn test_from_error_with_label() {
+        let err = AstModule::parse("x.star", "f(a = 1, a = 2)\n".to_owned(), &Dialect::Standard)
+            .unwrap_err();
+        let diagnostic = Diagnostic::from_error(&err);
+        assert_eq!(2, diagnostic.labels.len());
+        assert_eq!(
+            Some("first given here"),
+            diagnostic.labels[1].message.as_deref()
+        );
+        assert_eq!(2, diagnostic.labels[1].span.begin().get());
+    }
+
+    #[test]
+    fn test_from_fail() {
+        let err = crate::assert::fail("def f():\n  fail('oops')\nf()", "oops");
+        let diagnostic = Diagnostic::from_error(&err);
+        assert_eq!(Some("fail"), diagnostic.code.as_deref());
+        assert!(diagnostic.help.unwrap().contains("Traceback"));
+    }
+}
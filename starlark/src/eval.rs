@@ -26,20 +26,39 @@ pub(crate) mod soft_error;
 
 use std::collections::HashMap;
 use std::mem;
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::Instant;
 
 use dupe::Dupe;
 pub use runtime::arguments::Arguments;
+pub use runtime::async_eval::AsyncEvaluation;
+pub use runtime::async_eval::YieldPoints;
+#[cfg(feature = "tokio")]
+pub use runtime::async_file_loader::AsyncFileLoader;
+#[cfg(feature = "tokio")]
+pub use runtime::async_file_loader::CachingFileLoader;
+#[cfg(feature = "tokio")]
+pub use runtime::async_file_loader::LoadContext;
+#[cfg(feature = "tokio")]
+pub use runtime::async_file_loader::LoadFuture;
 pub use runtime::before_stmt::BeforeStmtFuncDyn;
+pub use runtime::coverage::Coverage;
 pub use runtime::evaluator::Evaluator;
 pub use runtime::file_loader::FileLoader;
 pub use runtime::file_loader::ReturnFileLoader;
+pub use runtime::limits::EvalLimits;
+pub use runtime::observer::EvalObserver;
+#[cfg(feature = "tracing")]
+pub use runtime::observer::TracingObserver;
+pub use runtime::optimization_level::OptimizationLevel;
 pub use runtime::params::parser::ParametersParser;
 pub use runtime::params::spec::ParametersSpec;
 pub use runtime::params::spec::ParametersSpecParam;
 pub use runtime::profile::data::ProfileData;
 pub use runtime::profile::mode::ProfileMode;
+pub use runtime::replay::EvalTrace;
+pub use runtime::replay::NativeResult;
+pub use runtime::replay::TraceEvent;
+pub use runtime::replay::TraceRecorder;
+pub use runtime::replay::TraceReplayer;
 pub use soft_error::SoftErrorHandler;
 pub use starlark_syntax::call_stack::CallStack;
 use starlark_syntax::slice_vec_ext::SliceExt;
@@ -50,24 +69,45 @@ use crate::collections::symbol::symbol::Symbol;
 use crate::docs::DocString;
 use crate::environment::Globals;
 use crate::eval::compiler::Compiler;
+use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::DefInfo;
+use crate::eval::compiler::def::FrozenDef;
+use crate::eval::compiler::scope::BindingSource;
 use crate::eval::compiler::scope::ModuleScopes;
 use crate::eval::compiler::scope::ScopeId;
 use crate::eval::compiler::scope::scope_resolver_globals::ScopeResolverGlobals;
 pub use crate::eval::params::param_specs;
 use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
+use crate::eval::runtime::coverage::counted_stmts;
 use crate::eval::runtime::evaluator;
+use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
+use crate::eval::runtime::params::spec::StaticParam;
+pub use crate::stdlib::skylib::SkylibFileLoader;
 use crate::syntax::DialectTypes;
+use crate::util::instant::Instant;
+use crate::values::FrozenValue;
 use crate::values::Value;
+use crate::values::ValueLike;
 
 impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
     /// Evaluate an [`AstModule`] with this [`Evaluator`], modifying the in-scope
     /// [`Module`](crate::environment::Module) as appropriate.
     pub fn eval_module(&mut self, ast: AstModule, globals: &Globals) -> crate::Result<Value<'v>> {
-        #[cfg(not(target_arch = "wasm32"))]
         let start = Instant::now();
 
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "starlark::eval_module",
+            filename = ast.codemap().filename(),
+            statements = tracing::field::Empty
+        )
+        .entered();
+
+        if self.coverage.enabled() {
+            self.coverage.add_statements(&counted_stmts(&ast));
+        }
+
         let (codemap, statement, dialect, typecheck) = ast.into_parts();
 
         let codemap = self.module_env.frozen_heap().alloc_any(codemap.dupe());
@@ -83,6 +123,7 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
             module_slot_count,
             scope_data,
             top_level_stmt_count,
+            module_bindings,
         } = ModuleScopes::check_module_err(
             self.module_env.mutable_names(),
             self.module_env.frozen_heap(),
@@ -95,9 +136,19 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
             &dialect,
         )?;
 
+        #[cfg(feature = "tracing")]
+        span.record("statements", top_level_stmt_count);
+
         let scope_names = scope_data.get_scope(ScopeId::module());
         let local_names = self.frozen_heap().alloc_any_slice(&scope_names.used);
 
+        for (name, binding_id) in &module_bindings {
+            if let BindingSource::Source(span) = scope_data.get_binding(*binding_id).source {
+                self.module_env
+                    .add_definition_span(*name, FrozenFileSpan::new(codemap, span));
+            }
+        }
+
         self.module_env.slots().ensure_slots(module_slot_count);
         let old_def_info = mem::replace(
             &mut self.module_def_info,
@@ -130,6 +181,7 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
             check_types: dialect.enable_types == DialectTypes::Enable,
             top_level_stmt_count,
             typecheck,
+            str_accumulators: Vec::new(),
         };
 
         let res = compiler.eval_module(cst, local_names);
@@ -139,13 +191,17 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
 
         self.module_def_info = old_def_info;
 
-        #[cfg(not(target_arch = "wasm32"))]
         self.module_env.add_eval_duration(start.elapsed());
 
         self.run_infrequent_instr_checks()?;
 
+        let res = res.map_err(|e| e.into_error())?;
+        if dialect.enable_export_list {
+            self.module_env.apply_export_list()?;
+        }
+
         // Return the result of evaluation
-        res.map_err(|e| e.into_error())
+        Ok(res)
     }
 
     /// Evaluate a function stored in a [`Value`], passing in `positional` and `named` arguments.
@@ -180,4 +236,73 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
 
         res
     }
+
+    /// Call `function` once for each item of `args`, passing the item as positional arguments,
+    /// and return the results in order. Stops at the first error.
+    ///
+    /// This is equivalent to calling [`eval_function`](Evaluator::eval_function) in a loop,
+    /// but cheaper for callbacks called many times, like per-row filters:
+    /// the call stack is prepared once for the batch, and when `function` is a `def`,
+    /// arguments are bound to its parameters once for all calls with the same number of arguments.
+    pub fn eval_function_batch<A: AsRef<[Value<'v>]>>(
+        &mut self,
+        function: Value<'v>,
+        args: impl IntoIterator<Item = A>,
+    ) -> crate::Result<Vec<Value<'v>>> {
+        /// Parameters of the def bound for the number of positional arguments.
+        enum Bound<'v> {
+            Def(&'v Def<'v>, Box<[StaticParam<Value<'v>>]>),
+            FrozenDef(&'v FrozenDef, Box<[StaticParam<FrozenValue>]>),
+            /// Not a `def`, or the arguments can't be bound statically.
+            Generic,
+        }
+
+        fn bind<'v>(function: Value<'v>, pos: usize) -> Bound<'v> {
+            if let Some(def) = function.downcast_ref::<FrozenDef>() {
+                if let Some(params) = def.parameters.bind_static(pos, &[]) {
+                    return Bound::FrozenDef(def, params);
+                }
+            } else if let Some(def) = function.downcast_ref::<Def>() {
+                if let Some(params) = def.parameters.bind_static(pos, &[]) {
+                    return Bound::Def(def, params);
+                }
+            }
+            Bound::Generic
+        }
+
+        self.call_stack.alloc_if_needed(
+            self.max_callstack_size
+                .unwrap_or(evaluator::DEFAULT_STACK_SIZE),
+        )?;
+        let args = args.into_iter();
+        let mut results = Vec::with_capacity(args.size_hint().0);
+        // See `eval_function` for the empty frame.
+        let res = self.with_call_stack(Value::new_none(), None, |this| {
+            let mut bound: Option<(usize, Bound)> = None;
+            for args in args {
+                let args = args.as_ref();
+                this.report_forward_progress()?;
+                if !matches!(&bound, Some((pos, _)) if *pos == args.len()) {
+                    bound = Some((args.len(), bind(function, args.len())));
+                }
+                let result = match &bound.as_ref().unwrap().1 {
+                    Bound::FrozenDef(def, params) => {
+                        this.with_call_stack(function, None, |this| {
+                            def.invoke_with_bound_args(function, args, params, this)
+                        })?
+                    }
+                    Bound::Def(def, params) => this.with_call_stack(function, None, |this| {
+                        def.invoke_with_bound_args(function, args, params, this)
+                    })?,
+                    Bound::Generic => function.invoke_pos(args, this)?,
+                };
+                results.push(result);
+            }
+            Ok(())
+        });
+
+        self.run_infrequent_instr_checks()?;
+
+        res.map(|()| results)
+    }
 }
@@ -22,6 +22,7 @@ pub(crate) mod bytecode;
 pub(crate) mod call;
 pub(crate) mod compiler;
 pub(crate) mod definitely_assigned;
+pub(crate) mod feedback;
 pub(crate) mod for_loop;
 pub(crate) mod frame;
 pub(crate) mod if_debug;
@@ -29,6 +30,7 @@ pub(crate) mod instr;
 pub(crate) mod instr_arg;
 pub(crate) mod instr_impl;
 pub(crate) mod instrs;
+pub(crate) mod method_cache;
 pub(crate) mod native_function;
 pub(crate) mod opcode;
 pub(crate) mod repr;
@@ -37,7 +37,9 @@ use crate::eval::runtime::arguments::ArgumentsFull;
 use crate::eval::runtime::arguments::ArgumentsImpl;
 use crate::eval::runtime::arguments::ArgumentsPos;
 use crate::eval::runtime::arguments::ResolvedArgName;
+use crate::eval::runtime::params::spec::StaticParam;
 use crate::values::FrozenStringValue;
+use crate::values::FrozenValue;
 
 /// Call arguments.
 pub(crate) trait BcCallArgs<S: ArgSymbol>: BcInstrArg {
@@ -69,6 +71,15 @@ pub(crate) struct BcCallArgsPos {
     pub(crate) pos: BcSlotInRange,
 }
 
+/// Call arguments bound to `def` parameters at compile time, taken from the stack.
+#[derive(Debug)]
+pub(crate) struct BcCallArgsBound {
+    /// Positional then named arguments.
+    pub(crate) args: BcSlotInRange,
+    /// Where each parameter takes its value from.
+    pub(crate) params: Box<[StaticParam<FrozenValue>]>,
+}
+
 impl<S: ArgSymbol> BcCallArgsFull<S> {
     /// Number of positional arguments.
     fn pos(&self) -> u32 {
@@ -97,6 +108,44 @@ impl BcCallArgsFull<Symbol> {
     }
 }
 
+impl BcCallArgsFull<Symbol> {
+    /// Bind arguments to parameters of the `def`, if it can be done at compile time.
+    pub(crate) fn bind_static(&self, def: &FrozenDef) -> Option<BcCallArgsBound> {
+        if self.args.is_some() || self.kwargs.is_some() {
+            return None;
+        }
+        let names: Vec<&str> = self.names.iter().map(|(name, _)| name.as_str()).collect();
+        Some(BcCallArgsBound {
+            args: self.pos_named,
+            params: def.parameters.bind_static(self.pos() as usize, &names)?,
+        })
+    }
+}
+
+impl BcCallArgsPos {
+    /// Bind arguments to parameters of the `def`, if it can be done at compile time.
+    pub(crate) fn bind_static(&self, def: &FrozenDef) -> Option<BcCallArgsBound> {
+        Some(BcCallArgsBound {
+            args: self.pos,
+            params: def.parameters.bind_static(self.pos.len() as usize, &[])?,
+        })
+    }
+}
+
+impl Display for BcCallArgsBound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.args)?;
+        for param in &*self.params {
+            match param {
+                StaticParam::Arg(i) => write!(f, " {i}")?,
+                StaticParam::Default(v) => write!(f, " ={v}")?,
+                StaticParam::Unset => write!(f, " -")?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<S: ArgSymbol> Display for BcCallArgsFull<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let BcCallArgsFull {
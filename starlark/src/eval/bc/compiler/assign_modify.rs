@@ -21,7 +21,10 @@ use starlark_syntax::syntax::ast::AssignOp;
 
 use crate::collections::symbol::symbol::Symbol;
 use crate::eval::bc::compiler::expr::write_n_exprs;
+use crate::eval::bc::feedback::ObservedTypes;
+use crate::eval::bc::feedback::TypeClass;
 use crate::eval::bc::instr_impl::InstrAddAssign;
+use crate::eval::bc::instr_impl::InstrAddAssignInt;
 use crate::eval::bc::instr_impl::InstrArrayIndex;
 use crate::eval::bc::instr_impl::InstrArrayIndexSet;
 use crate::eval::bc::instr_impl::InstrBitAnd;
@@ -32,12 +35,16 @@ use crate::eval::bc::instr_impl::InstrFloorDivide;
 use crate::eval::bc::instr_impl::InstrLeftShift;
 use crate::eval::bc::instr_impl::InstrLoadModule;
 use crate::eval::bc::instr_impl::InstrMultiply;
+use crate::eval::bc::instr_impl::InstrMultiplyInt;
 use crate::eval::bc::instr_impl::InstrObjectField;
 use crate::eval::bc::instr_impl::InstrPercent;
 use crate::eval::bc::instr_impl::InstrRightShift;
 use crate::eval::bc::instr_impl::InstrSetObjectField;
 use crate::eval::bc::instr_impl::InstrStoreModule;
+use crate::eval::bc::instr_impl::InstrStrAccumulate;
+use crate::eval::bc::instr_impl::InstrStrAccumulateAdd;
 use crate::eval::bc::instr_impl::InstrSub;
+use crate::eval::bc::instr_impl::InstrSubInt;
 use crate::eval::bc::stack_ptr::BcSlotIn;
 use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::bc::stack_ptr::BcSlotsN;
@@ -68,9 +75,18 @@ impl AssignOnWriteBc for AssignOp {
         bc: &mut BcWriter,
     ) {
         let arg = (v0, v1, target);
+        let int = match self {
+            AssignOp::Add | AssignOp::Subtract | AssignOp::Multiply => {
+                bc.write_feedback_two(span, v0, v1) == ObservedTypes::Only(TypeClass::INT)
+            }
+            _ => false,
+        };
         match self {
+            AssignOp::Add if int => bc.write_instr::<InstrAddAssignInt>(span, arg),
             AssignOp::Add => bc.write_instr::<InstrAddAssign>(span, arg),
+            AssignOp::Subtract if int => bc.write_instr::<InstrSubInt>(span, arg),
             AssignOp::Subtract => bc.write_instr::<InstrSub>(span, arg),
+            AssignOp::Multiply if int => bc.write_instr::<InstrMultiplyInt>(span, arg),
             AssignOp::Multiply => bc.write_instr::<InstrMultiply>(span, arg),
             AssignOp::Divide => bc.write_instr::<InstrDivide>(span, arg),
             AssignOp::FloorDivide => bc.write_instr::<InstrFloorDivide>(span, arg),
@@ -94,7 +110,11 @@ impl AssignModifyLhs {
                 index.mark_definitely_assigned_after(bc);
             }
             AssignModifyLhs::LocalCaptured(_) => {}
-            AssignModifyLhs::Local(local) => bc.mark_definitely_assigned(local.node),
+            AssignModifyLhs::Local(local)
+            | AssignModifyLhs::LocalStrAccumulate(local)
+            | AssignModifyLhs::LocalStrAccumulateAdd(local) => {
+                bc.mark_definitely_assigned(local.node)
+            }
             AssignModifyLhs::Module(_) => {}
         }
     }
@@ -166,6 +186,24 @@ impl AssignModifyLhs {
                 );
                 bc.write_mov(span, lhs_rhs.get::<1>().to_in(), slot.to_bc_slot().to_out());
             }),
+            AssignModifyLhs::LocalStrAccumulate(s) | AssignModifyLhs::LocalStrAccumulateAdd(s) => {
+                bc.alloc_slots_c(|lhs_rhs: BcSlotsN<2>, bc| {
+                    let slot = s.node;
+                    bc.write_load_local(span, slot, lhs_rhs.get::<0>().to_out());
+                    rhs.write_bc(lhs_rhs.get::<1>().to_out(), bc);
+                    let arg = (
+                        lhs_rhs.get::<0>().to_in(),
+                        lhs_rhs.get::<1>().to_in(),
+                        lhs_rhs.get::<1>().to_out(),
+                    );
+                    if let AssignModifyLhs::LocalStrAccumulate(_) = self {
+                        bc.write_instr::<InstrStrAccumulate>(span, arg);
+                    } else {
+                        bc.write_instr::<InstrStrAccumulateAdd>(span, arg);
+                    }
+                    bc.write_mov(span, lhs_rhs.get::<1>().to_in(), slot.to_bc_slot().to_out());
+                })
+            }
             AssignModifyLhs::LocalCaptured(s) => bc.alloc_slots_c(|lhs_rhs: BcSlotsN<2>, bc| {
                 let slot = s.node;
                 bc.write_load_local_captured(span, slot, lhs_rhs.get::<0>().to_out());
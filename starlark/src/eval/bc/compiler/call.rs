@@ -27,6 +27,7 @@ use crate::eval::bc::compiler::expr::write_exprs;
 use crate::eval::bc::instr_impl::InstrCall;
 use crate::eval::bc::instr_impl::InstrCallFrozen;
 use crate::eval::bc::instr_impl::InstrCallFrozenDef;
+use crate::eval::bc::instr_impl::InstrCallFrozenDefBound;
 use crate::eval::bc::instr_impl::InstrCallFrozenDefPos;
 use crate::eval::bc::instr_impl::InstrCallFrozenNative;
 use crate::eval::bc::instr_impl::InstrCallFrozenNativePos;
@@ -39,6 +40,7 @@ use crate::eval::bc::instr_impl::InstrCallPos;
 use crate::eval::bc::instr_impl::InstrIsInstance;
 use crate::eval::bc::instr_impl::InstrLen;
 use crate::eval::bc::instr_impl::InstrType;
+use crate::eval::bc::method_cache::MethodCache;
 use crate::eval::bc::native_function::BcNativeFunction;
 use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::bc::writer::BcWriter;
@@ -132,13 +134,32 @@ impl IrSpanned<CallCompiled> {
         let file_span = bc.alloc_file_span(span);
         if let Some(fun) = FrozenValueTyped::<FrozenDef>::new(fun) {
             Self::write_args(args, bc, |args, bc| match args {
-                Either::Left(npops) => {
+                // Exactly the parameters passed positionally: collected without work anyway.
+                Either::Left(npops)
+                    if npops.pos.len() as usize == fun.as_ref().parameters.len() =>
+                {
                     bc.write_instr::<InstrCallFrozenDefPos>(span, (fun, npops, file_span, target))
                 }
-                Either::Right(args) => bc.write_instr::<InstrCallFrozenDef>(
-                    span,
-                    (fun, args.resolve(fun.as_ref()), file_span, target),
-                ),
+                Either::Left(npops) => match npops.bind_static(fun.as_ref()) {
+                    Some(args) => bc.write_instr::<InstrCallFrozenDefBound>(
+                        span,
+                        (fun, args, file_span, target),
+                    ),
+                    None => bc.write_instr::<InstrCallFrozenDefPos>(
+                        span,
+                        (fun, npops, file_span, target),
+                    ),
+                },
+                Either::Right(args) => match args.bind_static(fun.as_ref()) {
+                    Some(args) => bc.write_instr::<InstrCallFrozenDefBound>(
+                        span,
+                        (fun, args, file_span, target),
+                    ),
+                    None => bc.write_instr::<InstrCallFrozenDef>(
+                        span,
+                        (fun, args.resolve(fun.as_ref()), file_span, target),
+                    ),
+                },
             })
         } else if let Some(fun) = FrozenValueTyped::<NativeFunction>::new(fun) {
             let fun = BcNativeFunction::new(fun);
@@ -191,11 +212,13 @@ impl IrSpanned<CallCompiled> {
                             ),
                         );
                     } else {
+                        let cache = bc.heap.alloc_any(MethodCache::default());
                         bc.write_instr::<InstrCallMethodPos>(
                             span,
                             (
                                 this,
                                 symbol.clone(),
+                                cache,
                                 BcCallArgsPos { pos },
                                 file_span,
                                 target,
@@ -211,9 +234,10 @@ impl IrSpanned<CallCompiled> {
                             (this, symbol.clone(), known_method, args, file_span, target),
                         );
                     } else {
+                        let cache = bc.heap.alloc_any(MethodCache::default());
                         bc.write_instr::<InstrCallMethod>(
                             span,
-                            (this, symbol.clone(), args, file_span, target),
+                            (this, symbol.clone(), cache, args, file_span, target),
                         );
                     }
                 })
@@ -24,6 +24,8 @@ use starlark_syntax::slice_vec_ext::SliceExt;
 use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::eval::bc::compiler::if_compiler::write_if_else;
+use crate::eval::bc::feedback::ObservedTypes;
+use crate::eval::bc::feedback::TypeClass;
 use crate::eval::bc::instr_impl::*;
 use crate::eval::bc::slow_arg::BcInstrSlowArg;
 use crate::eval::bc::stack_ptr::BcSlot;
@@ -127,7 +129,7 @@ impl ExprCompiled {
             ExprCompiled::Local(local) => bc.mark_definitely_assigned(*local),
             ExprCompiled::LocalCaptured(_) => {}
             ExprCompiled::Module(_) => {}
-            ExprCompiled::Tuple(xs) | ExprCompiled::List(xs) => {
+            ExprCompiled::Tuple(xs) | ExprCompiled::List(xs) | ExprCompiled::Set(xs) => {
                 for x in xs {
                     x.mark_definitely_assigned_after(bc);
                 }
@@ -345,6 +347,15 @@ impl IrSpanned<ExprCompiled> {
                 }
             }
             ExprCompiled::Dict(xs) => Self::write_dict(span, xs, target, bc),
+            ExprCompiled::Set(xs) => {
+                let spans = xs.map(|x| x.span);
+                write_exprs(xs, bc, |xs, bc| {
+                    bc.write_instr_explicit::<InstrSetNPop>(
+                        BcInstrSlowArg { span, spans },
+                        (xs, target),
+                    );
+                });
+            }
             ExprCompiled::Compr(compr) => compr.write_bc(span, target, bc),
             ExprCompiled::Slice(l_start_stop_step) => {
                 let (l, start, stop, step) = &**l_start_stop_step;
@@ -417,25 +428,55 @@ impl IrSpanned<ExprCompiled> {
             }
             ExprCompiled::Builtin2(op, l_r) => {
                 let (l, r) = &**l_r;
+                // `x + 1`, `i < 10`: int is likely even without type feedback.
+                // Not for `*` since `[x] * 10` is as common as `x * 10`.
+                let int_operand =
+                    !matches!(op, Builtin2::Multiply) && (l.is_inline_int() || r.is_inline_int());
                 write_n_exprs([l, r], bc, |[l, r], bc| {
                     let arg = (l, r, target);
+                    let observed = match op {
+                        Builtin2::Compare(_)
+                        | Builtin2::Add
+                        | Builtin2::Sub
+                        | Builtin2::Multiply => bc.write_feedback_two(span, l, r),
+                        _ => ObservedTypes::Mixed,
+                    };
+                    let int = int_operand || observed == ObservedTypes::Only(TypeClass::INT);
                     match op {
                         Builtin2::Equals => unreachable!("handled above"),
+                        Builtin2::Compare(CompareOp::Less) if int => {
+                            bc.write_instr::<InstrLessInt>(span, arg)
+                        }
                         Builtin2::Compare(CompareOp::Less) => {
                             bc.write_instr::<InstrLess>(span, arg)
                         }
+                        Builtin2::Compare(CompareOp::Greater) if int => {
+                            bc.write_instr::<InstrGreaterInt>(span, arg)
+                        }
                         Builtin2::Compare(CompareOp::Greater) => {
                             bc.write_instr::<InstrGreater>(span, arg)
                         }
+                        Builtin2::Compare(CompareOp::LessOrEqual) if int => {
+                            bc.write_instr::<InstrLessOrEqualInt>(span, arg)
+                        }
                         Builtin2::Compare(CompareOp::LessOrEqual) => {
                             bc.write_instr::<InstrLessOrEqual>(span, arg)
                         }
+                        Builtin2::Compare(CompareOp::GreaterOrEqual) if int => {
+                            bc.write_instr::<InstrGreaterOrEqualInt>(span, arg)
+                        }
                         Builtin2::Compare(CompareOp::GreaterOrEqual) => {
                             bc.write_instr::<InstrGreaterOrEqual>(span, arg)
                         }
                         Builtin2::In => bc.write_instr::<InstrIn>(span, arg),
+                        Builtin2::Sub if int => bc.write_instr::<InstrSubInt>(span, arg),
                         Builtin2::Sub => bc.write_instr::<InstrSub>(span, arg),
+                        Builtin2::Add if int => bc.write_instr::<InstrAddInt>(span, arg),
+                        Builtin2::Add if observed == ObservedTypes::Only(TypeClass::STR) => {
+                            bc.write_instr::<InstrAddStr>(span, arg)
+                        }
                         Builtin2::Add => bc.write_instr::<InstrAdd>(span, arg),
+                        Builtin2::Multiply if int => bc.write_instr::<InstrMultiplyInt>(span, arg),
                         Builtin2::Multiply => bc.write_instr::<InstrMultiply>(span, arg),
                         Builtin2::Divide => bc.write_instr::<InstrDivide>(span, arg),
                         Builtin2::FloorDivide => bc.write_instr::<InstrFloorDivide>(span, arg),
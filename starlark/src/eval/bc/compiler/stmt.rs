@@ -18,11 +18,13 @@
 use crate::eval::bc::bytecode::Bc;
 use crate::eval::bc::compiler::if_compiler::write_if_else;
 use crate::eval::bc::compiler::if_compiler::write_if_then;
+use crate::eval::bc::feedback::BcFeedback;
 use crate::eval::bc::instr_impl::InstrCheckType;
 use crate::eval::bc::instr_impl::InstrPossibleGc;
 use crate::eval::bc::instr_impl::InstrReturn;
 use crate::eval::bc::instr_impl::InstrReturnCheckType;
 use crate::eval::bc::instr_impl::InstrReturnConst;
+use crate::eval::bc::instr_impl::InstrStrAccumulateFinish;
 use crate::eval::bc::stack_ptr::BcSlotIn;
 use crate::eval::bc::writer::BcWriter;
 use crate::eval::compiler::expr::ExprCompiled;
@@ -47,12 +49,13 @@ pub(crate) fn write_for(
     body: impl FnOnce(&mut BcWriter),
 ) {
     let definitely_assigned = bc.save_definitely_assigned();
+    let range = over.node.is_range();
 
     over.write_bc_cb(bc, |over, bc| {
         if let Some(var) = var.as_local_non_captured() {
             // Typical case: `for x in ...: ...`,
             // compile loop assignment directly to a local variable.
-            bc.write_for(over, var.to_bc_slot().to_out(), span, |bc| {
+            bc.write_for(over, range, var.to_bc_slot().to_out(), span, |bc| {
                 bc.mark_definitely_assigned(var);
                 body(bc);
             })
@@ -61,7 +64,7 @@ pub(crate) fn write_for(
             // compile loop assignment to a temporary variable,
             // and reassign it in the loop body.
             bc.alloc_slot(|var_slot, bc| {
-                bc.write_for(over, var_slot.to_out(), span, |bc| {
+                bc.write_for(over, range, var_slot.to_out(), span, |bc| {
                     var.write_bc(var_slot.to_in(), bc);
                     var.mark_definitely_assigned_after(bc);
                     body(bc);
@@ -121,6 +124,7 @@ impl StmtCompiled {
             }
             StmtCompiled::Break => {}
             StmtCompiled::Continue => {}
+            StmtCompiled::StrAccumulateFinish(_) => {}
         }
     }
 
@@ -250,6 +254,9 @@ impl IrSpanned<StmtCompiled> {
             StmtCompiled::Continue => {
                 bc.write_continue(span);
             }
+            StmtCompiled::StrAccumulateFinish(slot) => {
+                bc.write_instr::<InstrStrAccumulateFinish>(span, *slot);
+            }
         }
     }
 }
@@ -262,7 +269,21 @@ impl StmtsCompiled {
         param_count: u32,
         heap: &FrozenHeap,
     ) -> Bc {
+        self.as_bc_with_feedback(compiler, local_names, param_count, heap, BcFeedback::None)
+            .0
+    }
+
+    /// Compile, recording type feedback or specializing instructions with it.
+    pub(crate) fn as_bc_with_feedback(
+        &self,
+        compiler: &StmtCompileContext,
+        local_names: FrozenRef<'static, [FrozenStringValue]>,
+        param_count: u32,
+        heap: &FrozenHeap,
+        feedback: BcFeedback,
+    ) -> (Bc, BcFeedback) {
         let mut bc = BcWriter::new(local_names, param_count, heap);
+        bc.set_feedback(feedback);
         self.write_bc(compiler, &mut bc);
 
         // Small optimization: if the last statement is return,
@@ -279,6 +300,7 @@ impl StmtsCompiled {
             }
         }
 
-        bc.finish()
+        let feedback = bc.take_feedback();
+        (bc.finish(), feedback)
     }
 }
@@ -0,0 +1,186 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Type feedback, used by [`OptimizationLevel::Aggressive`](crate::eval::OptimizationLevel).
+//!
+//! An aggressively optimized function is first compiled with instructions which record
+//! the types of the operands of arithmetic, comparisons and `for` loops into cells.
+//! After a few calls, the function is compiled again, and the operations where only
+//! one type was observed are written as specialized instructions.
+//!
+//! Specialized instructions check their operands, and fall back to the generic
+//! implementation when the check fails, so they are correct for any input:
+//! the feedback only makes the common case faster.
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+use crate::values::FrozenRef;
+use crate::values::Value;
+use crate::values::list::ListRef;
+
+/// Bit sets of observed types.
+pub(crate) struct TypeClass;
+
+impl TypeClass {
+    pub(crate) const INT: u8 = 1;
+    pub(crate) const STR: u8 = 2;
+    pub(crate) const LIST: u8 = 4;
+    pub(crate) const OTHER: u8 = 8;
+
+    /// Class of a value. Only inline ints are `INT`, big ints are `OTHER`.
+    #[inline]
+    pub(crate) fn of(value: Value) -> u8 {
+        if value.unpack_inline_int().is_some() {
+            TypeClass::INT
+        } else if value.unpack_str().is_some() {
+            TypeClass::STR
+        } else if ListRef::from_value(value).is_some() {
+            TypeClass::LIST
+        } else {
+            TypeClass::OTHER
+        }
+    }
+}
+
+/// Types observed at one instruction.
+///
+/// The low four bits are the types of the left (or only) operand,
+/// and the high four bits the types of the right operand.
+#[derive(Debug, Default)]
+pub(crate) struct TypeFeedbackCell(AtomicU8);
+
+impl TypeFeedbackCell {
+    #[inline]
+    pub(crate) fn record(&self, bits: u8) {
+        // Relaxed is enough: feedback is a hint, and a lost update only
+        // makes a specialization less likely.
+        if self.0.load(Ordering::Relaxed) & bits != bits {
+            self.0.fetch_or(bits, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_one(&self, v: Value) {
+        self.record(TypeClass::of(v));
+    }
+
+    #[inline]
+    pub(crate) fn record_two(&self, l: Value, r: Value) {
+        self.record(TypeClass::of(l) | (TypeClass::of(r) << 4));
+    }
+
+    pub(crate) fn get(&self) -> u8 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Display for TypeFeedbackCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#04x}", self.get())
+    }
+}
+
+/// Types observed at one site, when compiling with feedback.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ObservedTypes {
+    /// Nothing was observed, or the site executed values of several types.
+    Mixed,
+    /// Both operands (or the only operand) were always of this class.
+    Only(u8),
+}
+
+impl ObservedTypes {
+    fn from_bits(bits: u8, two: bool) -> ObservedTypes {
+        let l = bits & 0xf;
+        let r = bits >> 4;
+        let single = l.is_power_of_two();
+        if single && (!two || r == l) {
+            ObservedTypes::Only(l)
+        } else {
+            ObservedTypes::Mixed
+        }
+    }
+}
+
+/// How the bytecode writer uses type feedback.
+#[derive(Debug, Default)]
+pub(crate) enum BcFeedback {
+    /// Write generic instructions.
+    #[default]
+    None,
+    /// Write instructions recording the types into these cells, one per site.
+    Record(Vec<FrozenRef<'static, TypeFeedbackCell>>),
+    /// Write specialized instructions for the types recorded in the cells.
+    Specialize {
+        cells: Vec<FrozenRef<'static, TypeFeedbackCell>>,
+        next: usize,
+    },
+}
+
+impl BcFeedback {
+    /// Take the types recorded for the next site, when specializing.
+    /// Sites are numbered in write order, which is the same for both compilations
+    /// because they compile the same IR.
+    pub(crate) fn next_observed(&mut self, two: bool) -> ObservedTypes {
+        match self {
+            BcFeedback::Specialize { cells, next } => {
+                let bits = cells.get(*next).map_or(0, |c| c.get());
+                *next += 1;
+                ObservedTypes::from_bits(bits, two)
+            }
+            _ => ObservedTypes::Mixed,
+        }
+    }
+
+    /// Cells, after compilation.
+    pub(crate) fn into_cells(self) -> Vec<FrozenRef<'static, TypeFeedbackCell>> {
+        match self {
+            BcFeedback::None => Vec::new(),
+            BcFeedback::Record(cells) | BcFeedback::Specialize { cells, .. } => cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::bc::feedback::ObservedTypes;
+    use crate::eval::bc::feedback::TypeClass;
+
+    #[test]
+    fn test_observed_types() {
+        let int2 = TypeClass::INT | (TypeClass::INT << 4);
+        assert_eq!(
+            ObservedTypes::Only(TypeClass::INT),
+            ObservedTypes::from_bits(int2, true)
+        );
+        let int_str = TypeClass::INT | (TypeClass::STR << 4);
+        assert_eq!(
+            ObservedTypes::Mixed,
+            ObservedTypes::from_bits(int_str, true)
+        );
+        let mixed = TypeClass::INT | TypeClass::STR;
+        assert_eq!(ObservedTypes::Mixed, ObservedTypes::from_bits(mixed, false));
+        assert_eq!(
+            ObservedTypes::Only(TypeClass::LIST),
+            ObservedTypes::from_bits(TypeClass::LIST, false)
+        );
+        assert_eq!(ObservedTypes::Mixed, ObservedTypes::from_bits(0, true));
+    }
+}
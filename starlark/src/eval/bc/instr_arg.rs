@@ -32,6 +32,7 @@ use crate::eval::bc::addr::BcAddr;
 use crate::eval::bc::addr::BcAddrOffset;
 use crate::eval::bc::addr::BcAddrOffsetNeg;
 use crate::eval::bc::addr::BcPtrAddr;
+use crate::eval::bc::call::BcCallArgsBound;
 use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
 use crate::eval::bc::for_loop::LoopDepth;
@@ -689,6 +690,19 @@ impl<S: ArgSymbol> BcInstrArg for BcCallArgsFull<S> {
     fn visit_jump_addr(_param: &Self, _ip: BcAddr, _consumer: &mut dyn FnMut(BcAddr)) {}
 }
 
+impl BcInstrArg for BcCallArgsBound {
+    fn fmt_append(
+        param: &Self,
+        _ip: BcAddr,
+        _end_arg: Option<&BcInstrEndArg>,
+        f: &mut dyn Write,
+    ) -> fmt::Result {
+        write!(f, " {{{param}}}")
+    }
+
+    fn visit_jump_addr(_param: &Self, _ip: BcAddr, _consumer: &mut dyn FnMut(BcAddr)) {}
+}
+
 impl BcInstrArg for BcCallArgsPos {
     fn fmt_append(
         param: &Self,
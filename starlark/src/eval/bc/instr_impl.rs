@@ -23,6 +23,7 @@ use std::ptr;
 
 use starlark_syntax::eval_exception::EvalException;
 
+use crate::ErrorCode;
 use crate::coerce::coerce;
 use crate::collections::Hashed;
 use crate::collections::SmallMap;
@@ -38,14 +39,17 @@ use crate::eval::bc::addr::BcAddrOffsetNeg;
 use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::bytecode::Bc;
 use crate::eval::bc::call::BcCallArgs;
+use crate::eval::bc::call::BcCallArgsBound;
 use crate::eval::bc::call::BcCallArgsForDef;
 use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
+use crate::eval::bc::feedback::TypeFeedbackCell;
 use crate::eval::bc::for_loop::LoopDepth;
 use crate::eval::bc::frame::BcFramePtr;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr::InstrControl;
 use crate::eval::bc::instr_arg::BcInstrArg;
+use crate::eval::bc::method_cache::MethodCache;
 use crate::eval::bc::native_function::BcNativeFunction;
 use crate::eval::bc::slow_arg::BcInstrEndArg;
 use crate::eval::bc::stack_ptr::BcSlotIn;
@@ -53,6 +57,7 @@ use crate::eval::bc::stack_ptr::BcSlotInRange;
 use crate::eval::bc::stack_ptr::BcSlotInRangeFrom;
 use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::compiler::add_span_to_expr_error;
+use crate::eval::compiler::constants::Constants;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::compiler::def::ParameterCompiled;
@@ -79,13 +84,21 @@ use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::StringValueLike;
 use crate::values::Value;
+use crate::values::ValueLike;
+use crate::values::array::Array;
 use crate::values::dict::Dict;
 use crate::values::int::pointer_i32::PointerI32;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::list::ListRef;
+use crate::values::range::Range;
+use crate::values::string::accumulator::StrAccumulator;
+use crate::values::string::accumulator::str_accumulate;
+use crate::values::string::accumulator::str_accumulate_add;
 use crate::values::string::dot_format::format_one;
 use crate::values::string::interpolation::percent_s_one;
 use crate::values::types::known_methods::KnownMethod;
 use crate::values::types::list::value::ListData;
+use crate::values::types::set::value::SetData;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
 
 /// Instructions which either fail or proceed to the following instruction,
@@ -153,6 +166,7 @@ pub(crate) struct InstrObjectFieldImpl;
 pub(crate) struct InstrSetObjectFieldImpl;
 pub(crate) struct InstrSliceImpl;
 pub(crate) struct InstrArrayIndex2Impl;
+pub(crate) struct InstrStrAccumulateFinishImpl;
 
 pub(crate) type InstrLoadLocal = InstrNoFlow<InstrLoadLocalImpl>;
 pub(crate) type InstrLoadLocalCaptured = InstrNoFlow<InstrLoadLocalCapturedImpl>;
@@ -169,6 +183,7 @@ pub(crate) type InstrObjectField = InstrNoFlow<InstrObjectFieldImpl>;
 pub(crate) type InstrSetObjectField = InstrNoFlow<InstrSetObjectFieldImpl>;
 pub(crate) type InstrSlice = InstrNoFlow<InstrSliceImpl>;
 pub(crate) type InstrArrayIndex2 = InstrNoFlow<InstrArrayIndex2Impl>;
+pub(crate) type InstrStrAccumulateFinish = InstrNoFlow<InstrStrAccumulateFinishImpl>;
 
 impl InstrNoFlowImpl for InstrLoadLocalImpl {
     type Arg = (LocalSlotId, BcSlotOut);
@@ -186,6 +201,25 @@ impl InstrNoFlowImpl for InstrLoadLocalImpl {
     }
 }
 
+impl InstrNoFlowImpl for InstrStrAccumulateFinishImpl {
+    type Arg = LocalSlotId;
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        slot: &LocalSlotId,
+    ) -> crate::Result<()> {
+        // The loop may not have assigned the variable.
+        if let Some(value) = frame.get_slot(slot.to_captured_or_not()) {
+            let value = StrAccumulator::finish(value, eval.heap());
+            frame.set_slot(slot.to_captured_or_not(), value);
+        }
+        Ok(())
+    }
+}
+
 impl InstrNoFlowImpl for InstrLoadLocalCapturedImpl {
     type Arg = (LocalCapturedSlotId, BcSlotOut);
 
@@ -293,9 +327,13 @@ impl InstrNoFlowImpl for InstrUnpackImpl {
         let v = frame.get_bc_slot(*source);
         let nvl = v.length()?;
         if nvl != target.len() as i32 {
-            return Err(crate::Error::new_other(
-                AssignError::IncorrectNumberOfValueToUnpack(target.len() as i32, nvl),
-            ));
+            return Err(
+                crate::Error::new_other(AssignError::IncorrectNumberOfValueToUnpack(
+                    target.len() as i32,
+                    nvl,
+                ))
+                .with_code(ErrorCode::new(418)),
+            );
         }
         let mut i = 0;
         for item in v.iterate(eval.heap())? {
@@ -584,6 +622,13 @@ pub(crate) trait InstrBinOpImpl: 'static {
 
 pub(crate) trait InstrUnOpImpl: 'static {
     fn eval<'v>(v: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>>;
+
+    /// The builtin function which the instruction replaces a call to, reported to the
+    /// observer as called.
+    #[inline(always)]
+    fn builtin() -> Option<FrozenValue> {
+        None
+    }
 }
 
 pub(crate) struct InstrBinOpWrapper<I: InstrBinOpImpl>(marker::PhantomData<I>);
@@ -620,7 +665,12 @@ impl<I: InstrUnOpImpl> InstrNoFlowImpl for InstrUnOpWrapper<I> {
         (source, target): &(BcSlotIn, BcSlotOut),
     ) -> crate::Result<()> {
         let source = frame.get_bc_slot(*source);
-        let value = I::eval(source, eval.heap())?;
+        let value = match I::builtin() {
+            None => I::eval(source, eval.heap())?,
+            Some(function) => {
+                eval.observe_builtin(function, |eval| I::eval(source, eval.heap()))?
+            }
+        };
         frame.set_bc_slot(*target, value);
         Ok(())
     }
@@ -628,6 +678,8 @@ impl<I: InstrUnOpImpl> InstrNoFlowImpl for InstrUnOpWrapper<I> {
 
 pub(crate) struct InstrAddImpl;
 pub(crate) struct InstrAddAssignImpl;
+pub(crate) struct InstrStrAccumulateImpl;
+pub(crate) struct InstrStrAccumulateAddImpl;
 pub(crate) struct InstrSubImpl;
 pub(crate) struct InstrMultiplyImpl;
 pub(crate) struct InstrPercentImpl;
@@ -643,6 +695,8 @@ pub(crate) struct InstrInImpl;
 
 pub(crate) type InstrAdd = InstrBinOp<InstrAddImpl>;
 pub(crate) type InstrAddAssign = InstrBinOp<InstrAddAssignImpl>;
+pub(crate) type InstrStrAccumulate = InstrBinOp<InstrStrAccumulateImpl>;
+pub(crate) type InstrStrAccumulateAdd = InstrBinOp<InstrStrAccumulateAddImpl>;
 pub(crate) type InstrSub = InstrBinOp<InstrSubImpl>;
 pub(crate) type InstrMultiply = InstrBinOp<InstrMultiplyImpl>;
 pub(crate) type InstrPercent = InstrBinOp<InstrPercentImpl>;
@@ -670,6 +724,20 @@ impl InstrBinOpImpl for InstrAddAssignImpl {
     }
 }
 
+impl InstrBinOpImpl for InstrStrAccumulateImpl {
+    #[inline(always)]
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        str_accumulate(v0, v1, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrStrAccumulateAddImpl {
+    #[inline(always)]
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        str_accumulate_add(v0, v1, heap)
+    }
+}
+
 impl InstrBinOpImpl for InstrSubImpl {
     #[inline(always)]
     fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
@@ -844,6 +912,135 @@ impl InstrCompareImpl for InstrGreaterOrEqualImpl {
     }
 }
 
+pub(crate) struct InstrRecordTypeImpl;
+pub(crate) struct InstrRecordTypesImpl;
+
+pub(crate) type InstrRecordType = InstrNoFlow<InstrRecordTypeImpl>;
+pub(crate) type InstrRecordTypes = InstrNoFlow<InstrRecordTypesImpl>;
+
+impl InstrNoFlowImpl for InstrRecordTypeImpl {
+    type Arg = (BcSlotIn, FrozenRef<'static, TypeFeedbackCell>);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        _eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (v, cell): &(BcSlotIn, FrozenRef<'static, TypeFeedbackCell>),
+    ) -> crate::Result<()> {
+        cell.record_one(frame.get_bc_slot(*v));
+        Ok(())
+    }
+}
+
+impl InstrNoFlowImpl for InstrRecordTypesImpl {
+    type Arg = (BcSlotIn, BcSlotIn, FrozenRef<'static, TypeFeedbackCell>);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        _eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (l, r, cell): &(BcSlotIn, BcSlotIn, FrozenRef<'static, TypeFeedbackCell>),
+    ) -> crate::Result<()> {
+        cell.record_two(frame.get_bc_slot(*l), frame.get_bc_slot(*r));
+        Ok(())
+    }
+}
+
+// Instructions specialized by type feedback. Each checks its operands,
+// and falls back to the generic operation if they are not of the expected type.
+
+pub(crate) struct InstrAddIntImpl;
+pub(crate) struct InstrAddAssignIntImpl;
+pub(crate) struct InstrSubIntImpl;
+pub(crate) struct InstrMultiplyIntImpl;
+pub(crate) struct InstrAddStrImpl;
+
+pub(crate) type InstrAddInt = InstrBinOp<InstrAddIntImpl>;
+pub(crate) type InstrAddAssignInt = InstrBinOp<InstrAddAssignIntImpl>;
+pub(crate) type InstrSubInt = InstrBinOp<InstrSubIntImpl>;
+pub(crate) type InstrMultiplyInt = InstrBinOp<InstrMultiplyIntImpl>;
+pub(crate) type InstrAddStr = InstrBinOp<InstrAddStrImpl>;
+
+impl InstrBinOpImpl for InstrAddIntImpl {
+    #[inline(always)]
+    fn eval<'v>(l: Value<'v>, r: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (l.unpack_inline_int(), r.unpack_inline_int()) {
+            if let Some(x) = a.checked_add(b) {
+                return Ok(Value::new_int(x));
+            }
+        }
+        l.add(r, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrAddAssignIntImpl {
+    #[inline(always)]
+    fn eval<'v>(l: Value<'v>, r: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (l.unpack_inline_int(), r.unpack_inline_int()) {
+            if let Some(x) = a.checked_add(b) {
+                return Ok(Value::new_int(x));
+            }
+        }
+        add_assign(l, r, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrSubIntImpl {
+    #[inline(always)]
+    fn eval<'v>(l: Value<'v>, r: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (l.unpack_inline_int(), r.unpack_inline_int()) {
+            if let Some(x) = a.checked_sub(b) {
+                return Ok(Value::new_int(x));
+            }
+        }
+        l.sub(r, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrMultiplyIntImpl {
+    #[inline(always)]
+    fn eval<'v>(l: Value<'v>, r: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (l.unpack_inline_int(), r.unpack_inline_int()) {
+            if let Some(x) = a.checked_mul_i32(b.to_i32()) {
+                return Ok(Value::new_int(x));
+            }
+        }
+        l.mul(r, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrAddStrImpl {
+    #[inline(always)]
+    fn eval<'v>(l: Value<'v>, r: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (l.unpack_str(), r.unpack_str()) {
+            if !a.is_empty() && !b.is_empty() {
+                heap.check_string_size(a.len() + b.len())?;
+                return Ok(heap.alloc_str_concat(a, b).to_value());
+            }
+        }
+        l.add(r, heap)
+    }
+}
+
+pub(crate) struct InstrCompareInt<I: InstrCompareImpl>(marker::PhantomData<I>);
+
+impl<I: InstrCompareImpl> InstrBinOpImpl for InstrCompareInt<I> {
+    #[inline(always)]
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let (Some(a), Some(b)) = (v0.unpack_inline_int(), v1.unpack_inline_int()) {
+            return Ok(Value::new_bool(I::eval_compare(a.cmp(&b))));
+        }
+        InstrCompare::<I>::eval(v0, v1, heap)
+    }
+}
+
+pub(crate) type InstrLessInt = InstrBinOp<InstrCompareInt<InstrLessImpl>>;
+pub(crate) type InstrGreaterInt = InstrBinOp<InstrCompareInt<InstrGreaterImpl>>;
+pub(crate) type InstrLessOrEqualInt = InstrBinOp<InstrCompareInt<InstrLessOrEqualImpl>>;
+pub(crate) type InstrGreaterOrEqualInt = InstrBinOp<InstrCompareInt<InstrGreaterOrEqualImpl>>;
+
 pub(crate) struct InstrTypeImpl;
 pub(crate) type InstrType = InstrUnOp<InstrTypeImpl>;
 
@@ -852,6 +1049,11 @@ impl InstrUnOpImpl for InstrTypeImpl {
     fn eval<'v>(v: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
         Ok(v.get_type_value().to_frozen_value().to_value())
     }
+
+    #[inline(always)]
+    fn builtin() -> Option<FrozenValue> {
+        Some(Constants::get().fn_type.0)
+    }
 }
 
 pub(crate) struct InstrTypeIsImpl;
@@ -882,13 +1084,13 @@ impl InstrNoFlowImpl for InstrIsInstanceImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
         frame: BcFramePtr<'v>,
         _: BcPtrAddr,
         (arg, t, target): &(BcSlotIn, TypeCompiled<FrozenValue>, BcSlotOut),
     ) -> crate::Result<()> {
         let arg = frame.get_bc_slot(*arg);
-        let r = t.matches(arg);
+        let r = eval.observe_builtin(Constants::get().fn_isinstance.0, |_| t.matches(arg));
         frame.set_bc_slot(*target, Value::new_bool(r));
         Ok(())
     }
@@ -902,6 +1104,11 @@ impl InstrUnOpImpl for InstrLenImpl {
     fn eval<'v>(v: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
         Ok(heap.alloc(v.length()?))
     }
+
+    #[inline(always)]
+    fn builtin() -> Option<FrozenValue> {
+        Some(Constants::get().fn_len.0)
+    }
 }
 
 pub(crate) struct InstrTupleNPopImpl;
@@ -912,6 +1119,7 @@ pub(crate) struct InstrDictConstKeysImpl;
 pub(crate) struct InstrDictNPopImpl;
 pub(crate) struct InstrListNewImpl;
 pub(crate) struct InstrDictNewImpl;
+pub(crate) struct InstrSetNPopImpl;
 
 pub(crate) type InstrTupleNPop = InstrNoFlow<InstrTupleNPopImpl>;
 pub(crate) type InstrListNew = InstrNoFlow<InstrListNewImpl>;
@@ -921,6 +1129,7 @@ pub(crate) type InstrDictNew = InstrNoFlow<InstrDictNewImpl>;
 pub(crate) type InstrDictOfConsts = InstrNoFlow<InstrDictOfConstsImpl>;
 pub(crate) type InstrDictConstKeys = InstrNoFlow<InstrDictConstKeysImpl>;
 pub(crate) type InstrDictNPop = InstrNoFlow<InstrDictNPopImpl>;
+pub(crate) type InstrSetNPop = InstrNoFlow<InstrSetNPopImpl>;
 
 impl InstrNoFlowImpl for InstrTupleNPopImpl {
     type Arg = (BcSlotInRange, BcSlotOut);
@@ -1013,7 +1222,8 @@ impl InstrNoFlowImpl for InstrDictNPopImpl {
             let prev = dict.insert_hashed(k, v);
             if prev.is_some() {
                 let e =
-                    crate::Error::new_other(EvalError::DuplicateDictionaryKey(k.key().to_string()));
+                    crate::Error::new_other(EvalError::DuplicateDictionaryKey(k.key().to_string()))
+                        .with_code(ErrorCode::new(417));
                 let spans = &Bc::slow_arg_at_ptr(ip).spans;
                 return Err(add_span_to_expr_error(e, spans[i], eval).into_error());
             }
@@ -1024,6 +1234,34 @@ impl InstrNoFlowImpl for InstrDictNPopImpl {
     }
 }
 
+impl InstrNoFlowImpl for InstrSetNPopImpl {
+    type Arg = (BcSlotInRange, BcSlotOut);
+
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr,
+        (values, target): &(BcSlotInRange, BcSlotOut),
+    ) -> crate::Result<()> {
+        let items = frame.get_bc_slot_range(*values);
+        let mut set = SetData::default();
+        for (i, x) in items.iter().enumerate() {
+            match x.get_hashed() {
+                Ok(x) => {
+                    set.add_hashed(x);
+                }
+                Err(e) => {
+                    let spans = &Bc::slow_arg_at_ptr(ip).spans;
+                    return Err(add_span_to_expr_error(e, spans[i], eval).into_error());
+                }
+            }
+        }
+        let set = eval.heap().alloc(set);
+        frame.set_bc_slot(*target, set);
+        Ok(())
+    }
+}
+
 impl InstrNoFlowImpl for InstrDictConstKeysImpl {
     type Arg = (Box<[Hashed<FrozenValue>]>, BcSlotInRangeFrom, BcSlotOut);
 
@@ -1328,6 +1566,213 @@ impl BcInstr for InstrIterStop {
     }
 }
 
+/// Setup `for` loop over a list, specialized by type feedback.
+pub(crate) struct InstrIterList;
+/// `continue` statement in a loop over a list, specialized by type feedback.
+pub(crate) struct InstrContinueList;
+
+/// Next element of a list iterator, without virtual calls.
+/// Returns `None` if `iter` is not a list iterator.
+#[inline(always)]
+fn list_iter_next<'v>(iter: Value<'v>, index: usize) -> Option<Option<Value<'v>>> {
+    // Mutable lists iterate over their content array, frozen lists over themselves.
+    if let Some(array) = iter.downcast_ref::<Array>() {
+        Some(array.content().get(index).copied())
+    } else if let Some(list) = ListRef::from_value(iter) {
+        Some(list.content().get(index).copied())
+    } else {
+        None
+    }
+}
+
+impl BcInstr for InstrIterList {
+    type Arg = (BcSlotIn, LoopDepth, BcSlotOut, BcSlotOut, BcAddrOffset);
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (over, loop_depth, iter_slot, var, end): &(
+            BcSlotIn,
+            LoopDepth,
+            BcSlotOut,
+            BcSlotOut,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        let over = frame.get_bc_slot(*over);
+        let iter = match over.get_ref().iterate(over, eval.heap()) {
+            Ok(iter) => iter,
+            Err(e) => return InstrControl::Err(e),
+        };
+        let next = match list_iter_next(iter, 0) {
+            Some(next) => next,
+            None => iter.get_ref().iter_next(0, eval.heap()),
+        };
+        match next {
+            Some(next) => {
+                frame.set_bc_slot(*iter_slot, iter);
+                frame.set_bc_slot(*var, next);
+                frame.set_iter_index(*loop_depth, 1);
+                InstrControl::Next(ip.add_instr::<Self>())
+            }
+            None => {
+                iter.get_ref().iter_stop();
+                InstrControl::Next(ip.add_rel(*end))
+            }
+        }
+    }
+}
+
+impl BcInstr for InstrContinueList {
+    type Arg = (
+        BcSlotIn,
+        LoopDepth,
+        BcSlotOut,
+        BcAddrOffsetNeg,
+        BcAddrOffset,
+    );
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (iter, loop_depth, var, begin, end): &(
+            BcSlotIn,
+            LoopDepth,
+            BcSlotOut,
+            BcAddrOffsetNeg,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        if let Err(e) = eval.report_forward_progress() {
+            return InstrControl::Err(e);
+        }
+        let iter = frame.get_bc_slot(*iter);
+        let loop_depth = *loop_depth;
+        let i = frame.get_iter_index(loop_depth);
+        let next = match list_iter_next(iter, i) {
+            Some(next) => next,
+            None => iter.get_ref().iter_next(i, eval.heap()),
+        };
+        match next {
+            Some(next) => {
+                frame.set_iter_index(loop_depth, i + 1);
+                frame.set_bc_slot(*var, next);
+                InstrControl::Next(ip.add_rel_neg(*begin))
+            }
+            None => {
+                iter.get_ref().iter_stop();
+                InstrControl::Next(ip.add_rel(*end))
+            }
+        }
+    }
+}
+
+/// Setup `for` loop over a `range`, known statically.
+pub(crate) struct InstrIterRange;
+/// `continue` statement in a loop over a `range`.
+pub(crate) struct InstrContinueRange;
+
+/// Next element of a range iterator, without virtual calls.
+/// Returns `None` if `iter` is not a range.
+#[inline(always)]
+fn range_iter_next<'v>(iter: Value<'v>, index: usize, heap: Heap<'v>) -> Option<Option<Value<'v>>> {
+    let range = iter.downcast_ref::<Range>()?;
+    Some(range.iter_nth(index).map(|x| heap.alloc(x)))
+}
+
+impl BcInstr for InstrIterRange {
+    type Arg = (BcSlotIn, LoopDepth, BcSlotOut, BcSlotOut, BcAddrOffset);
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (over, loop_depth, iter_slot, var, end): &(
+            BcSlotIn,
+            LoopDepth,
+            BcSlotOut,
+            BcSlotOut,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        let over = frame.get_bc_slot(*over);
+        // Range is its own iterator. Check the type anyway, `range` may be shadowed.
+        let (iter, next) = match range_iter_next(over, 0, eval.heap()) {
+            Some(next) => (over, next),
+            None => {
+                let iter = match over.get_ref().iterate(over, eval.heap()) {
+                    Ok(iter) => iter,
+                    Err(e) => return InstrControl::Err(e),
+                };
+                (iter, iter.get_ref().iter_next(0, eval.heap()))
+            }
+        };
+        match next {
+            Some(next) => {
+                frame.set_bc_slot(*iter_slot, iter);
+                frame.set_bc_slot(*var, next);
+                frame.set_iter_index(*loop_depth, 1);
+                InstrControl::Next(ip.add_instr::<Self>())
+            }
+            None => {
+                iter.get_ref().iter_stop();
+                InstrControl::Next(ip.add_rel(*end))
+            }
+        }
+    }
+}
+
+impl BcInstr for InstrContinueRange {
+    type Arg = (
+        BcSlotIn,
+        LoopDepth,
+        BcSlotOut,
+        BcAddrOffsetNeg,
+        BcAddrOffset,
+    );
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (iter, loop_depth, var, begin, end): &(
+            BcSlotIn,
+            LoopDepth,
+            BcSlotOut,
+            BcAddrOffsetNeg,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        if let Err(e) = eval.report_forward_progress() {
+            return InstrControl::Err(e);
+        }
+        let iter = frame.get_bc_slot(*iter);
+        let loop_depth = *loop_depth;
+        let i = frame.get_iter_index(loop_depth);
+        let next = match range_iter_next(iter, i, eval.heap()) {
+            Some(next) => next,
+            None => iter.get_ref().iter_next(i, eval.heap()),
+        };
+        match next {
+            Some(next) => {
+                frame.set_iter_index(loop_depth, i + 1);
+                frame.set_bc_slot(*var, next);
+                InstrControl::Next(ip.add_rel_neg(*begin))
+            }
+            None => {
+                iter.get_ref().iter_stop();
+                InstrControl::Next(ip.add_rel(*end))
+            }
+        }
+    }
+}
+
 pub(crate) struct InstrReturnConst;
 pub(crate) struct InstrReturn;
 pub(crate) struct InstrReturnCheckType;
@@ -1515,6 +1960,7 @@ pub(crate) struct InstrCallFrozenGenericImpl<F: BcFrozenCallable, A: BcCallArgs<
     marker::PhantomData<(F, A)>,
 );
 pub(crate) struct InstrCallFrozenDefImpl<A: BcCallArgsForDef>(marker::PhantomData<A>);
+pub(crate) struct InstrCallFrozenDefBoundImpl;
 pub(crate) struct InstrCallMethodImpl<A: BcCallArgs<Symbol>>(marker::PhantomData<A>);
 pub(crate) struct InstrCallMaybeKnownMethodImpl<A: BcCallArgs<Symbol>>(marker::PhantomData<A>);
 
@@ -1523,6 +1969,7 @@ pub(crate) type InstrCallPos = InstrNoFlow<InstrCallImpl<BcCallArgsPos>>;
 pub(crate) type InstrCallFrozenDef =
     InstrNoFlow<InstrCallFrozenDefImpl<BcCallArgsFull<ResolvedArgName>>>;
 pub(crate) type InstrCallFrozenDefPos = InstrNoFlow<InstrCallFrozenDefImpl<BcCallArgsPos>>;
+pub(crate) type InstrCallFrozenDefBound = InstrNoFlow<InstrCallFrozenDefBoundImpl>;
 pub(crate) type InstrCallFrozenNative =
     InstrNoFlow<InstrCallFrozenGenericImpl<BcNativeFunction, BcCallArgsFull<Symbol>>>;
 pub(crate) type InstrCallFrozenNativePos =
@@ -1608,6 +2055,37 @@ impl<A: BcCallArgsForDef> InstrNoFlowImpl for InstrCallFrozenDefImpl<A> {
     }
 }
 
+impl InstrNoFlowImpl for InstrCallFrozenDefBoundImpl {
+    type Arg = (
+        FrozenValueTyped<'static, FrozenDef>,
+        BcCallArgsBound,
+        FrozenRef<'static, FrameSpan>,
+        BcSlotOut,
+    );
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (fun, args, span, target): &(
+            FrozenValueTyped<'static, FrozenDef>,
+            BcCallArgsBound,
+            FrozenRef<'static, FrameSpan>,
+            BcSlotOut,
+        ),
+    ) -> crate::Result<()> {
+        eval.report_forward_progress()?;
+        let arguments = frame.get_bc_slot_range(args.args);
+        let r = eval.with_call_stack(fun.to_value(), Some(*span), |eval| {
+            fun.as_ref()
+                .invoke_with_bound_args(fun.to_value(), arguments, &args.params, eval)
+        })?;
+        frame.set_bc_slot(*target, r);
+        Ok(())
+    }
+}
+
 /// Common of method invocation instructions.
 #[inline(always)]
 fn call_method_common<'v>(
@@ -1659,6 +2137,7 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
     type Arg = (
         BcSlotIn,
         Symbol,
+        FrozenRef<'static, MethodCache>,
         A,
         FrozenRef<'static, FrameSpan>,
         BcSlotOut,
@@ -1669,9 +2148,10 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
         eval: &mut Evaluator<'v, '_, '_>,
         frame: BcFramePtr<'v>,
         _ip: BcPtrAddr,
-        (this, symbol, args, span, target): &(
+        (this, symbol, cache, args, span, target): &(
             BcSlotIn,
             Symbol,
+            FrozenRef<'static, MethodCache>,
             A,
             FrozenRef<'static, FrameSpan>,
             BcSlotOut,
@@ -1679,7 +2159,11 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
     ) -> crate::Result<()> {
         let this = frame.get_bc_slot(*this);
         let arguments = Arguments(args.pop_from_stack(frame));
-        call_method_common(eval, frame, this, symbol, &arguments, *span, *target)
+        eval.report_forward_progress()?;
+        let method = cache.get_attr(this, symbol, eval.heap())?;
+        let r = method.invoke(this, *span, &arguments, eval)?;
+        frame.set_bc_slot(*target, r);
+        Ok(())
     }
 }
 
@@ -35,6 +35,8 @@ use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr_impl::InstrEnd;
 use crate::eval::bc::instr_impl::InstrIter;
+use crate::eval::bc::instr_impl::InstrIterList;
+use crate::eval::bc::instr_impl::InstrIterRange;
 use crate::eval::bc::opcode::BcOpcode;
 use crate::eval::bc::opcode::BcOpcodeHandler;
 use crate::eval::bc::repr::BC_INSTR_ALIGN;
@@ -272,6 +274,12 @@ impl BcInstrs {
             if opcode == BcOpcode::Iter {
                 let for_loop = ptr.get_instr::<InstrIter>();
                 loop_ends.push(ip.offset(for_loop.arg.4));
+            } else if opcode == BcOpcode::IterList {
+                let for_loop = ptr.get_instr::<InstrIterList>();
+                loop_ends.push(ip.offset(for_loop.arg.4));
+            } else if opcode == BcOpcode::IterRange {
+                let for_loop = ptr.get_instr::<InstrIterRange>();
+                loop_ends.push(ip.offset(for_loop.arg.4));
             }
         }
         Ok(())
@@ -0,0 +1,155 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Polymorphic inline cache for method calls.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::ptr;
+use std::sync::OnceLock;
+
+use crate::collections::symbol::symbol::Symbol;
+use crate::eval::compiler::expr::MemberOrValue;
+use crate::eval::compiler::expr::get_attr_no_attr_error;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::layout::vtable::AValueVTable;
+use crate::values::types::unbound::UnboundValue;
+
+/// Number of receiver types cached per call site.
+/// Sites which see more types than this look up the method every call.
+pub(crate) const METHOD_CACHE_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+struct MethodCacheEntry {
+    vtable: &'static AValueVTable,
+    /// `None` if the type has no such method, and the attribute is resolved with `get_attr`.
+    method: Option<&'static UnboundValue>,
+}
+
+/// Method resolution of a call site, for up to [`METHOD_CACHE_SIZE`] receiver types.
+///
+/// Entries are filled in order and never replaced, so lookups are lock-free,
+/// and frozen bytecode can be executed on several threads.
+#[derive(Default)]
+pub(crate) struct MethodCache {
+    entries: [OnceLock<MethodCacheEntry>; METHOD_CACHE_SIZE],
+}
+
+impl MethodCache {
+    #[inline]
+    fn lookup(&self, vtable: &'static AValueVTable) -> Option<Option<&'static UnboundValue>> {
+        for entry in &self.entries {
+            match entry.get() {
+                Some(entry) if ptr::eq(entry.vtable, vtable) => return Some(entry.method),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+        None
+    }
+
+    fn insert(&self, entry: MethodCacheEntry) {
+        for slot in &self.entries {
+            // Another thread may fill the slot first, then try the next one.
+            if slot.set(entry).is_ok() {
+                return;
+            }
+        }
+    }
+
+    /// Like `get_attr_hashed_raw`, but skip the method lookup for cached types.
+    #[inline]
+    pub(crate) fn get_attr<'v>(
+        &self,
+        x: Value<'v>,
+        attribute: &Symbol,
+        heap: Heap<'v>,
+    ) -> crate::Result<MemberOrValue<'v, 'static>> {
+        let aref = x.get_ref();
+        let vtable = aref.vtable();
+        let method = match self.lookup(vtable) {
+            Some(method) => method,
+            None => {
+                let method = vtable
+                    .methods()
+                    .and_then(|methods| methods.get_frozen_symbol(attribute));
+                self.insert(MethodCacheEntry { vtable, method });
+                method
+            }
+        };
+        if let Some(method) = method {
+            return Ok(MemberOrValue::Member(method));
+        }
+        match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
+            None => Err(get_attr_no_attr_error(x, attribute)),
+            Some(x) => Ok(MemberOrValue::Value(x)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.iter().filter(|e| e.get().is_some()).count()
+    }
+}
+
+impl Debug for MethodCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Display for MethodCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Entries depend on execution, so do not print them in bytecode dumps.
+        write!(f, "<cache>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_polymorphic_call_site() {
+        let mut a = Assert::new();
+        a.module(
+            "f",
+            r#"
+def f(xs):
+    return [x.describe("a") for x in xs]
+"#,
+        );
+        // Several receiver types at one call site, none of which has `describe` as a method.
+        a.pass(
+            r#"
+load('f', 'f')
+R = record(describe = typing.Any)
+S = struct(describe = lambda k: k + "!")
+xs = [S, R(describe = lambda k: k.upper()), struct(describe = len), R(describe = str)]
+for _ in range(3):
+    assert_eq(["a!", "A", 1, "a"], f(xs))
+"#,
+        );
+        a.fail(
+            "load('f', 'f')\nf([1])",
+            "Object of type `int` has no attribute `describe`",
+        );
+    }
+}
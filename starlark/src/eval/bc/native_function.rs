@@ -57,6 +57,11 @@ impl BcNativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
+        let fun = self.fun.as_ref();
+        eval.check_deterministic(fun.deterministic, &fun.name)?;
+        if let Some(trace) = eval.trace {
+            return trace.native_call(fun, eval, args);
+        }
         self.imp.invoke(eval, args)
     }
 }
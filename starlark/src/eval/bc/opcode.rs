@@ -63,6 +63,9 @@ pub(crate) enum BcOpcode {
     In,
     Add,
     AddAssign,
+    StrAccumulate,
+    StrAccumulateAdd,
+    StrAccumulateFinish,
     Sub,
     Multiply,
     Percent,
@@ -88,6 +91,7 @@ pub(crate) enum BcOpcode {
     DictNPop,
     DictOfConsts,
     DictConstKeys,
+    SetNPop,
     ComprListAppend,
     ComprDictInsert,
     CheckType,
@@ -105,6 +109,7 @@ pub(crate) enum BcOpcode {
     CallPos,
     CallFrozenDef,
     CallFrozenDefPos,
+    CallFrozenDefBound,
     CallFrozenNative,
     CallFrozenNativePos,
     CallFrozen,
@@ -115,6 +120,21 @@ pub(crate) enum BcOpcode {
     CallMaybeKnownMethodPos,
     Def,
     ArrayIndex2,
+    RecordType,
+    RecordTypes,
+    AddInt,
+    AddAssignInt,
+    SubInt,
+    MultiplyInt,
+    AddStr,
+    LessInt,
+    GreaterInt,
+    LessOrEqualInt,
+    GreaterOrEqualInt,
+    IterList,
+    ContinueList,
+    IterRange,
+    ContinueRange,
     PossibleGc,
     End,
 }
@@ -155,6 +175,7 @@ impl BcOpcode {
             | BcOpcode::CallPos
             | BcOpcode::CallFrozenDef
             | BcOpcode::CallFrozenDefPos
+            | BcOpcode::CallFrozenDefBound
             | BcOpcode::CallFrozenNative
             | BcOpcode::CallFrozenNativePos
             | BcOpcode::CallFrozen
@@ -18,25 +18,36 @@
 //! Bytecode writer.
 
 use std::cmp;
+use std::mem;
 
 use crate::cast::transmute;
 use crate::eval::bc::addr::BcAddr;
 use crate::eval::bc::addr::BcAddrOffset;
 use crate::eval::bc::bytecode::Bc;
 use crate::eval::bc::definitely_assigned::BcDefinitelyAssigned;
+use crate::eval::bc::feedback::BcFeedback;
+use crate::eval::bc::feedback::ObservedTypes;
+use crate::eval::bc::feedback::TypeClass;
+use crate::eval::bc::feedback::TypeFeedbackCell;
 use crate::eval::bc::for_loop::LoopDepth;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr_impl::InstrBr;
 use crate::eval::bc::instr_impl::InstrBreak;
 use crate::eval::bc::instr_impl::InstrConst;
 use crate::eval::bc::instr_impl::InstrContinue;
+use crate::eval::bc::instr_impl::InstrContinueList;
+use crate::eval::bc::instr_impl::InstrContinueRange;
 use crate::eval::bc::instr_impl::InstrIfBr;
 use crate::eval::bc::instr_impl::InstrIfNotBr;
 use crate::eval::bc::instr_impl::InstrIter;
+use crate::eval::bc::instr_impl::InstrIterList;
+use crate::eval::bc::instr_impl::InstrIterRange;
 use crate::eval::bc::instr_impl::InstrIterStop;
 use crate::eval::bc::instr_impl::InstrLoadLocal;
 use crate::eval::bc::instr_impl::InstrLoadLocalCaptured;
 use crate::eval::bc::instr_impl::InstrMov;
+use crate::eval::bc::instr_impl::InstrRecordType;
+use crate::eval::bc::instr_impl::InstrRecordTypes;
 use crate::eval::bc::instr_impl::InstrStoreLocalCaptured;
 use crate::eval::bc::instrs::BcInstrsWriter;
 use crate::eval::bc::instrs::PatchAddr;
@@ -148,6 +159,19 @@ struct BcWriterForLoop {
     inner_addr: BcAddr,
     /// Addresses to patch with the address of the instruction after the loop.
     end_addrs_to_patch: Vec<PatchAddr>,
+    /// Kind of `for` loop instruction written, `continue` is specialized the same way.
+    kind: BcForLoopKind,
+}
+
+/// Specialization of `for` loop instructions.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum BcForLoopKind {
+    /// Any iterable.
+    Generic,
+    /// Only lists were observed iterated over.
+    List,
+    /// Iterable is known to be a `range`.
+    Range,
 }
 
 /// Write bytecode here.
@@ -172,6 +196,8 @@ pub(crate) struct BcWriter<'f> {
     for_loops: Vec<BcWriterForLoop>,
     /// Max observed loop depth.
     max_loop_depth: LoopDepth,
+    /// Record or use type feedback.
+    feedback: BcFeedback,
 
     /// Allocate various objects here.
     pub(crate) heap: &'f FrozenHeap,
@@ -202,9 +228,20 @@ impl<'f> BcWriter<'f> {
             heap,
             for_loops: Vec::new(),
             max_loop_depth: LoopDepth(0),
+            feedback: BcFeedback::None,
         }
     }
 
+    /// Record type feedback, or specialize instructions using it.
+    pub(crate) fn set_feedback(&mut self, feedback: BcFeedback) {
+        self.feedback = feedback;
+    }
+
+    /// Take the feedback back, to keep the cells allocated while recording.
+    pub(crate) fn take_feedback(&mut self) -> BcFeedback {
+        mem::take(&mut self.feedback)
+    }
+
     /// Finish writing the bytecode.
     #[allow(let_underscore_drop)]
     pub(crate) fn finish(self) -> Bc {
@@ -220,6 +257,7 @@ impl<'f> BcWriter<'f> {
             heap,
             for_loops,
             max_loop_depth,
+            feedback: _,
         } = self;
         let _ = heap;
         let _ = definitely_assigned;
@@ -270,7 +308,18 @@ impl<'f> BcWriter<'f> {
     }
 
     pub(crate) fn mark_before_stmt(&mut self, span: FrameSpan) {
-        self.stmt_locs.push(self.ip(), BcStmtLoc { span })
+        // A top-level statement is preceded by a GC point with the same span, where the
+        // statement is entered, so here it only continues.
+        if self.last_opcode == BcOpcode::PossibleGc
+            && self
+                .stmt_locs
+                .last_stmt_idx()
+                .is_some_and(|idx| self.stmt_locs.locs[idx as usize].span == span)
+        {
+            self.stmt_locs.push_prev(self.ip());
+        } else {
+            self.stmt_locs.push(self.ip(), BcStmtLoc { span })
+        }
     }
 
     /// Write an instruction, return address and argument.
@@ -447,16 +496,18 @@ impl<'f> BcWriter<'f> {
         let for_loop = self.for_loops.last().unwrap();
         let jump_back = self.ip().offset_from(for_loop.inner_addr).neg();
         let var = for_loop.var;
-        let (addr, arg) = self.write_instr_ret_arg::<InstrContinue>(
-            span,
-            (
-                for_loop.iter,
-                loop_depth,
-                var,
-                jump_back,
-                BcAddrOffset::FORWARD,
-            ),
+        let arg = (
+            for_loop.iter,
+            loop_depth,
+            var,
+            jump_back,
+            BcAddrOffset::FORWARD,
         );
+        let (addr, arg) = match for_loop.kind {
+            BcForLoopKind::Generic => self.write_instr_ret_arg::<InstrContinue>(span, arg),
+            BcForLoopKind::List => self.write_instr_ret_arg::<InstrContinueList>(span, arg),
+            BcForLoopKind::Range => self.write_instr_ret_arg::<InstrContinueRange>(span, arg),
+        };
         let end_patch = self.instrs.addr_to_patch(addr, unsafe { &(*arg).4 });
         let for_loop = self.for_loops.last_mut().unwrap();
         for_loop.end_addrs_to_patch.push(end_patch);
@@ -471,10 +522,11 @@ impl<'f> BcWriter<'f> {
         for_loop.end_addrs_to_patch.push(end_patch);
     }
 
-    /// Write for loop.
+    /// Write for loop. `range` is true if `over` is known to be a `range`.
     pub(crate) fn write_for(
         &mut self,
         over: BcSlotIn,
+        range: bool,
         var: BcSlotOut,
         span: FrameSpan,
         body: impl FnOnce(&mut BcWriter),
@@ -486,16 +538,26 @@ impl<'f> BcWriter<'f> {
             let definitely_assigned = bc.save_definitely_assigned();
 
             let loop_depth = LoopDepth(bc.for_loops.len() as u32);
-            let (addr, arg) = bc.write_instr_ret_arg::<InstrIter>(
-                span,
-                (over, loop_depth, iter.to_out(), var, BcAddrOffset::FORWARD),
-            );
+            let kind = if range {
+                BcForLoopKind::Range
+            } else if bc.write_feedback_one(span, over) == ObservedTypes::Only(TypeClass::LIST) {
+                BcForLoopKind::List
+            } else {
+                BcForLoopKind::Generic
+            };
+            let arg = (over, loop_depth, iter.to_out(), var, BcAddrOffset::FORWARD);
+            let (addr, arg) = match kind {
+                BcForLoopKind::Generic => bc.write_instr_ret_arg::<InstrIter>(span, arg),
+                BcForLoopKind::List => bc.write_instr_ret_arg::<InstrIterList>(span, arg),
+                BcForLoopKind::Range => bc.write_instr_ret_arg::<InstrIterRange>(span, arg),
+            };
             let end_patch = bc.instrs.addr_to_patch(addr, unsafe { &(*arg).4 });
             bc.for_loops.push(BcWriterForLoop {
                 inner_addr: bc.ip(),
                 end_addrs_to_patch: vec![end_patch],
                 var,
                 iter: iter.to_in(),
+                kind,
             });
             bc.max_loop_depth = cmp::max(bc.max_loop_depth, LoopDepth(bc.for_loops.len() as u32));
             body(bc);
@@ -621,6 +683,39 @@ impl<'f> BcWriter<'f> {
         r
     }
 
+    fn alloc_feedback_cell(&mut self) -> Option<FrozenRef<'static, TypeFeedbackCell>> {
+        match &mut self.feedback {
+            BcFeedback::Record(cells) => {
+                let cell = self.heap.alloc_any(TypeFeedbackCell::default());
+                cells.push(cell);
+                Some(cell)
+            }
+            _ => None,
+        }
+    }
+
+    /// Type feedback site for an operand: write an instruction recording its type,
+    /// or return the types recorded earlier.
+    pub(crate) fn write_feedback_one(&mut self, span: FrameSpan, v: BcSlotIn) -> ObservedTypes {
+        if let Some(cell) = self.alloc_feedback_cell() {
+            self.write_instr::<InstrRecordType>(span, (v, cell));
+        }
+        self.feedback.next_observed(false)
+    }
+
+    /// Type feedback site for the operands of a binary operation.
+    pub(crate) fn write_feedback_two(
+        &mut self,
+        span: FrameSpan,
+        l: BcSlotIn,
+        r: BcSlotIn,
+    ) -> ObservedTypes {
+        if let Some(cell) = self.alloc_feedback_cell() {
+            self.write_instr::<InstrRecordTypes>(span, (l, r, cell));
+        }
+        self.feedback.next_observed(true)
+    }
+
     pub(crate) fn alloc_file_span(&self, span: FrameSpan) -> FrozenRef<'static, FrameSpan> {
         self.heap.alloc_any(span)
     }
@@ -35,12 +35,14 @@ pub(crate) mod types;
 
 use starlark_syntax::eval_exception::EvalException;
 
+use crate::ErrorCode;
 use crate::codemap::CodeMap;
 use crate::environment::Globals;
 use crate::eval::Evaluator;
 use crate::eval::compiler::scope::ModuleScopeData;
 use crate::eval::compiler::scope::ScopeId;
 use crate::eval::compiler::scope::ScopeNames;
+use crate::eval::compiler::stmt::StrAccumulatorVar;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::values::FrozenRef;
 
@@ -51,6 +53,12 @@ pub(crate) fn add_span_to_expr_error(
     span: FrameSpan,
     eval: &Evaluator,
 ) -> EvalException {
+    // Errors raised while evaluating are at least runtime errors.
+    let e = if e.code() == ErrorCode::OTHER {
+        e.with_code(ErrorCode::RUNTIME)
+    } else {
+        e
+    };
     EvalException::new_with_callstack(e, span.span.span(), &span.span.file(), || {
         eval.call_stack.to_diagnostic_frames(span.inlined_frames)
     })
@@ -92,6 +100,8 @@ pub(crate) struct Compiler<'v, 'a, 'e, 'x> {
     pub(crate) top_level_stmt_count: usize,
     /// Set with `@starlark-rust: typecheck`.
     pub(crate) typecheck: bool,
+    /// Variables appended to in the loops being compiled.
+    pub(crate) str_accumulators: Vec<StrAccumulatorVar>,
 }
 
 impl Compiler<'_, '_, '_, '_> {
@@ -220,10 +220,10 @@ impl CallCompiled {
         let eval = ctx.eval()?;
 
         // Only if all call arguments are frozen values.
-        args.all_values(|arguments| {
-            let v = fun.to_value().invoke(arguments.frozen_to_v(), eval).ok()?;
-            ExprCompiled::try_value(span, v, eval.module_env.frozen_heap())
-        })?
+        let v = args
+            .all_values(|arguments| fun.to_value().invoke(arguments.frozen_to_v(), eval).ok())??;
+        // Not the frozen heap of the evaluator, which is temporary when optimizing on freeze.
+        ExprCompiled::try_value(span, v, ctx.frozen_heap())
     }
 
     // Optimize `MyEnum(arg)`.
@@ -48,6 +48,7 @@ pub(crate) struct Constants {
     pub(crate) fn_tuple: BuiltinFn,
     pub(crate) fn_isinstance: BuiltinFn,
     pub(crate) fn_set: BuiltinFn,
+    pub(crate) fn_range: BuiltinFn,
     // Technically, this is not a function.
     pub(crate) typing_callable: BuiltinFn,
 }
@@ -64,6 +65,7 @@ impl Constants {
                 fn_tuple: BuiltinFn(g.get_frozen("tuple").unwrap()),
                 fn_isinstance: BuiltinFn(g.get_frozen("isinstance").unwrap()),
                 fn_set: BuiltinFn(g.get_frozen("set").unwrap()),
+                fn_range: BuiltinFn(g.get_frozen("range").unwrap()),
                 typing_callable: {
                     let typing = g
                         .get_frozen("typing")
@@ -17,17 +17,18 @@
 
 //! Implementation of `def`.
 
-use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Write;
-use std::ptr;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 
 use allocative::Allocative;
 use derivative::Derivative;
 use derive_more::Display;
 use dupe::Dupe;
 use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
 use starlark_derive::NoSerialize;
 use starlark_derive::VisitSpanMut;
 use starlark_derive::starlark_value;
@@ -52,8 +53,11 @@ use crate::docs::DocString;
 use crate::docs::DocStringKind;
 use crate::environment::FrozenModuleData;
 use crate::environment::Globals;
+use crate::environment::Module;
 use crate::eval::Arguments;
 use crate::eval::bc::bytecode::Bc;
+use crate::eval::bc::feedback::BcFeedback;
+use crate::eval::bc::feedback::TypeFeedbackCell;
 use crate::eval::bc::frame::alloca_frame;
 use crate::eval::compiler::Compiler;
 use crate::eval::compiler::def_inline::InlineDefBody;
@@ -70,14 +74,17 @@ use crate::eval::compiler::scope::payload::CstStmt;
 use crate::eval::compiler::scope::payload::CstTypeExpr;
 use crate::eval::compiler::span::IrSpanned;
 use crate::eval::compiler::stmt::OptimizeOnFreezeContext;
+use crate::eval::compiler::stmt::OptimizeOnFreezeEvalContext;
 use crate::eval::compiler::stmt::StmtCompileContext;
 use crate::eval::compiler::stmt::StmtsCompiled;
 use crate::eval::runtime::arguments::ArgumentsImpl;
 use crate::eval::runtime::arguments::ResolvedArgName;
+use crate::eval::runtime::evaluator::DEFAULT_STACK_SIZE;
 use crate::eval::runtime::evaluator::Evaluator;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
 use crate::eval::runtime::params::spec::ParametersSpec;
+use crate::eval::runtime::params::spec::StaticParam;
 use crate::eval::runtime::profile::instant::ProfilerInstant;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::eval::runtime::slots::LocalSlotIdCapturedOrNot;
@@ -90,13 +97,13 @@ use crate::values::Freeze;
 use crate::values::FreezeResult;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
+use crate::values::FrozenHeapRef;
 use crate::values::FrozenRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::Trace;
-use crate::values::Tracer;
 use crate::values::Value;
 use crate::values::ValueLike;
 use crate::values::frozen_ref::AtomicFrozenRefOption;
@@ -109,38 +116,73 @@ enum DefError {
     CheckReturnTypeNoType,
 }
 
-/// Store frozen `StmtCompiled`.
-/// This is initialized in `post_freeze`.
-struct StmtCompiledCell {
-    cell: UnsafeCell<Bc>,
+/// Calls of a function recording type feedback, after which it is specialized.
+const TYPE_FEEDBACK_CALLS: u32 = 4;
+
+/// Type feedback of a frozen `def` compiled with `OptimizationLevel::Aggressive`.
+/// Created in `post_freeze`, when the bytecode recording the feedback is written.
+struct DefTypeFeedback {
+    /// Body optimized on freeze, compiled again once the feedback is collected.
+    body: StmtsCompiled,
+    /// Cells written by the recording bytecode, in site order.
+    cells: Vec<FrozenRef<'static, TypeFeedbackCell>>,
+    /// Number of calls which used the recording bytecode.
+    calls: AtomicU32,
+    specialized: OnceCell<OwnedBc>,
 }
 
-unsafe impl<'v> Trace<'v> for StmtCompiledCell {
-    fn trace(&mut self, _: &Tracer<'v>) {
-        // Bytecode contains only frozen values.
-    }
+/// Bytecode compiled after the `def` statement, with the heap it allocated into.
+struct OwnedBc {
+    bc: Bc,
+    _heap: FrozenHeapRef,
 }
 
-unsafe impl Sync for StmtCompiledCell {}
-unsafe impl Send for StmtCompiledCell {}
+// Bytecode contains only frozen values.
+unsafe impl Sync for OwnedBc {}
+unsafe impl Send for OwnedBc {}
 
-impl StmtCompiledCell {
-    fn new() -> StmtCompiledCell {
-        StmtCompiledCell {
-            cell: UnsafeCell::new(Bc::default()),
+impl DefTypeFeedback {
+    fn bc<'a>(&'a self, recording: &'a Bc) -> &'a Bc {
+        match self.specialized.get() {
+            Some(specialized) => &specialized.bc,
+            None => recording,
         }
     }
 
-    /// This function is unsafe if other thread is executing the stmt.
-    unsafe fn set(&self, value: Bc) {
-        unsafe {
-            ptr::drop_in_place(self.cell.get());
-            ptr::write(self.cell.get(), value);
+    /// Bytecode to run a call with, specializing after enough calls.
+    fn bc_for_call<'a>(
+        &'a self,
+        recording: &'a Bc,
+        def_info: &DefInfo,
+        param_count: u32,
+    ) -> &'a Bc {
+        if let Some(specialized) = self.specialized.get() {
+            return &specialized.bc;
         }
-    }
-
-    fn get(&self) -> &Bc {
-        unsafe { &*self.cell.get() }
+        if self.calls.fetch_add(1, Ordering::Relaxed) + 1 < TYPE_FEEDBACK_CALLS {
+            return recording;
+        }
+        &self
+            .specialized
+            .get_or_init(|| {
+                // Allocate into a new heap: the module heap is frozen by now.
+                let heap = FrozenHeap::new();
+                let (bc, _) = self.body.as_bc_with_feedback(
+                    &def_info.stmt_compile_context,
+                    def_info.used,
+                    param_count,
+                    &heap,
+                    BcFeedback::Specialize {
+                        cells: self.cells.clone(),
+                        next: 0,
+                    },
+                );
+                OwnedBc {
+                    bc,
+                    _heap: heap.into_ref(),
+                }
+            })
+            .bc
     }
 }
 
@@ -342,9 +384,11 @@ pub(crate) struct DefInfo {
     /// Slots to copy from the parent.
     /// Module-level identifiers are not copied over, to avoid excess copying.
     pub(crate) parent: FrozenRef<'static, [CopySlotFromParent]>,
-    /// Statement compiled for non-frozen def.
+    /// Statement compiled for non-frozen def, on first call.
     #[derivative(Debug = "ignore")]
-    stmt_compiled: Bc,
+    stmt_compiled: OnceCell<OwnedBc>,
+    /// Number of parameter variables, including `*args` and `**kwargs`.
+    param_count: u32,
     // The compiled expression for the body of this definition, to be run
     // after the parameters are evaluated.
     #[derivative(Debug = "ignore")]
@@ -359,6 +403,27 @@ pub(crate) struct DefInfo {
 }
 
 impl DefInfo {
+    /// Bytecode for non-frozen def. Compiling is deferred until the function is called,
+    /// since many functions of a module are never called before it is frozen.
+    fn stmt_compiled(&self) -> &Bc {
+        &self
+            .stmt_compiled
+            .get_or_init(|| {
+                let heap = FrozenHeap::new();
+                let bc = self.body_stmts.as_bc(
+                    &self.stmt_compile_context,
+                    self.used,
+                    self.param_count,
+                    &heap,
+                );
+                OwnedBc {
+                    bc,
+                    _heap: heap.into_ref(),
+                }
+            })
+            .bc
+    }
+
     pub(crate) fn empty() -> FrozenRef<'static, DefInfo> {
         static EMPTY: Lazy<DefInfo> = Lazy::new(|| DefInfo {
             name: const_frozen_string!("<empty>"),
@@ -369,7 +434,8 @@ impl DefInfo {
             docstring: None,
             used: FrozenRef::new(&[]),
             parent: FrozenRef::new(&[]),
-            stmt_compiled: Bc::default(),
+            stmt_compiled: OnceCell::new(),
+            param_count: 0,
             body_stmts: StmtsCompiled::empty(),
             stmt_compile_context: StmtCompileContext::default(),
             inline_def_body: None,
@@ -393,7 +459,8 @@ impl DefInfo {
             docstring: None,
             used: local_names,
             parent,
-            stmt_compiled: Bc::default(),
+            stmt_compiled: OnceCell::new(),
+            param_count: 0,
             body_stmts: StmtsCompiled::empty(),
             stmt_compile_context: StmtCompileContext::default(),
             inline_def_body: None,
@@ -490,6 +557,9 @@ impl Compiler<'_, '_, '_, '_> {
         let inline_def_body = if has_types {
             // It is harder to inline if a function declares parameter types or return type.
             None
+        } else if self.eval.coverage.enabled() {
+            // Inlined calls don't run the body, so its statements would never be counted.
+            None
         } else {
             inline_def_body(&params, &body)
         };
@@ -509,12 +579,8 @@ impl Compiler<'_, '_, '_, '_> {
             docstring,
             used,
             parent: self.eval.frozen_heap().alloc_any_slice(&scope_names.parent),
-            stmt_compiled: body.as_bc(
-                &self.compile_context(return_type.is_some()),
-                used,
-                param_count,
-                self.eval.module_env.frozen_heap(),
-            ),
+            stmt_compiled: OnceCell::new(),
+            param_count,
             body_stmts: body,
             inline_def_body,
             stmt_compile_context: self.compile_context(return_type.is_some()),
@@ -558,10 +624,17 @@ pub(crate) struct DefGen<V> {
     /// can be accessed from evaluator's module.
     #[allocative(skip)]
     pub(crate) module: AtomicFrozenRefOption<FrozenModuleData>,
-    /// This field is only used in `FrozenDef`. It is populated in `post_freeze`.
+    /// This field is only used in `FrozenDef`.
+    /// It is populated on first call after the module is frozen.
+    #[derivative(Debug = "ignore")]
+    #[allocative(skip)]
+    #[trace(unsafe_ignore)]
+    optimized_on_freeze_stmt: OnceCell<OwnedBc>,
+    /// Only used in `FrozenDef` compiled with type feedback. Populated in `post_freeze`.
     #[derivative(Debug = "ignore")]
     #[allocative(skip)]
-    optimized_on_freeze_stmt: StmtCompiledCell,
+    #[trace(unsafe_ignore)]
+    type_feedback: OnceCell<Box<DefTypeFeedback>>,
 }
 
 impl<V> Display for DefGen<V> {
@@ -594,7 +667,8 @@ impl<'v> Def<'v> {
             return_type,
             captured,
             module: AtomicFrozenRefOption::new(eval.top_frame_def_frozen_module(false)?),
-            optimized_on_freeze_stmt: StmtCompiledCell::new(),
+            optimized_on_freeze_stmt: OnceCell::new(),
+            type_feedback: OnceCell::new(),
             def_info: stmt,
         }))
     }
@@ -618,6 +692,7 @@ impl<'v> Freeze for Def<'v> {
             captured,
             module,
             optimized_on_freeze_stmt: self.optimized_on_freeze_stmt,
+            type_feedback: self.type_feedback,
         })
     }
 }
@@ -643,6 +718,10 @@ where
         self.def_info.name.as_str().to_owned()
     }
 
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
     fn invoke(
         &self,
         me: Value<'v>,
@@ -688,9 +767,137 @@ where
 {
     pub(crate) fn bc(&self) -> &Bc {
         if Self::FROZEN {
-            self.optimized_on_freeze_stmt.get()
+            let bc = self.optimized_on_freeze_bc();
+            match self.type_feedback.get() {
+                Some(feedback) => feedback.bc(bc),
+                None => bc,
+            }
+        } else {
+            self.def_info.stmt_compiled()
+        }
+    }
+
+    /// Like `bc`, but counts the call for type feedback.
+    #[inline(always)]
+    fn bc_for_call(&self) -> &Bc {
+        if Self::FROZEN {
+            let bc = self.optimized_on_freeze_bc();
+            match self.type_feedback.get() {
+                Some(feedback) => {
+                    feedback.bc_for_call(bc, &self.def_info, self.parameters.len() as u32)
+                }
+                None => bc,
+            }
         } else {
-            &self.def_info.stmt_compiled
+            self.def_info.stmt_compiled()
+        }
+    }
+
+    /// Whether the function body was compiled to bytecode.
+    #[cfg(test)]
+    pub(crate) fn is_compiled(&self) -> bool {
+        if Self::FROZEN {
+            self.optimized_on_freeze_stmt.get().is_some()
+        } else {
+            self.def_info.stmt_compiled.get().is_some()
+        }
+    }
+
+    /// Bytecode of frozen def, compiled on first call.
+    #[inline(always)]
+    fn optimized_on_freeze_bc(&self) -> &Bc {
+        &self
+            .optimized_on_freeze_stmt
+            .get_or_init(|| {
+                if self.def_info.stmt_compile_context.fold_on_freeze {
+                    Module::with_temp_heap(|module| self.fold_on_freeze(module))
+                } else {
+                    Heap::temp(|heap| self.optimize_on_freeze(heap))
+                }
+            })
+            .bc
+    }
+
+    /// Optimize the function body with fully frozen module, and compile it.
+    #[cold]
+    fn optimize_on_freeze(&self, heap: Heap<'_>) -> OwnedBc {
+        let def_module = self
+            .module
+            .load_relaxed()
+            .expect("module is set in `post_freeze`");
+        let frozen_heap = FrozenHeap::new();
+
+        // All module variables are frozen, so we can inline more aggressively.
+        let body = self.def_info.body_stmts.optimize(&mut OptCtx::new(
+            &mut OptimizeOnFreezeContext {
+                module: def_module.as_ref(),
+                heap,
+                frozen_heap: &frozen_heap,
+            },
+            self.parameters.len().try_into().unwrap(),
+            false,
+        ));
+        self.compile_on_freeze(body, frozen_heap)
+    }
+
+    /// Like `optimize_on_freeze`, but also fold calls of speculative-safe functions
+    /// with frozen arguments, like methods of frozen globals, using an evaluator of `module`.
+    #[cold]
+    fn fold_on_freeze(&self, module: Module<'_>) -> OwnedBc {
+        let def_module = self
+            .module
+            .load_relaxed()
+            .expect("module is set in `post_freeze`");
+        let frozen_heap = FrozenHeap::new();
+
+        let body = {
+            let mut eval = Evaluator::new(&module);
+            // Folded calls push a frame. If the stack can't be allocated they fail,
+            // so are not folded.
+            let _ = eval.call_stack.alloc_if_needed(DEFAULT_STACK_SIZE);
+            self.def_info.body_stmts.optimize(&mut OptCtx::new(
+                &mut OptimizeOnFreezeEvalContext {
+                    module: def_module.as_ref(),
+                    eval: &mut eval,
+                    frozen_heap: &frozen_heap,
+                },
+                self.parameters.len().try_into().unwrap(),
+                true,
+            ))
+        };
+        // Folded calls may return values allocated on the frozen heap of `module`.
+        frozen_heap.add_reference(&module.into_frozen_heap());
+        self.compile_on_freeze(body, frozen_heap)
+    }
+
+    fn compile_on_freeze(&self, body: StmtsCompiled, frozen_heap: FrozenHeap) -> OwnedBc {
+        let param_count = self.parameters.len() as u32;
+        let bc = if self.def_info.stmt_compile_context.type_feedback {
+            let (bc, feedback) = body.as_bc_with_feedback(
+                &self.def_info.stmt_compile_context,
+                self.def_info.used,
+                param_count,
+                &frozen_heap,
+                BcFeedback::Record(Vec::new()),
+            );
+            let _ = self.type_feedback.set(Box::new(DefTypeFeedback {
+                body,
+                cells: feedback.into_cells(),
+                calls: AtomicU32::new(0),
+                specialized: OnceCell::new(),
+            }));
+            bc
+        } else {
+            body.as_bc(
+                &self.def_info.stmt_compile_context,
+                self.def_info.used,
+                param_count,
+                &frozen_heap,
+            )
+        };
+        OwnedBc {
+            bc,
+            _heap: frozen_heap.into_ref(),
         }
     }
 
@@ -746,7 +953,7 @@ where
     where
         'v: 'a,
     {
-        let bc = self.bc();
+        let bc = self.bc_for_call();
         alloca_frame(
             eval,
             bc.local_count,
@@ -759,7 +966,7 @@ where
                 //   which does not have access to `eval` thus cannot access the frame indirectly.
                 let slots = unsafe { eval.current_frame.locals_mut() };
                 self.parameters.collect_inline(args, slots, eval.heap())?;
-                self.invoke_raw(me, eval)
+                self.invoke_raw(me, bc, eval)
             },
         )
     }
@@ -779,6 +986,36 @@ where
         self.invoke_impl(me, args, eval)
     }
 
+    /// Invoke the function with arguments bound to parameters at compile time,
+    /// without collecting them at runtime.
+    pub(crate) fn invoke_with_bound_args(
+        &self,
+        me: Value<'v>,
+        args: &[Value<'v>],
+        params: &[StaticParam<V>],
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let bc = self.bc_for_call();
+        alloca_frame(
+            eval,
+            bc.local_count,
+            bc.max_stack_size,
+            bc.max_loop_depth,
+            |eval| {
+                // SAFETY: `slots` is unique, see `invoke_impl`.
+                let slots = unsafe { eval.current_frame.locals_mut() };
+                for (slot, param) in slots.iter_mut().zip(params) {
+                    *slot = match *param {
+                        StaticParam::Arg(i) => Some(args[i as usize]),
+                        StaticParam::Default(x) => Some(x.to_value()),
+                        StaticParam::Unset => None,
+                    };
+                }
+                self.invoke_raw(me, bc, eval)
+            },
+        )
+    }
+
     /// Invoke the function, assuming that:
     /// * the frame has been allocated and stored in `eval.current_frame`
     /// * the arguments have been collected into the frame
@@ -786,6 +1023,7 @@ where
     fn invoke_raw(
         &self,
         me: Value<'v>,
+        bc: &Bc,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
         // println!("invoking {}", self.def.stmt.name.node);
@@ -814,8 +1052,7 @@ where
             debug_assert!(self.module.load_relaxed().is_some());
         }
 
-        eval.eval_bc(me, self.bc())
-            .map_err(EvalException::into_error)
+        eval.eval_bc(me, bc).map_err(EvalException::into_error)
     }
 
     pub(crate) fn resolve_arg_name(&self, name: Hashed<&str>) -> ResolvedArgName {
@@ -834,48 +1071,13 @@ where
 }
 
 impl FrozenDef {
-    pub(crate) fn post_freeze(
-        &self,
-        module: FrozenRef<FrozenModuleData>,
-        heap: Heap<'_>,
-        frozen_heap: &FrozenHeap,
-    ) {
+    /// Record the module the function is declared in. The body is optimized
+    /// with the frozen module and compiled lazily, on first call.
+    pub(crate) fn post_freeze(&self, module: FrozenRef<FrozenModuleData>) {
         // Module passed to this function is not always module where the function is declared:
         // A function can be created in a frozen module and frozen later in another module.
-        // `def_module` variable contains a module where this `def` is declared.
-        let def_module = match self.module.load_relaxed() {
-            None => {
-                self.module.store_relaxed(module);
-                module
-            }
-            Some(module) => module,
-        };
-
-        // Now perform the optimization of function body with fully frozen module:
-        // all module variables are frozen, so we can inline more aggressively.
-        let body_optimized = self
-            .def_info
-            .body_stmts
-            .optimize(&mut OptCtx::new(
-                &mut OptimizeOnFreezeContext {
-                    module: def_module.as_ref(),
-                    heap,
-                    frozen_heap,
-                },
-                self.parameters.len().try_into().unwrap(),
-            ))
-            .as_bc(
-                &self.def_info.stmt_compile_context,
-                self.def_info.used,
-                self.parameters.len() as u32,
-                frozen_heap,
-            );
-
-        // Store the optimized body.
-        // This is (relatively) safe because we know that during freeze
-        // nobody has a reference to stmt: nobody is executing this `def`.
-        unsafe {
-            self.optimized_on_freeze_stmt.set(body_optimized);
+        if self.module.load_relaxed().is_none() {
+            self.module.store_relaxed(module);
         }
     }
 }
@@ -59,6 +59,7 @@ use crate::eval::compiler::scope::payload::CstIdent;
 use crate::eval::compiler::span::IrSpanned;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
+use crate::eval::runtime::optimization_level::OptimizationLevel;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::values::FrozenHeap;
@@ -72,6 +73,7 @@ use crate::values::Value;
 use crate::values::ValueError;
 use crate::values::ValueLike;
 use crate::values::bool::StarlarkBool;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::function::BoundMethodGen;
 use crate::values::function::FrozenBoundMethod;
 use crate::values::list::ListRef;
@@ -243,6 +245,8 @@ pub(crate) enum ExprCompiled {
     Tuple(Vec<IrSpanned<ExprCompiled>>),
     List(Vec<IrSpanned<ExprCompiled>>),
     Dict(Vec<(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>)>),
+    /// Set literal, never empty.
+    Set(Vec<IrSpanned<ExprCompiled>>),
     /// Comprehension.
     Compr(ComprCompiled),
     If(
@@ -320,6 +324,32 @@ impl ExprCompiled {
         }
     }
 
+    /// Expression is builtin `range` function.
+    pub(crate) fn is_fn_range(&self) -> bool {
+        match self.as_value() {
+            Some(value) => value == Constants::get().fn_range,
+            None => false,
+        }
+    }
+
+    /// Expression is known to evaluate to a `range`: either a constant range,
+    /// or a call to builtin `range`.
+    pub(crate) fn is_range(&self) -> bool {
+        match self {
+            Self::Value(v) => v.downcast_ref::<Range>().is_some(),
+            Self::Call(call) => call.fun.is_fn_range(),
+            _ => false,
+        }
+    }
+
+    /// Expression is a constant int which fits in an inline int.
+    pub(crate) fn is_inline_int(&self) -> bool {
+        match self.as_value() {
+            Some(value) => value.unpack_inline_int().is_some(),
+            None => false,
+        }
+    }
+
     /// Expression is builtin `isinstance` function.
     pub(crate) fn is_fn_isinstance(&self) -> bool {
         match self.as_value() {
@@ -507,6 +537,7 @@ impl IrSpanned<ExprCompiled> {
                 ExprCompiled::tuple(xs.map(|e| e.optimize(ctx)), ctx.frozen_heap())
             }
             ExprCompiled::List(xs) => ExprCompiled::List(xs.map(|e| e.optimize(ctx))),
+            ExprCompiled::Set(xs) => ExprCompiled::Set(xs.map(|e| e.optimize(ctx))),
             ExprCompiled::Dict(kvs) => {
                 ExprCompiled::Dict(kvs.map(|(k, v)| (k.optimize(ctx), v.optimize(ctx))))
             }
@@ -877,6 +908,18 @@ impl ExprCompiled {
                     ctx.frozen_heap()
                         .alloc_simple(BoundMethodGen::new(left, *m)),
                 ),
+                // Attributes are functions, so only evaluate those which are safe to.
+                UnboundValue::Attr(attr) if ctx.aggressive && attr.speculative_exec_safe => {
+                    let v = attr.invoke(left.to_value(), ctx.heap()).ok()?;
+                    match v.unpack_frozen() {
+                        Some(v) => Some(v),
+                        None => Some(
+                            ctx.frozen_heap()
+                                .alloc_str_intern(v.unpack_str()?)
+                                .to_frozen_value(),
+                        ),
+                    }
+                }
                 UnboundValue::Attr(..) => None,
             },
             MemberOrValue::Value(v) => v.unpack_frozen(),
@@ -1051,6 +1094,7 @@ impl AstLiteralCompile for AstLiteral {
             AstLiteral::Int(i) => heap.alloc(StarlarkInt::from(i.node.clone())),
             AstLiteral::Float(f) => heap.alloc(f.node),
             AstLiteral::String(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::Bytes(x) => heap.alloc(StarlarkBytes::new(x.node.as_slice())),
             AstLiteral::Ellipsis => heap.alloc(Ellipsis),
         }
     }
@@ -1107,7 +1151,7 @@ impl<P: AstPayload> CompilerExprUtil<P> for ExprP<P> {
 
 #[cold]
 #[inline(never)]
-fn get_attr_no_attr_error<'v>(x: Value<'v>, attribute: &Symbol) -> crate::Error {
+pub(crate) fn get_attr_no_attr_error<'v>(x: Value<'v>, attribute: &Symbol) -> crate::Error {
     match did_you_mean(attribute.as_str(), x.dir_attr().iter().map(|s| s.as_str())) {
         None => ValueError::NoAttr(x.get_type().to_owned(), attribute.as_str().to_owned()).into(),
         Some(better) => ValueError::NoAttrDidYouMean(
@@ -1220,7 +1264,8 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e, '_> {
 
     fn opt_ctx<'s>(&'s mut self) -> OptCtx<'v, 'a, 'e, 's> {
         let param_count = self.current_scope().param_count();
-        OptCtx::new(self.eval, param_count)
+        let aggressive = self.eval.optimization_level == OptimizationLevel::Aggressive;
+        OptCtx::new(self.eval, param_count, aggressive)
     }
 
     pub(crate) fn expr(
@@ -1261,6 +1306,10 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e, '_> {
                     .collect::<Result<_, CompilerInternalError>>()?;
                 ExprCompiled::Dict(xs)
             }
+            ExprP::Set(exprs) => {
+                let xs = self.exprs(exprs)?;
+                ExprCompiled::Set(xs)
+            }
             ExprP::If(cond_then_expr_else_expr) => {
                 let (cond, then_expr, else_expr) = &**cond_then_expr_else_expr;
                 let cond = self.expr(cond)?;
@@ -60,6 +60,8 @@ impl<'v> Compiler<'v, '_, '_, '_> {
         let name = &load.node.module.node;
 
         let span = FrameSpan::new(FrozenFileSpan::new(self.codemap, load.span));
+        // Loads are not compiled to bytecode, so aren't seen by `before_stmt`.
+        self.eval.coverage.before_stmt(span.span.file_span_ref());
 
         let loadenv = match self.eval.loader.as_ref() {
             None => {
@@ -71,6 +73,9 @@ impl<'v> Compiler<'v, '_, '_, '_> {
             }
             Some(loader) => expr_throw(loader.load(name), span, self.eval)?,
         };
+        if let Some(trace) = self.eval.trace {
+            expr_throw(trace.load(name, &loadenv), span, self.eval)?;
+        }
 
         for load_arg in &load.node.args {
             let (slot, _captured) = self
@@ -108,15 +113,19 @@ impl<'v> Compiler<'v, '_, '_, '_> {
             ));
         }
 
-        let stmt = self
-            .module_top_level_stmt(stmt)
-            .map_err(|e| e.into_eval_exception())?;
-        let bc = stmt.as_bc(
-            &self.compile_context(false),
-            local_names,
-            0,
-            self.eval.module_env.frozen_heap(),
-        );
+        let bc = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("starlark::compile").entered();
+            let stmt = self
+                .module_top_level_stmt(stmt)
+                .map_err(|e| e.into_eval_exception())?;
+            stmt.as_bc(
+                &self.compile_context(false),
+                local_names,
+                0,
+                self.eval.module_env.frozen_heap(),
+            )
+        };
         // We don't preserve locals between top level statements.
         // That is OK for now: the only locals used in module evaluation
         // are comprehension bindings.
@@ -18,6 +18,7 @@
 use crate::environment::FrozenModuleData;
 use crate::eval::Evaluator;
 use crate::eval::compiler::stmt::OptimizeOnFreezeContext;
+use crate::eval::compiler::stmt::OptimizeOnFreezeEvalContext;
 use crate::values::FrozenHeap;
 use crate::values::Heap;
 
@@ -46,6 +47,24 @@ impl<'v, 'a, 'e> OptCtxEval<'v, 'a, 'e> for OptimizeOnFreezeContext<'v, 'a> {
     }
 }
 
+impl<'v, 'a, 'e> OptCtxEval<'v, 'a, 'e> for OptimizeOnFreezeEvalContext<'v, 'a, 'e, '_> {
+    fn heap(&self) -> Heap<'v> {
+        self.eval.heap()
+    }
+
+    fn frozen_heap(&self) -> &FrozenHeap {
+        self.frozen_heap
+    }
+
+    fn eval(&mut self) -> Option<&mut Evaluator<'v, 'a, 'e>> {
+        Some(self.eval)
+    }
+
+    fn frozen_module(&self) -> Option<&FrozenModuleData> {
+        Some(self.module)
+    }
+}
+
 impl<'v, 'a, 'e> OptCtxEval<'v, 'a, 'e> for Evaluator<'v, 'a, 'e> {
     fn heap(&self) -> Heap<'v> {
         self.heap()
@@ -73,14 +92,21 @@ pub(crate) struct OptCtx<'v: 'a, 'a, 'e: 'a, 'x> {
     pub(crate) eval: &'x mut dyn OptCtxEval<'v, 'a, 'e>,
     /// Current function parameter slot count. Zero when compiling module.
     pub(crate) param_count: u32,
+    /// Enable the optimizations of `OptimizationLevel::Aggressive`.
+    pub(crate) aggressive: bool,
 }
 
 impl<'v, 'a, 'e: 'a, 'x> OptCtx<'v, 'a, 'e, 'x> {
     pub(crate) fn new(
         eval: &'x mut dyn OptCtxEval<'v, 'a, 'e>,
         param_count: u32,
+        aggressive: bool,
     ) -> OptCtx<'v, 'a, 'e, 'x> {
-        OptCtx { eval, param_count }
+        OptCtx {
+            eval,
+            param_count,
+            aggressive,
+        }
     }
 
     pub(crate) fn heap(&self) -> Heap<'v> {
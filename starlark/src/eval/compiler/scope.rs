@@ -47,6 +47,7 @@ use starlark_syntax::syntax::ast::Visibility;
 use starlark_syntax::syntax::top_level_stmts::top_level_stmts_mut;
 use starlark_syntax::syntax::uniplate::VisitMut;
 
+use crate::ErrorCode;
 use crate::codemap::CodeMap;
 use crate::codemap::Span;
 use crate::environment::Module;
@@ -85,7 +86,17 @@ enum ScopeError {
 
 impl From<ScopeError> for crate::Error {
     fn from(e: ScopeError) -> Self {
+        let (code, args) = match &e {
+            ScopeError::VariableNotFound(name) => (301, vec![("name", name.clone())]),
+            ScopeError::VariableNotFoundDidYouMean(name, suggestion) => (
+                301,
+                vec![("name", name.clone()), ("suggestion", suggestion.clone())],
+            ),
+            ScopeError::TypeExpressionGlobalOrBuiltin(name) => (302, vec![("name", name.clone())]),
+        };
         crate::Error::new_kind(crate::ErrorKind::Scope(anyhow::Error::new(e)))
+            .with_code(ErrorCode::new(code))
+            .with_message_args(args)
     }
 }
 
@@ -111,6 +122,8 @@ pub(crate) struct ModuleScopes<'f> {
     pub(crate) cst: CstStmt,
     /// Number of top-level statements in the module.
     pub(crate) top_level_stmt_count: usize,
+    /// Bindings of the module variables.
+    pub(crate) module_bindings: SmallMap<FrozenStringValue, BindingId>,
 }
 
 struct UnscopeBinding {
@@ -410,7 +423,7 @@ impl<'f> ModuleScopes<'f> {
         );
         let top_level_stmt_count = scope.top_level_stmt_count;
         let errors = mem::take(&mut scope.errors);
-        let (module_slot_count, scope_data, _module_bindings) = scope.exit_module();
+        let (module_slot_count, scope_data, module_bindings) = scope.exit_module();
         (
             errors,
             ModuleScopes {
@@ -418,6 +431,7 @@ impl<'f> ModuleScopes<'f> {
                 scope_data,
                 module_slot_count,
                 top_level_stmt_count,
+                module_bindings,
             },
         )
     }
@@ -24,13 +24,18 @@
 //! Bazel's BUILD file). The BUILD dialect does not allow `def` statements.
 
 use std::cmp;
+use std::convert::Infallible;
 
 use starlark_derive::VisitSpanMut;
+use starlark_map::small_map::SmallMap;
 use starlark_syntax::slice_vec_ext::SliceExt;
 use starlark_syntax::syntax::ast::AssignOp;
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTargetP;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::BinOp;
 use starlark_syntax::syntax::ast::DefP;
+use starlark_syntax::syntax::ast::ExprP;
 use starlark_syntax::syntax::ast::ForP;
 use starlark_syntax::syntax::ast::StmtP;
 use thiserror::Error;
@@ -47,10 +52,14 @@ use crate::eval::compiler::expr::ExprLogicalBinOp;
 use crate::eval::compiler::expr_bool::ExprCompiledBool;
 use crate::eval::compiler::known::list_to_tuple;
 use crate::eval::compiler::opt_ctx::OptCtx;
+use crate::eval::compiler::scope::BindingId;
 use crate::eval::compiler::scope::Captured;
+use crate::eval::compiler::scope::ResolvedIdent;
 use crate::eval::compiler::scope::Slot;
+use crate::eval::compiler::scope::payload::CstAssignIdent;
 use crate::eval::compiler::scope::payload::CstAssignTarget;
 use crate::eval::compiler::scope::payload::CstExpr;
+use crate::eval::compiler::scope::payload::CstPayload;
 use crate::eval::compiler::scope::payload::CstStmt;
 use crate::eval::compiler::small_vec_1::SmallVec1;
 use crate::eval::compiler::span::IrSpanned;
@@ -58,8 +67,10 @@ use crate::eval::runtime::evaluator::Evaluator;
 use crate::eval::runtime::evaluator::GC_THRESHOLD;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
+use crate::eval::runtime::optimization_level::OptimizationLevel;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
+use crate::util::instant::Instant;
 use crate::values::FrozenHeap;
 use crate::values::FrozenValue;
 use crate::values::Heap;
@@ -76,6 +87,11 @@ pub(crate) enum AssignModifyLhs {
     Dot(IrSpanned<ExprCompiled>, String),
     Array(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>),
     Local(IrSpanned<LocalSlotId>),
+    /// `x += ...` where `x` is only appended to in the enclosing loop,
+    /// so it may hold a `StrAccumulator` until the loop exits.
+    LocalStrAccumulate(IrSpanned<LocalSlotId>),
+    /// `x = x + ...`, like `LocalStrAccumulate`, but never modifies `x` in place.
+    LocalStrAccumulateAdd(IrSpanned<LocalSlotId>),
     LocalCaptured(IrSpanned<LocalCapturedSlotId>),
     Module(IrSpanned<ModuleSlotId>),
 }
@@ -101,12 +117,19 @@ pub(crate) enum StmtCompiled {
     ),
     Break,
     Continue,
+    /// Replace a `StrAccumulator` in the local variable with a string.
+    StrAccumulateFinish(LocalSlotId),
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct StmtCompileContext {
     /// Current function has return type.
     pub(crate) has_return_type: bool,
+    /// Specialize the function by type feedback after it is frozen.
+    pub(crate) type_feedback: bool,
+    /// Optimize the function with an evaluator when it is frozen,
+    /// to fold calls and attributes involving frozen globals.
+    pub(crate) fold_on_freeze: bool,
 }
 
 pub(crate) struct OptimizeOnFreezeContext<'v, 'a> {
@@ -118,6 +141,15 @@ pub(crate) struct OptimizeOnFreezeContext<'v, 'a> {
     pub(crate) frozen_heap: &'a FrozenHeap,
 }
 
+/// Like `OptimizeOnFreezeContext`, but with an evaluator,
+/// so speculative-safe functions called with frozen values can be folded.
+pub(crate) struct OptimizeOnFreezeEvalContext<'v, 'a, 'e, 'x> {
+    pub(crate) module: &'x FrozenModuleData,
+    /// Evaluator of a temporary module, whose heaps are discarded after the freeze.
+    pub(crate) eval: &'x mut Evaluator<'v, 'a, 'e>,
+    pub(crate) frozen_heap: &'x FrozenHeap,
+}
+
 impl AssignModifyLhs {
     fn optimize(&self, ctx: &mut OptCtx) -> AssignModifyLhs {
         match self {
@@ -128,6 +160,8 @@ impl AssignModifyLhs {
                 AssignModifyLhs::Array(expr.optimize(ctx), index.optimize(ctx))
             }
             l @ (AssignModifyLhs::Local(..)
+            | AssignModifyLhs::LocalStrAccumulate(..)
+            | AssignModifyLhs::LocalStrAccumulateAdd(..)
             | AssignModifyLhs::LocalCaptured(..)
             | AssignModifyLhs::Module(..)) => l.clone(),
         }
@@ -168,12 +202,13 @@ impl IrSpanned<StmtCompiled> {
                 let body = body.optimize(ctx);
                 StmtsCompiled::for_stmt(span, var, over, body)
             }
-            s @ (StmtCompiled::PossibleGc | StmtCompiled::Break | StmtCompiled::Continue) => {
-                StmtsCompiled::one(IrSpanned {
-                    span,
-                    node: s.clone(),
-                })
-            }
+            s @ (StmtCompiled::PossibleGc
+            | StmtCompiled::Break
+            | StmtCompiled::Continue
+            | StmtCompiled::StrAccumulateFinish(..)) => StmtsCompiled::one(IrSpanned {
+                span,
+                node: s.clone(),
+            }),
             StmtCompiled::AssignModify(lhs, op, rhs) => StmtsCompiled::one(IrSpanned {
                 span,
                 node: StmtCompiled::AssignModify(lhs.optimize(ctx), *op, rhs.optimize(ctx)),
@@ -205,6 +240,21 @@ impl StmtsCompiled {
         self.0.as_slice()
     }
 
+    /// Block always ends with `break`, `continue` or `return`: it is terminal,
+    /// or ends with an `if` which has both branches always terminating.
+    fn always_terminates(&self) -> bool {
+        match self.last() {
+            Some(IrSpanned {
+                node: StmtCompiled::If(cond_t_f),
+                ..
+            }) => {
+                let (_, t, f) = &**cond_t_f;
+                t.always_terminates() && f.always_terminates()
+            }
+            _ => self.is_terminal(),
+        }
+    }
+
     /// Last statement in this block is `break`, `continue` or `return`.
     fn is_terminal(&self) -> bool {
         if let Some(stmt) = self.last() {
@@ -231,7 +281,7 @@ impl StmtsCompiled {
             SmallVec1::One(s) => stmts.extend(s.optimize(ctx)),
             SmallVec1::Vec(ss) => {
                 for s in ss {
-                    if stmts.is_terminal() {
+                    if stmts.is_terminal() || (ctx.aggressive && stmts.always_terminates()) {
                         break;
                     }
                     stmts.extend(s.optimize(ctx));
@@ -478,9 +528,14 @@ impl Compiler<'_, '_, '_, '_> {
                             node: LocalSlotId(slot.0),
                             span: span_lhs,
                         };
+                        let lhs = if op == AssignOp::Add && self.str_accumulator(ident).is_some() {
+                            AssignModifyLhs::LocalStrAccumulate(lhs)
+                        } else {
+                            AssignModifyLhs::Local(lhs)
+                        };
                         Ok(StmtsCompiled::one(IrSpanned {
                             span: span_stmt,
-                            node: StmtCompiled::AssignModify(AssignModifyLhs::Local(lhs), op, rhs),
+                            node: StmtCompiled::AssignModify(lhs, op, rhs),
                         }))
                     }
                     (Slot::Local(slot), Captured::Yes) => {
@@ -514,6 +569,144 @@ impl Compiler<'_, '_, '_, '_> {
             }
         }
     }
+
+    /// The slot of `ident`, if it holds a `StrAccumulator` in the loop being compiled.
+    fn str_accumulator(&self, ident: &CstAssignIdent) -> Option<LocalSlotId> {
+        let binding = ident.payload?;
+        self.str_accumulators
+            .iter()
+            .find(|a| a.binding == binding)
+            .map(|a| a.slot)
+    }
+
+    /// Find the local variables which can hold a `StrAccumulator` in the loop `body`:
+    /// those only used in `x += ...` or `x = x + ...` statements,
+    /// with a string or a variable on the right.
+    fn find_str_accumulators(&mut self, body: &CstStmt) {
+        // For each binding, whether all its uses in the loop allow an accumulator.
+        let mut bindings: SmallMap<BindingId, bool> = SmallMap::new();
+        // Reads of `x` in `x = x + ...`, which are not other uses of `x`.
+        let mut appends: Vec<Span> = Vec::new();
+        fn other_use(ident: &CstAssignIdent, bindings: &mut SmallMap<BindingId, bool>) {
+            if let Some(binding) = ident.payload {
+                bindings.insert(binding, false);
+            }
+        }
+        fn append(binding: BindingId, rhs: &CstExpr, bindings: &mut SmallMap<BindingId, bool>) {
+            let ok = bindings.entry(binding).or_insert(true);
+            *ok = *ok && is_str_expr(rhs);
+        }
+        fn visit(
+            stmt: &CstStmt,
+            bindings: &mut SmallMap<BindingId, bool>,
+            appends: &mut Vec<Span>,
+        ) {
+            match &stmt.node {
+                StmtP::AssignModify(lhs, op, rhs) => match &lhs.node {
+                    AssignTargetP::Identifier(ident) if *op == AssignOp::Add => {
+                        if let Some(binding) = ident.payload {
+                            append(binding, rhs, bindings);
+                        }
+                    }
+                    lhs => lhs.visit_lvalue(|ident| other_use(ident, bindings)),
+                },
+                StmtP::Assign(assign) => match self_append(assign) {
+                    Some((binding, read, rhs)) => {
+                        append(binding, rhs, bindings);
+                        appends.push(read);
+                    }
+                    None => assign.lhs.visit_lvalue(|ident| other_use(ident, bindings)),
+                },
+                StmtP::For(ForP { var, .. }) => {
+                    var.visit_lvalue(|ident| other_use(ident, bindings))
+                }
+                StmtP::Def(def) => {
+                    // Variables of the enclosing function used in a nested `def` are captured,
+                    // and the nested function's own variables live in a different frame.
+                    other_use(&def.name, bindings);
+                    return;
+                }
+                _ => {}
+            }
+            stmt.visit_stmt(|stmt| visit(stmt, bindings, appends));
+        }
+        visit(body, &mut bindings, &mut appends);
+        let _ = body.visit_ident(|ident| {
+            if let Some(ResolvedIdent::Slot(_, binding)) = ident.node.payload {
+                if !appends.contains(&ident.span) {
+                    bindings.insert(binding, false);
+                }
+            }
+            Ok::<_, Infallible>(())
+        });
+
+        for (binding, ok) in bindings {
+            if !ok || self.str_accumulators.iter().any(|a| a.binding == binding) {
+                continue;
+            }
+            let binding_data = self.scope_data.get_binding(binding);
+            if binding_data.captured != Captured::No {
+                continue;
+            }
+            if let Ok(Slot::Local(slot)) = binding_data.resolved_slot(&self.codemap) {
+                self.str_accumulators.push(StrAccumulatorVar {
+                    binding,
+                    slot: LocalSlotId(slot.0),
+                });
+            }
+        }
+    }
+}
+
+/// Local variable compiled to use a `StrAccumulator` in the loop being compiled.
+pub(crate) struct StrAccumulatorVar {
+    binding: BindingId,
+    slot: LocalSlotId,
+}
+
+/// `x = x + rhs` (without a type annotation): the binding of `x`,
+/// the span of the read of `x`, and `rhs`.
+fn self_append(assign: &AssignP<CstPayload>) -> Option<(BindingId, Span, &CstExpr)> {
+    let AssignP { lhs, ty: None, rhs } = assign else {
+        return None;
+    };
+    let (AssignTargetP::Identifier(ident), ExprP::Op(x, BinOp::Add, rhs)) = (&lhs.node, &rhs.node)
+    else {
+        return None;
+    };
+    match (&x.node, ident.payload) {
+        (ExprP::Identifier(x_ident), Some(binding)) => match x_ident.node.payload {
+            Some(ResolvedIdent::Slot(Slot::Local(_), x_binding)) if x_binding == binding => {
+                Some((binding, x.span, rhs))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Expression which evaluates to a string (or fails), or a variable,
+/// which usually holds a string when appended to a string.
+fn is_str_expr(expr: &CstExpr) -> bool {
+    match &expr.node {
+        ExprP::Literal(AstLiteral::String(_)) | ExprP::FString(_) | ExprP::Identifier(_) => true,
+        ExprP::Op(lhs, BinOp::Add, rhs) => is_str_expr(lhs) || is_str_expr(rhs),
+        ExprP::Op(lhs, BinOp::Percent, _) => {
+            matches!(lhs.node, ExprP::Literal(AstLiteral::String(_)))
+        }
+        ExprP::If(c_t_f) => {
+            let (_, t, f) = &**c_t_f;
+            is_str_expr(t) && is_str_expr(f)
+        }
+        ExprP::Call(fun, _) => match &fun.node {
+            ExprP::Dot(this, method) => {
+                matches!(this.node, ExprP::Literal(AstLiteral::String(_)))
+                    && matches!(method.node.as_str(), "format" | "join")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
 }
 
 // There are two requirements to perform a GC:
@@ -545,14 +738,26 @@ impl Compiler<'_, '_, '_, '_> {
 //
 // We also require that `extra_v` is None, since otherwise the user might have
 // additional values stashed somewhere.
+//
+// With a pause budget, values left unreachable by previous collections are
+// dropped here in steps, within what is left of the budget. Dropping moves
+// no live value, so it also happens in loops and calls, see
+// `Evaluator::run_infrequent_instr_checks`.
 pub(crate) fn possible_gc(eval: &mut Evaluator) {
-    if !eval.disable_gc && eval.heap().allocated_bytes() >= eval.next_gc_level {
+    if eval.disable_gc {
+        return;
+    }
+    let start = Instant::now();
+    if eval.heap().allocated_bytes() >= eval.next_gc_level {
         // When we are at a module scope (as checked above) the eval contains
         // references to all values, so walking covers everything and the unsafe
         // is satisfied.
         unsafe { eval.garbage_collect() }
         eval.next_gc_level = cmp::max(eval.heap().allocated_bytes() * 2, GC_THRESHOLD);
     }
+    if let Some(budget) = eval.gc_pause_budget {
+        eval.heap().sweep(budget.saturating_sub(start.elapsed()));
+    }
 }
 
 /// Implement lhs |= rhs, which is special in Starlark, because dicts are mutated,
@@ -639,7 +844,11 @@ pub(crate) fn add_assign<'v>(
 
 impl Compiler<'_, '_, '_, '_> {
     pub(crate) fn compile_context(&self, has_return_type: bool) -> StmtCompileContext {
-        StmtCompileContext { has_return_type }
+        StmtCompileContext {
+            has_return_type,
+            type_feedback: self.eval.optimization_level == OptimizationLevel::Aggressive,
+            fold_on_freeze: self.eval.optimization_level == OptimizationLevel::Aggressive,
+        }
     }
 
     pub(crate) fn stmt(
@@ -765,8 +974,18 @@ impl Compiler<'_, '_, '_, '_> {
                 let over = list_to_tuple(over);
                 let var = self.assign_target(var)?;
                 let over = self.expr(&over)?;
-                let st = self.stmt(body, false)?;
-                Ok(StmtsCompiled::for_stmt(span, var, over, st))
+                let outer_accumulators = self.str_accumulators.len();
+                self.find_str_accumulators(body);
+                let st = self.stmt(body, false);
+                let accumulators = self.str_accumulators.split_off(outer_accumulators);
+                let mut stmts = StmtsCompiled::for_stmt(span, var, over, st?);
+                for accumulator in accumulators {
+                    stmts.extend(StmtsCompiled::one(IrSpanned {
+                        span,
+                        node: StmtCompiled::StrAccumulateFinish(accumulator.slot),
+                    }));
+                }
+                Ok(stmts)
             }
             StmtP::Return(None) => Ok(StmtsCompiled::one(IrSpanned {
                 node: StmtCompiled::Return(IrSpanned {
@@ -795,7 +1014,22 @@ impl Compiler<'_, '_, '_, '_> {
                 Ok(r)
             }
             StmtP::Expression(e) => self.stmt_expr(e),
-            StmtP::Assign(AssignP { lhs, ty, rhs }) => {
+            StmtP::Assign(assign) => {
+                if let Some((_, _, rhs)) = self_append(assign)
+                    && let AssignTargetP::Identifier(ident) = &assign.lhs.node
+                    && let Some(slot) = self.str_accumulator(ident)
+                {
+                    let rhs = self.expr(rhs)?;
+                    let lhs = AssignModifyLhs::LocalStrAccumulateAdd(IrSpanned {
+                        node: slot,
+                        span: FrameSpan::new(FrozenFileSpan::new(self.codemap, assign.lhs.span)),
+                    });
+                    return Ok(StmtsCompiled::one(IrSpanned {
+                        span,
+                        node: StmtCompiled::AssignModify(lhs, AssignOp::Add, rhs),
+                    }));
+                }
+                let AssignP { lhs, ty, rhs } = assign;
                 let rhs = self.expr(rhs)?;
                 let ty = self.expr_for_type(ty.as_ref());
                 let lhs = self.assign_target(lhs)?;
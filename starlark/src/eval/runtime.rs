@@ -16,16 +16,26 @@
  */
 
 pub(crate) mod arguments;
+pub(crate) mod async_eval;
+#[cfg(feature = "tokio")]
+pub(crate) mod async_file_loader;
 pub(crate) mod before_stmt;
 pub(crate) mod cheap_call_stack;
+pub(crate) mod coverage;
 pub(crate) mod evaluator;
 pub(crate) mod file_loader;
 pub(crate) mod frame_span;
 pub(crate) mod frozen_file_span;
 pub(crate) mod inlined_frame;
+pub(crate) mod limits;
+pub(crate) mod observer;
+pub(crate) mod optimization_level;
 pub(crate) mod params;
 pub(crate) mod profile;
+pub(crate) mod replay;
 pub(crate) mod rust_loc;
 pub(crate) mod slots;
 pub(crate) mod small_duration;
+#[cfg(feature = "tokio")]
+pub(crate) mod tokio_runtime;
 pub(crate) mod visit_span;
@@ -26,6 +26,7 @@ use starlark_map::small_set::SmallSet;
 use starlark_syntax::value_error;
 use thiserror::Error;
 
+use crate::ErrorCode;
 use crate::cast::transmute;
 use crate::coerce::Coerce;
 use crate::coerce::coerce;
@@ -67,7 +68,31 @@ pub(crate) enum FunctionError {
 
 impl From<FunctionError> for crate::Error {
     fn from(e: FunctionError) -> Self {
+        let (code, args) = match &e {
+            FunctionError::ExtraPositionalArg { count, function } => (
+                501,
+                vec![("count", count.to_string()), ("function", function.clone())],
+            ),
+            FunctionError::ExtraNamedArg { names, function } => (
+                502,
+                vec![("names", names.join(", ")), ("function", function.clone())],
+            ),
+            FunctionError::RepeatedArg { name } => (503, vec![("name", name.clone())]),
+            FunctionError::ArgsValueIsNotString => (504, Vec::new()),
+            FunctionError::ArgsArrayIsNotIterable => (505, Vec::new()),
+            FunctionError::KwArgsIsNotDict => (506, Vec::new()),
+            FunctionError::WrongNumberOfArgs { min, max, got } => (
+                507,
+                vec![
+                    ("min", min.to_string()),
+                    ("max", max.to_string()),
+                    ("got", got.to_string()),
+                ],
+            ),
+        };
         crate::Error::new_kind(crate::ErrorKind::Function(anyhow::Error::new(e)))
+            .with_code(ErrorCode::new(code))
+            .with_message_args(args)
     }
 }
 
@@ -293,7 +318,7 @@ impl<'v, 'a> Arguments<'v, 'a> {
             Some(kwargs) => {
                 if self.0.names().names().is_empty() {
                     match kwargs.downcast_ref_key_string() {
-                        Some(kwargs) => Ok(kwargs.clone()),
+                        Some(kwargs) => Ok(kwargs.clone().into_inline()),
                         None => Err(FunctionError::ArgsValueIsNotString.into()),
                     }
                 } else {
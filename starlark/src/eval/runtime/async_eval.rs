@@ -0,0 +1,337 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Evaluation polled as a future, see [`AsyncEvaluation`].
+
+use std::cell::Cell;
+use std::future::Future;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic;
+use std::sync::atomic::AtomicBool;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::thread;
+use std::time::Duration;
+
+use starlark_syntax::syntax::module::AstModule;
+
+use crate::ErrorCode;
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::eval::runtime::evaluator::EvaluatorError;
+use crate::util::instant::Instant;
+
+/// How long evaluation runs between yield points, unless set with
+/// [`AsyncEvaluation::spawn_with_time_slice`].
+const DEFAULT_TIME_SLICE: Duration = Duration::from_millis(1);
+
+#[derive(Default)]
+struct ControlState {
+    /// Evaluation may run, because the future has been polled since it last yielded.
+    running: bool,
+    /// Waker of the last poll, woken when evaluation yields or finishes.
+    waker: Option<Waker>,
+}
+
+impl ControlState {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Shared between the future and the thread running the evaluation.
+#[derive(Default)]
+struct Control {
+    state: Mutex<ControlState>,
+    resumed: Condvar,
+    /// Set when the future is dropped.
+    cancelled: AtomicBool,
+}
+
+impl Control {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(atomic::Ordering::Acquire)
+    }
+
+    /// Wait until the future is polled, or dropped.
+    fn wait_for_poll(&self, mut state: MutexGuard<ControlState>) {
+        while !state.running && !self.is_cancelled() {
+            state = self.resumed.wait(state).unwrap();
+        }
+    }
+
+    /// Hand control back to the future, and wait until it is polled again, or dropped.
+    fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.running = false;
+        state.wake();
+        self.wait_for_poll(state);
+    }
+}
+
+/// Yield points for evaluators run by [`AsyncEvaluation`].
+pub struct YieldPoints {
+    control: Arc<Control>,
+    time_slice: Duration,
+}
+
+impl YieldPoints {
+    /// Make `eval` yield to the future every time slice, at the points it checks for
+    /// cancellation: every so many instructions, and before each native call. It also
+    /// stops with a cancellation error there once the future is dropped.
+    ///
+    /// This replaces any function set with
+    /// [`Evaluator::set_check_cancelled`](Evaluator::set_check_cancelled).
+    pub fn install(&self, eval: &mut Evaluator) {
+        let control = self.control.clone();
+        let time_slice = self.time_slice;
+        let slice_start = Cell::new(Instant::now());
+        eval.set_check_cancelled(Box::new(move || {
+            if slice_start.get().elapsed() >= time_slice {
+                control.pause();
+                slice_start.set(Instant::now());
+            }
+            control.is_cancelled()
+        }));
+        eval.set_check_cancelled_at_native_calls(true);
+    }
+}
+
+type Outcome<R> = thread::Result<crate::Result<R>>;
+
+/// An evaluation on its own thread, which only makes progress while this future is
+/// polled, so long-running scripts don't block an async executor.
+///
+/// Evaluation runs for a time slice, then yields until the next poll. Dropping the
+/// future cancels the evaluation cooperatively: it stops with an error at its next yield
+/// point, and the thread exits. A native function which blocks is not interrupted, but
+/// `Evaluator::block_on`, with the `tokio` feature, stops waiting on cancellation.
+///
+/// ```ignore
+/// let module = AsyncEvaluation::eval_module(ast, globals).await?;
+/// ```
+pub struct AsyncEvaluation<R> {
+    control: Arc<Control>,
+    outcome: Arc<Mutex<Option<Outcome<R>>>>,
+}
+
+impl<R: Send + 'static> AsyncEvaluation<R> {
+    /// Run `f` on a new thread, once the future is first polled. `f` should create its
+    /// evaluators and call [`YieldPoints::install`] on them.
+    pub fn spawn(f: impl FnOnce(&YieldPoints) -> crate::Result<R> + Send + 'static) -> Self {
+        Self::spawn_with_time_slice(DEFAULT_TIME_SLICE, f)
+    }
+
+    /// Like [`spawn`](AsyncEvaluation::spawn), but running for `time_slice` between
+    /// yields, rather than a millisecond.
+    pub fn spawn_with_time_slice(
+        time_slice: Duration,
+        f: impl FnOnce(&YieldPoints) -> crate::Result<R> + Send + 'static,
+    ) -> Self {
+        let control = Arc::new(Control::default());
+        let outcome = Arc::new(Mutex::new(None));
+        let yields = YieldPoints {
+            control: control.clone(),
+            time_slice,
+        };
+        let thread_outcome = outcome.clone();
+        thread::spawn(move || {
+            // Start lazily, like other futures.
+            yields
+                .control
+                .wait_for_poll(yields.control.state.lock().unwrap());
+            let res = if yields.control.is_cancelled() {
+                Ok(Err(crate::Error::new_other(EvaluatorError::Cancelled)
+                    .with_code(ErrorCode::CANCELLED)))
+            } else {
+                panic::catch_unwind(AssertUnwindSafe(|| f(&yields)))
+            };
+            *thread_outcome.lock().unwrap() = Some(res);
+            yields.control.state.lock().unwrap().wake();
+        });
+        AsyncEvaluation { control, outcome }
+    }
+}
+
+impl AsyncEvaluation<FrozenModule> {
+    /// Evaluate `ast` with `globals`, returning the frozen module.
+    ///
+    /// For loads, limits or other settings, use [`spawn`](AsyncEvaluation::spawn)
+    /// and configure the evaluator there.
+    pub fn eval_module(ast: AstModule, globals: Globals) -> Self {
+        Self::spawn(move |yields| {
+            Module::with_temp_heap(|module| {
+                {
+                    let mut eval = Evaluator::new(&module);
+                    yields.install(&mut eval);
+                    eval.eval_module(ast, &globals)?;
+                }
+                Ok(module.freeze()?)
+            })
+        })
+    }
+}
+
+impl<R> Future for AsyncEvaluation<R> {
+    type Output = crate::Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut state = self.control.state.lock().unwrap();
+            state.waker = Some(cx.waker().clone());
+            state.running = true;
+            self.control.resumed.notify_one();
+        }
+        match self.outcome.lock().unwrap().take() {
+            Some(Ok(res)) => Poll::Ready(res),
+            Some(Err(panic)) => panic::resume_unwind(panic),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<R> Drop for AsyncEvaluation<R> {
+    fn drop(&mut self) {
+        self.control
+            .cancelled
+            .store(true, atomic::Ordering::Release);
+        // Take the lock so the store can't race with the evaluation starting to wait.
+        let _state = self.control.state.lock().unwrap();
+        self.control.resumed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::mpsc;
+    use std::task::Context;
+    use std::task::Waker;
+    use std::time::Duration;
+
+    use starlark_syntax::syntax::module::AstModule;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::AsyncEvaluation;
+    use crate::eval::Evaluator;
+    use crate::syntax::Dialect;
+
+    fn parse(program: &str) -> AstModule {
+        AstModule::parse("test.star", program.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_eval_module() {
+        let ast = parse("x = len([i for i in range(1000)])");
+        let module = AsyncEvaluation::eval_module(ast, Globals::standard())
+            .await
+            .unwrap();
+        assert_eq!("1000", module.get("x").unwrap().value().to_str());
+    }
+
+    #[tokio::test]
+    async fn test_error() {
+        let ast = parse("fail('oops')");
+        let err = AsyncEvaluation::eval_module(ast, Globals::standard())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("oops"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_yields() {
+        let ast = parse(
+            r#"
+def loop():
+    for i in range(100000):
+        str(i)
+loop()
+"#,
+        );
+        let mut fut = pin!(AsyncEvaluation::spawn_with_time_slice(
+            Duration::ZERO,
+            move |yields| {
+                Module::with_temp_heap(|module| {
+                    let mut eval = Evaluator::new(&module);
+                    yields.install(&mut eval);
+                    eval.eval_module(ast, &Globals::standard())?;
+                    Ok(())
+                })
+            },
+        ));
+        let mut polls = 0;
+        future::poll_fn(|cx| {
+            polls += 1;
+            fut.as_mut().poll(cx)
+        })
+        .await
+        .unwrap();
+        assert!(polls > 10, "{polls}");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_on_drop() {
+        let ast = parse(
+            r#"
+def loop():
+    for i in range(1000000000):
+        str(i)
+loop()
+"#,
+        );
+        let (tx, rx) = mpsc::channel();
+        let fut = AsyncEvaluation::spawn(move |yields| {
+            let res = Module::with_temp_heap(|module| {
+                let mut eval = Evaluator::new(&module);
+                yields.install(&mut eval);
+                eval.eval_module(ast, &Globals::standard()).map(|_| ())
+            });
+            tx.send(res.as_ref().err().map(|e| e.to_string())).unwrap();
+            res
+        });
+        let res = tokio::time::timeout(Duration::from_millis(50), fut).await;
+        assert!(res.is_err());
+        let err = rx.recv_timeout(Duration::from_secs(10)).unwrap().unwrap();
+        assert!(err.contains("cancelled"), "{err}");
+    }
+
+    #[test]
+    fn test_starts_when_polled() {
+        let (tx, rx) = mpsc::channel();
+        let mut fut = pin!(AsyncEvaluation::spawn(move |_| {
+            tx.send(()).unwrap();
+            Ok(())
+        }));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        let _ = fut.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+}
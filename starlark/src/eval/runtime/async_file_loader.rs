@@ -0,0 +1,350 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! An async variant of [`FileLoader`](crate::eval::FileLoader), with the `tokio` feature,
+//! for hosts which fetch modules over the network.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Poll;
+
+use dupe::Dupe;
+use thiserror::Error;
+use tokio::sync::OnceCell;
+
+use crate::environment::FrozenModule;
+
+/// The future returned by [`AsyncFileLoader::load`].
+pub type LoadFuture<'a> = Pin<Box<dyn Future<Output = crate::Result<FrozenModule>> + Send + 'a>>;
+
+/// Async variant of [`FileLoader`](crate::eval::FileLoader), usually wrapped in a
+/// [`CachingFileLoader`].
+///
+/// Evaluation itself is synchronous, so to load a module, fetch and parse it, load the
+/// modules it depends on through `loads`, and then evaluate it with a
+/// [`ReturnFileLoader`](crate::eval::ReturnFileLoader) of those:
+///
+/// ```ignore
+/// impl AsyncFileLoader for HttpLoader {
+///     fn load<'a>(&'a self, path: &'a str, loads: &'a LoadContext<'a>) -> LoadFuture<'a> {
+///         Box::pin(async move {
+///             let source = self.fetch(path).await?;
+///             let ast = AstModule::parse(path, source, &Dialect::Standard)?;
+///             let paths: Vec<&str> = ast.loads().iter().map(|l| l.module_id).collect();
+///             let deps = loads.load_all(&paths).await?;
+///             let modules = paths.iter().copied().zip(deps.iter()).collect();
+///             Module::with_temp_heap(|module| {
+///                 let loader = ReturnFileLoader { modules: &modules };
+///                 let mut eval = Evaluator::new(&module);
+///                 eval.set_loader(&loader);
+///                 eval.eval_module(ast, &self.globals)?;
+///                 drop(eval);
+///                 module.freeze()
+///             })
+///         })
+///     }
+/// }
+/// ```
+pub trait AsyncFileLoader: Send + Sync {
+    /// Load the module given by the load statement `path`.
+    ///
+    /// Modules it depends on should be loaded with `loads`, so they are shared with the
+    /// rest of the load and cycles between them are detected.
+    fn load<'a>(&'a self, path: &'a str, loads: &'a LoadContext<'a>) -> LoadFuture<'a>;
+}
+
+#[derive(Error, Debug)]
+enum AsyncFileLoaderError {
+    #[error("Load cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Modules, loaded or being loaded.
+    modules: HashMap<String, Arc<OnceCell<FrozenModule>>>,
+    /// For each module being loaded, the modules it is waiting for.
+    waiting: HashMap<String, Vec<String>>,
+}
+
+impl CacheState {
+    /// The chain of waiting modules from `from` to `to`, if there is one.
+    fn waiting_chain(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut stack = vec![vec![from.to_owned()]];
+        let mut visited = vec![from];
+        while let Some(chain) = stack.pop() {
+            let last = chain.last().unwrap();
+            if last == to {
+                return Some(chain);
+            }
+            for next in self.waiting.get(last.as_str()).into_iter().flatten() {
+                if !visited.contains(&next.as_str()) {
+                    visited.push(next);
+                    let mut chain = chain.clone();
+                    chain.push(next.clone());
+                    stack.push(chain);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Memoizes the modules produced by an [`AsyncFileLoader`].
+///
+/// Each module is loaded at most once, even when requested concurrently, by several
+/// modules or several tasks. Loads which fail are not cached, so are retried by the next
+/// request. A module which loads itself, directly or indirectly, fails with an error
+/// showing the chain of loads, like `a.star -> b.star -> a.star`.
+pub struct CachingFileLoader<L> {
+    loader: L,
+    state: Mutex<CacheState>,
+}
+
+impl<L: AsyncFileLoader> CachingFileLoader<L> {
+    /// Cache the modules loaded by `loader`.
+    pub fn new(loader: L) -> Self {
+        CachingFileLoader {
+            loader,
+            state: Mutex::default(),
+        }
+    }
+
+    /// The wrapped loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Load `path`, or return it from the cache.
+    pub async fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        self.context(None).load(path).await
+    }
+
+    /// The module `path`, if it has already been loaded.
+    pub fn get(&self, path: &str) -> Option<FrozenModule> {
+        let state = self.state.lock().unwrap();
+        state.modules.get(path)?.get().map(|m| m.dupe())
+    }
+
+    /// Forget all loaded modules, so they are loaded again by the next request.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().modules.clear();
+    }
+
+    fn context<'a>(&'a self, importer: Option<&'a str>) -> LoadContext<'a> {
+        LoadContext {
+            loader: &self.loader,
+            state: &self.state,
+            importer,
+        }
+    }
+}
+
+/// Loads the dependencies of a module, given to [`AsyncFileLoader::load`].
+pub struct LoadContext<'a> {
+    loader: &'a dyn AsyncFileLoader,
+    state: &'a Mutex<CacheState>,
+    /// The module whose dependencies are loaded, `None` for requests from outside.
+    importer: Option<&'a str>,
+}
+
+impl<'a> LoadContext<'a> {
+    /// The module whose dependencies are loaded, `None` at the top of the load.
+    pub fn importer(&self) -> Option<&str> {
+        self.importer
+    }
+
+    /// Load the module `path`, or return it from the cache.
+    pub async fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        let cell = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(importer) = self.importer {
+                if let Some(mut cycle) = state.waiting_chain(path, importer) {
+                    cycle.push(path.to_owned());
+                    return Err(crate::Error::new_other(AsyncFileLoaderError::Cycle(cycle)));
+                }
+                state
+                    .waiting
+                    .entry(importer.to_owned())
+                    .or_default()
+                    .push(path.to_owned());
+            }
+            state.modules.entry(path.to_owned()).or_default().dupe()
+        };
+        let res = cell
+            .get_or_try_init(|| async {
+                let loads = LoadContext {
+                    loader: self.loader,
+                    state: self.state,
+                    importer: Some(path),
+                };
+                let res = self.loader.load(path, &loads).await;
+                self.state.lock().unwrap().waiting.remove(path);
+                res
+            })
+            .await
+            .map(|m| m.dupe());
+        if let Some(importer) = self.importer {
+            let mut state = self.state.lock().unwrap();
+            if let Some(waiting) = state.waiting.get_mut(importer) {
+                if let Some(i) = waiting.iter().position(|p| p == path) {
+                    waiting.swap_remove(i);
+                }
+            }
+        }
+        res
+    }
+
+    /// Load all of `paths` concurrently, returning the modules in the same order.
+    pub async fn load_all(&self, paths: &[&str]) -> crate::Result<Vec<FrozenModule>> {
+        let mut futures: Vec<_> = paths
+            .iter()
+            .map(|path| Some(Box::pin(self.load(path))))
+            .collect();
+        let mut results: Vec<Option<FrozenModule>> = vec![None; paths.len()];
+        std::future::poll_fn(|cx| {
+            let mut pending = false;
+            for (future, result) in futures.iter_mut().zip(results.iter_mut()) {
+                if let Some(f) = future {
+                    match f.as_mut().poll(cx) {
+                        Poll::Ready(Ok(m)) => {
+                            *result = Some(m);
+                            *future = None;
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Pending => pending = true,
+                    }
+                }
+            }
+            if pending {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        })
+        .await?;
+        Ok(results.into_iter().map(|m| m.unwrap()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use tokio::runtime::Runtime;
+
+    use crate::environment::FrozenModule;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::AsyncFileLoader;
+    use crate::eval::CachingFileLoader;
+    use crate::eval::Evaluator;
+    use crate::eval::LoadContext;
+    use crate::eval::LoadFuture;
+    use crate::eval::ReturnFileLoader;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    /// Evaluates modules from sources, counting the loads of each.
+    struct SourceLoader {
+        sources: HashMap<&'static str, &'static str>,
+        loaded: Mutex<Vec<String>>,
+    }
+
+    impl SourceLoader {
+        fn new(sources: &[(&'static str, &'static str)]) -> CachingFileLoader<SourceLoader> {
+            CachingFileLoader::new(SourceLoader {
+                sources: sources.iter().copied().collect(),
+                loaded: Mutex::default(),
+            })
+        }
+    }
+
+    impl AsyncFileLoader for SourceLoader {
+        fn load<'a>(&'a self, path: &'a str, loads: &'a LoadContext<'a>) -> LoadFuture<'a> {
+            Box::pin(async move {
+                self.loaded.lock().unwrap().push(path.to_owned());
+                // Give other loads a chance to run.
+                tokio::task::yield_now().await;
+                let source = self.sources[path].to_owned();
+                let ast = AstModule::parse(path, source, &Dialect::Extended)?;
+                let paths: Vec<String> =
+                    ast.loads().iter().map(|l| l.module_id.to_owned()).collect();
+                let paths: Vec<&str> = paths.iter().map(|p| p.as_str()).collect();
+                let deps = loads.load_all(&paths).await?;
+                let modules: HashMap<&str, &FrozenModule> =
+                    paths.iter().copied().zip(deps.iter()).collect();
+                Module::with_temp_heap(|module| {
+                    let loader = ReturnFileLoader { modules: &modules };
+                    let mut eval = Evaluator::new(&module);
+                    eval.set_loader(&loader);
+                    eval.eval_module(ast, &Globals::standard())?;
+                    drop(eval);
+                    Ok::<_, crate::Error>(module.freeze()?)
+                })
+            })
+        }
+    }
+
+    fn runtime() -> Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shared_dependency_loaded_once() {
+        let loader = SourceLoader::new(&[
+            (
+                "a.star",
+                "load('b.star', 'b'); load('c.star', 'c'); a = b + c",
+            ),
+            ("b.star", "load('d.star', 'd'); b = d + 1"),
+            ("c.star", "load('d.star', 'd'); c = d + 2"),
+            ("d.star", "d = 10"),
+        ]);
+        let a = runtime().block_on(loader.load("a.star")).unwrap();
+        assert_eq!(23, a.get("a").unwrap().value().unpack_i32().unwrap());
+        let mut loaded = loader.loader().loaded.lock().unwrap().clone();
+        loaded.sort();
+        assert_eq!(vec!["a.star", "b.star", "c.star", "d.star"], loaded);
+
+        assert!(loader.get("d.star").is_some());
+        runtime().block_on(loader.load("b.star")).unwrap();
+        assert_eq!(4, loader.loader().loaded.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_cycle() {
+        let loader = SourceLoader::new(&[
+            ("a.star", "load('b.star', 'b'); a = b"),
+            ("b.star", "load('c.star', 'c'); b = c"),
+            ("c.star", "load('a.star', 'a'); c = a"),
+        ]);
+        let err = runtime().block_on(loader.load("a.star")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Load cycle: a.star -> b.star -> c.star -> a.star"),
+            "{err}"
+        );
+        assert!(loader.get("a.star").is_none());
+    }
+}
@@ -44,6 +44,10 @@ struct CheapFrame<'v> {
 }
 
 impl CheapFrame<'_> {
+    fn is_empty(&self) -> bool {
+        self.function.is_none() && self.span.is_none()
+    }
+
     fn location(&self) -> Option<FileSpan> {
         self.span.map(|span| span.span.to_file_span())
     }
@@ -219,6 +223,11 @@ impl<'v> CheapCallStack<'v> {
         // The first entry is just the entire module, so skip it
         let mut frames = Vec::new();
         for frame in &self.stack[1..self.count] {
+            // `eval_function` pushes an empty frame, which is in the middle of the stack
+            // when a native function calls back into Starlark.
+            if frame.is_empty() {
+                continue;
+            }
             frame.extend_frames(&mut frames);
         }
         inlined_frames.extend_frames(&mut frames);
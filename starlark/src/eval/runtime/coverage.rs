@@ -0,0 +1,391 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Statement coverage, counting how many times each statement is executed.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use starlark_map::StarlarkHasherBuilder;
+use starlark_syntax::codemap::CodeMaps;
+use starlark_syntax::internal_error;
+
+use crate::codemap::CodeMapId;
+use crate::codemap::FileSpan;
+use crate::codemap::FileSpanRef;
+use crate::codemap::Span;
+use crate::syntax::AstModule;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+
+#[derive(Debug, thiserror::Error)]
+enum CoverageError {
+    #[error("Invalid LCOV on line {0}: `{1}`")]
+    InvalidLcov(usize, String),
+}
+
+/// Number of times each line of Starlark code was executed, obtained with
+/// [`Evaluator::gen_coverage`](crate::eval::Evaluator::gen_coverage).
+///
+/// Lines are 1-based. A line counts as executed as many times as the statement starting
+/// on it which was executed most. Lines with statements which never ran are present
+/// with a count of zero, while lines without statements are absent.
+///
+/// Coverage of several evaluations, for example of every test file, can be combined with
+/// [`merge`](Coverage::merge), including across processes by going through
+/// [LCOV](https://github.com/linux-test-project/lcov) with
+/// [`to_lcov`](Coverage::to_lcov) and [`from_lcov`](Coverage::from_lcov).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Record that `line` of `file` has a statement, executed `hits` more times.
+    pub fn add(&mut self, file: &str, line: usize, hits: u64) {
+        let lines = match self.files.get_mut(file) {
+            Some(lines) => lines,
+            None => self.files.entry(file.to_owned()).or_default(),
+        };
+        *lines.entry(line).or_default() += hits;
+    }
+
+    /// Record the statements of `module` with no executions, so a report shows those
+    /// which never ran, even in files which were never evaluated. Statements which are
+    /// compiled to nothing, `pass` and docstrings, are left out, as they are by
+    /// [`Evaluator::enable_coverage`](crate::eval::Evaluator::enable_coverage).
+    pub fn add_module(&mut self, module: &AstModule) {
+        for stmt in counted_stmts(module) {
+            self.add(stmt.filename(), stmt.resolve_span().begin.line + 1, 0);
+        }
+    }
+
+    /// Add the counts of `other` to this coverage.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (file, lines) in &other.files {
+            for (line, hits) in lines {
+                self.add(file, *line, *hits);
+            }
+        }
+    }
+
+    /// The files with coverage, sorted by name.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(|x| x.as_str())
+    }
+
+    /// The lines with statements in `file`, sorted, and how many times each was executed.
+    pub fn lines(&self, file: &str) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.files
+            .get(file)
+            .into_iter()
+            .flat_map(|lines| lines.iter().map(|(line, hits)| (*line, *hits)))
+    }
+
+    /// How many times `line` of `file` was executed, or `None` if it has no statement.
+    pub fn hits(&self, file: &str, line: usize) -> Option<u64> {
+        self.files.get(file)?.get(&line).copied()
+    }
+
+    /// Format as an LCOV tracefile, as read by `genhtml` and most coverage services.
+    pub fn to_lcov(&self) -> String {
+        let mut res = String::new();
+        for (file, lines) in &self.files {
+            writeln!(res, "TN:\nSF:{file}").unwrap();
+            for (line, hits) in lines {
+                writeln!(res, "DA:{line},{hits}").unwrap();
+            }
+            writeln!(
+                res,
+                "LF:{}\nLH:{}\nend_of_record",
+                lines.len(),
+                lines.values().filter(|hits| **hits != 0).count()
+            )
+            .unwrap();
+        }
+        res
+    }
+
+    /// Parse the line counts of an LCOV tracefile, such as one written by
+    /// [`to_lcov`](Coverage::to_lcov). Records other than `SF` and `DA` are ignored.
+    pub fn from_lcov(lcov: &str) -> anyhow::Result<Coverage> {
+        let mut res = Coverage::default();
+        let mut file = None;
+        for (i, line) in lcov.lines().enumerate() {
+            let invalid = || CoverageError::InvalidLcov(i + 1, line.to_owned());
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix("SF:") {
+                file = Some(name);
+            } else if let Some(da) = line.strip_prefix("DA:") {
+                // The optional third field is a checksum of the line.
+                let mut fields = da.split(',');
+                let (Some(file), Some(number), Some(hits)) = (file, fields.next(), fields.next())
+                else {
+                    return Err(invalid().into());
+                };
+                let (Ok(number), Ok(hits)) = (number.parse(), hits.parse()) else {
+                    return Err(invalid().into());
+                };
+                res.add(file, number, hits);
+            } else if line == "end_of_record" {
+                file = None;
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// The statements of `module` which are counted when they run, leaving out `pass` and
+/// literals such as docstrings, which are compiled to nothing.
+pub(crate) fn counted_stmts(module: &AstModule) -> Vec<FileSpan> {
+    fn go(x: &AstStmt, module: &AstModule, res: &mut Vec<FileSpan>) {
+        match &x.node {
+            Stmt::Statements(_) | Stmt::Pass => {}
+            Stmt::Expression(e) if matches!(e.node, Expr::Literal(_)) => {}
+            _ => res.push(module.file_span(x.span)),
+        }
+        x.visit_stmt(|x| go(x, module, res))
+    }
+
+    let mut res = Vec::new();
+    go(module.statement(), module, &mut res);
+    res
+}
+
+/// Counts executions of each statement when enabled with
+/// [`Evaluator::enable_coverage`](crate::eval::Evaluator::enable_coverage).
+pub(crate) struct CoverageCollector(
+    // Box because when coverage is not enabled, we want this to be small and cheap
+    Option<Box<CoverageState>>,
+);
+
+struct CoverageState {
+    files: CodeMaps,
+    /// File of the last statement, which is already in `files`.
+    last_file: Option<CodeMapId>,
+    stmts: HashMap<(CodeMapId, Span), u64, StarlarkHasherBuilder>,
+}
+
+impl CoverageCollector {
+    pub(crate) fn new() -> Self {
+        Self(None)
+    }
+
+    pub(crate) fn enable(&mut self) {
+        self.0 = Some(Box::new(CoverageState {
+            files: CodeMaps::default(),
+            last_file: None,
+            stmts: HashMap::default(),
+        }));
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Record statements with no executions, so those which never run are reported.
+    pub(crate) fn add_statements(&mut self, stmts: &[FileSpan]) {
+        if let Some(state) = &mut self.0 {
+            for stmt in stmts {
+                state.files.add(&stmt.file);
+                state.stmts.entry((stmt.file.id(), stmt.span)).or_insert(0);
+            }
+        }
+    }
+
+    pub(crate) fn before_stmt(&mut self, span: FileSpanRef) {
+        if let Some(state) = &mut self.0 {
+            let file = span.file.id();
+            if state.last_file != Some(file) {
+                state.files.add(span.file);
+                state.last_file = Some(file);
+            }
+            *state.stmts.entry((file, span.span)).or_insert(0) += 1;
+        }
+    }
+
+    pub(crate) fn r#gen(&self) -> crate::Result<Coverage> {
+        let mut res = Coverage::default();
+        let Some(state) = &self.0 else {
+            return Ok(res);
+        };
+        for ((file, span), hits) in &state.stmts {
+            let codemap = state
+                .files
+                .get(*file)
+                .ok_or_else(|| internal_error!("no file corresponding to file id"))?;
+            let line = codemap.find_line(span.begin()) + 1;
+            let max = res
+                .files
+                .entry(codemap.filename().to_owned())
+                .or_default()
+                .entry(line)
+                .or_insert(0);
+            *max = (*max).max(*hits);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Coverage;
+    use crate::eval::Evaluator;
+    use crate::eval::ReturnFileLoader;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Value;
+
+    fn coverage(program: &str) -> Coverage {
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_coverage();
+            let ast =
+                AstModule::parse("test.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            eval.gen_coverage().unwrap()
+        })
+    }
+
+    #[test]
+    fn test_coverage() {
+        let coverage = coverage(
+            r#"
+def f(x):
+    if x:
+        return 1
+    return 2
+
+for i in range(3):
+    f(True)
+"#,
+        );
+        assert_eq!(vec!["test.star"], coverage.files().collect::<Vec<_>>());
+        assert_eq!(Some(3), coverage.hits("test.star", 3));
+        assert_eq!(Some(3), coverage.hits("test.star", 4));
+        assert_eq!(Some(0), coverage.hits("test.star", 5));
+        assert_eq!(Some(3), coverage.hits("test.star", 8));
+        // Top-level statements, which are preceded by a GC point, are counted once.
+        assert_eq!(Some(1), coverage.hits("test.star", 2));
+        assert_eq!(Some(1), coverage.hits("test.star", 7));
+        // Blank line.
+        assert_eq!(None, coverage.hits("test.star", 6));
+    }
+
+    #[test]
+    fn test_coverage_uncompiled() {
+        let lib = Module::with_temp_heap(|module| {
+            module.set("x", Value::new_none());
+            module.freeze()
+        })
+        .unwrap();
+        let modules = HashMap::from([("lib.star", &lib)]);
+        let loader = ReturnFileLoader { modules: &modules };
+        let coverage = Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_coverage();
+            eval.set_loader(&loader);
+            let program = r#"
+load("lib.star", "x")
+def f():
+    """Docstring."""
+    pass
+f()
+"#;
+            let ast =
+                AstModule::parse("test.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            eval.gen_coverage().unwrap()
+        });
+        assert_eq!(Some(1), coverage.hits("test.star", 2));
+        assert_eq!(Some(1), coverage.hits("test.star", 3));
+        // No code to run.
+        assert_eq!(None, coverage.hits("test.star", 4));
+        assert_eq!(None, coverage.hits("test.star", 5));
+    }
+
+    #[test]
+    fn test_coverage_inlined() {
+        // Calls to `f` from another module would be inlined, without coverage.
+        let mut coverage = Coverage::default();
+        let lib = Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_coverage();
+            let ast = AstModule::parse(
+                "lib.star",
+                "def f():\n    return 1\n".to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            coverage.merge(&eval.gen_coverage().unwrap());
+            drop(eval);
+            module.freeze()
+        })
+        .unwrap();
+        let modules = HashMap::from([("lib.star", &lib)]);
+        let loader = ReturnFileLoader { modules: &modules };
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_coverage();
+            eval.set_loader(&loader);
+            let ast = AstModule::parse(
+                "test.star",
+                "load(\"lib.star\", \"f\")\nf()\n".to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            coverage.merge(&eval.gen_coverage().unwrap());
+        });
+        assert_eq!(Some(1), coverage.hits("lib.star", 2));
+    }
+
+    #[test]
+    fn test_coverage_not_enabled() {
+        Module::with_temp_heap(|module| {
+            let eval = Evaluator::new(&module);
+            assert!(eval.gen_coverage().is_err());
+        })
+    }
+
+    #[test]
+    fn test_merge_and_lcov() {
+        let mut a = Coverage::default();
+        a.add("b.star", 2, 0);
+        a.add("a.star", 1, 1);
+        let mut b = Coverage::default();
+        b.add("b.star", 2, 3);
+        b.add("b.star", 1, 0);
+        a.merge(&b);
+
+        let lcov = a.to_lcov();
+        assert_eq!(
+            "TN:\nSF:a.star\nDA:1,1\nLF:1\nLH:1\nend_of_record\n\
+             TN:\nSF:b.star\nDA:1,0\nDA:2,3\nLF:2\nLH:1\nend_of_record\n",
+            lcov
+        );
+        assert_eq!(a, Coverage::from_lcov(&lcov).unwrap());
+        assert!(Coverage::from_lcov("DA:1,1\n").is_err());
+        assert!(Coverage::from_lcov("SF:a.star\nDA:x,1\n").is_err());
+    }
+}
@@ -18,6 +18,10 @@
 use std::collections::HashSet;
 use std::mem;
 use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 use dupe::Dupe;
 use starlark_syntax::eval_exception::EvalException;
@@ -25,6 +29,7 @@ use starlark_syntax::frame::Frame;
 use starlark_syntax::internal_error;
 use thiserror::Error;
 
+use crate::ErrorCode;
 use crate::any::AnyLifetime;
 use crate::cast;
 use crate::codemap::FileSpan;
@@ -35,8 +40,12 @@ use crate::collections::string_pool::StringPool;
 use crate::const_frozen_string;
 use crate::environment::FrozenModuleData;
 use crate::environment::Module;
+#[cfg(feature = "fs")]
+use crate::environment::OsPolicy;
 use crate::environment::slots::ModuleSlotId;
 use crate::eval::CallStack;
+use crate::eval::EvalLimits;
+use crate::eval::EvalObserver;
 use crate::eval::FileLoader;
 use crate::eval::SoftErrorHandler;
 use crate::eval::bc::addr::BcPtrAddr;
@@ -51,8 +60,11 @@ use crate::eval::compiler::def::FrozenDef;
 use crate::eval::runtime::before_stmt::BeforeStmt;
 use crate::eval::runtime::before_stmt::BeforeStmtFunc;
 use crate::eval::runtime::cheap_call_stack::CheapCallStack;
+use crate::eval::runtime::coverage::Coverage;
+use crate::eval::runtime::coverage::CoverageCollector;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
+use crate::eval::runtime::optimization_level::OptimizationLevel;
 use crate::eval::runtime::profile::bc::BcProfile;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
@@ -64,16 +76,22 @@ use crate::eval::runtime::profile::or_instrumentation::ProfileOrInstrumentationM
 use crate::eval::runtime::profile::stmt::StmtProfile;
 use crate::eval::runtime::profile::time_flame::TimeFlameProfile;
 use crate::eval::runtime::profile::typecheck::TypecheckProfile;
+use crate::eval::runtime::replay::TraceMode;
+use crate::eval::runtime::replay::TraceRecorder;
+use crate::eval::runtime::replay::TraceReplayer;
 use crate::eval::runtime::rust_loc::rust_loc;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::eval::soft_error::HardErrorSoftErrorHandler;
+use crate::platform::platform;
 use crate::stdlib::breakpoint::BreakpointConsole;
 use crate::stdlib::breakpoint::RealBreakpointConsole;
 use crate::stdlib::extra::PrintHandler;
 use crate::stdlib::extra::StderrPrintHandler;
+use crate::util::instant::Instant;
 use crate::values::FrozenHeap;
 use crate::values::FrozenRef;
+use crate::values::FrozenValue;
 use crate::values::Heap;
 use crate::values::Trace;
 use crate::values::Tracer;
@@ -85,7 +103,7 @@ use crate::values::layout::value_captured::ValueCaptured;
 use crate::values::layout::value_captured::value_captured_get;
 
 #[derive(Error, Debug)]
-enum EvaluatorError {
+pub(crate) enum EvaluatorError {
     #[error("Profiling was not enabled")]
     ProfilingNotEnabled,
     #[error("Profile data already collected")]
@@ -106,6 +124,8 @@ enum EvaluatorError {
     ZeroCallstackSize,
     #[error("Evaluation cancelled")]
     Cancelled,
+    #[error("Evaluation deadline exceeded")]
+    DeadlineExceeded,
     #[error("Max heap size is already set")]
     HeapSizeAlreadySet,
     #[error("Max heap size cannot be zero")]
@@ -120,8 +140,12 @@ be informative."
     TickLimitAlreadySet,
     #[error("Max tick count cannot be zero")]
     ZeroTickLimit,
+    #[error("Max string size cannot be zero")]
+    ZeroStringSize,
     #[error("Execution duration limit of {0} ticks has been exceeded")]
     TickLimitExceeded(u64),
+    #[error("Function `{0}` is not deterministic, so can't be called by a hermetic evaluator")]
+    NondeterministicNative(String),
 }
 
 /// Number of bytes to allocate between GC's.
@@ -155,12 +179,18 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) verbose_gc: bool,
     // Size of the heap when we should next perform a GC.
     pub(crate) next_gc_level: usize,
+    /// If set, drop unreachable values in steps, keeping each GC pause within the budget.
+    pub(crate) gc_pause_budget: Option<Duration>,
     /// Run static typechecking of the module being evaluated.
     pub(crate) static_typechecking: bool,
+    /// Optimization level of functions compiled by this evaluator.
+    pub(crate) optimization_level: OptimizationLevel,
     // Profiling or instrumentation enabled.
     pub(crate) profile_or_instrumentation_mode: ProfileOrInstrumentationMode,
     // Used for line profiling
     stmt_profile: StmtProfile,
+    // Statement hit counts, see `enable_coverage`.
+    pub(crate) coverage: CoverageCollector,
     // Holds things that require hooking into evaluation.
     eval_instrumentation: EvaluationInstrumentation<'a, 'e>,
     // Total time spent in runtime typechecking.
@@ -193,10 +223,28 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) call_stack: CheapCallStack<'v>,
     /// Function to check if evaluation should be cancelled early
     pub(crate) is_cancelled: Box<dyn Fn() -> bool + 'a>,
+    /// Also run the infrequent checks, including `is_cancelled`, before each native call.
+    pub(crate) check_at_native_calls: bool,
+    /// Receives events of the evaluation, see `set_observer`.
+    pub(crate) observer: Option<&'a dyn EvalObserver>,
+    /// Records or replays the native calls, see `set_recorder` and `set_replayer`.
+    pub(crate) trace: Option<TraceMode<'a>>,
+    /// Time after which evaluation fails, checked with `is_cancelled`.
+    pub(crate) deadline: Option<Instant>,
+    /// Flag set by another thread to cancel evaluation, checked with `is_cancelled`.
+    pub(crate) cancellation: Option<Arc<AtomicBool>>,
     /// A counter to track when to perform "infrequent" checks like cancellation, timeouts, etc
     pub(crate) infrequent_instr_check_counter: u32,
     /// Total number of ticks executed so far
     pub(crate) total_tick_count_at_last_infrequent_check: u64,
+    /// Only call deterministic native functions, see `set_hermetic`.
+    pub(crate) hermetic: bool,
+    /// What the `os` library extension may access.
+    #[cfg(feature = "fs")]
+    pub(crate) os_policy: Option<OsPolicy>,
+    /// Runtime used by native functions to await host futures.
+    #[cfg(feature = "tokio")]
+    pub(crate) tokio_handle: Option<tokio::runtime::Handle>,
 }
 
 // We use this to validate that the Evaluator lifetimes have the expected variance.
@@ -217,6 +265,8 @@ fn _check_variance() {
         let _: &&'a2 (dyn PrintHandler + 'a2) = &a.print_handler;
         let _: &&'a2 (dyn SoftErrorHandler + 'a2) = &a.soft_error_handler;
         let _: &Box<dyn Fn() -> bool + 'a2> = &a.is_cancelled;
+        let _: &Option<&'a2 dyn EvalObserver> = &a.observer;
+        let _: &Option<TraceMode<'a2>> = &a.trace;
         let _: &Evaluator<'v, 'a2, 'e> = &a;
     }
 }
@@ -274,11 +324,13 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             extra: None,
             extra_mut: None,
             next_gc_level: GC_THRESHOLD,
+            gc_pause_budget: None,
             disable_gc: false,
             alloca: Alloca::new(),
             profile_or_instrumentation_mode: ProfileOrInstrumentationMode::None,
             heap_profile: HeapProfile::new(),
             stmt_profile: StmtProfile::new(),
+            coverage: CoverageCollector::new(),
             typecheck_profile: TypecheckProfile::default(),
             time_flame_profile: TimeFlameProfile::new(),
             eval_instrumentation: EvaluationInstrumentation::new(),
@@ -289,12 +341,23 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             soft_error_handler: &HardErrorSoftErrorHandler,
             verbose_gc: false,
             static_typechecking: false,
+            optimization_level: OptimizationLevel::Default,
             max_callstack_size: None,
             max_heap_size: None,
             max_tick_count: None,
             is_cancelled: Box::new(|| false),
+            check_at_native_calls: false,
+            observer: None,
+            trace: None,
+            deadline: None,
+            cancellation: None,
             infrequent_instr_check_counter: 0,
             total_tick_count_at_last_infrequent_check: 0,
+            hermetic: false,
+            #[cfg(feature = "fs")]
+            os_policy: None,
+            #[cfg(feature = "tokio")]
+            tokio_handle: None,
         }
     }
 
@@ -310,6 +373,22 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.verbose_gc = true;
     }
 
+    /// Limit the time spent in each garbage collection to about `budget`,
+    /// for hosts which can't afford long pauses.
+    ///
+    /// A collection copies the reachable values, then drops the unreachable ones.
+    /// With a budget, dropping is split in steps, each running until the budget is used.
+    /// Steps run before statements at the top level of a module, and every thousand or so
+    /// loop iterations and calls, so values are also dropped while a long function runs.
+    ///
+    /// The budget bounds the dropping steps only. Collections still happen only before
+    /// top-level statements, because only there are all the roots known, and copying the
+    /// reachable values is not split, so a pause can still exceed the budget when many
+    /// values are reachable.
+    pub fn set_gc_pause_budget(&mut self, budget: Duration) {
+        self.gc_pause_budget = Some(budget);
+    }
+
     /// Enable static typechecking. For example:
     ///
     /// ```python
@@ -321,6 +400,18 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.static_typechecking = enable;
     }
 
+    /// Set the optimization level of functions declared in modules evaluated from now on.
+    pub fn set_optimization_level(&mut self, level: OptimizationLevel) {
+        self.optimization_level = level;
+    }
+
+    /// Intern strings of at most `max_len` bytes created by the evaluation, so equal short
+    /// strings share a single value, see [`Heap::set_intern_strings`]. Reduces the memory
+    /// of programs creating many duplicate short strings, at the cost of a lookup for each.
+    pub fn set_intern_strings(&mut self, max_len: Option<usize>) {
+        self.heap().set_intern_strings(max_len);
+    }
+
     /// Set the [`FileLoader`] used to resolve `load()` statements.
     /// A list of all load statements can be obtained through
     /// [`AstModule::loads`](crate::syntax::AstModule::loads).
@@ -451,6 +542,37 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
     }
 
+    /// Count how many times each statement is executed, allowing
+    /// [`Evaluator::gen_coverage`] to be used.
+    ///
+    /// Unlike the [coverage profile](ProfileMode::Coverage), this only counts statements,
+    /// so is cheap enough to leave on when running tests, and can be combined with
+    /// profiling. Statements are only counted in code compiled after this call, so call it
+    /// before evaluating the module. Functions compiled with coverage enabled are never
+    /// inlined into their callers, so their statements are counted however they are called.
+    pub fn enable_coverage(&mut self) {
+        if self.coverage.enabled() {
+            return;
+        }
+        self.coverage.enable();
+        self.before_stmt_fn(&|span, continued, eval| {
+            if !continued {
+                eval.coverage.before_stmt(span);
+            }
+        });
+    }
+
+    /// Get the statement coverage of the modules evaluated so far.
+    ///
+    /// Only valid if [`Evaluator::enable_coverage`] was called. Like
+    /// [`coverage`](Evaluator::coverage), the optimizer may remove some statements.
+    pub fn gen_coverage(&self) -> crate::Result<Coverage> {
+        if !self.coverage.enabled() {
+            return Err(crate::Error::new_other(EvaluatorError::CoverageNotEnabled));
+        }
+        self.coverage.r#gen()
+    }
+
     /// Enable interactive `breakpoint()`. When enabled, `breakpoint()`
     /// reads commands from stdin and write to stdout.
     /// When disabled (default), `breakpoint()` function results in error.
@@ -511,16 +633,131 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.print_handler = handler;
     }
 
+    /// Print `text` with the print handler, recording it when recording a trace.
+    pub(crate) fn println(&self, text: &str) -> crate::Result<()> {
+        if let Some(trace) = self.trace {
+            trace.print(text);
+        }
+        self.print_handler.println(text)
+    }
+
     /// Set deprecation handler. If not set, deprecations are treated as hard errors.
     pub fn set_soft_error_handler(&mut self, handler: &'a (dyn SoftErrorHandler + 'a)) {
         self.soft_error_handler = handler;
     }
 
+    /// Only allow calls to native functions and methods which are deterministic: those
+    /// marked `#[starlark(deterministic)]` or `#[starlark(speculative_exec_safe)]`, which
+    /// includes the core builtins. Calling any other native, like those of the
+    /// [`Os`](crate::environment::LibraryExtension::Os) extension or `debug`, fails,
+    /// as does calling a custom value whose
+    /// [`StarlarkValue::is_deterministic`](crate::values::StarlarkValue::is_deterministic)
+    /// is false. Off by default.
+    ///
+    /// Evaluate with [`Globals::hermetic`](crate::environment::Globals::hermetic)
+    /// so that the nondeterministic builtins are not bound at all.
+    ///
+    /// Iteration order and hashing don't need this: dicts and sets iterate in insertion
+    /// order, and hashes are not seeded, so they are the same on every run.
+    pub fn set_hermetic(&mut self, hermetic: bool) {
+        self.hermetic = hermetic;
+    }
+
+    /// Fail if the evaluator is hermetic and the native `name` being called is not
+    /// deterministic.
+    #[inline]
+    pub(crate) fn check_deterministic(&self, deterministic: bool, name: &str) -> crate::Result<()> {
+        if self.hermetic && !deterministic {
+            return Err(crate::Error::new_other(
+                EvaluatorError::NondeterministicNative(name.to_owned()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail if the evaluator is hermetic and calling `function` is not deterministic.
+    #[inline]
+    pub(crate) fn check_deterministic_value(&self, function: Value<'v>) -> crate::Result<()> {
+        if self.hermetic && !function.get_ref().is_deterministic() {
+            return Err(crate::Error::new_other(
+                EvaluatorError::NondeterministicNative(function.name_for_call_stack()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Set what the functions of the [`Os`](crate::environment::LibraryExtension::Os)
+    /// library extension may access. Without a policy they fail.
+    #[cfg(feature = "fs")]
+    pub fn set_os_policy(&mut self, policy: OsPolicy) {
+        self.os_policy = Some(policy);
+    }
+
     /// Set canceled-checking function. This function is called periodically to check if the evaluator should return early (with an error condition).
     pub fn set_check_cancelled(&mut self, is_canceled: Box<dyn Fn() -> bool + 'a>) {
         self.is_cancelled = is_canceled
     }
 
+    /// Report function calls, statements and garbage collections to `observer`.
+    ///
+    /// Statements are only reported for code compiled after this call, so call it before
+    /// evaluating the module.
+    pub fn set_observer(&mut self, observer: &'a dyn EvalObserver) {
+        self.observer = Some(observer);
+        self.before_stmt_fn(&|span, continued, eval| {
+            if let (Some(observer), false) = (eval.observer, continued) {
+                observer.before_stmt(span);
+            }
+        });
+    }
+
+    /// Record in `recorder` the results of native function calls, the modules loaded and
+    /// the lines printed, to replay the evaluation later with
+    /// [`set_replayer`](Evaluator::set_replayer).
+    pub fn set_recorder(&mut self, recorder: &'a TraceRecorder) {
+        self.trace = Some(TraceMode::Record(recorder));
+    }
+
+    /// Replay an evaluation recorded with [`set_recorder`](Evaluator::set_recorder), using the
+    /// results of native function calls from the trace rather than the host.
+    /// Evaluate the same code with the same loader, and call
+    /// [`TraceReplayer::finish`] afterwards to check the whole trace was replayed.
+    pub fn set_replayer(&mut self, replayer: &'a TraceReplayer) {
+        self.trace = Some(TraceMode::Replay(replayer));
+    }
+
+    /// Fail the evaluation with error code `E0606` once `deadline` has passed.
+    ///
+    /// Like the function given to [`set_check_cancelled`](Evaluator::set_check_cancelled),
+    /// the deadline is checked every so many function calls and loop iterations, so evaluation
+    /// stops shortly after the deadline unless it is blocked in a native function.
+    ///
+    /// Reads the system clock, so use [`set_timeout`](Evaluator::set_timeout) on targets
+    /// without one.
+    pub fn set_deadline(&mut self, deadline: std::time::Instant) {
+        self.set_timeout(deadline.saturating_duration_since(std::time::Instant::now()));
+    }
+
+    /// Fail the evaluation with error code `E0606` once `timeout` has passed, measured
+    /// by the clock of the [`Platform`](crate::platform::Platform). Checked like the
+    /// [deadline](Evaluator::set_deadline).
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+
+    /// Fail the evaluation with error code `E0604` once `cancelled` is set to `true`,
+    /// typically from another thread. Checked like the [deadline](Evaluator::set_deadline).
+    pub fn set_cancellation(&mut self, cancelled: Arc<AtomicBool>) {
+        self.cancellation = Some(cancelled);
+    }
+
+    /// Also call the function given to [`set_check_cancelled`](Evaluator::set_check_cancelled),
+    /// and check the heap and tick limits, before each call to a native function or method,
+    /// rather than only every so many instructions. Off by default.
+    pub fn set_check_cancelled_at_native_calls(&mut self, enabled: bool) {
+        self.check_at_native_calls = enabled;
+    }
+
     /// Called to add an entry to the call stack, by the function being invoked.
     /// Called for all types of function, including those written in Rust.
     #[inline(always)]
@@ -539,12 +776,48 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
 
         self.call_stack.push(function, span)?;
+        let observed = self.observer.map(|observer| {
+            observer.enter_function(function, span.map(|s| s.span.file_span_ref()));
+            (observer, self.heap().allocated_bytes())
+        });
         // Must always call .pop regardless
         let res = within(self).map_err(|e| add_diagnostics(e, self));
         self.call_stack.pop();
+        if let Some((observer, allocated_before)) = observed {
+            let allocated = self
+                .heap()
+                .allocated_bytes()
+                .saturating_sub(allocated_before);
+            observer.exit_function(function, allocated);
+        }
         res
     }
 
+    /// Run `within`, which implements a call to the builtin `function` without calling it,
+    /// like the instruction for `len(x)`, reporting the call to the observer if any.
+    #[inline(always)]
+    pub(crate) fn observe_builtin<R>(
+        &mut self,
+        function: FrozenValue,
+        within: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        match self.observer {
+            None => within(self),
+            Some(observer) => {
+                let function = function.to_value();
+                observer.enter_function(function, None);
+                let allocated_before = self.heap().allocated_bytes();
+                let res = within(self);
+                let allocated = self
+                    .heap()
+                    .allocated_bytes()
+                    .saturating_sub(allocated_before);
+                observer.exit_function(function, allocated);
+                res
+            }
+        }
+    }
+
     /// The active heap where [`Value`]s are allocated.
     pub fn heap(&self) -> Heap<'v> {
         self.module_env.heap()
@@ -582,6 +855,7 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             crate::Error::new_other(EvaluatorError::LocalVariableReferencedBeforeAssignment(
                 name,
             ))
+            .with_code(ErrorCode::new(303))
         }
 
         match self.top_frame_def_frozen_module(false)? {
@@ -604,6 +878,7 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         crate::Error::new_other(EvaluatorError::LocalVariableReferencedBeforeAssignment(
             name,
         ))
+        .with_code(ErrorCode::new(303))
     }
 
     #[inline(always)]
@@ -800,12 +1075,21 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
     /// and using them will lead to a segfault.
     /// Do not call during Starlark evaluation.
     pub unsafe fn garbage_collect(&mut self) {
+        let allocated_bytes_before = self.heap().allocated_bytes();
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "starlark::gc",
+            allocated_bytes_before,
+            allocated_bytes_after = tracing::field::Empty
+        )
+        .entered();
+
         unsafe {
             if self.verbose_gc {
-                eprintln!(
-                    "Starlark: allocated bytes: {}, starting GC...",
+                platform().write_stderr(&format!(
+                    "Starlark: allocated bytes: {}, starting GC...\n",
                     self.heap().allocated_bytes()
-                );
+                ));
             }
 
             self.stmt_profile
@@ -825,16 +1109,17 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             // the end of the closure. Once we regain control we record the
             // matching exit, which covers the time it took to drop the old
             // heap.
-            self.heap().garbage_collect(|tracer| {
-                self.trace(tracer);
-
-                // See above, this enter begins right as our closure ends, and
-                // will catch the implicit drop of the old arena as the
-                // self.heap() lets it auto-drop on return from the
-                // .garbage_collect()
-                self.time_flame_profile
-                    .record_call_enter(const_frozen_string!("cleanup").to_value());
-            });
+            self.heap()
+                .garbage_collect(self.gc_pause_budget.is_some(), |tracer| {
+                    self.trace(tracer);
+
+                    // See above, this enter begins right as our closure ends, and
+                    // will catch the implicit drop of the old arena as the
+                    // self.heap() lets it auto-drop on return from the
+                    // .garbage_collect()
+                    self.time_flame_profile
+                        .record_call_enter(const_frozen_string!("cleanup").to_value());
+                });
             // This exists the "cleanup" in the closure above
             self.time_flame_profile.record_call_exit();
 
@@ -842,12 +1127,19 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             self.time_flame_profile.record_call_exit();
 
             if self.verbose_gc {
-                eprintln!(
-                    "Starlark: GC complete. Allocated bytes: {}.",
+                platform().write_stderr(&format!(
+                    "Starlark: GC complete. Allocated bytes: {}.\n",
                     self.heap().allocated_bytes()
-                );
+                ));
             }
         }
+
+        if let Some(observer) = self.observer {
+            observer.garbage_collected(allocated_bytes_before, self.heap().allocated_bytes());
+        }
+
+        #[cfg(feature = "tracing")]
+        span.record("allocated_bytes_after", self.heap().allocated_bytes());
     }
 
     /// Note that the `Drop` for the `T` will not be called. That's safe if there is no `Drop`,
@@ -943,6 +1235,38 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
     }
 
+    /// Set all the resource limits of this evaluation at once, see [`EvalLimits`].
+    ///
+    /// Fails if any of the limits is zero or was already set.
+    pub fn set_limits(&mut self, limits: EvalLimits) -> anyhow::Result<()> {
+        if let Some(max_call_depth) = limits.max_call_depth {
+            self.set_max_callstack_size(max_call_depth)?;
+        }
+        if let Some(max_heap_bytes) = limits.max_heap_bytes {
+            self.set_max_heap_size(max_heap_bytes)?;
+        }
+        if let Some(max_steps) = limits.max_steps {
+            self.set_max_tick_count(max_steps)?;
+        }
+        if let Some(max_string_bytes) = limits.max_string_bytes {
+            if max_string_bytes == 0 {
+                return Err(EvaluatorError::ZeroStringSize.into());
+            }
+            self.heap().set_max_string_size(Some(max_string_bytes));
+        }
+        Ok(())
+    }
+
+    /// The resource limits of this evaluation.
+    pub fn limits(&self) -> EvalLimits {
+        EvalLimits {
+            max_heap_bytes: self.max_heap_size,
+            max_steps: self.max_tick_count,
+            max_call_depth: self.max_callstack_size,
+            max_string_bytes: self.heap().max_string_size(),
+        }
+    }
+
     /// Sets max call stack size.
     /// Stack allocation will happen on entry point of evaluation if not allocated yet.
     pub fn set_max_callstack_size(&mut self, stack_size: usize) -> anyhow::Result<()> {
@@ -1008,9 +1332,10 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         let current = self.heap().peak_allocated_bytes() + self.frozen_heap().allocated_bytes();
 
         if current > limit {
-            Some(ResourceCheckResult::Exceeded(crate::Error::new_other(
-                EvaluatorError::HeapLimitExceeded(limit),
-            )))
+            Some(ResourceCheckResult::Exceeded(
+                crate::Error::new_other(EvaluatorError::HeapLimitExceeded(limit))
+                    .with_code(ErrorCode::HEAP_LIMIT),
+            ))
         } else if current > (limit / 2) {
             Some(ResourceCheckResult::Warn {
                 usage: current as u64,
@@ -1063,9 +1388,10 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         let current = self.get_total_tick_count();
 
         if current > limit {
-            Some(ResourceCheckResult::Exceeded(crate::Error::new_other(
-                EvaluatorError::TickLimitExceeded(limit),
-            )))
+            Some(ResourceCheckResult::Exceeded(
+                crate::Error::new_other(EvaluatorError::TickLimitExceeded(limit))
+                    .with_code(ErrorCode::TICK_LIMIT),
+            ))
         } else if current > (limit / 2) {
             Some(ResourceCheckResult::Warn {
                 usage: current,
@@ -1099,9 +1425,30 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         Ok(())
     }
 
+    #[inline(always)]
+    pub(crate) fn before_native_call(&mut self) -> crate::Result<()> {
+        if self.check_at_native_calls {
+            self.run_infrequent_instr_checks()
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn run_infrequent_instr_checks(&mut self) -> crate::Result<()> {
-        if (self.is_cancelled)() {
-            return Err(crate::Error::new_other(EvaluatorError::Cancelled));
+        let cancelled = self
+            .cancellation
+            .as_ref()
+            .is_some_and(|c| c.load(atomic::Ordering::Relaxed));
+        if cancelled || (self.is_cancelled)() {
+            return Err(
+                crate::Error::new_other(EvaluatorError::Cancelled).with_code(ErrorCode::CANCELLED)
+            );
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(crate::Error::new_other(EvaluatorError::DeadlineExceeded)
+                    .with_code(ErrorCode::DEADLINE_EXCEEDED));
+            }
         }
         if let Some(ResourceCheckResult::Exceeded(e)) = self.check_heap_size_limit() {
             return Err(e);
@@ -1109,6 +1456,11 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         if let Some(ResourceCheckResult::Exceeded(e)) = self.check_tick_count_limit() {
             return Err(e);
         }
+        // Unlike collecting, dropping unreachable values moves no live value,
+        // so it is possible here, in loops and function calls.
+        if let Some(budget) = self.gc_pause_budget {
+            self.heap().sweep(budget);
+        }
         Ok(())
     }
 }
@@ -1219,8 +1571,16 @@ pub(crate) fn before_stmt(
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use crate::ErrorClass;
+    use crate::ErrorCode;
     use crate::environment::Globals;
     use crate::environment::Module;
+    use crate::eval::EvalLimits;
     use crate::eval::Evaluator;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
@@ -1301,4 +1661,68 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_limits() {
+        let globals = Globals::standard();
+        let eval_with_limits = |program: &str| {
+            Module::with_temp_heap(|module| {
+                let mut eval = Evaluator::new(&module);
+                eval.set_limits(EvalLimits {
+                    max_call_depth: Some(20),
+                    max_string_bytes: Some(1000),
+                    ..EvalLimits::default()
+                })
+                .unwrap();
+                assert_eq!(Some(1000), eval.limits().max_string_bytes);
+                let ast =
+                    AstModule::parse("test.bzl", program.to_owned(), &Dialect::Standard).unwrap();
+                eval.eval_module(ast, &globals).map(|_| ())
+            })
+        };
+
+        eval_with_limits("x = 'x' * 1000 + ''").unwrap();
+        for program in [
+            "x = 'x' * 1000000",
+            "x = 'x' * 1000 + 'y'",
+            "x = ''.join(['x'] * 1001)",
+            "x = ('x' * 600).replace('x', 'yy')",
+        ] {
+            let err = eval_with_limits(program).unwrap_err();
+            assert_eq!(ErrorCode::STRING_LIMIT, err.code(), "{program}");
+            assert_eq!(ErrorClass::ResourceExhausted, err.class());
+        }
+
+        let err = eval_with_limits("def f(n): return f(n + 1)\nf(0)").unwrap_err();
+        assert_eq!(ErrorClass::ResourceExhausted, err.class());
+    }
+
+    #[test]
+    fn test_deadline_and_cancellation() {
+        let globals = Globals::standard();
+        let program = "def loop():\n    for i in range(1000000):\n        pass\nloop()";
+        let eval_with = |f: &dyn Fn(&mut Evaluator)| {
+            Module::with_temp_heap(|module| {
+                let mut eval = Evaluator::new(&module);
+                f(&mut eval);
+                let ast =
+                    AstModule::parse("test.bzl", program.to_owned(), &Dialect::Standard).unwrap();
+                eval.eval_module(ast, &globals).map(|_| ())
+            })
+        };
+
+        let err = eval_with(&|eval| eval.set_deadline(Instant::now())).unwrap_err();
+        assert_eq!(ErrorCode::DEADLINE_EXCEEDED, err.code());
+        assert_eq!(ErrorClass::Cancelled, err.class());
+
+        let err = eval_with(&|eval| eval.set_timeout(Duration::ZERO)).unwrap_err();
+        assert_eq!(ErrorCode::DEADLINE_EXCEEDED, err.code());
+        assert_eq!(ErrorClass::Cancelled, err.class());
+
+        let err =
+            eval_with(&|eval| eval.set_cancellation(Arc::new(AtomicBool::new(true)))).unwrap_err();
+        assert_eq!(ErrorCode::CANCELLED, err.code());
+
+        eval_with(&|eval| eval.set_cancellation(Arc::new(AtomicBool::new(false)))).unwrap();
+    }
 }
@@ -0,0 +1,57 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+/// Limits on the resources used by an evaluation, for evaluating untrusted code.
+///
+/// Set with [`Evaluator::set_limits`](crate::eval::Evaluator::set_limits). When a limit is
+/// exceeded, the evaluation fails with an error whose [`class`](crate::Error::class) is
+/// [`ErrorClass::ResourceExhausted`](crate::ErrorClass::ResourceExhausted), and whose
+/// [`code`](crate::Error::code) says which limit it was.
+///
+/// ```
+/// # use starlark::eval::EvalLimits;
+/// let limits = EvalLimits {
+///     max_heap_bytes: Some(100 << 20),
+///     max_steps: Some(10_000_000),
+///     ..EvalLimits::default()
+/// };
+/// ```
+#[derive(Debug, Default, Copy, Clone, Dupe, Eq, PartialEq)]
+pub struct EvalLimits {
+    /// Maximum bytes allocated on the Starlark heaps, see
+    /// [`Evaluator::set_max_heap_size`](crate::eval::Evaluator::set_max_heap_size) for how
+    /// precisely it is enforced. Fails with `E0602`.
+    pub max_heap_bytes: Option<usize>,
+    /// Maximum number of steps, which are function calls and loop iterations, see
+    /// [`Evaluator::set_max_tick_count`](crate::eval::Evaluator::set_max_tick_count).
+    /// Fails with `E0603`.
+    pub max_steps: Option<u64>,
+    /// Maximum depth of Starlark calls. Fails with `E0601`.
+    pub max_call_depth: Option<usize>,
+    /// Maximum size in bytes of a string or bytes value created by concatenation, repetition,
+    /// `join` or `replace`, checked before the value is allocated. Fails with `E0605`.
+    pub max_string_bytes: Option<usize>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("String of {size} bytes exceeds the limit of {limit} bytes")]
+pub(crate) struct StringSizeLimitExceeded {
+    pub(crate) size: usize,
+    pub(crate) limit: usize,
+}
@@ -0,0 +1,152 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Hooks for observing evaluation, for tracing and profiling by the host.
+
+#[cfg(feature = "tracing")]
+use std::cell::RefCell;
+
+use crate::codemap::FileSpanRef;
+use crate::values::Value;
+
+/// Receives events as an [`Evaluator`](crate::eval::Evaluator) runs, registered with
+/// [`Evaluator::set_observer`](crate::eval::Evaluator::set_observer).
+///
+/// All the methods do nothing by default. They are called on the evaluating thread, while
+/// evaluation waits, so should be cheap.
+pub trait EvalObserver {
+    /// A function, of any kind, is about to be called from `call_site`, if it is known.
+    fn enter_function(&self, _function: Value<'_>, _call_site: Option<FileSpanRef>) {}
+
+    /// The call to `function` matching the last `enter_function` returned or failed, having
+    /// allocated `allocated_bytes` on the heap, including in the functions it called.
+    fn exit_function(&self, _function: Value<'_>, _allocated_bytes: usize) {}
+
+    /// A statement is about to be executed. Only called for code compiled after the observer was
+    /// set, so set it before evaluating the module.
+    fn before_stmt(&self, _span: FileSpanRef) {}
+
+    /// A garbage collection reduced the heap from `before_bytes` to `after_bytes`.
+    fn garbage_collected(&self, _before_bytes: usize, _after_bytes: usize) {}
+}
+
+/// An [`EvalObserver`] which makes a `tracing` span for each call, named `starlark::call`,
+/// with the function name, the call site and the bytes allocated during the call.
+///
+/// The spans are at debug level and nest like the Starlark call stack, so are suitable for
+/// flame graphs and distributed traces.
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+pub struct TracingObserver {
+    spans: RefCell<Vec<tracing::span::EnteredSpan>>,
+}
+
+#[cfg(feature = "tracing")]
+impl TracingObserver {
+    /// Create a new observer, with no spans entered.
+    pub fn new() -> TracingObserver {
+        TracingObserver::default()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl EvalObserver for TracingObserver {
+    fn enter_function(&self, function: Value<'_>, call_site: Option<FileSpanRef>) {
+        let span = tracing::debug_span!(
+            "starlark::call",
+            function = %function.name_for_call_stack(),
+            call_site = tracing::field::Empty,
+            allocated_bytes = tracing::field::Empty
+        );
+        if let Some(call_site) = call_site {
+            span.record("call_site", tracing::field::display(call_site));
+        }
+        self.spans.borrow_mut().push(span.entered());
+    }
+
+    fn exit_function(&self, _function: Value<'_>, allocated_bytes: usize) {
+        if let Some(span) = self.spans.borrow_mut().pop() {
+            span.record("allocated_bytes", allocated_bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::codemap::FileSpanRef;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::EvalObserver;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Value;
+
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<String>>);
+
+    impl EvalObserver for Recorder {
+        fn enter_function(&self, function: Value<'_>, _call_site: Option<FileSpanRef>) {
+            self.0
+                .borrow_mut()
+                .push(format!("enter {}", function.name_for_call_stack()));
+        }
+
+        fn exit_function(&self, function: Value<'_>, _allocated_bytes: usize) {
+            self.0
+                .borrow_mut()
+                .push(format!("exit {}", function.name_for_call_stack()));
+        }
+
+        fn before_stmt(&self, span: FileSpanRef) {
+            self.0
+                .borrow_mut()
+                .push(format!("stmt {}", span.resolve_span().begin.line));
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let recorder = Recorder::default();
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.set_observer(&recorder);
+            let ast = AstModule::parse(
+                "test.bzl",
+                "def f(x):\n    return len(x)\nf([1])\n".to_owned(),
+                &Dialect::Standard,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            crate::Result::Ok(())
+        })
+        .unwrap();
+        let events = recorder.0.borrow();
+        let calls: Vec<&str> = events
+            .iter()
+            .map(|x| x.as_str())
+            .filter(|x| !x.starts_with("stmt"))
+            .collect();
+        assert_eq!(vec!["enter f", "enter len", "exit len", "exit f"], calls);
+        // The statement in `f`, on the second line, runs within the call to `f`.
+        let position = |x: &str| events.iter().position(|e| e == x).unwrap();
+        assert!(position("enter f") < position("stmt 1"));
+        assert!(position("stmt 1") < position("enter len"));
+    }
+}
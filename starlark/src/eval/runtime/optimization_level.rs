@@ -0,0 +1,49 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+/// How much work the compiler does to make functions faster.
+///
+/// Set with [`Evaluator::set_optimization_level`](crate::eval::Evaluator::set_optimization_level)
+/// before evaluating the module which declares the functions.
+#[derive(Debug, Default, Copy, Clone, Dupe, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum OptimizationLevel {
+    /// Optimizations which are cheap and never slow down the code.
+    #[default]
+    Default,
+    /// Also specialize frozen functions by type feedback: the first calls of a function
+    /// record the types of operands of arithmetic, comparisons and `for` loops,
+    /// then the function is compiled again with instructions specialized for the
+    /// types observed (for example, `int` arithmetic or iteration over lists).
+    ///
+    /// Specialized instructions check their operands and fall back to the generic
+    /// operation, so the results are the same, but the first calls are slower.
+    ///
+    /// When a function is frozen, its body is also optimized with the values of the
+    /// frozen globals, like `Default` does, and in addition:
+    ///
+    /// * calls of functions safe to evaluate speculatively with constant arguments,
+    ///   like `CONFIG.get("key")` or `" ".join(NAMES)`, are evaluated once,
+    ///   so `if` statements on them keep only the branch taken;
+    /// * attributes of constants which are safe to evaluate, like the `index` of an
+    ///   enumeration value, are read once, rather than on each iteration of a loop;
+    /// * statements following an `if` whose branches all `return`, `break` or
+    ///   `continue` are removed.
+    Aggressive,
+}
@@ -95,6 +95,17 @@ pub(crate) enum ParameterKind<V> {
     KWargs,
 }
 
+/// Where a parameter takes its value from, when a call is bound at compile time.
+#[derive(Debug, Copy, Clone, Dupe, PartialEq)]
+pub(crate) enum StaticParam<V> {
+    /// Argument at this index, counting positional arguments then named.
+    Arg(u32),
+    /// Parameter is not passed, take its default value.
+    Default(V),
+    /// Optional parameter is not passed, the slot remains `None`.
+    Unset,
+}
+
 #[derive(Debug, Copy, Clone, Dupe, PartialEq, Eq, PartialOrd, Ord)]
 enum CurrentParameterStyle {
     /// Parameter can be only filled positionally.
@@ -551,6 +562,43 @@ impl<'v, V: ValueLike<'v>> ParametersSpec<V> {
     }
 }
 
+impl<V: Copy> ParametersSpec<V> {
+    /// Bind call arguments to parameters when the call shape is known at compile time:
+    /// `pos` positional arguments followed by the `names` arguments.
+    ///
+    /// Returns `None` if the call needs `*args` or `**kwargs` to be constructed,
+    /// or if it is an error, in which case it is reported by regular argument collection.
+    pub(crate) fn bind_static(&self, pos: usize, names: &[&str]) -> Option<Box<[StaticParam<V>]>> {
+        if self.indices.args.is_some() || self.indices.kwargs.is_some() {
+            return None;
+        }
+        if pos > self.indices.num_positional as usize {
+            return None;
+        }
+        let mut bound: Vec<Option<u32>> = vec![None; self.param_kinds.len()];
+        for (i, b) in bound.iter_mut().enumerate().take(pos) {
+            *b = Some(i as u32);
+        }
+        for (i, name) in names.iter().enumerate() {
+            let index = *self.names.get_str(name)? as usize;
+            if bound[index].is_some() {
+                return None;
+            }
+            bound[index] = Some((pos + i) as u32);
+        }
+        bound
+            .into_iter()
+            .zip(self.param_kinds.iter())
+            .map(|(bound, kind)| match (bound, kind) {
+                (Some(i), _) => Some(StaticParam::Arg(i)),
+                (None, ParameterKind::Defaulted(x)) => Some(StaticParam::Default(*x)),
+                (None, ParameterKind::Optional) => Some(StaticParam::Unset),
+                (None, _) => None,
+            })
+            .collect()
+    }
+}
+
 impl<'v> ParametersSpec<Value<'v>> {
     /// Move parameters from [`Arguments`] to a list of [`Value`],
     /// using the supplied [`ParametersSpec`].
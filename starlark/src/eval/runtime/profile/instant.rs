@@ -23,7 +23,7 @@ use allocative::Allocative;
 /// Real `Instant` for production code, thread-local counter for tests.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Allocative)]
 pub(crate) struct ProfilerInstant(
-    #[cfg(not(test))] std::time::Instant,
+    #[cfg(not(test))] crate::util::instant::Instant,
     #[cfg(test)] u64, // Millis.
 );
 
@@ -35,7 +35,7 @@ impl ProfilerInstant {
     pub(crate) fn now() -> Self {
         #[cfg(not(test))]
         {
-            ProfilerInstant(std::time::Instant::now())
+            ProfilerInstant(crate::util::instant::Instant::now())
         }
         #[cfg(test)]
         {
@@ -0,0 +1,497 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Recording of what an evaluation gets from native code, and replay of the evaluation
+//! against such a recording, to reproduce an evaluation without the host which ran it.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::hash::Hasher;
+
+use dupe::Dupe;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use starlark_map::StarlarkHasher;
+
+use crate::environment::FrozenModule;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::function::NativeFunction;
+
+#[derive(Debug, thiserror::Error)]
+enum ReplayError {
+    #[error("Replay diverged from the trace at event {0}: {1}")]
+    Diverged(usize, String),
+    #[error("Replay stopped after {0} of the {1} events of the trace")]
+    Incomplete(usize, usize),
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// What an evaluation got from native code, recorded with a [`TraceRecorder`], to
+/// replay the evaluation with a [`TraceReplayer`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalTrace {
+    /// The events, in the order they happened.
+    pub events: Vec<TraceEvent>,
+}
+
+impl EvalTrace {
+    /// The trace as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Read a trace written by [`to_json`](EvalTrace::to_json).
+    pub fn from_json(json: &str) -> anyhow::Result<EvalTrace> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// An event of an [`EvalTrace`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A call to the native function `function` finished with `result`.
+    /// The `nested` events which follow this one happened during the call.
+    NativeCall {
+        /// The name of the function.
+        function: String,
+        /// The number of events which happened during the call.
+        nested: usize,
+        /// What the call returned.
+        result: NativeResult,
+    },
+    /// The module `module` was loaded, its public globals hashing to `hash`.
+    Load {
+        /// The name of the module.
+        module: String,
+        /// The hash of its public globals.
+        hash: String,
+    },
+    /// A line printed by `print` or `pprint`.
+    Print {
+        /// The line, without the newline.
+        text: String,
+    },
+}
+
+/// The result of a native function call in an [`EvalTrace`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeResult {
+    /// A value with this `repr`. Values which JSON represents exactly, like lists of
+    /// strings, also have their `json`, and only those are replayed without calling
+    /// the function again.
+    Value {
+        /// The `repr` of the value.
+        repr: String,
+        /// The value as JSON, if JSON represents it exactly. Absent otherwise, so `None`
+        /// is distinguished from JSON `null`.
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_some"
+        )]
+        json: Option<serde_json::Value>,
+    },
+    /// An error with this message.
+    Error {
+        /// The message of the error.
+        message: String,
+    },
+}
+
+/// Deserialize a present field as `Some`, even if it is `null`.
+fn deserialize_some<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+impl NativeResult {
+    fn new<'v>(res: &crate::Result<Value<'v>>, heap: Heap<'v>) -> NativeResult {
+        match res {
+            Ok(value) => {
+                let repr = value.to_repr();
+                let json = value
+                    .to_json_value()
+                    .ok()
+                    .filter(|json| heap.alloc(json).to_repr() == repr);
+                NativeResult::Value { repr, json }
+            }
+            Err(e) => NativeResult::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// A hash of the public globals of `module`, by name and `repr`.
+fn module_hash(module: &FrozenModule) -> String {
+    let mut hasher = StarlarkHasher::new();
+    for name in module.names() {
+        hasher.write(name.as_str().as_bytes());
+        hasher.write_u8(0);
+        if let Ok(value) = module.get(name.as_str()) {
+            hasher.write(value.value().to_repr().as_bytes());
+        }
+        hasher.write_u8(0);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Records an [`EvalTrace`] of the evaluations it is given to, with
+/// [`Evaluator::set_recorder`](crate::eval::Evaluator::set_recorder).
+///
+/// Calls to the native functions which are safe to evaluate speculatively, like `len`,
+/// only depend on their arguments, so are not recorded. Neither are calls to methods.
+#[derive(Default)]
+pub struct TraceRecorder {
+    events: RefCell<Vec<TraceEvent>>,
+}
+
+impl TraceRecorder {
+    /// Create a recorder with an empty trace.
+    pub fn new() -> TraceRecorder {
+        TraceRecorder::default()
+    }
+
+    /// The trace recorded so far.
+    pub fn trace(&self) -> EvalTrace {
+        EvalTrace {
+            events: self.events.borrow().clone(),
+        }
+    }
+
+    fn native_call<'v>(
+        &self,
+        function: &NativeFunction,
+        eval: &mut Evaluator<'v, '_, '_>,
+        args: &Arguments<'v, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let index = self.events.borrow().len();
+        let res = function.function.invoke(eval, args);
+        let mut events = self.events.borrow_mut();
+        let nested = events.len() - index;
+        events.insert(
+            index,
+            TraceEvent::NativeCall {
+                function: function.name.clone(),
+                nested,
+                result: NativeResult::new(&res, eval.heap()),
+            },
+        );
+        res
+    }
+}
+
+/// Replays an [`EvalTrace`], given to an evaluation with
+/// [`Evaluator::set_replayer`](crate::eval::Evaluator::set_replayer).
+///
+/// Recorded native function calls which returned data, or failed, are not made again,
+/// the result from the trace is used instead, and what they printed is printed again.
+/// Other calls are made again, and must return a value with the same `repr`. Loaded
+/// modules must have the same globals as when recorded. The evaluation fails if it
+/// diverges from the trace.
+pub struct TraceReplayer {
+    trace: EvalTrace,
+    next: Cell<usize>,
+}
+
+impl TraceReplayer {
+    /// Create a replayer, starting from the first event of `trace`.
+    pub fn new(trace: EvalTrace) -> TraceReplayer {
+        TraceReplayer {
+            trace,
+            next: Cell::new(0),
+        }
+    }
+
+    /// Fail unless the evaluations replayed all the events of the trace.
+    pub fn finish(&self) -> crate::Result<()> {
+        let next = self.next.get();
+        if next < self.trace.events.len() {
+            return Err(crate::Error::new_other(ReplayError::Incomplete(
+                next,
+                self.trace.events.len(),
+            )));
+        }
+        Ok(())
+    }
+
+    fn diverged(&self, index: usize, message: String) -> crate::Error {
+        crate::Error::new_other(ReplayError::Diverged(index, message))
+    }
+
+    fn native_call<'v>(
+        &self,
+        function: &NativeFunction,
+        eval: &mut Evaluator<'v, '_, '_>,
+        args: &Arguments<'v, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let index = self.next.get();
+        let (nested, result) = match self.trace.events.get(index) {
+            Some(TraceEvent::NativeCall {
+                function: name,
+                nested,
+                result,
+            }) if *name == function.name => (*nested, result),
+            _ => {
+                return Err(self.diverged(
+                    index,
+                    format!("call to `{}` which was not recorded", function.name),
+                ));
+            }
+        };
+        match result {
+            NativeResult::Value { repr, json: None } => {
+                // The nested events are replayed by calling the function.
+                self.next.set(index + 1);
+                let value = function.function.invoke(eval, args)?;
+                if self.next.get() != index + 1 + nested {
+                    return Err(self.diverged(
+                        index,
+                        format!("call to `{}` made different calls", function.name),
+                    ));
+                }
+                let replayed = value.to_repr();
+                if replayed != *repr {
+                    return Err(self.diverged(
+                        index,
+                        format!(
+                            "`{}` returned `{}`, but `{}` when recorded",
+                            function.name, replayed, repr
+                        ),
+                    ));
+                }
+                Ok(value)
+            }
+            NativeResult::Value {
+                json: Some(json), ..
+            } => {
+                self.skip_nested(index, nested, eval)?;
+                Ok(eval.heap().alloc(json))
+            }
+            NativeResult::Error { message } => {
+                self.skip_nested(index, nested, eval)?;
+                Err(crate::Error::new_native(ReplayError::Failed(
+                    message.clone(),
+                )))
+            }
+        }
+    }
+
+    /// Move past the events during the call at `index`, printing again its lines.
+    fn skip_nested(&self, index: usize, nested: usize, eval: &Evaluator) -> crate::Result<()> {
+        let events = self
+            .trace
+            .events
+            .get(index + 1..index + 1 + nested)
+            .unwrap_or_default();
+        for event in events {
+            if let TraceEvent::Print { text } = event {
+                eval.print_handler.println(text)?;
+            }
+        }
+        self.next.set(index + 1 + nested);
+        Ok(())
+    }
+
+    fn load(&self, module: &str, hash: String) -> crate::Result<()> {
+        let index = self.next.get();
+        match self.trace.events.get(index) {
+            Some(TraceEvent::Load {
+                module: recorded,
+                hash: recorded_hash,
+            }) if recorded == module => {
+                if *recorded_hash != hash {
+                    return Err(self.diverged(
+                        index,
+                        format!("module `{module}` changed since it was recorded"),
+                    ));
+                }
+                self.next.set(index + 1);
+                Ok(())
+            }
+            _ => Err(self.diverged(index, format!("load of `{module}` which was not recorded"))),
+        }
+    }
+}
+
+/// Whether an [`Evaluator`] records or replays a trace.
+#[derive(Clone, Copy, Dupe)]
+pub(crate) enum TraceMode<'a> {
+    Record(&'a TraceRecorder),
+    Replay(&'a TraceReplayer),
+}
+
+impl TraceMode<'_> {
+    /// Call `function`, which is only done through here while tracing.
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn native_call<'v>(
+        self,
+        function: &NativeFunction,
+        eval: &mut Evaluator<'v, '_, '_>,
+        args: &Arguments<'v, '_>,
+    ) -> crate::Result<Value<'v>> {
+        if function.speculative_exec_safe {
+            return function.function.invoke(eval, args);
+        }
+        match self {
+            TraceMode::Record(recorder) => recorder.native_call(function, eval, args),
+            TraceMode::Replay(replayer) => replayer.native_call(function, eval, args),
+        }
+    }
+
+    pub(crate) fn load(self, name: &str, module: &FrozenModule) -> crate::Result<()> {
+        let hash = module_hash(module);
+        match self {
+            TraceMode::Record(recorder) => {
+                recorder.events.borrow_mut().push(TraceEvent::Load {
+                    module: name.to_owned(),
+                    hash,
+                });
+                Ok(())
+            }
+            TraceMode::Replay(replayer) => replayer.load(name, hash),
+        }
+    }
+
+    pub(crate) fn print(self, text: &str) {
+        if let TraceMode::Record(recorder) = self {
+            recorder.events.borrow_mut().push(TraceEvent::Print {
+                text: text.to_owned(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::atomic::AtomicI32;
+    use std::sync::atomic::Ordering;
+
+    use starlark_derive::starlark_module;
+
+    use crate as starlark;
+    use crate::environment::FrozenModule;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::LibraryExtension;
+    use crate::environment::Module;
+    use crate::eval::EvalTrace;
+    use crate::eval::Evaluator;
+    use crate::eval::TraceEvent;
+    use crate::eval::TraceRecorder;
+    use crate::eval::TraceReplayer;
+    use crate::eval::runtime::replay::TraceMode;
+    use crate::stdlib::PrintHandler;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    static NEXT_ID: AtomicI32 = AtomicI32::new(0);
+
+    #[starlark_module]
+    fn ids(builder: &mut GlobalsBuilder) {
+        fn next_id() -> anyhow::Result<i32> {
+            Ok(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+        }
+    }
+
+    #[derive(Default)]
+    struct Lines(RefCell<Vec<String>>);
+
+    impl PrintHandler for Lines {
+        fn println(&self, text: &str) -> crate::Result<()> {
+            self.0.borrow_mut().push(text.to_owned());
+            Ok(())
+        }
+    }
+
+    fn eval(code: &str, mode: TraceMode, lines: &Lines) -> crate::Result<FrozenModule> {
+        let globals = GlobalsBuilder::extended_by(&[LibraryExtension::Print])
+            .with(ids)
+            .build();
+        let ast = AstModule::parse("test.star", code.to_owned(), &Dialect::Standard)?;
+        Module::with_temp_heap(|module| {
+            {
+                let mut eval = Evaluator::new(&module);
+                eval.set_print_handler(lines);
+                match mode {
+                    TraceMode::Record(recorder) => eval.set_recorder(recorder),
+                    TraceMode::Replay(replayer) => eval.set_replayer(replayer),
+                }
+                eval.eval_module(ast, &globals)?;
+            }
+            Ok(module.freeze()?)
+        })
+    }
+
+    const CODE: &str = r#"
+x = next_id()
+print("x", x)
+y = [next_id(), len("ab")]
+"#;
+
+    #[test]
+    fn test_record_replay() {
+        let recorder = TraceRecorder::new();
+        let lines = Lines::default();
+        let recorded = eval(CODE, TraceMode::Record(&recorder), &lines).unwrap();
+        let trace = EvalTrace::from_json(&recorder.trace().to_json()).unwrap();
+        assert_eq!(recorder.trace(), trace);
+        // `next_id` twice and `print`, but not `len`.
+        let calls = trace
+            .events
+            .iter()
+            .filter(|x| matches!(x, TraceEvent::NativeCall { .. }))
+            .count();
+        assert_eq!(3, calls);
+
+        NEXT_ID.store(100, Ordering::Relaxed);
+        let replayer = TraceReplayer::new(trace);
+        let replayed_lines = Lines::default();
+        let replayed = eval(CODE, TraceMode::Replay(&replayer), &replayed_lines).unwrap();
+        replayer.finish().unwrap();
+        for name in ["x", "y"] {
+            assert_eq!(
+                recorded.get(name).unwrap().value().to_repr(),
+                replayed.get(name).unwrap().value().to_repr()
+            );
+        }
+        assert_eq!(lines.0.into_inner(), replayed_lines.0.into_inner());
+    }
+
+    #[test]
+    fn test_replay_diverged() {
+        let recorder = TraceRecorder::new();
+        eval(CODE, TraceMode::Record(&recorder), &Lines::default()).unwrap();
+        let replayer = TraceReplayer::new(recorder.trace());
+        // The trace starts with a call to `next_id`, not `print`.
+        let err = eval(
+            "print('a')",
+            TraceMode::Replay(&replayer),
+            &Lines::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("diverged"), "{err}");
+    }
+}
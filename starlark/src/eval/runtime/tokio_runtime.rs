@@ -0,0 +1,170 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Native functions awaiting futures on the host tokio runtime, with the `tokio` feature.
+
+use std::future::Future;
+use std::pin::pin;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::runtime::Handle;
+
+use crate::eval::Evaluator;
+use crate::eval::runtime::evaluator::EvaluatorError;
+
+/// How often to check for cancellation while awaiting a future.
+const CANCELLED_CHECK_PERIOD: Duration = Duration::from_millis(10);
+
+#[derive(Error, Debug)]
+enum TokioRuntimeError {
+    #[error("No tokio runtime is available, call `Evaluator::set_tokio_handle` first")]
+    NoHandle,
+}
+
+impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
+    /// Set the tokio runtime used by [`block_on`](Evaluator::block_on).
+    ///
+    /// The runtime must be multi-threaded and have the time driver enabled.
+    pub fn set_tokio_handle(&mut self, handle: Handle) {
+        self.tokio_handle = Some(handle);
+    }
+
+    /// The tokio runtime set with [`set_tokio_handle`](Evaluator::set_tokio_handle).
+    pub fn tokio_handle(&self) -> Option<&Handle> {
+        self.tokio_handle.as_ref()
+    }
+
+    /// Run a future on the host tokio runtime, blocking evaluation until it completes.
+    ///
+    /// Evaluation is synchronous, so it must run on a thread where blocking is allowed,
+    /// usually via [`tokio::task::spawn_blocking`]:
+    ///
+    /// ```ignore
+    /// #[starlark_module]
+    /// fn http(builder: &mut GlobalsBuilder) {
+    ///     fn fetch(url: &str, eval: &mut Evaluator) -> starlark::Result<String> {
+    ///         let body = eval.block_on(async { reqwest::get(url).await?.text().await })?;
+    ///         body.map_err(starlark::Error::new_other)
+    ///     }
+    /// }
+    ///
+    /// let handle = tokio::runtime::Handle::current();
+    /// tokio::task::spawn_blocking(move || {
+    ///     Module::with_temp_heap(|module| {
+    ///         let mut eval = Evaluator::new(&module);
+    ///         eval.set_tokio_handle(handle);
+    ///         eval.eval_module(ast, &globals)?;
+    ///         starlark::Result::Ok(())
+    ///     })
+    /// })
+    /// .await??;
+    /// ```
+    ///
+    /// Limits still apply: this fails if evaluation is over its heap or tick limits, and
+    /// the function passed to [`set_check_cancelled`](Evaluator::set_check_cancelled) is
+    /// polled while waiting, so timeouts built on it interrupt the wait. On cancellation
+    /// the future is dropped.
+    pub fn block_on<F: Future>(&mut self, future: F) -> crate::Result<F::Output> {
+        let Some(handle) = self.tokio_handle.clone() else {
+            return Err(crate::Error::new_other(TokioRuntimeError::NoHandle));
+        };
+        self.run_infrequent_instr_checks()?;
+        let is_cancelled = &self.is_cancelled;
+        handle.block_on(async {
+            let mut future = pin!(future);
+            let mut interval = tokio::time::interval(CANCELLED_CHECK_PERIOD);
+            loop {
+                tokio::select! {
+                    biased;
+                    res = &mut future => return Ok(res),
+                    _ = interval.tick() => {
+                        if is_cancelled() {
+                            return Err(crate::Error::new_other(EvaluatorError::Cancelled));
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::time::Duration;
+
+    use starlark_derive::starlark_module;
+    use tokio::runtime::Runtime;
+
+    use crate as starlark;
+    use crate::assert::Assert;
+    use crate::environment::GlobalsBuilder;
+    use crate::eval::Evaluator;
+
+    #[starlark_module]
+    fn helpers(builder: &mut GlobalsBuilder) {
+        fn sleep_then(x: i32, eval: &mut Evaluator) -> starlark::Result<i32> {
+            eval.block_on(async {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                x
+            })
+        }
+
+        fn wait_forever(eval: &mut Evaluator) -> starlark::Result<i32> {
+            eval.block_on(future::pending::<i32>())
+        }
+    }
+
+    fn runtime() -> Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_block_on() {
+        let rt = runtime();
+        let handle = rt.handle().clone();
+        let mut a = Assert::new();
+        a.globals_add(helpers);
+        a.setup_eval(move |eval| eval.set_tokio_handle(handle.clone()));
+        a.eq("3", "sleep_then(3)");
+    }
+
+    #[test]
+    fn test_block_on_cancelled() {
+        let rt = runtime();
+        let handle = rt.handle().clone();
+        let mut a = Assert::new();
+        a.globals_add(helpers);
+        a.setup_eval(move |eval| {
+            eval.set_tokio_handle(handle.clone());
+            eval.set_check_cancelled(Box::new(|| true));
+        });
+        a.fail("wait_forever()", "Evaluation cancelled");
+    }
+
+    #[test]
+    fn test_block_on_no_handle() {
+        let mut a = Assert::new();
+        a.globals_add(helpers);
+        a.fail("sleep_then(3)", "set_tokio_handle");
+    }
+}
@@ -19,7 +19,8 @@
 //! Starlark is a deterministic version of Python, with [a specification](https://github.com/bazelbuild/starlark/blob/master/spec.md),
 //! used by (amongst others) the [Buck](https://buck.build) and [Bazel](https://bazel.build) build systems.
 //!
-//! To evaluate a simple file:
+//! To evaluate a file and read a global as a Rust value, see [`simple`]. To evaluate a
+//! simple file with full control:
 //!
 //! ```
 //! # fn run() -> starlark::Result<()> {
@@ -399,6 +400,19 @@
 //! # }
 //! # fn main(){ run().unwrap(); }
 //! ```
+//!
+//! ## Cargo features
+//!
+//! Parsing, evaluation and the core standard library never touch the operating system.
+//! The rest is behind default features, so a `default-features = false` build is suitable
+//! for sandboxed or embedded environments:
+//!
+//! * `fs`: `AstModule::parse_file`, and the `os` library extension, which reads files and
+//!   the environment when an `OsPolicy` allows it.
+//! * `clock`: the system clock, used for profiling and timing. Without it all durations
+//!   are zero.
+//! * `readline`: the interactive terminal used by `breakpoint()`, which reads the
+//!   environment to find its history file.
 
 // Features we use
 #![allow(stable_features)]
@@ -442,6 +456,8 @@ mod macros;
 pub use starlark_derive::starlark_module;
 pub use starlark_derive::type_matcher;
 pub use starlark_syntax::Error;
+pub use starlark_syntax::ErrorClass;
+pub use starlark_syntax::ErrorCode;
 pub use starlark_syntax::ErrorKind;
 pub use starlark_syntax::Result;
 pub use starlark_syntax::StarlarkResultExt;
@@ -457,9 +473,11 @@ pub mod docs;
 pub mod environment;
 pub mod errors;
 pub mod eval;
+pub mod platform;
 mod private;
 pub mod read_line;
 mod sealed;
+pub mod simple;
 pub mod syntax;
 pub mod typing;
 
@@ -0,0 +1,132 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The services of the host used by the interpreter: a clock, random numbers and
+//! standard output.
+//!
+//! By default these come from the standard library, see [`StdPlatform`]. Targets without
+//! them, like `wasm32-unknown-unknown` in a browser, can install their own [`Platform`]
+//! with [`set_platform`], and build without the `fs`, `clock` and `readline` features,
+//! which read files, the environment and the system clock directly.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum PlatformError {
+    #[error("The platform has already been set")]
+    AlreadySet,
+}
+
+/// A clock, random numbers and standard output for the interpreter.
+///
+/// The clock is used for profiling, timing module evaluation, deadlines
+/// ([`Evaluator::set_timeout`](crate::eval::Evaluator::set_timeout)) and the time slices of
+/// async evaluation, standard error by the default `print` handler and verbose GC.
+/// Random numbers are not used by the interpreter itself, but are available to native
+/// functions, so they work on every target.
+pub trait Platform: Send + Sync {
+    /// Time elapsed since an arbitrary point, which doesn't change while the process runs.
+    /// Must never decrease. Returning zero disables timing, and makes deadlines pass at once.
+    fn now(&self) -> Duration;
+
+    /// A random number. Doesn't need to be cryptographically secure.
+    fn random_u64(&self) -> u64;
+
+    /// Write `text` to standard output.
+    fn write_stdout(&self, text: &str);
+
+    /// Write `text` to standard error.
+    fn write_stderr(&self, text: &str);
+}
+
+/// The [`Platform`] from the standard library, used unless another one is set.
+///
+/// Without the `clock` feature, or on `wasm32-unknown-unknown`, where reading the clock
+/// panics, the time is always zero.
+pub struct StdPlatform;
+
+impl Platform for StdPlatform {
+    #[cfg(all(
+        feature = "clock",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    fn now(&self) -> Duration {
+        static START: OnceLock<std::time::Instant> = OnceLock::new();
+        START.get_or_init(std::time::Instant::now).elapsed()
+    }
+
+    #[cfg(not(all(
+        feature = "clock",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    )))]
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn random_u64(&self) -> u64 {
+        // Each `RandomState` is seeded differently, randomly where the platform can.
+        RandomState::new().hash_one(0u64)
+    }
+
+    fn write_stdout(&self, text: &str) {
+        print!("{text}");
+    }
+
+    fn write_stderr(&self, text: &str) {
+        eprint!("{text}");
+    }
+}
+
+static PLATFORM: OnceLock<&'static dyn Platform> = OnceLock::new();
+
+/// Use `platform` for the rest of the process.
+///
+/// Can only be called once, and should be called before any evaluation, since
+/// instants from different clocks can't be compared.
+pub fn set_platform(platform: &'static dyn Platform) -> anyhow::Result<()> {
+    PLATFORM
+        .set(platform)
+        .map_err(|_| PlatformError::AlreadySet.into())
+}
+
+/// The current [`Platform`], [`StdPlatform`] unless [`set_platform`] was called.
+#[inline]
+pub fn platform() -> &'static dyn Platform {
+    match PLATFORM.get() {
+        Some(platform) => *platform,
+        None => &StdPlatform,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::platform::Platform;
+    use crate::platform::StdPlatform;
+
+    #[test]
+    fn test_std_platform() {
+        let before = StdPlatform.now();
+        assert!(StdPlatform.now() >= before);
+        // Two equal random numbers in a row are vanishingly unlikely.
+        assert_ne!(StdPlatform.random_u64(), StdPlatform.random_u64());
+    }
+}
@@ -18,7 +18,7 @@
 // This is not public API, but it is used by Starlark command line utility.
 #![doc(hidden)]
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "readline", not(target_arch = "wasm32")))]
 mod with_or_without_rustyline {
     use std::env;
     use std::io;
@@ -70,10 +70,10 @@ mod with_or_without_rustyline {
     }
 }
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(not(all(feature = "readline", not(target_arch = "wasm32"))))]
 mod with_or_without_rustyline {
     #[derive(thiserror::Error, Debug)]
-    #[error("Rustyline is not supported on wasm32")]
+    #[error("Rustyline is not supported on wasm32 or without the `readline` feature")]
     struct NoRustyline;
 
     pub struct ReadLine(());
@@ -0,0 +1,187 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Evaluate a file and read one of its globals as a Rust value, in a single call.
+//!
+//! ```
+//! # fn run() -> starlark::Result<()> {
+//! use serde::Deserialize;
+//! use starlark::environment::Globals;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     replicas: u32,
+//! }
+//!
+//! let source = r#"
+//! def double(x):
+//!     return x * 2
+//!
+//! config = {"name": "web", "replicas": double(3)}
+//! "#;
+//! let config: Config =
+//!     starlark::simple::eval_str("config.star", source, "config", &Globals::standard(), None)?;
+//! assert_eq!(config.replicas, 6);
+//! # Ok(())
+//! # }
+//! # fn main(){ run().unwrap(); }
+//! ```
+//!
+//! Files are parsed with [`Dialect::Extended`]. For anything more involved, such as other
+//! dialects, limits or reading several globals, use [`AstModule`], [`Module`] and
+//! [`Evaluator`] directly, as shown in the [crate documentation](crate).
+
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use starlark_syntax::syntax::module::AstModuleFields;
+use thiserror::Error;
+
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::eval::FileLoader;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Debug, Error)]
+enum SimpleError {
+    #[error("Module `{0}` has no global `{1}`")]
+    NoGlobal(String, String),
+    #[error("Global `{0}` can't be converted to the requested type: {1}")]
+    Convert(String, serde_json::Error),
+}
+
+fn eval_ast<T: DeserializeOwned>(
+    ast: AstModule,
+    name: &str,
+    globals: &Globals,
+    loader: Option<&dyn FileLoader>,
+) -> crate::Result<T> {
+    let filename = ast.codemap().filename().to_owned();
+    let module = Module::with_temp_heap(|module| -> crate::Result<FrozenModule> {
+        {
+            let mut eval = Evaluator::new(&module);
+            if let Some(loader) = loader {
+                eval.set_loader(loader);
+            }
+            eval.eval_module(ast, globals)?;
+        }
+        Ok(module.freeze()?)
+    })?;
+    let value = module
+        .get(name)
+        .map_err(|_| crate::Error::new_other(SimpleError::NoGlobal(filename, name.to_owned())))?;
+    let json = serde_json::to_value(value.value())
+        .map_err(|e| crate::Error::new_other(SimpleError::Convert(name.to_owned(), e)))?;
+    serde_json::from_value(json)
+        .map_err(|e| crate::Error::new_other(SimpleError::Convert(name.to_owned(), e)))
+}
+
+/// Evaluate `source`, and convert its global `name` to `T`.
+///
+/// The value is converted as if it were encoded and decoded as JSON, so it can be made
+/// of `None`, bools, numbers, strings, lists, tuples, dicts and structs. `load()`
+/// statements are resolved with `loader`, and are an error if it is `None`.
+pub fn eval_str<T: DeserializeOwned>(
+    filename: &str,
+    source: &str,
+    name: &str,
+    globals: &Globals,
+    loader: Option<&dyn FileLoader>,
+) -> crate::Result<T> {
+    let ast = AstModule::parse(filename, source.to_owned(), &Dialect::Extended)?;
+    eval_ast(ast, name, globals, loader)
+}
+
+/// Evaluate the file at `path`, and convert its global `name` to `T`.
+/// For details see [`eval_str`].
+#[cfg(feature = "fs")]
+pub fn eval_file<T: DeserializeOwned>(
+    path: &Path,
+    name: &str,
+    globals: &Globals,
+    loader: Option<&dyn FileLoader>,
+) -> crate::Result<T> {
+    let ast = AstModule::parse_file(path, &Dialect::Extended)?;
+    eval_ast(ast, name, globals, loader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use crate::environment::Globals;
+    use crate::environment::LibraryExtension;
+    use crate::eval::ReturnFileLoader;
+    use crate::simple::eval_str;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+        host: String,
+        ports: Vec<u16>,
+    }
+
+    #[test]
+    fn test_eval_str() {
+        let server: Server = eval_str(
+            "server.star",
+            "server = struct(host = 'localhost', ports = [80, 443])",
+            "server",
+            &Globals::extended_by(&[LibraryExtension::StructType]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Server {
+                host: "localhost".to_owned(),
+                ports: vec![80, 443],
+            },
+            server
+        );
+    }
+
+    #[test]
+    fn test_eval_str_with_load() {
+        let lib = crate::assert::pass_module("port = 8080");
+        let modules = HashMap::from([("lib.star", &lib)]);
+        let loader = ReturnFileLoader { modules: &modules };
+        let port: u16 = eval_str(
+            "main.star",
+            "load('lib.star', 'port')\nx = port",
+            "x",
+            &Globals::standard(),
+            Some(&loader),
+        )
+        .unwrap();
+        assert_eq!(8080, port);
+    }
+
+    #[test]
+    fn test_eval_str_errors() {
+        let err = eval_str::<u16>("x.star", "x = 1", "y", &Globals::standard(), None).unwrap_err();
+        assert!(err.to_string().contains("no global `y`"), "{err}");
+        let err =
+            eval_str::<u16>("x.star", "x = 'a'", "x", &Globals::standard(), None).unwrap_err();
+        assert!(err.to_string().contains("can't be converted"), "{err}");
+    }
+}
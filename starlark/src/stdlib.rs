@@ -25,17 +25,29 @@ use crate::values::namespace::globals::register_namespace;
 
 pub(crate) mod breakpoint;
 pub(crate) mod call_stack;
+pub(crate) mod catch;
+#[cfg(feature = "cbor")]
+pub(crate) mod cbor;
 pub(crate) mod extra;
 mod funcs;
 pub(crate) mod internal;
 pub(crate) mod json;
+pub(crate) mod lazy;
+pub(crate) mod library;
+#[cfg(feature = "msgpack")]
+pub(crate) mod msgpack;
+#[cfg(feature = "fs")]
+pub(crate) mod os;
 pub(crate) mod partial;
+pub(crate) mod skylib;
 
 pub use extra::PrintHandler;
 
 use crate::stdlib::funcs::globals::register_globals;
 use crate::stdlib::internal::register_internal;
+use crate::values::decimal::globals::register_decimal;
 use crate::values::enumeration::globals::register_enum;
+use crate::values::provider::globals::register_provider;
 use crate::values::record::globals::register_record;
 use crate::values::structs::structs::register_struct;
 use crate::values::types::set::set::register_set;
@@ -98,6 +110,32 @@ pub enum LibraryExtension {
     CallStack,
     /// Definitions to support the `set` type, the `set()` constructor.
     SetType,
+    /// Definitions to support providers, the `provider()`, `provider_field()` and
+    /// `provider_collection()` functions.
+    Provider,
+    /// Add `os.read_file`, `os.list_dir` and `os.getenv`, which fail unless allowed by
+    /// the [`OsPolicy`](crate::environment::OsPolicy) set with
+    /// [`Evaluator::set_os_policy`](crate::eval::Evaluator::set_os_policy).
+    #[cfg(feature = "fs")]
+    Os,
+    /// Add `msgpack.encode()` and `msgpack.decode()`, like `json`, but for MessagePack
+    /// given as a list of ints.
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+    /// Add `cbor.encode()` and `cbor.decode()`, like `json`, but for CBOR given as a
+    /// list of ints.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// Add a function `catch(f, *args, **kwargs)` which calls `f`, and returns a struct
+    /// with its result, or the message of the error if it failed.
+    Catch,
+    /// Definitions to support the `decimal` type, exact decimal numbers, and the
+    /// `decimal()` constructor.
+    Decimal,
+    /// Add `lazy.map()` and `lazy.filter()`, which record steps to apply to each element
+    /// of an iterable, and `lazy.collect()` and `lazy.for_each()`, which apply them one
+    /// element at a time, without a list for each step.
+    Lazy,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -124,6 +162,16 @@ impl LibraryExtension {
             Internal,
             CallStack,
             SetType,
+            Provider,
+            #[cfg(feature = "fs")]
+            Os,
+            #[cfg(feature = "msgpack")]
+            Msgpack,
+            #[cfg(feature = "cbor")]
+            Cbor,
+            Catch,
+            Decimal,
+            Lazy,
         ]
     }
 
@@ -136,6 +184,7 @@ impl LibraryExtension {
             RecordType => register_record(builder),
             EnumType => register_enum(builder),
             SetType => register_set(builder),
+            Provider => register_provider(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
@@ -149,6 +198,15 @@ impl LibraryExtension {
             Typing => typing::globals::register_typing(builder),
             Internal => register_internal(builder),
             CallStack => call_stack::global(builder),
+            #[cfg(feature = "fs")]
+            Os => os::os(builder),
+            #[cfg(feature = "msgpack")]
+            Msgpack => msgpack::msgpack(builder),
+            #[cfg(feature = "cbor")]
+            Cbor => cbor::cbor(builder),
+            Catch => catch::catch(builder),
+            Decimal => register_decimal(builder),
+            Lazy => lazy::lazy(builder),
         }
     }
 }
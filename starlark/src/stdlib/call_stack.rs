@@ -98,6 +98,7 @@ pub(crate) fn global(builder: &mut GlobalsBuilder) {
     /// strip_frames will pop N frames from the top of the call stack, which can
     /// be useful to hide non-interesting lines - for example, strip_frames=1
     /// will hide the call to and location of `call_stack()` itself.
+    #[starlark(deterministic)]
     fn call_stack(
         #[starlark(require=named, default = 0)] strip_frames: u32,
         eval: &mut Evaluator,
@@ -113,6 +114,7 @@ pub(crate) fn global(builder: &mut GlobalsBuilder) {
     ///
     /// With `n=0` returns `call_stack_frame` itself.
     /// Returns `None` if `n` is greater than or equal to the stack size.
+    #[starlark(deterministic)]
     fn call_stack_frame(
         #[starlark(require = pos)] n: u32,
         eval: &mut Evaluator,
@@ -0,0 +1,229 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `catch` function.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::ErrorClass;
+use crate::ErrorKind;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::eval::runtime::arguments::ArgumentsFull;
+use crate::stdlib::funcs::other::FailError;
+use crate::values::Value;
+use crate::values::serde::ValueDeserializer;
+use crate::values::structs::AllocStruct;
+
+/// The `failure` field of a `catch()` result: the error as a struct of `message`, `code`,
+/// `fields` and `call_stack`.
+fn failure<'v>(e: &crate::Error, eval: &Evaluator<'v, '_, '_>) -> Value<'v> {
+    let heap = eval.heap();
+    let (message, fields): (String, Vec<(&str, Value)>) = match e.kind() {
+        ErrorKind::Fail(fail) => {
+            let message = fail.to_string();
+            let message = message.strip_prefix(' ').unwrap_or(&message).to_owned();
+            let deserializer = ValueDeserializer::new();
+            let fields = match fail.downcast_ref::<FailError>() {
+                Some(fail) => fail
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let value = field
+                            .data
+                            .as_ref()
+                            .and_then(|data| deserializer.alloc(data, heap).ok())
+                            .unwrap_or_else(|| heap.alloc(field.repr.as_str()));
+                        (field.name.as_str(), value)
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            (message, fields)
+        }
+        _ => (e.without_diagnostic().to_string(), Vec::new()),
+    };
+    // Only the frames below `catch()`, which are those not on the current stack.
+    let mut call_stack = e.call_stack().clone();
+    let outer = eval.call_stack().frames.len().min(call_stack.frames.len());
+    call_stack.frames.drain(..outer);
+    heap.alloc(AllocStruct([
+        ("message", heap.alloc(message)),
+        ("code", heap.alloc(e.code().to_string())),
+        ("fields", heap.alloc(AllocStruct(fields))),
+        ("call_stack", heap.alloc(call_stack.to_string())),
+    ]))
+}
+
+/// Can Starlark code recover from this error? All the failures caused by the program,
+/// like `fail()`, a division by zero or bad arguments to a builtin, but not internal errors
+/// or resource limits like stack overflow, timeouts or cancellation.
+fn is_catchable(e: &crate::Error) -> bool {
+    !matches!(
+        e.class(),
+        ErrorClass::ResourceExhausted | ErrorClass::Cancelled | ErrorClass::Internal
+    )
+}
+
+#[starlark_module]
+pub fn catch(builder: &mut GlobalsBuilder) {
+    /// Call `f` with the given arguments, returning a struct with fields `ok`, `value`,
+    /// `error` and `failure`.
+    ///
+    /// If the call succeeds, `ok` is `True` and `value` is its result.
+    /// If it fails, by calling `fail()` or with an error like a division by zero
+    /// or a missing key, `ok` is `False` and `error` is the error message.
+    /// Internal errors and exceeded limits, like stack overflows, are not caught.
+    ///
+    /// `failure` is `None` on success, otherwise a struct describing the error:
+    /// `message`, without the `fail:` prefix, `code`, like `"E0600"`, `fields`, a struct
+    /// of the keyword arguments given to `fail()`, and `call_stack`, the traceback from
+    /// `f` to the failure.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// catch(lambda x: x + 1, 1).value == 2
+    /// catch(fail, "oops").error == "fail: oops"
+    /// not catch(lambda: {}["x"]).ok
+    /// catch(fail, "oops", code = 3).failure.fields.code == 3
+    /// catch(lambda: 1 // 0).failure.code == "E0403"
+    /// # "#);
+    /// ```
+    #[starlark(deterministic)]
+    fn catch<'v>(
+        #[starlark(require = pos)] f: Value<'v>,
+        #[starlark(args)] args: Value<'v>,
+        #[starlark(kwargs)] kwargs: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let params = Arguments(ArgumentsFull {
+            args: Some(args),
+            kwargs: Some(kwargs),
+            ..ArgumentsFull::default()
+        });
+        let (ok, value, error, failure) = match f.invoke(&params, eval) {
+            Ok(value) => (true, value, Value::new_none(), Value::new_none()),
+            Err(e) if is_catchable(&e) => (
+                false,
+                Value::new_none(),
+                eval.heap().alloc(e.without_diagnostic().to_string()),
+                failure(&e, eval),
+            ),
+            Err(e) => return Err(e),
+        };
+        Ok(eval.heap().alloc(AllocStruct([
+            ("ok", Value::new_bool(ok)),
+            ("value", value),
+            ("error", error),
+            ("failure", failure),
+        ])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::catch;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_catch() {
+        let mut a = Assert::new();
+        a.globals_add(catch);
+        a.is_true(
+            r#"
+def add(x, y = 0):
+    return x + y
+
+r = catch(add, 1, y = 2)
+r.ok and r.value == 3 and r.error == None
+"#,
+        );
+        a.is_true(
+            r#"
+def f(x):
+    if x:
+        fail("bad", x)
+    return 1 // x
+
+r1 = catch(f, 7)
+r2 = catch(f, 0)
+r3 = catch(f)
+r4 = catch(int, "x")
+all([
+    not r1.ok, r1.value == None, r1.error == "fail: bad 7",
+    not r2.ok, "zero" in r2.error, r2.failure.code == "E0403",
+    not r3.ok, "x" in r3.error,
+    not r4.ok, r4.value == None,
+])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_catch_failure() {
+        let mut a = Assert::new();
+        a.globals_add(catch);
+        a.is_true(
+            r#"
+def check(x):
+    if x < 0:
+        fail("negative:", x, value = x, hint = "use abs", f = check)
+    return x
+
+def outer(x):
+    return check(x)
+
+r = catch(outer, -2)
+f = r.failure
+all([
+    catch(outer, 2).failure == None,
+    f.message == "negative: -2",
+    f.code == "E0600",
+    f.fields.value == -2,
+    f.fields.hint == "use abs",
+    f.fields.f == repr(check),
+    "in outer" in f.call_stack,
+    "in check" in f.call_stack,
+    "in catch" not in f.call_stack,
+])
+"#,
+        );
+        a.is_true(
+            r#"
+f = catch(lambda: {}["x"]).failure
+f.code == "E0411" and f.fields == struct() and "x" in f.message
+"#,
+        );
+    }
+
+    #[test]
+    fn test_catch_uncatchable() {
+        let mut a = Assert::new();
+        a.globals_add(catch);
+        a.fail(
+            r#"
+def f():
+    return f()
+
+catch(f)
+"#,
+            "Starlark call stack overflow",
+        );
+    }
+}
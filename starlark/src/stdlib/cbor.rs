@@ -0,0 +1,168 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `cbor` library extension, with the same structure as the `json` module, and
+//! the matching [`Value::to_cbor`] and [`Value::from_cbor`] for the host side.
+//!
+//! Starlark strings can't hold arbitrary bytes, so the encoded form is a list of ints.
+
+use num_bigint::BigInt;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::collections::SmallMap;
+use crate::environment::GlobalsBuilder;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::dict::Dict;
+use crate::values::list::AllocList;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::types::int::int_or_big::StarlarkInt;
+
+#[derive(Debug, thiserror::Error)]
+enum CborError {
+    #[error("Expected a list of bytes, got `{0}`, which is not in the range 0 to 255")]
+    NotAByte(i32),
+    #[error("Trailing {0} bytes after the CBOR value")]
+    TrailingBytes(usize),
+    #[error("Invalid CBOR: {0}")]
+    Decode(String),
+    #[error("Unsupported CBOR value: {0:?}")]
+    Unsupported(ciborium::Value),
+}
+
+fn alloc_cbor<'v>(heap: Heap<'v>, x: ciborium::Value) -> anyhow::Result<Value<'v>> {
+    Ok(match x {
+        ciborium::Value::Null => Value::new_none(),
+        ciborium::Value::Bool(x) => Value::new_bool(x),
+        ciborium::Value::Integer(x) => {
+            let x = i128::from(x);
+            match i64::try_from(x) {
+                Ok(x) => heap.alloc(x),
+                Err(_) => heap.alloc(StarlarkInt::from(BigInt::from(x))),
+            }
+        }
+        ciborium::Value::Float(x) => heap.alloc(x),
+        ciborium::Value::Text(x) => heap.alloc(x.as_str()),
+        ciborium::Value::Bytes(x) => heap.alloc(AllocList(x.into_iter().map(i32::from))),
+        // Tags, e.g. for dates, only annotate the value they wrap.
+        ciborium::Value::Tag(_, x) => alloc_cbor(heap, *x)?,
+        ciborium::Value::Array(xs) => heap.alloc(AllocList(
+            xs.into_iter()
+                .map(|x| alloc_cbor(heap, x))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )),
+        ciborium::Value::Map(xs) => {
+            let mut content = SmallMap::with_capacity(xs.len());
+            for (k, v) in xs {
+                let k = alloc_cbor(heap, k)?
+                    .get_hashed()
+                    .map_err(|e| e.into_anyhow())?;
+                content.insert_hashed(k, alloc_cbor(heap, v)?);
+            }
+            heap.alloc(Dict::new(content))
+        }
+        x => return Err(CborError::Unsupported(x).into()),
+    })
+}
+
+impl<'v> Value<'v> {
+    /// Convert the value to CBOR.
+    ///
+    /// Return an error if the value or any contained value does not support conversion,
+    /// as for [`to_json`](Value::to_json).
+    pub fn to_cbor(self) -> anyhow::Result<Vec<u8>> {
+        let mut res = Vec::new();
+        ciborium::ser::into_writer(&self, &mut res).map_err(|e| anyhow::anyhow!("{e}"))?;
+        Ok(res)
+    }
+
+    /// Build a value from a single CBOR data item.
+    ///
+    /// Maps become dicts, arrays become lists, byte strings become lists of ints,
+    /// and tags are ignored.
+    pub fn from_cbor(heap: Heap<'v>, mut bytes: &[u8]) -> anyhow::Result<Value<'v>> {
+        let x: ciborium::Value =
+            ciborium::de::from_reader(&mut bytes).map_err(|e| CborError::Decode(e.to_string()))?;
+        if !bytes.is_empty() {
+            return Err(CborError::TrailingBytes(bytes.len()).into());
+        }
+        alloc_cbor(heap, x)
+    }
+}
+
+pub(crate) fn cbor(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn cbor_members(globals: &mut GlobalsBuilder) {
+        /// Encode a value as CBOR, returning the bytes as a list of ints.
+        #[starlark(deterministic)]
+        fn encode<'v>(
+            #[starlark(require = pos)] x: Value<'v>,
+            heap: Heap<'v>,
+        ) -> anyhow::Result<Value<'v>> {
+            Ok(heap.alloc(AllocList(x.to_cbor()?.into_iter().map(i32::from))))
+        }
+
+        /// Decode CBOR, given as a list of ints, like those from `encode`.
+        #[starlark(deterministic)]
+        fn decode<'v>(
+            #[starlark(require = pos)] x: UnpackListOrTuple<i32>,
+            heap: Heap<'v>,
+        ) -> anyhow::Result<Value<'v>> {
+            let bytes = x
+                .items
+                .into_iter()
+                .map(|b| u8::try_from(b).map_err(|_| CborError::NotAByte(b)))
+                .collect::<Result<Vec<u8>, _>>()?;
+            Value::from_cbor(heap, &bytes)
+        }
+    }
+
+    globals.namespace("cbor", cbor_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_cbor_encode() {
+        let a = Assert::new();
+        a.eq("[0x83, 1, 0xf5, 0xf6]", "cbor.encode([1, True, None])");
+        a.eq("[0xa1, 0x61, 0x6b, 0x61, 0x76]", "cbor.encode({'k': 'v'})");
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let a = Assert::new();
+        a.eq(
+            "[10, None, False, {'k': 'v'}, 3.5, -9223372036854775808]",
+            "cbor.decode(cbor.encode([10, None, False, {'k': 'v'}, 3.5, -9223372036854775808]))",
+        );
+        // A byte string, and a tagged value.
+        a.eq("[1, 2]", "cbor.decode([0x42, 1, 2])");
+        a.eq("1", "cbor.decode([0xc1, 1])");
+    }
+
+    #[test]
+    fn test_cbor_decode_fail() {
+        let a = Assert::new();
+        a.fail("cbor.decode([-1])", "not in the range 0 to 255");
+        a.fail("cbor.decode([1, 2])", "Trailing 1 bytes");
+        a.fail("cbor.decode([0x61])", "Invalid CBOR");
+    }
+}
@@ -23,6 +23,7 @@ use starlark_derive::starlark_module;
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
+use crate::platform::platform;
 use crate::values::StringValue;
 use crate::values::Value;
 use crate::values::ValueOfUnchecked;
@@ -44,6 +45,7 @@ pub fn filter(builder: &mut GlobalsBuilder) {
     /// filter(None, [True, None, False]) == [True, False]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn filter<'v>(
         #[starlark(require = pos)] func: NoneOr<ValueOfUnchecked<'v, StarlarkFunction>>,
         #[starlark(require = pos)] seq: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -79,6 +81,7 @@ pub fn map(builder: &mut GlobalsBuilder) {
     /// map(lambda x: x * 2, [1, 2, 3, 4]) == [2, 4, 6, 8]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn map<'v>(
         #[starlark(require = pos)] func: ValueOfUnchecked<'v, StarlarkFunction>,
         #[starlark(require = pos)] seq: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -125,7 +128,7 @@ pub(crate) struct StderrPrintHandler;
 
 impl PrintHandler for StderrPrintHandler {
     fn println(&self, text: &str) -> crate::Result<()> {
-        eprintln!("{text}");
+        platform().write_stderr(&format!("{text}\n"));
         Ok(())
     }
 }
@@ -133,27 +136,27 @@ impl PrintHandler for StderrPrintHandler {
 #[starlark_module]
 pub fn print(builder: &mut GlobalsBuilder) {
     /// Print some values to the output.
+    #[starlark(deterministic)]
     fn print(
         #[starlark(args)] args: UnpackTuple<Value>,
         eval: &mut Evaluator,
     ) -> starlark::Result<NoneType> {
         // In practice most users should want to put the print somewhere else, but this does for now
         // Unfortunately, we can't use PrintWrapper because strings to_str() and Display are different.
-        eval.print_handler
-            .println(&args.items.iter().map(|x| x.to_str()).join(" "))?;
+        eval.println(&args.items.iter().map(|x| x.to_str()).join(" "))?;
         Ok(NoneType)
     }
 }
 
 #[starlark_module]
 pub fn pprint(builder: &mut GlobalsBuilder) {
+    #[starlark(deterministic)]
     fn pprint(
         #[starlark(args)] args: UnpackTuple<Value>,
         eval: &mut Evaluator,
     ) -> starlark::Result<NoneType> {
         // In practice most users may want to put the print somewhere else, but this does for now
-        eval.print_handler
-            .println(&format!("{:#}", PrintWrapper(&args.items)))?;
+        eval.println(&format!("{:#}", PrintWrapper(&args.items)))?;
         Ok(NoneType)
     }
 }
@@ -174,6 +177,7 @@ fn pretty_repr<'v>(
 #[starlark_module]
 pub fn pstr(builder: &mut GlobalsBuilder) {
     /// Like `str`, but produces more verbose pretty-printed output
+    #[starlark(deterministic)]
     fn pstr<'v>(
         #[starlark(require = pos)] a: Value<'v>,
         eval: &mut Evaluator<'v, '_, '_>,
@@ -189,6 +193,7 @@ pub fn pstr(builder: &mut GlobalsBuilder) {
 #[starlark_module]
 pub fn prepr(builder: &mut GlobalsBuilder) {
     /// Like `repr`, but produces more verbose pretty-printed output
+    #[starlark(deterministic)]
     fn prepr<'v>(
         #[starlark(require = pos)] a: Value<'v>,
         eval: &mut Evaluator<'v, '_, '_>,
@@ -20,6 +20,7 @@ use crate::stdlib::funcs::min_max::register_min_max;
 use crate::stdlib::funcs::other::register_other;
 use crate::stdlib::funcs::zip::register_zip;
 use crate::values::bool::globals::register_bool;
+use crate::values::bytes::globals::register_bytes;
 use crate::values::float::globals::register_float;
 use crate::values::int::globals::register_int;
 use crate::values::none::globals::register_none;
@@ -37,6 +38,7 @@ pub(crate) fn register_globals(globals: &mut GlobalsBuilder) {
     register_bool(globals);
     register_none(globals);
     register_str(globals);
+    register_bytes(globals);
     register_range(globals);
     register_int(globals);
     register_num(globals);
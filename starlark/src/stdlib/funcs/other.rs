@@ -19,10 +19,13 @@
 //! dialect of Starlark
 
 use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
 
 use starlark_derive::starlark_module;
 
 use crate as starlark;
+use crate::collections::SmallMap;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
 use crate::values::AllocValue;
@@ -32,16 +35,59 @@ use crate::values::Value;
 use crate::values::ValueError;
 use crate::values::ValueLike;
 use crate::values::list::AllocList;
+use crate::values::serde::ValueData;
 use crate::values::tuple::UnpackTuple;
 use crate::values::typing::StarlarkIter;
 use crate::values::typing::never::StarlarkNever;
 use crate::values::typing::ty::AbstractType;
 use crate::values::value_of_unchecked::ValueOfUnchecked;
 
+/// A field of a [`FailError`]. Fields are copied out of the heap, so they outlive it.
+#[derive(Debug)]
+pub(crate) struct FailField {
+    pub(crate) name: String,
+    /// [`None`] if the value is not data, like a function.
+    pub(crate) data: Option<ValueData>,
+    pub(crate) repr: String,
+}
+
+/// The error of a `fail()` call with keyword arguments.
+#[derive(Debug)]
+pub(crate) struct FailError {
+    /// The positional arguments, each preceded by a space.
+    message: String,
+    pub(crate) fields: Vec<FailField>,
+}
+
+impl FailError {
+    fn new(message: String, fields: SmallMap<String, Value>) -> FailError {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| FailField {
+                name,
+                data: ValueData::from_value(value).ok(),
+                repr: value.to_repr(),
+            })
+            .collect();
+        FailError { message, fields }
+    }
+}
+
+impl Display for FailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FailError {}
+
 #[starlark_module]
 pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// fail: fail the execution
     ///
+    /// Keyword arguments are attached to the error as fields, which are not part of
+    /// the message, but can be read by `catch()`.
+    ///
     /// ```
     /// # starlark::assert::fail(r#"
     /// fail("this is an error")  # fail: this is an error
@@ -49,8 +95,15 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// # starlark::assert::fail(r#"
     /// fail("oops", 1, False)  # fail: oops 1 False
     /// # "#, "oops 1 False");
+    /// # starlark::assert::fail(r#"
+    /// fail("bad input", code = 3)  # fail: bad input
+    /// # "#, "bad input");
     /// ```
-    fn fail(#[starlark(args)] args: UnpackTuple<Value>) -> starlark::Result<StarlarkNever> {
+    #[starlark(deterministic)]
+    fn fail<'v>(
+        #[starlark(args)] args: UnpackTuple<Value<'v>>,
+        #[starlark(kwargs)] fields: SmallMap<String, Value<'v>>,
+    ) -> starlark::Result<StarlarkNever> {
         let mut s = String::new();
         for x in args.items {
             s.push(' ');
@@ -59,9 +112,12 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
                 None => x.collect_repr(&mut s),
             }
         }
-        Err(starlark::Error::new_kind(starlark::ErrorKind::Fail(
-            anyhow::Error::msg(s),
-        )))
+        let e = if fields.is_empty() {
+            anyhow::Error::msg(s)
+        } else {
+            anyhow::Error::new(FailError::new(s, fields))
+        };
+        Err(starlark::Error::new_kind(starlark::ErrorKind::Fail(e)))
     }
 
     /// [any](
@@ -336,6 +392,7 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// ```
     // This function is not spec-safe, because it may call `key` function
     // which might be not spec-safe.
+    #[starlark(deterministic)]
     fn sorted<'v>(
         #[starlark(require = pos)] x: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
         #[starlark(require = named)] key: Option<Value<'v>>,
@@ -60,10 +60,12 @@ impl<'v, 'a> AllocValue<'v> for &'a serde_json::Number {
             heap.alloc(x)
         } else if let Some(x) = self.as_i64() {
             heap.alloc(x)
-        } else if let Some(x) = self.as_f64() {
-            heap.alloc(x)
         } else if let Ok(x) = BigInt::from_str(&self.to_string()) {
+            // Check for an integer before a float, since large integers are also
+            // representable, lossily, as floats.
             heap.alloc(StarlarkInt::from(x))
+        } else if let Some(x) = self.as_f64() {
+            heap.alloc(x)
         } else {
             panic!("Unrepresentable number: {self:?}")
         }
@@ -84,10 +86,10 @@ impl<'a> AllocFrozenValue for &'a serde_json::Number {
             heap.alloc(x)
         } else if let Some(x) = self.as_i64() {
             heap.alloc(x)
-        } else if let Some(x) = self.as_f64() {
-            heap.alloc(x)
         } else if let Ok(x) = BigInt::from_str(&self.to_string()) {
             heap.alloc(StarlarkInt::from(x))
+        } else if let Some(x) = self.as_f64() {
+            heap.alloc(x)
         } else {
             panic!("Unrepresentable number: {self:?}")
         }
@@ -211,15 +213,20 @@ impl AllocFrozenValue for serde_json::Value {
 pub(crate) fn json(globals: &mut GlobalsBuilder) {
     #[starlark_module]
     fn json_members(globals: &mut GlobalsBuilder) {
+        #[starlark(deterministic)]
         fn encode(#[starlark(require = pos)] x: Value) -> anyhow::Result<String> {
             x.to_json()
         }
 
+        #[starlark(deterministic)]
         fn decode<'v>(
             #[starlark(require = pos)] x: &str,
             heap: Heap<'v>,
         ) -> anyhow::Result<Value<'v>> {
-            Ok(heap.alloc(serde_json::from_str::<serde_json::Value>(x)?))
+            let mut de = serde_json::Deserializer::from_str(x);
+            let res = Value::from_deserialize(heap, &mut de)?;
+            de.end()?;
+            Ok(res)
         }
     }
 
@@ -232,6 +239,8 @@ pub(crate) fn json(globals: &mut GlobalsBuilder) {
 #[cfg(test)]
 mod tests {
     use crate::assert::Assert;
+    use crate::values::Heap;
+    use crate::values::Value;
 
     #[test]
     fn test_json_encode() {
@@ -260,6 +269,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_json_value_round_trip() {
+        Heap::temp(|heap| {
+            let json: serde_json::Value =
+                serde_json::from_str(r#"[1, 1.0, 123456789123456789123456789, {"k": null}]"#)
+                    .unwrap();
+            let value = Value::from_json_value(heap, &json);
+            assert_eq!(
+                r#"[1, 1.0, 123456789123456789123456789, {"k": None}]"#,
+                value.to_repr()
+            );
+            assert_eq!(json, value.to_json_value().unwrap());
+        });
+    }
+
     #[test]
     fn test_json_very_large_int() {
         let a = Assert::new();
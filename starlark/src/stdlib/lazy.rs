@@ -0,0 +1,265 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! `lazy.map` and `lazy.filter`, which compose over an iterable without building a list
+//! for each step.
+//!
+//! `lazy.map(f, lazy.filter(p, xs))` doesn't call anything, it records the steps. When the
+//! result is consumed by `lazy.collect` or `lazy.for_each`, each element of `xs` is passed
+//! through all the steps before the next one is read, so only the final results are kept.
+//! Combined with a lazy source, like `range` or a
+//! [`NativeIterator`](crate::values::native_iterator::NativeIterator), nothing is
+//! materialized before the last step.
+
+use allocative::Allocative;
+use derive_more::Display;
+use starlark_derive::Freeze;
+use starlark_derive::NoSerialize;
+use starlark_derive::Trace;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::Coerce;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::starlark_complex_value;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueLifetimeless;
+use crate::values::ValueLike;
+use crate::values::ValueOfUnchecked;
+use crate::values::function::StarlarkFunction;
+use crate::values::none::NoneOr;
+use crate::values::none::NoneType;
+use crate::values::typing::iter::StarlarkIter;
+
+#[derive(Debug, thiserror::Error)]
+enum LazyError {
+    #[error(
+        "Results of `lazy.map` and `lazy.filter` can only be consumed by `lazy.collect` or `lazy.for_each`"
+    )]
+    Iterate,
+}
+
+#[derive(Debug, Clone, Coerce, Trace, Freeze, Allocative)]
+#[repr(C)]
+enum LazyStep<V> {
+    Map(V),
+    /// `None` keeps the elements which are not `None`.
+    Filter(V),
+}
+
+/// Steps applied to each element of `source` when consumed.
+#[derive(
+    Debug,
+    Trace,
+    Coerce,
+    Freeze,
+    Display,
+    NoSerialize,
+    ProvidesStaticType,
+    Allocative
+)]
+#[display("lazy_iterable")]
+#[repr(C)]
+struct LazyIterableGen<V: ValueLifetimeless> {
+    source: V,
+    steps: Vec<LazyStep<V>>,
+}
+
+starlark_complex_value!(LazyIterable);
+
+#[starlark_value(type = "lazy_iterable")]
+impl<'v, V: ValueLike<'v>> StarlarkValue<'v> for LazyIterableGen<V>
+where
+    Self: ProvidesStaticType<'v>,
+{
+    unsafe fn iterate(&self, _me: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        // Applying the steps calls functions, which needs an evaluator.
+        Err(crate::Error::new_other(LazyError::Iterate))
+    }
+}
+
+/// `seq` with `step` added, reusing the steps of `seq` if it is lazy itself.
+fn add_step<'v>(seq: Value<'v>, step: LazyStep<Value<'v>>, heap: Heap<'v>) -> Value<'v> {
+    let (source, mut steps) = match LazyIterable::from_value(seq) {
+        Some(lazy) => (lazy.source, lazy.steps.clone()),
+        None => (seq, Vec::new()),
+    };
+    steps.push(step);
+    heap.alloc(LazyIterable { source, steps })
+}
+
+/// Pass each element of `seq` through its steps, giving the results to `f`.
+fn run<'v>(
+    seq: Value<'v>,
+    eval: &mut Evaluator<'v, '_, '_>,
+    mut f: impl FnMut(Value<'v>, &mut Evaluator<'v, '_, '_>) -> crate::Result<()>,
+) -> crate::Result<()> {
+    let (source, steps) = match LazyIterable::from_value(seq) {
+        Some(lazy) => (lazy.source, lazy.steps.as_slice()),
+        None => (seq, [].as_slice()),
+    };
+    'elems: for mut v in source.iterate(eval.heap())? {
+        for step in steps {
+            match step {
+                LazyStep::Map(func) => v = func.invoke_pos(&[v], eval)?,
+                LazyStep::Filter(func) => {
+                    let keep = if func.is_none() {
+                        !v.is_none()
+                    } else {
+                        func.invoke_pos(&[v], eval)?.to_bool()
+                    };
+                    if !keep {
+                        continue 'elems;
+                    }
+                }
+            }
+        }
+        f(v, eval)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn lazy(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn lazy_members(globals: &mut GlobalsBuilder) {
+        /// Like `map(func, seq)`, but only records the call, to be applied to each element
+        /// when the result is consumed by `lazy.collect` or `lazy.for_each`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// lazy.collect(lazy.map(lambda x: x * 2, range(4))) == [0, 2, 4, 6]
+        /// # "#);
+        /// ```
+        #[starlark(deterministic)]
+        fn map<'v>(
+            #[starlark(require = pos)] func: ValueOfUnchecked<'v, StarlarkFunction>,
+            #[starlark(require = pos)] seq: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+            heap: Heap<'v>,
+        ) -> starlark::Result<Value<'v>> {
+            Ok(add_step(seq.get(), LazyStep::Map(func.get()), heap))
+        }
+
+        /// Like `filter(func, seq)`, but only records the call, like `lazy.map`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// lazy.collect(lazy.filter(lambda x: x % 2, range(6))) == [1, 3, 5]
+        /// lazy.collect(lazy.filter(None, [1, None, 2])) == [1, 2]
+        /// # "#);
+        /// ```
+        #[starlark(deterministic)]
+        fn filter<'v>(
+            #[starlark(require = pos)] func: NoneOr<ValueOfUnchecked<'v, StarlarkFunction>>,
+            #[starlark(require = pos)] seq: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+            heap: Heap<'v>,
+        ) -> starlark::Result<Value<'v>> {
+            let func = match func {
+                NoneOr::None => Value::new_none(),
+                NoneOr::Other(func) => func.get(),
+            };
+            Ok(add_step(seq.get(), LazyStep::Filter(func), heap))
+        }
+
+        /// The elements of `seq` as a list, after applying the steps recorded by
+        /// `lazy.map` and `lazy.filter`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// lazy.collect(lazy.map(str, lazy.filter(lambda x: x > 1, [1, 2, 3]))) == ["2", "3"]
+        /// lazy.collect((1, 2)) == [1, 2]
+        /// # "#);
+        /// ```
+        #[starlark(deterministic)]
+        fn collect<'v>(
+            #[starlark(require = pos)] seq: Value<'v>,
+            eval: &mut Evaluator<'v, '_, '_>,
+        ) -> starlark::Result<Vec<Value<'v>>> {
+            let mut res = Vec::new();
+            run(seq, eval, |v, _| {
+                res.push(v);
+                Ok(())
+            })?;
+            Ok(res)
+        }
+
+        /// Call `func` with each element of `seq`, after applying the steps recorded by
+        /// `lazy.map` and `lazy.filter`, without keeping the results.
+        #[starlark(deterministic)]
+        fn for_each<'v>(
+            #[starlark(require = pos)] func: ValueOfUnchecked<'v, StarlarkFunction>,
+            #[starlark(require = pos)] seq: Value<'v>,
+            eval: &mut Evaluator<'v, '_, '_>,
+        ) -> starlark::Result<NoneType> {
+            run(seq, eval, |v, eval| {
+                func.get().invoke_pos(&[v], eval)?;
+                Ok(())
+            })?;
+            Ok(NoneType)
+        }
+    }
+
+    globals.namespace("lazy", lazy_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_lazy_steps_interleave() {
+        // Each element goes through every step before the next is read.
+        let mut a = Assert::new();
+        a.disable_static_typechecking();
+        a.is_true(
+            r#"
+log = []
+def tag(name):
+    def f(x):
+        log.append((name, x))
+        return x
+    return f
+res = lazy.collect(lazy.map(tag("b"), lazy.filter(tag("a"), [1, 0, 2])))
+res == [1, 2] and log == [("a", 1), ("b", 1), ("a", 0), ("a", 2), ("b", 2)]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_lazy_for_each() {
+        assert::is_true(
+            r#"
+res = []
+lazy.for_each(lambda x: res.append(x), lazy.map(lambda x: x + 1, range(3)))
+res == [1, 2, 3]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_lazy_iterate_fails() {
+        assert::fail(
+            "[x for x in lazy.map(str, [1])]",
+            "can only be consumed by `lazy.collect`",
+        );
+    }
+}
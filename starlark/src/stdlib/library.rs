@@ -0,0 +1,321 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A [`Library`] is a set of globals chosen at the granularity of single definitions,
+//! whose members can be tagged with the version they were introduced in.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use allocative::Allocative;
+use dupe::Dupe;
+
+use crate::analysis::Lint;
+use crate::analysis::library::LibraryWarning;
+use crate::analysis::library::lint;
+use crate::environment::Globals;
+use crate::environment::GlobalsBuilder;
+use crate::environment::LibraryExtension;
+use crate::syntax::AstModule;
+
+#[derive(Debug, thiserror::Error)]
+enum LibraryError {
+    #[error("Invalid library version `{0}`, expected `MAJOR.MINOR`")]
+    InvalidVersion(String),
+}
+
+/// A version of a [`Library`], like `1.2`, ordered by major then minor number.
+#[derive(
+    Debug, Clone, Copy, Dupe, PartialEq, Eq, PartialOrd, Ord, Hash, Allocative
+)]
+pub struct LibraryVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+}
+
+impl LibraryVersion {
+    /// The version `major.minor`.
+    pub const fn new(major: u32, minor: u32) -> LibraryVersion {
+        LibraryVersion { major, minor }
+    }
+}
+
+impl Display for LibraryVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for LibraryVersion {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<LibraryVersion> {
+        let invalid = || crate::Error::new_other(LibraryError::InvalidVersion(s.to_owned()));
+        let (major, minor) = s.split_once('.').ok_or_else(invalid)?;
+        Ok(LibraryVersion {
+            major: major.parse().map_err(|_| invalid())?,
+            minor: minor.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Used to build a [`Library`], a finer grained alternative to
+/// [`GlobalsBuilder::extended_by`].
+///
+/// Definitions are named like `print`, or `json.decode` for members of namespaces.
+/// Disabling a namespace disables all its members.
+///
+/// ```
+/// # use starlark::environment::LibraryBuilder;
+/// # use starlark::environment::LibraryExtension;
+/// # use starlark::environment::LibraryVersion;
+/// let library = LibraryBuilder::standard()
+///     .extension(LibraryExtension::Json)
+///     .disable("json.decode")
+///     .introduced_in("json.encode", LibraryVersion::new(1, 1))
+///     .pin(LibraryVersion::new(1, 0))
+///     .build();
+/// assert!(library.is_unavailable("json.decode"));
+/// assert!(library.is_unavailable("json.encode"));
+/// assert!(!library.is_unavailable("json"));
+/// ```
+pub struct LibraryBuilder {
+    builder: GlobalsBuilder,
+    disabled: HashSet<String>,
+    introduced: HashMap<String, LibraryVersion>,
+    pinned: Option<LibraryVersion>,
+}
+
+impl LibraryBuilder {
+    /// A builder with no definitions.
+    pub fn new() -> LibraryBuilder {
+        Self::from_globals(GlobalsBuilder::new())
+    }
+
+    /// A builder with the definitions of the Starlark standard.
+    pub fn standard() -> LibraryBuilder {
+        Self::from_globals(GlobalsBuilder::standard())
+    }
+
+    fn from_globals(builder: GlobalsBuilder) -> LibraryBuilder {
+        LibraryBuilder {
+            builder,
+            disabled: HashSet::new(),
+            introduced: HashMap::new(),
+            pinned: None,
+        }
+    }
+
+    /// Add the definitions of an extension.
+    pub fn extension(mut self, extension: LibraryExtension) -> Self {
+        extension.add(&mut self.builder);
+        self
+    }
+
+    /// Add the definitions of several extensions.
+    pub fn extensions(self, extensions: &[LibraryExtension]) -> Self {
+        extensions.iter().fold(self, |res, x| res.extension(*x))
+    }
+
+    /// Add the definitions made by `f`, usually those of the host.
+    pub fn with(mut self, f: impl FnOnce(&mut GlobalsBuilder)) -> Self {
+        f(&mut self.builder);
+        self
+    }
+
+    /// Leave the definition `name` out of the library.
+    pub fn disable(mut self, name: &str) -> Self {
+        self.disabled.insert(name.to_owned());
+        self
+    }
+
+    /// Undo a previous [`disable`](LibraryBuilder::disable).
+    pub fn enable(mut self, name: &str) -> Self {
+        self.disabled.remove(name);
+        self
+    }
+
+    /// Record that the definition `name` was introduced in `version`, so is left out
+    /// of libraries pinned to an earlier version.
+    pub fn introduced_in(mut self, name: &str, version: LibraryVersion) -> Self {
+        self.introduced.insert(name.to_owned(), version);
+        self
+    }
+
+    /// Only include the definitions introduced in `version` or earlier.
+    /// Definitions without an [`introduced_in`](LibraryBuilder::introduced_in) version
+    /// are always included.
+    pub fn pin(mut self, version: LibraryVersion) -> Self {
+        self.pinned = Some(version);
+        self
+    }
+
+    /// Build the [`Library`].
+    pub fn build(mut self) -> Library {
+        let mut unavailable = HashMap::new();
+        for name in &self.disabled {
+            unavailable.insert(name.clone(), Unavailable::Disabled);
+        }
+        if let Some(pinned) = self.pinned {
+            for (name, version) in &self.introduced {
+                if *version > pinned && !unavailable.contains_key(name) {
+                    unavailable.insert(name.clone(), Unavailable::Introduced(*version));
+                }
+            }
+        }
+        self.builder.retain(|name| !unavailable.contains_key(name));
+        Library {
+            globals: self.builder.build(),
+            unavailable,
+            pinned: self.pinned,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Dupe)]
+enum Unavailable {
+    Disabled,
+    Introduced(LibraryVersion),
+}
+
+/// The globals chosen by a [`LibraryBuilder`], along with what was left out,
+/// so scripts can be checked against them.
+#[derive(Debug, Clone)]
+pub struct Library {
+    globals: Globals,
+    unavailable: HashMap<String, Unavailable>,
+    pinned: Option<LibraryVersion>,
+}
+
+impl Library {
+    /// The globals to evaluate scripts with.
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    /// The version the library was pinned to, if any.
+    pub fn version(&self) -> Option<LibraryVersion> {
+        self.pinned
+    }
+
+    /// Whether the definition `name`, like `print` or `json.decode`, was left out.
+    pub fn is_unavailable(&self, name: &str) -> bool {
+        self.unavailable.contains_key(name)
+    }
+
+    /// Report the uses of definitions which were disabled, or introduced after
+    /// the pinned version. Names bound by the module, which shadow builtins, are not reported.
+    pub fn check(&self, module: &AstModule) -> Vec<Lint> {
+        let check = |name: &str| {
+            Some(match self.unavailable.get(name)? {
+                Unavailable::Disabled => LibraryWarning::Disabled(name.to_owned()),
+                Unavailable::Introduced(version) => {
+                    LibraryWarning::TooNew(name.to_owned(), *version, self.pinned?)
+                }
+            })
+        };
+        lint(module, check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert::Assert;
+    use crate::syntax::Dialect;
+
+    fn library() -> Library {
+        LibraryBuilder::standard()
+            .extensions(&[LibraryExtension::Json, LibraryExtension::Print])
+            .disable("print")
+            .introduced_in("json.decode", LibraryVersion::new(1, 3))
+            .introduced_in("zip", LibraryVersion::new(1, 1))
+            .pin(LibraryVersion::new(1, 2))
+            .build()
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(
+            LibraryVersion::new(1, 12),
+            "1.12".parse::<LibraryVersion>().unwrap()
+        );
+        assert_eq!("1.12", LibraryVersion::new(1, 12).to_string());
+        assert!(LibraryVersion::new(1, 2) < LibraryVersion::new(1, 10));
+        assert!("1".parse::<LibraryVersion>().is_err());
+        assert!("1.x".parse::<LibraryVersion>().is_err());
+    }
+
+    #[test]
+    fn test_globals() {
+        let mut a = Assert::new();
+        a.globals(library().globals().dupe());
+        a.is_true("zip([1], [2]) == [(1, 2)]");
+        a.eq("'[1]'", "json.encode([1])");
+        a.fail("print(1)", "Variable `print` not found");
+        a.fail("json.decode('1')", "no attribute `decode`");
+    }
+
+    #[test]
+    fn test_check() {
+        let module = AstModule::parse(
+            "x.star",
+            r#"
+def f(x):
+    print(x)
+    return json.decode(x)
+def g(xs):
+    return [zip for zip in xs]
+"#
+            .to_owned(),
+            &Dialect::AllOptionsInternal,
+        )
+        .unwrap();
+        let lints = library().check(&module);
+        let lints: Vec<_> = lints
+            .iter()
+            .map(|x| (x.short_name.as_str(), x.problem.as_str()))
+            .collect();
+        assert_eq!(
+            lints,
+            &[
+                (
+                    "unavailable-builtin",
+                    "`print` is not available in this library"
+                ),
+                (
+                    "builtin-too-new",
+                    "`json.decode` was introduced in 1.3, but the library is pinned to 1.2"
+                ),
+            ]
+        );
+
+        // Bindings in the module shadow the builtins.
+        let module = AstModule::parse(
+            "x.star",
+            "print = len\nprint(1)\n".to_owned(),
+            &Dialect::AllOptionsInternal,
+        )
+        .unwrap();
+        assert!(library().check(&module).is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `msgpack` library extension, with the same structure as the `json` module, and
+//! the matching [`Value::to_msgpack`] and [`Value::from_msgpack`] for the host side.
+//!
+//! Starlark strings can't hold arbitrary bytes, so the encoded form is a list of ints.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::list::AllocList;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+
+#[derive(Debug, thiserror::Error)]
+enum MsgpackError {
+    #[error("Expected a list of bytes, got `{0}`, which is not in the range 0 to 255")]
+    NotAByte(i32),
+    #[error("Trailing {0} bytes after the MessagePack value")]
+    TrailingBytes(usize),
+}
+
+impl<'v> Value<'v> {
+    /// Convert the value to MessagePack.
+    ///
+    /// Return an error if the value or any contained value does not support conversion,
+    /// as for [`to_json`](Value::to_json).
+    pub fn to_msgpack(self) -> anyhow::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(&self).map_err(|e| anyhow::anyhow!(e))
+    }
+
+    /// Build a value from a single MessagePack value.
+    ///
+    /// Maps become dicts, arrays become lists, and binary data becomes a list of ints.
+    pub fn from_msgpack(heap: Heap<'v>, mut bytes: &[u8]) -> anyhow::Result<Value<'v>> {
+        let mut de = rmp_serde::Deserializer::new(&mut bytes);
+        let res = Value::from_deserialize(heap, &mut de)?;
+        if !bytes.is_empty() {
+            return Err(MsgpackError::TrailingBytes(bytes.len()).into());
+        }
+        Ok(res)
+    }
+}
+
+pub(crate) fn msgpack(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn msgpack_members(globals: &mut GlobalsBuilder) {
+        /// Encode a value as MessagePack, returning the bytes as a list of ints.
+        #[starlark(deterministic)]
+        fn encode<'v>(
+            #[starlark(require = pos)] x: Value<'v>,
+            heap: Heap<'v>,
+        ) -> anyhow::Result<Value<'v>> {
+            Ok(heap.alloc(AllocList(x.to_msgpack()?.into_iter().map(i32::from))))
+        }
+
+        /// Decode MessagePack, given as a list of ints, like those from `encode`.
+        #[starlark(deterministic)]
+        fn decode<'v>(
+            #[starlark(require = pos)] x: UnpackListOrTuple<i32>,
+            heap: Heap<'v>,
+        ) -> anyhow::Result<Value<'v>> {
+            let bytes = x
+                .items
+                .into_iter()
+                .map(|b| u8::try_from(b).map_err(|_| MsgpackError::NotAByte(b)))
+                .collect::<Result<Vec<u8>, _>>()?;
+            Value::from_msgpack(heap, &bytes)
+        }
+    }
+
+    globals.namespace("msgpack", msgpack_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_msgpack_encode() {
+        let a = Assert::new();
+        a.eq("[0x93, 1, 0xc3, 0xc0]", "msgpack.encode([1, True, None])");
+        a.eq(
+            "[0x81, 0xa1, 0x6b, 0xa1, 0x76]",
+            "msgpack.encode({'k': 'v'})",
+        );
+    }
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let a = Assert::new();
+        a.eq(
+            "[10, None, False, {'k': 'v'}, 3.5, 9223372036854775807]",
+            "msgpack.decode(msgpack.encode([10, None, False, {'k': 'v'}, 3.5, 9223372036854775807]))",
+        );
+        // Binary data, `bin 8` of two bytes.
+        a.eq("[1, 2]", "msgpack.decode([0xc4, 2, 1, 2])");
+    }
+
+    #[test]
+    fn test_msgpack_decode_fail() {
+        let a = Assert::new();
+        a.fail("msgpack.decode([256])", "not in the range 0 to 255");
+        a.fail("msgpack.decode([1, 2])", "Trailing 1 bytes");
+        a.fail("msgpack.encode(len)", "not supported on type");
+    }
+}
@@ -0,0 +1,209 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `os` library extension, where everything must be granted by an [`OsPolicy`].
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::none::NoneOr;
+
+#[derive(Debug, thiserror::Error)]
+enum OsError {
+    #[error("Reading `{0}` is not allowed, see `Evaluator::set_os_policy`")]
+    ReadNotAllowed(String),
+    #[error("Reading environment variable `{0}` is not allowed, see `Evaluator::set_os_policy`")]
+    EnvNotAllowed(String),
+    #[error("Failed to read `{0}`: {1}")]
+    Io(String, io::Error),
+}
+
+/// The operating system access granted to `os` functions, set with
+/// [`Evaluator::set_os_policy`]. Nothing is allowed unless granted.
+///
+/// ```
+/// use starlark::environment::OsPolicy;
+///
+/// let policy = OsPolicy::new()
+///     .allow_read("config")
+///     .allow_env("HOME");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct OsPolicy {
+    read: Vec<PathBuf>,
+    env: Vec<String>,
+}
+
+impl OsPolicy {
+    /// A policy which allows nothing.
+    pub fn new() -> OsPolicy {
+        OsPolicy::default()
+    }
+
+    /// Allow reading `path`, and everything below it if it is a directory.
+    ///
+    /// Paths are compared after resolving symlinks, so links can't escape the directory.
+    pub fn allow_read(mut self, path: impl Into<PathBuf>) -> OsPolicy {
+        self.read.push(path.into());
+        self
+    }
+
+    /// Allow reading the environment variable `name`.
+    pub fn allow_env(mut self, name: impl Into<String>) -> OsPolicy {
+        self.env.push(name.into());
+        self
+    }
+
+    fn check_read(&self, path: &str) -> crate::Result<PathBuf> {
+        let not_allowed = || crate::Error::new_other(OsError::ReadNotAllowed(path.to_owned()));
+        // Missing files are reported as not allowed, so scripts can't probe for them.
+        let resolved = fs::canonicalize(path).map_err(|_| not_allowed())?;
+        let allowed = self.read.iter().any(|root| match fs::canonicalize(root) {
+            Ok(root) => resolved.starts_with(root),
+            Err(_) => false,
+        });
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(not_allowed())
+        }
+    }
+
+    fn check_env(&self, name: &str) -> crate::Result<()> {
+        if self.env.iter().any(|x| x == name) {
+            Ok(())
+        } else {
+            Err(crate::Error::new_other(OsError::EnvNotAllowed(
+                name.to_owned(),
+            )))
+        }
+    }
+}
+
+fn policy<'a>(eval: &'a Evaluator) -> &'a OsPolicy {
+    static DENY_ALL: OsPolicy = OsPolicy {
+        read: Vec::new(),
+        env: Vec::new(),
+    };
+    eval.os_policy.as_ref().unwrap_or(&DENY_ALL)
+}
+
+fn io_error(path: &Path, e: io::Error) -> crate::Error {
+    crate::Error::new_other(OsError::Io(path.display().to_string(), e))
+}
+
+pub(crate) fn os(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn os_members(globals: &mut GlobalsBuilder) {
+        /// Read the file at `path` as a string.
+        fn read_file(
+            #[starlark(require = pos)] path: &str,
+            eval: &mut Evaluator,
+        ) -> starlark::Result<String> {
+            let path = policy(eval).check_read(path)?;
+            fs::read_to_string(&path).map_err(|e| io_error(&path, e))
+        }
+
+        /// The names of the entries of the directory at `path`, sorted.
+        fn list_dir(
+            #[starlark(require = pos)] path: &str,
+            eval: &mut Evaluator,
+        ) -> starlark::Result<Vec<String>> {
+            let path = policy(eval).check_read(path)?;
+            let mut res = Vec::new();
+            for entry in fs::read_dir(&path).map_err(|e| io_error(&path, e))? {
+                let entry = entry.map_err(|e| io_error(&path, e))?;
+                res.push(entry.file_name().to_string_lossy().into_owned());
+            }
+            res.sort();
+            Ok(res)
+        }
+
+        /// The value of the environment variable `name`, or `default` if it is not set.
+        fn getenv(
+            #[starlark(require = pos)] name: &str,
+            #[starlark(require = pos, default = NoneOr::None)] default: NoneOr<String>,
+            eval: &mut Evaluator,
+        ) -> starlark::Result<NoneOr<String>> {
+            policy(eval).check_env(name)?;
+            Ok(match std::env::var(name) {
+                Ok(value) => NoneOr::Other(value),
+                Err(_) => default,
+            })
+        }
+    }
+
+    globals.namespace("os", os_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::assert::Assert;
+    use crate::environment::Globals;
+    use crate::environment::LibraryExtension;
+    use crate::environment::OsPolicy;
+
+    fn assert_with_policy(policy: OsPolicy) -> Assert<'static> {
+        let mut a = Assert::new();
+        a.globals(Globals::extended_by(&[LibraryExtension::Os]));
+        a.setup_eval(move |eval| eval.set_os_policy(policy.clone()));
+        a
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = std::env::temp_dir().join(format!("starlark_os_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "hello").unwrap();
+        let dir_str = dir.to_str().unwrap().replace('\\', "/");
+
+        let a = assert_with_policy(OsPolicy::new().allow_read(dir.join("sub")));
+        a.eq("'hello'", &format!("os.read_file('{dir_str}/sub/a.txt')"));
+        a.eq("['a.txt']", &format!("os.list_dir('{dir_str}/sub')"));
+        a.fail(&format!("os.list_dir('{dir_str}')"), "is not allowed");
+        a.fail(
+            &format!("os.read_file('{dir_str}/sub/../sub/missing.txt')"),
+            "is not allowed",
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_getenv() {
+        let a = assert_with_policy(OsPolicy::new().allow_env("STARLARK_OS_TEST_UNSET"));
+        a.eq("'x'", "os.getenv('STARLARK_OS_TEST_UNSET', 'x')");
+        a.eq("None", "os.getenv('STARLARK_OS_TEST_UNSET')");
+        a.fail("os.getenv('PATH')", "is not allowed");
+    }
+
+    #[test]
+    fn test_no_policy() {
+        let mut a = Assert::new();
+        a.globals(Globals::extended_by(&[LibraryExtension::Os]));
+        a.fail("os.getenv('PATH')", "is not allowed");
+    }
+}
@@ -57,6 +57,7 @@ use crate::values::types::tuple::value::Tuple;
 #[starlark_module]
 pub fn partial(builder: &mut GlobalsBuilder) {
     /// Construct a partial application. In almost all cases it is simpler to use a `lamdba`.
+    #[starlark(deterministic)]
     fn partial<'v>(
         #[starlark(require = pos)] func: Value<'v>,
         #[starlark(args)] args: Value<'v>,
@@ -157,6 +158,11 @@ where
         "partial".to_owned()
     }
 
+    fn is_deterministic(&self) -> bool {
+        // The function is checked when it is called.
+        true
+    }
+
     fn invoke(
         &self,
         _me: Value<'v>,
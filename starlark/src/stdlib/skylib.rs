@@ -0,0 +1,232 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Native implementations of the most used [Bazel skylib](https://github.com/bazelbuild/bazel-skylib)
+//! modules, loaded with [`SkylibFileLoader`].
+
+mod collections;
+mod dicts;
+mod paths;
+mod sets;
+mod shell;
+mod types;
+
+use crate::environment::FrozenModule;
+use crate::environment::GlobalsBuilder;
+use crate::eval::FileLoader;
+
+#[derive(Debug, thiserror::Error)]
+enum SkylibError {
+    #[error("Unknown skylib module `{0}`, available are: {1}")]
+    UnknownModule(String, String),
+    #[error("Can't load `{0}`, only skylib modules can be loaded")]
+    NoFallback(String),
+}
+
+/// The prefixes of skylib labels, for repository names with and without bzlmod.
+const PREFIXES: &[&str] = &["@bazel_skylib//lib:", "@@bazel_skylib//lib:"];
+
+/// The skylib files provided, and the struct-like value each exports, named after the file.
+const MODULES: &[(&str, fn(&mut GlobalsBuilder))] = &[
+    ("collections", collections::collections_members),
+    ("dicts", dicts::dicts_members),
+    ("paths", paths::paths_members),
+    ("sets", sets::sets_members),
+    ("shell", shell::shell_members),
+    ("types", types::types_members),
+];
+
+/// A [`FileLoader`] which resolves skylib labels like `@bazel_skylib//lib:paths.bzl` to
+/// native implementations, so `.bzl` files written for Bazel can be run unmodified.
+/// Other paths go to the fallback loader.
+///
+/// The modules are `collections`, `dicts`, `paths`, `sets` (the dict based sets of
+/// `new_sets.bzl`), `shell` and `types`.
+///
+/// ```
+/// # use starlark::environment::Globals;
+/// # use starlark::environment::Module;
+/// # use starlark::eval::Evaluator;
+/// # use starlark::eval::SkylibFileLoader;
+/// # use starlark::syntax::AstModule;
+/// # use starlark::syntax::Dialect;
+/// let code = r#"
+/// load("@bazel_skylib//lib:paths.bzl", "paths")
+/// paths.join("a", "b")
+/// "#;
+/// let ast = AstModule::parse("BUILD.bzl", code.to_owned(), &Dialect::Standard).unwrap();
+/// let loader = SkylibFileLoader::new();
+/// let res = Module::with_temp_heap(|module| {
+///     let mut eval = Evaluator::new(&module);
+///     eval.set_loader(&loader);
+///     eval.eval_module(ast, &Globals::standard()).unwrap().to_str()
+/// });
+/// assert_eq!("a/b", res);
+/// ```
+#[derive(Default)]
+pub struct SkylibFileLoader<'a> {
+    fallback: Option<&'a dyn FileLoader>,
+}
+
+impl<'a> SkylibFileLoader<'a> {
+    /// A loader for only the skylib modules.
+    pub fn new() -> SkylibFileLoader<'a> {
+        SkylibFileLoader { fallback: None }
+    }
+
+    /// A loader for the skylib modules, which loads other paths with `fallback`.
+    pub fn with_fallback(fallback: &'a dyn FileLoader) -> SkylibFileLoader<'a> {
+        SkylibFileLoader {
+            fallback: Some(fallback),
+        }
+    }
+
+    /// The skylib module for `path`, if it is a skylib label.
+    fn skylib_module(path: &str) -> Option<crate::Result<FrozenModule>> {
+        let file = PREFIXES.iter().find_map(|p| path.strip_prefix(p))?;
+        let name = file.strip_suffix(".bzl").unwrap_or(file);
+        let Some((name, members)) = MODULES.iter().find(|(n, _)| *n == name) else {
+            let available = MODULES.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+            return Some(Err(crate::Error::new_other(SkylibError::UnknownModule(
+                path.to_owned(),
+                available.join(", "),
+            ))));
+        };
+        let globals = GlobalsBuilder::new().with_namespace(name, members).build();
+        Some(FrozenModule::from_globals(&globals).map_err(Into::into))
+    }
+}
+
+impl<'a> FileLoader for SkylibFileLoader<'a> {
+    fn load(&self, path: &str) -> crate::Result<FrozenModule> {
+        if let Some(module) = Self::skylib_module(path) {
+            return module;
+        }
+        match self.fallback {
+            Some(fallback) => fallback.load(path),
+            None => Err(crate::Error::new_other(SkylibError::NoFallback(
+                path.to_owned(),
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert::Assert;
+
+    fn assert() -> Assert<'static> {
+        let mut a = Assert::new();
+        let loader = SkylibFileLoader::new();
+        for (name, _) in MODULES {
+            let path = format!("@bazel_skylib//lib:{name}.bzl");
+            a.module_add(&path, loader.load(&path).unwrap());
+        }
+        a
+    }
+
+    #[test]
+    fn test_paths() {
+        assert().is_true(
+            r#"
+load("@bazel_skylib//lib:paths.bzl", "paths")
+all([
+    paths.basename("a/b.txt") == "b.txt",
+    paths.dirname("a/b/c") == "a/b",
+    paths.dirname("/a") == "/",
+    paths.join("a", "b", "/c", "d") == "/c/d",
+    paths.normalize("a/./b/../c//d/") == "a/c/d",
+    paths.normalize("/../a") == "/a",
+    paths.normalize("../a") == "../a",
+    paths.relativize("a/b/c", "a") == "b/c",
+    paths.split_extension("a/b.tar.gz") == ("a/b.tar", ".gz"),
+    paths.split_extension("a/.bashrc") == ("a/.bashrc", ""),
+    paths.replace_extension("a.txt", ".md") == "a.md",
+    paths.starts_with("a/b/c", "a/b"),
+    not paths.starts_with("a/bc", "a/b"),
+    paths.is_normalized("a/b"),
+    not paths.is_normalized("a/../b"),
+])
+"#,
+        );
+        assert().fail(
+            r#"
+load("@bazel_skylib//lib:paths.bzl", "paths")
+paths.relativize("a/b", "c")
+"#,
+            "is not beneath",
+        );
+    }
+
+    #[test]
+    fn test_sets() {
+        assert().is_true(
+            r#"
+load("@bazel_skylib//lib:sets.bzl", "sets")
+load("@bazel_skylib//lib:types.bzl", "types")
+s = sets.make([1, 2, 2])
+all([
+    sets.insert(s, 3) == s,
+    sets.to_list(s) == [1, 2, 3],
+    sets.contains(s, 2),
+    sets.length(sets.remove(s, 2)) == 2,
+    sets.to_list(sets.union(s, sets.make([4]))) == [1, 3, 4],
+    sets.to_list(sets.intersection(s, sets.make([3, 4]))) == [3],
+    sets.to_list(sets.difference(s, sets.make([3]))) == [1],
+    sets.is_subset(sets.make([1]), s),
+    sets.disjoint(s, sets.make([5])),
+    sets.is_equal(sets.copy(s), s),
+    sets.repr(s) == "[1, 3]",
+    types.is_set(s),
+])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_dicts_collections_shell_types() {
+        assert().is_true(
+            r#"
+load("@bazel_skylib//lib:dicts.bzl", "dicts")
+load("@bazel_skylib//lib:collections.bzl", "collections")
+load("@bazel_skylib//lib:shell.bzl", "shell")
+load("@bazel_skylib//lib:types.bzl", "types")
+all([
+    dicts.add({"a": 1}, {"a": 2, "b": 3}, c = 4) == {"a": 2, "b": 3, "c": 4},
+    dicts.omit({"a": 1, "b": 2}, ["a"]) == {"b": 2},
+    dicts.pick({"a": 1, "b": 2}, ["b", "c"]) == {"b": 2},
+    collections.uniq([1, 2, 1, 3]) == [1, 2, 3],
+    collections.after_each(",", ["a", "b"]) == ["a", ",", "b", ","],
+    collections.before_each("-x", ["a"]) == ["-x", "a"],
+    shell.quote("it's") == "'it'\\''s'",
+    shell.array_literal(["a", 1]) == "('a' '1')",
+    types.is_list([]) and types.is_string("") and types.is_dict({}) and types.is_none(None),
+    types.is_function(len) and not types.is_depset([]),
+])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_unknown() {
+        let loader = SkylibFileLoader::new();
+        let err = loader.load("@bazel_skylib//lib:unittest.bzl").unwrap_err();
+        assert!(err.to_string().contains("Unknown skylib module"));
+        assert!(loader.load("foo.bzl").is_err());
+    }
+}
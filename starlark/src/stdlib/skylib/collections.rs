@@ -0,0 +1,74 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `collections.bzl`.
+
+use starlark_derive::starlark_module;
+use starlark_map::small_set::SmallSet;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::Value;
+use crate::values::ValueOfUnchecked;
+use crate::values::typing::iter::StarlarkIter;
+
+#[starlark_module]
+pub(crate) fn collections_members(builder: &mut GlobalsBuilder) {
+    /// The elements of `iterable`, each followed by `separator`.
+    #[starlark(deterministic)]
+    fn after_each<'v>(
+        #[starlark(require = pos)] separator: Value<'v>,
+        #[starlark(require = pos)] iterable: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Vec<Value<'v>>> {
+        let mut res = Vec::new();
+        for x in iterable.get().iterate(eval.heap())? {
+            res.push(x);
+            res.push(separator);
+        }
+        Ok(res)
+    }
+
+    /// The elements of `iterable`, each preceded by `separator`.
+    #[starlark(deterministic)]
+    fn before_each<'v>(
+        #[starlark(require = pos)] separator: Value<'v>,
+        #[starlark(require = pos)] iterable: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Vec<Value<'v>>> {
+        let mut res = Vec::new();
+        for x in iterable.get().iterate(eval.heap())? {
+            res.push(separator);
+            res.push(x);
+        }
+        Ok(res)
+    }
+
+    /// The elements of `iterable` without duplicates, keeping the first occurrence.
+    #[starlark(deterministic)]
+    fn uniq<'v>(
+        #[starlark(require = pos)] iterable: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Vec<Value<'v>>> {
+        let mut res = SmallSet::new();
+        for x in iterable.get().iterate(eval.heap())? {
+            res.insert_hashed(x.get_hashed()?);
+        }
+        Ok(res.into_iter().collect())
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `dicts.bzl`.
+
+use starlark_derive::starlark_module;
+use starlark_map::small_map::SmallMap;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::Value;
+use crate::values::dict::Dict;
+use crate::values::dict::DictRef;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::tuple::UnpackTuple;
+
+#[starlark_module]
+pub(crate) fn dicts_members(builder: &mut GlobalsBuilder) {
+    /// A new dict with the entries of all the dicts, and then the keyword arguments, with
+    /// later entries replacing earlier ones.
+    #[starlark(deterministic)]
+    fn add<'v>(
+        #[starlark(args)] dictionaries: UnpackTuple<DictRef<'v>>,
+        #[starlark(kwargs)] kwargs: DictRef<'v>,
+    ) -> anyhow::Result<Dict<'v>> {
+        let mut res = SmallMap::new();
+        for d in dictionaries.items.iter().chain([&kwargs]) {
+            for (k, v) in d.iter_hashed() {
+                res.insert_hashed(k, v);
+            }
+        }
+        Ok(Dict::new(res))
+    }
+
+    /// A new dict without the given keys.
+    #[starlark(deterministic)]
+    fn omit<'v>(
+        #[starlark(require = pos)] dictionary: DictRef<'v>,
+        #[starlark(require = pos)] keys: UnpackListOrTuple<Value<'v>>,
+    ) -> starlark::Result<Dict<'v>> {
+        let mut res: SmallMap<Value<'v>, Value<'v>> = dictionary.iter_hashed().collect();
+        for k in keys.items {
+            res.shift_remove_hashed(k.get_hashed()?.as_ref());
+        }
+        Ok(Dict::new(res))
+    }
+
+    /// A new dict with only the given keys, in the order of `keys`. Missing keys are ignored.
+    #[starlark(deterministic)]
+    fn pick<'v>(
+        #[starlark(require = pos)] dictionary: DictRef<'v>,
+        #[starlark(require = pos)] keys: UnpackListOrTuple<Value<'v>>,
+    ) -> starlark::Result<Dict<'v>> {
+        let mut res = SmallMap::new();
+        for k in keys.items {
+            let k = k.get_hashed()?;
+            if let Some(v) = dictionary.get_hashed(k) {
+                res.insert_hashed(k, v);
+            }
+        }
+        Ok(Dict::new(res))
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `paths.bzl`: manipulation of `/`-separated paths.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::tuple::UnpackTuple;
+
+#[derive(Debug, thiserror::Error)]
+enum PathsError {
+    #[error("Path '{0}' is not beneath '{1}'")]
+    NotBeneath(String, String),
+}
+
+fn basename_of(p: &str) -> &str {
+    p.rsplit_once('/').map_or(p, |(_, base)| base)
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || (bytes.len() > 2 && bytes[1] == b':' && (bytes[2] == b'/' || bytes[2] == b'\\'))
+}
+
+fn normalize_path(path: &str) -> String {
+    if path.is_empty() {
+        return ".".to_owned();
+    }
+    let initial_slashes = if path.starts_with("//") && !path.starts_with("///") {
+        2
+    } else if path.starts_with('/') {
+        1
+    } else {
+        0
+    };
+    let is_relative = initial_slashes == 0;
+    let mut components: Vec<&str> = Vec::new();
+    for c in path.split('/') {
+        match c {
+            "" | "." => {}
+            ".." => {
+                if components.last().is_some_and(|x| *x != "..") {
+                    components.pop();
+                } else if is_relative {
+                    components.push(c);
+                }
+            }
+            _ => components.push(c),
+        }
+    }
+    let res = "/".repeat(initial_slashes) + &components.join("/");
+    if res.is_empty() { ".".to_owned() } else { res }
+}
+
+fn split_ext(p: &str) -> (&str, &str) {
+    let b = basename_of(p);
+    match b.rfind('.') {
+        None | Some(0) => (p, ""),
+        Some(dot) => p.split_at(p.len() - (b.len() - dot)),
+    }
+}
+
+#[starlark_module]
+pub(crate) fn paths_members(builder: &mut GlobalsBuilder) {
+    /// The part of `p` after the last `/`.
+    #[starlark(deterministic)]
+    fn basename(#[starlark(require = pos)] p: &str) -> anyhow::Result<String> {
+        Ok(basename_of(p).to_owned())
+    }
+
+    /// The part of `p` before the last `/`, without trailing slashes.
+    #[starlark(deterministic)]
+    fn dirname(#[starlark(require = pos)] p: &str) -> anyhow::Result<String> {
+        Ok(match p.rsplit_once('/') {
+            None => String::new(),
+            Some(("", _)) => "/".to_owned(),
+            Some((prefix, _)) => prefix.trim_end_matches('/').to_owned(),
+        })
+    }
+
+    /// Whether `path` is absolute, including Windows paths like `C:/x`.
+    #[starlark(deterministic)]
+    fn is_absolute(#[starlark(require = pos)] path: &str) -> anyhow::Result<bool> {
+        Ok(is_absolute_path(path))
+    }
+
+    /// Join path components, where an absolute component discards those before it.
+    #[starlark(deterministic)]
+    fn join(
+        #[starlark(require = pos)] path: &str,
+        #[starlark(args)] others: UnpackTuple<&str>,
+    ) -> anyhow::Result<String> {
+        let mut res = path.to_owned();
+        for p in others.items {
+            if is_absolute_path(p) {
+                res = p.to_owned();
+            } else if res.is_empty() || res.ends_with('/') {
+                res.push_str(p);
+            } else {
+                res.push('/');
+                res.push_str(p);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Remove `.` components, resolve `..` components and duplicate slashes.
+    #[starlark(deterministic)]
+    fn normalize(#[starlark(require = pos)] path: &str) -> anyhow::Result<String> {
+        Ok(normalize_path(path))
+    }
+
+    /// Whether `str` has no `..` components, and no `.` components unless
+    /// `look_for_same_level_references` is false.
+    #[starlark(deterministic)]
+    fn is_normalized(
+        #[starlark(require = pos)] str: &str,
+        #[starlark(default = true)] look_for_same_level_references: bool,
+    ) -> anyhow::Result<bool> {
+        Ok(str
+            .split('/')
+            .all(|c| c != ".." && !(look_for_same_level_references && c == ".")))
+    }
+
+    /// The path of `path` relative to `start`, which must be an ancestor of it.
+    #[starlark(deterministic)]
+    fn relativize(
+        #[starlark(require = pos)] path: &str,
+        #[starlark(require = pos)] start: &str,
+    ) -> anyhow::Result<String> {
+        let normalized = normalize_path(path);
+        let segments: Vec<&str> = normalized.split('/').collect();
+        let normalized_start = normalize_path(start);
+        let start_segments: Vec<&str> = match normalized_start.as_str() {
+            "." => Vec::new(),
+            s => s.split('/').collect(),
+        };
+        let not_beneath = || PathsError::NotBeneath(path.to_owned(), start.to_owned());
+        if path.starts_with('/') != start.starts_with('/') || segments.len() < start_segments.len()
+        {
+            return Err(not_beneath().into());
+        }
+        if start_segments.iter().zip(&segments).any(|(a, b)| a != b) {
+            return Err(not_beneath().into());
+        }
+        Ok(segments[start_segments.len()..].join("/"))
+    }
+
+    /// Replace the extension of `p`, including its dot, with `new_extension`.
+    #[starlark(deterministic)]
+    fn replace_extension(
+        #[starlark(require = pos)] p: &str,
+        #[starlark(require = pos)] new_extension: &str,
+    ) -> anyhow::Result<String> {
+        Ok(format!("{}{}", split_ext(p).0, new_extension))
+    }
+
+    /// Split `p` into the part before the extension, and the extension with its dot.
+    #[starlark(deterministic)]
+    fn split_extension(#[starlark(require = pos)] p: &str) -> anyhow::Result<(String, String)> {
+        let (root, ext) = split_ext(p);
+        Ok((root.to_owned(), ext.to_owned()))
+    }
+
+    /// Whether `path_a` is `path_b`, or below it, after normalizing both.
+    #[starlark(deterministic)]
+    fn starts_with(
+        #[starlark(require = pos)] path_a: &str,
+        #[starlark(require = pos)] path_b: &str,
+    ) -> anyhow::Result<bool> {
+        let a = normalize_path(path_a);
+        let b = normalize_path(path_b);
+        Ok(match a.strip_prefix(b.as_str()) {
+            None => false,
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || b.ends_with('/'),
+        })
+    }
+}
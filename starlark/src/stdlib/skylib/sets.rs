@@ -0,0 +1,268 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `sets.bzl`: sets represented, as in skylib, by `struct(_values = dict)`, so they
+//! interoperate with Starlark code written against skylib.
+
+use starlark_derive::starlark_module;
+use starlark_map::small_map::SmallMap;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::ValueOfUnchecked;
+use crate::values::dict::Dict;
+use crate::values::dict::DictMut;
+use crate::values::dict::DictRef;
+use crate::values::none::NoneOr;
+use crate::values::structs::AllocStruct;
+use crate::values::tuple::UnpackTuple;
+use crate::values::typing::iter::StarlarkIter;
+
+#[derive(Debug, thiserror::Error)]
+enum SetsError {
+    #[error("Expected a set created by `sets.make`, got a value of type `{0}`")]
+    NotASet(&'static str),
+}
+
+fn make_set<'v>(
+    heap: Heap<'v>,
+    elements: impl IntoIterator<Item = Value<'v>>,
+) -> crate::Result<Value<'v>> {
+    let mut values = SmallMap::new();
+    for x in elements {
+        values.insert_hashed(x.get_hashed()?, Value::new_none());
+    }
+    let values = heap.alloc(Dict::new(values));
+    Ok(heap.alloc(AllocStruct([("_values", values)])))
+}
+
+/// The `_values` dict of a set.
+fn set_values<'v>(s: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+    match s.get_attr("_values", heap)? {
+        Some(values) if DictRef::from_value(values).is_some() => Ok(values),
+        _ => Err(crate::Error::new_other(SetsError::NotASet(s.get_type()))),
+    }
+}
+
+/// The elements of a set, in insertion order.
+fn set_elements<'v>(s: Value<'v>, heap: Heap<'v>) -> crate::Result<Vec<Value<'v>>> {
+    let values = set_values(s, heap)?;
+    Ok(DictRef::from_value(values).unwrap().keys().collect())
+}
+
+fn set_contains<'v>(s: Value<'v>, x: Value<'v>, heap: Heap<'v>) -> crate::Result<bool> {
+    let values = set_values(s, heap)?;
+    Ok(DictRef::from_value(values).unwrap().get(x)?.is_some())
+}
+
+#[starlark_module]
+pub(crate) fn sets_members(builder: &mut GlobalsBuilder) {
+    /// A new set, with the given elements.
+    #[starlark(deterministic)]
+    fn make<'v>(
+        #[starlark(require = pos, default = NoneOr::None)] elements: NoneOr<
+            ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+        >,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let elements = match elements {
+            NoneOr::None => Vec::new(),
+            NoneOr::Other(xs) => xs.get().iterate(eval.heap())?.collect(),
+        };
+        make_set(eval.heap(), elements)
+    }
+
+    /// A new set with the same elements as `s`.
+    #[starlark(deterministic)]
+    fn copy<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let xs = set_elements(s, eval.heap())?;
+        make_set(eval.heap(), xs)
+    }
+
+    /// The elements of `s`, in insertion order.
+    #[starlark(deterministic)]
+    fn to_list<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Vec<Value<'v>>> {
+        set_elements(s, eval.heap())
+    }
+
+    /// Add `e` to `s`, returning `s`.
+    #[starlark(deterministic)]
+    fn insert<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        #[starlark(require = pos)] e: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let values = set_values(s, eval.heap())?;
+        DictMut::from_value(values)?
+            .aref
+            .insert_hashed(e.get_hashed()?, Value::new_none());
+        Ok(s)
+    }
+
+    /// Remove `e` from `s`, if it is present, returning `s`.
+    #[starlark(deterministic)]
+    fn remove<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        #[starlark(require = pos)] e: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let values = set_values(s, eval.heap())?;
+        DictMut::from_value(values)?
+            .aref
+            .remove_hashed(e.get_hashed()?);
+        Ok(s)
+    }
+
+    /// Whether `e` is in `a`.
+    #[starlark(deterministic)]
+    fn contains<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] e: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<bool> {
+        set_contains(a, e, eval.heap())
+    }
+
+    /// The number of elements in `s`.
+    #[starlark(deterministic)]
+    fn length<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<i32> {
+        Ok(set_elements(s, eval.heap())?.len() as i32)
+    }
+
+    /// Whether `a` and `b` have the same elements.
+    #[starlark(deterministic)]
+    fn is_equal<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<bool> {
+        let heap = eval.heap();
+        set_values(a, heap)?.equals(set_values(b, heap)?)
+    }
+
+    /// Whether every element of `a` is in `b`.
+    #[starlark(deterministic)]
+    fn is_subset<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<bool> {
+        let heap = eval.heap();
+        for x in set_elements(a, heap)? {
+            if !set_contains(b, x, heap)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Whether `a` and `b` have no elements in common.
+    #[starlark(deterministic)]
+    fn disjoint<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<bool> {
+        let heap = eval.heap();
+        for x in set_elements(a, heap)? {
+            if set_contains(b, x, heap)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// A new set of the elements in both `a` and `b`.
+    #[starlark(deterministic)]
+    fn intersection<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let heap = eval.heap();
+        let mut res = Vec::new();
+        for x in set_elements(a, heap)? {
+            if set_contains(b, x, heap)? {
+                res.push(x);
+            }
+        }
+        make_set(heap, res)
+    }
+
+    /// A new set of the elements in any of the arguments.
+    #[starlark(deterministic)]
+    fn union<'v>(
+        #[starlark(args)] args: UnpackTuple<Value<'v>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let heap = eval.heap();
+        let mut res = Vec::new();
+        for s in args.items {
+            res.extend(set_elements(s, heap)?);
+        }
+        make_set(heap, res)
+    }
+
+    /// A new set of the elements in `a` but not in `b`.
+    #[starlark(deterministic)]
+    fn difference<'v>(
+        #[starlark(require = pos)] a: Value<'v>,
+        #[starlark(require = pos)] b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let heap = eval.heap();
+        let mut res = Vec::new();
+        for x in set_elements(a, heap)? {
+            if !set_contains(b, x, heap)? {
+                res.push(x);
+            }
+        }
+        make_set(heap, res)
+    }
+
+    /// The representation of `s`, as the list of its elements.
+    #[starlark(deterministic)]
+    fn repr<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<String> {
+        let xs = set_elements(s, eval.heap())?;
+        Ok(eval.heap().alloc(xs).to_repr())
+    }
+
+    /// Same as `repr`.
+    #[starlark(deterministic)]
+    fn str<'v>(
+        #[starlark(require = pos)] s: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<String> {
+        let xs = set_elements(s, eval.heap())?;
+        Ok(eval.heap().alloc(xs).to_repr())
+    }
+}
@@ -0,0 +1,54 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `shell.bzl`: quoting for POSIX shells.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::Value;
+use crate::values::ValueOfUnchecked;
+use crate::values::typing::iter::StarlarkIter;
+
+fn quote_str(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[starlark_module]
+pub(crate) fn shell_members(builder: &mut GlobalsBuilder) {
+    /// Quote `s` so a shell treats it as a single word.
+    #[starlark(deterministic)]
+    fn quote(#[starlark(require = pos)] s: &str) -> anyhow::Result<String> {
+        Ok(quote_str(s))
+    }
+
+    /// A shell array literal, like `('a' 'b')`, of the quoted string forms of the elements.
+    #[starlark(deterministic)]
+    fn array_literal<'v>(
+        #[starlark(require = pos)] iterable: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<String> {
+        let words: Vec<String> = iterable
+            .get()
+            .iterate(eval.heap())?
+            .map(|x| quote_str(&x.to_str()))
+            .collect();
+        Ok(format!("({})", words.join(" ")))
+    }
+}
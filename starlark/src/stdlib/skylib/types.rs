@@ -0,0 +1,98 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Skylib `types.bzl`: type predicates.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::Value;
+use crate::values::structs::StructRef;
+
+#[starlark_module]
+pub(crate) fn types_members(builder: &mut GlobalsBuilder) {
+    /// Whether `v` is a list.
+    #[starlark(deterministic)]
+    fn is_list(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "list")
+    }
+
+    /// Whether `v` is a string.
+    #[starlark(deterministic)]
+    fn is_string(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.unpack_str().is_some())
+    }
+
+    /// Whether `v` is a bool.
+    #[starlark(deterministic)]
+    fn is_bool(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.unpack_bool().is_some())
+    }
+
+    /// Whether `v` is `None`.
+    #[starlark(deterministic)]
+    fn is_none(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.is_none())
+    }
+
+    /// Whether `v` is an int.
+    #[starlark(deterministic)]
+    fn is_int(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "int")
+    }
+
+    /// Whether `v` is a float.
+    #[starlark(deterministic)]
+    fn is_float(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "float")
+    }
+
+    /// Whether `v` is a tuple.
+    #[starlark(deterministic)]
+    fn is_tuple(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "tuple")
+    }
+
+    /// Whether `v` is a dict.
+    #[starlark(deterministic)]
+    fn is_dict(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "dict")
+    }
+
+    /// Whether `v` is a function.
+    #[starlark(deterministic)]
+    fn is_function(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "function")
+    }
+
+    /// Whether `v` is a depset. There are no depsets outside Bazel, so always false.
+    #[starlark(deterministic)]
+    fn is_depset(#[starlark(require = pos)] v: Value) -> anyhow::Result<bool> {
+        Ok(v.get_type() == "depset")
+    }
+
+    /// Whether `v` is a set created by `sets.make`.
+    #[starlark(deterministic)]
+    fn is_set<'v>(
+        #[starlark(require = pos)] v: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<bool> {
+        Ok(StructRef::from_value(v).is_some() && v.has_attr("_values", eval.heap()))
+    }
+}
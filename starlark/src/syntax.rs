@@ -22,3 +22,5 @@ pub use starlark_syntax::dialect::DialectTypes;
 pub use starlark_syntax::syntax::AstLoad;
 pub use starlark_syntax::syntax::AstModule;
 pub use starlark_syntax::syntax::ast;
+pub use starlark_syntax::syntax::edit;
+pub use starlark_syntax::syntax::trivia;
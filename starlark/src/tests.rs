@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+#[cfg(feature = "ast_cache")]
+mod ast_cache;
 mod basic;
 mod bc;
 mod before_stmt;
@@ -0,0 +1,51 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_syntax::syntax::AstModule;
+use starlark_syntax::syntax::Dialect;
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+
+fn eval_from_bytes(program: &str) -> crate::Result<String> {
+    let ast = AstModule::parse("cached.star", program.to_owned(), &Dialect::Extended)?;
+    let ast = AstModule::from_bytes(&ast.to_bytes(), &Dialect::Extended)?;
+    Module::with_temp_heap(|module| {
+        let mut eval = Evaluator::new(&module);
+        Ok(eval.eval_module(ast, &Globals::standard())?.to_repr())
+    })
+}
+
+#[test]
+fn test_eval_from_bytes() {
+    let program = r#"
+def f(xs):
+    return [x * 2 for x in xs if x != 2]
+
+f([1, 2, 3])
+"#;
+    assert_eq!(eval_from_bytes(program).unwrap(), "[2, 6]");
+}
+
+#[test]
+fn test_eval_from_bytes_error_location() {
+    let err = eval_from_bytes("x = 1\nfail('oops')\n").unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!(span.to_string(), "cached.star:2:1-13");
+    assert_eq!(span.source_span(), "fail('oops')");
+}
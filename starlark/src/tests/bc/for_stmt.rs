@@ -37,3 +37,11 @@ fn test_for_continue() {
         "def test(x):\n  for i in x:\n    if i: continue\n    noop(i)",
     );
 }
+
+#[test]
+fn test_for_str_accumulate() {
+    bc_golden_test(
+        "for_str_accumulate",
+        "def test(x):\n  s = ''\n  for i in x:\n    s += i\n    s = s + ','\n  return s",
+    );
+}
@@ -336,3 +336,64 @@ G_F_PTR = g([])
         "native frame size is too large: {frame_native_size}, evaluation may result in native stack overflow",
     );
 }
+
+#[test]
+fn test_call_bound_args() {
+    use crate::eval::bc::opcode::BcOpcode;
+    use crate::eval::compiler::def::FrozenDef;
+    use crate::values::ValueLike;
+
+    let mut a = Assert::new();
+    // Bad calls below must fail at runtime, not in the typechecker.
+    a.disable_static_typechecking();
+    let m = a.module(
+        "f",
+        r#"
+# Two statements, so these are not inlined.
+def f(a, b = 2, *, c = 3):
+    r = (a, b, c)
+    return r
+
+def g(x, /, y):
+    r = (x, y)
+    return r
+
+def calls():
+    return [f(1), f(1, c = 30), f(b = 20, a = 10), g(1, y = 2), f(1, 2)]
+"#,
+    );
+    let calls = m.get("calls").unwrap();
+    let opcodes = calls
+        .value()
+        .downcast_ref::<FrozenDef>()
+        .unwrap()
+        .bc()
+        .instrs
+        .opcodes();
+    assert!(opcodes.contains(&BcOpcode::CallFrozenDefBound));
+    assert!(!opcodes.contains(&BcOpcode::CallFrozenDef));
+
+    a.pass(
+        r#"
+load('f', 'calls')
+assert_eq([(1, 2, 3), (1, 2, 30), (10, 20, 3), (1, 2), (1, 2, 3)], calls())
+"#,
+    );
+    // Calls which can't be bound statically are still reported at runtime.
+    a.fail(
+        "load('f', 'f')\ndef h(): return f()\nh()",
+        "Missing parameter `a`",
+    );
+    a.fail(
+        "load('f', 'f')\ndef h(): return f(1, d = 1)\nh()",
+        "Found `d` extra named",
+    );
+    a.fail(
+        "load('f', 'f')\ndef h(): return f(1, a = 1)\nh()",
+        "occurs more than once",
+    );
+    a.fail(
+        "load('f', 'g')\ndef h(): return g(x = 1, y = 2)\nh()",
+        "Missing positional-only",
+    );
+}
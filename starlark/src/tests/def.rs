@@ -21,6 +21,7 @@ use crate::assert;
 use crate::assert::Assert;
 use crate::environment::Module;
 use crate::eval::Evaluator;
+use crate::values::Value;
 
 #[test]
 fn test_lambda() {
@@ -182,6 +183,40 @@ value = {"test": "hello"}
     .unwrap();
 }
 
+#[test]
+fn test_eval_function_batch() {
+    let mut a = Assert::new();
+    let m = a.module(
+        "a",
+        r#"
+def f(x, y = 10):
+    z = x + y
+    return z
+"#,
+    );
+    let f = m.get("f").unwrap();
+    Module::with_temp_heap(|module| {
+        let f = module.heap().access_owned_frozen_value(&f);
+        let mut eval = Evaluator::new(&module);
+        let one = Value::testing_new_int(1);
+        let two = Value::testing_new_int(2);
+        let res = eval
+            .eval_function_batch(f, [vec![one], vec![two], vec![one, two]])
+            .unwrap();
+        assert_eq!(
+            vec!["11", "12", "3"],
+            res.iter().map(|x| x.to_str()).collect::<Vec<_>>()
+        );
+
+        let err = eval
+            .eval_function_batch(f, [vec![one], vec![one, two, one]])
+            .unwrap_err();
+        assert!(err.to_string().contains("extra positional"), "{err}");
+        crate::Result::Ok(())
+    })
+    .unwrap();
+}
+
 #[test]
 fn test_context_captured() {
     let mut a = Assert::new();
@@ -20,6 +20,7 @@ mod attrs;
 mod docs;
 mod freeze;
 mod module;
+mod struct_fields;
 mod trace;
 mod unpack_value;
 mod unpack_value_attr;
@@ -0,0 +1,127 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for `#[derive(ToStarlark, FromStarlark)]`.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::FromStarlark;
+use crate::values::ToStarlark;
+use crate::values::Value;
+use crate::values::list::UnpackList;
+use crate::values::type_repr::StarlarkTypeRepr;
+
+#[derive(StarlarkTypeRepr, ToStarlark, FromStarlark, Debug, PartialEq)]
+struct Server {
+    host: String,
+    #[starlark(rename = "port_number")]
+    port: i32,
+    #[starlark(default)]
+    tags: UnpackList<String>,
+    #[starlark(skip)]
+    connections: u32,
+}
+
+#[derive(StarlarkTypeRepr, ToStarlark, FromStarlark)]
+#[starlark(dict)]
+struct Labels<'v> {
+    team: String,
+    extra: Value<'v>,
+}
+
+#[starlark_module]
+fn servers(builder: &mut GlobalsBuilder) {
+    fn make_server(host: String) -> anyhow::Result<Server> {
+        Ok(Server {
+            host,
+            port: 80,
+            tags: UnpackList {
+                items: vec!["web".to_owned()],
+            },
+            connections: 3,
+        })
+    }
+
+    fn describe(server: Server) -> anyhow::Result<String> {
+        Ok(format!(
+            "{}:{} {:?} {}",
+            server.host, server.port, server.tags.items, server.connections
+        ))
+    }
+
+    fn relabel<'v>(labels: Labels<'v>) -> anyhow::Result<Labels<'v>> {
+        Ok(Labels {
+            team: labels.team.to_uppercase(),
+            extra: labels.extra,
+        })
+    }
+}
+
+fn assert() -> Assert<'static> {
+    let mut a = Assert::new();
+    a.globals_add(servers);
+    a
+}
+
+#[test]
+fn test_to_starlark() {
+    assert().eq(
+        "struct(host = 'a', port_number = 80, tags = ['web'])",
+        "make_server('a')",
+    );
+    assert().eq(
+        "{'team': 'X', 'extra': [1]}",
+        "relabel({'team': 'x', 'extra': [1]})",
+    );
+}
+
+#[test]
+fn test_from_starlark() {
+    assert().eq(
+        "'a:1 [] 0'",
+        "describe(struct(host = 'a', port_number = 1))",
+    );
+    assert().eq(
+        "'a:1 [\"x\"] 0'",
+        "describe(struct(host = 'a', port_number = 1, tags = ['x']))",
+    );
+    assert().fail(
+        "describe(struct(host = 'a'))",
+        "Missing field `port_number` for `Server`",
+    );
+    assert().fail(
+        "describe(struct(host = 'a', port_number = 1, connections = 2))",
+        "Unknown field `connections` for `Server`",
+    );
+    assert().fail(
+        "describe(struct(host = 1, port_number = 1))",
+        "Field `host` for `Server` expected `str`",
+    );
+    assert().fail("relabel({1: 2})", "keys must be strings");
+}
+
+#[test]
+fn test_type_repr() {
+    assert_eq!("struct(..)", Server::starlark_type_repr().to_string());
+    assert_eq!(
+        "dict[str, typing.Any]",
+        Labels::starlark_type_repr().to_string()
+    );
+}
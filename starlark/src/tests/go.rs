@@ -21,6 +21,8 @@ use itertools::Itertools;
 
 use crate::assert;
 use crate::assert::Assert;
+use crate::assert::ConformanceOutcome;
+use crate::syntax::Dialect;
 
 #[test]
 fn test_go() {
@@ -173,3 +175,42 @@ not ("one" in range(10))
 "#,
     );
 }
+
+#[test]
+fn test_conformance_report() {
+    let code = r#"
+assert_eq(1 + 1, 2)
+---
+x = 1 // 0 ### "division by zero"
+---
+x = 1 ### "no error here"
+---
+def f(x: int):
+    return x
+"#;
+
+    let mut assert = Assert::new();
+    assert.disable_static_typechecking();
+    let report = assert.conformance_report("test.star", code);
+    assert_eq!(3, report.passed());
+    assert_eq!("test.star", report.file);
+
+    assert.dialect(&Dialect::Standard);
+    let report = assert.conformance_report("test.star", code);
+    assert_eq!(2, report.passed());
+    let failures = report.failures().collect::<Vec<_>>();
+    assert_eq!(6, failures[0].line);
+    assert_eq!(
+        ConformanceOutcome::MissingError {
+            line: 6,
+            expected: "\"no error here\"".to_owned()
+        },
+        failures[0].outcome
+    );
+    assert_eq!(8, failures[1].line);
+    assert!(matches!(
+        failures[1].outcome,
+        ConformanceOutcome::UnexpectedError { .. }
+    ));
+    assert!(report.to_json().contains("\"failed\": 2"));
+}
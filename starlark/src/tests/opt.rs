@@ -20,9 +20,13 @@
 mod constant_folding;
 mod def_inline;
 mod eq;
+mod fast_int;
+mod fold_on_freeze;
 mod if_rand;
+mod lazy_compile;
 mod list_add;
 mod speculative_exec;
+mod type_feedback;
 mod type_is;
 mod types;
 
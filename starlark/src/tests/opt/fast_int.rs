@@ -0,0 +1,123 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Test instructions specialized for inline ints without type feedback.
+
+use crate::assert::Assert;
+use crate::environment::FrozenModule;
+use crate::eval::bc::opcode::BcOpcode;
+use crate::eval::compiler::def::FrozenDef;
+use crate::values::ValueLike;
+
+fn opcodes(module: &FrozenModule, name: &str) -> Vec<BcOpcode> {
+    let f = module.get(name).unwrap();
+    let f = f.value().downcast_ref::<FrozenDef>().unwrap();
+    f.bc().instrs.opcodes()
+}
+
+#[test]
+fn test_int_const_operand() {
+    let mut a = Assert::new();
+    let m = a.module(
+        "f",
+        r#"
+def inc(x):
+    return x + 1
+
+def small(x):
+    return x < 10
+
+def repeat(x):
+    return x * 2
+"#,
+    );
+    assert!(opcodes(&m, "inc").contains(&BcOpcode::AddInt));
+    assert!(opcodes(&m, "small").contains(&BcOpcode::LessInt));
+    assert!(opcodes(&m, "repeat").contains(&BcOpcode::Multiply));
+
+    a.pass(
+        r#"
+load('f', 'inc', 'small')
+assert_eq(2, inc(1))
+assert_eq(2.5, inc(1.5))
+assert_eq(1 << 40, inc((1 << 40) - 1))
+assert_eq(True, small(9))
+assert_eq(False, small(1 << 40))
+assert_eq(True, small(9.5))
+"#,
+    );
+    a.fail("load('f', 'inc')\ninc('a')", "not supported");
+}
+
+#[test]
+fn test_for_range() {
+    let mut a = Assert::new();
+    let m = a.module(
+        "f",
+        r#"
+def total(n):
+    s = 0
+    for i in range(n):
+        s += i
+    return s
+
+def evens():
+    return [i for i in range(10, 0, -2)]
+
+def nested(n):
+    r = []
+    for i in range(n):
+        for j in range(i):
+            if j == 1:
+                continue
+            r.append((i, j))
+    return r
+"#,
+    );
+    assert!(opcodes(&m, "total").contains(&BcOpcode::IterRange));
+    assert!(opcodes(&m, "total").contains(&BcOpcode::ContinueRange));
+    assert!(!opcodes(&m, "total").contains(&BcOpcode::Iter));
+    assert!(opcodes(&m, "evens").contains(&BcOpcode::IterRange));
+
+    a.pass(
+        r#"
+load('f', 'total', 'evens', 'nested')
+assert_eq(45, total(10))
+assert_eq(0, total(0))
+assert_eq(0, total(-5))
+assert_eq([10, 8, 6, 4, 2], evens())
+assert_eq([(1, 0), (2, 0), (3, 0), (3, 2)], nested(4))
+"#,
+    );
+}
+
+#[test]
+fn test_for_range_shadowed() {
+    // `range` is not the builtin here, so the loop is not specialized.
+    let a = Assert::new();
+    a.pass(
+        r#"
+def range(n):
+    return ["x"] * n
+
+def f():
+    return [i for i in range(2)]
+
+assert_eq(["x", "x"], f())
+"#,
+    );
+}
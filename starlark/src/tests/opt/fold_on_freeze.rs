@@ -0,0 +1,93 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Test optimizations of frozen functions with `OptimizationLevel::Aggressive`.
+
+use crate::assert::Assert;
+use crate::environment::FrozenModule;
+use crate::eval::OptimizationLevel;
+use crate::eval::bc::opcode::BcOpcode;
+use crate::eval::compiler::def::FrozenDef;
+use crate::values::ValueLike;
+
+fn opcodes(module: &FrozenModule, name: &str) -> Vec<BcOpcode> {
+    let f = module.get(name).unwrap();
+    let f = f.value().downcast_ref::<FrozenDef>().unwrap();
+    f.bc().instrs.opcodes()
+}
+
+const PROGRAM: &str = r#"
+CONFIG = {"debug": False}
+NAMES = ["a", "b"]
+Color = enum("red", "green")
+GREEN = Color("green")
+
+def fold():
+    if CONFIG.get("debug"):
+        print("debug")
+    return "-".join(NAMES)
+
+def attr():
+    return GREEN.index
+
+def dead(x):
+    if x:
+        return 1
+    else:
+        return 2
+    fail("unreachable")
+"#;
+
+fn calls(opcodes: &[BcOpcode]) -> bool {
+    opcodes
+        .iter()
+        .any(|op| format!("{op:?}").starts_with("Call"))
+}
+
+#[test]
+fn test_fold_on_freeze() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_optimization_level(OptimizationLevel::Aggressive));
+    let m = a.module("m", PROGRAM);
+    a.pass(
+        r#"
+load("m", "fold", "attr", "dead")
+assert_eq("a-b", fold())
+assert_eq(1, attr())
+assert_eq(2, dead(False))
+"#,
+    );
+    assert!(!calls(&opcodes(&m, "fold")));
+    assert!(!opcodes(&m, "attr").contains(&BcOpcode::ObjectField));
+    assert!(!calls(&opcodes(&m, "dead")));
+}
+
+#[test]
+fn test_default_level_does_not_fold_on_freeze() {
+    let mut a = Assert::new();
+    let m = a.module("m", PROGRAM);
+    a.pass(
+        r#"
+load("m", "fold", "attr", "dead")
+assert_eq("a-b", fold())
+assert_eq(1, attr())
+assert_eq(2, dead(False))
+"#,
+    );
+    assert!(calls(&opcodes(&m, "fold")));
+    assert!(opcodes(&m, "attr").contains(&BcOpcode::ObjectField));
+    assert!(calls(&opcodes(&m, "dead")));
+}
@@ -0,0 +1,66 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Test function bodies are compiled to bytecode on first call.
+
+use crate::assert;
+use crate::assert::Assert;
+use crate::environment::FrozenModule;
+use crate::eval::compiler::def::FrozenDef;
+use crate::values::ValueLike;
+
+fn is_compiled(module: &FrozenModule, name: &str) -> bool {
+    let f = module.get(name).unwrap();
+    f.value().downcast_ref::<FrozenDef>().unwrap().is_compiled()
+}
+
+#[test]
+fn test_compiled_on_first_call() {
+    let mut a = Assert::new();
+    let m = a.module(
+        "m",
+        r#"
+def f(x):
+    y = x + 1
+    return y
+
+def g(x):
+    y = x * 2
+    return y
+"#,
+    );
+    assert!(!is_compiled(&m, "f"));
+    assert!(!is_compiled(&m, "g"));
+
+    a.pass("load('m', 'f')\nassert_eq(2, f(1))");
+    assert!(is_compiled(&m, "f"));
+    assert!(!is_compiled(&m, "g"));
+}
+
+#[test]
+fn test_unfrozen_def_called_before_freeze() {
+    assert::pass(
+        r#"
+def f(x):
+    y = x + 1
+    return y
+
+assert_eq(2, f(1))
+assert_eq(3, f(2))
+"#,
+    );
+}
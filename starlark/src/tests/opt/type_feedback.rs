@@ -0,0 +1,112 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Test functions specialized by type feedback.
+
+use crate::assert::Assert;
+use crate::environment::FrozenModule;
+use crate::eval::OptimizationLevel;
+use crate::eval::bc::opcode::BcOpcode;
+use crate::eval::compiler::def::FrozenDef;
+use crate::values::ValueLike;
+
+fn opcodes(module: &FrozenModule, name: &str) -> Vec<BcOpcode> {
+    let f = module.get(name).unwrap();
+    let f = f.value().downcast_ref::<FrozenDef>().unwrap();
+    f.bc().instrs.opcodes()
+}
+
+fn aggressive() -> Assert<'static> {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_optimization_level(OptimizationLevel::Aggressive));
+    a
+}
+
+#[test]
+fn test_type_feedback_specializes() {
+    let mut a = aggressive();
+    let m = a.module(
+        "f",
+        r#"
+def f(xs):
+    s = 0
+    for x in xs:
+        s += x * 2
+    return s
+"#,
+    );
+    assert!(opcodes(&m, "f").contains(&BcOpcode::RecordTypes));
+
+    a.pass("load('f', 'f')\n[assert_eq(12, f([1, 2, 3])) for _ in range(10)]");
+
+    let opcodes = opcodes(&m, "f");
+    assert!(!opcodes.contains(&BcOpcode::RecordTypes));
+    assert!(opcodes.contains(&BcOpcode::IterList));
+    assert!(opcodes.contains(&BcOpcode::ContinueList));
+    assert!(opcodes.contains(&BcOpcode::MultiplyInt));
+    assert!(opcodes.contains(&BcOpcode::AddAssignInt));
+}
+
+#[test]
+fn test_type_feedback_deopt() {
+    let mut a = aggressive();
+    let m = a.module(
+        "f",
+        r#"
+def add(x, y):
+    return x + y
+
+def less(x, y):
+    return x < y
+"#,
+    );
+    // Calls with constant arguments are inlined, and so don't record, so call with a variable.
+    a.pass(
+        r#"
+load('f', 'add', 'less')
+def calls():
+    for i in range(10):
+        assert_eq(i + 2, add(i, 2))
+        assert_eq(i < 2, less(i, 2))
+calls()
+"#,
+    );
+    assert!(opcodes(&m, "add").contains(&BcOpcode::AddInt));
+    assert!(opcodes(&m, "less").contains(&BcOpcode::LessInt));
+
+    // Specialized instructions fall back to the generic operation.
+    a.pass(
+        r#"
+load('f', 'add', 'less')
+assert_eq("ab", add("a", "b"))
+assert_eq([1, 2], add([1], [2]))
+assert_eq(1 << 40, add(1 << 39, 1 << 39))
+assert_eq(True, less("a", "b"))
+assert_eq(False, less(1 << 40, 1))
+"#,
+    );
+    a.fail("load('f', 'add')\nadd(1, 'a')", "not supported");
+}
+
+#[test]
+fn test_default_level_does_not_record() {
+    let mut a = Assert::new();
+    let m = a.module("f", "def f(x, y): return x + y");
+    a.pass("load('f', 'f')\n[assert_eq(2, f(1, 1)) for _ in range(10)]");
+    assert!(!opcodes(&m, "f").contains(&BcOpcode::RecordTypes));
+    assert!(!opcodes(&m, "f").contains(&BcOpcode::AddInt));
+}
@@ -17,22 +17,42 @@
 
 //! Test of runtime.
 
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::mem;
+use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use allocative::Allocative;
 use derive_more::Display;
 use once_cell::sync::Lazy;
 use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
 
 use crate as starlark;
+use crate::ErrorCode;
+use crate::ErrorKind;
+use crate::StarlarkResultExt;
+use crate::any::ProvidesStaticType;
 use crate::assert;
 use crate::assert::Assert;
+use crate::assert::test_functions;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::errors::DiagnosticRenderOptions;
+use crate::eval::Arguments;
 use crate::eval::Evaluator;
+use crate::starlark_simple_value;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 use crate::values::FrozenHeap;
 use crate::values::Heap;
+use crate::values::NoSerialize;
+use crate::values::StarlarkValue;
+use crate::values::Value;
 use crate::values::any::StarlarkAny;
 
 #[test]
@@ -88,6 +108,136 @@ r = [y(), mk()]
     assert_eq!(COUNT.load(Ordering::SeqCst), 5);
 }
 
+#[test]
+fn test_gc_pause_budget_drops_in_steps() {
+    static COUNT: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(0));
+
+    #[derive(Default, Debug, Display)]
+    struct Dealloc;
+
+    impl Drop for Dealloc {
+        fn drop(&mut self) {
+            COUNT.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[starlark_module]
+    fn globals(builder: &mut GlobalsBuilder) {
+        fn mk() -> anyhow::Result<StarlarkAny<Dealloc>> {
+            Ok(StarlarkAny::new(Dealloc))
+        }
+    }
+
+    fn eval_stmt(eval: &mut Evaluator, globals: &Globals, program: &str) {
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, globals).unwrap();
+    }
+
+    let globals = GlobalsBuilder::standard().with(globals).build();
+    Module::with_temp_heap(|module| {
+        let mut eval = Evaluator::new(&module);
+        eval.set_gc_pause_budget(Duration::ZERO);
+        eval_stmt(
+            &mut eval,
+            &globals,
+            "x = [mk() for _ in range(1000)]\nx = None",
+        );
+        assert_eq!(0, COUNT.load(Ordering::SeqCst));
+
+        // Nothing is reachable from Rust, so it is safe to collect.
+        unsafe { eval.garbage_collect() };
+        // Dropping is deferred to the next GC points.
+        assert_eq!(0, COUNT.load(Ordering::SeqCst));
+
+        eval_stmt(&mut eval, &globals, "y = 1");
+        let dropped = COUNT.load(Ordering::SeqCst);
+        assert!(dropped > 0 && dropped < 1000, "{dropped}");
+        for _ in 0..10 {
+            eval_stmt(&mut eval, &globals, "y = 1");
+        }
+        assert_eq!(1000, COUNT.load(Ordering::SeqCst));
+
+        // Garbage is also dropped in loops and function bodies, between GC points.
+        eval_stmt(
+            &mut eval,
+            &globals,
+            "x = [mk() for _ in range(1000)]\nx = None",
+        );
+        unsafe { eval.garbage_collect() };
+        eval_stmt(
+            &mut eval,
+            &globals,
+            "def f():\n  for _ in range(100000):\n    pass\nf()",
+        );
+        assert_eq!(2000, COUNT.load(Ordering::SeqCst));
+    });
+}
+
+#[test]
+fn test_error_codes() {
+    fn code(program: &str, msg: &str) -> String {
+        assert::fail(program, msg).code().to_string()
+    }
+    assert_eq!("E0301", code("x + 1", "Variable `x` not found"));
+    assert_eq!("E0411", code("{}['x']", "was not found"));
+    assert_eq!("E0414", code("[].foo", "has no attribute"));
+    assert_eq!(
+        "E0501",
+        code("def f(): pass\nf(1)", "extra positional argument")
+    );
+    assert_eq!("E0600", code("fail('oops')", "oops"));
+    assert_eq!("E0403", code("1 // 0", "division by zero"));
+    assert_eq!("E0416", code("{[]: 1}", "not hashable"));
+    assert_eq!("E0417", code("{'a': 1, 'a': 2}", "key repeated"));
+    assert_eq!("E0418", code("a, b = [1]", "Unpacked 1 values"));
+    assert_eq!("E0419", code("'%s %s' % (1,)", "Not enough arguments"));
+    assert_eq!(
+        "E0303",
+        code(
+            "def f():
+  x
+  x = 1
+f()",
+            "referenced before assignment"
+        )
+    );
+    // Errors without a more specific code, here from a method.
+    assert_eq!("E0607", code("'{}'.format()", "Not enough parameters"));
+}
+
+#[test]
+fn test_message_catalog() {
+    let catalog = HashMap::from([
+        (
+            ErrorCode::new(301),
+            "Variable `{name}` introuvable".to_owned(),
+        ),
+        (
+            ErrorCode::new(411),
+            "Clé `{key}` absente ({message})".to_owned(),
+        ),
+    ]);
+    let options = DiagnosticRenderOptions {
+        catalog: Some(Arc::new(catalog.clone())),
+        ..DiagnosticRenderOptions::default()
+    };
+    let e = assert::fail("x + 1", "Variable `x` not found");
+    assert_eq!("Variable `x` introuvable", e.message_from_catalog(&catalog));
+    assert!(
+        e.display_with_options(&options)
+            .to_string()
+            .contains("error[E0301]: Variable `x` introuvable")
+    );
+    let e = assert::fail("{}['y']", "was not found");
+    assert_eq!(
+        r#"Clé `"y"` absente (Key `"y"` was not found)"#,
+        e.message_from_catalog(&catalog)
+    );
+    // Codes without a template keep their message.
+    let e = assert::fail("fail('oops')", "oops");
+    assert_eq!("fail: oops", e.message_from_catalog(&catalog));
+}
+
 // This test relies on stack behavior which does not hold when
 // ASAN is enabled. See D47571173 for more context.
 #[cfg_attr(rust_nightly, cfg(not(sanitize = "address")))]
@@ -207,6 +357,190 @@ f()
     assert!(d.to_string().contains("fail(\"bad\")"));
 }
 
+#[test]
+fn test_callstack_through_native() {
+    #[starlark_module]
+    fn globals(builder: &mut GlobalsBuilder) {
+        // Native functions returning `anyhow::Result` lose the starlark error type.
+        fn call_anyhow<'v>(
+            f: Value<'v>,
+            eval: &mut Evaluator<'v, '_, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            eval.eval_function(f, &[], &[]).into_anyhow_result()
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(globals);
+    let e = a.fail(
+        r#"
+def inner():
+    fail("oops")
+def outer():
+    return call_anyhow(inner)
+outer()
+"#,
+        "oops",
+    );
+    assert!(matches!(e.kind(), ErrorKind::Fail(_)));
+    assert_eq!("fail(\"oops\")", e.span().unwrap().source_span());
+    let frames: Vec<&str> = e
+        .call_stack()
+        .frames
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(vec!["outer", "call_anyhow", "inner", "fail"], frames);
+}
+
+#[test]
+fn test_intern_strings() {
+    fn allocated_bytes(intern: Option<usize>) -> usize {
+        let program = r#"
+xs = [str(i % 10 + 10) for i in range(1000)]
+ys = ["key_" + str(i % 10) for i in range(1000)]
+"#;
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Extended).unwrap();
+        let globals = Globals::extended_internal();
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.set_intern_strings(intern);
+            eval.disable_gc();
+            eval.eval_module(ast, &globals).unwrap();
+            module.heap().allocated_bytes()
+        })
+    }
+
+    let plain = allocated_bytes(None);
+    let interned = allocated_bytes(Some(16));
+    assert!(
+        interned + 2000 * 16 < plain,
+        "interned {interned} bytes, plain {plain} bytes"
+    );
+}
+
+#[test]
+fn test_small_collections_inline() {
+    // Number of arrays and bytes per dict allocated for 100 lists and 100 dicts of `len` elements.
+    fn allocated(len: usize) -> (usize, usize) {
+        let program = format!(
+            "xs = [list(range({len})) for _ in range(100)]\n\
+             ys = [{{i: i for i in range({len})}} for _ in range(100)]\n"
+        );
+        let ast = AstModule::parse("x.star", program, &Dialect::Extended).unwrap();
+        let globals = Globals::extended_internal();
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            eval.disable_gc();
+            eval.eval_module(ast, &globals).unwrap();
+            let summary = module.heap().allocated_summary().summary;
+            let counts = |t| summary.get(t).copied().unwrap_or_default();
+            (counts("array").count, counts("dict").bytes / 100)
+        })
+    }
+
+    let (arrays4, dict_bytes4) = allocated(4);
+    let (arrays5, dict_bytes5) = allocated(5);
+    // Only the two outer lists allocate arrays as they grow.
+    assert!(arrays4 < 20, "{arrays4} arrays");
+    assert!(arrays5 > 100, "{arrays5} arrays");
+    // Small dicts do not allocate anything besides the dict value.
+    assert_eq!(dict_bytes4, allocated(2).1);
+    assert!(dict_bytes4 < dict_bytes5);
+}
+
+#[test]
+fn test_hermetic() {
+    /// A callable value which doesn't say it is deterministic.
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display("clock")]
+    struct Clock;
+    starlark_simple_value!(Clock);
+
+    #[starlark_value(type = "clock")]
+    impl<'v> StarlarkValue<'v> for Clock {
+        fn invoke(
+            &self,
+            _me: Value<'v>,
+            _args: &Arguments<'v, '_>,
+            _eval: &mut Evaluator<'v, '_, '_>,
+        ) -> crate::Result<Value<'v>> {
+            Ok(Value::testing_new_int(42))
+        }
+    }
+
+    #[starlark_module]
+    fn globals(builder: &mut GlobalsBuilder) {
+        fn now() -> anyhow::Result<i32> {
+            Ok(42)
+        }
+
+        #[starlark(deterministic)]
+        fn double(x: i32) -> anyhow::Result<i32> {
+            Ok(x * 2)
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| {
+        globals(builder);
+        builder.set("clock", Clock);
+    });
+    a.setup_eval(|eval| eval.set_hermetic(true));
+    a.pass(
+        r#"
+xs = sorted([2, 1])
+xs.append(double(3))
+print(xs)
+assert_eq([1, 2, 6], xs)
+assert_eq({"a": 1}, json.decode(json.encode({"a": 1})))
+"#,
+    );
+    a.fail("now()", "Function `now` is not deterministic");
+    a.fail("debug(1)", "Function `debug` is not deterministic");
+    a.fail("clock()", "Function `clock` is not deterministic");
+    a.fail("f = now\nf()", "Function `now` is not deterministic");
+}
+
+#[test]
+fn test_hermetic_globals() {
+    #[starlark_module]
+    fn globals(builder: &mut GlobalsBuilder) {
+        fn now() -> anyhow::Result<i32> {
+            Ok(42)
+        }
+
+        #[starlark(deterministic)]
+        fn double(x: i32) -> anyhow::Result<i32> {
+            Ok(x * 2)
+        }
+    }
+
+    let globals = GlobalsBuilder::extended()
+        .with(test_functions)
+        .with(globals)
+        .with_namespace("clock", globals)
+        .build()
+        .hermetic();
+    assert!(globals.get("now").is_none());
+    assert!(globals.get("debug").is_none());
+    assert!(globals.get("double").is_some());
+    let mut a = Assert::new();
+    a.globals(globals);
+    a.setup_eval(|eval| eval.set_hermetic(true));
+    a.pass(
+        r#"
+assert_eq([1, 2, 6], sorted([2, 1]) + [double(3)])
+assert_eq(8, clock.double(4))
+assert_eq({"a": 1}, json.decode(json.encode({"a": 1})))
+"#,
+    );
+    a.fail("now()", "Variable `now` not found");
+    a.fail("clock.now()", "has no attribute `now`");
+    #[cfg(feature = "fs")]
+    a.fail("os.getenv('HOME')", "has no attribute `getenv`");
+}
+
 #[test]
 fn test_display_debug() {
     Heap::temp(|heap| {
@@ -216,7 +550,7 @@ fn test_display_debug() {
         assert_eq!(val.to_str(), "([1, 2], \"test\", True)");
         assert_eq!(
             format!("{val:?}"),
-            "Value(TupleGen { content: [Value(ListGen(ListData { content: Cell { value: ValueTyped(Value(Array { len: 2, capacity: 4, iter_count: 0, content: [Value(1), Value(2)] })) } })), Value(\"test\"), Value(StarlarkBool(true))] })"
+            "Value(TupleGen { content: [Value(ListGen(ListData { content: Cell { value: ValueTyped(Value(Array { len: 2, capacity: 2, iter_count: 0, content: [Value(1), Value(2)] })) } })), Value(\"test\"), Value(StarlarkBool(true))] })"
         );
         let v = heap.alloc("test");
         assert_eq!(format!("{v}"), "\"test\"");
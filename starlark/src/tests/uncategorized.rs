@@ -26,6 +26,8 @@ use starlark_derive::starlark_value;
 use starlark_syntax::golden_test_template::golden_test_template;
 
 use crate as starlark;
+use crate::ErrorClass;
+use crate::ErrorCode;
 use crate::any::ProvidesStaticType;
 use crate::assert;
 use crate::assert::Assert;
@@ -530,6 +532,36 @@ fn test_load_reexport() {
     );
 }
 
+#[test]
+fn test_export_list() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| {
+        d.enable_export_list = true;
+        d.enable_load_reexport = false;
+    });
+    a.module("a", "__all__ = ['x', 'y']\nx = 1\ny = 2\nz = 3\n_p = 4");
+    a.pass("load('a', 'x', 'y')\nassert_eq(x + y, 3)");
+    a.fail(
+        "load('a', 'z')",
+        "Module symbol `z` is not exported, it is not listed in `__all__`",
+    );
+    a.fail("load('a', '_p')", "Cannot import private symbol `_p`");
+    // Listing a loaded symbol reexports it.
+    a.module("b", "load('a', 'x')\n__all__ = ['x']");
+    a.pass("load('b', 'x')\nassert_eq(x, 1)");
+    a.fail(
+        "__all__ = ['w']",
+        "`__all__` lists `w`, which is not defined",
+    );
+    a.fail("__all__ = [1]", "`__all__` must be a list of strings");
+
+    // Without the dialect option `__all__` is an ordinary variable.
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_export_list = false);
+    a.module("a", "__all__ = ['x']\nx = 1\nz = 3");
+    a.pass("load('a', 'z')\nassert_eq(z, 3)");
+}
+
 #[test]
 fn test_module_visibility_preserved_by_evaluator() -> crate::Result<()> {
     // Make sure that when we use a module in the evaluator, the entering / exiting the
@@ -619,11 +651,30 @@ fn test_cancellation() -> crate::Result<()> {
         if !err_msg.contains(expected) {
             panic!("Error:\n{err:#?}\nExpected:\n{expected:?}")
         }
+        assert_eq!(ErrorClass::Cancelled, err.unwrap_err().class());
 
         Ok(())
     })
 }
 
+#[test]
+fn test_tick_limit_class() {
+    let globals = Globals::standard();
+    Module::with_temp_heap(|module| {
+        let mut eval = Evaluator::new(&module);
+        eval.set_max_tick_count(10).unwrap();
+        let ast = AstModule::parse(
+            "ticks.bzl",
+            "def loop():\n    for i in range(1000000):\n       pass\nloop()".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        let err = eval.eval_module(ast, &globals).unwrap_err();
+        assert_eq!(ErrorCode::TICK_LIMIT, err.code());
+        assert_eq!(ErrorClass::ResourceExhausted, err.class());
+    })
+}
+
 #[test]
 fn test_load_did_you_mean() {
     let mut a = Assert::new();
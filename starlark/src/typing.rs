@@ -36,13 +36,16 @@ pub(crate) mod fill_types_for_lint;
 pub(crate) mod function;
 pub(crate) mod interface;
 pub(crate) mod mode;
+pub(crate) mod narrow;
 pub(crate) mod oracle;
 pub(crate) mod small_arc_vec;
 pub(crate) mod small_arc_vec_or_static;
 pub(crate) mod starlark_value;
 pub(crate) mod structs;
+pub(crate) mod structural_record;
 pub(crate) mod tuple;
 pub(crate) mod ty;
+pub(crate) mod type_var;
 pub(crate) mod typecheck;
 pub(crate) mod user;
 
@@ -62,9 +65,11 @@ pub use oracle::traits::TypingBinOp;
 pub use oracle::traits::TypingUnOp;
 pub use starlark_value::TyStarlarkValue;
 pub use structs::TyStruct;
+pub use structural_record::TyStructuralRecord;
 pub use ty::Approximation;
 pub use ty::Ty;
 pub use ty::TypeRenderConfig;
+pub use type_var::TyTypeVar;
 pub use typecheck::AstModuleTypecheck;
 pub use typecheck::TypeMap;
 pub use user::TyUser;
@@ -22,6 +22,7 @@ use starlark_map::small_map::SmallMap;
 use starlark_syntax::syntax::ast::AssignOp;
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTargetP;
+use starlark_syntax::syntax::ast::BinOp;
 use starlark_syntax::syntax::ast::ClauseP;
 use starlark_syntax::syntax::ast::DefP;
 use starlark_syntax::syntax::ast::ExprP;
@@ -51,6 +52,8 @@ use crate::typing::arc_ty::ArcTy;
 use crate::typing::callable_param::ParamIsRequired;
 use crate::typing::error::InternalError;
 use crate::typing::mode::TypecheckMode;
+use crate::typing::narrow::Narrow;
+use crate::typing::narrow::narrowings;
 use crate::typing::tuple::TyTuple;
 use crate::typing::ty::Approximation;
 use crate::typing::ty::Ty;
@@ -98,11 +101,17 @@ pub(crate) struct Bindings<'a> {
     /// ```
     pub(crate) check: Vec<&'a CstExpr>,
     pub(crate) check_type: Vec<(Span, Option<&'a CstExpr>, Ty)>,
+    /// Conditions known where variables are used, by the span of the use,
+    /// which narrow the types of the variables there.
+    pub(crate) narrowed: HashMap<Span, Vec<Narrow>>,
 }
 
 pub(crate) struct BindingsCollect<'a, 'b> {
     pub(crate) bindings: Bindings<'a>,
     pub(crate) approximations: &'b mut Vec<Approximation>,
+    /// Conditions known at the current point, innermost last.
+    /// `None` where the variable is assigned, which invalidates earlier conditions.
+    narrowings: Vec<(BindingId, Option<Narrow>)>,
 }
 
 impl<'a, 'b> BindingsCollect<'a, 'b> {
@@ -118,12 +127,83 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
         let mut res = BindingsCollect {
             bindings: Bindings::default(),
             approximations,
+            narrowings: Vec::new(),
         };
 
         res.visit(Visit::Stmt(x), &Ty::any(), typecheck_mode, codemap)?;
         Ok(res)
     }
 
+    /// Forget the conditions pushed since `len`, keeping the assignments.
+    fn truncate_narrowings(&mut self, len: usize) {
+        let mut i = len;
+        while i < self.narrowings.len() {
+            if self.narrowings[i].1.is_some() {
+                self.narrowings.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn kill_narrowings(&mut self, lhs: &CstAssignTarget) {
+        lhs.visit_lvalue(|x| {
+            if let Some(id) = x.payload {
+                self.narrowings.push((id, None));
+            }
+        });
+    }
+
+    /// The conditions narrowing the variable `id` at the current point.
+    fn narrowings_of(&self, id: BindingId) -> Vec<Narrow> {
+        let mut res = Vec::new();
+        for (x, narrow) in self.narrowings.iter().rev() {
+            if *x == id {
+                match narrow {
+                    Some(narrow) => res.push(narrow.clone()),
+                    None => break,
+                }
+            }
+        }
+        res
+    }
+
+    /// Visit `x` where `cond` is known to be `positive`.
+    fn visit_narrowed(
+        &mut self,
+        x: Visit<'a, CstPayload>,
+        cond: &CstExpr,
+        positive: bool,
+        return_type: &Ty,
+        typecheck_mode: TypecheckMode,
+        codemap: &CodeMap,
+    ) -> Result<(), InternalError> {
+        let len = self.narrowings.len();
+        self.push_narrowings(cond, positive);
+        self.visit(x, return_type, typecheck_mode, codemap)?;
+        self.truncate_narrowings(len);
+        Ok(())
+    }
+
+    fn push_narrowings(&mut self, cond: &CstExpr, positive: bool) {
+        self.narrowings.extend(
+            narrowings(cond, positive)
+                .into_iter()
+                .map(|(id, narrow)| (id, Some(narrow))),
+        );
+    }
+
+    /// Visit a nested function, which runs later, where no conditions are known.
+    fn visit_nested(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), InternalError>,
+    ) -> Result<(), InternalError> {
+        let narrowings = std::mem::take(&mut self.narrowings);
+        let res = f(self);
+        self.narrowings = narrowings;
+        res
+    }
+
     fn assign(
         &mut self,
         lhs: &'a CstAssignTarget,
@@ -283,8 +363,9 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
             name.resolved_binding_id(codemap)?,
             Ty::function(params2, ret_ty.clone()),
         );
-        def.visit_children_err(|x| self.visit(x, &ret_ty, typecheck_mode, codemap))?;
-        Ok(())
+        self.visit_nested(|this| {
+            def.visit_children_err(|x| this.visit(x, &ret_ty, typecheck_mode, codemap))
+        })
     }
 
     fn visit(
@@ -310,13 +391,22 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
                                 .insert(id.resolved_binding_id(codemap)?, ty2);
                         }
                     }
-                    self.assign(lhs, BindExpr::Expr(rhs), codemap)?
+                    self.assign(lhs, BindExpr::Expr(rhs), codemap)?;
+                    x.visit_children_err(|x| self.visit(x, return_type, typecheck_mode, codemap))?;
+                    self.kill_narrowings(lhs);
+                    return Ok(());
                 }
                 StmtP::AssignModify(lhs, op, rhs) => {
-                    self.assign(lhs, BindExpr::AssignModify(lhs, *op, rhs), codemap)?
+                    self.assign(lhs, BindExpr::AssignModify(lhs, *op, rhs), codemap)?;
+                    x.visit_children_err(|x| self.visit(x, return_type, typecheck_mode, codemap))?;
+                    self.kill_narrowings(lhs);
+                    return Ok(());
                 }
-                StmtP::For(ForP { var, over, body: _ }) => {
-                    self.assign(var, BindExpr::Iter(Box::new(BindExpr::Expr(over))), codemap)?
+                StmtP::For(ForP { var, over, body }) => {
+                    self.assign(var, BindExpr::Iter(Box::new(BindExpr::Expr(over))), codemap)?;
+                    // The body may run again after the assignments in it.
+                    self.kill_narrowings(var);
+                    self.kill_assigned(body);
                 }
                 StmtP::Def(def) => {
                     self.visit_def(def, typecheck_mode, codemap)?;
@@ -360,11 +450,116 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
 
                     self.bindings.check.push(x)
                 }
-                StmtP::If(x, _) => self.bindings.check.push(x),
-                StmtP::IfElse(x, _) => self.bindings.check.push(x),
+                StmtP::If(cond, body) => {
+                    self.bindings.check.push(cond);
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        Visit::Stmt(body),
+                        cond,
+                        true,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                StmtP::IfElse(cond, then_else) => {
+                    let (then_block, else_block) = &**then_else;
+                    self.bindings.check.push(cond);
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        Visit::Stmt(then_block),
+                        cond,
+                        true,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    self.visit_narrowed(
+                        Visit::Stmt(else_block),
+                        cond,
+                        false,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                StmtP::Statements(stmts) => {
+                    let len = self.narrowings.len();
+                    for stmt in stmts {
+                        self.visit(Visit::Stmt(stmt), return_type, typecheck_mode, codemap)?;
+                        // After `if x == None: return`, `x` is not `None`.
+                        match &**stmt {
+                            StmtP::If(cond, body) if terminates(body) => {
+                                self.push_narrowings(cond, false)
+                            }
+                            StmtP::IfElse(cond, then_else) => {
+                                let (then_block, else_block) = &**then_else;
+                                match (terminates(then_block), terminates(else_block)) {
+                                    (true, false) => self.push_narrowings(cond, false),
+                                    (false, true) => self.push_narrowings(cond, true),
+                                    _ => {}
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    self.truncate_narrowings(len);
+                    return Ok(());
+                }
                 _ => {}
             },
             Visit::Expr(x) => match &**x {
+                ExprP::Identifier(ident) => {
+                    if let Some(ResolvedIdent::Slot(_, id)) = &ident.node.payload {
+                        let narrowed = self.narrowings_of(*id);
+                        if !narrowed.is_empty() {
+                            self.bindings.narrowed.insert(ident.span, narrowed);
+                        }
+                    }
+                }
+                ExprP::If(cond_then_else) => {
+                    let (cond, then_expr, else_expr) = &**cond_then_else;
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        Visit::Expr(then_expr),
+                        cond,
+                        true,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    self.visit_narrowed(
+                        Visit::Expr(else_expr),
+                        cond,
+                        false,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                // `x != None and x.f`, `x == None or x.f`.
+                ExprP::Op(lhs, op @ (BinOp::And | BinOp::Or), rhs) => {
+                    self.visit(Visit::Expr(lhs), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        Visit::Expr(rhs),
+                        lhs,
+                        *op == BinOp::And,
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                ExprP::Lambda(_) => {
+                    return self.visit_nested(|this| {
+                        Visit::Expr(x).visit_children_err(|x| {
+                            this.visit(x, return_type, typecheck_mode, codemap)
+                        })
+                    });
+                }
                 ExprP::ListComprehension(_, for1, clauses)
                 | ExprP::DictComprehension(_, for1, clauses) => {
                     fn get_for_clause(x: &ClauseP<CstPayload>) -> Option<&ForClauseP<CstPayload>> {
@@ -389,4 +584,36 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
         x.visit_children_err(|x| self.visit(x, return_type, typecheck_mode, codemap))?;
         Ok(())
     }
+
+    /// Invalidate the conditions on the variables assigned in `x`.
+    fn kill_assigned(&mut self, x: &CstStmt) {
+        match &**x {
+            StmtP::Assign(AssignP { lhs, .. }) | StmtP::AssignModify(lhs, _, _) => {
+                self.kill_narrowings(lhs)
+            }
+            StmtP::For(ForP { var, .. }) => self.kill_narrowings(var),
+            // Nested functions are separate scopes.
+            StmtP::Def(_) => return,
+            _ => {}
+        }
+        x.visit_stmt(|x| self.kill_assigned(x));
+    }
+}
+
+/// Does executing `x` never continue with the next statement.
+fn terminates(x: &CstStmt) -> bool {
+    match &**x {
+        StmtP::Return(_) | StmtP::Break | StmtP::Continue => true,
+        StmtP::Expression(x) => match &**x {
+            ExprP::Call(f, _) => matches!(
+                &f.node,
+                ExprP::Identifier(f) if f.node.ident == "fail"
+                    && matches!(f.node.payload, Some(ResolvedIdent::Global(_)))
+            ),
+            _ => false,
+        },
+        StmtP::Statements(xs) => xs.last().is_some_and(terminates),
+        StmtP::IfElse(_, then_else) => terminates(&then_else.0) && terminates(&then_else.1),
+        _ => false,
+    }
 }
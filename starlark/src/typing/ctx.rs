@@ -16,6 +16,7 @@
  */
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 use starlark_map::unordered_map::UnorderedMap;
@@ -46,11 +47,13 @@ use crate::typing::error::InternalError;
 use crate::typing::error::TypingError;
 use crate::typing::error::TypingOrInternalError;
 use crate::typing::fill_types_for_lint::ModuleVarTypes;
+use crate::typing::narrow::Narrow;
 use crate::typing::oracle::ctx::TypingOracleCtx;
 use crate::typing::oracle::traits::TypingBinOp;
 use crate::typing::oracle::traits::TypingUnOp;
 use crate::typing::ty::Approximation;
 use crate::typing::ty::Ty;
+use crate::values::bytes::StarlarkBytes;
 
 pub(crate) struct TypingContext<'a> {
     pub(crate) oracle: TypingOracleCtx<'a>,
@@ -60,6 +63,8 @@ pub(crate) struct TypingContext<'a> {
     pub(crate) approximoations: RefCell<Vec<Approximation>>,
     pub(crate) types: UnorderedMap<BindingId, Ty>,
     pub(crate) module_var_types: &'a ModuleVarTypes,
+    /// Conditions narrowing the types of variables, by the span of their uses.
+    pub(crate) narrowed: &'a HashMap<Span, Vec<Narrow>>,
 }
 
 impl TypingContext<'_> {
@@ -380,7 +385,18 @@ impl TypingContext<'_> {
         Ok(self.result_to_ty(self.oracle.expr_slice(span, self.expression_type(x)?)))
     }
 
-    fn expr_ident(&self, x: &CstIdent) -> Ty {
+    fn expr_ident(&self, x: &CstIdent) -> Result<Ty, InternalError> {
+        let mut ty = self.expr_ident_declared(x);
+        if let Some(narrowed) = self.narrowed.get(&x.span) {
+            for narrow in narrowed {
+                ty = narrow.apply(&ty, self.oracle)?;
+            }
+        }
+        Ok(ty)
+    }
+
+    /// The type of the variable, ignoring the conditions known where it is used.
+    fn expr_ident_declared(&self, x: &CstIdent) -> Ty {
         match &x.node.payload {
             Some(ResolvedIdent::Slot(Slot::Module(module_slot_id), _)) => self
                 .module_var_types
@@ -428,7 +444,7 @@ impl TypingContext<'_> {
                 stop.as_deref(),
                 stride.as_deref(),
             ),
-            ExprP::Identifier(x) => Ok(self.expr_ident(x)),
+            ExprP::Identifier(x) => self.expr_ident(x),
             ExprP::Lambda(_) => {
                 self.approximation("We don't type check lambdas", ());
                 Ok(Ty::any_callable())
@@ -437,6 +453,7 @@ impl TypingContext<'_> {
                 AstLiteral::Int(_) => Ok(Ty::int()),
                 AstLiteral::Float(_) => Ok(Ty::float()),
                 AstLiteral::String(_) => Ok(Ty::string()),
+                AstLiteral::Bytes(_) => Ok(Ty::starlark_value::<StarlarkBytes>()),
                 AstLiteral::Ellipsis => Ok(Ty::any()),
             },
             ExprP::Not(x) => {
@@ -471,6 +488,10 @@ impl TypingContext<'_> {
                     .unzip();
                 Ok(Ty::dict(Ty::unions(ks), Ty::unions(vs)))
             }
+            ExprP::Set(xs) => {
+                let ts = xs.try_map(|x| self.expression_type(x))?;
+                Ok(Ty::set(Ty::unions(ts)))
+            }
             ExprP::ListComprehension(a, b, c) => {
                 self.check_comprehension(b, c)?;
                 Ok(Ty::list(self.expression_type(a)?))
@@ -100,6 +100,16 @@ pub trait TyCustomImpl: Debug + Display + Hash + Ord + Allocative + Send + Sync
     fn intersects_with(&self, _other: &TyBasic) -> bool {
         false
     }
+    /// Like [`intersects_with`](TyCustomImpl::intersects_with), for types which need the oracle
+    /// to decide, for example by comparing the types of attributes.
+    fn intersects_with_oracle(
+        &self,
+        other: &TyBasic,
+        ctx: TypingOracleCtx,
+    ) -> Result<bool, InternalError> {
+        let _unused = ctx;
+        Ok(self.intersects_with(other))
+    }
 
     /// Create runtime type matcher for values.
     fn matcher<T: TypeMatcherAlloc>(&self, factory: T) -> T::Result;
@@ -119,7 +129,11 @@ pub(crate) trait TyCustomDyn: Debug + Display + Allocative + Send + Sync + 'stat
         args: &TyCallArgs,
         oracle: TypingOracleCtx,
     ) -> Result<Ty, TypingOrInternalError>;
-    fn is_intersects_with_dyn(&self, other: &TyBasic) -> bool;
+    fn is_intersects_with_dyn(
+        &self,
+        other: &TyBasic,
+        ctx: TypingOracleCtx,
+    ) -> Result<bool, InternalError>;
     fn as_callable_dyn(&self) -> Option<TyCallable>;
     fn as_function_dyn(&self) -> Option<&TyFunction>;
     fn iter_item_dyn(&self) -> Result<Ty, TypingNoContextError>;
@@ -189,8 +203,12 @@ impl<T: TyCustomImpl> TyCustomDyn for T {
         self.as_callable()
     }
 
-    fn is_intersects_with_dyn(&self, other: &TyBasic) -> bool {
-        self.intersects_with(other)
+    fn is_intersects_with_dyn(
+        &self,
+        other: &TyBasic,
+        ctx: TypingOracleCtx,
+    ) -> Result<bool, InternalError> {
+        self.intersects_with_oracle(other, ctx)
     }
 
     fn as_function_dyn(&self) -> Option<&TyFunction> {
@@ -283,7 +301,7 @@ impl TyCustom {
         other: &TyBasic,
         ctx: TypingOracleCtx,
     ) -> Result<bool, InternalError> {
-        if self.0.is_intersects_with_dyn(other) {
+        if self.0.is_intersects_with_dyn(other, ctx)? {
             return Ok(true);
         }
         match other {
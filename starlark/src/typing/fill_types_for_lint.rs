@@ -18,9 +18,11 @@
 use std::fmt::Display;
 use std::iter;
 
+use starlark_map::sorted_map::SortedMap;
 use starlark_map::unordered_map;
 use starlark_map::unordered_map::UnorderedMap;
 use starlark_syntax::slice_vec_ext::SliceExt;
+use starlark_syntax::syntax::ast::ArgumentP;
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTargetP;
 use starlark_syntax::syntax::ast::AstLiteral;
@@ -56,6 +58,8 @@ use crate::eval::compiler::scope::payload::CstTypeExpr;
 use crate::typing::Approximation;
 use crate::typing::ParamSpec;
 use crate::typing::Ty;
+use crate::typing::TyStructuralRecord;
+use crate::typing::TyTypeVar;
 use crate::typing::TypingOracleCtx;
 use crate::typing::callable_param::ParamIsRequired;
 use crate::typing::error::InternalError;
@@ -63,9 +67,14 @@ use crate::typing::error::TypingError;
 use crate::util::arc_str::ArcStr;
 use crate::values::Heap;
 use crate::values::Value;
+use crate::values::ValueLike;
 use crate::values::tuple::AllocTuple;
 use crate::values::types::ellipsis::Ellipsis;
+use crate::values::typing::structural_record::StructuralRecordType;
+use crate::values::typing::structural_record::TypingRecord;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
+use crate::values::typing::type_var::TypingTypeVar;
+use crate::values::typing::type_var::TypingTypeVarValue;
 
 /// Value computed during partial evaluation of globals.
 #[derive(Clone)]
@@ -134,11 +143,87 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
         GlobalValue::any()
     }
 
+    /// If `f` is `x.attr`, and evaluates to a value, that value.
+    fn dot_value(&mut self, f: &CstExpr, attr: &str) -> Result<Option<Value<'v>>, InternalError> {
+        match &f.node {
+            ExprP::Dot(_, a) if a.node == attr => Ok(self.expr(f)?.value),
+            _ => Ok(None),
+        }
+    }
+
+    /// `typing.TypeVar("T")` declares a type variable used in later type expressions.
+    fn type_var(
+        &mut self,
+        f: &CstExpr,
+        args: &CallArgsP<CstPayload>,
+    ) -> Result<Option<GlobalValue<'v>>, InternalError> {
+        let Some(f) = self.dot_value(f, "TypeVar")? else {
+            return Ok(None);
+        };
+        if f.downcast_ref::<TypingTypeVar>().is_none() {
+            return Ok(None);
+        }
+        let [arg] = args.args.as_slice() else {
+            return Ok(None);
+        };
+        let ArgumentP::Positional(arg) = &arg.node else {
+            return Ok(None);
+        };
+        let ExprP::Literal(AstLiteral::String(name)) = &arg.node else {
+            return Ok(None);
+        };
+        Ok(Some(GlobalValue::value(self.heap.alloc_simple(
+            TypingTypeVarValue(TyTypeVar::new(&name.node)),
+        ))))
+    }
+
+    /// `typing.record(name = str)` declares a structural type.
+    fn structural_record(
+        &mut self,
+        f: &CstExpr,
+        args: &CallArgsP<CstPayload>,
+    ) -> Result<Option<GlobalValue<'v>>, InternalError> {
+        let Some(f) = self.dot_value(f, "record")? else {
+            return Ok(None);
+        };
+        if f.downcast_ref::<TypingRecord>().is_none() {
+            return Ok(None);
+        }
+        let mut fields = Vec::new();
+        for arg in &args.args {
+            let ArgumentP::Named(name, ty) = &arg.node else {
+                return Ok(None);
+            };
+            let Some(ty) = self.expr(ty)?.value else {
+                return Ok(None);
+            };
+            match TypeCompiled::new(ty, self.heap) {
+                Ok(ty) => fields.push((ArcStr::from(name.as_str()), ty.as_ty().clone())),
+                Err(e) => {
+                    self.errors
+                        .push(TypingError::new_anyhow(e, arg.span, self.ctx.codemap));
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(GlobalValue::value(self.heap.alloc_simple(
+            StructuralRecordType(TyStructuralRecord {
+                fields: SortedMap::from_iter(fields),
+            }),
+        ))))
+    }
+
     fn call(
         &mut self,
-        _f: &CstExpr,
-        _args: &CallArgsP<CstPayload>,
+        f: &CstExpr,
+        args: &CallArgsP<CstPayload>,
     ) -> Result<GlobalValue<'v>, InternalError> {
+        if let Some(type_var) = self.type_var(f, args)? {
+            return Ok(type_var);
+        }
+        if let Some(record) = self.structural_record(f, args)? {
+            return Ok(record);
+        }
         // TODO(nga): could be a call like `record(...)`, and we need to evaluate it.
         Ok(GlobalValue::any())
     }
@@ -164,11 +249,30 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
     fn expr_literal(&mut self, literal: &AstLiteral) -> Result<GlobalValue<'v>, InternalError> {
         match literal {
             AstLiteral::String(s) => Ok(GlobalValue::value(self.heap.alloc(s.node.as_str()))),
-            // Not used in type expressions.
-            _ => Ok(GlobalValue::any()),
+            // Not used in type expressions, but give the types of module variables.
+            AstLiteral::Int(_) => Ok(GlobalValue::ty(Ty::int())),
+            AstLiteral::Float(_) => Ok(GlobalValue::ty(Ty::float())),
+            AstLiteral::Bytes(_) | AstLiteral::Ellipsis => Ok(GlobalValue::any()),
         }
     }
 
+    /// The union of the types of the items of a list or dict literal. Module variables are
+    /// not typed by what functions add to them, so the items of an empty literal are `Any`,
+    /// where in a function they would be inferred from the appends.
+    fn items_ty<'e>(
+        &mut self,
+        xs: impl ExactSizeIterator<Item = &'e CstExpr>,
+    ) -> Result<Ty, InternalError> {
+        if xs.len() == 0 {
+            return Ok(Ty::any());
+        }
+        let mut ts = Vec::new();
+        for x in xs {
+            ts.push(self.expr(x)?.ty);
+        }
+        Ok(Ty::unions(ts))
+    }
+
     fn tuple(&mut self, xs: &[CstExpr]) -> Result<GlobalValue<'v>, InternalError> {
         let xs = xs.try_map(|x| self.expr_spanned(x))?;
         if let Ok(xs) = xs.try_map(|v| v.value.ok_or(())) {
@@ -270,6 +374,12 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
             ExprP::Identifier(ident) => self.expr_ident(ident),
             ExprP::Literal(lit) => self.expr_literal(lit),
             ExprP::Op(lhs, op, rhs) => self.bin_op(span, lhs, *op, rhs),
+            ExprP::List(xs) => Ok(GlobalValue::ty(Ty::list(self.items_ty(xs.iter())?))),
+            ExprP::Dict(xs) => {
+                let k = self.items_ty(xs.iter().map(|(k, _)| k))?;
+                let v = self.items_ty(xs.iter().map(|(_, v)| v))?;
+                Ok(GlobalValue::ty(Ty::dict(k, v)))
+            }
             // These are not used in type expressions.
             ExprP::Slice(..)
             | ExprP::Lambda(_)
@@ -278,8 +388,7 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
             | ExprP::Plus(..)
             | ExprP::BitNot(..)
             | ExprP::If(..)
-            | ExprP::List(_)
-            | ExprP::Dict(_)
+            | ExprP::Set(_)
             | ExprP::ListComprehension(_, _, _)
             | ExprP::DictComprehension(_, _, _)
             | ExprP::FString(_) => Ok(GlobalValue::any()),
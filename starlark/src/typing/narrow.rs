@@ -0,0 +1,197 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Narrowing of the types of variables by conditions, like `if x != None:`.
+
+use dupe::Dupe;
+use starlark_syntax::syntax::ast::ArgumentP;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::BinOp;
+use starlark_syntax::syntax::ast::ExprP;
+
+use crate::eval::compiler::scope::BindingId;
+use crate::eval::compiler::scope::ResolvedIdent;
+use crate::eval::compiler::scope::payload::CstExpr;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TypingOracleCtx;
+use crate::typing::error::InternalError;
+use crate::values::FrozenValue;
+
+/// A condition on the value of a variable.
+#[derive(Clone, Debug)]
+pub(crate) enum NarrowTest {
+    /// `x == None`.
+    None,
+    /// `type(x) == "name"`.
+    TypeName(String),
+    /// `isinstance(x, t)`.
+    IsInstance(Ty),
+}
+
+/// A condition known to be true, or known to be false, where a variable is used.
+#[derive(Clone, Debug)]
+pub(crate) struct Narrow {
+    pub(crate) test: NarrowTest,
+    pub(crate) positive: bool,
+}
+
+impl Narrow {
+    fn new(test: NarrowTest, positive: bool) -> Narrow {
+        Narrow { test, positive }
+    }
+
+    /// Keep only the alternatives of `ty` which may satisfy the condition.
+    pub(crate) fn apply(&self, ty: &Ty, oracle: TypingOracleCtx) -> Result<Ty, InternalError> {
+        if ty.is_any() {
+            return Ok(match (&self.test, self.positive) {
+                (NarrowTest::None, true) => Ty::none(),
+                (NarrowTest::IsInstance(t), true) => t.dupe(),
+                _ => Ty::any(),
+            });
+        }
+        let mut keep = Vec::new();
+        for x in ty.iter_union() {
+            if self.keeps(x, oracle)? {
+                keep.push(Ty::basic(x.dupe()));
+            }
+        }
+        if keep.is_empty() {
+            // The code is unreachable, do not turn that into type errors.
+            return Ok(ty.dupe());
+        }
+        Ok(Ty::unions(keep))
+    }
+
+    fn keeps(&self, x: &TyBasic, oracle: TypingOracleCtx) -> Result<bool, InternalError> {
+        if let TyBasic::Any = x {
+            return Ok(true);
+        }
+        Ok(match &self.test {
+            NarrowTest::None => (x == &TyBasic::none()) == self.positive,
+            NarrowTest::TypeName(name) => match x.as_name() {
+                Some(x) => (x == name) == self.positive,
+                None => true,
+            },
+            NarrowTest::IsInstance(t) => {
+                if self.positive {
+                    oracle.intersects(&Ty::basic(x.dupe()), t)?
+                } else {
+                    !t.iter_union().contains(x)
+                }
+            }
+        })
+    }
+}
+
+/// The local variable `x` is bound to, if `x` is an identifier.
+fn var(x: &CstExpr) -> Option<BindingId> {
+    match &x.node {
+        ExprP::Identifier(x) => match &x.node.payload {
+            Some(ResolvedIdent::Slot(_, id)) => Some(*id),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The global `x` refers to, if `x` is an identifier resolved to a builtin.
+fn global(x: &CstExpr) -> Option<(&str, FrozenValue)> {
+    match &x.node {
+        ExprP::Identifier(x) => match &x.node.payload {
+            Some(ResolvedIdent::Global(g)) => Some((x.node.ident.as_str(), *g)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The variable tested by a call `name(x)` to the builtin `name`,
+/// and the other positional arguments.
+fn call_of<'a>(x: &'a CstExpr, name: &str) -> Option<(BindingId, Vec<&'a CstExpr>)> {
+    let ExprP::Call(f, args) = &x.node else {
+        return None;
+    };
+    if global(f)?.0 != name {
+        return None;
+    }
+    let mut pos = Vec::new();
+    for arg in &args.args {
+        match &arg.node {
+            ArgumentP::Positional(x) => pos.push(x),
+            _ => return None,
+        }
+    }
+    let (first, rest) = pos.split_first()?;
+    Some((var(first)?, rest.to_vec()))
+}
+
+/// The narrowing by `lhs == rhs`.
+fn eq(lhs: &CstExpr, rhs: &CstExpr) -> Option<(BindingId, NarrowTest)> {
+    if let Some(id) = var(lhs) {
+        if global(rhs)?.1.is_none() {
+            return Some((id, NarrowTest::None));
+        }
+        return None;
+    }
+    let (id, rest) = call_of(lhs, "type")?;
+    match (rest.as_slice(), &rhs.node) {
+        ([], ExprP::Literal(AstLiteral::String(name))) => {
+            Some((id, NarrowTest::TypeName(name.node.clone())))
+        }
+        _ => None,
+    }
+}
+
+/// The variables narrowed where `cond` is known to be `positive`.
+pub(crate) fn narrowings(cond: &CstExpr, positive: bool) -> Vec<(BindingId, Narrow)> {
+    match &cond.node {
+        ExprP::Not(x) => narrowings(x, !positive),
+        ExprP::Op(lhs, BinOp::And, rhs) if positive => {
+            let mut res = narrowings(lhs, true);
+            res.extend(narrowings(rhs, true));
+            res
+        }
+        ExprP::Op(lhs, BinOp::Or, rhs) if !positive => {
+            let mut res = narrowings(lhs, false);
+            res.extend(narrowings(rhs, false));
+            res
+        }
+        ExprP::Op(lhs, op @ (BinOp::Equal | BinOp::NotEqual), rhs) => {
+            let positive = (*op == BinOp::Equal) == positive;
+            match eq(lhs, rhs).or_else(|| eq(rhs, lhs)) {
+                Some((id, test)) => vec![(id, Narrow::new(test, positive))],
+                None => Vec::new(),
+            }
+        }
+        ExprP::Call(..) => match call_of(cond, "isinstance") {
+            Some((id, rest)) => match rest.as_slice() {
+                [t] => match global(t).and_then(|(_, t)| t.to_value().get_ref().eval_type()) {
+                    Some(t) => vec![(id, Narrow::new(NarrowTest::IsInstance(t), positive))],
+                    None => Vec::new(),
+                },
+                _ => Vec::new(),
+            },
+            None => Vec::new(),
+        },
+        // A truthy value is not `None`.
+        ExprP::Identifier(..) if positive => match var(cond) {
+            Some(id) => vec![(id, Narrow::new(NarrowTest::None, false))],
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
@@ -41,6 +41,7 @@ use crate::typing::error::TypingNoContextOrInternalError;
 use crate::typing::error::TypingOrInternalError;
 use crate::typing::starlark_value::TyStarlarkValue;
 use crate::typing::tuple::TyTuple;
+use crate::typing::type_var::TypeVarBindings;
 use crate::values::dict::value::MutableDict;
 use crate::values::list::value::List;
 use crate::values::set::value::MutableSet;
@@ -143,11 +144,13 @@ impl<'a> TypingOracleCtx<'a> {
     }
 
     #[allow(clippy::redundant_pattern_matching)]
+    /// Check the arguments of a call, binding type variables in `type_vars` if given.
     fn validate_args(
         &self,
         params: &ParamSpec,
         args: &TyCallArgs,
         span: Span,
+        mut type_vars: Option<&mut TypeVarBindings>,
     ) -> Result<(), TypingOrInternalError> {
         // Want to figure out which arguments go in which positions
         let mut param_args: Vec<Vec<Spanned<&Ty>>> = vec![vec![]; params.params().len()];
@@ -227,7 +230,12 @@ impl<'a> TypingOracleCtx<'a> {
                             ));
                         }
                     }
-                    [arg] => self.validate_type(*arg, &param.ty)?,
+                    [arg] => {
+                        self.validate_type(*arg, &param.ty)?;
+                        if let Some(type_vars) = &mut type_vars {
+                            type_vars.unify(&param.ty, arg.node, *self);
+                        }
+                    }
                     [_, _, ..] => {
                         return Err(TypingOrInternalError::Internal(InternalError::msg(
                             "Multiple arguments bound to parameter",
@@ -241,11 +249,17 @@ impl<'a> TypingOracleCtx<'a> {
                         // For an arg, we require the type annotation to be inner value,
                         // rather than the outer (which is always a tuple)
                         self.validate_type(ty, &param.ty)?;
+                        if let Some(type_vars) = &mut type_vars {
+                            type_vars.unify(&param.ty, ty.node, *self);
+                        }
                     }
                 }
                 ParamMode::Kwargs => {
                     for ty in args {
                         self.validate_type(ty, &param.ty)?;
+                        if let Some(type_vars) = &mut type_vars {
+                            type_vars.unify(&param.ty, ty.node, *self);
+                        }
                     }
                 }
             }
@@ -259,8 +273,13 @@ impl<'a> TypingOracleCtx<'a> {
         fun: &TyCallable,
         args: &TyCallArgs,
     ) -> Result<Ty, TypingOrInternalError> {
-        self.validate_args(fun.params(), args, span)?;
-        Ok(fun.result().dupe())
+        if !TypeVarBindings::mentions_type_vars(fun.result()) {
+            self.validate_args(fun.params(), args, span, None)?;
+            return Ok(fun.result().dupe());
+        }
+        let mut type_vars = TypeVarBindings::default();
+        self.validate_args(fun.params(), args, span, Some(&mut type_vars))?;
+        Ok(type_vars.substitute(fun.result()))
     }
 
     #[allow(clippy::collapsible_else_if)]
@@ -280,7 +299,7 @@ impl<'a> TypingOracleCtx<'a> {
                         ty: fun.to_string(),
                     },
                 )),
-            TyBasic::Iter(_) | TyBasic::Type => {
+            TyBasic::Iter(_) | TyBasic::Type | TyBasic::TypeVar(_) => {
                 // Unknown type, may be callable.
                 Ok(Ty::any())
             }
@@ -325,7 +344,7 @@ impl<'a> TypingOracleCtx<'a> {
         }
     }
 
-    fn iter_item_basic(&self, ty: &TyBasic) -> Result<Ty, TypingNoContextError> {
+    pub(crate) fn iter_item_basic(&self, ty: &TyBasic) -> Result<Ty, TypingNoContextError> {
         match ty {
             TyBasic::Any => Ok(Ty::any()),
             TyBasic::StarlarkValue(ty) => ty.iter_item(),
@@ -333,7 +352,7 @@ impl<'a> TypingOracleCtx<'a> {
             TyBasic::Dict(k, _v) => Ok((**k).dupe()),
             TyBasic::Tuple(tuple) => Ok(tuple.item_ty()),
             TyBasic::Callable(_) => Ok(Ty::any()),
-            TyBasic::Type => Ok(Ty::any()),
+            TyBasic::Type | TyBasic::TypeVar(_) => Ok(Ty::any()),
             TyBasic::Iter(ty) => Ok(ty.to_ty()),
             TyBasic::Custom(ty) => ty.0.iter_item_dyn(),
             TyBasic::Set(item) => Ok((**item).dupe()),
@@ -359,7 +378,11 @@ impl<'a> TypingOracleCtx<'a> {
         index: Spanned<&TyBasic>,
     ) -> Result<Ty, TypingNoContextOrInternalError> {
         match array {
-            TyBasic::Any | TyBasic::Callable(_) | TyBasic::Iter(_) | TyBasic::Type => Ok(Ty::any()),
+            TyBasic::Any
+            | TyBasic::Callable(_)
+            | TyBasic::Iter(_)
+            | TyBasic::Type
+            | TyBasic::TypeVar(_) => Ok(Ty::any()),
             TyBasic::Tuple(tuple) => {
                 if !self.intersects_basic(index.node, &TyBasic::int())? {
                     return Err(TypingNoContextOrInternalError::Typing);
@@ -459,9 +482,17 @@ impl<'a> TypingOracleCtx<'a> {
         }
     }
 
-    fn expr_dot_basic(&self, array: &TyBasic, attr: &str) -> Result<Ty, TypingNoContextError> {
+    pub(crate) fn expr_dot_basic(
+        &self,
+        array: &TyBasic,
+        attr: &str,
+    ) -> Result<Ty, TypingNoContextError> {
         match array {
-            TyBasic::Any | TyBasic::Callable(_) | TyBasic::Iter(_) | TyBasic::Type => Ok(Ty::any()),
+            TyBasic::Any
+            | TyBasic::Callable(_)
+            | TyBasic::Iter(_)
+            | TyBasic::Type
+            | TyBasic::TypeVar(_) => Ok(Ty::any()),
             TyBasic::StarlarkValue(s) => s.attr(attr),
             TyBasic::Tuple(_) => Err(TypingNoContextError),
             TyBasic::List(elem) => match attr {
@@ -537,6 +568,7 @@ impl<'a> TypingOracleCtx<'a> {
                 Ok(x) => Ok(Ty::basic(TyBasic::StarlarkValue(x))),
                 Err(TypingNoContextError) => Err(TypingNoContextError),
             },
+            TyBasic::TypeVar(_) => Ok(Ty::any()),
             _ => Err(TypingNoContextError),
         }
     }
@@ -563,7 +595,11 @@ impl<'a> TypingOracleCtx<'a> {
         rhs: Spanned<&TyBasic>,
     ) -> Result<Ty, TypingNoContextOrInternalError> {
         match lhs {
-            TyBasic::Any | TyBasic::Iter(_) | TyBasic::Callable(_) | TyBasic::Type => Ok(Ty::any()),
+            TyBasic::Any
+            | TyBasic::Iter(_)
+            | TyBasic::Callable(_)
+            | TyBasic::Type
+            | TyBasic::TypeVar(_) => Ok(Ty::any()),
             TyBasic::StarlarkValue(lhs) => Ok(lhs.bin_op(bin_op, rhs.node)?),
             lhs @ TyBasic::List(elem) => match bin_op {
                 TypingBinOp::Less => {
@@ -689,7 +725,10 @@ impl<'a> TypingOracleCtx<'a> {
         bin_op: TypingBinOp,
         rhs: Spanned<&TyBasic>,
     ) -> Result<Ty, TypingOrInternalError> {
-        if let TyBasic::Any = lhs.node {
+        if let TyBasic::Any | TyBasic::TypeVar(_) = lhs.node {
+            return Ok(Ty::any());
+        }
+        if let TyBasic::TypeVar(_) = rhs.node {
             return Ok(Ty::any());
         }
 
@@ -917,6 +956,7 @@ impl<'a> TypingOracleCtx<'a> {
                 kwargs: None,
             },
             Span::default(),
+            None,
         ) {
             Ok(()) => Ok(true),
             Err(TypingOrInternalError::Internal(e)) => Err(e),
@@ -938,6 +978,8 @@ impl<'a> TypingOracleCtx<'a> {
     fn intersects_one_side(&self, x: &TyBasic, y: &TyBasic) -> Result<bool, InternalError> {
         match (x, y) {
             (TyBasic::Any, _) => Ok(true),
+            // Bound at call sites, and otherwise unknown.
+            (TyBasic::TypeVar(_), _) => Ok(true),
             (TyBasic::List(x), TyBasic::List(y)) => self.intersects(x, y),
             (TyBasic::List(_), TyBasic::StarlarkValue(y)) => Ok(y.is_list()),
             (TyBasic::List(_), _) => Ok(false),
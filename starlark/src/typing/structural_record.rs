@@ -0,0 +1,138 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Structural types, declared with `typing.record`.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use allocative::Allocative;
+use starlark_derive::type_matcher;
+use starlark_map::sorted_map::SortedMap;
+
+use crate as starlark;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TypingOracleCtx;
+use crate::typing::custom::TyCustomImpl;
+use crate::typing::error::InternalError;
+use crate::typing::error::TypingNoContextError;
+use crate::util::arc_str::ArcStr;
+use crate::values::Value;
+use crate::values::provider::Provider;
+use crate::values::record::Record;
+use crate::values::structs::StructRef;
+use crate::values::typing::type_compiled::alloc::TypeMatcherAlloc;
+use crate::values::typing::type_compiled::matcher::TypeMatcher;
+use crate::values::typing::type_compiled::matcher::TypeMatcherBox;
+use crate::values::typing::type_compiled::matcher::TypeMatcherBoxAlloc;
+
+/// The value of the field `name` of a struct, record or provider.
+fn field_value<'v>(value: Value<'v>, name: &str) -> Option<Value<'v>> {
+    if let Some(s) = StructRef::from_value(value) {
+        s.iter().find(|(k, _)| k.as_str() == name).map(|(_, v)| v)
+    } else if let Some(r) = Record::from_value(value) {
+        r.iter().find(|(k, _)| *k == name).map(|(_, v)| v)
+    } else if let Some(p) = Provider::from_value(value) {
+        p.iter().find(|(k, _)| *k == name).map(|(_, v)| v)
+    } else {
+        None
+    }
+}
+
+#[derive(Allocative, Debug, Clone)]
+struct StructuralRecordMatcher {
+    fields: Vec<(String, TypeMatcherBox)>,
+}
+
+#[type_matcher]
+impl TypeMatcher for StructuralRecordMatcher {
+    fn matches(&self, value: Value) -> bool {
+        self.fields
+            .iter()
+            .all(|(name, matcher)| match field_value(value, name) {
+                Some(v) => matcher.matches(v),
+                None => false,
+            })
+    }
+}
+
+/// Structural type, like `typing.record(name = str, deps = list[str])`.
+///
+/// Any value which has the fields with matching types conforms to the type,
+/// whether it is a struct, a record or a provider.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Allocative)]
+pub struct TyStructuralRecord {
+    /// The fields the values must have, with their types.
+    pub(crate) fields: SortedMap<ArcStr, Ty>,
+}
+
+impl TyCustomImpl for TyStructuralRecord {
+    fn as_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn attribute(&self, attr: &str) -> Result<Ty, TypingNoContextError> {
+        match self.fields.get(attr) {
+            Some(ty) => Ok(ty.clone()),
+            None => Err(TypingNoContextError),
+        }
+    }
+
+    fn intersects(x: &Self, y: &Self) -> bool {
+        x == y
+    }
+
+    fn intersects_with_oracle(
+        &self,
+        other: &TyBasic,
+        ctx: TypingOracleCtx,
+    ) -> Result<bool, InternalError> {
+        for (name, ty) in &self.fields {
+            match ctx.expr_dot_basic(other, name.as_str()) {
+                Ok(other_ty) => {
+                    if !ctx.intersects(&other_ty, ty)? {
+                        return Ok(false);
+                    }
+                }
+                Err(TypingNoContextError) => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    fn matcher<T: TypeMatcherAlloc>(&self, factory: T) -> T::Result {
+        factory.alloc(StructuralRecordMatcher {
+            fields: self
+                .fields
+                .iter()
+                .map(|(name, ty)| (name.as_str().to_owned(), TypeMatcherBoxAlloc.ty(ty)))
+                .collect(),
+        })
+    }
+}
+
+impl Display for TyStructuralRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        display_container::fmt_container(
+            f,
+            "typing.record(",
+            ")",
+            self.fields.iter().map(|(k, v)| format!("{k} = {v}")),
+        )
+    }
+}
@@ -26,6 +26,7 @@ use starlark_syntax::golden_test_template::golden_test_template;
 use crate as starlark;
 use crate::assert::Assert;
 use crate::environment::FrozenModule;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
 use crate::environment::Module;
 use crate::eval::Evaluator;
@@ -48,8 +49,11 @@ use crate::values::typing::StarlarkIter;
 
 mod call;
 mod callable;
+mod generic;
 mod list;
+mod narrow;
 mod special_function;
+mod structural_record;
 mod tuple;
 mod types;
 
@@ -476,3 +480,40 @@ def test() -> test:
 "#,
     );
 }
+
+#[test]
+fn test_typemap_bindings() {
+    let ast = AstModule::parse(
+        "test.star",
+        r#"
+def foo(x: str):
+    y = len(x)
+    z = [y]
+"#
+        .to_owned(),
+        &Dialect::AllOptionsInternal,
+    )
+    .unwrap();
+    let (errors, typemap, _, _) = ast.typecheck(&Globals::standard(), &HashMap::new());
+    assert!(errors.is_empty());
+    let bindings: Vec<_> = typemap
+        .bindings()
+        .into_iter()
+        .map(|(name, span, ty)| {
+            (
+                name,
+                typemap.codemap().resolve_span(span).begin.line,
+                ty.to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("foo", 1, "def(x: str) -> typing.Any".to_owned()),
+            ("x", 1, "str".to_owned()),
+            ("y", 2, "int".to_owned()),
+            ("z", 3, "list[int]".to_owned()),
+        ],
+        bindings
+    );
+}
@@ -0,0 +1,42 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Generic functions, declared with `typing.TypeVar`.
+
+use crate::typing::tests::TypeCheck;
+
+#[test]
+fn test_generic_function() {
+    TypeCheck::new().ty("x").ty("y").ty("z").check(
+        "generic_function",
+        r#"
+T = typing.TypeVar("T")
+K = typing.TypeVar("K")
+V = typing.TypeVar("V")
+
+def first(xs: list[T]) -> T:
+    return xs[0]
+
+def swap(d: dict[K, V]) -> dict[V, K]:
+    return {v: k for k, v in d.items()}
+
+def test():
+    x = first([1, 2])
+    y = first(["a"])
+    z = swap({"a": 1})
+"#,
+    );
+}
@@ -56,6 +56,21 @@ def test():
     );
 }
 
+#[test]
+fn test_list_module_variable() {
+    TypeCheck::new().ty("x").ty("e").check(
+        "list_module_variable",
+        r#"
+X = [1, "a"]
+E = []
+def test():
+    x = X
+    e = E
+    e.append(1)
+"#,
+    );
+}
+
 #[test]
 fn test_list_function() {
     TypeCheck::new().ty("x").check(
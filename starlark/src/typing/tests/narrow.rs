@@ -0,0 +1,46 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Narrowing of the types of variables by conditions.
+
+use crate::typing::tests::TypeCheck;
+
+#[test]
+fn test_narrow() {
+    TypeCheck::new()
+        .ty("a")
+        .ty("b")
+        .ty("c")
+        .ty("d")
+        .ty("e")
+        .check(
+            "narrow",
+            r#"
+def f(x: str | None, y: int | str, z: list[int] | None, w: str | list[str]):
+    if x == None:
+        return
+    a = x
+    if type(y) == "string":
+        b = y
+    else:
+        c = y
+    d = z[0] if z != None else 0
+    if not isinstance(w, str):
+        fail("expected a string")
+    e = w
+"#,
+        );
+}
@@ -0,0 +1,53 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Structural types, declared with `typing.record`.
+
+use crate::typing::tests::TypeCheck;
+
+#[test]
+fn test_structural_record() {
+    TypeCheck::new().ty("x").ty("y").ty("z").check(
+        "structural_record",
+        r#"
+Target = typing.record(name = str, deps = list[str])
+
+def name_of(t: Target) -> str:
+    return t.name
+
+def test():
+    x = name_of(struct(name = "a", deps = []))
+    y = Target(name = "b", deps = ["a"])
+    z = y.deps
+"#,
+    );
+}
+
+#[test]
+fn test_structural_record_errors() {
+    TypeCheck::new().check(
+        "structural_record_errors",
+        r#"
+Target = typing.record(name = str, deps = list[str])
+
+def version_of(t: Target):
+    return t.version
+
+def test():
+    Target(name = 1, deps = [])
+"#,
+    );
+}
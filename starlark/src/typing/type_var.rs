@@ -0,0 +1,238 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Type variables, for generic functions.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::ParamSpec;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TypingOracleCtx;
+use crate::typing::callable::TyCallable;
+use crate::typing::callable_param::ParamMode;
+use crate::typing::tuple::TyTuple;
+use crate::util::arc_str::ArcStr;
+
+/// A type variable, like `T` in `def first(xs: list[T]) -> T`, declared with
+/// `T = typing.TypeVar("T")`.
+///
+/// When a function whose result type mentions type variables is called, the variables are
+/// bound to the types of the arguments, and replaced in the result type. Elsewhere, for
+/// example in the body of the function, a type variable is treated like `typing.Any`.
+#[derive(Eq, PartialEq, Hash, Clone, Dupe, Debug, Ord, PartialOrd, Allocative)]
+pub struct TyTypeVar {
+    name: ArcStr,
+}
+
+impl TyTypeVar {
+    /// Create a type variable. Variables with the same name are the same variable.
+    pub fn new(name: &str) -> TyTypeVar {
+        TyTypeVar {
+            name: ArcStr::from(name),
+        }
+    }
+
+    /// The name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for TyTypeVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Types bound to type variables while checking one call.
+#[derive(Default, Debug)]
+pub(crate) struct TypeVarBindings {
+    bindings: SmallMap<TyTypeVar, Ty>,
+}
+
+impl TypeVarBindings {
+    /// Does this type mention any type variable.
+    pub(crate) fn mentions_type_vars(ty: &Ty) -> bool {
+        ty.iter_union().iter().any(|x| match x {
+            TyBasic::TypeVar(_) => true,
+            TyBasic::List(x) | TyBasic::Iter(x) | TyBasic::Set(x) => Self::mentions_type_vars(x),
+            TyBasic::Dict(k, v) => Self::mentions_type_vars(k) || Self::mentions_type_vars(v),
+            TyBasic::Tuple(TyTuple::Of(x)) => Self::mentions_type_vars(x),
+            TyBasic::Tuple(TyTuple::Elems(xs)) => xs.iter().any(Self::mentions_type_vars),
+            TyBasic::Callable(c) => {
+                Self::mentions_type_vars(c.result())
+                    || c.params()
+                        .params()
+                        .iter()
+                        .any(|p| Self::mentions_type_vars(&p.ty))
+            }
+            TyBasic::Any | TyBasic::StarlarkValue(_) | TyBasic::Type | TyBasic::Custom(_) => false,
+        })
+    }
+
+    fn bind(&mut self, var: &TyTypeVar, ty: Ty) {
+        match self.bindings.get_mut(var) {
+            Some(bound) => *bound = Ty::union2(bound.dupe(), ty),
+            None => {
+                self.bindings.insert(var.dupe(), ty);
+            }
+        }
+    }
+
+    /// Bind the type variables mentioned in the type of a parameter `param`
+    /// to the matching parts of the type of the argument `arg`.
+    pub(crate) fn unify(&mut self, param: &Ty, arg: &Ty, oracle: TypingOracleCtx) {
+        let (vars, concrete): (Vec<&TyBasic>, Vec<&TyBasic>) = param
+            .iter_union()
+            .iter()
+            .partition(|x| matches!(x, TyBasic::TypeVar(_)));
+        if arg.is_any() {
+            for var in vars {
+                if let TyBasic::TypeVar(var) = var {
+                    self.bind(var, Ty::any());
+                }
+            }
+            return;
+        }
+        for a in arg.iter_union() {
+            // In `T | None` given `int | None`, `None` matches `None`, so only `int` is bound to `T`.
+            let mut matched = false;
+            for p in &concrete {
+                if Self::mentions_type_vars(&Ty::basic((*p).dupe())) {
+                    matched |= self.unify_basic(p, a, oracle);
+                } else {
+                    matched |= p == &a;
+                }
+            }
+            if !matched {
+                for var in &vars {
+                    if let TyBasic::TypeVar(var) = var {
+                        self.bind(var, Ty::basic(a.dupe()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the argument has the shape of the parameter.
+    fn unify_basic(&mut self, param: &TyBasic, arg: &TyBasic, oracle: TypingOracleCtx) -> bool {
+        match (param, arg) {
+            (TyBasic::List(p), TyBasic::List(a)) | (TyBasic::Set(p), TyBasic::Set(a)) => {
+                self.unify(p, a, oracle);
+                true
+            }
+            (TyBasic::Dict(p_k, p_v), TyBasic::Dict(a_k, a_v)) => {
+                self.unify(p_k, a_k, oracle);
+                self.unify(p_v, a_v, oracle);
+                true
+            }
+            (TyBasic::Tuple(p), TyBasic::Tuple(a)) => {
+                match (p, a) {
+                    (TyTuple::Elems(ps), TyTuple::Elems(xs)) if ps.len() == xs.len() => {
+                        for (p, a) in ps.iter().zip(xs.iter()) {
+                            self.unify(p, a, oracle);
+                        }
+                    }
+                    (TyTuple::Elems(ps), TyTuple::Of(a)) => {
+                        for p in ps.iter() {
+                            self.unify(p, a, oracle);
+                        }
+                    }
+                    (TyTuple::Of(p), a) => self.unify(p, &a.item_ty(), oracle),
+                    (TyTuple::Elems(_), TyTuple::Elems(_)) => return false,
+                }
+                true
+            }
+            (TyBasic::Iter(p), a) => match oracle.iter_item_basic(a) {
+                Ok(item) => {
+                    self.unify(p, &item, oracle);
+                    true
+                }
+                Err(_) => false,
+            },
+            (TyBasic::Callable(p), a) => {
+                let a = match a {
+                    TyBasic::Callable(a) => a.dupe(),
+                    TyBasic::Custom(a) => match a.0.as_callable_dyn() {
+                        Some(a) => a,
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                self.unify(p.result(), a.result(), oracle);
+                if let Some(p_params) = p.params().all_required_pos_only() {
+                    let a_params = a
+                        .params()
+                        .params()
+                        .iter()
+                        .take_while(|x| x.allows_pos() && x.mode != ParamMode::Args);
+                    for (p, a) in p_params.into_iter().zip(a_params) {
+                        // Parameters are contravariant, but binding to the union is as good here.
+                        self.unify(p, &a.ty, oracle);
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace the bound type variables in `ty`, and the unbound ones with `typing.Any`.
+    pub(crate) fn substitute(&self, ty: &Ty) -> Ty {
+        Ty::unions(
+            ty.iter_union()
+                .iter()
+                .map(|x| self.substitute_basic(x))
+                .collect(),
+        )
+    }
+
+    fn substitute_basic(&self, ty: &TyBasic) -> Ty {
+        match ty {
+            TyBasic::TypeVar(var) => match self.bindings.get(var) {
+                // Bound only to `Never`, for example by an empty list, so nothing is known.
+                Some(bound) if !bound.is_never() => bound.dupe(),
+                _ => Ty::any(),
+            },
+            TyBasic::List(x) => Ty::list(self.substitute(x)),
+            TyBasic::Iter(x) => Ty::iter(self.substitute(x)),
+            TyBasic::Set(x) => Ty::set(self.substitute(x)),
+            TyBasic::Dict(k, v) => Ty::dict(self.substitute(k), self.substitute(v)),
+            TyBasic::Tuple(TyTuple::Of(x)) => Ty::tuple_of(self.substitute(x)),
+            TyBasic::Tuple(TyTuple::Elems(xs)) => {
+                Ty::tuple(xs.iter().map(|x| self.substitute(x)).collect())
+            }
+            TyBasic::Callable(c) => Ty::basic(TyBasic::Callable(TyCallable::new(
+                match c.params().all_required_pos_only() {
+                    Some(params) => {
+                        ParamSpec::pos_only(params.into_iter().map(|p| self.substitute(p)), [])
+                    }
+                    None => c.params().dupe(),
+                },
+                self.substitute(c.result()),
+            ))),
+            TyBasic::Any | TyBasic::StarlarkValue(_) | TyBasic::Type | TyBasic::Custom(_) => {
+                Ty::basic(ty.dupe())
+            }
+        }
+    }
+}
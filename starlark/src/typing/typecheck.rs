@@ -77,6 +77,7 @@ pub(crate) fn solve_bindings(
         approximoations: RefCell::new(Vec::new()),
         types,
         module_var_types,
+        narrowed: &bindings.narrowed,
     };
     const ITERATIONS: usize = 100;
     for _iteration in 0..ITERATIONS {
@@ -154,6 +155,23 @@ impl Display for TypeMap {
 }
 
 impl TypeMap {
+    /// The inferred types of the top-level functions and of the variables bound in them, with
+    /// their names and the span where each is first bound, in source order.
+    pub fn bindings(&self) -> Vec<(&str, Span, &Ty)> {
+        let mut res: Vec<_> = self
+            .bindings
+            .entries_unordered()
+            .map(|(_, (name, span, ty))| (name.as_str(), *span, ty))
+            .collect();
+        res.sort_by_key(|(_, span, _)| span.begin());
+        res
+    }
+
+    /// The code map of the module, to resolve the spans of [`TypeMap::bindings`].
+    pub fn codemap(&self) -> &CodeMap {
+        &self.codemap
+    }
+
     #[cfg(test)]
     pub(crate) fn find_bindings_by_name<'a>(&'a self, name: &str) -> Vec<&'a Ty> {
         self.bindings
@@ -19,6 +19,7 @@
 
 pub(crate) mod arc_or_static;
 pub(crate) mod arc_str;
+pub(crate) mod instant;
 pub(crate) mod non_static_type_id;
 pub(crate) mod refcell;
 pub(crate) mod rtabort;
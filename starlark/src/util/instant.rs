@@ -0,0 +1,56 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Like [`std::time::Instant`], but read from the clock of the
+//! [`Platform`](crate::platform::Platform), so it works on every target. Where there is
+//! no clock all instants are equal, so all durations are zero.
+
+use std::ops::Add;
+use std::time::Duration;
+
+use allocative::Allocative;
+use dupe::Dupe;
+
+use crate::platform::platform;
+
+#[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq, Ord, PartialOrd, Allocative)]
+pub(crate) struct Instant(Duration);
+
+impl Instant {
+    #[inline]
+    pub(crate) fn now() -> Instant {
+        Instant(platform().now())
+    }
+
+    #[inline]
+    pub(crate) fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    #[inline]
+    pub(crate) fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant(self.0.saturating_add(rhs))
+    }
+}
@@ -35,8 +35,10 @@ pub use owned_frozen_ref::OwnedRefFrozenRef;
 pub use starlark_derive::AllocFrozenValue;
 pub use starlark_derive::AllocValue;
 pub use starlark_derive::Freeze;
+pub use starlark_derive::FromStarlark;
 pub use starlark_derive::NoSerialize;
 pub use starlark_derive::StarlarkAttrs;
+pub use starlark_derive::ToStarlark;
 pub use starlark_derive::Trace;
 pub use starlark_derive::UnpackValue;
 pub use starlark_derive::starlark_attrs;
@@ -48,6 +50,7 @@ pub use crate::coerce::Coerce;
 pub use crate::values::alloc_value::AllocFrozenValue;
 pub use crate::values::alloc_value::AllocValue;
 pub use crate::values::demand::Demand;
+pub use crate::values::deserialize::ValueDeserializeSeed;
 pub use crate::values::error::ValueError;
 pub use crate::values::freeze::Freeze;
 pub use crate::values::freeze_error::FreezeError;
@@ -58,10 +61,13 @@ pub use crate::values::iter::StarlarkIterator;
 pub use crate::values::layout::avalues::static_::AllocStaticSimple;
 pub use crate::values::layout::complex::ValueTypedComplex;
 pub use crate::values::layout::freezer::Freezer;
+pub use crate::values::layout::heap::arena::ArenaStats;
 pub use crate::values::layout::heap::heap_type::FrozenHeap;
 pub use crate::values::layout::heap::heap_type::FrozenHeapRef;
 pub use crate::values::layout::heap::heap_type::Heap;
 pub use crate::values::layout::heap::heap_type::Tracer;
+pub use crate::values::layout::heap::profile::analysis::HeapAnalysis;
+pub use crate::values::layout::heap::profile::analysis::RetainedGlobal;
 pub use crate::values::layout::heap::send::DynStarlark;
 pub use crate::values::layout::heap::send::HeapSendable;
 pub use crate::values::layout::identity::ValueIdentity;
@@ -85,7 +91,11 @@ pub use crate::values::traits::StarlarkValue;
 pub use crate::values::types::any;
 pub use crate::values::types::any_complex;
 pub use crate::values::types::array;
+#[cfg(feature = "arrow")]
+pub use crate::values::types::arrow;
 pub use crate::values::types::bool;
+pub use crate::values::types::bytes;
+pub use crate::values::types::decimal;
 pub use crate::values::types::dict;
 pub use crate::values::types::enumeration;
 pub use crate::values::types::float;
@@ -94,7 +104,11 @@ pub use crate::values::types::int;
 pub use crate::values::types::list;
 pub use crate::values::types::list_or_tuple;
 pub use crate::values::types::namespace;
+pub use crate::values::types::native_iterator;
 pub use crate::values::types::none;
+#[cfg(feature = "protobuf")]
+pub use crate::values::types::proto;
+pub use crate::values::types::provider;
 pub use crate::values::types::range;
 pub use crate::values::types::record;
 pub use crate::values::types::set;
@@ -114,6 +128,7 @@ pub use crate::values::value_of_unchecked::ValueOfUncheckedGeneric;
 mod alloc_value;
 mod comparison;
 pub(crate) mod demand;
+mod deserialize;
 pub(crate) mod error;
 mod freeze;
 mod freeze_error;
@@ -124,6 +139,7 @@ pub(crate) mod layout;
 mod owned;
 pub(crate) mod owned_frozen_ref;
 pub(crate) mod recursive_repr_or_json_guard;
+pub mod serde;
 mod stack_guard;
 pub(crate) mod starlark_type_id;
 pub(crate) mod thin_box_slice_frozen_value;
@@ -20,6 +20,8 @@ use std::hash::Hash;
 
 use itertools::Itertools;
 use starlark_map::Equivalent;
+use starlark_map::InlineStorage;
+use starlark_map::StarlarkHashValue;
 use starlark_map::small_set::SmallSet;
 
 use crate::collections::SmallMap;
@@ -40,14 +42,16 @@ pub(crate) fn equals_slice<E, X1, X2>(
     Ok(true)
 }
 
-pub(crate) fn equals_small_map<E, K1, K2, V1, V2>(
-    x: &SmallMap<K1, V1>,
-    y: &SmallMap<K2, V2>,
+pub(crate) fn equals_small_map<E, K1, K2, V1, V2, I1, I2>(
+    x: &SmallMap<K1, V1, I1>,
+    y: &SmallMap<K2, V2, I2>,
     f: impl Fn(&V1, &V2) -> Result<bool, E>,
 ) -> Result<bool, E>
 where
     K1: Eq + Equivalent<K2>,
     K2: Eq,
+    I1: InlineStorage<(K1, V1), StarlarkHashValue>,
+    I2: InlineStorage<(K2, V2), StarlarkHashValue>,
 {
     if x.len() != y.len() {
         return Ok(false);
@@ -0,0 +1,191 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Build Starlark values directly from any serde input.
+
+use std::fmt;
+use std::str::FromStr;
+
+use dupe::Dupe;
+use num_bigint::BigInt;
+use serde::Deserializer;
+use serde::de::DeserializeSeed;
+use serde::de::Error;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+
+use crate::collections::SmallMap;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::dict::Dict;
+use crate::values::list::AllocList;
+use crate::values::types::int::int_or_big::StarlarkInt;
+
+/// The key `serde_json` uses to pass numbers through `deserialize_any`
+/// when the `arbitrary_precision` feature is enabled.
+const SERDE_JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// A [`DeserializeSeed`] producing a [`Value`] allocated on a heap.
+///
+/// Maps become dicts, sequences and bytes become lists, and unit or `None` becomes `None`.
+/// Useful for deserializing Starlark values nested in a larger Rust structure,
+/// otherwise use [`Value::from_deserialize`].
+#[derive(Clone, Copy, Debug, Dupe)]
+pub struct ValueDeserializeSeed<'v>(pub Heap<'v>);
+
+impl<'v> Value<'v> {
+    /// Build a value from any serde deserializer (JSON, YAML, CBOR, ...),
+    /// without going through an intermediate representation.
+    ///
+    /// Maps become dicts, sequences and bytes become lists, and unit or `None` becomes `None`.
+    /// Fails if the input contains map keys which are not hashable.
+    pub fn from_deserialize<'de, D: Deserializer<'de>>(
+        heap: Heap<'v>,
+        deserializer: D,
+    ) -> Result<Value<'v>, D::Error> {
+        ValueDeserializeSeed(heap).deserialize(deserializer)
+    }
+}
+
+impl<'de, 'v> DeserializeSeed<'de> for ValueDeserializeSeed<'v> {
+    type Value = Value<'v>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value<'v>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+fn alloc_number<'v, E: Error>(heap: Heap<'v>, x: &str) -> Result<Value<'v>, E> {
+    if let Ok(x) = i64::from_str(x) {
+        Ok(heap.alloc(x))
+    } else if let Ok(x) = BigInt::from_str(x) {
+        Ok(heap.alloc(StarlarkInt::from(x)))
+    } else if let Ok(x) = f64::from_str(x) {
+        Ok(heap.alloc(x))
+    } else {
+        Err(E::custom(format_args!("Unrepresentable number: {x}")))
+    }
+}
+
+impl<'de, 'v> Visitor<'de> for ValueDeserializeSeed<'v> {
+    type Value = Value<'v>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value representable in Starlark")
+    }
+
+    fn visit_bool<E: Error>(self, x: bool) -> Result<Value<'v>, E> {
+        Ok(Value::new_bool(x))
+    }
+
+    fn visit_i64<E: Error>(self, x: i64) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(x))
+    }
+
+    fn visit_u64<E: Error>(self, x: u64) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(x))
+    }
+
+    fn visit_i128<E: Error>(self, x: i128) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(StarlarkInt::from(BigInt::from(x))))
+    }
+
+    fn visit_u128<E: Error>(self, x: u128) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(StarlarkInt::from(BigInt::from(x))))
+    }
+
+    fn visit_f64<E: Error>(self, x: f64) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(x))
+    }
+
+    fn visit_str<E: Error>(self, x: &str) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(x))
+    }
+
+    fn visit_bytes<E: Error>(self, x: &[u8]) -> Result<Value<'v>, E> {
+        Ok(self.0.alloc(AllocList(x.iter().map(|b| *b as i32))))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Value<'v>, E> {
+        Ok(Value::new_none())
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Value<'v>, E> {
+        Ok(Value::new_none())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value<'v>, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value<'v>, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value<'v>, A::Error> {
+        let mut xs = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(x) = seq.next_element_seed(self)? {
+            xs.push(x);
+        }
+        Ok(self.0.alloc(AllocList(xs)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value<'v>, A::Error> {
+        let mut content = SmallMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(k) = map.next_key_seed(self)? {
+            if content.is_empty() && k.unpack_str() == Some(SERDE_JSON_NUMBER_TOKEN) {
+                let x: String = map.next_value()?;
+                return alloc_number(self.0, &x);
+            }
+            let k = k.get_hashed().map_err(A::Error::custom)?;
+            let v = map.next_value_seed(self)?;
+            content.insert_hashed(k, v);
+        }
+        Ok(self.0.alloc(Dict::new(content)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    fn from_json(x: &str) -> String {
+        Heap::temp(|heap| {
+            let mut de = serde_json::Deserializer::from_str(x);
+            Value::from_deserialize(heap, &mut de).unwrap().to_repr()
+        })
+    }
+
+    #[test]
+    fn test_from_deserialize() {
+        assert_eq!(
+            r#"[1, None, True, {"k": "v"}]"#,
+            from_json(r#"[1, null, true, {"k": "v"}]"#)
+        );
+        assert_eq!("3.5", from_json("3.5"));
+        assert_eq!(
+            "123456789123456789123456789",
+            from_json("123456789123456789123456789")
+        );
+        assert_eq!(r#"{"a": [], "b": {}}"#, from_json(r#"{"a": [], "b": {}}"#));
+    }
+}
@@ -19,6 +19,7 @@
 
 use thiserror::Error;
 
+use crate::ErrorCode;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 
@@ -64,7 +65,44 @@ pub enum ValueError {
 
 impl From<ValueError> for crate::Error {
     fn from(e: ValueError) -> Self {
+        let (code, args) = match &e {
+            ValueError::OperationNotSupported { op, typ } => {
+                (401, vec![("op", op.clone()), ("type", typ.clone())])
+            }
+            ValueError::OperationNotSupportedBinary { op, left, right } => (
+                402,
+                vec![
+                    ("op", op.clone()),
+                    ("left", left.clone()),
+                    ("right", right.clone()),
+                ],
+            ),
+            ValueError::DivisionByZero => (403, Vec::new()),
+            ValueError::IntegerOverflow => (404, Vec::new()),
+            ValueError::NegativeShiftCount => (405, Vec::new()),
+            ValueError::IncorrectParameterType => (406, Vec::new()),
+            ValueError::IncorrectParameterTypeNamed(name) => (407, vec![("name", name.clone())]),
+            ValueError::MissingThis => (408, Vec::new()),
+            ValueError::MissingRequired(name) => (409, vec![("name", name.clone())]),
+            ValueError::IndexOutOfBound(index) => (410, vec![("index", index.to_string())]),
+            ValueError::KeyNotFound(key) => (411, vec![("key", key.clone())]),
+            ValueError::CannotMutateImmutableValue => (412, Vec::new()),
+            ValueError::MutationDuringIteration => (413, Vec::new()),
+            ValueError::NoAttr(typ, attr) => {
+                (414, vec![("type", typ.clone()), ("attr", attr.clone())])
+            }
+            ValueError::NoAttrDidYouMean(typ, attr, suggestion) => (
+                414,
+                vec![
+                    ("type", typ.clone()),
+                    ("attr", attr.clone()),
+                    ("suggestion", suggestion.clone()),
+                ],
+            ),
+        };
         crate::Error::new_kind(crate::ErrorKind::Value(anyhow::Error::new(e)))
+            .with_code(ErrorCode::new(code))
+            .with_message_args(args)
     }
 }
 
@@ -76,6 +114,16 @@ pub(crate) enum ControlError {
     TooManyRecursionLevel,
 }
 
+impl From<ControlError> for crate::Error {
+    fn from(e: ControlError) -> Self {
+        let code = ErrorCode::new(match e {
+            ControlError::NotHashableValue(_) => 416,
+            ControlError::TooManyRecursionLevel => ErrorCode::STACK_OVERFLOW.number(),
+        });
+        crate::Error::new_other(e).with_code(code)
+    }
+}
+
 impl ValueError {
     #[cold]
     pub(crate) fn unsupported_owned<T>(
@@ -22,6 +22,9 @@ use std::marker;
 use std::marker::PhantomData;
 
 use starlark_map::Hashed;
+use starlark_map::InlineStorage;
+use starlark_map::StarlarkHashValue;
+use starlark_map::small_map::InlineEntries;
 use starlark_map::small_map::SmallMap;
 use starlark_map::small_set::SmallSet;
 use starlark_syntax::slice_vec_ext::VecExt;
@@ -228,17 +231,42 @@ where
     type Frozen = SmallMap<K::Frozen, V::Frozen>;
 
     fn freeze(self, freezer: &Freezer) -> FreezeResult<SmallMap<K::Frozen, V::Frozen>> {
-        let mut new = SmallMap::with_capacity(self.len());
-        for (key, value) in self.into_iter_hashed() {
-            let hash = key.hash();
-            let key = key.into_key().freeze(freezer)?;
-            // TODO(nga): verify hash unchanged after freeze.
-            let key = Hashed::new_unchecked(hash, key);
-            let value = value.freeze(freezer)?;
-            new.insert_hashed_unique_unchecked(key, value);
-        }
-        Ok(new)
+        freeze_small_map(self, freezer)
+    }
+}
+
+impl<K, V, const N: usize> Freeze for SmallMap<K, V, InlineEntries<K, V, N>>
+where
+    K: Freeze,
+    V: Freeze,
+{
+    type Frozen = SmallMap<K::Frozen, V::Frozen, InlineEntries<K::Frozen, V::Frozen, N>>;
+
+    fn freeze(self, freezer: &Freezer) -> FreezeResult<Self::Frozen> {
+        freeze_small_map(self, freezer)
+    }
+}
+
+fn freeze_small_map<K, V, I, J>(
+    map: SmallMap<K, V, I>,
+    freezer: &Freezer,
+) -> FreezeResult<SmallMap<K::Frozen, V::Frozen, J>>
+where
+    K: Freeze,
+    V: Freeze,
+    I: InlineStorage<(K, V), StarlarkHashValue>,
+    J: InlineStorage<(K::Frozen, V::Frozen), StarlarkHashValue>,
+{
+    let mut new = SmallMap::with_capacity_inline(map.len());
+    for (key, value) in map.into_iter_hashed() {
+        let hash = key.hash();
+        let key = key.into_key().freeze(freezer)?;
+        // TODO(nga): verify hash unchanged after freeze.
+        let key = Hashed::new_unchecked(hash, key);
+        let value = value.freeze(freezer)?;
+        new.insert_hashed_unique_unchecked(key, value);
     }
+    Ok(new)
 }
 
 impl<T> Freeze for SmallSet<T>
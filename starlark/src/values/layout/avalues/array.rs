@@ -17,6 +17,8 @@
 
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::mem;
+use std::mem::MaybeUninit;
 use std::slice;
 
 use crate::cast;
@@ -42,9 +44,7 @@ use crate::values::layout::heap::repr::ForwardPtr;
 use crate::values::types::any_array::AnyArray;
 use crate::values::types::array::Array;
 
-fn array_avalue<'v>(
-    cap: u32,
-) -> AValueImpl<'v, impl AValue<'v, StarlarkValue = Array<'v>, ExtraElem = Value<'v>>> {
+fn array_avalue<'v>(cap: u32) -> AValueImpl<'v, AValueArray> {
     AValueImpl::<AValueArray>::new(unsafe { Array::new(0, cap) })
 }
 
@@ -113,6 +113,38 @@ impl<'v> AValue<'v> for AValueArray {
     }
 }
 
+/// Array of capacity `N` placed in the extra of another value,
+/// so that value and its array are a single allocation.
+///
+/// The array is a regular heap object, except it is not visited when iterating the heap,
+/// and it is moved to a separate allocation by GC.
+#[repr(C)]
+pub(crate) struct InlineArray<'v, const N: usize> {
+    repr: AValueRepr<AValueImpl<'v, AValueArray>>,
+    content: [MaybeUninit<Value<'v>>; N],
+}
+
+impl<'v, const N: usize> InlineArray<'v, N> {
+    /// Write an empty array to `place`.
+    pub(crate) unsafe fn init(place: *mut MaybeUninit<Self>) -> ValueTyped<'v, Array<'v>> {
+        const {
+            assert!(N != 0, "empty array is allocated statically");
+        }
+        debug_assert_eq!(
+            mem::offset_of!(Self, content),
+            AValueRepr::<AValueArray>::offset_of_extra()
+        );
+        unsafe {
+            let repr = &raw mut (*(place as *mut Self)).repr;
+            repr.write(AValueRepr {
+                header: AValueHeader::new::<AValueArray>(),
+                payload: array_avalue(N as u32),
+            });
+            ValueTyped::new_repr(&*repr)
+        }
+    }
+}
+
 pub(crate) struct AValueAnyArray<T>(PhantomData<T>);
 
 impl<'v, T: Debug + 'static> AValue<'v> for AValueAnyArray<T> {
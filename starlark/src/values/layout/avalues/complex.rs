@@ -73,6 +73,9 @@ where
         freezer: &Freezer,
     ) -> FreezeResult<FrozenValue> {
         unsafe {
+            if let Err(e) = (*me).payload.on_freeze() {
+                return Err(FreezeError::new(e.to_string()));
+            }
             if let Some(f) = try_freeze_directly::<Self>(me, freezer) {
                 return f;
             }
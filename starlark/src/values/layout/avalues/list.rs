@@ -29,10 +29,13 @@ use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
 use crate::values::ValueTyped;
+use crate::values::array::VALUE_EMPTY_ARRAY;
 use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::AValueImpl;
 use crate::values::layout::avalue::heap_copy_impl;
+use crate::values::layout::avalues::array::InlineArray;
 use crate::values::layout::heap::maybe_uninit_slice_util::maybe_uninit_write_from_exact_size_iter;
+use crate::values::layout::heap::repr::AValueForward;
 use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::heap::repr::ForwardPtr;
@@ -108,6 +111,43 @@ impl<'v> AValue<'v> for AValueList {
     }
 }
 
+/// List with the array for `N` elements in its extra.
+///
+/// When the list grows beyond that, it switches to a separately allocated array
+/// like [`AValueList`], and GC copies it as [`AValueList`].
+struct AValueInlineList<const N: usize>;
+
+impl<'v, const N: usize> AValue<'v> for AValueInlineList<N> {
+    type StarlarkValue = ListGen<ListData<'v>>;
+
+    type ExtraElem = InlineArray<'v, N>;
+
+    fn extra_len(_value: &ListGen<ListData<'v>>) -> usize {
+        1
+    }
+
+    fn offset_of_extra() -> usize {
+        mem::size_of::<Self::StarlarkValue>()
+    }
+
+    unsafe fn heap_freeze(
+        me: *mut AValueRepr<Self::StarlarkValue>,
+        freezer: &Freezer,
+    ) -> FreezeResult<FrozenValue> {
+        AValueForward::assert_does_not_overwrite_extra::<Self>();
+        unsafe { AValueList::heap_freeze(me, freezer) }
+    }
+
+    unsafe fn heap_copy(
+        me: *mut AValueRepr<Self::StarlarkValue>,
+        tracer: &Tracer<'v>,
+    ) -> Value<'v> {
+        AValueForward::assert_does_not_overwrite_extra::<Self>();
+        // The inline array is copied to a separate allocation when the content is traced.
+        unsafe { heap_copy_impl::<AValueList>(me, tracer, Trace::trace) }
+    }
+}
+
 pub(crate) struct AValueFrozenList;
 
 impl<'v> AValue<'v> for AValueFrozenList {
@@ -181,11 +221,39 @@ impl FrozenHeap {
 }
 
 impl<'v> Heap<'v> {
+    /// Allocate an empty list with the given capacity.
+    /// Arrays of up to four elements are placed in the same allocation as the list.
+    fn alloc_list_with_capacity(self, cap: usize) -> ValueTyped<'v, ListGen<ListData<'v>>> {
+        match cap {
+            1 => self.alloc_inline_list::<1>(),
+            2 => self.alloc_inline_list::<2>(),
+            3 => self.alloc_inline_list::<3>(),
+            4 => self.alloc_inline_list::<4>(),
+            _ => {
+                let array = self.alloc_array(cap);
+                self.alloc_raw(list_avalue(array))
+            }
+        }
+    }
+
+    /// Allocate an empty list with an array of capacity `N` in the same allocation.
+    fn alloc_inline_list<const N: usize>(self) -> ValueTyped<'v, ListGen<ListData<'v>>> {
+        let empty = VALUE_EMPTY_ARRAY.unpack().to_value_typed();
+        let (list, extra) = self.alloc_raw_extra(AValueImpl::<AValueInlineList<N>>::new(ListGen(
+            ListData::new(empty),
+        )));
+        unsafe {
+            let array = InlineArray::init((*extra).as_mut_ptr());
+            list.0.content.set(array);
+        }
+        list
+    }
+
     /// Allocate a list with the given elements.
     pub(crate) fn alloc_list(self, elems: &[Value<'v>]) -> Value<'v> {
-        let array = self.alloc_array(elems.len());
-        array.extend_from_slice(elems);
-        self.alloc_raw(list_avalue(array)).to_value()
+        let list = self.alloc_list_with_capacity(elems.len());
+        list.0.content.get().extend_from_slice(elems);
+        list.to_value()
     }
 
     /// Allocate a list with the given elements.
@@ -201,18 +269,21 @@ impl<'v> Heap<'v> {
         elems: impl IntoIterator<Item = Result<Value<'v>, E>>,
     ) -> Result<Value<'v>, E> {
         let elems = elems.into_iter();
-        let array = self.alloc_array(0);
-        let list = self.alloc_raw(list_avalue(array));
+        let list = match elems.size_hint() {
+            (lower, Some(upper)) if lower == upper => self.alloc_list_with_capacity(lower),
+            _ => self.alloc_list_with_capacity(0),
+        };
         list.0.try_extend(elems, self)?;
         Ok(list.to_value())
     }
 
     /// Allocate a list by concatenating two slices.
     pub(crate) fn alloc_list_concat(self, a: &[Value<'v>], b: &[Value<'v>]) -> Value<'v> {
-        let array = self.alloc_array(a.len() + b.len());
+        let list = self.alloc_list_with_capacity(a.len() + b.len());
+        let array = list.0.content.get();
         array.extend_from_slice(a);
         array.extend_from_slice(b);
-        self.alloc_raw(list_avalue(array)).to_value()
+        list.to_value()
     }
 }
 
@@ -20,6 +20,7 @@ use std::mem;
 
 use crate::cast;
 use crate::private::Private;
+use crate::values::FreezeError;
 use crate::values::FreezeResult;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
@@ -68,6 +69,9 @@ impl<'v, T: StarlarkValue<'v> + HeapSendable<'v> + HeapSyncable<'v>> AValue<'v>
         freezer: &Freezer,
     ) -> FreezeResult<FrozenValue> {
         unsafe {
+            if let Err(e) = (*me).payload.on_freeze() {
+                return Err(FreezeError::new(e.to_string()));
+            }
             if let Some(f) = try_freeze_directly::<Self>(me, freezer) {
                 return f;
             }
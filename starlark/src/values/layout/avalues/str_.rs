@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::mem;
 use std::ptr::copy_nonoverlapping;
 
 use starlark_map::Hashed;
@@ -50,6 +51,25 @@ pub(crate) fn starlark_str<'v>(
     AValueImpl::<StarlarkStrAValue>::new(unsafe { StarlarkStr::new(len, hash) })
 }
 
+/// Substrings shorter than this are copied: a substring takes as much memory as
+/// a string of this length, and keeps its parent alive.
+pub(crate) const SUBSTR_MIN_LEN: usize = 2 * mem::size_of::<usize>();
+
+/// Substrings shorter than `1 / SUBSTR_MAX_WASTE` of the string owning their
+/// storage are copied, so a short slice of a large string does not keep it alive.
+const SUBSTR_MAX_WASTE: usize = 4;
+
+/// Longest result of `Heap::alloc_str_concat` which may be interned,
+/// with [`Heap::set_intern_strings`].
+const INTERN_CONCAT_MAX_LEN: usize = 64;
+
+#[inline]
+pub(crate) fn starlark_substr<'v>(
+    len: usize,
+) -> AValueImpl<'v, impl AValue<'v, ExtraElem = usize> + Send + Sync> {
+    AValueImpl::<StarlarkSubstrAValue>::new(unsafe { StarlarkStr::new_substr(len) })
+}
+
 pub(crate) struct StarlarkStrAValue;
 
 impl<'v> AValue<'v> for StarlarkStrAValue {
@@ -110,6 +130,64 @@ impl<'v> AValue<'v> for StarlarkStrAValue {
     }
 }
 
+/// A string referencing the storage of another string, see `StarlarkStr::substr_parent`.
+pub(crate) struct StarlarkSubstrAValue;
+
+impl<'v> AValue<'v> for StarlarkSubstrAValue {
+    type StarlarkValue = StarlarkStr;
+
+    type ExtraElem = usize;
+
+    fn extra_len(_value: &StarlarkStr) -> usize {
+        // Parent and offset.
+        2
+    }
+
+    fn offset_of_extra() -> usize {
+        StarlarkStr::offset_of_content()
+    }
+
+    const IS_STR: bool = true;
+
+    unsafe fn heap_freeze(
+        me: *mut AValueRepr<Self::StarlarkValue>,
+        freezer: &Freezer,
+    ) -> FreezeResult<FrozenValue> {
+        unsafe {
+            // Frozen strings are interned, so copy the content
+            // rather than keeping the whole parent alive.
+            let s = (*me).payload.as_str();
+            let fv = freezer.alloc(s);
+            debug_assert!(fv.is_str());
+            AValueHeader::overwrite_with_forward::<Self::StarlarkValue>(
+                me,
+                ForwardPtr::new_frozen(fv),
+            );
+            Ok(fv)
+        }
+    }
+
+    unsafe fn heap_copy(
+        me: *mut AValueRepr<Self::StarlarkValue>,
+        tracer: &Tracer<'v>,
+    ) -> Value<'v> {
+        unsafe {
+            let len = (*me).payload.len();
+            let (parent, offset) = (*me).payload.substr_parent().unwrap_unchecked();
+            // The parent is a plain string, so there are no cycles to worry about,
+            // and it can be copied before the substring.
+            let mut parent = parent.to_value();
+            tracer.trace(&mut parent);
+            let v = tracer.alloc_substr(parent, offset, len);
+            AValueHeader::overwrite_with_forward::<Self::StarlarkValue>(
+                me,
+                ForwardPtr::new_unfrozen(v),
+            );
+            v
+        }
+    }
+}
+
 impl FrozenHeap {
     /// Allocate a string on this heap. Be careful about the warnings around
     /// [`FrozenValue`].
@@ -144,6 +222,8 @@ impl<'v> Heap<'v> {
     pub fn alloc_str(self, x: &str) -> StringValue<'v> {
         if let Some(x) = constant_string(x) {
             x.to_string_value()
+        } else if self.intern_str_len(x.len()) {
+            self.alloc_str_intern(x)
         } else {
             self.alloc_str_init(x.len(), StarlarkStr::UNINIT_HASH, |dest| unsafe {
                 copy_nonoverlapping(x.as_ptr(), dest, x.len())
@@ -171,6 +251,16 @@ impl<'v> Heap<'v> {
             self.alloc_str(y)
         } else if y.is_empty() {
             self.alloc_str(x)
+        } else if x.len() + y.len() <= INTERN_CONCAT_MAX_LEN
+            && self.intern_str_len(x.len() + y.len())
+        {
+            // Concatenate on the stack, to look the result up in the interner.
+            let mut buf = [0; INTERN_CONCAT_MAX_LEN];
+            buf[..x.len()].copy_from_slice(x.as_bytes());
+            buf[x.len()..x.len() + y.len()].copy_from_slice(y.as_bytes());
+            // Concatenation of two strings is valid UTF-8.
+            let s = unsafe { std::str::from_utf8_unchecked(&buf[..x.len() + y.len()]) };
+            self.alloc_str_intern(s)
         } else {
             self.alloc_str_init(x.len() + y.len(), StarlarkStr::UNINIT_HASH, |dest| unsafe {
                 copy_nonoverlapping(x.as_ptr(), dest, x.len());
@@ -202,6 +292,27 @@ impl<'v> Heap<'v> {
         }
     }
 
+    /// Allocate a substring of `parent`, given as a subslice of it.
+    ///
+    /// Long substrings reference the storage of `parent` instead of copying it,
+    /// unless they are a small part of it.
+    pub(crate) fn alloc_substr(self, parent: StringValue<'v>, s: &str) -> StringValue<'v> {
+        if s.len() == parent.len() {
+            return parent;
+        }
+        if s.len() < SUBSTR_MIN_LEN {
+            return self.alloc_str(s);
+        }
+        // Reference the string owning the storage, so substrings do not chain.
+        let (root, root_offset) = parent.as_ref().substr_parent().unwrap_or((parent, 0));
+        if s.len() * SUBSTR_MAX_WASTE < root.len() {
+            return self.alloc_str(s);
+        }
+        let offset = root_offset + (s.as_ptr() as usize - parent.as_str().as_ptr() as usize);
+        debug_assert_eq!(Some(s), root.as_str().get(offset..offset + s.len()));
+        self.alloc_substr_raw(root, offset, s.len())
+    }
+
     pub(crate) fn alloc_char(self, x: char) -> StringValue<'v> {
         let mut dst = [0; 4];
         let res = x.encode_utf8(&mut dst);
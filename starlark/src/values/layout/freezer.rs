@@ -25,17 +25,26 @@ use crate::values::FrozenRef;
 use crate::values::HeapSendable;
 use crate::values::layout::avalue::AValue;
 use crate::values::layout::heap::arena::Reservation;
+use crate::values::layout::heap::profile::analysis::HeapReferencesBuilder;
 use crate::values::layout::heap::repr::AValueOrForwardUnpack;
 use crate::values::layout::heap::send::HeapSyncable;
 use crate::values::layout::value::FrozenValue;
 use crate::values::layout::value::Value;
 
+#[cfg(feature = "parallel_freeze")]
+pub(crate) mod parallel;
+
 /// Used to `freeze` values by [`Freeze::freeze`](crate::values::Freeze::freeze).
 pub struct Freezer<'fv> {
     /// Freezing into this heap.
     pub(crate) heap: &'fv FrozenHeap,
     /// Defs frozen by this freezer.
     pub(crate) frozen_defs: RefCell<Vec<FrozenRef<'static, FrozenDef>>>,
+    /// Other threads are freezing the same heap, see `parallel`.
+    #[cfg(feature = "parallel_freeze")]
+    parallel: bool,
+    /// When `Some`, record the references between values, see `Module::enable_heap_analysis`.
+    pub(crate) references: Option<RefCell<HeapReferencesBuilder>>,
 }
 
 impl<'fv> Freezer<'fv> {
@@ -43,6 +52,17 @@ impl<'fv> Freezer<'fv> {
         Freezer {
             heap,
             frozen_defs: RefCell::new(Vec::new()),
+            #[cfg(feature = "parallel_freeze")]
+            parallel: false,
+            references: None,
+        }
+    }
+
+    #[cfg(feature = "parallel_freeze")]
+    pub(crate) fn new_parallel(heap: &'fv FrozenHeap) -> Self {
+        Freezer {
+            parallel: true,
+            ..Freezer::new(heap)
         }
     }
 
@@ -65,6 +85,19 @@ impl<'fv> Freezer<'fv> {
 
     /// Freeze a nested value while freezing yourself.
     pub fn freeze(&self, value: Value) -> FreezeResult<FrozenValue> {
+        let Some(references) = &self.references else {
+            return self.freeze_impl(value);
+        };
+        references.borrow_mut().enter();
+        let res = self.freeze_impl(value);
+        match &res {
+            Ok(frozen) => references.borrow_mut().exit(*frozen),
+            Err(_) => references.borrow_mut().exit_with_error(),
+        }
+        res
+    }
+
+    fn freeze_impl(&self, value: Value) -> FreezeResult<FrozenValue> {
         // Case 1: We have our value encoded in our pointer
         if let Some(x) = value.unpack_frozen() {
             return Ok(x);
@@ -72,6 +105,10 @@ impl<'fv> Freezer<'fv> {
 
         // Case 2: We have already been replaced with a forwarding, or need to freeze
         let value = value.0.unpack_ptr().unwrap();
+        #[cfg(feature = "parallel_freeze")]
+        if self.parallel {
+            return unsafe { parallel::freeze_shared(self, value) };
+        }
         match value.unpack() {
             AValueOrForwardUnpack::Forward(x) => {
                 Ok(unsafe { x.forward_ptr().unpack_frozen_value() })
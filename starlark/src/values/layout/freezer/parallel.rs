@@ -0,0 +1,354 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Freezing the slots of a module on several threads.
+//!
+//! Each worker freezes into its own [`FrozenHeap`], taking slots from a shared counter.
+//! Values reachable from slots handled by different workers are shared, so a worker
+//! must claim a value before freezing it: it swaps the header word from the vtable to
+//! [`CLAIMED_HEADER`] with a compare-and-swap. The winner keeps the vtable on a thread
+//! local stack, so the usual `heap_freeze` implementation runs unchanged, and
+//! [`AValueHeader::overwrite_with_forward`] stores the forward atomically. Every
+//! `heap_freeze` installs the forward before freezing any children, so a worker finding
+//! a claimed value only waits for a few instructions, and cycles can't deadlock.
+//! If freezing fails or panics before the forward is written, the vtable is put back,
+//! and a waiting worker claims the value itself.
+//!
+//! This is behind the `parallel_freeze` feature, tested with Miri in CI.
+//!
+//! Only the worker owning a claim reads the value, the others only read the header word,
+//! so values are never accessed from two threads at once, which is why the usual
+//! `Send` requirement on heap values is enough.
+
+use std::cell::RefCell;
+use std::hint;
+use std::mem;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use crate::eval::compiler::def::FrozenDef;
+use crate::values::FreezeResult;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenRef;
+use crate::values::FrozenValue;
+use crate::values::Value;
+use crate::values::layout::heap::repr::AValueHeader;
+use crate::values::layout::heap::repr::AValueOrForward;
+use crate::values::layout::heap::repr::CLAIMED_HEADER;
+use crate::values::layout::heap::repr::ForwardPtr;
+use crate::values::layout::vtable::AValueDyn;
+use crate::values::layout::vtable::AValueVTable;
+use crate::values::layout::vtable::StarlarkValueRawPtr;
+
+thread_local! {
+    /// Values claimed by this thread which haven't been overwritten with a forward yet,
+    /// with their vtables. Innermost last.
+    static CLAIMS: RefCell<Vec<(usize, &'static AValueVTable)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The vtable of a value claimed by the current thread.
+pub(crate) fn claimed_vtable(header: *const AValueHeader) -> &'static AValueVTable {
+    CLAIMS.with_borrow(|claims| {
+        claims
+            .iter()
+            .rev()
+            .find(|(p, _)| *p == header as usize)
+            .expect("value claimed by another thread")
+            .1
+    })
+}
+
+/// A value claimed by the current thread.
+///
+/// Dropping it gives the value back if the forward wasn't written, because freezing
+/// failed or panicked, so no other worker waits for it forever.
+struct Claim<'a> {
+    word: &'a AtomicUsize,
+    /// The header word before claiming, which is the vtable.
+    header: usize,
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        CLAIMS.with_borrow_mut(|claims| {
+            let claim = claims.pop();
+            debug_assert_eq!(Some(self.word.as_ptr() as usize), claim.map(|x| x.0));
+        });
+        if self.word.load(Ordering::Relaxed) == CLAIMED_HEADER {
+            self.word.store(self.header, Ordering::Release);
+        }
+    }
+}
+
+/// Freeze a pointer to an unfrozen value, claiming it first.
+pub(super) unsafe fn freeze_shared(
+    freezer: &Freezer,
+    value: &AValueOrForward,
+) -> FreezeResult<FrozenValue> {
+    unsafe {
+        let addr = value as *const AValueOrForward as usize;
+        let word = AtomicUsize::from_ptr(addr as *mut usize);
+        loop {
+            let header = word.load(Ordering::Acquire);
+            if header & 1 != 0 {
+                return Ok(ForwardPtr::from_forward_word(header).unpack_frozen_value());
+            }
+            if header == CLAIMED_HEADER {
+                // Another worker is between claiming the value and writing the forward.
+                hint::spin_loop();
+                continue;
+            }
+            if word
+                .compare_exchange_weak(header, CLAIMED_HEADER, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+
+            let vtable = &*(header as *const AValueVTable);
+            CLAIMS.with_borrow_mut(|claims| claims.push((addr, vtable)));
+            let _claim = Claim { word, header };
+            let payload = StarlarkValueRawPtr::new_header(&*(addr as *const AValueHeader));
+            return AValueDyn::new(payload, vtable).heap_freeze(freezer);
+        }
+    }
+}
+
+/// What a worker produced, which is all frozen, so safe to send to the calling thread.
+struct WorkerOutput {
+    frozen: Vec<(usize, Option<FrozenValue>)>,
+    frozen_defs: Vec<FrozenRef<'static, FrozenDef>>,
+    heap: FrozenHeapRef,
+}
+
+// SAFETY: frozen values and frozen heaps may be shared between threads.
+unsafe impl Send for WorkerOutput {}
+
+/// Freeze `values` on `threads` threads.
+///
+/// The values are frozen into new heaps, which `freezer`'s heap keeps alive,
+/// and defs are recorded into `freezer` for the post-freeze pass.
+pub(crate) fn freeze_parallel<'v>(
+    freezer: &Freezer,
+    values: &[Option<Value<'v>>],
+    threads: usize,
+) -> FreezeResult<Vec<Option<FrozenValue>>> {
+    // Safe because of the claiming protocol described at the top of the module.
+    let shared: &[Option<Value<'static>>] = unsafe { mem::transmute(values) };
+    let next = AtomicUsize::new(0);
+    let outputs: Vec<FreezeResult<WorkerOutput>> = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| -> FreezeResult<WorkerOutput> {
+                    let heap = FrozenHeap::new();
+                    let worker = Freezer::new_parallel(&heap);
+                    let mut frozen = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(value) = shared.get(i) else {
+                            break;
+                        };
+                        let value = match value {
+                            Some(value) => Some(worker.freeze(*value)?),
+                            None => None,
+                        };
+                        frozen.push((i, value));
+                    }
+                    let frozen_defs = worker.frozen_defs.into_inner();
+                    Ok(WorkerOutput {
+                        frozen,
+                        frozen_defs,
+                        heap: heap.into_ref(),
+                    })
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+
+    let mut res = vec![None; values.len()];
+    for output in outputs {
+        let output = output?;
+        for (i, value) in output.frozen {
+            res[i] = value;
+        }
+        freezer.heap.add_reference(&output.heap);
+        freezer.frozen_defs.borrow_mut().extend(output.frozen_defs);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::panic::AssertUnwindSafe;
+
+    use allocative::Allocative;
+    use derive_more::Display;
+    use starlark_derive::NoSerialize;
+    use starlark_derive::starlark_value;
+
+    use crate as starlark;
+    use crate::any::ProvidesStaticType;
+    use crate::environment::FrozenModule;
+    use crate::environment::Module;
+    use crate::starlark_simple_value;
+    use crate::values::FreezeResult;
+    use crate::values::StarlarkValue;
+    use crate::values::Value;
+    use crate::values::list::AllocList;
+    use crate::values::list::ListRef;
+    use crate::values::types::list::value::ListData;
+
+    /// Workers race differently on each run, so the racy tests freeze several times.
+    const RUNS: usize = if cfg!(miri) { 2 } else { 50 };
+    const THREADS: usize = 8;
+    const SLOTS: usize = if cfg!(miri) { 16 } else { 64 };
+
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display("unfreezable")]
+    struct Unfreezable(bool);
+
+    starlark_simple_value!(Unfreezable);
+
+    #[starlark_value(type = "unfreezable")]
+    impl<'v> StarlarkValue<'v> for Unfreezable {
+        fn on_freeze(&self) -> crate::Result<()> {
+            if self.0 {
+                panic!("panicking on freeze");
+            }
+            Err(crate::Error::new_other(anyhow::anyhow!("unfreezable")))
+        }
+    }
+
+    fn freeze_with_slots(
+        slots: impl for<'v> Fn(&Module<'v>) -> Vec<(String, Value<'v>)>,
+    ) -> FreezeResult<FrozenModule> {
+        Module::with_temp_heap(|module| {
+            module.set_freeze_threads(THREADS);
+            for (name, value) in slots(&module) {
+                module.set(&name, value);
+            }
+            module.freeze()
+        })
+    }
+
+    /// Every slot references a chain of lists, which the workers race to freeze.
+    #[test]
+    fn test_freeze_parallel_contended() {
+        for _ in 0..RUNS {
+            let module = freeze_with_slots(|module| {
+                let heap = module.heap();
+                let mut shared = heap.alloc(AllocList::EMPTY);
+                for i in 0..10 {
+                    shared = heap.alloc(AllocList([shared, heap.alloc(format!("s{i}"))]));
+                }
+                let mut slots = vec![("shared".to_owned(), shared)];
+                for i in 0..SLOTS {
+                    let x = heap.alloc(AllocList([shared, heap.alloc(format!("x{i}"))]));
+                    slots.push((format!("x{i}"), x));
+                }
+                slots
+            })
+            .unwrap();
+
+            let shared = module.get("shared").unwrap();
+            for i in 0..SLOTS {
+                let x = module.get(&format!("x{i}")).unwrap();
+                let x = ListRef::from_value(x.value()).unwrap();
+                assert!(x[0].ptr_eq(shared.value()));
+                assert_eq!(Some(format!("x{i}").as_str()), x[1].unpack_str());
+            }
+            let mut list = ListRef::from_value(shared.value()).unwrap();
+            for i in (0..10).rev() {
+                assert_eq!(Some(format!("s{i}").as_str()), list[1].unpack_str());
+                list = ListRef::from_value(list[0]).unwrap();
+            }
+            assert!(list.is_empty());
+        }
+    }
+
+    /// Cycles spanning slots, which are likely frozen by different workers.
+    #[test]
+    fn test_freeze_parallel_cycles() {
+        for _ in 0..RUNS {
+            let module = freeze_with_slots(|module| {
+                let heap = module.heap();
+                let mut slots = Vec::new();
+                for i in 0..SLOTS / 2 {
+                    let a = heap.alloc(AllocList::EMPTY);
+                    let b = heap.alloc(AllocList([a]));
+                    ListData::from_value_mut(a).unwrap().push(b, heap);
+                    ListData::from_value_mut(a).unwrap().push(a, heap);
+                    slots.push((format!("a{i}"), a));
+                    slots.push((format!("b{i}"), b));
+                }
+                slots
+            })
+            .unwrap();
+
+            for i in 0..SLOTS / 2 {
+                let a = module.get(&format!("a{i}")).unwrap();
+                let b = module.get(&format!("b{i}")).unwrap();
+                let a_list = ListRef::from_value(a.value()).unwrap();
+                assert!(a_list[0].ptr_eq(b.value()));
+                assert!(a_list[1].ptr_eq(a.value()));
+                let b_list = ListRef::from_value(b.value()).unwrap();
+                assert!(b_list[0].ptr_eq(a.value()));
+            }
+        }
+    }
+
+    /// A shared value failing to freeze is given back by each worker claiming it,
+    /// so every worker fails rather than waiting for it.
+    #[test]
+    fn test_freeze_parallel_error() {
+        for _ in 0..RUNS {
+            let err = freeze_with_slots(|module| {
+                let heap = module.heap();
+                let shared = heap.alloc(AllocList([heap.alloc(Unfreezable(false))]));
+                (0..SLOTS)
+                    .map(|i| (format!("x{i}"), heap.alloc(AllocList([shared]))))
+                    .collect()
+            })
+            .unwrap_err();
+            assert!(err.err_msg.contains("unfreezable"), "{err:?}");
+        }
+    }
+
+    /// Same for a panic, which is resumed on the calling thread.
+    #[test]
+    fn test_freeze_parallel_panic() {
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            freeze_with_slots(|module| {
+                let heap = module.heap();
+                let shared = heap.alloc(AllocList([heap.alloc(Unfreezable(true))]));
+                (0..SLOTS)
+                    .map(|i| (format!("x{i}"), heap.alloc(AllocList([shared]))))
+                    .collect()
+            })
+        }));
+        let err = res.unwrap_err();
+        assert_eq!(Some(&"panicking on freeze"), err.downcast_ref::<&str>());
+    }
+}
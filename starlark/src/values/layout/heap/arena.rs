@@ -28,6 +28,7 @@
 
 use std::collections::HashMap;
 use std::mem;
+use std::mem::ManuallyDrop;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::slice;
@@ -47,6 +48,7 @@ use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::AValueImpl;
 use crate::values::layout::avalue::BlackHole;
 use crate::values::layout::avalues::str_::starlark_str;
+use crate::values::layout::avalues::str_::starlark_substr;
 use crate::values::layout::heap::allocator::api::ArenaAllocator;
 use crate::values::layout::heap::allocator::api::ChunkAllocationDirection;
 use crate::values::layout::heap::call_enter_exit::CallEnter;
@@ -61,6 +63,7 @@ use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueOrForward;
 use crate::values::layout::heap::repr::AValueOrForwardUnpack;
 use crate::values::layout::heap::repr::AValueRepr;
+use crate::values::layout::pointer::RawPointer;
 use crate::values::layout::vtable::AValueVTable;
 use crate::values::string::str_type::StarlarkStr;
 
@@ -77,6 +80,28 @@ pub(crate) const MIN_ALLOC: AlignedSize = {
     )
 };
 
+/// Statistics about the memory chunks backing a heap.
+///
+/// `allocated_bytes` is split into the bytes holding values, the bytes still free
+/// at the end of each arena's current chunk, and the bytes at the end of earlier
+/// chunks which were left unused because the next value didn't fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArenaStats {
+    /// Number of chunks requested from the system allocator.
+    pub chunks: usize,
+    /// Bytes in all the chunks.
+    pub allocated_bytes: usize,
+    /// Bytes occupied by values, including their headers and padding.
+    pub used_bytes: usize,
+    /// Bytes free in the current chunks, which further allocations will use.
+    pub available_bytes: usize,
+    /// Bytes at the end of earlier chunks which will never be used.
+    pub wasted_bytes: usize,
+    /// The highest `allocated_bytes` seen, including before garbage collections.
+    pub peak_allocated_bytes: usize,
+}
+
 #[derive(Default)]
 pub(crate) struct Arena<A: ArenaAllocator> {
     /// Arena for things which don't need dropping (e.g. strings)
@@ -219,6 +244,29 @@ impl<A: ArenaAllocator> Arena<A> {
         self.drop.remaining_capacity() + self.non_drop.remaining_capacity()
     }
 
+    /// Statistics about the chunks, with `peak_allocated_bytes` the current size.
+    pub(crate) fn stats(&self) -> ArenaStats {
+        let mut chunks = 0;
+        let mut used_bytes = 0;
+        for bump in [&self.drop, &self.non_drop] {
+            // Safe because we don't allocate while iterating.
+            for chunk in unsafe { bump.iter_allocated_chunks_rev() } {
+                chunks += 1;
+                used_bytes += chunk.len();
+            }
+        }
+        let allocated_bytes = self.allocated_bytes();
+        let available_bytes = self.available_bytes();
+        ArenaStats {
+            chunks,
+            allocated_bytes,
+            used_bytes,
+            available_bytes,
+            wasted_bytes: allocated_bytes.saturating_sub(used_bytes + available_bytes),
+            peak_allocated_bytes: allocated_bytes,
+        }
+    }
+
     /// Don't forget to call this function to release memory.
     pub(crate) fn finish(&mut self) {
         self.drop.finish();
@@ -319,6 +367,22 @@ impl<A: ArenaAllocator> Arena<A> {
         unsafe { &mut (*v).header }
     }
 
+    /// Allocate a string of `len` bytes at `offset` in the storage of `parent`.
+    #[inline]
+    pub(crate) fn alloc_substr(
+        &self,
+        parent: RawPointer,
+        offset: usize,
+        len: usize,
+    ) -> *mut AValueHeader {
+        let (v, extra) = self.alloc_extra::<_>(starlark_substr(len));
+        let extra = unsafe { &mut *extra };
+        debug_assert_eq!(2, extra.len());
+        extra[0].write(parent.ptr_value());
+        extra[1].write(offset);
+        unsafe { &mut (*v).header }
+    }
+
     #[inline]
     pub(crate) fn alloc_str(&self, x: &str) -> *mut AValueHeader {
         self.alloc_str_init(x.len(), StarlarkStr::UNINIT_HASH, |dest| unsafe {
@@ -442,7 +506,7 @@ impl<A: ArenaAllocator> Arena<A> {
     }
 
     // Iterate over the values in the both bumps in any order
-    fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
+    pub(crate) fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
         for bump in [&self.drop, &self.non_drop] {
             Self::for_each_unordered_in_bump(bump, &mut f);
         }
@@ -474,6 +538,17 @@ impl<A: ArenaAllocator> Arena<A> {
     }
 }
 
+impl Arena<Bump> {
+    /// An arena with room for `bytes` of values before it needs another chunk.
+    pub(crate) fn with_capacity(bytes: usize) -> Self {
+        // We can't know how values will split between the arenas, so guess evenly.
+        Arena {
+            drop: Bump::with_capacity(bytes / 2),
+            non_drop: Bump::with_capacity(bytes - bytes / 2),
+        }
+    }
+}
+
 impl<A: ArenaAllocator> Drop for Arena<A> {
     fn drop(&mut self) {
         self.for_each_drop_unordered(|x| {
@@ -484,6 +559,66 @@ impl<A: ArenaAllocator> Drop for Arena<A> {
     }
 }
 
+/// An arena left by garbage collection, whose values are all unreachable,
+/// dropped in steps rather than all at once.
+pub(crate) struct ArenaSweep<A: ArenaAllocator> {
+    /// Never dropped, because values are dropped by `step`.
+    arena: ManuallyDrop<Arena<A>>,
+    /// Remaining parts of the chunks of the `drop` bump, with values not dropped yet.
+    chunks: Vec<*const [MaybeUninit<u8>]>,
+}
+
+// Chunks point into the memory owned by the arena.
+unsafe impl<A: ArenaAllocator + Send> Send for ArenaSweep<A> {}
+
+impl<A: ArenaAllocator> ArenaSweep<A> {
+    pub(crate) fn new(arena: Arena<A>) -> Self {
+        // SAFETY: nothing is allocated in the arena anymore.
+        let chunks = unsafe { arena.drop.iter_allocated_chunks_rev() }
+            .map(|chunk| chunk as *const [MaybeUninit<u8>])
+            .collect();
+        ArenaSweep {
+            arena: ManuallyDrop::new(arena),
+            chunks,
+        }
+    }
+
+    /// Drop at most `limit` values. Return `true` if all the values are dropped.
+    pub(crate) fn step(&mut self, mut limit: usize) -> bool {
+        while let Some(chunk) = self.chunks.last_mut() {
+            let mut iter = Arena::<A>::iter_chunk(unsafe { &**chunk });
+            while limit != 0 {
+                match iter.next() {
+                    Some(x) => {
+                        if let Some(x) = x.unpack_header() {
+                            x.0.drop_in_place(x.payload_ptr());
+                            limit -= 1;
+                        }
+                    }
+                    None => break,
+                }
+            }
+            if !iter.chunk.is_empty() {
+                *chunk = iter.chunk;
+                return false;
+            }
+            self.chunks.pop();
+        }
+        true
+    }
+}
+
+impl<A: ArenaAllocator> Drop for ArenaSweep<A> {
+    fn drop(&mut self) {
+        self.step(usize::MAX);
+        // Free the memory without running `Drop` of `Arena`, which would drop the values again.
+        unsafe {
+            drop(ptr::read(&self.arena.drop));
+            drop(ptr::read(&self.arena.non_drop));
+        }
+    }
+}
+
 impl<A: ArenaAllocator> Allocative for Arena<A> {
     fn visit<'a, 'b: 'a>(&self, visitor: &'a mut allocative::Visitor<'b>) {
         let Arena { drop, non_drop } = self;
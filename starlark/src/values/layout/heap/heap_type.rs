@@ -20,6 +20,7 @@ use std::cell::Cell;
 use std::cell::RefCell;
 use std::cell::RefMut;
 use std::cmp;
+use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
@@ -31,6 +32,7 @@ use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::ptr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use bumpalo::Bump;
@@ -38,10 +40,13 @@ use dupe::Dupe;
 use dupe::IterDupedExt;
 use starlark_map::small_set::SmallSet;
 
+use crate::ErrorCode;
 use crate::cast;
 use crate::cast::transmute;
 use crate::collections::StarlarkHashValue;
+use crate::eval::runtime::limits::StringSizeLimitExceeded;
 use crate::eval::runtime::profile::instant::ProfilerInstant;
+use crate::util::instant::Instant;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::FrozenStringValue;
@@ -61,6 +66,8 @@ use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::AValueImpl;
 use crate::values::layout::heap::allocator::alloc::allocator::ChunkAllocator;
 use crate::values::layout::heap::arena::Arena;
+use crate::values::layout::heap::arena::ArenaStats;
+use crate::values::layout::heap::arena::ArenaSweep;
 use crate::values::layout::heap::arena::ArenaVisitor;
 use crate::values::layout::heap::arena::Reservation;
 use crate::values::layout::heap::call_enter_exit::CallEnter;
@@ -69,6 +76,7 @@ use crate::values::layout::heap::call_enter_exit::NeedsDrop;
 use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::fast_cell::FastCell;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueOrForwardUnpack;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::heap::send::HeapSyncable;
@@ -90,11 +98,19 @@ pub(crate) enum HeapKind {
 struct OwnedHeap {
     /// Peak memory seen when a garbage collection takes place (may be lower than currently allocated)
     peak_allocated: Cell<usize>,
+    /// Bytes to make room for whenever the arena is created, see `Heap::reserve`.
+    reserved: Cell<usize>,
+    /// Largest string which may be created, see `Heap::set_max_string_size`.
+    max_string_size: Cell<Option<usize>>,
+    /// Intern strings up to this length, see `Heap::set_intern_strings`.
+    intern_strings: Cell<Option<usize>>,
     arena: FastCell<Arena<Bump>>,
     str_interner: RefCell<StringValueInterner<'static>>,
     /// Memory I depend on.
     refs: RefCell<SmallSet<FrozenHeapRef>>,
     ban_gc: Cell<bool>,
+    /// Arenas left by garbage collections, with values not dropped yet.
+    pending_sweeps: RefCell<VecDeque<ArenaSweep<Bump>>>,
 }
 
 impl OwnedHeap {
@@ -102,10 +118,14 @@ impl OwnedHeap {
     fn new() -> Self {
         Self {
             peak_allocated: Default::default(),
+            reserved: Default::default(),
+            max_string_size: Default::default(),
+            intern_strings: Default::default(),
             arena: Default::default(),
             str_interner: Default::default(),
             refs: Default::default(),
             ban_gc: Cell::new(true),
+            pending_sweeps: Default::default(),
         }
     }
 }
@@ -308,6 +328,14 @@ impl FrozenHeapRef {
             .map_or_else(HeapSummary::default, |a| a.arena.allocated_summary())
     }
 
+    /// Statistics about the chunks of memory backing this heap.
+    /// Doesn't include the heaps it keeps alive by reference.
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.0
+            .as_ref()
+            .map_or_else(ArenaStats::default, |a| a.arena.stats())
+    }
+
     /// Get the name of this heap.
     ///
     /// Names can be assigned when finalizing frozen heaps; in practice, this is done when freezing
@@ -323,6 +351,22 @@ impl FrozenHeapRef {
     pub fn refs(&self) -> impl Iterator<Item = &FrozenHeapRef> {
         self.0.as_ref().map(|h| h.refs.iter()).into_iter().flatten()
     }
+
+    /// Call `f` with each value of this heap, and its memory as counted by heap summaries.
+    /// Doesn't include the heaps it keeps alive by reference. The values must not be used
+    /// after the heap is dropped.
+    pub(crate) fn for_each_value(&self, mut f: impl FnMut(FrozenValue, usize)) {
+        if let Some(heap) = &self.0 {
+            heap.arena.for_each_unordered(|x| {
+                // SAFETY: the values live as long as the heap.
+                let x: &'static AValueHeader = unsafe { &*(x as *const AValueHeader) };
+                f(
+                    FrozenValue::new_ptr_query_is_str(x),
+                    x.unpack().total_memory_for_profile(),
+                )
+            })
+        }
+    }
 }
 
 impl FrozenHeap {
@@ -456,6 +500,11 @@ impl FrozenHeap {
         self.arena.allocated_summary()
     }
 
+    /// Statistics about the chunks of memory backing this heap.
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.arena.stats()
+    }
+
     pub(crate) fn reserve_with_extra<'v, 'v2, T>(
         &'v self,
         extra_len: usize,
@@ -493,6 +542,76 @@ impl<'v> Heap<'v> {
         self.0.arena.borrow().available_bytes()
     }
 
+    /// Make room for about `bytes` of values up front, for hosts which know roughly
+    /// how much an evaluation will allocate.
+    ///
+    /// If nothing has been allocated on the heap yet, the memory is reserved immediately,
+    /// otherwise it is reserved when the heap is next garbage collected.
+    /// The reservation is kept, so each garbage collection starts with at least this much room.
+    pub fn reserve(self, bytes: usize) {
+        self.0.reserved.set(bytes);
+        let stats = self.0.arena.borrow().stats();
+        if stats.used_bytes == 0 && stats.available_bytes < bytes {
+            // Safe because there are no values in the arena, and the arena is only
+            // borrowed while allocating, which can't be happening now.
+            unsafe {
+                *self.0.arena.get_mut() = Arena::with_capacity(bytes);
+            }
+        }
+    }
+
+    /// Fail operations which would create a string or bytes value larger than `size` bytes,
+    /// see [`EvalLimits::max_string_bytes`](crate::eval::EvalLimits::max_string_bytes).
+    pub fn set_max_string_size(self, size: Option<usize>) {
+        self.0.max_string_size.set(size);
+    }
+
+    /// The limit set by [`set_max_string_size`](Heap::set_max_string_size).
+    pub fn max_string_size(self) -> Option<usize> {
+        self.0.max_string_size.get()
+    }
+
+    /// Intern strings of at most `max_len` bytes allocated from now on, so equal short
+    /// strings created by evaluation, like dictionary keys or the results of `str`,
+    /// share a single value. Off (`None`) by default.
+    ///
+    /// Interned strings are kept alive until the heap is dropped, even when no longer
+    /// referenced, so `max_len` should be small.
+    pub fn set_intern_strings(self, max_len: Option<usize>) {
+        self.0.intern_strings.set(max_len);
+    }
+
+    /// Whether a string of `len` bytes should be interned, see `set_intern_strings`.
+    #[inline]
+    pub(in crate::values::layout) fn intern_str_len(self, len: usize) -> bool {
+        match self.0.intern_strings.get() {
+            Some(max_len) => len <= max_len,
+            None => false,
+        }
+    }
+
+    /// Check a string of `size` bytes may be created, before allocating it.
+    #[inline]
+    pub(crate) fn check_string_size(self, size: usize) -> crate::Result<()> {
+        match self.0.max_string_size.get() {
+            Some(limit) if size > limit => {
+                Err(
+                    crate::Error::new_other(StringSizeLimitExceeded { size, limit })
+                        .with_code(ErrorCode::STRING_LIMIT),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Statistics about the chunks of memory backing this heap.
+    pub fn arena_stats(self) -> ArenaStats {
+        ArenaStats {
+            peak_allocated_bytes: self.peak_allocated_bytes(),
+            ..self.0.arena.borrow().stats()
+        }
+    }
+
     pub(in crate::values::layout) fn alloc_raw<A>(
         self,
         x: AValueImpl<'v, A>,
@@ -541,6 +660,21 @@ impl<'v> Heap<'v> {
         }
     }
 
+    pub(in crate::values::layout) fn alloc_substr_raw(
+        self,
+        parent: StringValue<'v>,
+        offset: usize,
+        len: usize,
+    ) -> StringValue<'v> {
+        let arena = self.0.arena.borrow();
+        let v = arena.alloc_substr(parent.to_value().ptr_value(), offset, len);
+        // Same as `alloc_str_init`, we promise not to clear the arena other than for GC.
+        unsafe {
+            let value = Value::new_ptr(&*v, true);
+            StringValue::new_unchecked(value)
+        }
+    }
+
     /// Allocate a new value on a [`Heap`].
     pub fn alloc<T: AllocValue<'v>>(self, x: T) -> Value<'v> {
         x.alloc_value(self)
@@ -590,7 +724,10 @@ impl<'v> Heap<'v> {
     /// the sense that any `Value<'v>` not returned by `Tracer` _will become
     /// invalid_. Furthermore, any references to values, e.g `&'v str` will
     /// also become invalid.
-    pub(crate) unsafe fn garbage_collect(self, f: impl FnOnce(&Tracer<'v>)) {
+    ///
+    /// With `defer_sweep`, the unreachable values are not dropped during the collection,
+    /// but later, by [`sweep`](Heap::sweep).
+    pub(crate) unsafe fn garbage_collect(self, defer_sweep: bool, f: impl FnOnce(&Tracer<'v>)) {
         if self.0.ban_gc.get() {
             return;
         }
@@ -598,24 +735,48 @@ impl<'v> Heap<'v> {
         unsafe {
             // Record the highest peak, so it never decreases
             self.0.peak_allocated.set(self.peak_allocated_bytes());
-            self.garbage_collect_internal(f)
+            self.garbage_collect_internal(defer_sweep, f)
         }
     }
 
-    unsafe fn garbage_collect_internal(self, f: impl FnOnce(&Tracer<'v>)) {
+    unsafe fn garbage_collect_internal(self, defer_sweep: bool, f: impl FnOnce(&Tracer<'v>)) {
         unsafe {
             // Must rewrite all Value's so they point at the new heap.
             // Take the arena out of the heap to make sure nobody allocates in it,
             // but hold the reference until the GC is done.
-            let _arena = self.0.arena.take();
+            let arena = self.0.arena.take();
 
             let tracer = Tracer::<'v> {
-                arena: Arena::default(),
+                arena: Arena::with_capacity(self.0.reserved.get()),
                 phantom: PhantomData,
             };
             f(&tracer);
             self.0.arena.set(tracer.arena);
+            if defer_sweep {
+                self.0
+                    .pending_sweeps
+                    .borrow_mut()
+                    .push_back(ArenaSweep::new(arena));
+            }
+        }
+    }
+
+    /// Drop values left unreachable by garbage collections with deferred sweep,
+    /// until done or `budget` is exceeded. Return `true` if nothing is left to drop.
+    pub(crate) fn sweep(self, budget: Duration) -> bool {
+        /// Values to drop between checks of the clock.
+        const STEP: usize = 256;
+
+        let start = Instant::now();
+        let mut pending = self.0.pending_sweeps.borrow_mut();
+        while let Some(sweep) = pending.front_mut() {
+            if sweep.step(STEP) {
+                pending.pop_front();
+            } else if start.elapsed() >= budget {
+                return false;
+            }
         }
+        true
     }
 
     /// Obtain a summary of how much memory is currently allocated by this heap.
@@ -700,6 +861,11 @@ impl<'v> Tracer<'v> {
         unsafe { Value::new_ptr(&*v, true) }
     }
 
+    pub(crate) fn alloc_substr(&self, parent: Value<'v>, offset: usize, len: usize) -> Value<'v> {
+        let v = self.arena.alloc_substr(parent.ptr_value(), offset, len);
+        unsafe { Value::new_ptr(&*v, true) }
+    }
+
     fn adjust(&self, value: Value<'v>) -> Value<'v> {
         // Case 1, doesn't point at the old arena
         if !value.0.is_unfrozen() {
@@ -778,4 +944,40 @@ assert_eq(x, "foox")
         "#,
         );
     }
+
+    #[test]
+    fn test_reserve() {
+        Heap::temp(|heap| {
+            heap.reserve(1 << 20);
+            let stats = heap.arena_stats();
+            assert!(stats.available_bytes >= 1 << 20, "{stats:?}");
+            assert_eq!(0, stats.used_bytes);
+
+            for i in 0..1000 {
+                heap.alloc_str(&format!("string number {i}"));
+            }
+            // Everything fits, so nothing new was requested.
+            let after = heap.arena_stats();
+            assert_eq!(stats.chunks, after.chunks);
+            assert_eq!(stats.allocated_bytes, after.allocated_bytes);
+            assert!(after.used_bytes > 0);
+        });
+    }
+
+    #[test]
+    fn test_arena_stats() {
+        Heap::temp(|heap| {
+            for i in 0..10000 {
+                heap.alloc((i.to_string(), i + 1_000_000_000_000i64));
+            }
+            let stats = heap.arena_stats();
+            assert!(stats.chunks > 1, "{stats:?}");
+            assert_eq!(
+                stats.allocated_bytes,
+                stats.used_bytes + stats.available_bytes + stats.wasted_bytes
+            );
+            assert_eq!(heap.allocated_bytes(), stats.allocated_bytes);
+            assert!(stats.peak_allocated_bytes >= stats.allocated_bytes);
+        });
+    }
 }
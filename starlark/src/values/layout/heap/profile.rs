@@ -19,6 +19,7 @@
 
 pub(crate) mod aggregated;
 pub(crate) mod alloc_counts;
+pub(crate) mod analysis;
 pub(crate) mod by_type;
 pub(crate) mod string_index;
 mod summary_by_function;
@@ -0,0 +1,479 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Memory retained by the values of a frozen heap.
+//!
+//! While freezing, we record which values each value references. A value retains the
+//! values which are only reachable through it, that is those it dominates in the graph
+//! of references from the module variables.
+
+use std::collections::HashMap;
+use std::mem;
+
+use allocative::Allocative;
+
+use crate::eval::runtime::profile::csv::CsvWriter;
+use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
+use crate::util::arc_str::ArcStr;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenValue;
+use crate::values::layout::pointer::RawPointer;
+
+/// Records the references between values while freezing.
+#[derive(Default)]
+pub(crate) struct HeapReferencesBuilder {
+    /// Values frozen so far by each call to `Freezer::freeze` in progress.
+    stack: Vec<Vec<FrozenValue>>,
+    references: HeapReferences,
+}
+
+impl HeapReferencesBuilder {
+    pub(crate) fn enter(&mut self) {
+        self.stack.push(Vec::new());
+    }
+
+    pub(crate) fn exit(&mut self, value: FrozenValue) {
+        let children = self.stack.pop().unwrap_or_default();
+        if !children.is_empty() {
+            // Values which were already frozen don't freeze their children again.
+            self.references
+                .children
+                .entry(value.ptr_value())
+                .or_insert(children);
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.push(value),
+            None => self.references.roots.push(value),
+        }
+    }
+
+    pub(crate) fn exit_with_error(&mut self) {
+        self.stack.pop();
+    }
+
+    pub(crate) fn finish(&mut self) -> HeapReferences {
+        mem::take(&mut self.references)
+    }
+}
+
+/// The references between the values of a frozen heap.
+#[derive(Debug, Default, Allocative)]
+pub(crate) struct HeapReferences {
+    /// Values frozen other than by another value, like the module variables.
+    roots: Vec<FrozenValue>,
+    /// The values referenced by each value which references any.
+    children: HashMap<RawPointer, Vec<FrozenValue>>,
+}
+
+/// The memory retained by a module variable, see [`HeapAnalysis`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetainedGlobal {
+    /// Name of the variable.
+    pub name: String,
+    /// Type of the value.
+    pub type_name: String,
+    /// Bytes of the value itself, including any memory it owns outside the heap.
+    pub self_bytes: usize,
+    /// Bytes of the value and of all the values only reachable through it.
+    pub retained_bytes: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    type_name: &'static str,
+    /// The first module variable with this value.
+    name: Option<String>,
+    /// Zero for values of other heaps, such as those of loaded modules.
+    self_bytes: usize,
+    retained_bytes: usize,
+}
+
+/// Memory kept alive by the values of a frozen module, as returned by
+/// [`FrozenModule::heap_analysis`](crate::environment::FrozenModule::heap_analysis).
+#[derive(Debug)]
+pub struct HeapAnalysis {
+    /// Node 0 is a virtual root referencing the module variables.
+    nodes: Vec<Node>,
+    /// Children of each node in the dominator tree.
+    dominated: Vec<Vec<usize>>,
+    globals: Vec<(String, usize)>,
+    /// Bytes of the values of the heap which are not reachable from the module variables.
+    unreachable_bytes: usize,
+}
+
+impl HeapAnalysis {
+    pub(crate) fn new(
+        heap: &FrozenHeapRef,
+        references: &HeapReferences,
+        globals: impl IntoIterator<Item = (String, FrozenValue)>,
+    ) -> HeapAnalysis {
+        let mut sizes = HashMap::new();
+        heap.for_each_value(|value, bytes| {
+            sizes.insert(value.ptr_value(), bytes);
+        });
+
+        let mut graph = Graph::default();
+        let globals: Vec<(String, usize)> = globals
+            .into_iter()
+            .map(|(name, value)| (name, graph.node(value)))
+            .collect();
+        for &root in &references.roots {
+            graph.node(root);
+        }
+        graph.succs[0] = (1..graph.values.len()).collect();
+        // Nodes are added as we go, so this visits everything reachable.
+        let mut i = 1;
+        while i < graph.values.len() {
+            let value = graph.values[i];
+            if let Some(children) = references.children.get(&value.ptr_value()) {
+                let succs = children.iter().map(|x| graph.node(*x)).collect();
+                graph.succs[i] = succs;
+            }
+            i += 1;
+        }
+
+        let mut nodes = Vec::with_capacity(graph.values.len());
+        nodes.push(Node {
+            type_name: "",
+            name: None,
+            self_bytes: 0,
+            retained_bytes: 0,
+        });
+        for value in &graph.values[1..] {
+            let self_bytes = sizes.remove(&value.ptr_value()).unwrap_or(0);
+            nodes.push(Node {
+                type_name: value.to_value().get_type(),
+                name: None,
+                self_bytes,
+                retained_bytes: self_bytes,
+            });
+        }
+        for (name, node) in &globals {
+            nodes[*node].name.get_or_insert_with(|| name.clone());
+        }
+
+        let (idom, postorder) = dominators(&graph.succs);
+        let mut dominated = vec![Vec::new(); nodes.len()];
+        // Dominators come after the nodes they dominate in postorder.
+        for &node in &postorder {
+            if node != 0 {
+                nodes[idom[node]].retained_bytes += nodes[node].retained_bytes;
+            }
+        }
+        for (node, &idom) in idom.iter().enumerate().skip(1) {
+            dominated[idom].push(node);
+        }
+
+        HeapAnalysis {
+            nodes,
+            dominated,
+            globals,
+            unreachable_bytes: sizes.values().sum(),
+        }
+    }
+
+    /// Bytes retained by the module variables.
+    pub fn retained_bytes(&self) -> usize {
+        self.nodes[0].retained_bytes
+    }
+
+    /// Bytes of values in the heap not reachable from the module variables, like the
+    /// constants of functions, or values allocated while freezing.
+    pub fn unreachable_bytes(&self) -> usize {
+        self.unreachable_bytes
+    }
+
+    /// The memory retained by each module variable, largest first.
+    pub fn globals(&self) -> Vec<RetainedGlobal> {
+        let mut res: Vec<RetainedGlobal> = self
+            .globals
+            .iter()
+            .map(|(name, node)| {
+                let node = &self.nodes[*node];
+                RetainedGlobal {
+                    name: name.clone(),
+                    type_name: node.type_name.to_owned(),
+                    self_bytes: node.self_bytes,
+                    retained_bytes: node.retained_bytes,
+                }
+            })
+            .collect();
+        res.sort_by(|a, b| {
+            b.retained_bytes
+                .cmp(&a.retained_bytes)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        res
+    }
+
+    /// Bytes retained by values shared by several module variables, so retained by none
+    /// of them.
+    fn shared_bytes(&self) -> usize {
+        self.dominated[0]
+            .iter()
+            .filter(|x| self.nodes[**x].name.is_none())
+            .map(|x| self.nodes[*x].retained_bytes)
+            .sum()
+    }
+
+    /// The memory retained by each module variable, as CSV.
+    pub fn gen_summary_csv(&self) -> String {
+        let mut csv = CsvWriter::new(["Variable", "Type", "Self bytes", "Retained bytes"]);
+        let mut row = |name: &str, type_name: &str, self_bytes: usize, retained_bytes: usize| {
+            csv.write_value(name);
+            csv.write_value(type_name);
+            csv.write_value(self_bytes);
+            csv.write_value(retained_bytes);
+            csv.finish_row();
+        };
+        let total = self.retained_bytes() + self.unreachable_bytes;
+        row("TOTALS", "", total, total);
+        for global in self.globals() {
+            row(
+                &global.name,
+                &global.type_name,
+                global.self_bytes,
+                global.retained_bytes,
+            );
+        }
+        let shared = self.shared_bytes();
+        row("(shared)", "", shared, shared);
+        row(
+            "(unreachable)",
+            "",
+            self.unreachable_bytes,
+            self.unreachable_bytes,
+        );
+        csv.finish()
+    }
+
+    /// The dominator tree, in the format of
+    /// [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl):
+    /// the stacks are the variable names followed by the types of the values retained
+    /// through it, and the sizes the bytes of the values themselves.
+    pub fn gen_flame_data(&self) -> String {
+        let mut data = FlameGraphData::default();
+        for &node in &self.dominated[0] {
+            if self.nodes[node].retained_bytes == 0 {
+                continue;
+            }
+            let name = match &self.nodes[node].name {
+                Some(name) => ArcStr::from(name.as_str()),
+                None => ArcStr::new_static("(shared)"),
+            };
+            self.add_flame(node, data.root().child(name));
+        }
+        if self.unreachable_bytes != 0 {
+            data.root()
+                .child(ArcStr::new_static("(unreachable)"))
+                .add(self.unreachable_bytes as u64);
+        }
+        data.write()
+    }
+
+    fn add_flame(&self, node: usize, flame: &mut FlameGraphNode) {
+        let flame = flame.child(ArcStr::new_static(self.nodes[node].type_name));
+        if self.nodes[node].self_bytes != 0 {
+            flame.add(self.nodes[node].self_bytes as u64);
+        }
+        for &child in &self.dominated[node] {
+            if self.nodes[child].retained_bytes != 0 {
+                self.add_flame(child, flame);
+            }
+        }
+    }
+}
+
+/// The graph of references, where node 0 is the virtual root.
+struct Graph {
+    index: HashMap<RawPointer, usize>,
+    /// The value of each node, with a placeholder for the root.
+    values: Vec<FrozenValue>,
+    succs: Vec<Vec<usize>>,
+}
+
+impl Default for Graph {
+    fn default() -> Graph {
+        Graph {
+            index: HashMap::new(),
+            values: vec![FrozenValue::new_none()],
+            succs: vec![Vec::new()],
+        }
+    }
+}
+
+impl Graph {
+    fn node(&mut self, value: FrozenValue) -> usize {
+        if let Some(node) = self.index.get(&value.ptr_value()) {
+            return *node;
+        }
+        let node = self.values.len();
+        self.index.insert(value.ptr_value(), node);
+        self.values.push(value);
+        self.succs.push(Vec::new());
+        node
+    }
+}
+
+/// The immediate dominator of each node of a graph, where all the nodes are reachable from
+/// node 0, and the nodes in postorder. Uses "A Simple, Fast Dominance Algorithm" by Cooper,
+/// Harvey and Kennedy.
+fn dominators(succs: &[Vec<usize>]) -> (Vec<usize>, Vec<usize>) {
+    let n = succs.len();
+
+    // Not recursive, since values can be nested deeply.
+    let mut postorder = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    visited[0] = true;
+    let mut stack = vec![(0, 0)];
+    while let Some(top) = stack.last_mut() {
+        let (node, next) = *top;
+        match succs[node].get(next) {
+            Some(&succ) => {
+                top.1 += 1;
+                if !visited[succ] {
+                    visited[succ] = true;
+                    stack.push((succ, 0));
+                }
+            }
+            None => {
+                postorder.push(node);
+                stack.pop();
+            }
+        }
+    }
+    debug_assert_eq!(n, postorder.len(), "all nodes must be reachable");
+
+    let mut order = vec![0; n];
+    for (i, &node) in postorder.iter().enumerate() {
+        order[node] = i;
+    }
+    let mut preds = vec![Vec::new(); n];
+    for (node, succs) in succs.iter().enumerate() {
+        for &succ in succs {
+            preds[succ].push(node);
+        }
+    }
+
+    const UNDEFINED: usize = usize::MAX;
+    let mut idom = vec![UNDEFINED; n];
+    idom[0] = 0;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // Reverse postorder, without the root, which is last.
+        for &node in postorder.iter().rev().skip(1) {
+            let mut new_idom = UNDEFINED;
+            for &pred in &preds[node] {
+                if idom[pred] == UNDEFINED {
+                    continue;
+                }
+                new_idom = if new_idom == UNDEFINED {
+                    pred
+                } else {
+                    intersect(&idom, &order, pred, new_idom)
+                };
+            }
+            if idom[node] != new_idom {
+                idom[node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+    (idom, postorder)
+}
+
+fn intersect(idom: &[usize], order: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while order[a] < order[b] {
+            a = idom[a];
+        }
+        while order[b] < order[a] {
+            b = idom[b];
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::layout::heap::profile::analysis::HeapAnalysis;
+    use crate::values::layout::heap::profile::analysis::dominators;
+
+    #[test]
+    fn test_dominators() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3 -> 4 -> 3
+        let succs = vec![vec![1, 2], vec![3], vec![3], vec![4], vec![3]];
+        let (idom, postorder) = dominators(&succs);
+        assert_eq!(vec![0, 0, 0, 0, 3], idom);
+        assert_eq!(Some(&0), postorder.last());
+    }
+
+    fn analyse(program: &str) -> HeapAnalysis {
+        Module::with_temp_heap(|module| {
+            module.enable_heap_analysis();
+            {
+                let mut eval = Evaluator::new(&module);
+                let ast =
+                    AstModule::parse("test.star", program.to_owned(), &Dialect::Standard).unwrap();
+                eval.eval_module(ast, &Globals::standard()).unwrap();
+            }
+            module.freeze().unwrap().heap_analysis().unwrap()
+        })
+    }
+
+    #[test]
+    fn test_heap_analysis() {
+        let analysis = analyse(
+            r#"
+big = ["x" * 1000]
+alias = big
+small = 1
+a = [{"y" * 2000: 1}]
+b = [a[0]]
+"#,
+        );
+
+        let globals = analysis.globals();
+        let global = |name: &str| globals.iter().find(|x| x.name == name).unwrap();
+        assert!(global("big").retained_bytes >= 1000);
+        assert_eq!(global("big").retained_bytes, global("alias").retained_bytes);
+        assert_eq!("list", global("big").type_name);
+        assert_eq!(0, global("small").retained_bytes);
+        // The dict is referenced by both `a` and `b`, so neither retains it.
+        assert!(global("a").retained_bytes < 1000);
+        assert!(global("b").retained_bytes < 1000);
+
+        let flame = analysis.gen_flame_data();
+        assert!(flame.contains("big;list;string "), "{flame}");
+        assert!(flame.contains("(shared);dict;string "), "{flame}");
+        assert!(analysis.gen_summary_csv().starts_with("Variable,"));
+    }
+
+    #[test]
+    fn test_heap_analysis_not_enabled() {
+        Module::with_temp_heap(|module| {
+            assert!(module.freeze().unwrap().heap_analysis().is_err());
+        })
+    }
+}
@@ -19,6 +19,10 @@ use std::hash::Hash;
 use std::mem;
 use std::mem::ManuallyDrop;
 use std::ptr;
+#[cfg(feature = "parallel_freeze")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "parallel_freeze")]
+use std::sync::atomic::Ordering;
 
 use dupe::Dupe;
 
@@ -28,12 +32,20 @@ use crate::values::FrozenValue;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 use crate::values::layout::avalue::AValue;
+#[cfg(feature = "parallel_freeze")]
+use crate::values::layout::freezer::parallel::claimed_vtable;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::value_alloc_size::ValueAllocSize;
 use crate::values::layout::vtable::AValueDyn;
 use crate::values::layout::vtable::AValueVTable;
 use crate::values::layout::vtable::StarlarkValueRawPtr;
 
+/// Header word of a value being frozen by a thread in a parallel freeze.
+///
+/// Not a valid vtable pointer, and has the lowest bit unset, so it is not a forward either.
+#[cfg(feature = "parallel_freeze")]
+pub(crate) const CLAIMED_HEADER: usize = 2;
+
 #[derive(Clone)]
 #[repr(C)]
 pub(crate) struct AValueHeader(pub(crate) &'static AValueVTable);
@@ -81,6 +93,13 @@ impl ForwardPtr {
         ForwardPtr(ptr)
     }
 
+    /// Forward pointer from the first word of an [`AValueForward`].
+    #[cfg(feature = "parallel_freeze")]
+    pub(crate) fn from_forward_word(word: usize) -> ForwardPtr {
+        debug_assert!((word & 1) != 0);
+        ForwardPtr(word & !1)
+    }
+
     /// Create a forward pointer to a frozen value. This is used during heap freeze.
     pub(crate) fn new_frozen(value: FrozenValue) -> ForwardPtr {
         ForwardPtr::new(value.0.raw().ptr_value())
@@ -273,6 +292,10 @@ impl AValueHeader {
         forward_ptr: ForwardPtr,
     ) -> T {
         unsafe {
+            #[cfg(feature = "parallel_freeze")]
+            if ptr::read(me as *const usize) == CLAIMED_HEADER {
+                return Self::overwrite_claimed_with_forward(me, forward_ptr);
+            }
             // TODO(nga): we don't need to do virtual call to obtain memory size
             let sz = (*me).header.unpack().memory_size();
             let p = me as *const AValueRepr<T>;
@@ -283,6 +306,29 @@ impl AValueHeader {
         }
     }
 
+    /// Like `overwrite_with_forward`, for a value claimed by a parallel freeze.
+    ///
+    /// The header holds `CLAIMED_HEADER` rather than the vtable, and other threads
+    /// may be reading the header word, so the forward is published atomically.
+    #[cfg(feature = "parallel_freeze")]
+    unsafe fn overwrite_claimed_with_forward<'v, T: StarlarkValue<'v>>(
+        me: *mut AValueRepr<T>,
+        forward_ptr: ForwardPtr,
+    ) -> T {
+        unsafe {
+            let header = &raw const (*me).header;
+            let vtable = claimed_vtable(header);
+            let sz =
+                AValueDyn::new(StarlarkValueRawPtr::new_header(&*header), vtable).memory_size();
+            let res = ptr::read(&(*me).payload);
+            let forward = AValueForward::new(forward_ptr, sz);
+            let p = me as *mut AValueForward;
+            ptr::write(&raw mut (*p).object_size, forward.object_size);
+            AtomicUsize::from_ptr(p as *mut usize).store(forward.forward_ptr, Ordering::Release);
+            res
+        }
+    }
+
     /// Cast header pointer to repr pointer.
     #[inline]
     pub(crate) unsafe fn as_repr<'v, T: StarlarkValue<'v>>(&self) -> &AValueRepr<T> {
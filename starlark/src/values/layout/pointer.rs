@@ -277,7 +277,7 @@ unsafe fn untag_pointer<'a>(x: usize) -> &'a AValueOrForward {
 
 impl<'p> Pointer<'p> {
     #[inline]
-    unsafe fn new(ptr: RawPointer) -> Pointer<'p> {
+    pub(crate) unsafe fn new(ptr: RawPointer) -> Pointer<'p> {
         Pointer {
             ptr,
             _phantom: PhantomData,
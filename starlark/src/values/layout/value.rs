@@ -119,6 +119,7 @@ use crate::values::recursive_repr_or_json_guard::repr_stack_push;
 use crate::values::stack_guard;
 use crate::values::starlark_type_id::StarlarkTypeId;
 use crate::values::string::str_type::StarlarkStr;
+use crate::values::string::str_type::slice_string_value;
 use crate::values::structs::value::FrozenStruct;
 use crate::values::tuple::value::VALUE_EMPTY_TUPLE;
 use crate::values::type_repr::StarlarkTypeRepr;
@@ -551,6 +552,12 @@ impl<'v> Value<'v> {
         self.0.raw()
     }
 
+    /// Inverse of [`ptr_value`](Value::ptr_value), for a pointer to a live value.
+    #[inline]
+    pub(crate) unsafe fn from_ptr_value(ptr: RawPointer) -> Value<'v> {
+        unsafe { Value(Pointer::new(ptr)) }
+    }
+
     /// `type(x)`.
     pub fn get_type(self) -> &'static str {
         self.vtable().type_name
@@ -579,6 +586,9 @@ impl<'v> Value<'v> {
         stride: Option<Value<'v>>,
         heap: Heap<'v>,
     ) -> crate::Result<Value<'v>> {
+        if let Some(s) = StringValue::new(self) {
+            return slice_string_value(s, start, stop, stride, heap);
+        }
         self.get_ref().slice(start, stop, stride, heap)
     }
 
@@ -669,6 +679,7 @@ impl<'v> Value<'v> {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
+        eval.check_deterministic_value(self)?;
         eval.with_call_stack(self, location, |eval| {
             self.get_ref_full().invoke(args, eval)
         })
@@ -818,6 +829,7 @@ impl<'v> Value<'v> {
                 } else if rs.is_empty() {
                     return Ok(self);
                 } else {
+                    heap.check_string_size(ls.len() + rs.len())?;
                     return Ok(heap.alloc_str_concat(ls, rs).to_value());
                 }
             }
@@ -865,10 +877,20 @@ impl<'v> Value<'v> {
     }
 
     /// Convert the value to JSON value.
+    ///
+    /// Integers and floats stay distinct (`1` vs `1.0`), and integers of any size
+    /// are kept exactly.
     pub fn to_json_value(self) -> anyhow::Result<serde_json::Value> {
         serde_json::to_value(self).map_err(|e| anyhow::anyhow!(e))
     }
 
+    /// Convert a JSON value to a Starlark value, the inverse of [`to_json_value`](Value::to_json_value).
+    ///
+    /// Numbers without a fraction or exponent become integers, of any size, otherwise floats.
+    pub fn from_json_value(heap: Heap<'v>, json: &serde_json::Value) -> Value<'v> {
+        heap.alloc(json)
+    }
+
     /// Forwards to [`StarlarkValue::set_attr`].
     pub fn set_attr(self, attribute: &str, alloc_value: Value<'v>) -> crate::Result<()> {
         self.get_ref().set_attr(attribute, alloc_value)
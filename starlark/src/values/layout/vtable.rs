@@ -538,6 +538,11 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.name_for_call_stack)(self.value, me)
     }
 
+    #[inline]
+    pub(crate) fn is_deterministic(self) -> bool {
+        (self.vtable.starlark_value.is_deterministic)(self.value)
+    }
+
     #[inline]
     pub(crate) fn export_as(
         self,
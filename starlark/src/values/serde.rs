@@ -0,0 +1,449 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Serialization of values to plain data, which keeps their types, to store the globals of
+//! a [`FrozenModule`] in any serde format, like CBOR or MessagePack, and build them again
+//! on a fresh heap, possibly in another process.
+//!
+//! ```
+//! # use starlark::environment::FrozenModule;
+//! # use starlark::values::serde::ModuleData;
+//! # use starlark::values::serde::ValueDeserializer;
+//! # fn f(module: &FrozenModule) -> anyhow::Result<()> {
+//! let json = serde_json::to_string(&ModuleData::from_module(module)?)?;
+//! let data: ModuleData = serde_json::from_str(&json)?;
+//! let copy = ValueDeserializer::new().module(&data)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Unlike JSON, tuples stay tuples, dict keys can be any hashable value, and ints of
+//! any size are kept exactly. Functions can't be serialized. Values referenced several
+//! times are serialized, and built again, once per reference.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use ::serde::Deserialize;
+use ::serde::Serialize;
+use anyhow::Context;
+use num_bigint::BigInt;
+
+use crate::any::ProvidesStaticType;
+use crate::collections::SmallMap;
+use crate::environment::FrozenModule;
+use crate::environment::Module;
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueIdentity;
+use crate::values::ValueLike;
+use crate::values::bytes::StarlarkBytes;
+use crate::values::dict::Dict;
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::AllocList;
+use crate::values::list::ListRef;
+use crate::values::set::SetRef;
+use crate::values::set::value::SetData;
+use crate::values::structs::AllocStruct;
+use crate::values::structs::StructRef;
+use crate::values::tuple::AllocTuple;
+use crate::values::tuple::TupleRef;
+use crate::values::types::int::int_or_big::StarlarkInt;
+
+#[derive(Debug, thiserror::Error)]
+enum ValueSerdeError {
+    #[error("Can't serialize a value of type `{0}`")]
+    Unsupported(String),
+    #[error("Can't serialize a value which contains itself")]
+    Cycle,
+    #[error("No deserializer registered for custom values of kind `{0}`")]
+    UnknownKind(String),
+    #[error("Invalid int `{0}`")]
+    InvalidInt(String),
+}
+
+/// A value as plain data, which serde can serialize with any format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueData {
+    /// `None`.
+    None,
+    /// A bool.
+    Bool(bool),
+    /// An int which fits in `i64`.
+    Int(i64),
+    /// An int which does not fit in `i64`, in decimal.
+    BigInt(String),
+    /// A float.
+    Float(f64),
+    /// A string.
+    String(String),
+    /// Bytes.
+    Bytes(Vec<u8>),
+    /// A list.
+    List(Vec<ValueData>),
+    /// A tuple.
+    Tuple(Vec<ValueData>),
+    /// Keys and values, in the order of the dict.
+    Dict(Vec<(ValueData, ValueData)>),
+    /// A set, in iteration order.
+    Set(Vec<ValueData>),
+    /// Struct fields, in order.
+    Struct(Vec<(String, ValueData)>),
+    /// A value implementing [`ValueSerialize`], with its kind.
+    Custom {
+        /// The kind, used to find the deserializer.
+        kind: String,
+        /// The data of the value.
+        data: Box<ValueData>,
+    },
+}
+
+impl ValueData {
+    /// The data of a value of built-in types (`None`, bools, ints, floats, strings,
+    /// bytes, lists, tuples, dicts, sets and structs), or of a custom value implementing
+    /// [`ValueSerialize`]. For a frozen value, call [`to_value`](crate::values::FrozenValue::to_value).
+    pub fn from_value(value: Value) -> anyhow::Result<ValueData> {
+        ValueData::from_value_impl(value, &mut Vec::new())
+    }
+
+    fn from_value_impl<'v>(
+        value: Value<'v>,
+        stack: &mut Vec<ValueIdentity<'v>>,
+    ) -> anyhow::Result<ValueData> {
+        if value.is_none() {
+            return Ok(ValueData::None);
+        }
+        if let Some(x) = value.unpack_bool() {
+            return Ok(ValueData::Bool(x));
+        }
+        if let Some(x) = value.unpack_str() {
+            return Ok(ValueData::String(x.to_owned()));
+        }
+        if value.get_type() == "int" {
+            return Ok(match i64::unpack_value(value) {
+                Ok(Some(x)) => ValueData::Int(x),
+                _ => ValueData::BigInt(value.to_str()),
+            });
+        }
+        if let Some(x) = value.downcast_ref::<StarlarkFloat>() {
+            return Ok(ValueData::Float(x.0));
+        }
+        if let Some(x) = value.downcast_ref::<StarlarkBytes>() {
+            return Ok(ValueData::Bytes(x.as_bytes().to_vec()));
+        }
+        if let Some(x) = value.request_value::<&dyn ValueSerialize>() {
+            return Ok(ValueData::Custom {
+                kind: x.kind().to_owned(),
+                data: Box::new(x.to_data()?),
+            });
+        }
+        if stack.contains(&value.identity()) {
+            return Err(ValueSerdeError::Cycle.into());
+        }
+        stack.push(value.identity());
+        let res = ValueData::from_container(value, stack);
+        stack.pop();
+        res
+    }
+
+    fn from_container<'v>(
+        value: Value<'v>,
+        stack: &mut Vec<ValueIdentity<'v>>,
+    ) -> anyhow::Result<ValueData> {
+        let mut all = |xs: &mut dyn Iterator<Item = Value<'v>>| {
+            xs.map(|x| ValueData::from_value_impl(x, stack))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        if let Some(xs) = ListRef::from_value(value) {
+            return Ok(ValueData::List(all(&mut xs.iter())?));
+        }
+        if let Some(xs) = TupleRef::from_value(value) {
+            return Ok(ValueData::Tuple(all(&mut xs.iter())?));
+        }
+        if let Ok(Some(xs)) = SetRef::unpack_value(value) {
+            return Ok(ValueData::Set(all(&mut xs.aref.iter())?));
+        }
+        if let Some(xs) = DictRef::from_value(value) {
+            return Ok(ValueData::Dict(
+                xs.iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            ValueData::from_value_impl(k, stack)?,
+                            ValueData::from_value_impl(v, stack)?,
+                        ))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ));
+        }
+        if let Some(xs) = StructRef::from_value(value) {
+            return Ok(ValueData::Struct(
+                xs.iter()
+                    .map(|(k, v)| {
+                        Ok((k.as_str().to_owned(), ValueData::from_value_impl(v, stack)?))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ));
+        }
+        Err(ValueSerdeError::Unsupported(value.get_type().to_owned()).into())
+    }
+}
+
+/// The public globals of a module, as data.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleData {
+    /// The names and values of the globals, in the order of the module.
+    pub globals: Vec<(String, ValueData)>,
+}
+
+impl ModuleData {
+    /// The data of all the public globals of `module`, failing if any can't be serialized.
+    pub fn from_module(module: &FrozenModule) -> anyhow::Result<ModuleData> {
+        let mut globals = Vec::new();
+        for name in module.names() {
+            let value = module.get(name.as_str())?;
+            let data = ValueData::from_value(value.value())
+                .with_context(|| format!("Serializing global `{}`", name.as_str()))?;
+            globals.push((name.as_str().to_owned(), data));
+        }
+        Ok(ModuleData { globals })
+    }
+}
+
+/// The serialization of a custom value, which it provides from
+/// [`StarlarkValue::provide`](crate::values::StarlarkValue::provide) with
+/// `demand.provide_value::<&dyn ValueSerialize>(self)`.
+///
+/// The value is built again by the function registered for its kind with
+/// [`ValueDeserializer::register`].
+pub trait ValueSerialize {
+    /// The kind of value, usually its type name.
+    fn kind(&self) -> &str;
+
+    /// The content of the value, with [`ValueData::from_value`] for the values it holds.
+    fn to_data(&self) -> anyhow::Result<ValueData>;
+}
+
+unsafe impl<'v> ProvidesStaticType<'v> for &'v dyn ValueSerialize {
+    type StaticType = &'static dyn ValueSerialize;
+}
+
+type CustomDeserialize = Box<
+    dyn for<'v> Fn(&ValueDeserializer, &ValueData, Heap<'v>) -> anyhow::Result<Value<'v>>
+        + Send
+        + Sync,
+>;
+
+/// Builds values from [`ValueData`], with the functions registered for custom values.
+#[derive(Default)]
+pub struct ValueDeserializer {
+    custom: HashMap<String, CustomDeserialize>,
+}
+
+impl ValueDeserializer {
+    /// A deserializer of the built-in types only.
+    pub fn new() -> ValueDeserializer {
+        ValueDeserializer::default()
+    }
+
+    /// Build the custom values of `kind` from their [`ValueSerialize::to_data`] with `f`,
+    /// which can call back [`alloc`](ValueDeserializer::alloc) for the values they hold.
+    pub fn register(
+        &mut self,
+        kind: &str,
+        f: impl for<'v> Fn(&ValueDeserializer, &ValueData, Heap<'v>) -> anyhow::Result<Value<'v>>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        self.custom.insert(kind.to_owned(), Box::new(f));
+    }
+
+    /// Allocate the value of `data` on `heap`.
+    pub fn alloc<'v>(&self, data: &ValueData, heap: Heap<'v>) -> anyhow::Result<Value<'v>> {
+        let all = |xs: &[ValueData]| {
+            xs.iter()
+                .map(|x| self.alloc(x, heap))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(match data {
+            ValueData::None => Value::new_none(),
+            ValueData::Bool(x) => Value::new_bool(*x),
+            ValueData::Int(x) => heap.alloc(*x),
+            ValueData::BigInt(x) => heap.alloc(StarlarkInt::from(
+                BigInt::from_str(x).map_err(|_| ValueSerdeError::InvalidInt(x.clone()))?,
+            )),
+            ValueData::Float(x) => heap.alloc(*x),
+            ValueData::String(x) => heap.alloc(x.as_str()),
+            ValueData::Bytes(x) => heap.alloc(StarlarkBytes::new(x.as_slice())),
+            ValueData::List(xs) => heap.alloc(AllocList(all(xs)?)),
+            ValueData::Tuple(xs) => heap.alloc(AllocTuple(all(xs)?)),
+            ValueData::Dict(xs) => {
+                let mut content = SmallMap::with_capacity(xs.len());
+                for (k, v) in xs {
+                    content.insert_hashed(
+                        self.alloc(k, heap)?
+                            .get_hashed()
+                            .map_err(|e| e.into_anyhow())?,
+                        self.alloc(v, heap)?,
+                    );
+                }
+                heap.alloc(Dict::new(content))
+            }
+            ValueData::Set(xs) => {
+                let mut set = SetData::default();
+                for x in xs {
+                    set.add_hashed(
+                        self.alloc(x, heap)?
+                            .get_hashed()
+                            .map_err(|e| e.into_anyhow())?,
+                    );
+                }
+                heap.alloc(set)
+            }
+            ValueData::Struct(xs) => heap.alloc(AllocStruct(
+                xs.iter()
+                    .map(|(k, v)| Ok((k.as_str(), self.alloc(v, heap)?)))
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )),
+            ValueData::Custom { kind, data } => match self.custom.get(kind) {
+                Some(f) => f(self, data, heap)?,
+                None => return Err(ValueSerdeError::UnknownKind(kind.clone()).into()),
+            },
+        })
+    }
+
+    /// Build a frozen module with the globals of `data`.
+    pub fn module(&self, data: &ModuleData) -> anyhow::Result<FrozenModule> {
+        Module::with_temp_heap(|module| {
+            for (name, x) in &data.globals {
+                module.set(name, self.alloc(x, module.heap())?);
+            }
+            Ok(module.freeze()?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use allocative::Allocative;
+    use starlark_derive::NoSerialize;
+    use starlark_derive::starlark_value;
+
+    use crate as starlark;
+    use crate::any::ProvidesStaticType;
+    use crate::environment::FrozenModule;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::starlark_simple_value;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Heap;
+    use crate::values::StarlarkValue;
+    use crate::values::demand::Demand;
+    use crate::values::list::AllocList;
+    use crate::values::serde::ModuleData;
+    use crate::values::serde::ValueData;
+    use crate::values::serde::ValueDeserializer;
+    use crate::values::serde::ValueSerialize;
+
+    fn frozen_module(code: &str) -> FrozenModule {
+        let ast =
+            AstModule::parse("test.star", code.to_owned(), &Dialect::AllOptionsInternal).unwrap();
+        Module::with_temp_heap(|module| {
+            Evaluator::new(&module)
+                .eval_module(ast, &Globals::extended_internal())
+                .unwrap();
+            module.freeze()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_module_round_trip() {
+        let module = frozen_module(
+            r#"
+x = [1, (2, "a"), {1: b"xy", (1, 2): None}, set([3, 4]), struct(a = 1.5)]
+y = 123456789012345678901234567890
+"#,
+        );
+        let json = serde_json::to_string(&ModuleData::from_module(&module).unwrap()).unwrap();
+        let data: ModuleData = serde_json::from_str(&json).unwrap();
+        let copy = ValueDeserializer::new().module(&data).unwrap();
+        for name in ["x", "y"] {
+            assert_eq!(
+                module.get(name).unwrap().value().to_repr(),
+                copy.get(name).unwrap().value().to_repr()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let module = frozen_module("def f(): pass\nx = []\nx.append(x)\n");
+        let err = ModuleData::from_module(&module).unwrap_err();
+        assert!(format!("{err:#}").contains("type `function`"), "{err:#}");
+        let err = ValueData::from_value(module.get("x").unwrap().value()).unwrap_err();
+        assert!(err.to_string().contains("contains itself"), "{err}");
+    }
+
+    #[derive(
+        ProvidesStaticType,
+        derive_more::Display,
+        Debug,
+        NoSerialize,
+        Allocative
+    )]
+    #[display("Point({})", _0)]
+    struct Point(i32);
+
+    starlark_simple_value!(Point);
+
+    #[starlark_value(type = "Point")]
+    impl<'v> StarlarkValue<'v> for Point {
+        fn provide(&'v self, demand: &mut Demand<'_, 'v>) {
+            demand.provide_value::<&dyn ValueSerialize>(self);
+        }
+    }
+
+    impl ValueSerialize for Point {
+        fn kind(&self) -> &str {
+            "Point"
+        }
+
+        fn to_data(&self) -> anyhow::Result<ValueData> {
+            Ok(ValueData::Int(self.0.into()))
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        Heap::temp(|heap| {
+            let point = heap.alloc_simple(Point(3));
+            let data = ValueData::from_value(heap.alloc(AllocList([point]))).unwrap();
+            assert!(ValueDeserializer::new().alloc(&data, heap).is_err());
+            let mut de = ValueDeserializer::new();
+            de.register("Point", |_, data, heap| match data {
+                ValueData::Int(x) => Ok(heap.alloc_simple(Point(i32::try_from(*x)?))),
+                _ => Err(anyhow::anyhow!("Expected an int")),
+            });
+            assert_eq!("[Point(3)]", de.alloc(&data, heap).unwrap().to_repr());
+        });
+    }
+}
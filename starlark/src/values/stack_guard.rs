@@ -75,7 +75,7 @@ fn inc() -> StackGuard {
 /// Check stack depth does not exceed configured max stack depth.
 fn check() -> anyhow::Result<()> {
     if unlikely(STACK_DEPTH.with(|stack_depth| stack_depth.get()) >= MAX_RECURSION) {
-        return Err(ControlError::TooManyRecursionLevel.into());
+        return Err(crate::Error::from(ControlError::TooManyRecursionLevel).into_anyhow());
     }
     Ok(())
 }
@@ -37,6 +37,8 @@ use std::sync::atomic::AtomicUsize;
 use either::Either;
 use hashbrown::HashTable;
 use starlark_map::Hashed;
+use starlark_map::InlineStorage;
+use starlark_map::StarlarkHashValue;
 use starlark_map::small_set::SmallSet;
 
 use crate::collections::SmallMap;
@@ -86,7 +88,9 @@ unsafe impl<'v, T: Trace<'v>> Trace<'v> for HashTable<T> {
     }
 }
 
-unsafe impl<'v, K: Trace<'v>, V: Trace<'v>> Trace<'v> for SmallMap<K, V> {
+unsafe impl<'v, K: Trace<'v>, V: Trace<'v>, I: InlineStorage<(K, V), StarlarkHashValue>> Trace<'v>
+    for SmallMap<K, V, I>
+{
     fn trace(&mut self, tracer: &Tracer<'v>) {
         for (k, v) in self.iter_mut_unchecked() {
             k.trace(tracer);
@@ -394,9 +394,7 @@ pub trait StarlarkValue<'v>:
             let _ = hasher;
             Ok(())
         } else {
-            Err(crate::Error::new_other(ControlError::NotHashableValue(
-                Self::TYPE.to_owned(),
-            )))
+            Err(ControlError::NotHashableValue(Self::TYPE.to_owned()).into())
         }
     }
 
@@ -447,6 +445,13 @@ pub trait StarlarkValue<'v>:
         ValueError::unsupported(self, "call()")
     }
 
+    /// Calling this value gives the same result every time it is called with the same
+    /// arguments, on every run. Only such values can be called by a hermetic evaluator,
+    /// see [`Evaluator::set_hermetic`](crate::eval::Evaluator::set_hermetic).
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+
     /// Return the result of `a[index]` if `a` is indexable.
     fn at(&self, index: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
         ValueError::unsupported_with(self, "[]", index)
@@ -907,4 +912,15 @@ pub trait StarlarkValue<'v>:
     fn try_freeze_directly(&self, _freezer: &Freezer<'_>) -> Option<FreezeResult<FrozenValue>> {
         None
     }
+
+    /// Called when the module owning this value is frozen, before the value itself is,
+    /// for example to check its invariants. An error fails the
+    /// [`freeze`](crate::environment::Module::freeze) of the module.
+    ///
+    /// Values contained in `self` may already have been frozen, so they must not be accessed.
+    /// Dropping caches or converting the representation belongs in [`Freeze::freeze`].
+    #[starlark_internal_vtable(skip)]
+    fn on_freeze(&self) -> crate::Result<()> {
+        Ok(())
+    }
 }
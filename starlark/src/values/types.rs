@@ -19,8 +19,12 @@ pub mod any;
 pub mod any_array;
 pub mod any_complex;
 pub mod array;
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod bigint;
 pub mod bool;
+pub mod bytes;
+pub mod decimal;
 pub mod dict;
 pub(crate) mod ellipsis;
 pub mod enumeration;
@@ -31,8 +35,12 @@ pub(crate) mod known_methods;
 pub mod list;
 pub mod list_or_tuple;
 pub mod namespace;
+pub mod native_iterator;
 pub mod none;
 pub(crate) mod num;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod provider;
 pub mod range;
 pub mod record;
 pub mod set;
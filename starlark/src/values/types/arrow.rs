@@ -0,0 +1,48 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Arrow arrays and record batches as Starlark values, available with the `arrow` feature.
+//!
+//! Values wrap the Arrow data without copying it: indexing and iteration convert only
+//! the elements which are accessed. This lets a host expose a batch to user code, for
+//! example to filter rows, without building a list of dicts:
+//!
+//! ```ignore
+//! module.set("batch", module.heap().alloc(ArrowRecordBatch::new(batch)));
+//! ```
+//!
+//! ```python
+//! def keep(row):
+//!     return row.status == "ok" and row.latency_ms < 100
+//!
+//! kept = [i for i, row in enumerate(batch) if keep(row)]
+//! ```
+//!
+//! Indexing a batch with a column name gives an [`ArrowArray`], and with an int gives an
+//! [`ArrowRow`], whose columns are read as attributes or by name, like `row["status"]`.
+//!
+//! Nulls, booleans, ints, floats and strings convert to the matching Starlark values.
+//! Elements of list arrays are [`ArrowArray`]s, and elements of any other type are
+//! one-element [`ArrowArray`]s.
+
+pub(crate) mod array;
+pub(crate) mod convert;
+pub(crate) mod record_batch;
+
+pub use crate::values::types::arrow::array::ArrowArray;
+pub use crate::values::types::arrow::record_batch::ArrowRecordBatch;
+pub use crate::values::types::arrow::record_batch::ArrowRow;
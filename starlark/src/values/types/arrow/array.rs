@@ -0,0 +1,100 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use arrow_array::Array;
+use arrow_array::ArrayRef;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::starlark_simple_value;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::index::convert_index;
+use crate::values::types::arrow::convert::element_to_starlark;
+
+/// An Arrow array, whose elements are converted to Starlark values when accessed.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct ArrowArray {
+    #[allocative(skip)]
+    array: ArrayRef,
+}
+
+starlark_simple_value!(ArrowArray);
+
+impl ArrowArray {
+    /// The result of calling `type()` on an array.
+    pub const TYPE: &'static str = "arrow_array";
+
+    /// Wrap an array.
+    pub fn new(array: ArrayRef) -> ArrowArray {
+        ArrowArray { array }
+    }
+
+    /// The underlying array.
+    pub fn array(&self) -> &ArrayRef {
+        &self.array
+    }
+}
+
+impl Display for ArrowArray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "arrow_array(type={}, len={})",
+            self.array.data_type(),
+            self.array.len()
+        )
+    }
+}
+
+#[starlark_value(type = ArrowArray::TYPE)]
+impl<'v> StarlarkValue<'v> for ArrowArray {
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.array.len() as i32)
+    }
+
+    fn at(&self, index: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        let index = convert_index(index, self.length()?)?;
+        Ok(element_to_starlark(&self.array, index as usize, heap))
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        let rem = self.array.len().saturating_sub(index);
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, heap: Heap<'v>) -> Option<Value<'v>> {
+        if index < self.array.len() {
+            Some(element_to_starlark(&self.array, index, heap))
+        } else {
+            None
+        }
+    }
+
+    unsafe fn iter_stop(&self) {}
+}
@@ -0,0 +1,64 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion of Arrow array elements to Starlark values.
+
+use arrow_array::Array;
+use arrow_array::ArrayRef;
+use arrow_array::cast::AsArray;
+use arrow_array::types::Float32Type;
+use arrow_array::types::Float64Type;
+use arrow_array::types::Int8Type;
+use arrow_array::types::Int16Type;
+use arrow_array::types::Int32Type;
+use arrow_array::types::Int64Type;
+use arrow_array::types::UInt8Type;
+use arrow_array::types::UInt16Type;
+use arrow_array::types::UInt32Type;
+use arrow_array::types::UInt64Type;
+use arrow_schema::DataType;
+
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::types::arrow::array::ArrowArray;
+
+/// Convert the element at `index`, which must be in bounds.
+pub(crate) fn element_to_starlark<'v>(array: &ArrayRef, index: usize, heap: Heap<'v>) -> Value<'v> {
+    if array.is_null(index) {
+        return Value::new_none();
+    }
+    match array.data_type() {
+        DataType::Null => Value::new_none(),
+        DataType::Boolean => Value::new_bool(array.as_boolean().value(index)),
+        DataType::Int8 => heap.alloc(i32::from(array.as_primitive::<Int8Type>().value(index))),
+        DataType::Int16 => heap.alloc(i32::from(array.as_primitive::<Int16Type>().value(index))),
+        DataType::Int32 => heap.alloc(array.as_primitive::<Int32Type>().value(index)),
+        DataType::Int64 => heap.alloc(array.as_primitive::<Int64Type>().value(index)),
+        DataType::UInt8 => heap.alloc(i32::from(array.as_primitive::<UInt8Type>().value(index))),
+        DataType::UInt16 => heap.alloc(i32::from(array.as_primitive::<UInt16Type>().value(index))),
+        DataType::UInt32 => heap.alloc(array.as_primitive::<UInt32Type>().value(index)),
+        DataType::UInt64 => heap.alloc(array.as_primitive::<UInt64Type>().value(index)),
+        DataType::Float32 => heap.alloc(array.as_primitive::<Float32Type>().value(index) as f64),
+        DataType::Float64 => heap.alloc(array.as_primitive::<Float64Type>().value(index)),
+        DataType::Utf8 => heap.alloc(array.as_string::<i32>().value(index)),
+        DataType::LargeUtf8 => heap.alloc(array.as_string::<i64>().value(index)),
+        DataType::Utf8View => heap.alloc(array.as_string_view().value(index)),
+        DataType::List(_) => heap.alloc(ArrowArray::new(array.as_list::<i32>().value(index))),
+        DataType::LargeList(_) => heap.alloc(ArrowArray::new(array.as_list::<i64>().value(index))),
+        _ => heap.alloc(ArrowArray::new(array.slice(index, 1))),
+    }
+}
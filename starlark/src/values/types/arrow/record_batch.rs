@@ -0,0 +1,252 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use arrow_array::RecordBatch;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::starlark_simple_value;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::index::convert_index;
+use crate::values::types::arrow::array::ArrowArray;
+use crate::values::types::arrow::convert::element_to_starlark;
+
+#[derive(Debug, thiserror::Error)]
+enum ArrowError {
+    #[error("Record batch has no column `{0}`")]
+    UnknownColumn(String),
+}
+
+fn column_index(batch: &RecordBatch, name: &str) -> crate::Result<usize> {
+    batch
+        .schema_ref()
+        .index_of(name)
+        .map_err(|_| crate::Error::new_other(ArrowError::UnknownColumn(name.to_owned())))
+}
+
+fn column_names(batch: &RecordBatch) -> Vec<String> {
+    batch
+        .schema_ref()
+        .fields()
+        .iter()
+        .map(|field| field.name().clone())
+        .collect()
+}
+
+/// An Arrow record batch. Indexing with a column name gives the column as an
+/// [`ArrowArray`], and with an int gives an [`ArrowRow`]. Iteration is over the rows.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct ArrowRecordBatch {
+    #[allocative(skip)]
+    batch: RecordBatch,
+}
+
+starlark_simple_value!(ArrowRecordBatch);
+
+impl ArrowRecordBatch {
+    /// The result of calling `type()` on a record batch.
+    pub const TYPE: &'static str = "record_batch";
+
+    /// Wrap a record batch.
+    pub fn new(batch: RecordBatch) -> ArrowRecordBatch {
+        ArrowRecordBatch { batch }
+    }
+
+    /// The underlying record batch.
+    pub fn batch(&self) -> &RecordBatch {
+        &self.batch
+    }
+
+    fn row(&self, row: usize) -> ArrowRow {
+        ArrowRow {
+            batch: self.batch.clone(),
+            row,
+        }
+    }
+}
+
+impl Display for ArrowRecordBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "record_batch(rows={}, columns=[{}])",
+            self.batch.num_rows(),
+            column_names(&self.batch).join(", ")
+        )
+    }
+}
+
+#[starlark_value(type = ArrowRecordBatch::TYPE)]
+impl<'v> StarlarkValue<'v> for ArrowRecordBatch {
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.batch.num_rows() as i32)
+    }
+
+    fn at(&self, index: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        if let Some(name) = index.unpack_str() {
+            let column = column_index(&self.batch, name)?;
+            return Ok(heap.alloc(ArrowArray::new(self.batch.column(column).clone())));
+        }
+        let row = convert_index(index, self.length()?)?;
+        Ok(heap.alloc(self.row(row as usize)))
+    }
+
+    fn get_attr(&self, attribute: &str, heap: Heap<'v>) -> Option<Value<'v>> {
+        match attribute {
+            "column_names" => Some(heap.alloc(column_names(&self.batch))),
+            _ => None,
+        }
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        vec!["column_names".to_owned()]
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        let rem = self.batch.num_rows().saturating_sub(index);
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, heap: Heap<'v>) -> Option<Value<'v>> {
+        if index < self.batch.num_rows() {
+            Some(heap.alloc(self.row(index)))
+        } else {
+            None
+        }
+    }
+
+    unsafe fn iter_stop(&self) {}
+}
+
+/// A row of an [`ArrowRecordBatch`], sharing its data. Columns are read as attributes,
+/// or by indexing with the column name.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct ArrowRow {
+    #[allocative(skip)]
+    batch: RecordBatch,
+    row: usize,
+}
+
+starlark_simple_value!(ArrowRow);
+
+impl ArrowRow {
+    /// The result of calling `type()` on a row.
+    pub const TYPE: &'static str = "record_batch_row";
+
+    /// The index of the row in its batch.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+}
+
+impl Display for ArrowRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record_batch_row(")?;
+        Heap::temp(|heap| {
+            for (i, name) in column_names(&self.batch).iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                let value = element_to_starlark(self.batch.column(i), self.row, heap);
+                write!(f, "{}={}", name, value.to_repr())?;
+            }
+            Ok::<(), fmt::Error>(())
+        })?;
+        write!(f, ")")
+    }
+}
+
+#[starlark_value(type = ArrowRow::TYPE)]
+impl<'v> StarlarkValue<'v> for ArrowRow {
+    fn at(&self, index: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        let Some(name) = index.unpack_str() else {
+            return ValueError::unsupported_with(self, "[]", index);
+        };
+        let column = column_index(&self.batch, name)?;
+        Ok(element_to_starlark(
+            self.batch.column(column),
+            self.row,
+            heap,
+        ))
+    }
+
+    fn get_attr(&self, attribute: &str, heap: Heap<'v>) -> Option<Value<'v>> {
+        let column = self.batch.schema_ref().index_of(attribute).ok()?;
+        Some(element_to_starlark(
+            self.batch.column(column),
+            self.row,
+            heap,
+        ))
+    }
+
+    fn has_attr(&self, attribute: &str, _heap: Heap<'v>) -> bool {
+        self.batch.schema_ref().index_of(attribute).is_ok()
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        column_names(&self.batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::ArrayRef;
+    use arrow_array::Int64Array;
+    use arrow_array::RecordBatch;
+    use arrow_array::StringArray;
+
+    use crate::assert::Assert;
+    use crate::values::types::arrow::ArrowRecordBatch;
+
+    fn batch() -> ArrowRecordBatch {
+        let ids: ArrayRef = Arc::new(Int64Array::from(vec![Some(1), Some(2), None]));
+        let names: ArrayRef = Arc::new(StringArray::from(vec!["a", "b", "c"]));
+        ArrowRecordBatch::new(RecordBatch::try_from_iter([("id", ids), ("name", names)]).unwrap())
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let mut a = Assert::new();
+        a.globals_add(|builder| builder.set("batch", batch()));
+        a.eq("3", "len(batch)");
+        a.eq("['id', 'name']", "batch.column_names");
+        a.eq("[1, 2, None]", "list(batch['id'])");
+        a.eq("'b'", "batch[1].name");
+        a.eq("'c'", "batch[-1]['name']");
+        a.eq(
+            "['a']",
+            "[row.name for row in batch if row.id != None and row.id < 2]",
+        );
+        a.eq("'record_batch_row(id=1, name=\"a\")'", "repr(batch[0])");
+        a.fail("batch['missing']", "no column `missing`");
+    }
+}
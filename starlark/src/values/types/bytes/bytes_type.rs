@@ -0,0 +1,248 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hasher;
+
+use allocative::Allocative;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::typing::Ty;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::index::apply_slice;
+use crate::values::index::convert_index;
+
+/// An immutable sequence of bytes, the result of a `b"..."` literal or `bytes()`.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative
+)]
+pub struct StarlarkBytes(Box<[u8]>);
+
+starlark_simple_value!(StarlarkBytes);
+
+impl StarlarkBytes {
+    /// The result of calling `type()` on bytes.
+    pub const TYPE: &'static str = "bytes";
+
+    /// Create a new [`StarlarkBytes`].
+    pub fn new(bytes: impl Into<Box<[u8]>>) -> StarlarkBytes {
+        StarlarkBytes(bytes.into())
+    }
+
+    /// The underlying bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Display for StarlarkBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        for b in self.as_bytes() {
+            match b {
+                b'\n' => f.write_str("\\n")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\r' => f.write_str("\\r")?,
+                b'"' => f.write_str("\\\"")?,
+                b'\\' => f.write_str("\\\\")?,
+                0x20..=0x7E => write!(f, "{}", *b as char)?,
+                x => write!(f, "\\x{x:02x}")?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+#[starlark_value(type = StarlarkBytes::TYPE)]
+impl<'v> StarlarkValue<'v> for StarlarkBytes {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(crate::values::types::bytes::methods::bytes_methods)
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.0.len() as i32)
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        hasher.write(&self.0);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self == other),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self.0.cmp(&other.0)),
+            None => ValueError::unsupported_with(self, "cmp()", other),
+        }
+    }
+
+    /// Indexing gives the byte as an int, rather than a bytes of length one.
+    fn at(&self, index: Value, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        let i = convert_index(index, self.0.len() as i32)? as usize;
+        Ok(heap.alloc(self.0[i] as i32))
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: Heap<'v>,
+    ) -> crate::Result<Value<'v>> {
+        let res = apply_slice(&self.0, start, stop, stride)?;
+        Ok(heap.alloc(StarlarkBytes::new(res)))
+    }
+
+    /// Either a byte, given as an int, or a subsequence, given as bytes.
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        if let Some(other) = StarlarkBytes::from_value(other) {
+            let needle = other.as_bytes();
+            return Ok(needle.is_empty() || self.0.windows(needle.len()).any(|w| w == needle));
+        }
+        match i32::unpack_value(other)? {
+            Some(b) => Ok(u8::try_from(b).is_ok_and(|b| self.0.contains(&b))),
+            None => ValueError::unsupported_with(self, "in", other),
+        }
+    }
+
+    fn add(&self, other: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        let other = StarlarkBytes::from_value(other)?;
+        if let Err(e) = heap.check_string_size(self.0.len() + other.0.len()) {
+            return Some(Err(e));
+        }
+        Some(Ok(
+            heap.alloc(StarlarkBytes::new([&*self.0, &*other.0].concat()))
+        ))
+    }
+
+    fn mul(&self, other: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        let n = match i32::unpack_value(other) {
+            Ok(Some(n)) => n,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Err(e) = heap.check_string_size(self.0.len().saturating_mul(n.max(0) as usize)) {
+            return Some(Err(e));
+        }
+        Some(Ok(
+            heap.alloc(StarlarkBytes::new(self.0.repeat(n.max(0) as usize)))
+        ))
+    }
+
+    fn rmul(&self, lhs: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        self.mul(lhs, heap)
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        Ty::starlark_value::<StarlarkBytes>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_literals() {
+        assert::all_true(
+            r#"
+len(b"abc") == 3
+b"abc"[0] == 97
+b"abc"[-1] == 99
+b"\x00\xff"[1] == 255
+b"\377" == b"\xff"
+rb"a\n" == b"a\\n"
+b"é" == bytes("é")
+b"abc"[1:] == b"bc"
+b"abc"[::-1] == b"cba"
+b"ab" + b"c" == b"abc"
+b"ab" * 2 == b"abab"
+b"a" < b"b"
+b"bc" in b"abc"
+98 in b"abc"
+not (300 in b"abc")
+b"abc" != "abc"
+type(b"") == "bytes"
+not b""
+"#,
+        );
+    }
+
+    #[test]
+    fn test_repr() {
+        assert::eq("'b\"a\\\\n\\\\x00\\\\xff\"'", r#"repr(b"a\n\x00\xff")"#);
+        assert::eq("'b\"abc\"'", r#"str([b"abc"])[1:-1]"#);
+    }
+
+    #[test]
+    fn test_hash() {
+        assert::is_true(r#"{b"a": 1}[b"a"] == 1"#);
+    }
+
+    #[test]
+    fn test_types() {
+        assert::pass(
+            r#"
+def f(x: bytes) -> int:
+    return x[0]
+
+f(b"x")
+"#,
+        );
+        assert::fail(
+            r#"
+def g(x: str) -> str:
+    return x
+
+# The typechecker checks `def` bodies, not top-level statements.
+def h():
+    g(b"x")
+"#,
+            "Expected type `str` but got `bytes`",
+        );
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::bytes::StarlarkBytes;
+
+#[derive(Debug, thiserror::Error)]
+enum BytesError {
+    #[error("bytes() element {0} is not in the range 0 to 255")]
+    OutOfRange(i32),
+    #[error("bytes() expects a string, bytes or an iterable of ints, got an element of type `{0}`")]
+    NotAnInt(String),
+}
+
+#[starlark_module]
+pub(crate) fn register_bytes(globals: &mut GlobalsBuilder) {
+    /// [bytes](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes
+    /// ): convert a value to bytes.
+    ///
+    /// `bytes(x)` accepts a string, which is encoded as UTF-8, bytes, which are
+    /// returned unchanged, or an iterable of ints, each of which must be between 0
+    /// and 255.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// bytes("hello") == b"hello"
+    /// bytes([104, 105]) == b"hi"
+    /// bytes("日本") == b"\xe6\x97\xa5\xe6\x9c\xac"
+    /// # "#);
+    /// ```
+    #[starlark(as_type = StarlarkBytes, speculative_exec_safe)]
+    fn bytes<'v>(
+        #[starlark(require = pos)] x: Value<'v>,
+        heap: Heap<'v>,
+    ) -> starlark::Result<StarlarkBytes> {
+        if let Some(s) = x.unpack_str() {
+            return Ok(StarlarkBytes::new(s.as_bytes()));
+        }
+        if let Some(b) = StarlarkBytes::from_value(x) {
+            return Ok(b.clone());
+        }
+        let mut res = Vec::new();
+        for v in x.iterate(heap)? {
+            let Some(i) = i32::unpack_value(v)? else {
+                return Err(starlark::Error::new_other(BytesError::NotAnInt(
+                    v.get_type().to_owned(),
+                )));
+            };
+            match u8::try_from(i) {
+                Ok(b) => res.push(b),
+                Err(_) => return Err(starlark::Error::new_other(BytesError::OutOfRange(i))),
+            }
+        }
+        Ok(StarlarkBytes::new(res))
+    }
+}
@@ -0,0 +1,41 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `bytes` type.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::MethodsBuilder;
+use crate::values::bytes::StarlarkBytes;
+
+#[starlark_module]
+pub(crate) fn bytes_methods(builder: &mut MethodsBuilder) {
+    /// [bytes.elems](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes·elems
+    /// ): returns the bytes as a list of ints.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// b"AB".elems() == [65, 66]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn elems(this: &StarlarkBytes) -> anyhow::Result<Vec<i32>> {
+        Ok(this.as_bytes().iter().map(|b| *b as i32).collect())
+    }
+}
@@ -0,0 +1,500 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hasher;
+
+use allocative::Allocative;
+use num_bigint::BigInt;
+use num_bigint::Sign;
+use num_traits::Signed;
+use num_traits::Zero;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TypingBinOp;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::types::int::int_or_big::StarlarkIntRef;
+
+/// Digits kept after the decimal point by `/` when the quotient doesn't terminate.
+const DIV_PLACES: u32 = 28;
+
+/// Largest exponent accepted when parsing, so `decimal("1e1000000000")` can't exhaust memory.
+const MAX_EXPONENT: i64 = 1000;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DecimalError {
+    #[error("Invalid decimal literal `{0}`")]
+    InvalidLiteral(String),
+    #[error("Exponent of decimal literal `{0}` is too large")]
+    ExponentTooLarge(String),
+    #[error("Number of decimal places must not be negative, got {0}")]
+    NegativePlaces(i32),
+    #[error(
+        "decimal() expects a string, an int or a decimal, got a value of type `{0}` (write floats as strings, like `decimal(\"0.1\")`)"
+    )]
+    NotConvertible(String),
+}
+
+/// An exact decimal number, constructed with `decimal()`.
+///
+/// The value is `digits / 10**scale`. Unlike floats, decimals represent numbers like
+/// `0.1` exactly, and keep the number of places they were written with, so
+/// `decimal("1.10")` prints as `1.10`, but is equal to `decimal("1.1")`.
+#[derive(Clone, Debug, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct StarlarkDecimal {
+    digits: BigInt,
+    scale: u32,
+}
+
+starlark_simple_value!(StarlarkDecimal);
+
+fn pow10(n: u32) -> BigInt {
+    BigInt::from(10).pow(n)
+}
+
+/// `n / d` rounded to the nearest integer, ties to even.
+fn div_round_half_even(n: &BigInt, d: &BigInt) -> BigInt {
+    let q = n / d;
+    let r = n % d;
+    if r.is_zero() {
+        return q;
+    }
+    let away = match (r.abs() * BigInt::from(2)).cmp(&d.abs()) {
+        Ordering::Less => false,
+        Ordering::Equal => !(&q % 2u32).is_zero(),
+        Ordering::Greater => true,
+    };
+    if !away {
+        q
+    } else if (n.sign() == Sign::Minus) == (d.sign() == Sign::Minus) {
+        q + 1
+    } else {
+        q - 1
+    }
+}
+
+/// `n / d` rounded towards negative infinity, like `//` on ints.
+fn div_floor(n: &BigInt, d: &BigInt) -> BigInt {
+    let q = n / d;
+    if !(n % d).is_zero() && (n.sign() == Sign::Minus) != (d.sign() == Sign::Minus) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+impl StarlarkDecimal {
+    /// The result of calling `type()` on a decimal.
+    pub const TYPE: &'static str = "decimal";
+
+    /// The decimal `digits / 10**scale`.
+    pub fn new(digits: BigInt, scale: u32) -> StarlarkDecimal {
+        StarlarkDecimal { digits, scale }
+    }
+
+    /// The digits of the decimal, without the decimal point.
+    pub fn digits(&self) -> &BigInt {
+        &self.digits
+    }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub(crate) fn from_int(i: StarlarkIntRef) -> StarlarkDecimal {
+        StarlarkDecimal::new(i.to_big(), 0)
+    }
+
+    /// Parse a decimal literal, like `-12.50` or `1.5e3`.
+    pub(crate) fn parse(s: &str) -> Result<StarlarkDecimal, DecimalError> {
+        let invalid = || DecimalError::InvalidLiteral(s.to_owned());
+        let t = s.trim();
+        let (mantissa, exponent) = match t.find(['e', 'E']) {
+            Some(i) => (&t[..i], t[i + 1..].parse::<i64>().map_err(|_| invalid())?),
+            None => (t, 0),
+        };
+        if exponent.abs() > MAX_EXPONENT {
+            return Err(DecimalError::ExponentTooLarge(s.to_owned()));
+        }
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(m) => (true, m),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int_part.is_empty() && frac_part.is_empty()
+            || !int_part
+                .bytes()
+                .chain(frac_part.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let mut digits: BigInt = format!("{int_part}{frac_part}")
+            .parse()
+            .map_err(|_| invalid())?;
+        if negative {
+            digits = -digits;
+        }
+        let scale = frac_part.len() as i64 - exponent;
+        if scale >= 0 {
+            let scale = u32::try_from(scale).map_err(|_| invalid())?;
+            Ok(StarlarkDecimal::new(digits, scale))
+        } else {
+            Ok(StarlarkDecimal::new(digits * pow10((-scale) as u32), 0))
+        }
+    }
+
+    /// Ints are converted to decimals when used as the other operand.
+    fn unpack_operand<'a>(value: Value<'a>) -> Option<Cow<'a, StarlarkDecimal>> {
+        if let Some(d) = StarlarkDecimal::from_value(value) {
+            return Some(Cow::Borrowed(d));
+        }
+        StarlarkIntRef::unpack(value).map(|i| Cow::Owned(StarlarkDecimal::from_int(i)))
+    }
+
+    /// The digits at a larger or equal `scale`.
+    fn digits_at(&self, scale: u32) -> BigInt {
+        &self.digits * pow10(scale - self.scale)
+    }
+
+    /// Both digits at the larger of the two scales, and that scale.
+    fn align(&self, other: &StarlarkDecimal) -> (BigInt, BigInt, u32) {
+        let scale = self.scale.max(other.scale);
+        (self.digits_at(scale), other.digits_at(scale), scale)
+    }
+
+    /// Drop trailing zeros after the decimal point, keeping at least `min_scale` places.
+    fn trim(mut self, min_scale: u32) -> StarlarkDecimal {
+        let ten = BigInt::from(10);
+        while self.scale > min_scale && (&self.digits % &ten).is_zero() {
+            self.digits /= &ten;
+            self.scale -= 1;
+        }
+        self
+    }
+
+    fn cmp_decimal(&self, other: &StarlarkDecimal) -> Ordering {
+        let (a, b, _) = self.align(other);
+        a.cmp(&b)
+    }
+
+    fn add_decimal(&self, other: &StarlarkDecimal) -> StarlarkDecimal {
+        let (a, b, scale) = self.align(other);
+        StarlarkDecimal::new(a + b, scale)
+    }
+
+    fn sub_decimal(&self, other: &StarlarkDecimal) -> StarlarkDecimal {
+        let (a, b, scale) = self.align(other);
+        StarlarkDecimal::new(a - b, scale)
+    }
+
+    fn mul_decimal(&self, other: &StarlarkDecimal) -> StarlarkDecimal {
+        StarlarkDecimal::new(&self.digits * &other.digits, self.scale + other.scale)
+    }
+
+    /// Exact if the quotient fits in [`DIV_PLACES`] places, otherwise rounded half to even.
+    fn div_decimal(&self, other: &StarlarkDecimal) -> crate::Result<StarlarkDecimal> {
+        if other.digits.is_zero() {
+            return Err(ValueError::DivisionByZero.into());
+        }
+        let scale = DIV_PLACES.max(self.scale);
+        let n = &self.digits * pow10(scale + other.scale - self.scale);
+        let q = div_round_half_even(&n, &other.digits);
+        Ok(StarlarkDecimal::new(q, scale).trim(self.scale.saturating_sub(other.scale)))
+    }
+
+    fn floor_div_decimal(&self, other: &StarlarkDecimal) -> crate::Result<StarlarkDecimal> {
+        if other.digits.is_zero() {
+            return Err(ValueError::DivisionByZero.into());
+        }
+        let (a, b, _) = self.align(other);
+        Ok(StarlarkDecimal::new(div_floor(&a, &b), 0))
+    }
+
+    /// The remainder has the sign of `other`, like `%` on ints.
+    fn percent_decimal(&self, other: &StarlarkDecimal) -> crate::Result<StarlarkDecimal> {
+        if other.digits.is_zero() {
+            return Err(ValueError::DivisionByZero.into());
+        }
+        let (a, b, scale) = self.align(other);
+        let r = &a - &b * div_floor(&a, &b);
+        Ok(StarlarkDecimal::new(r, scale))
+    }
+
+    /// Round to exactly `places` digits after the decimal point, ties to even.
+    pub(crate) fn round(&self, places: i32) -> Result<StarlarkDecimal, DecimalError> {
+        let places = u32::try_from(places).map_err(|_| DecimalError::NegativePlaces(places))?;
+        if places >= self.scale {
+            Ok(StarlarkDecimal::new(self.digits_at(places), places))
+        } else {
+            let q = div_round_half_even(&self.digits, &pow10(self.scale - places));
+            Ok(StarlarkDecimal::new(q, places))
+        }
+    }
+}
+
+impl Display for StarlarkDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_negative() {
+            f.write_str("-")?;
+        }
+        let abs = self.digits.abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            f.write_str(&abs)
+        } else if abs.len() > scale {
+            let (int_part, frac_part) = abs.split_at(abs.len() - scale);
+            write!(f, "{int_part}.{frac_part}")
+        } else {
+            write!(f, "0.{}{abs}", "0".repeat(scale - abs.len()))
+        }
+    }
+}
+
+#[starlark_value(type = StarlarkDecimal::TYPE)]
+impl<'v> StarlarkValue<'v> for StarlarkDecimal {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(crate::values::types::decimal::methods::decimal_methods)
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.digits.is_zero()
+    }
+
+    /// Equal decimals hash the same whatever their number of places.
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        let trimmed = self.clone().trim(0);
+        hasher.write(&trimmed.digits.to_signed_bytes_le());
+        hasher.write_u32(trimmed.scale);
+        Ok(())
+    }
+
+    /// Only equal to other decimals, so hashing stays consistent with ints.
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match StarlarkDecimal::from_value(other) {
+            Some(other) => Ok(self.cmp_decimal(other) == Ordering::Equal),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
+        match StarlarkDecimal::unpack_operand(other) {
+            Some(other) => Ok(self.cmp_decimal(&other)),
+            None => ValueError::unsupported_with(self, "compare", other),
+        }
+    }
+
+    fn plus(&self, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        Ok(heap.alloc(self.clone()))
+    }
+
+    fn minus(&self, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        Ok(heap.alloc(StarlarkDecimal::new(-&self.digits, self.scale)))
+    }
+
+    fn add(&self, other: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        let other = StarlarkDecimal::unpack_operand(other)?;
+        Some(Ok(heap.alloc(self.add_decimal(&other))))
+    }
+
+    fn radd(&self, lhs: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        self.add(lhs, heap)
+    }
+
+    fn sub(&self, other: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        match StarlarkDecimal::unpack_operand(other) {
+            Some(other) => Ok(heap.alloc(self.sub_decimal(&other))),
+            None => ValueError::unsupported_with(self, "-", other),
+        }
+    }
+
+    fn mul(&self, other: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        let other = StarlarkDecimal::unpack_operand(other)?;
+        Some(Ok(heap.alloc(self.mul_decimal(&other))))
+    }
+
+    fn rmul(&self, lhs: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
+        self.mul(lhs, heap)
+    }
+
+    fn div(&self, other: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        match StarlarkDecimal::unpack_operand(other) {
+            Some(other) => Ok(heap.alloc(self.div_decimal(&other)?)),
+            None => ValueError::unsupported_with(self, "/", other),
+        }
+    }
+
+    fn floor_div(&self, other: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        match StarlarkDecimal::unpack_operand(other) {
+            Some(other) => Ok(heap.alloc(self.floor_div_decimal(&other)?)),
+            None => ValueError::unsupported_with(self, "//", other),
+        }
+    }
+
+    fn percent(&self, other: Value<'v>, heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        match StarlarkDecimal::unpack_operand(other) {
+            Some(other) => Ok(heap.alloc(self.percent_decimal(&other)?)),
+            None => ValueError::unsupported_with(self, "%", other),
+        }
+    }
+
+    fn bin_op_ty(op: TypingBinOp, rhs: &TyBasic) -> Option<Ty> {
+        if rhs != &TyBasic::Any
+            && rhs != &TyBasic::int()
+            && rhs != &TyBasic::starlark_value::<StarlarkDecimal>()
+        {
+            return None;
+        }
+        match op {
+            TypingBinOp::Add
+            | TypingBinOp::Sub
+            | TypingBinOp::Mul
+            | TypingBinOp::Div
+            | TypingBinOp::FloorDiv
+            | TypingBinOp::Percent => Some(Ty::starlark_value::<StarlarkDecimal>()),
+            TypingBinOp::Less => Some(Ty::bool()),
+            _ => None,
+        }
+    }
+
+    fn rbin_op_ty(lhs: &TyBasic, op: TypingBinOp) -> Option<Ty> {
+        match op {
+            TypingBinOp::Add | TypingBinOp::Mul if lhs == &TyBasic::int() => {
+                Some(Ty::starlark_value::<StarlarkDecimal>())
+            }
+            _ => None,
+        }
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        Ty::starlark_value::<StarlarkDecimal>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_arithmetic() {
+        assert::all_true(
+            r#"
+decimal("0.1") + decimal("0.2") == decimal("0.3")
+decimal("1.10") == decimal("1.1")
+decimal("2.5") * 2 == decimal("5")
+2 * decimal("2.5") == decimal("5.0")
+1 + decimal("0.5") == decimal("1.5")
+decimal("1") - decimal("0.01") == decimal("0.99")
+decimal("1") / 4 == decimal("0.25")
+decimal(1) / 3 == decimal("0.3333333333333333333333333333")
+decimal("7") // 2 == decimal("3")
+decimal("-7") // 2 == decimal("-4")
+decimal("-7.5") % 2 == decimal("0.5")
+-decimal("1.5") == decimal("-1.5")
+decimal("1.5e3") == decimal(1500)
+decimal("1") < decimal("1.01")
+decimal("2") > 1
+not decimal("0.00")
+decimal("1") != 1
+type(decimal("1")) == "decimal"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_str() {
+        assert::all_true(
+            r#"
+str(decimal("1.10")) == "1.10"
+str(decimal("-0.05")) == "-0.05"
+str(decimal("1.5") * decimal("1.5")) == "2.25"
+str(decimal("10.00") / 4) == "2.50"
+str(decimal("12.5e-3")) == "0.0125"
+str(decimal(" 42 ")) == "42"
+repr([decimal("1.0")]) == "[1.0]"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_round() {
+        assert::all_true(
+            r#"
+str(decimal("2.675").round(2)) == "2.68"
+str(decimal("2.665").round(2)) == "2.66"
+str(decimal("-2.5").round()) == "-2"
+str(decimal("1.5").round(3)) == "1.500"
+"#,
+        );
+        assert::fail(r#"decimal("1").round(-1)"#, "must not be negative");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert::fail(r#"decimal("1.2.3")"#, "Invalid decimal literal");
+        assert::fail(r#"decimal("nan")"#, "Invalid decimal literal");
+        assert::fail(r#"decimal("1e100000")"#, "too large");
+        assert::fail("decimal(0.1)", "write floats as strings");
+        assert::fail(r#"decimal("1") / 0"#, "Cannot divide by zero");
+        assert::fail(r#"decimal("1") + 1.0"#, "`decimal` and `float`");
+    }
+
+    #[test]
+    fn test_hash() {
+        assert::is_true(r#"{decimal("1.50"): 1}[decimal("1.5")] == 1"#);
+    }
+
+    #[test]
+    fn test_types() {
+        assert::pass(
+            r#"
+def total(price: decimal, count: int) -> decimal:
+    return price * count + decimal("0.50")
+
+total(decimal("1.25"), 3)
+"#,
+        );
+        assert::fail(
+            r#"
+def g(x: str) -> str:
+    return x
+
+# The typechecker checks `def` bodies, not top-level statements.
+def h():
+    g(decimal("1"))
+"#,
+            "Expected type `str` but got `decimal`",
+        );
+    }
+}
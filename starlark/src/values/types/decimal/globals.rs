@@ -0,0 +1,61 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::Value;
+use crate::values::types::decimal::StarlarkDecimal;
+use crate::values::types::decimal::decimal_type::DecimalError;
+use crate::values::types::int::int_or_big::StarlarkIntRef;
+
+#[starlark_module]
+pub(crate) fn register_decimal(globals: &mut GlobalsBuilder) {
+    /// Convert a value to an exact decimal number.
+    ///
+    /// `decimal(x)` accepts a string, like `"-12.50"` or `"1.5e3"`, an int, or a
+    /// decimal, which is returned unchanged. Floats are rejected, since they have
+    /// already been rounded, so write them as strings instead.
+    ///
+    /// Decimals support arithmetic with each other and with ints. Division keeps 28
+    /// places when the result doesn't terminate. Decimals are only equal to other
+    /// decimals, comparing by value, so `decimal("1.10") == decimal("1.1")`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// decimal("0.1") + decimal("0.2") == decimal("0.3")
+    /// str(decimal("19.99") * 3) == "59.97"
+    /// decimal(5) / 2 == decimal("2.5")
+    /// # "#);
+    /// ```
+    #[starlark(as_type = StarlarkDecimal, speculative_exec_safe)]
+    fn decimal<'v>(#[starlark(require = pos)] x: Value<'v>) -> starlark::Result<StarlarkDecimal> {
+        if let Some(s) = x.unpack_str() {
+            return StarlarkDecimal::parse(s).map_err(starlark::Error::new_other);
+        }
+        if let Some(d) = StarlarkDecimal::from_value(x) {
+            return Ok(d.clone());
+        }
+        match StarlarkIntRef::unpack(x) {
+            Some(i) => Ok(StarlarkDecimal::from_int(i)),
+            None => Err(starlark::Error::new_other(DecimalError::NotConvertible(
+                x.get_type().to_owned(),
+            ))),
+        }
+    }
+}
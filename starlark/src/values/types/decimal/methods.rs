@@ -0,0 +1,45 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `decimal` type.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::MethodsBuilder;
+use crate::values::types::decimal::StarlarkDecimal;
+
+#[starlark_module]
+pub(crate) fn decimal_methods(builder: &mut MethodsBuilder) {
+    /// Round to exactly `places` digits after the decimal point, with ties going to
+    /// the even digit.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// str(decimal("2.675").round(2)) == "2.68"
+    /// str(decimal("0.125").round(2)) == "0.12"
+    /// str(decimal("3").round(2)) == "3.00"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn round(
+        this: &StarlarkDecimal,
+        #[starlark(require = pos, default = 0)] places: i32,
+    ) -> anyhow::Result<StarlarkDecimal> {
+        Ok(this.round(places)?)
+    }
+}
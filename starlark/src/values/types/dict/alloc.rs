@@ -17,8 +17,6 @@
 
 use std::iter;
 
-use starlark_map::small_map::SmallMap;
-
 use crate::typing::Ty;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -27,6 +25,7 @@ use crate::values::FrozenValue;
 use crate::values::Heap;
 use crate::values::Value;
 use crate::values::dict::Dict;
+use crate::values::dict::value::DictMap;
 use crate::values::dict::value::FrozenDictData;
 use crate::values::layout::value::ValueLike;
 use crate::values::type_repr::StarlarkTypeRepr;
@@ -80,14 +79,14 @@ where
 {
     fn alloc_value(self, heap: Heap<'v>) -> Value<'v> {
         let iter = self.0.into_iter();
-        let mut map = SmallMap::with_capacity(iter.size_hint().0);
+        let mut content = DictMap::with_capacity_inline(iter.size_hint().0);
         for (k, v) in iter {
-            map.insert_hashed(
+            content.insert_hashed(
                 k.alloc_value(heap).get_hashed().unwrap(),
                 v.alloc_value(heap),
             );
         }
-        heap.alloc(Dict::new(map))
+        heap.alloc(Dict { content })
     }
 }
 
@@ -99,13 +98,13 @@ where
 {
     fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
         let iter = self.0.into_iter();
-        let mut map = SmallMap::with_capacity(iter.size_hint().0);
+        let mut content = DictMap::with_capacity_inline(iter.size_hint().0);
         for (k, v) in iter {
-            map.insert_hashed(
+            content.insert_hashed(
                 k.alloc_frozen_value(heap).get_hashed().unwrap(),
                 v.alloc_frozen_value(heap),
             );
         }
-        heap.alloc(FrozenDictData { content: map })
+        heap.alloc(FrozenDictData { content })
     }
 }
@@ -51,6 +51,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x == {}
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn clear(this: Value) -> anyhow::Result<NoneType> {
         let mut this = DictMut::from_value(this)?;
         this.aref.clear();
@@ -105,6 +106,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x.items() == [("one", 1), ("two", 2)]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn items<'v>(
         this: DictRef<'v>,
         heap: Heap<'v>,
@@ -166,6 +168,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// {'one': 1}.pop('four')   # error: not found
     /// # "#, "not found");
     /// ```
+    #[starlark(deterministic)]
     fn pop<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] key: Value<'v>,
@@ -218,6 +221,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// {}.popitem()   # error: empty dict
     /// # "#, "empty dict");
     /// ```
+    #[starlark(deterministic)]
     fn popitem<'v>(this: Value<'v>) -> anyhow::Result<(Value<'v>, Value<'v>)> {
         let mut this = DictMut::from_value(this)?;
 
@@ -259,6 +263,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x == {"one": 1, "two": 2, "three": 0, "four": None}
     /// # )"#)
     /// ```
+    #[starlark(deterministic)]
     fn setdefault<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] key: Value<'v>,
@@ -305,6 +310,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x == {"a": 1, "b": 2, "c": 3, "d": 4, "e": 5}
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn update<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] pairs: Option<
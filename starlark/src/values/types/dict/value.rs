@@ -33,6 +33,7 @@ use serde::Serialize;
 use starlark::register_avalue_simple_frozen;
 use starlark_derive::starlark_value;
 use starlark_map::Equivalent;
+use starlark_map::small_map::InlineEntries;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
@@ -83,12 +84,24 @@ impl<'v> Display for Dict<'v> {
     }
 }
 
+/// Storage of dict entries. Up to four entries are stored inline,
+/// so small dicts do not need an allocation besides the dict value itself.
+pub(crate) type DictMap<K, V> = SmallMap<K, V, InlineEntries<K, V, 4>>;
+
 /// Define the dict type.
-#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[derive(Clone, Trace, Debug, ProvidesStaticType, Allocative)]
 #[repr(transparent)]
 pub struct Dict<'v> {
     /// The data stored by the dictionary. The keys must all be hashable values.
-    pub(crate) content: SmallMap<Value<'v>, Value<'v>>,
+    pub(crate) content: DictMap<Value<'v>, Value<'v>>,
+}
+
+impl<'v> Default for Dict<'v> {
+    fn default() -> Self {
+        Dict {
+            content: DictMap::new_inline(),
+        }
+    }
 }
 
 impl<'v> StarlarkTypeRepr for Dict<'v> {
@@ -99,11 +112,19 @@ impl<'v> StarlarkTypeRepr for Dict<'v> {
     }
 }
 
-#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[derive(Clone, Debug, ProvidesStaticType, Allocative)]
 #[repr(transparent)]
 pub(crate) struct FrozenDictData {
     /// The data stored by the dictionary. The keys must all be hashable values.
-    pub(crate) content: SmallMap<FrozenValue, FrozenValue>,
+    pub(crate) content: DictMap<FrozenValue, FrozenValue>,
+}
+
+impl Default for FrozenDictData {
+    fn default() -> Self {
+        FrozenDictData {
+            content: DictMap::new_inline(),
+        }
+    }
 }
 
 /// Alias is used in `StarlarkDocs` derive.
@@ -113,7 +134,7 @@ pub(crate) type MutableDict<'v> = DictGen<RefCell<Dict<'v>>>;
 
 pub(crate) static VALUE_EMPTY_FROZEN_DICT: AllocStaticSimple<DictGen<FrozenDictData>> =
     AllocStaticSimple::alloc(DictGen(FrozenDictData {
-        content: SmallMap::new(),
+        content: DictMap::new_inline(),
     }));
 
 unsafe impl<'v> Coerce<Dict<'v>> for FrozenDictData {}
@@ -191,7 +212,9 @@ impl<'v> Dict<'v> {
     /// Use [`AllocDict`](crate::values::dict::AllocDict) or [`SmallMap`]
     /// to allocate a new dictionary on the heap.
     pub fn new(content: SmallMap<Value<'v>, Value<'v>>) -> Self {
-        Self { content }
+        Self {
+            content: content.into_inline(),
+        }
     }
 
     /// Number of elements in the dict.
@@ -252,7 +275,7 @@ impl<'v> Dict<'v> {
     }
 
     /// Try to coerce all keys to strings.
-    pub(crate) fn downcast_ref_key_string(&self) -> Option<&SmallMap<StringValue<'v>, Value<'v>>> {
+    pub(crate) fn downcast_ref_key_string(&self) -> Option<&DictMap<StringValue<'v>, Value<'v>>> {
         for &key in self.content.keys() {
             if unlikely(!key.is_str()) {
                 return None;
@@ -263,13 +286,13 @@ impl<'v> Dict<'v> {
         // and we just checked above that all keys are strings.
 
         fn _assert_coerce<'v>(
-            s: SmallMap<StringValue<'v>, Value<'v>>,
-        ) -> SmallMap<Value<'v>, Value<'v>> {
+            s: DictMap<StringValue<'v>, Value<'v>>,
+        ) -> DictMap<Value<'v>, Value<'v>> {
             coerce(s)
         }
 
         Some(unsafe {
-            transmute!(&SmallMap<Value, Value>, &SmallMap<StringValue, Value>, &self.content)
+            transmute!(&DictMap<Value, Value>, &DictMap<StringValue, Value>, &self.content)
         })
     }
 
@@ -316,7 +339,7 @@ impl<'v> Freeze for DictGen<RefCell<Dict<'v>>> {
 }
 
 trait DictLike<'v>: Debug + Allocative {
-    type ContentRef<'a>: Deref<Target = SmallMap<Value<'v>, Value<'v>>>
+    type ContentRef<'a>: Deref<Target = DictMap<Value<'v>, Value<'v>>>
     where
         Self: 'a,
         'v: 'a;
@@ -324,19 +347,19 @@ trait DictLike<'v>: Debug + Allocative {
     // These functions are unsafe for the same reason
     // `StarlarkValue` iterator functions are unsafe.
     unsafe fn iter_start(&self);
-    unsafe fn content_unchecked(&self) -> &SmallMap<Value<'v>, Value<'v>>;
+    unsafe fn content_unchecked(&self) -> &DictMap<Value<'v>, Value<'v>>;
     unsafe fn iter_stop(&self);
     fn set_at(&self, index: Hashed<Value<'v>>, value: Value<'v>) -> crate::Result<()>;
 }
 
 impl<'v> DictLike<'v> for RefCell<Dict<'v>> {
     type ContentRef<'a>
-        = Ref<'a, SmallMap<Value<'v>, Value<'v>>>
+        = Ref<'a, DictMap<Value<'v>, Value<'v>>>
     where
         Self: 'a,
         'v: 'a;
 
-    fn content<'a>(&'a self) -> Ref<'a, SmallMap<Value<'v>, Value<'v>>> {
+    fn content<'a>(&'a self) -> Ref<'a, DictMap<Value<'v>, Value<'v>>> {
         Ref::map(self.borrow(), |x| &x.content)
     }
 
@@ -353,7 +376,7 @@ impl<'v> DictLike<'v> for RefCell<Dict<'v>> {
     }
 
     #[inline]
-    unsafe fn content_unchecked(&self) -> &SmallMap<Value<'v>, Value<'v>> {
+    unsafe fn content_unchecked(&self) -> &DictMap<Value<'v>, Value<'v>> {
         unsafe {
             // SAFETY: this function contract is, caller must ensure that the value is borrowed.
             &self.try_borrow_unguarded().ok().unwrap_unchecked().content
@@ -366,19 +389,19 @@ impl<'v> DictLike<'v> for RefCell<Dict<'v>> {
                 xs.content.insert_hashed(index, alloc_value);
                 Ok(())
             }
-            Err(_) => Err(crate::Error::new_other(ValueError::MutationDuringIteration)),
+            Err(_) => Err(ValueError::MutationDuringIteration.into()),
         }
     }
 }
 
 impl<'v> DictLike<'v> for FrozenDictData {
     type ContentRef<'a>
-        = &'a SmallMap<Value<'v>, Value<'v>>
+        = &'a DictMap<Value<'v>, Value<'v>>
     where
         Self: 'a,
         'v: 'a;
 
-    fn content<'a>(&'a self) -> &'a SmallMap<Value<'v>, Value<'v>> {
+    fn content<'a>(&'a self) -> &'a DictMap<Value<'v>, Value<'v>> {
         coerce(&self.content)
     }
 
@@ -386,7 +409,7 @@ impl<'v> DictLike<'v> for FrozenDictData {
 
     unsafe fn iter_stop(&self) {}
 
-    unsafe fn content_unchecked(&self) -> &SmallMap<Value<'v>, Value<'v>> {
+    unsafe fn content_unchecked(&self) -> &DictMap<Value<'v>, Value<'v>> {
         coerce(&self.content)
     }
 
@@ -450,9 +473,7 @@ where
     fn at(&self, index: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
         match self.0.content().get_hashed_by_value(index.get_hashed()?) {
             Some(v) => Ok(v.to_value()),
-            None => Err(crate::Error::new_other(ValueError::KeyNotFound(
-                index.to_repr(),
-            ))),
+            None => Err(ValueError::KeyNotFound(index.to_repr()).into()),
         }
     }
 
@@ -508,7 +529,7 @@ where
         for (k, v) in rhs.iter_hashed() {
             items.insert_hashed(k, v);
         }
-        Ok(heap.alloc(Dict::new(items)))
+        Ok(heap.alloc(Dict { content: items }))
     }
 
     fn typechecker_ty(&self) -> Option<Ty> {
@@ -247,6 +247,10 @@ where
 
     // TODO(nga): replace `Color("RED")` with `Color.RED`.
     //   https://www.internalfb.com/tasks/?t=183515013
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
     fn invoke(
         &self,
         _me: Value<'v>,
@@ -398,6 +402,7 @@ fn enum_type_methods(builder: &mut MethodsBuilder) {
         }
     }
 
+    #[starlark(deterministic)]
     fn values<'v>(this: Value<'v>) -> anyhow::Result<AllocList<impl Iterator<Item = Value<'v>>>> {
         let this = EnumType::from_value(this).unwrap();
         match this {
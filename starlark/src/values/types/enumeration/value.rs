@@ -145,12 +145,12 @@ impl<'v, V: ValueLike<'v>> serde::Serialize for EnumValueGen<V> {
 
 #[starlark_module]
 fn enum_value_methods(methods: &mut MethodsBuilder) {
-    #[starlark(attribute)]
+    #[starlark(attribute, speculative_exec_safe)]
     fn index(this: &EnumValue) -> starlark::Result<i32> {
         Ok(this.index)
     }
 
-    #[starlark(attribute)]
+    #[starlark(attribute, speculative_exec_safe)]
     fn value<'v>(this: &EnumValue<'v>) -> starlark::Result<Value<'v>> {
         Ok(this.value.to_value())
     }
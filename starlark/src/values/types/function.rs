@@ -25,6 +25,7 @@ use starlark_derive::NoSerialize;
 use starlark_derive::starlark_value;
 
 use crate as starlark;
+use crate::__derive_refs::components::NativeCallableDocs;
 use crate::any::ProvidesStaticType;
 use crate::coerce::Coerce;
 use crate::docs::DocItem;
@@ -111,6 +112,7 @@ impl NativeFunc {
         eval: &mut Evaluator<'v, '_, '_>,
         args: &Arguments<'v, '_>,
     ) -> crate::Result<Value<'v>> {
+        eval.before_native_call()?;
         (self.0)(eval, &self.1, args)
     }
 }
@@ -130,8 +132,10 @@ pub(crate) struct NativeFunction {
     pub(crate) ty: Ty,
     /// Safe to evaluate speculatively.
     pub(crate) speculative_exec_safe: bool,
+    /// Can be called by a hermetic evaluator.
+    pub(crate) deterministic: bool,
     #[derivative(Debug = "ignore")]
-    pub(crate) docs: DocItem,
+    pub(crate) docs: NativeCallableDocs,
     pub(crate) special_builtin_function: Option<SpecialBuiltinFunction>,
 }
 
@@ -150,12 +154,19 @@ impl<'v> AllocValue<'v> for NativeFunction {
 /// Define the function type
 #[starlark_value(type = FUNCTION_TYPE)]
 impl<'v> StarlarkValue<'v> for NativeFunction {
+    fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
     fn invoke(
         &self,
         _me: Value<'v>,
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
+        if let Some(trace) = eval.trace {
+            return trace.native_call(self, eval, args);
+        }
         self.function.invoke(eval, args).map_err(Into::into)
     }
 
@@ -186,7 +197,7 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
     }
 
     fn documentation(&self) -> DocItem {
-        self.docs.clone()
+        self.docs.get().clone()
     }
 
     fn typechecker_ty(&self) -> Option<Ty> {
@@ -264,6 +275,7 @@ impl NativeMeth {
         this: Value<'v>,
         args: &Arguments<'v, '_>,
     ) -> crate::Result<Value<'v>> {
+        eval.before_native_call()?;
         (self.0)(eval, this, &self.1, args)
     }
 }
@@ -278,8 +290,10 @@ pub(crate) struct NativeMethod {
     pub(crate) ty: Ty,
     /// Safe to evaluate speculatively.
     pub(crate) speculative_exec_safe: bool,
+    /// Can be called by a hermetic evaluator.
+    pub(crate) deterministic: bool,
     #[derivative(Debug = "ignore")]
-    pub(crate) docs: DocItem,
+    pub(crate) docs: NativeCallableDocs,
 }
 
 starlark_simple_value!(NativeMethod);
@@ -287,7 +301,7 @@ starlark_simple_value!(NativeMethod);
 #[starlark_value(type = "native_method")]
 impl<'v> StarlarkValue<'v> for NativeMethod {
     fn documentation(&self) -> DocItem {
-        self.docs.clone()
+        self.docs.get().clone()
     }
 
     fn typechecker_ty(&self) -> Option<Ty> {
@@ -371,6 +385,10 @@ impl<'v, V: ValueLike<'v>> StarlarkValue<'v> for BoundMethodGen<V>
 where
     Self: ProvidesStaticType<'v>,
 {
+    fn is_deterministic(&self) -> bool {
+        self.method.deterministic
+    }
+
     fn invoke(
         &self,
         _me: Value<'v>,
@@ -37,6 +37,7 @@ use num_traits::Zero;
 use starlark_syntax::lexer::TokenInt;
 
 use crate as starlark;
+use crate::ErrorCode;
 use crate::typing::Ty;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -63,6 +64,25 @@ enum StarlarkIntError {
     RightShiftNegative,
 }
 
+impl From<StarlarkIntError> for crate::Error {
+    fn from(e: StarlarkIntError) -> Self {
+        let code = ErrorCode::new(match e {
+            StarlarkIntError::CannotRepresentAsExact(_) => 415,
+            StarlarkIntError::FloorDivisionByZero(..) | StarlarkIntError::ModuloByZero(..) => 403,
+            StarlarkIntError::LeftShiftOverflow => 404,
+            StarlarkIntError::LeftShiftNegative | StarlarkIntError::RightShiftNegative => 405,
+        });
+        crate::Error::new_value(e).with_code(code)
+    }
+}
+
+impl StarlarkIntError {
+    /// As an `anyhow::Error` which converts back to a `crate::Error` with its code.
+    fn into_anyhow(self) -> anyhow::Error {
+        crate::Error::from(self).into_anyhow()
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -118,7 +138,7 @@ impl StarlarkInt {
                     return Ok(StarlarkInt::from(i));
                 }
             }
-            Err(StarlarkIntError::CannotRepresentAsExact(f).into())
+            Err(StarlarkIntError::CannotRepresentAsExact(f).into_anyhow())
         }
     }
 
@@ -162,7 +182,7 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
-    fn to_big(self) -> BigInt {
+    pub(crate) fn to_big(self) -> BigInt {
         match self {
             StarlarkIntRef::Small(i) => i.to_bigint(),
             StarlarkIntRef::Big(i) => i.get().clone(),
@@ -196,7 +216,7 @@ impl<'v> StarlarkIntRef<'v> {
                 StarlarkInt::Small(a),
                 StarlarkInt::Small(b),
             )
-            .into());
+            .into_anyhow());
         }
         let sig = b.signum() * a.signum();
         let offset = if sig < 0 && a % b != 0 { 1 } else { 0 };
@@ -237,7 +257,7 @@ impl<'v> StarlarkIntRef<'v> {
                 StarlarkInt::from(a.clone()),
                 StarlarkInt::from(b.clone()),
             )
-            .into());
+            .into_anyhow());
         }
         let sig = Self::signum_big(b) * Self::signum_big(a);
         // TODO(nga): optimize.
@@ -273,7 +293,7 @@ impl<'v> StarlarkIntRef<'v> {
                 StarlarkInt::Small(a),
                 StarlarkInt::Small(b),
             )
-            .into());
+            .into_anyhow());
         }
         // In Rust `i32::min_value() % -1` is overflow, but we should eval it to zero.
         if a == i32::MIN && b == -1 {
@@ -298,7 +318,7 @@ impl<'v> StarlarkIntRef<'v> {
                 StarlarkInt::from(a.clone()),
                 StarlarkInt::from(b.clone()),
             )
-            .into());
+            .into_anyhow());
         }
         let r = a % b;
         if r.is_zero() {
@@ -340,7 +360,7 @@ impl<'v> StarlarkIntRef<'v> {
         }
 
         if other.is_negative() {
-            return Err(StarlarkIntError::LeftShiftNegative.into());
+            return Err(StarlarkIntError::LeftShiftNegative.into_anyhow());
         }
         if self.is_zero() || other.is_zero() {
             return Ok(self.to_owned());
@@ -348,11 +368,11 @@ impl<'v> StarlarkIntRef<'v> {
         if other > 100_000 {
             // Limit the size of the BigInt to avoid accidentally consuming
             // too much memory. 100_000 is practically enough for most use cases.
-            return Err(StarlarkIntError::LeftShiftOverflow.into());
+            return Err(StarlarkIntError::LeftShiftOverflow.into_anyhow());
         }
 
         match other {
-            StarlarkIntRef::Big(_) => Err(StarlarkIntError::LeftShiftOverflow.into()),
+            StarlarkIntRef::Big(_) => Err(StarlarkIntError::LeftShiftOverflow.into_anyhow()),
             StarlarkIntRef::Small(b) => {
                 // No overflow, checked above.
                 let b = b.to_u64().unwrap();
@@ -373,7 +393,7 @@ impl<'v> StarlarkIntRef<'v> {
         }
 
         if other.is_negative() {
-            return Err(StarlarkIntError::RightShiftNegative.into());
+            return Err(StarlarkIntError::RightShiftNegative.into_anyhow());
         }
         if self.is_zero() || other.is_zero() {
             return Ok(self.to_owned());
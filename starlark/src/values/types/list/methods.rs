@@ -52,6 +52,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x == [1, 2, 3]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn append<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] el: Value<'v>,
@@ -76,6 +77,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x == []
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn clear(this: Value) -> anyhow::Result<NoneType> {
         let this = ListData::from_value_mut(this)?;
         this.clear();
@@ -100,6 +102,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x == [1, 2, 3, "foo"]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn extend<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -184,6 +187,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x == ["a", "b", "c", "d", "e"]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn insert<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] index: i32,
@@ -217,6 +221,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x == [1]
     /// # )"#);
     /// ```
+    #[starlark(deterministic)]
     fn pop<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] index: Option<i32>,
@@ -263,6 +268,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// x.remove(2) # error: not found
     /// # "#, "not found");
     /// ```
+    #[starlark(deterministic)]
     fn remove<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] needle: Value<'v>,
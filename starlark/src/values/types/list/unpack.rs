@@ -19,6 +19,8 @@ use std::slice;
 use std::vec;
 
 use crate::typing::Ty;
+use crate::values::AllocValue;
+use crate::values::Heap;
 use crate::values::UnpackValue;
 use crate::values::Value;
 use crate::values::list::ListRef;
@@ -65,6 +67,12 @@ impl<'v, T: UnpackValue<'v>> UnpackValue<'v> for UnpackList<T> {
     }
 }
 
+impl<'v, T: AllocValue<'v>> AllocValue<'v> for UnpackList<T> {
+    fn alloc_value(self, heap: Heap<'v>) -> Value<'v> {
+        heap.alloc(self.items)
+    }
+}
+
 impl<T> IntoIterator for UnpackList<T> {
     type Item = T;
     type IntoIter = vec::IntoIter<T>;
@@ -31,6 +31,7 @@ pub fn register_namespace(builder: &mut GlobalsBuilder) {
     #[starlark(
         ty_custom_function = TyNamespaceFunction,
         as_type = FrozenNamespace,
+        deterministic,
     )]
     fn namespace<'v>(args: &Arguments<'v, '_>, heap: Heap<'v>) -> starlark::Result<Namespace<'v>> {
         args.no_positional_args(heap)?;
@@ -67,6 +67,10 @@ impl<'v, V: ValueLike<'v>> NamespaceGen<'v, V> {
     pub fn get(&self, key: &str) -> Option<V> {
         self.fields.get_hashed(Hashed::new(key)).map(|v| v.value)
     }
+
+    pub(crate) fn fields(&self) -> &SmallMap<V::String, MaybeDocHiddenValue<'v, V>> {
+        &self.fields
+    }
 }
 
 unsafe impl<'v> Coerce<NamespaceGen<'v, Value<'v>>> for NamespaceGen<'static, FrozenValue> {}
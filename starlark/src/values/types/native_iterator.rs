@@ -0,0 +1,143 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rust iterators as Starlark iterables, see [`NativeIterator`].
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Debug;
+
+use allocative::Allocative;
+use derive_more::Display;
+use starlark_derive::NoSerialize;
+use starlark_derive::Trace;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::values::AllocValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+
+/// A Starlark iterable which produces the items of a Rust iterator as it is iterated,
+/// so a stream of any length can be given to Starlark without collecting it into a list.
+///
+/// Like a Python iterator, it is consumed by iterating it: a second loop over the same
+/// value only sees the items the first didn't take. It can't be frozen, so it can't be
+/// left in a module global when the module is frozen.
+///
+/// ```
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::eval::Evaluator;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+/// use starlark::values::native_iterator::NativeIterator;
+///
+/// Module::with_temp_heap(|module| {
+///     let records = module.heap().alloc(NativeIterator::new((0..).take(5)));
+///     module.set("records", records);
+///     let ast = AstModule::parse(
+///         "x.star",
+///         "[x * 2 for x in records]".to_owned(),
+///         &Dialect::Standard,
+///     )
+///     .unwrap();
+///     let mut eval = Evaluator::new(&module);
+///     let res = eval.eval_module(ast, &Globals::standard()).unwrap();
+///     assert_eq!("[0, 2, 4, 6, 8]", res.to_string());
+/// });
+/// ```
+#[derive(ProvidesStaticType, Trace, NoSerialize, Allocative, Display)]
+#[display("iterator")]
+pub struct NativeIterator<'v> {
+    // The iterator is `'static`, so holds no values, and there is nothing to trace.
+    #[trace(unsafe_ignore)]
+    #[allocative(skip)]
+    next: RefCell<Box<dyn FnMut(Heap<'v>) -> Option<Value<'v>> + Send>>,
+}
+
+impl<'v> NativeIterator<'v> {
+    /// Iterate over `iter`, allocating each item when it is reached.
+    pub fn new<I>(iter: I) -> Self
+    where
+        I: IntoIterator,
+        I::IntoIter: Send + 'static,
+        I::Item: AllocValue<'v>,
+    {
+        let mut iter = iter.into_iter();
+        NativeIterator {
+            next: RefCell::new(Box::new(move |heap| iter.next().map(|x| heap.alloc(x)))),
+        }
+    }
+}
+
+impl Debug for NativeIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeIterator").finish_non_exhaustive()
+    }
+}
+
+impl<'v> AllocValue<'v> for NativeIterator<'v> {
+    fn alloc_value(self, heap: Heap<'v>) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+#[starlark_value(type = "iterator")]
+impl<'v> StarlarkValue<'v> for NativeIterator<'v> {
+    unsafe fn iterate(&self, me: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        Ok(me)
+    }
+
+    unsafe fn iter_next(&self, _index: usize, heap: Heap<'v>) -> Option<Value<'v>> {
+        (self.next.borrow_mut())(heap)
+    }
+
+    unsafe fn iter_stop(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::values::native_iterator::NativeIterator;
+
+    #[test]
+    fn test_native_iterator() {
+        let mut a = Assert::new();
+        a.disable_static_typechecking();
+        a.setup_eval(|eval| {
+            let heap = eval.heap();
+            let xs = heap.alloc(NativeIterator::new(["a", "b", "c"].map(str::to_owned)));
+            eval.module().set("xs", xs);
+        });
+        a.is_true(
+            r#"
+first = []
+for x in xs:
+    first.append(x)
+    if x == "b":
+        break
+first == ["a", "b"] and list(xs) == ["c"] and list(xs) == []
+"#,
+        );
+        a.is_true(
+            "lazy.collect(lazy.map(lambda x: x + x, lazy.filter(lambda x: x != 'b', xs))) == ['aa', 'cc']",
+        );
+    }
+}
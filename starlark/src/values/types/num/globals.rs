@@ -35,6 +35,7 @@ pub(crate) fn register_num(globals: &mut GlobalsBuilder) {
     /// abs(-12.34) == 12.34
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn abs(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<Num> {
         match x {
             NumRef::Int(a) => Ok(Num::Int(a.abs())),
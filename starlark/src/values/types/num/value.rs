@@ -23,6 +23,7 @@ use std::ops::Sub;
 use dupe::Dupe;
 
 use crate as starlark;
+use crate::ErrorCode;
 use crate::collections::StarlarkHashValue;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -38,6 +39,15 @@ enum NumError {
     DivisionByZero(Num, Num),
 }
 
+impl From<NumError> for crate::Error {
+    fn from(e: NumError) -> Self {
+        let code = ErrorCode::new(match e {
+            NumError::DivisionByZero(..) => 403,
+        });
+        crate::Error::new_value(e).with_code(code)
+    }
+}
+
 /// [`NumRef`] represents a numerical value that can be unpacked from a [`Value`].
 ///
 /// It's an intermediate representation that facilitates conversions between
@@ -134,7 +144,10 @@ impl<'v> NumRef<'v> {
         let a = self.as_float();
         let b = other.as_float();
         if b == 0.0 {
-            Err(NumError::DivisionByZero(self.to_owned(), other.to_owned()).into())
+            Err(
+                crate::Error::from(NumError::DivisionByZero(self.to_owned(), other.to_owned()))
+                    .into_anyhow(),
+            )
         } else {
             Ok(a / b)
         }
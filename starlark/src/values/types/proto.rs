@@ -0,0 +1,46 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Protobuf messages as Starlark values, available with the `protobuf` feature.
+//!
+//! Messages are dynamic: they are described by a [`prost_reflect::MessageDescriptor`]
+//! loaded at runtime, so no generated Rust code is needed. Register a
+//! [`ProtoMessageType`] for each message the Starlark code should be able to build:
+//!
+//! ```ignore
+//! let descriptor = pool.get_message_by_name("example.Server").unwrap();
+//! globals.set("Server", ProtoMessageType::new(descriptor));
+//! ```
+//!
+//! Calling the type with keyword arguments, or with a dict, builds a [`ProtoMessage`],
+//! checking every field against the descriptor:
+//!
+//! ```python
+//! server = Server(host = "localhost", port = 80, tags = ["a", "b"])
+//! server.port == 80
+//! ```
+//!
+//! Fields are read as attributes, with unset fields giving their default value. Nested
+//! messages may be given as dicts, and enums as their name or number. Messages encode to
+//! JSON with `json.encode`, and to the binary wire format with
+//! [`ProtoMessage::encode_to_vec`].
+
+pub(crate) mod convert;
+pub(crate) mod message;
+
+pub use crate::values::types::proto::message::ProtoMessage;
+pub use crate::values::types::proto::message::ProtoMessageType;
@@ -0,0 +1,303 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion between Starlark values and protobuf field values.
+
+use std::collections::HashMap;
+
+use prost::bytes::Bytes;
+use prost_reflect::DynamicMessage;
+use prost_reflect::FieldDescriptor;
+use prost_reflect::Kind;
+use prost_reflect::MapKey;
+use prost_reflect::MessageDescriptor;
+use prost_reflect::ReflectMessage;
+
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+use crate::values::dict::AllocDict;
+use crate::values::dict::DictRef;
+use crate::values::list::AllocList;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::types::proto::message::ProtoMessage;
+
+#[derive(Debug, thiserror::Error)]
+enum ProtoError {
+    #[error("Message `{0}` has no field `{1}`")]
+    UnknownField(String, String),
+    #[error("Field `{0}` expects `{1}`, got a value of type `{2}`")]
+    WrongType(String, String, String),
+    #[error("Field `{0}` expects a `{1}` message, got a `{2}` message")]
+    WrongMessage(String, String, String),
+    #[error("Enum `{0}` has no value named `{1}`")]
+    UnknownEnumValue(String, String),
+    #[error("Field `{0}` is a map, so can't be used as a map key")]
+    InvalidMapKey(String),
+    #[error("Message `{0}` must be built from keyword arguments or a single dict")]
+    BadArguments(String),
+}
+
+pub(crate) fn bad_arguments(descriptor: &MessageDescriptor) -> crate::Error {
+    crate::Error::new_other(ProtoError::BadArguments(descriptor.full_name().to_owned()))
+}
+
+fn kind_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Double | Kind::Float => "float".to_owned(),
+        Kind::Int32
+        | Kind::Int64
+        | Kind::Uint32
+        | Kind::Uint64
+        | Kind::Sint32
+        | Kind::Sint64
+        | Kind::Fixed32
+        | Kind::Fixed64
+        | Kind::Sfixed32
+        | Kind::Sfixed64 => "int".to_owned(),
+        Kind::Bool => "bool".to_owned(),
+        Kind::String | Kind::Bytes => "str".to_owned(),
+        Kind::Message(m) => m.full_name().to_owned(),
+        Kind::Enum(e) => e.full_name().to_owned(),
+    }
+}
+
+fn wrong_type(field: &FieldDescriptor, expected: &str, value: Value) -> crate::Error {
+    crate::Error::new_other(ProtoError::WrongType(
+        field.full_name().to_owned(),
+        expected.to_owned(),
+        value.get_type().to_owned(),
+    ))
+}
+
+/// Build a message from field names and values, checking them against the descriptor.
+pub(crate) fn message_from_fields<'v>(
+    descriptor: &MessageDescriptor,
+    fields: impl IntoIterator<Item = (&'v str, Value<'v>)>,
+) -> crate::Result<DynamicMessage> {
+    let mut message = DynamicMessage::new(descriptor.clone());
+    for (name, value) in fields {
+        let Some(field) = descriptor.get_field_by_name(name) else {
+            return Err(crate::Error::new_other(ProtoError::UnknownField(
+                descriptor.full_name().to_owned(),
+                name.to_owned(),
+            )));
+        };
+        // Like in Python, `None` leaves the field unset.
+        if value.is_none() {
+            continue;
+        }
+        let value = field_from_starlark(&field, value)?;
+        message
+            .try_set_field(&field, value)
+            .map_err(|e| crate::Error::new_other(anyhow::anyhow!("{e}")))?;
+    }
+    Ok(message)
+}
+
+fn message_from_starlark(
+    field: &FieldDescriptor,
+    descriptor: &MessageDescriptor,
+    value: Value,
+) -> crate::Result<DynamicMessage> {
+    if let Some(message) = value.downcast_ref::<ProtoMessage>() {
+        if message.message().descriptor() != *descriptor {
+            return Err(crate::Error::new_other(ProtoError::WrongMessage(
+                field.full_name().to_owned(),
+                descriptor.full_name().to_owned(),
+                message.message().descriptor().full_name().to_owned(),
+            )));
+        }
+        return Ok(message.message().clone());
+    }
+    let Some(dict) = DictRef::from_value(value) else {
+        return Err(wrong_type(field, descriptor.full_name(), value));
+    };
+    let fields = dict
+        .iter()
+        .map(|(k, v)| match k.unpack_str() {
+            Some(k) => Ok((k, v)),
+            None => Err(wrong_type(field, "dict[str, typing.Any]", value)),
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    message_from_fields(descriptor, fields)
+}
+
+/// Convert a single (not repeated) value of the given kind.
+fn single_from_starlark(
+    field: &FieldDescriptor,
+    kind: &Kind,
+    value: Value,
+) -> crate::Result<prost_reflect::Value> {
+    let wrong = || wrong_type(field, &kind_name(kind), value);
+    Ok(match kind {
+        Kind::Bool => prost_reflect::Value::Bool(value.unpack_bool().ok_or_else(wrong)?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            prost_reflect::Value::I32(value.unpack_integer()?.ok_or_else(wrong)?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            prost_reflect::Value::I64(value.unpack_integer()?.ok_or_else(wrong)?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => {
+            prost_reflect::Value::U32(value.unpack_integer()?.ok_or_else(wrong)?)
+        }
+        Kind::Uint64 | Kind::Fixed64 => {
+            prost_reflect::Value::U64(value.unpack_integer()?.ok_or_else(wrong)?)
+        }
+        Kind::Float => {
+            prost_reflect::Value::F32(value.unpack_num().ok_or_else(wrong)?.as_float() as f32)
+        }
+        Kind::Double => prost_reflect::Value::F64(value.unpack_num().ok_or_else(wrong)?.as_float()),
+        Kind::String => {
+            prost_reflect::Value::String(value.unpack_str().ok_or_else(wrong)?.to_owned())
+        }
+        Kind::Bytes => prost_reflect::Value::Bytes(Bytes::copy_from_slice(
+            value.unpack_str().ok_or_else(wrong)?.as_bytes(),
+        )),
+        Kind::Message(descriptor) => {
+            prost_reflect::Value::Message(message_from_starlark(field, descriptor, value)?)
+        }
+        Kind::Enum(descriptor) => {
+            if let Some(name) = value.unpack_str() {
+                match descriptor.get_value_by_name(name) {
+                    Some(x) => prost_reflect::Value::EnumNumber(x.number()),
+                    None => {
+                        return Err(crate::Error::new_other(ProtoError::UnknownEnumValue(
+                            descriptor.full_name().to_owned(),
+                            name.to_owned(),
+                        )));
+                    }
+                }
+            } else {
+                prost_reflect::Value::EnumNumber(value.unpack_integer()?.ok_or_else(wrong)?)
+            }
+        }
+    })
+}
+
+fn map_key(field: &FieldDescriptor, key: prost_reflect::Value) -> crate::Result<MapKey> {
+    Ok(match key {
+        prost_reflect::Value::Bool(x) => MapKey::Bool(x),
+        prost_reflect::Value::I32(x) => MapKey::I32(x),
+        prost_reflect::Value::I64(x) => MapKey::I64(x),
+        prost_reflect::Value::U32(x) => MapKey::U32(x),
+        prost_reflect::Value::U64(x) => MapKey::U64(x),
+        prost_reflect::Value::String(x) => MapKey::String(x),
+        _ => {
+            return Err(crate::Error::new_other(ProtoError::InvalidMapKey(
+                field.full_name().to_owned(),
+            )));
+        }
+    })
+}
+
+/// Convert a value for the field, which may be repeated or a map.
+pub(crate) fn field_from_starlark(
+    field: &FieldDescriptor,
+    value: Value,
+) -> crate::Result<prost_reflect::Value> {
+    let kind = field.kind();
+    if field.is_map() {
+        let Kind::Message(entry) = &kind else {
+            unreachable!("map fields are always entry messages")
+        };
+        let key_field = entry.map_entry_key_field();
+        let value_field = entry.map_entry_value_field();
+        let Some(dict) = DictRef::from_value(value) else {
+            return Err(wrong_type(field, "dict", value));
+        };
+        let mut res = HashMap::with_capacity(dict.len());
+        for (k, v) in dict.iter() {
+            let k = map_key(field, single_from_starlark(field, &key_field.kind(), k)?)?;
+            let v = single_from_starlark(field, &value_field.kind(), v)?;
+            res.insert(k, v);
+        }
+        Ok(prost_reflect::Value::Map(res))
+    } else if field.is_list() {
+        let Some(xs) = UnpackListOrTuple::<Value>::unpack_value_opt(value) else {
+            return Err(wrong_type(field, "list", value));
+        };
+        Ok(prost_reflect::Value::List(
+            xs.items
+                .into_iter()
+                .map(|x| single_from_starlark(field, &kind, x))
+                .collect::<crate::Result<_>>()?,
+        ))
+    } else {
+        single_from_starlark(field, &kind, value)
+    }
+}
+
+fn map_key_to_starlark<'v>(key: &MapKey, heap: Heap<'v>) -> Value<'v> {
+    match key {
+        MapKey::Bool(x) => Value::new_bool(*x),
+        MapKey::I32(x) => heap.alloc(*x),
+        MapKey::I64(x) => heap.alloc(*x),
+        MapKey::U32(x) => heap.alloc(*x),
+        MapKey::U64(x) => heap.alloc(*x),
+        MapKey::String(x) => heap.alloc(x.as_str()),
+    }
+}
+
+/// Convert a field value, of a field of the given kind, to Starlark.
+///
+/// Enums become their name, or their number if it is not known to the descriptor.
+/// Bytes become a list of ints.
+pub(crate) fn value_to_starlark<'v>(
+    value: &prost_reflect::Value,
+    kind: &Kind,
+    heap: Heap<'v>,
+) -> Value<'v> {
+    match value {
+        prost_reflect::Value::Bool(x) => Value::new_bool(*x),
+        prost_reflect::Value::I32(x) => heap.alloc(*x),
+        prost_reflect::Value::I64(x) => heap.alloc(*x),
+        prost_reflect::Value::U32(x) => heap.alloc(*x),
+        prost_reflect::Value::U64(x) => heap.alloc(*x),
+        prost_reflect::Value::F32(x) => heap.alloc(*x as f64),
+        prost_reflect::Value::F64(x) => heap.alloc(*x),
+        prost_reflect::Value::String(x) => heap.alloc(x.as_str()),
+        prost_reflect::Value::Bytes(x) => heap.alloc(AllocList(x.iter().map(|b| *b as i32))),
+        prost_reflect::Value::EnumNumber(x) => match kind {
+            Kind::Enum(descriptor) => match descriptor.get_value(*x) {
+                Some(v) => heap.alloc(v.name()),
+                None => heap.alloc(*x),
+            },
+            _ => heap.alloc(*x),
+        },
+        prost_reflect::Value::Message(x) => heap.alloc(ProtoMessage::new(x.clone())),
+        prost_reflect::Value::List(xs) => heap.alloc(AllocList(
+            xs.iter().map(|x| value_to_starlark(x, kind, heap)),
+        )),
+        prost_reflect::Value::Map(xs) => {
+            let value_kind = match kind {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                _ => unreachable!("map fields are always entry messages"),
+            };
+            // Protobuf maps are unordered, so sort to make the result deterministic.
+            let mut xs: Vec<_> = xs.iter().collect();
+            xs.sort_by(|a, b| a.0.cmp(b.0));
+            heap.alloc(AllocDict(xs.into_iter().map(|(k, v)| {
+                (
+                    map_key_to_starlark(k, heap),
+                    value_to_starlark(v, &value_kind, heap),
+                )
+            })))
+        }
+    }
+}
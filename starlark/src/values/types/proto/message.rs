@@ -0,0 +1,241 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use prost::Message;
+use prost_reflect::DynamicMessage;
+use prost_reflect::MapKey;
+use prost_reflect::MessageDescriptor;
+use prost_reflect::ReflectMessage;
+use serde::Serialize;
+use serde::Serializer;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::starlark_simple_value;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+use crate::values::dict::DictRef;
+use crate::values::types::proto::convert::bad_arguments;
+use crate::values::types::proto::convert::message_from_fields;
+use crate::values::types::proto::convert::value_to_starlark;
+
+/// A protobuf message, with the fields checked against its descriptor.
+#[derive(Debug, Clone, ProvidesStaticType, Allocative)]
+pub struct ProtoMessage {
+    #[allocative(skip)]
+    message: DynamicMessage,
+}
+
+starlark_simple_value!(ProtoMessage);
+
+impl ProtoMessage {
+    /// The result of calling `type()` on a message.
+    pub const TYPE: &'static str = "proto_message";
+
+    /// Wrap a message.
+    pub fn new(message: DynamicMessage) -> ProtoMessage {
+        ProtoMessage { message }
+    }
+
+    /// The underlying message.
+    pub fn message(&self) -> &DynamicMessage {
+        &self.message
+    }
+
+    /// Encode the message in the protobuf binary wire format.
+    pub fn encode_to_vec(&self) -> Vec<u8> {
+        self.message.encode_to_vec()
+    }
+}
+
+fn fmt_map_key(key: &MapKey, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match key {
+        MapKey::Bool(x) => write!(f, "{}", if *x { "True" } else { "False" }),
+        MapKey::I32(x) => write!(f, "{x}"),
+        MapKey::I64(x) => write!(f, "{x}"),
+        MapKey::U32(x) => write!(f, "{x}"),
+        MapKey::U64(x) => write!(f, "{x}"),
+        MapKey::String(x) => write!(f, "{x:?}"),
+    }
+}
+
+fn fmt_value(value: &prost_reflect::Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        prost_reflect::Value::Bool(x) => write!(f, "{}", if *x { "True" } else { "False" }),
+        prost_reflect::Value::I32(x) => write!(f, "{x}"),
+        prost_reflect::Value::I64(x) => write!(f, "{x}"),
+        prost_reflect::Value::U32(x) => write!(f, "{x}"),
+        prost_reflect::Value::U64(x) => write!(f, "{x}"),
+        prost_reflect::Value::F32(x) => write!(f, "{x}"),
+        prost_reflect::Value::F64(x) => write!(f, "{x}"),
+        prost_reflect::Value::String(x) => write!(f, "{x:?}"),
+        prost_reflect::Value::Bytes(x) => write!(f, "{x:?}"),
+        prost_reflect::Value::EnumNumber(x) => write!(f, "{x}"),
+        prost_reflect::Value::Message(x) => fmt_message(x, f),
+        prost_reflect::Value::List(xs) => {
+            write!(f, "[")?;
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_value(x, f)?;
+            }
+            write!(f, "]")
+        }
+        prost_reflect::Value::Map(xs) => {
+            let mut xs: Vec<_> = xs.iter().collect();
+            xs.sort_by(|a, b| a.0.cmp(b.0));
+            write!(f, "{{")?;
+            for (i, (k, v)) in xs.into_iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_map_key(k, f)?;
+                write!(f, ": ")?;
+                fmt_value(v, f)?;
+            }
+            write!(f, "}}")
+        }
+    }
+}
+
+fn fmt_message(message: &DynamicMessage, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}(", message.descriptor().name())?;
+    for (i, (field, value)) in message.fields().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}=", field.name())?;
+        fmt_value(value, f)?;
+    }
+    write!(f, ")")
+}
+
+impl Display for ProtoMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_message(&self.message, f)
+    }
+}
+
+/// Encodes using the canonical protobuf JSON mapping.
+impl Serialize for ProtoMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.message.serialize(serializer)
+    }
+}
+
+#[starlark_value(type = ProtoMessage::TYPE)]
+impl<'v> StarlarkValue<'v> for ProtoMessage {
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        Ok(match other.downcast_ref::<ProtoMessage>() {
+            Some(other) => self.message == other.message,
+            None => false,
+        })
+    }
+
+    fn get_attr(&self, attribute: &str, heap: Heap<'v>) -> Option<Value<'v>> {
+        let field = self.message.descriptor().get_field_by_name(attribute)?;
+        let value = self.message.get_field(&field);
+        Some(value_to_starlark(&value, &field.kind(), heap))
+    }
+
+    fn has_attr(&self, attribute: &str, _heap: Heap<'v>) -> bool {
+        self.message
+            .descriptor()
+            .get_field_by_name(attribute)
+            .is_some()
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        self.message
+            .descriptor()
+            .fields()
+            .map(|field| field.name().to_owned())
+            .collect()
+    }
+}
+
+/// A protobuf message type, which builds [`ProtoMessage`] values when called.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct ProtoMessageType {
+    #[allocative(skip)]
+    descriptor: MessageDescriptor,
+}
+
+starlark_simple_value!(ProtoMessageType);
+
+impl ProtoMessageType {
+    /// The result of calling `type()` on a message type.
+    pub const TYPE: &'static str = "proto_message_type";
+
+    /// A message type described by the descriptor.
+    pub fn new(descriptor: MessageDescriptor) -> ProtoMessageType {
+        ProtoMessageType { descriptor }
+    }
+}
+
+impl Display for ProtoMessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proto_message_type({})", self.descriptor.full_name())
+    }
+}
+
+#[starlark_value(type = ProtoMessageType::TYPE)]
+impl<'v> StarlarkValue<'v> for ProtoMessageType {
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let positional: Vec<Value> = args.positions(eval.heap())?.collect();
+        let named = args.names_map()?;
+        let message = match positional.as_slice() {
+            [] => message_from_fields(
+                &self.descriptor,
+                named.iter().map(|(k, v)| (k.as_str(), *v)),
+            )?,
+            [dict] if named.is_empty() => {
+                let Some(dict) = DictRef::from_value(*dict) else {
+                    return Err(bad_arguments(&self.descriptor));
+                };
+                let fields = dict
+                    .iter()
+                    .map(|(k, v)| k.unpack_str().map(|k| (k, v)))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| bad_arguments(&self.descriptor))?;
+                message_from_fields(&self.descriptor, fields)?
+            }
+            _ => return Err(bad_arguments(&self.descriptor)),
+        };
+        Ok(eval.heap().alloc(ProtoMessage::new(message)))
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Providers, the typed records which rules return to pass information to their
+//! dependents, as in Buck2 and Bazel.
+//!
+//! Calling `provider()` produces a [`ProviderCallable`]. Calling the [`ProviderCallable`]
+//! produces a [`Provider`]. Rules usually return several providers, gathered in a
+//! [`ProviderCollection`] which is indexed by the provider callable:
+//!
+//! ```
+//! # starlark::assert::pass(r#"
+//! RunInfo = provider(fields = ["args"])
+//! LibInfo = provider(fields = {"name": provider_field(str), "deps": provider_field(list, default = [])})
+//! info = LibInfo(name = "foo")
+//! assert_eq(info.deps, [])
+//! providers = provider_collection([RunInfo(args = ["a"]), info])
+//! assert_eq(providers[LibInfo].name, "foo")
+//! assert_eq(LibInfo in providers, True)
+//! # "#);
+//! ```
+//!
+//! Once assigned to a global, a provider callable can also be used as a type, and the
+//! typechecker knows the fields of its instances.
+
+pub(crate) mod collection;
+pub(crate) mod globals;
+pub(crate) mod instance;
+pub(crate) mod matcher;
+pub(crate) mod provider_callable;
+
+pub use crate::values::provider::collection::FrozenProviderCollection;
+pub use crate::values::provider::collection::ProviderCollection;
+pub use crate::values::provider::instance::FrozenProvider;
+pub use crate::values::provider::instance::Provider;
+pub use crate::values::provider::provider_callable::FrozenProviderCallable;
+pub use crate::values::provider::provider_callable::ProviderCallable;
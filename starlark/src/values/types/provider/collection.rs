@@ -0,0 +1,195 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use display_container::fmt_container;
+use serde::Serialize;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::Coerce;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::starlark_complex_value;
+use crate::values::Freeze;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLifetimeless;
+use crate::values::ValueLike;
+use crate::values::comparison::equals_slice;
+use crate::values::none::NoneOr;
+use crate::values::provider::Provider;
+use crate::values::provider::ProviderCallable;
+use crate::values::types::type_instance_id::TypeInstanceId;
+
+#[derive(Debug, thiserror::Error)]
+enum ProviderCollectionError {
+    #[error("Provider collection can only contain providers, got a value of type `{0}`")]
+    NotAProvider(String),
+    #[error("Provider collection contains more than one `{0}` provider")]
+    Duplicate(String),
+    #[error(
+        "Provider collection must be indexed by a provider callable, got a value of type `{0}`"
+    )]
+    NotAProviderCallable(String),
+    #[error("Provider collection has no provider created by `{0}`")]
+    Missing(String),
+}
+
+/// The result of `provider_collection()`: providers, at most one of each provider callable,
+/// indexed by their provider callable.
+#[derive(Clone, Debug, Trace, Coerce, Freeze, ProvidesStaticType, Allocative)]
+#[repr(C)]
+pub struct ProviderCollectionGen<V: ValueLifetimeless> {
+    providers: Box<[V]>, // Must be Provider
+}
+
+starlark_complex_value!(pub ProviderCollection);
+
+impl<'v, V: ValueLike<'v>> Display for ProviderCollectionGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_container(f, "provider_collection[", "]", self.providers.iter())
+    }
+}
+
+fn provider_callable_id(callable: Value) -> crate::Result<TypeInstanceId> {
+    match ProviderCallable::from_value(callable) {
+        Some(x) => Ok(x.either(|x| x.id, |x| x.id)),
+        None => Err(crate::Error::new_other(
+            ProviderCollectionError::NotAProviderCallable(callable.get_type().to_owned()),
+        )),
+    }
+}
+
+impl<'v> ProviderCollection<'v> {
+    /// A collection of the given providers, checking there is at most one of each.
+    pub fn try_new(providers: Vec<Value<'v>>) -> crate::Result<Self> {
+        let mut ids = Vec::with_capacity(providers.len());
+        for p in &providers {
+            let Some(provider) = Provider::from_value(*p) else {
+                return Err(crate::Error::new_other(
+                    ProviderCollectionError::NotAProvider(p.get_type().to_owned()),
+                ));
+            };
+            let id = provider.provider_id();
+            if ids.contains(&id) {
+                return Err(crate::Error::new_other(ProviderCollectionError::Duplicate(
+                    provider.provider_name().unwrap_or("anon").to_owned(),
+                )));
+            }
+            ids.push(id);
+        }
+        Ok(ProviderCollection {
+            providers: providers.into_boxed_slice(),
+        })
+    }
+}
+
+impl<'v, V: ValueLike<'v>> ProviderCollectionGen<V> {
+    /// The providers, in the order they were given.
+    pub fn providers(&self) -> impl ExactSizeIterator<Item = Value<'v>> + '_ {
+        self.providers.iter().map(|p| p.to_value())
+    }
+
+    /// The provider created by `callable`, if there is one.
+    pub fn get(&self, callable: Value<'v>) -> crate::Result<Option<Value<'v>>> {
+        let id = provider_callable_id(callable)?;
+        Ok(self
+            .providers()
+            .find(|p| Provider::from_value(*p).unwrap().provider_id() == id))
+    }
+}
+
+#[starlark_value(type = "provider_collection")]
+impl<'v, V: ValueLike<'v>> StarlarkValue<'v> for ProviderCollectionGen<V>
+where
+    Self: ProvidesStaticType<'v>,
+{
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match ProviderCollection::from_value(other) {
+            Some(other) => equals_slice(&self.providers, &other.providers, |x, y| x.equals(*y)),
+            None => Ok(false),
+        }
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        for p in &*self.providers {
+            p.write_hash(hasher)?;
+        }
+        Ok(())
+    }
+
+    fn at(&self, index: Value<'v>, _heap: Heap<'v>) -> crate::Result<Value<'v>> {
+        match self.get(index)? {
+            Some(p) => Ok(p),
+            None => Err(crate::Error::new_other(ProviderCollectionError::Missing(
+                index.to_str(),
+            ))),
+        }
+    }
+
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        Ok(self.get(other)?.is_some())
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.providers.len() as i32)
+    }
+
+    fn iterate_collect(&self, _heap: Heap<'v>) -> crate::Result<Vec<Value<'v>>> {
+        Ok(self.providers().collect())
+    }
+
+    fn get_methods() -> Option<&'static Methods>
+    where
+        Self: Sized,
+    {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(provider_collection_methods)
+    }
+}
+
+impl<'v, V: ValueLike<'v>> Serialize for ProviderCollectionGen<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.providers.iter())
+    }
+}
+
+#[starlark_module]
+fn provider_collection_methods(methods: &mut MethodsBuilder) {
+    /// The provider created by `provider`, or `None` if there is none.
+    #[starlark(deterministic)]
+    fn get<'v>(
+        this: &ProviderCollection<'v>,
+        #[starlark(require = pos)] provider: Value<'v>,
+    ) -> starlark::Result<NoneOr<Value<'v>>> {
+        Ok(NoneOr::from_option(this.get(provider)?))
+    }
+}
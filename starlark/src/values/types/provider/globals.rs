@@ -0,0 +1,286 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of `provider` function.
+
+use dupe::Dupe;
+use either::Either;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::collections::SmallMap;
+use crate::environment::GlobalsBuilder;
+use crate::eval::Evaluator;
+use crate::values::Value;
+use crate::values::dict::DictRef;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::provider::ProviderCallable;
+use crate::values::provider::ProviderCollection;
+use crate::values::record::field::Field;
+use crate::values::typing::type_compiled::compiled::TypeCompiled;
+
+#[derive(Debug, thiserror::Error)]
+enum ProviderError {
+    #[error("Provider field names must be strings, got a value of type `{0}`")]
+    FieldNameNotString(String),
+    #[error(
+        "Provider field `{0}` must be a doc string or `provider_field()`, got a value of type `{1}`"
+    )]
+    InvalidField(String, String),
+}
+
+/// A field which may be omitted, defaulting to `None`.
+fn untyped_field<'v>() -> Field<'v> {
+    Field::new(TypeCompiled::any().to_value(), Some(Value::new_none()))
+}
+
+#[starlark_module]
+pub(crate) fn register_provider(builder: &mut GlobalsBuilder) {
+    /// Create a provider callable, which creates providers with the given fields.
+    ///
+    /// `fields` is either a list of field names, which may be omitted and default to `None`,
+    /// or a dict from field names to a doc string (for the same behavior) or a
+    /// `provider_field()`, which gives the type, and optionally the default.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// RunInfo = provider(doc = "How to run a target", fields = ["args", "env"])
+    /// info = RunInfo(args = ["--help"])
+    /// assert_eq(info.args, ["--help"])
+    /// assert_eq(info.env, None)
+    /// # "#);
+    /// ```
+    ///
+    /// Providers are compared by their provider callable and fields, so providers from
+    /// different callables are never equal, even with the same fields.
+    #[starlark(deterministic)]
+    fn provider<'v>(
+        #[starlark(require = named)] fields: Either<UnpackListOrTuple<String>, DictRef<'v>>,
+        #[starlark(require = named)] doc: Option<String>,
+    ) -> starlark::Result<ProviderCallable<'v>> {
+        let mut mp = SmallMap::new();
+        match fields {
+            Either::Left(names) => {
+                for name in names.items {
+                    mp.insert(name, untyped_field());
+                }
+            }
+            Either::Right(dict) => {
+                for (k, v) in dict.iter() {
+                    let Some(name) = k.unpack_str() else {
+                        return Err(crate::Error::new_other(ProviderError::FieldNameNotString(
+                            k.get_type().to_owned(),
+                        )));
+                    };
+                    let field = if let Some(field) = Field::from_value(v) {
+                        field.dupe()
+                    } else if v.unpack_str().is_some() {
+                        untyped_field()
+                    } else {
+                        return Err(crate::Error::new_other(ProviderError::InvalidField(
+                            name.to_owned(),
+                            v.get_type().to_owned(),
+                        )));
+                    };
+                    mp.insert(name.to_owned(), field);
+                }
+            }
+        }
+        Ok(ProviderCallable::new(mp, doc))
+    }
+
+    /// A typed field of a provider, used in the `fields` argument of `provider()`.
+    /// Without a `default`, the field must be given when creating the provider.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// LibInfo = provider(fields = {"name": provider_field(str), "deps": provider_field(list[str], default = [])})
+    /// assert_eq(LibInfo(name = "foo").deps, [])
+    /// # "#);
+    /// ```
+    #[starlark(deterministic)]
+    fn provider_field<'v>(
+        #[starlark(require = pos)] typ: Value<'v>,
+        default: Option<Value<'v>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Field<'v>> {
+        let compiled = TypeCompiled::new(typ, eval.heap())?;
+        if let Some(d) = default {
+            compiled.check_type(d, Some("default"))?;
+        }
+        Ok(Field::new(compiled, default))
+    }
+
+    /// Gather providers into a collection, indexed by provider callable.
+    /// It is an error to give two providers created by the same provider callable.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// RunInfo = provider(fields = ["args"])
+    /// DocInfo = provider(fields = ["text"])
+    /// providers = provider_collection([RunInfo(args = [])])
+    /// assert_eq(providers[RunInfo].args, [])
+    /// assert_eq(providers.get(DocInfo), None)
+    /// assert_eq(DocInfo in providers, False)
+    /// # "#);
+    /// ```
+    #[starlark(deterministic)]
+    fn provider_collection<'v>(
+        #[starlark(require = pos)] providers: UnpackListOrTuple<Value<'v>>,
+    ) -> starlark::Result<ProviderCollection<'v>> {
+        ProviderCollection::try_new(providers.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_provider() {
+        assert::pass(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int), "b": "Some doc", "c": provider_field(str, default = "x")})
+x = MyInfo(a = 1)
+assert_eq(x.a, 1)
+assert_eq(x.b, None)
+assert_eq(x.c, "x")
+assert_eq(dir(x), ["a", "b", "c"])
+assert_eq(type(x), "provider")
+assert_eq(MyInfo.type, "MyInfo")
+assert_eq(repr(x), 'MyInfo(a=1, b=None, c="x")')
+assert_eq(x, MyInfo(a = 1, c = "x"))
+assert_ne(x, MyInfo(a = 2))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_provider_different_callables() {
+        assert::pass(
+            r#"
+A = provider(fields = ["x"])
+B = provider(fields = ["x"])
+assert_ne(A(x = 1), B(x = 1))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_provider_fail() {
+        assert::fail(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int)})
+MyInfo(a = "x")
+"#,
+            "does not match the type annotation `int` for argument `a`",
+        );
+        assert::fail(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int)})
+MyInfo()
+"#,
+            "Missing named-only parameter",
+        );
+        assert::fail(
+            r#"
+MyInfo = provider(fields = ["a"])
+MyInfo(b = 1)
+"#,
+            "extra named",
+        );
+        assert::fail(
+            "provider(fields = {'a': 1})",
+            "must be a doc string or `provider_field()`",
+        );
+        assert::fail(
+            "provider(fields = ['a'])(a = 1)",
+            "not assigned to a global variable",
+        );
+    }
+
+    #[test]
+    fn test_provider_as_type() {
+        assert::pass(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int)})
+
+def f(x: MyInfo) -> int:
+    return x.a
+
+assert_eq(f(MyInfo(a = 1)), 1)
+"#,
+        );
+        assert::fail(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int)})
+OtherInfo = provider(fields = {"a": provider_field(int)})
+
+def f(x: MyInfo) -> int:
+    return x.a
+
+def g():
+    f(OtherInfo(a = 1))
+"#,
+            "Expected type `MyInfo` but got `OtherInfo`",
+        );
+        assert::fail(
+            r#"
+MyInfo = provider(fields = {"a": provider_field(int)})
+
+def f(x: MyInfo):
+    return x.b
+"#,
+            "The attribute `b` is not available on the type `MyInfo`",
+        );
+    }
+
+    #[test]
+    fn test_provider_collection() {
+        assert::pass(
+            r#"
+A = provider(fields = ["x"])
+B = provider(fields = ["y"])
+C = provider(fields = ["z"])
+c = provider_collection([A(x = 1), B(y = 2)])
+assert_eq(c[A].x, 1)
+assert_eq(c[B].y, 2)
+assert_eq(A in c, True)
+assert_eq(C in c, False)
+assert_eq(c.get(C), None)
+assert_eq(len(c), 2)
+assert_eq([type(p) for p in c], ["provider", "provider"])
+"#,
+        );
+        assert::fail(
+            r#"
+A = provider(fields = ["x"])
+provider_collection([A(x = 1), A(x = 2)])
+"#,
+            "more than one `A` provider",
+        );
+        assert::fail(
+            r#"
+A = provider(fields = ["x"])
+B = provider(fields = ["x"])
+provider_collection([A(x = 1)])[B]
+"#,
+            "has no provider created by `provider[B]`",
+        );
+        assert::fail("provider_collection([1])", "can only contain providers");
+    }
+}
@@ -0,0 +1,341 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use either::Either;
+use once_cell::sync::OnceCell;
+use starlark_derive::NoSerialize;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_map::StarlarkHasher;
+use starlark_map::small_map::SmallMap;
+use starlark_map::sorted_map::SortedMap;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::eval::ParametersSpec;
+use crate::eval::ParametersSpecParam;
+use crate::starlark_complex_values;
+use crate::typing::ParamIsRequired;
+use crate::typing::ParamSpec;
+use crate::typing::Ty;
+use crate::typing::callable::TyCallable;
+use crate::typing::starlark_value::TyStarlarkValue;
+use crate::typing::user::TyUser;
+use crate::typing::user::TyUserFields;
+use crate::typing::user::TyUserParams;
+use crate::util::ArcStr;
+use crate::values::Freeze;
+use crate::values::FreezeResult;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLifetimeless;
+use crate::values::ValueLike;
+use crate::values::ValueTypedComplex;
+use crate::values::provider::Provider;
+use crate::values::provider::matcher::ProviderMatcher;
+use crate::values::record::field::FieldGen;
+use crate::values::types::type_instance_id::TypeInstanceId;
+use crate::values::typing::type_compiled::type_matcher_factory::TypeMatcherFactory;
+
+#[derive(Debug, thiserror::Error)]
+enum ProviderCallableError {
+    #[error(
+        "Provider cannot be created if the provider callable is not assigned to a global variable"
+    )]
+    NotAssigned,
+}
+
+/// Types and parameters of a provider callable, computed when it is assigned to a global.
+#[derive(Debug)]
+pub(crate) struct TyProviderData {
+    /// Name of the provider.
+    pub(crate) name: String,
+    /// Type of the providers created.
+    pub(crate) ty_provider: Ty,
+    /// Type of the provider callable.
+    pub(crate) ty_provider_callable: Ty,
+    pub(crate) parameter_spec: ParametersSpec<FrozenValue>,
+}
+
+/// The result of `provider()`, which creates [`Provider`] values when called.
+#[derive(Debug, Trace, NoSerialize, ProvidesStaticType, Allocative)]
+pub struct ProviderCallableGen<V: ValueLifetimeless> {
+    pub(crate) id: TypeInstanceId,
+    #[allocative(skip)]
+    #[trace(unsafe_ignore)]
+    pub(crate) ty_provider_data: OnceCell<Arc<TyProviderData>>,
+    fields: SmallMap<String, FieldGen<V>>,
+    doc: Option<String>,
+}
+
+/// Provider callable in a heap.
+pub type ProviderCallable<'v> = ProviderCallableGen<Value<'v>>;
+/// Provider callable in a frozen heap.
+pub type FrozenProviderCallable = ProviderCallableGen<FrozenValue>;
+
+starlark_complex_values!(ProviderCallable);
+
+impl<V: ValueLifetimeless> Display for ProviderCallableGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ty_provider_data.get() {
+            Some(data) => write!(f, "provider[{}]", data.name),
+            None => write!(f, "provider[anon]"),
+        }
+    }
+}
+
+pub(crate) fn provider_fields<'v>(
+    x: Either<&'v ProviderCallable<'v>, &'v FrozenProviderCallable>,
+) -> &'v SmallMap<String, FieldGen<Value<'v>>> {
+    x.either(|x| &x.fields, |x| coerce(&x.fields))
+}
+
+impl<'v> ProviderCallable<'v> {
+    pub(crate) fn new(fields: SmallMap<String, FieldGen<Value<'v>>>, doc: Option<String>) -> Self {
+        Self {
+            id: TypeInstanceId::r#gen(),
+            ty_provider_data: OnceCell::new(),
+            fields,
+            doc,
+        }
+    }
+}
+
+impl<'v> Freeze for ProviderCallable<'v> {
+    type Frozen = FrozenProviderCallable;
+    fn freeze(self, freezer: &Freezer) -> FreezeResult<Self::Frozen> {
+        Ok(FrozenProviderCallable {
+            id: self.id,
+            ty_provider_data: self.ty_provider_data,
+            fields: self.fields.freeze(freezer)?,
+            doc: self.doc,
+        })
+    }
+}
+
+impl<V: ValueLifetimeless> ProviderCallableGen<V> {
+    /// The name of the provider, if it has been assigned to a global.
+    pub fn name(&self) -> Option<&str> {
+        Some(&self.ty_provider_data.get()?.name)
+    }
+
+    /// The documentation given to `provider()`.
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// The names of the fields.
+    pub fn field_names(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.fields.keys().map(String::as_str)
+    }
+
+    pub(crate) fn instance_ty(&self) -> Ty {
+        self.ty_provider_data
+            .get()
+            .expect("Providers can only be created if the callable is assigned")
+            .ty_provider
+            .dupe()
+    }
+}
+
+impl<'v, V: ValueLike<'v>> ProviderCallableGen<V> {
+    fn make_ty_provider_data(&self, name: &str) -> crate::Result<Arc<TyProviderData>> {
+        let fields: SortedMap<String, Ty> = self
+            .fields
+            .iter()
+            .map(|(name, field)| (name.clone(), field.ty()))
+            .collect();
+
+        let ty_provider = Ty::custom(TyUser::new(
+            name.to_owned(),
+            TyStarlarkValue::new::<Provider>(),
+            self.id,
+            TyUserParams {
+                matcher: Some(TypeMatcherFactory::new(ProviderMatcher { id: self.id })),
+                fields: TyUserFields {
+                    known: fields,
+                    unknown: false,
+                },
+                ..TyUserParams::default()
+            },
+        )?);
+
+        let ty_provider_callable = Ty::custom(TyUser::new(
+            format!("provider[{name}]"),
+            TyStarlarkValue::new::<ProviderCallable>(),
+            TypeInstanceId::r#gen(),
+            TyUserParams {
+                callable: Some(TyCallable::new(
+                    ParamSpec::new_named_only(self.fields.iter().map(|(name, field)| {
+                        (
+                            ArcStr::from(name.as_str()),
+                            if field.default.is_some() {
+                                ParamIsRequired::No
+                            } else {
+                                ParamIsRequired::Yes
+                            },
+                            field.ty(),
+                        )
+                    }))?,
+                    ty_provider.dupe(),
+                )),
+                ..TyUserParams::default()
+            },
+        )?);
+
+        let parameter_spec = ParametersSpec::new_named_only(
+            name,
+            self.fields.iter().map(|(name, field)| {
+                (
+                    name.as_str(),
+                    match field.default {
+                        None => ParametersSpecParam::Required,
+                        Some(_) => ParametersSpecParam::Optional,
+                    },
+                )
+            }),
+        );
+
+        Ok(Arc::new(TyProviderData {
+            name: name.to_owned(),
+            ty_provider,
+            ty_provider_callable,
+            parameter_spec,
+        }))
+    }
+}
+
+#[starlark_value(type = "provider_callable")]
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for ProviderCallableGen<V>
+where
+    Self: ProvidesStaticType<'v>,
+    FieldGen<V>: ProvidesStaticType<'v>,
+{
+    type Canonical = FrozenProviderCallable;
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        Ok(match ProviderCallable::from_value(other) {
+            Some(other) => other.either(|x| x.id, |x| x.id) == self.id,
+            None => false,
+        })
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        self.id.hash(hasher);
+        Ok(())
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn invoke(
+        &self,
+        me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let Some(ty_provider_data) = self.ty_provider_data.get() else {
+            return Err(crate::Error::new_other(ProviderCallableError::NotAssigned));
+        };
+
+        ty_provider_data
+            .parameter_spec
+            .parser(args, eval, |param_parser, eval| {
+                let fields = provider_fields(ProviderCallable::from_value(me).unwrap());
+                let mut values = Vec::with_capacity(fields.len());
+                for (name, field) in fields.iter() {
+                    let value = match field.default {
+                        None => {
+                            let v: Value = param_parser.next()?;
+                            field.typ.check_type(v, Some(name))?;
+                            v
+                        }
+                        Some(default) => match param_parser.next_opt::<Value>()? {
+                            None => default,
+                            Some(v) => {
+                                field.typ.check_type(v, Some(name))?;
+                                v
+                            }
+                        },
+                    };
+                    values.push(value);
+                }
+                Ok(eval.heap().alloc_complex(Provider {
+                    typ: me,
+                    values: values.into_boxed_slice(),
+                }))
+            })
+            .map_err(Into::into)
+    }
+
+    fn get_methods() -> Option<&'static Methods>
+    where
+        Self: Sized,
+    {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(provider_callable_methods)
+    }
+
+    fn eval_type(&self) -> Option<Ty> {
+        self.ty_provider_data.get().map(|t| t.ty_provider.dupe())
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        self.ty_provider_data
+            .get()
+            .map(|t| t.ty_provider_callable.dupe())
+    }
+
+    fn export_as(
+        &self,
+        variable_name: &str,
+        _eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<()> {
+        self.ty_provider_data
+            .get_or_try_init(|| self.make_ty_provider_data(variable_name))?;
+        Ok(())
+    }
+}
+
+#[starlark_module]
+fn provider_callable_methods(methods: &mut MethodsBuilder) {
+    /// The name of the provider, as assigned to a global.
+    #[starlark(attribute)]
+    fn r#type<'v>(this: ValueTypedComplex<'v, ProviderCallable<'v>>) -> starlark::Result<&'v str> {
+        Ok(this
+            .unpack()
+            .either(|x| x.name(), |x| x.name())
+            .unwrap_or(Provider::TYPE))
+    }
+}
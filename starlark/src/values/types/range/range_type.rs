@@ -88,6 +88,21 @@ impl Range {
         }
     }
 
+    /// Element at iteration `index`, or `None` if the iteration is finished.
+    /// Same as `iter_next`, but without allocating the result.
+    #[inline]
+    pub(crate) fn iter_nth(&self, index: usize) -> Option<i32> {
+        let index = i64::try_from(index).ok()?;
+        let step = self.step.get() as i64;
+        let x = (self.start as i64).checked_add(index.checked_mul(step)?)?;
+        let in_range = if step > 0 {
+            x < self.stop as i64
+        } else {
+            x > self.stop as i64
+        };
+        if in_range { Some(x as i32) } else { None }
+    }
+
     fn rem_range_at_iter(&self, index: usize) -> Option<Range> {
         let index = i64::try_from(index).ok()?;
 
@@ -363,4 +378,26 @@ mod tests {
             &format!("len(range({}, -1))", InlineInt::MIN),
         );
     }
+
+    #[test]
+    fn test_iter_nth() {
+        Heap::temp(|heap| {
+            for r in [
+                range_stop(5),
+                range(5, 0, -2),
+                range(i32::MAX - 3, i32::MAX, 2),
+                range(i32::MIN + 3, i32::MIN, -2),
+                range(3, 3, 1),
+            ] {
+                for index in 0..10 {
+                    unsafe {
+                        assert_eq!(
+                            r.iter_next(index, heap).map(|x| x.unpack_i32().unwrap()),
+                            r.iter_nth(index),
+                        );
+                    }
+                }
+            }
+        });
+    }
 }
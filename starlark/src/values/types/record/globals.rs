@@ -58,6 +58,7 @@ pub(crate) fn register_record(builder: &mut GlobalsBuilder) {
     /// Now the `port` field can be omitted, defaulting to `80` is not present (for example, `MyRecord(host="localhost").port == 80`).
     ///
     /// Records are stored deduplicating their field names, making them more memory efficient than dictionaries.
+    #[starlark(deterministic)]
     fn record<'v>(
         #[starlark(kwargs)] kwargs: SmallMap<String, Value<'v>>,
         eval: &mut Evaluator<'v, '_, '_>,
@@ -84,6 +85,7 @@ pub(crate) fn register_record(builder: &mut GlobalsBuilder) {
     /// rec.mask == 255
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn field<'v>(
         #[starlark(require = pos)] typ: Value<'v>,
         default: Option<Value<'v>>,
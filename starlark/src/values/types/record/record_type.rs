@@ -225,6 +225,10 @@ where
         Ok(())
     }
 
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
     fn invoke(
         &self,
         me: Value<'v>,
@@ -84,6 +84,7 @@ impl<'v> SetFromValue<'v> {
 
 #[starlark_module]
 pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
+    #[starlark(deterministic)]
     fn clear(this: Value) -> anyhow::Result<NoneType> {
         let mut this = SetMut::from_value(this)?;
         this.aref.clear();
@@ -99,6 +100,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.union(y) == set([1, 2, 3, 4, 5])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn union<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -127,6 +129,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.intersection(y) == set([3])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn intersection<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -154,6 +157,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.symmetric_difference(y) == set([1, 2, 4, 5])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn symmetric_difference<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -198,6 +202,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x == set([1, 2, 3, 4])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn add<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] value: Value<'v>,
@@ -216,6 +221,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// list(x) == [1, 3, 2, 4]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn update<'v>(
         this: Value<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -261,6 +267,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.remove(2) # error: not found
     /// # "#, "not found");
     /// ```
+    #[starlark(deterministic)]
     fn remove<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] value: Value<'v>,
@@ -297,6 +304,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x == set([1, 3])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn discard<'v>(
         this: Value<'v>,
         #[starlark(require = pos)] value: Value<'v>,
@@ -325,6 +333,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x == set([1])
     /// # )"#);
     /// ```
+    #[starlark(deterministic)]
     fn pop<'v>(this: Value<'v>) -> starlark::Result<Value<'v>> {
         let mut set = SetMut::from_value(this)?;
         match set.aref.content.pop() {
@@ -341,6 +350,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.difference(y) == set([1, 2])
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn difference<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -376,6 +386,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.issuperset(y) == True
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn issuperset<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -409,6 +420,7 @@ pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
     /// x.issubset(y)
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn issubset<'v>(
         this: SetRef<'v>,
         #[starlark(require=pos)] other: ValueOfUnchecked<'v, StarlarkIter<Value<'v>>>,
@@ -476,6 +488,16 @@ mod tests {
         assert::is_false("bool(set())")
     }
 
+    #[test]
+    fn test_literal() {
+        assert::is_true("s = {1, 2, 1}; s == set([1, 2])");
+        assert::eq("list({3, 1, 2})", "[3, 1, 2]");
+        assert::fail("{1, []}", "not hashable");
+        let mut a = Assert::new();
+        a.dialect_set(|d| d.enable_set_literals = false);
+        a.fail("{1}", "set literals are not allowed");
+    }
+
     #[test]
     fn test_union() {
         assert::eq(
@@ -17,6 +17,7 @@
 
 //! The string type. All strings must be valid UTF8.
 
+pub(crate) mod accumulator;
 mod alloc_unpack;
 pub(crate) mod dot_format;
 pub(crate) mod globals;
@@ -0,0 +1,279 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Buffer for strings built by repeated `+=` in a loop.
+//!
+//! Strings are immutable, so `s += x` copies `s`, and a loop appending to `s`
+//! is quadratic in the length of the result. When the compiler can prove that a
+//! local variable is only appended to in a loop body (it is not read or otherwise
+//! assigned there, and is not captured), it compiles `s += x` and `s = s + x` to
+//! append to a [`StrAccumulator`] stored in the variable instead, and replaces the
+//! accumulator with an ordinary string when the loop exits.
+//!
+//! Only local variables of functions are optimized: variables assigned at the
+//! top level of a module live in module slots, and always hold ordinary values.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use starlark_derive::NoSerialize;
+use starlark_derive::Trace;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::eval::compiler::stmt::add_assign;
+use crate::values::AllocValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+use crate::values::types::string::repr::string_repr;
+
+/// Mutable string buffer, never visible to Starlark code.
+#[derive(Debug, ProvidesStaticType, Trace, NoSerialize, Allocative)]
+pub(crate) struct StrAccumulator {
+    buffer: RefCell<String>,
+}
+
+impl StrAccumulator {
+    fn new(a: &str, b: &str) -> StrAccumulator {
+        let mut buffer = String::with_capacity((a.len() + b.len()) * 2);
+        buffer.push_str(a);
+        buffer.push_str(b);
+        StrAccumulator {
+            buffer: RefCell::new(buffer),
+        }
+    }
+
+    fn to_str_value<'v>(&self, heap: Heap<'v>) -> Value<'v> {
+        heap.alloc(self.buffer.borrow().as_str())
+    }
+
+    /// The string value of `value`, if it is an accumulator, otherwise `value` itself.
+    pub(crate) fn finish<'v>(value: Value<'v>, heap: Heap<'v>) -> Value<'v> {
+        match value.downcast_ref::<StrAccumulator>() {
+            Some(acc) => acc.to_str_value(heap),
+            None => value,
+        }
+    }
+}
+
+/// Append `rhs` to `lhs` if they are strings, otherwise call `add`.
+fn accumulate<'v>(
+    lhs: Value<'v>,
+    rhs: Value<'v>,
+    heap: Heap<'v>,
+    add: fn(Value<'v>, Value<'v>, Heap<'v>) -> crate::Result<Value<'v>>,
+) -> crate::Result<Value<'v>> {
+    if let Some(acc) = lhs.downcast_ref::<StrAccumulator>() {
+        if let Some(rhs) = rhs.unpack_str() {
+            acc.buffer.borrow_mut().push_str(rhs);
+            return Ok(lhs);
+        }
+        return add(acc.to_str_value(heap), rhs, heap);
+    }
+    if let (Some(l), Some(r)) = (lhs.unpack_str(), rhs.unpack_str())
+        && !l.is_empty()
+        && !r.is_empty()
+    {
+        return Ok(heap.alloc(StrAccumulator::new(l, r)));
+    }
+    add(lhs, rhs, heap)
+}
+
+/// Implementation of `s += x` when `s` is an accumulator variable.
+///
+/// Behaves like `add_assign`, except that the result may be an accumulator.
+pub(crate) fn str_accumulate<'v>(
+    lhs: Value<'v>,
+    rhs: Value<'v>,
+    heap: Heap<'v>,
+) -> crate::Result<Value<'v>> {
+    accumulate(lhs, rhs, heap, add_assign)
+}
+
+/// Implementation of `s = s + x` when `s` is an accumulator variable.
+///
+/// Behaves like `Value::add`, except that the result may be an accumulator.
+pub(crate) fn str_accumulate_add<'v>(
+    lhs: Value<'v>,
+    rhs: Value<'v>,
+    heap: Heap<'v>,
+) -> crate::Result<Value<'v>> {
+    accumulate(lhs, rhs, heap, |lhs, rhs, heap| lhs.add(rhs, heap))
+}
+
+impl<'v> AllocValue<'v> for StrAccumulator {
+    fn alloc_value(self, heap: Heap<'v>) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+impl Display for StrAccumulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        string_repr(&self.buffer.borrow(), &mut s);
+        f.write_str(&s)
+    }
+}
+
+#[starlark_value(type = "str_accumulator", NoFreeze)]
+impl<'v> StarlarkValue<'v> for StrAccumulator {}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_accumulate_in_loop() {
+        assert::pass(
+            r#"
+def build(n):
+    s = "<"
+    for i in range(n):
+        s += str(i) + ","
+    s += ">"
+    return s
+assert_eq(build(3), "<0,1,2,>")
+assert_eq(build(0), "<>")
+assert_eq(len(build(1000)), 3892)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_accumulate_break_and_nested() {
+        assert::pass(
+            r#"
+def build():
+    s = ""
+    for i in range(10):
+        for j in range(3):
+            s += "%s" % j
+        if i == 1:
+            break
+        s += "|"
+    return s
+assert_eq(build(), "012|012")
+"#,
+        );
+    }
+
+    #[test]
+    fn test_accumulate_assign_add() {
+        assert::pass(
+            r#"
+def build(xs, sep):
+    s = ""
+    for x in xs:
+        s = s + x
+        s = s + sep
+    return s
+assert_eq(build(["a", "b", "c"], ","), "a,b,c,")
+assert_eq(build([], ","), "")
+"#,
+        );
+    }
+
+    #[test]
+    fn test_accumulate_assign_add_list() {
+        // `s = s + x` creates a new list, while `s += x` extends it.
+        assert::pass(
+            r#"
+def build(x):
+    s = x
+    t = x
+    for i in range(2):
+        s = s + [i]
+    for i in range(2):
+        t += [i]
+    return s, t
+x = []
+assert_eq(build(x), ([0, 1], [0, 1]))
+assert_eq(x, [0, 1])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_accumulate_not_string() {
+        assert::fail(
+            r#"
+def build(x):
+    s = x
+    for i in range(3):
+        s += "y"
+    return s
+build(0)
+"#,
+            "not supported",
+        );
+        assert::fail(
+            r#"
+def build(x):
+    s = "x"
+    for i in range(3):
+        s += "y"
+        s += x
+    return s
+build(1)
+"#,
+            "not supported",
+        );
+    }
+
+    #[test]
+    fn test_accumulate_read_in_loop() {
+        // `s` is read in the loop, so it is not an accumulator.
+        assert::pass(
+            r#"
+def build():
+    s = "a"
+    r = []
+    for i in range(3):
+        s += "b"
+        r.append(s)
+    return r
+assert_eq(build(), ["ab", "abb", "abbb"])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_accumulate_nested_def() {
+        assert::pass(
+            r#"
+def build():
+    s = ""
+    fs = []
+    for i in range(2):
+        def f():
+            t = "a"
+            for j in range(2):
+                t += "b"
+            return t
+        fs.append(f)
+        s += "c"
+    return s + fs[0]() + fs[1]()
+assert_eq(build(), "ccabbabb")
+"#,
+        );
+    }
+}
@@ -23,6 +23,7 @@ use crate::eval::Evaluator;
 use crate::values::StringValue;
 use crate::values::Value;
 use crate::values::ValueLike;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::string::StarlarkStr;
 
 #[starlark_module]
@@ -122,13 +123,15 @@ pub(crate) fn register_str(globals: &mut GlobalsBuilder) {
     ///
     /// If x is a string, the result is x (without quotation).
     /// All other strings, such as elements of a list of strings, are
-    /// double-quoted.
+    /// double-quoted. Bytes are decoded as UTF-8, replacing invalid
+    /// sequences with U+FFFD.
     ///
     /// ```
     /// # starlark::assert::all_true(r#"
     /// str(1)                          == '1'
     /// str("x")                        == 'x'
     /// str([1, "x"])                   == "[1, \"x\"]"
+    /// str(b"x\xff")                   == "x\ufffd"
     /// # "#);
     /// ```
     #[starlark(as_type = StarlarkStr, speculative_exec_safe)]
@@ -139,6 +142,10 @@ pub(crate) fn register_str(globals: &mut GlobalsBuilder) {
         if let Some(a) = StringValue::new(a) {
             // Special case that can avoid reallocating, but is equivalent.
             Ok(a)
+        } else if let Some(b) = StarlarkBytes::from_value(a) {
+            Ok(eval
+                .heap()
+                .alloc_str(&String::from_utf8_lossy(b.as_bytes())))
         } else {
             let mut s = eval.string_pool.alloc();
             a.collect_repr(&mut s);
@@ -24,6 +24,7 @@ use dupe::Dupe;
 use num_traits::Signed;
 use thiserror::Error;
 
+use crate::ErrorCode;
 use crate::values::Heap;
 use crate::values::StringValue;
 use crate::values::UnpackValue;
@@ -58,6 +59,19 @@ enum StringInterpolationError {
     ExpectingFormatCharacter,
 }
 
+impl From<StringInterpolationError> for crate::Error {
+    fn from(e: StringInterpolationError) -> Self {
+        crate::Error::new_other(e).with_code(ErrorCode::new(419))
+    }
+}
+
+impl StringInterpolationError {
+    /// As an `anyhow::Error` which converts back to a `crate::Error` with its code.
+    fn into_anyhow(self) -> anyhow::Error {
+        crate::Error::from(self).into_anyhow()
+    }
+}
+
 enum PercentSFormat {
     /// `%s`.
     Str,
@@ -102,7 +116,7 @@ impl<'a> Iterator for PercentFormatParser<'a> {
             let prev_rem = self.rem;
             let (literal, rem) = self.rem.split_at(index_of_percent);
             match rem.as_bytes().get(1) {
-                None => return Some(Err(StringInterpolationError::IncompleteFormat.into())),
+                None => return Some(Err(StringInterpolationError::IncompleteFormat.into_anyhow())),
                 Some(f) => {
                     let res = match f {
                         b'%' => {
@@ -161,11 +175,13 @@ impl<'a> Iterator for PercentFormatParser<'a> {
                             // Note we need to find the second character, not the second byte.
                             let Some(c) = rem.chars().nth(1) else {
                                 return Some(Err(
-                                    StringInterpolationError::ExpectingFormatCharacter.into(),
+                                    StringInterpolationError::ExpectingFormatCharacter
+                                        .into_anyhow(),
                                 ));
                             };
                             return Some(Err(
-                                StringInterpolationError::UnsupportedFormatCharacter(c).into(),
+                                StringInterpolationError::UnsupportedFormatCharacter(c)
+                                    .into_anyhow(),
                             ));
                         }
                     };
@@ -208,7 +224,7 @@ pub(crate) fn percent(format: &str, value: Value) -> crate::Result<String> {
     let mut next_value = || -> anyhow::Result<Value> {
         values
             .next()
-            .ok_or_else(|| StringInterpolationError::NotEnoughParameters.into())
+            .ok_or_else(|| StringInterpolationError::NotEnoughParameters.into_anyhow())
     };
 
     // because of the way format is defined, we can deal with it as bytes
@@ -347,7 +363,7 @@ pub(crate) fn percent(format: &str, value: Value) -> crate::Result<String> {
         }
     }
     if values.next().is_some() {
-        Err(crate::Error::new_other(
+        Err(crate::Error::from(
             StringInterpolationError::TooManyParameters,
         ))
     } else {
@@ -396,13 +412,13 @@ pub(crate) fn percent_s_one<'v>(
             let one = match Tuple::from_value(arg) {
                 Some(tuple) => match tuple.content() {
                     [] => {
-                        return Err(crate::Error::new_other(
+                        return Err(crate::Error::from(
                             StringInterpolationError::NotEnoughParameters,
                         ));
                     }
                     [value] => *value,
                     [_, _, ..] => {
-                        return Err(crate::Error::new_other(
+                        return Err(crate::Error::from(
                             StringInterpolationError::TooManyParameters,
                         ));
                     }
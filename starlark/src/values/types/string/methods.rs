@@ -121,6 +121,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// list("Hello, 世界".elems()) == ["H", "e", "l", "l", "o", ",", " ", "世", "界"]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn elems<'v>(
         this: StringValue<'v>,
         heap: Heap<'v>,
@@ -171,6 +172,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// list("Hello, 世界".codepoints()) == [72, 101, 108, 108, 111, 44, 32, 19990, 30028]
     /// # "#);
     /// ```
+    #[starlark(deterministic)]
     fn codepoints<'v>(
         this: StringValue<'v>,
         heap: Heap<'v>,
@@ -271,7 +273,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         if let Some(StrIndices { start, haystack }) =
             convert_str_indices(this, start.into_option(), end.into_option())
         {
-            if let Some(index) = haystack.find(needle) {
+            if let Some(index) = fast_string::find(haystack, needle) {
                 let index = fast_string::len(&haystack[..index]);
                 return Ok((start + index).0 as i32);
             }
@@ -372,7 +374,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         if let Some(StrIndices { start, haystack }) =
             convert_str_indices(this, start.into_option(), end.into_option())
         {
-            if let Some(index) = haystack.find(needle) {
+            if let Some(index) = fast_string::find(haystack, needle) {
                 let index = fast_string::len(&haystack[..index]);
                 return Ok((start + index).0 as i32);
             }
@@ -652,9 +654,11 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                         r.push_str(s1);
                         r.push_str(this);
                         r.push_str(s2);
+                        heap.check_string_size(r.len())?;
                         for x in it {
                             r.push_str(this);
                             r.push_str(as_str(x)?.as_str());
+                            heap.check_string_size(r.len())?;
                         }
                         Ok(heap.alloc_typed_unchecked(r))
                     }
@@ -721,7 +725,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                 "Empty separator cannot be used for partitioning"
             ));
         }
-        if let Some(offset) = this.find(needle.as_str()) {
+        if let Some(offset) = fast_string::find(&this, &needle) {
             let offset2 = offset + needle.len();
             Ok((
                 heap.alloc_str(this.get(..offset).unwrap()),
@@ -763,25 +767,47 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         #[starlark(require = pos)] new: &str,
         #[starlark(require = pos)] count: Option<i32>,
         heap: Heap<'v>,
-    ) -> anyhow::Result<StringValue<'v>> {
+    ) -> starlark::Result<StringValue<'v>> {
+        if new.len() > old.len() && heap.max_string_size().is_some() {
+            // Check the size of the result before building it.
+            let matches = if old.is_empty() {
+                this.chars().count() + 1
+            } else {
+                fast_string::match_indices(this.as_str(), old).count()
+            };
+            let matches = match count {
+                Some(count) => cmp::min(matches, cmp::max(0, count) as usize),
+                None => matches,
+            };
+            heap.check_string_size(
+                this.len()
+                    .saturating_add(matches.saturating_mul(new.len() - old.len())),
+            )?;
+        }
         match count {
             Some(count) if count >= 0 => {
                 Ok(heap.alloc_str(&this.replacen(old, new, count as usize)))
             }
-            Some(count) => Err(anyhow::anyhow!(
-                "Replace final argument was negative '{}'",
-                count
-            )),
+            Some(count) => {
+                Err(anyhow::anyhow!("Replace final argument was negative '{}'", count).into())
+            }
             None => {
                 // Optimise `replace` using the Rust standard library definition,
                 // but avoiding redundant allocation in the last step
                 let x = this.as_str();
                 let mut result = String::new();
                 let mut last_end = 0;
-                for (start, part) in x.match_indices(old) {
+                let mut replace_at = |start: usize| {
                     result.push_str(unsafe { x.get_unchecked(last_end..start) });
                     result.push_str(new);
-                    last_end = start + part.len();
+                    last_end = start + old.len();
+                };
+                if old.is_empty() {
+                    // Matches at every character boundary.
+                    x.match_indices(old)
+                        .for_each(|(start, _)| replace_at(start));
+                } else {
+                    fast_string::match_indices(x, old).for_each(replace_at);
                 }
                 if result.is_empty() && last_end == 0 {
                     Ok(this)
@@ -818,7 +844,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         if let Some(StrIndices { start, haystack }) =
             convert_str_indices(this, start.into_option(), end.into_option())
         {
-            if let Some(index) = haystack.rfind(needle) {
+            if let Some(index) = fast_string::rfind(haystack, needle) {
                 let index = fast_string::len(&haystack[..index]);
                 return Ok((start + index).0 as i32);
             }
@@ -853,7 +879,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         if let Some(StrIndices { start, haystack }) =
             convert_str_indices(this, start.into_option(), end.into_option())
         {
-            if let Some(index) = haystack.rfind(needle) {
+            if let Some(index) = fast_string::rfind(haystack, needle) {
                 let index = fast_string::len(&haystack[..index]);
                 return Ok((start + index).0 as i32);
             }
@@ -889,7 +915,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                 "Empty separator cannot be used for partitioning"
             ));
         }
-        if let Some(offset) = this.rfind(needle.as_str()) {
+        if let Some(offset) = fast_string::rfind(&this, &needle) {
             let offset2 = offset + needle.len();
             Ok((
                 heap.alloc_str(this.get(..offset).unwrap()),
@@ -919,7 +945,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn rsplit<'v>(
-        this: &str,
+        this: StringValue<'v>,
         #[starlark(require = pos, default = NoneOr::None)] sep: NoneOr<&str>,
         #[starlark(require = pos, default = NoneOr::None)] maxsplit: NoneOr<i32>,
         heap: Heap<'v>,
@@ -934,10 +960,12 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                 }
             }
         };
+        let substr = |x: &str| heap.alloc_substr(this, x);
+        let this = this.as_str();
         Ok(match sep.into_option() {
             None => match maxsplit {
                 None => heap
-                    .alloc_typed_unchecked(AllocList(this.split_whitespace()))
+                    .alloc_typed_unchecked(AllocList(this.split_whitespace().map(substr)))
                     .cast(),
                 Some(maxsplit) => heap
                     .alloc_typed_unchecked(rsplitn_whitespace(this, maxsplit))
@@ -945,8 +973,9 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
             },
             Some(sep) => {
                 let mut v: Vec<_> = match maxsplit {
-                    None => this.rsplit(sep).collect(),
-                    Some(maxsplit) => this.rsplitn(maxsplit, sep).collect(),
+                    None if !sep.is_empty() => fast_string::rsplit(this, sep).map(substr).collect(),
+                    None => this.rsplit(sep).map(substr).collect(),
+                    Some(maxsplit) => this.rsplitn(maxsplit, sep).map(substr).collect(),
                 };
                 v.reverse();
                 heap.alloc_typed_unchecked(AllocList(v)).cast()
@@ -1018,7 +1047,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn split<'v>(
-        this: &str,
+        this: StringValue<'v>,
         #[starlark(require = pos, default = NoneOr::None)] sep: NoneOr<&str>,
         #[starlark(require = pos, default = NoneOr::None)] maxsplit: NoneOr<i32>,
         heap: Heap<'v>,
@@ -1033,9 +1062,12 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                 }
             }
         };
+        // Pieces longer than a few words reference the storage of `this`.
+        let substr = |x: &str| heap.alloc_substr(this, x);
+        let this = this.as_str();
         Ok(match (sep.into_option(), maxsplit) {
             (None, None) => heap
-                .alloc_typed_unchecked(AllocList(this.split_whitespace()))
+                .alloc_typed_unchecked(AllocList(this.split_whitespace().map(substr)))
                 .cast(),
             (None, Some(maxsplit)) => heap
                 .alloc_typed_unchecked(AllocList(splitn_whitespace(this, maxsplit)))
@@ -1050,17 +1082,20 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                     res.extend(
                         this.as_bytes()
                             .split(|x| *x == b)
-                            .map(|x| unsafe { std::str::from_utf8_unchecked(x) }),
+                            .map(|x| substr(unsafe { std::str::from_utf8_unchecked(x) })),
                     );
                     debug_assert_eq!(res.len(), count + 1);
                     heap.alloc_typed_unchecked(AllocList(res)).cast()
+                } else if !sep.is_empty() {
+                    heap.alloc_typed_unchecked(AllocList(fast_string::split(this, sep).map(substr)))
+                        .cast()
                 } else {
-                    heap.alloc_typed_unchecked(AllocList(this.split(sep)))
+                    heap.alloc_typed_unchecked(AllocList(this.split(sep).map(substr)))
                         .cast()
                 }
             }
             (Some(sep), Some(maxsplit)) => heap
-                .alloc_typed_unchecked(AllocList(this.splitn(maxsplit, sep)))
+                .alloc_typed_unchecked(AllocList(this.splitn(maxsplit, sep).map(substr)))
                 .cast(),
         })
     }
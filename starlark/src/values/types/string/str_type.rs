@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
 use std::cmp;
 use std::cmp::Ordering;
 use std::fmt;
@@ -50,10 +51,12 @@ use crate::values::FreezeResult;
 use crate::values::Freezer;
 use crate::values::Heap;
 use crate::values::StarlarkValue;
+use crate::values::StringValue;
 use crate::values::UnpackValue;
 use crate::values::Value;
 use crate::values::ValueError;
 use crate::values::index::apply_slice;
+use crate::values::layout::pointer::RawPointer;
 use crate::values::none::NoneOr;
 use crate::values::string::interpolation;
 use crate::values::string::repr::string_repr;
@@ -61,6 +64,11 @@ use crate::values::string::repr::string_repr;
 /// The result of calling `type()` on strings.
 pub const STRING_TYPE: &str = "string";
 
+/// Set in `len` of a string which references the storage of another string,
+/// instead of holding its content. The body of such a string is the parent value,
+/// followed by the byte offset of the substring in it.
+const SUBSTR_FLAG: u32 = 1 << 31;
+
 #[repr(C)] // We want the body to come after len
 #[derive(ProvidesStaticType, Allocative)]
 pub(crate) struct StarlarkStrN<const N: usize> {
@@ -108,7 +116,10 @@ impl Deref for StarlarkStr {
 
 impl PartialEq for StarlarkStr {
     fn eq(&self, other: &Self) -> bool {
-        self.as_aligned_padded_str() == other.as_aligned_padded_str()
+        match (self.as_aligned_padded_str(), other.as_aligned_padded_str()) {
+            (Some(x), Some(y)) => x == y,
+            _ => self.as_str() == other.as_str(),
+        }
     }
 }
 
@@ -146,7 +157,7 @@ impl StarlarkStr {
     /// Unsafe because if you do `unpack` on this it will blow up
     #[inline]
     pub(crate) const unsafe fn new(len: usize, hash: StarlarkHashValue) -> Self {
-        assert!(len as u32 as usize == len, "len overflow");
+        assert!(len < SUBSTR_FLAG as usize, "len overflow");
         StarlarkStr {
             str: StarlarkStrN {
                 hash: atomic::AtomicU32::new(hash.get()),
@@ -156,17 +167,56 @@ impl StarlarkStr {
         }
     }
 
+    /// Unsafe for the same reason as `new`, and the body must be filled as described in
+    /// [`SUBSTR_FLAG`].
+    #[inline]
+    pub(crate) unsafe fn new_substr(len: usize) -> Self {
+        let mut s = unsafe { Self::new(len, Self::UNINIT_HASH) };
+        s.str.len |= SUBSTR_FLAG;
+        s
+    }
+
+    #[inline]
+    pub(crate) fn is_substr(&self) -> bool {
+        self.str.len & SUBSTR_FLAG != 0
+    }
+
+    /// For a substring, the string owning its storage, and the byte offset in it.
+    #[inline]
+    pub(crate) fn substr_parent<'v>(&'v self) -> Option<(StringValue<'v>, usize)> {
+        if self.is_substr() {
+            unsafe {
+                let body = self.str.body.as_ptr();
+                let parent = Value::from_ptr_value(RawPointer::new_unchecked(*body));
+                Some((StringValue::new_unchecked(parent), *body.add(1)))
+            }
+        } else {
+            None
+        }
+    }
+
     /// Get a Rust string reference from this Starlark string.
     pub fn as_str(&self) -> &str {
         unsafe {
-            let slice = slice::from_raw_parts(self.str.body.as_ptr() as *const u8, self.len());
+            let data = match self.substr_parent() {
+                None => self.str.body.as_ptr() as *const u8,
+                Some((parent, offset)) => {
+                    (parent.as_ref().str.body.as_ptr() as *const u8).add(offset)
+                }
+            };
+            let slice = slice::from_raw_parts(data, self.len());
             str::from_utf8_unchecked(slice)
         }
     }
 
+    /// `None` for substrings, which are neither aligned nor padded.
     #[inline]
-    pub(crate) fn as_aligned_padded_str(&self) -> AlignedPaddedStr<'_> {
-        unsafe { AlignedPaddedStr::new(self.len(), self.str.body.as_ptr()) }
+    pub(crate) fn as_aligned_padded_str(&self) -> Option<AlignedPaddedStr<'_>> {
+        if self.is_substr() {
+            None
+        } else {
+            Some(unsafe { AlignedPaddedStr::new(self.len(), self.str.body.as_ptr()) })
+        }
     }
 
     /// Get cached hash value or compute if it is not cached yet.
@@ -192,18 +242,48 @@ impl StarlarkStr {
 
     /// String length, in bytes.
     pub fn len(&self) -> usize {
-        self.str.len as usize
+        (self.str.len & !SUBSTR_FLAG) as usize
     }
 
     /// Is this string empty?
     pub fn is_empty(&self) -> bool {
-        self.str.len == 0
+        self.len() == 0
     }
 
     pub(crate) fn offset_of_content() -> usize {
         memoffset::offset_of!(StarlarkStrN<0>, body)
     }
 
+    /// `self[start:stop:stride]`, borrowed from `self` unless the stride is not 1.
+    fn slice_str<'v>(
+        &self,
+        start: Option<Value<'v>>,
+        stop: Option<Value<'v>>,
+        stride: Option<Value<'v>>,
+    ) -> crate::Result<Cow<'_, str>> {
+        if matches!(stride, Some(stride) if stride.unpack_i32() != Some(1)) {
+            // The stride case is super rare and super complex, so let's do something inefficient but safe
+            let xs = self.chars().collect::<Vec<_>>();
+            let xs = apply_slice(&xs, start, stop, stride)?;
+            return Ok(Cow::Owned(xs.into_iter().collect::<String>()));
+        }
+
+        #[inline(always)]
+        fn start_stop_to_none_or(v: Option<Value>) -> crate::Result<NoneOr<i32>> {
+            match v {
+                None => Ok(NoneOr::None),
+                Some(v) => Ok(NoneOr::Other(i32::unpack_value_err(v)?)),
+            }
+        }
+
+        let (start, stop) = (start_stop_to_none_or(start)?, start_stop_to_none_or(stop)?);
+
+        match fast_string::convert_str_indices(self, start.into_option(), stop.into_option()) {
+            Some(StrIndices { haystack, .. }) => Ok(Cow::Borrowed(haystack)),
+            None => Ok(Cow::Borrowed("")),
+        }
+    }
+
     /// Format a Rust string like `repr(s)`.
     pub fn repr(s: &str) -> String {
         let mut buffer = String::new();
@@ -212,6 +292,20 @@ impl StarlarkStr {
     }
 }
 
+/// Slice a string, referencing the storage of `s` rather than copying long results.
+pub(crate) fn slice_string_value<'v>(
+    s: StringValue<'v>,
+    start: Option<Value<'v>>,
+    stop: Option<Value<'v>>,
+    stride: Option<Value<'v>>,
+    heap: Heap<'v>,
+) -> crate::Result<Value<'v>> {
+    Ok(match s.as_ref().slice_str(start, stop, stride)? {
+        Cow::Borrowed(x) => heap.alloc_substr(s, x).to_value(),
+        Cow::Owned(x) => heap.alloc(x),
+    })
+}
+
 /// How to hash a string in a way that is compatible with Value
 #[inline]
 pub(crate) fn hash_string_value<H: Hasher>(x: &str, state: &mut H) {
@@ -319,28 +413,11 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
         stride: Option<Value<'v>>,
         heap: Heap<'v>,
     ) -> crate::Result<Value<'v>> {
-        let s = self;
-        if matches!(stride, Some(stride) if stride.unpack_i32() != Some(1)) {
-            // The stride case is super rare and super complex, so let's do something inefficient but safe
-            let xs = s.chars().collect::<Vec<_>>();
-            let xs = apply_slice(&xs, start, stop, stride)?;
-            return Ok(heap.alloc(xs.into_iter().collect::<String>()));
-        }
-
-        #[inline(always)]
-        fn start_stop_to_none_or(v: Option<Value>) -> crate::Result<NoneOr<i32>> {
-            match v {
-                None => Ok(NoneOr::None),
-                Some(v) => Ok(NoneOr::Other(i32::unpack_value_err(v)?)),
-            }
-        }
-
-        let (start, stop) = (start_stop_to_none_or(start)?, start_stop_to_none_or(stop)?);
-
-        match fast_string::convert_str_indices(self, start.into_option(), stop.into_option()) {
-            Some(StrIndices { haystack, .. }) => Ok(heap.alloc_str(haystack).to_value()),
-            None => Ok(heap.alloc_str("").to_value()),
-        }
+        // `Value::slice` does not get here, but references the storage of the string instead.
+        Ok(match self.slice_str(start, stop, stride)? {
+            Cow::Borrowed(s) => heap.alloc_str(s).to_value(),
+            Cow::Owned(s) => heap.alloc(s),
+        })
     }
 
     fn add(&self, other: Value<'v>, heap: Heap<'v>) -> Option<crate::Result<Value<'v>>> {
@@ -348,6 +425,9 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
             if self.is_empty() {
                 Some(Ok(other))
             } else {
+                if let Err(e) = heap.check_string_size(self.len() + other_str.len()) {
+                    return Some(Err(e));
+                }
                 Some(Ok(heap.alloc_str_concat(self, other_str).to_value()))
             }
         } else {
@@ -361,7 +441,11 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
             Ok(None) => return None,
             Err(e) => return Some(Err(e)),
         };
-        let mut result = String::with_capacity(self.len() * cmp::max(0, l) as usize);
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        if let Err(e) = heap.check_string_size(len) {
+            return Some(Err(e));
+        }
+        let mut result = String::with_capacity(len);
         for _i in 0..l {
             result.push_str(self)
         }
@@ -393,6 +477,11 @@ impl Serialize for StarlarkStr {
 #[cfg(test)]
 mod tests {
     use crate::assert;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
     use crate::values::Heap;
     use crate::values::Value;
     use crate::values::index::apply_slice;
@@ -584,4 +673,74 @@ len("😿") == 1
         }
         Ok(())
     }
+
+    #[test]
+    fn test_substring_references_parent() {
+        Heap::temp(|heap| {
+            let s = heap.alloc_str(&"abcdefgh".repeat(8));
+            let sub = heap.alloc_substr(s, &s.as_str()[3..40]);
+            assert!(sub.as_ref().is_substr());
+            // Substrings of substrings reference the original string.
+            let sub2 = heap.alloc_substr(sub, &sub.as_str()[1..30]);
+            let (parent, offset) = sub2.as_ref().substr_parent().unwrap();
+            assert!(parent.to_value().ptr_eq(s.to_value()));
+            assert_eq!(4, offset);
+            assert_eq!(&s.as_str()[4..33], sub2.as_str());
+            assert_eq!(
+                heap.alloc_str(sub2.as_str()).get_hashed().hash(),
+                sub2.get_hashed().hash()
+            );
+            // Short substrings are copied.
+            assert!(!heap.alloc_substr(s, &s.as_str()[..3]).as_ref().is_substr());
+            // So are substrings which are a small part of their parent.
+            assert!(!heap.alloc_substr(s, &s.as_str()[..15]).as_ref().is_substr());
+            assert!(
+                !heap
+                    .alloc_substr(sub2, &sub2.as_str()[..15])
+                    .as_ref()
+                    .is_substr()
+            );
+        });
+    }
+
+    #[test]
+    fn test_substring_gc_frees_parent() {
+        // Bytes on the heap after GC, when only `s[start:end]` of a large `s` is kept.
+        fn retained(start: usize, end: usize) -> usize {
+            let program =
+                format!("s = str(list(range(100000)))\nkept = s[{start}:{end}]\ns = None");
+            let ast = AstModule::parse("x.star", program, &Dialect::Standard).unwrap();
+            let globals = Globals::standard();
+            Module::with_temp_heap(|module| {
+                let mut eval = Evaluator::new(&module);
+                eval.eval_module(ast, &globals).unwrap();
+                // Nothing is reachable from Rust, so it is safe to collect.
+                unsafe { eval.garbage_collect() };
+                module.heap().allocated_bytes()
+            })
+        }
+
+        let small = retained(5, 105);
+        assert!(small < 100_000, "{small}");
+        // A substring which is most of its parent keeps it alive.
+        let large = retained(5, 500_005);
+        assert!(large > 500_000, "{large}");
+    }
+
+    #[test]
+    fn test_substring_gc_and_freeze() {
+        assert::pass(
+            r#"
+s = "0123456789" * 10
+a = s[5:60]
+b = ("x " * 20 + s).split(" ")[-1]
+garbage_collect()
+assert_eq("56789" + "0123456789" * 5, a)
+assert_eq(s, b)
+assert_eq({a: 1}["56789" + "0123456789" * 5], 1)
+assert_eq(type(a), "string")
+assert_eq(a.split("9")[1], "012345678")
+"#,
+        );
+    }
 }
@@ -78,6 +78,7 @@ pub(crate) fn register_struct(builder: &mut GlobalsBuilder) {
     #[starlark(
         ty_custom_function = StructType,
         as_type = FrozenStruct,
+        deterministic,
     )]
     fn r#struct<'v>(args: &Arguments<'v, '_>, heap: Heap<'v>) -> starlark::Result<Struct<'v>> {
         args.no_positional_args(heap)?;
@@ -81,7 +81,10 @@ impl UnboundValue {
             self.to_frozen_value().to_value(),
             Some(span),
             |eval| match self {
-                UnboundValue::Method(m) => m.function.invoke(eval, this, args),
+                UnboundValue::Method(m) => {
+                    eval.check_deterministic(m.deterministic, &m.name)?;
+                    m.function.invoke(eval, this, args)
+                }
                 UnboundValue::Attr(a) => a.invoke(this, eval.heap()),
             },
         )
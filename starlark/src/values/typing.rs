@@ -23,9 +23,11 @@ pub(crate) mod globals;
 pub(crate) mod iter;
 pub mod macro_refs;
 pub(crate) mod never;
+pub(crate) mod structural_record;
 pub(crate) mod ty;
 pub(crate) mod type_compiled;
 pub(crate) mod type_type;
+pub(crate) mod type_var;
 
 pub use crate::values::types::type_instance_id::TypeInstanceId;
 pub use crate::values::typing::callable::FrozenStarlarkCallable;
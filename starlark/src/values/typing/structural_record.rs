@@ -0,0 +1,205 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use allocative::Allocative;
+use dupe::Dupe;
+use starlark_derive::NoSerialize;
+use starlark_derive::ProvidesStaticType;
+use starlark_map::sorted_map::SortedMap;
+
+use crate as starlark;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::typing::ParamIsRequired;
+use crate::typing::ParamSpec;
+use crate::typing::Ty;
+use crate::typing::TyStructuralRecord;
+use crate::util::arc_str::ArcStr;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocStaticSimple;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::starlark_value;
+use crate::values::structs::AllocStruct;
+use crate::values::typing::type_compiled::compiled::TypeCompiled;
+
+#[derive(Debug, thiserror::Error)]
+enum StructuralRecordError {
+    #[error("Missing field `{0}` of `{1}`")]
+    MissingField(String, String),
+    #[error("`{1}` has no field `{0}`")]
+    UnknownField(String, String),
+}
+
+/// `typing.record`, called like `typing.record(name = str, deps = list[str])`
+/// to declare a structural type.
+#[derive(
+    Debug,
+    derive_more::Display,
+    Allocative,
+    ProvidesStaticType,
+    NoSerialize
+)]
+#[display("{}", Self::TYPE)]
+pub(crate) struct TypingRecord;
+
+#[starlark_value(type = "typing.record")]
+impl<'v> StarlarkValue<'v> for TypingRecord {
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        args.no_positional_args(eval.heap())?;
+        let mut fields = Vec::new();
+        for (name, ty) in args.names_map()? {
+            let ty = TypeCompiled::new(ty, eval.heap())?;
+            fields.push((ArcStr::from(name.as_str()), ty.as_ty().clone()));
+        }
+        Ok(eval
+            .heap()
+            .alloc_simple(StructuralRecordType(TyStructuralRecord {
+                fields: SortedMap::from_iter(fields),
+            })))
+    }
+}
+
+impl AllocFrozenValue for TypingRecord {
+    fn alloc_frozen_value(self, _heap: &FrozenHeap) -> FrozenValue {
+        static RECORD: AllocStaticSimple<TypingRecord> = AllocStaticSimple::alloc(TypingRecord);
+
+        RECORD.to_frozen_value()
+    }
+}
+
+/// A structural type, the result of `typing.record(...)`.
+///
+/// As a type, it matches structs, records and providers with the fields. Calling it
+/// creates a struct with exactly these fields.
+#[derive(
+    Debug,
+    derive_more::Display,
+    Allocative,
+    ProvidesStaticType,
+    NoSerialize
+)]
+#[display("{}", _0)]
+pub(crate) struct StructuralRecordType(pub(crate) TyStructuralRecord);
+
+#[starlark_value(type = "structural_record")]
+impl<'v> StarlarkValue<'v> for StructuralRecordType {
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        args.no_positional_args(eval.heap())?;
+        let named = args.names_map()?;
+        for name in named.keys() {
+            if !self.0.fields.contains_key(name.as_str()) {
+                return Err(crate::Error::new_other(
+                    StructuralRecordError::UnknownField(
+                        name.as_str().to_owned(),
+                        self.0.to_string(),
+                    ),
+                ));
+            }
+        }
+        let mut values = Vec::with_capacity(self.0.fields.len());
+        for (name, ty) in &self.0.fields {
+            let Some((_, value)) = named.iter().find(|(k, _)| k.as_str() == name.as_str()) else {
+                return Err(crate::Error::new_other(
+                    StructuralRecordError::MissingField(
+                        name.as_str().to_owned(),
+                        self.0.to_string(),
+                    ),
+                ));
+            };
+            TypeCompiled::from_ty(ty, eval.heap()).check_type(*value, Some(name.as_str()))?;
+            values.push((name.as_str(), *value));
+        }
+        Ok(eval.heap().alloc(AllocStruct(values)))
+    }
+
+    fn eval_type(&self) -> Option<Ty> {
+        Some(Ty::custom(self.0.clone()))
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        let params = ParamSpec::new_named_only(
+            self.0
+                .fields
+                .iter()
+                .map(|(name, ty)| (name.dupe(), ParamIsRequired::Yes, ty.clone())),
+        )
+        .expect("Field names are unique");
+        Some(Ty::function(params, Ty::custom(self.0.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_structural_record_runtime() {
+        assert::pass(
+            r#"
+Target = typing.record(name = str, deps = list[str])
+
+def name_of(t: Target) -> str:
+    return t.name
+
+assert_eq("a", name_of(struct(name = "a", deps = [])))
+assert_eq("b", name_of(Target(name = "b", deps = ["a"])))
+Extra = record(name = str, deps = list[str], extra = int)
+assert_true(isinstance(Extra(name = "c", deps = [], extra = 1), Target))
+assert_false(isinstance(struct(name = "d"), Target))
+assert_false(isinstance(struct(name = 1, deps = []), Target))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_structural_record_construct() {
+        assert::fail(
+            r#"
+Target = typing.record(name = str, deps = list[str])
+Target(name = "a")
+"#,
+            "Missing field `deps`",
+        );
+        assert::fail(
+            r#"
+Target = typing.record(name = str)
+Target(name = 1)
+"#,
+            "does not match the type annotation `str` for argument `name`",
+        );
+    }
+}
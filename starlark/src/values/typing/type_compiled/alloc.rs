@@ -149,7 +149,7 @@ pub trait TypeMatcherAlloc: Sized {
 
     fn ty_basic(self, ty: &TyBasic) -> Self::Result {
         match ty {
-            TyBasic::Any => self.any(),
+            TyBasic::Any | TyBasic::TypeVar(_) => self.any(),
             TyBasic::StarlarkValue(x) => x.matcher(self),
             TyBasic::List(item) => self.list_of(item),
             TyBasic::Tuple(tuple) => tuple.matcher(self),
@@ -204,11 +204,13 @@ impl<T: TypeMatcher> Display for TypeCompiledImplAsStarlarkValue<T> {
 #[starlark_module]
 fn type_compiled_methods(methods: &mut MethodsBuilder) {
     /// True iff the value matches this type.
+    #[starlark(deterministic)]
     fn matches<'v>(this: Value<'v>, value: Value<'v>) -> anyhow::Result<bool> {
         Ok(this.get_ref().type_matches_value(value))
     }
 
     /// Error if the value does not match this type.
+    #[starlark(deterministic)]
     fn check_matches<'v>(this: Value<'v>, value: Value<'v>) -> anyhow::Result<NoneType> {
         if !this.get_ref().type_matches_value(value) {
             return Err(TypingError::ValueDoesNotMatchType(
@@ -28,6 +28,7 @@ use crate::values::typing::type_compiled::compiled::TypeCompiled;
 #[starlark_module]
 pub(crate) fn register_eval_type(globals: &mut GlobalsBuilder) {
     /// Create a runtime type object which can be used to check if a value matches the given type.
+    #[starlark(deterministic)]
     fn eval_type<'v>(
         #[starlark(require = pos)] ty: ValueOfUnchecked<'v, AbstractType>,
         eval: &mut Evaluator<'v, '_, '_>,
@@ -51,6 +52,7 @@ pub(crate) fn register_eval_type(globals: &mut GlobalsBuilder) {
     /// But last operation can be optimized like this:
     /// `L = eval_type(list); [isinstance(x, L) for x in y]`:
     /// `eval_type()` converts `list` value into prepared type matcher.
+    #[starlark(deterministic)]
     fn isinstance<'v>(
         #[starlark(require = pos)] value: Value<'v>,
         #[starlark(require = pos)] ty: ValueOfUnchecked<'v, AbstractType>,
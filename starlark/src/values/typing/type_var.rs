@@ -0,0 +1,121 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use allocative::Allocative;
+use starlark_derive::NoSerialize;
+use starlark_derive::ProvidesStaticType;
+
+use crate as starlark;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TyTypeVar;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocStaticSimple;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::starlark_value;
+
+/// `typing.TypeVar`, called like `T = typing.TypeVar("T")` to declare a type variable.
+#[derive(
+    Debug,
+    derive_more::Display,
+    Allocative,
+    ProvidesStaticType,
+    NoSerialize
+)]
+#[display("{}", Self::TYPE)]
+pub(crate) struct TypingTypeVar;
+
+#[starlark_value(type = "typing.TypeVar")]
+impl<'v> StarlarkValue<'v> for TypingTypeVar {
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        args.no_named_args()?;
+        let name = args.positional1(eval.heap())?;
+        let name = <&str>::unpack_named_param(name, "name")?;
+        Ok(eval
+            .heap()
+            .alloc_simple(TypingTypeVarValue(TyTypeVar::new(name))))
+    }
+}
+
+impl AllocFrozenValue for TypingTypeVar {
+    fn alloc_frozen_value(self, _heap: &FrozenHeap) -> FrozenValue {
+        static TYPE_VAR: AllocStaticSimple<TypingTypeVar> = AllocStaticSimple::alloc(TypingTypeVar);
+
+        TYPE_VAR.to_frozen_value()
+    }
+}
+
+/// A type variable, the result of `typing.TypeVar("T")`.
+#[derive(
+    Debug,
+    derive_more::Display,
+    Allocative,
+    ProvidesStaticType,
+    NoSerialize
+)]
+#[display("typing.TypeVar(\"{}\")", _0)]
+pub(crate) struct TypingTypeVarValue(pub(crate) TyTypeVar);
+
+#[starlark_value(type = "TypeVar")]
+impl<'v> StarlarkValue<'v> for TypingTypeVarValue {
+    fn eval_type(&self) -> Option<Ty> {
+        Some(Ty::basic(TyBasic::TypeVar(self.0.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_type_var_runtime() {
+        assert::pass(
+            r#"
+T = typing.TypeVar("T")
+
+def first(xs: list[T]) -> T:
+    return xs[0]
+
+assert_eq(1, first([1, 2]))
+assert_eq("a", first(["a"]))
+assert_true(isinstance(1, T))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_type_var_name() {
+        assert::fail(
+            "typing.TypeVar(1)",
+            "Type of parameter `name` doesn't match",
+        );
+    }
+}
@@ -0,0 +1,270 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+use starlark::StarlarkResultExt;
+use starlark::environment::Globals;
+use starlark::syntax::Dialect;
+
+use crate::eval::Context;
+use crate::eval::ContextMode;
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct BenchArgs {
+    #[arg(
+        long = "iterations",
+        default_value_t = 20,
+        help = "How many times to evaluate the file."
+    )]
+    iterations: usize,
+
+    #[arg(
+        long = "warmup",
+        default_value_t = 3,
+        help = "Evaluations to run, and ignore, before measuring."
+    )]
+    warmup: usize,
+
+    #[arg(
+        long = "save",
+        value_name = "FILE",
+        help = "Write the results as JSON, for use with `--baseline`."
+    )]
+    save: Option<PathBuf>,
+
+    #[arg(
+        long = "baseline",
+        value_name = "FILE",
+        help = "Compare against results saved with `--save`."
+    )]
+    baseline: Option<PathBuf>,
+
+    #[arg(
+        long = "threshold",
+        value_name = "PERCENT",
+        default_value_t = 5.0,
+        help = "Fail if the median is more than this much slower than the baseline."
+    )]
+    threshold: f64,
+
+    #[arg(value_name = "FILE", help = "File to evaluate.")]
+    file: PathBuf,
+}
+
+/// Timings in seconds.
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchStats {
+    iterations: usize,
+    mean: f64,
+    median: f64,
+    stddev: f64,
+    min: f64,
+    max: f64,
+}
+
+impl BenchStats {
+    fn new(mut samples: Vec<f64>) -> BenchStats {
+        samples.sort_by(f64::total_cmp);
+        let n = samples.len();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (samples[n / 2 - 1] + samples[n / 2]) / 2.0
+        } else {
+            samples[n / 2]
+        };
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        BenchStats {
+            iterations: n,
+            mean,
+            median,
+            stddev: variance.sqrt(),
+            min: samples[0],
+            max: samples[n - 1],
+        }
+    }
+}
+
+fn ms(secs: f64) -> String {
+    format!("{:.3}ms", secs * 1000.0)
+}
+
+pub(crate) fn bench(args: BenchArgs, dialect: Dialect, globals: Globals) -> anyhow::Result<()> {
+    if args.iterations == 0 {
+        return Err(anyhow::anyhow!("`--iterations` must be at least 1"));
+    }
+    let ctx = Context::new(
+        ContextMode::Run,
+        false,
+        &[],
+        None,
+        dialect,
+        globals,
+        Vec::new(),
+    )?;
+
+    let mut samples = Vec::with_capacity(args.iterations);
+    for i in 0..args.warmup + args.iterations {
+        let start = Instant::now();
+        ctx.load_path(&args.file).into_anyhow_result()?;
+        if i >= args.warmup {
+            samples.push(start.elapsed().as_secs_f64());
+        }
+    }
+    let stats = BenchStats::new(samples);
+    println!(
+        "{}: {} iterations, mean {}, median {}, stddev {}, min {}, max {}",
+        args.file.display(),
+        stats.iterations,
+        ms(stats.mean),
+        ms(stats.median),
+        ms(stats.stddev),
+        ms(stats.min),
+        ms(stats.max)
+    );
+
+    if let Some(save) = &args.save {
+        fs::write(save, serde_json::to_string_pretty(&stats)?)?;
+    }
+
+    if let Some(baseline) = &args.baseline {
+        let baseline: BenchStats = serde_json::from_str(&fs::read_to_string(baseline)?)?;
+        let change = (stats.median - baseline.median) / baseline.median * 100.0;
+        println!(
+            "Median {} vs baseline {}: {change:+.1}%",
+            ms(stats.median),
+            ms(baseline.median)
+        );
+        if change > args.threshold {
+            return Err(anyhow::anyhow!(
+                "Regressed by {change:.1}%, more than the {}% threshold",
+                args.threshold
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: BenchArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<BenchArgs, clap::Error> {
+        Cli::try_parse_from(["bench"].iter().chain(args)).map(|cli| cli.args)
+    }
+
+    fn baseline(median: f64) -> String {
+        serde_json::to_string(&BenchStats {
+            iterations: 1,
+            mean: median,
+            median,
+            stddev: 0.0,
+            min: median,
+            max: median,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_args() {
+        let args = parse(&["x.star"]).unwrap();
+        assert_eq!(20, args.iterations);
+        assert_eq!(3, args.warmup);
+        assert_eq!(5.0, args.threshold);
+        assert_eq!(None, args.save);
+        assert_eq!(None, args.baseline);
+        assert_eq!(PathBuf::from("x.star"), args.file);
+
+        let args = parse(&["--iterations=2", "--warmup=0", "--threshold=10", "x.star"]).unwrap();
+        assert_eq!(2, args.iterations);
+        assert_eq!(0, args.warmup);
+        assert_eq!(10.0, args.threshold);
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--iterations=-1", "x.star"]).is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = BenchStats::new(vec![4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(4, stats.iterations);
+        assert_eq!(2.5, stats.mean);
+        assert_eq!(2.5, stats.median);
+        assert_eq!(1.25f64.sqrt(), stats.stddev);
+        assert_eq!(1.0, stats.min);
+        assert_eq!(4.0, stats.max);
+
+        assert_eq!(2.0, BenchStats::new(vec![3.0, 1.0, 2.0]).median);
+    }
+
+    #[test]
+    fn test_bench() {
+        let dir = temp_dir("bench");
+        write_files(
+            &dir,
+            &[
+                ("x.star", "x = [i for i in range(10)]\n"),
+                ("slow.json", &baseline(1000.0)),
+                ("fast.json", &baseline(1e-12)),
+            ],
+        );
+        let file = dir.join("x.star");
+        let run = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.push(file.to_str().unwrap());
+            bench(
+                parse(&args).unwrap(),
+                Dialect::Standard,
+                Globals::standard(),
+            )
+        };
+
+        let err = run(&["--iterations=0"]).unwrap_err();
+        assert_eq!("`--iterations` must be at least 1", err.to_string());
+
+        let save = dir.join("save.json");
+        run(&[
+            "--iterations=3",
+            "--warmup=1",
+            "--save",
+            save.to_str().unwrap(),
+        ])
+        .unwrap();
+        let saved: BenchStats = serde_json::from_str(&fs::read_to_string(&save).unwrap()).unwrap();
+        assert_eq!(3, saved.iterations);
+
+        let slow = dir.join("slow.json");
+        run(&["--iterations=1", "--baseline", slow.to_str().unwrap()]).unwrap();
+        let fast = dir.join("fast.json");
+        let err = run(&["--iterations=1", "--baseline", fast.to_str().unwrap()]).unwrap_err();
+        assert!(err.to_string().starts_with("Regressed by "), "{err}");
+    }
+}
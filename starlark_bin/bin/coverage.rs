@@ -0,0 +1,168 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Statement coverage reports, from the coverage counted by the evaluator.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use dupe::Dupe;
+use starlark::eval::Coverage;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+use crate::deps::LoadGraph;
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum CoverageFormat {
+    Lcov,
+    Html,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct CoverageArgs {
+    #[arg(
+        long = "coverage",
+        value_name = "FILE",
+        help = "Collect statement coverage of the files and everything they load, \
+and write a report to the file."
+    )]
+    pub(crate) coverage: Option<PathBuf>,
+
+    #[arg(
+        long = "coverage-format",
+        default_value = "lcov",
+        requires = "coverage",
+        help = "Format of the coverage report."
+    )]
+    coverage_format: CoverageFormat,
+}
+
+/// A file covered by the report.
+struct CoveredFile {
+    path: String,
+    source: String,
+}
+
+/// Read and parse `file`, recording its statements in `report`, with the counts of
+/// `coverage`, or zero if they never ran.
+fn file_coverage(
+    file: &Path,
+    dialect: &Dialect,
+    coverage: &Coverage,
+    report: &mut Coverage,
+) -> Option<CoveredFile> {
+    let path = file.to_string_lossy().into_owned();
+    let source = fs::read_to_string(file).ok()?;
+    let ast = AstModule::parse(&path, source.clone(), dialect).ok()?;
+    report.add_module(&ast);
+    for (line, hits) in coverage.lines(&path) {
+        report.add(&path, line, hits);
+    }
+    Some(CoveredFile { path, source })
+}
+
+fn escape_html(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn percent(hit: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / total as f64
+    }
+}
+
+fn html(files: &[CoveredFile], report: &Coverage) -> String {
+    let mut res = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Starlark coverage</title>\n\
+<style>.hit { background: #dfd; } .miss { background: #fdd; } pre { margin: 0; }</style>\n\
+</head>\n<body>\n<h1>Coverage</h1>\n<ul>\n",
+    );
+    for (i, file) in files.iter().enumerate() {
+        let lines = report.lines(&file.path).count();
+        let hit = report
+            .lines(&file.path)
+            .filter(|(_, hits)| *hits != 0)
+            .count();
+        writeln!(
+            res,
+            "<li><a href=\"#file{i}\">{}</a>: {:.1}% of {lines} lines</li>",
+            escape_html(&file.path),
+            percent(hit, lines),
+        )
+        .unwrap();
+    }
+    res.push_str("</ul>\n");
+    for (i, file) in files.iter().enumerate() {
+        writeln!(
+            res,
+            "<h2 id=\"file{i}\">{}</h2>\n<table>",
+            escape_html(&file.path)
+        )
+        .unwrap();
+        for (line, text) in file.source.lines().enumerate() {
+            let class = match report.hits(&file.path, line + 1) {
+                Some(0) => " class=\"miss\"",
+                Some(_) => " class=\"hit\"",
+                None => "",
+            };
+            writeln!(
+                res,
+                "<tr{class}><td>{}</td><td><pre>{}</pre></td></tr>",
+                line + 1,
+                escape_html(text)
+            )
+            .unwrap();
+        }
+        res.push_str("</table>\n");
+    }
+    res.push_str("</body>\n</html>\n");
+    res
+}
+
+impl CoverageArgs {
+    /// Write the report covering `roots` and everything they load.
+    pub(crate) fn write_report(
+        &self,
+        coverage: &Coverage,
+        roots: &[PathBuf],
+        dialect: &Dialect,
+    ) -> anyhow::Result<()> {
+        let Some(output) = &self.coverage else {
+            return Ok(());
+        };
+        let mut report = Coverage::default();
+        let files: Vec<CoveredFile> = LoadGraph::new(roots, dialect)
+            .files
+            .iter()
+            .filter_map(|(file, _)| file_coverage(file, dialect, coverage, &mut report))
+            .collect();
+        let report = match self.coverage_format {
+            CoverageFormat::Lcov => report.to_lcov(),
+            CoverageFormat::Html => html(&files, &report),
+        };
+        fs::write(output, report)?;
+        Ok(())
+    }
+}
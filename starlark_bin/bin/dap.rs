@@ -30,6 +30,7 @@ use starlark::StarlarkResultExt;
 use starlark::debug::DapAdapter;
 use starlark::debug::DapAdapterClient;
 use starlark::debug::DapAdapterEvalHook;
+use starlark::debug::EvaluateExprInfo;
 use starlark::debug::dap_capabilities;
 use starlark::debug::prepare_dap_adapter;
 use starlark::debug::resolve_breakpoints;
@@ -117,6 +118,29 @@ impl Backend {
             AstModule::parse_file(Path::new(source), &self.dialect).into_anyhow_result()?,
         ))
     }
+
+    /// Watches are evaluated each time we stop, so use the value from the last stop,
+    /// adding the expression to the watches the first time it is seen.
+    fn watch(&self, expression: &str) -> anyhow::Result<EvaluateExprInfo> {
+        let watches = self.adapter.watches()?;
+        let existing = watches
+            .iter()
+            .find(|w| w.expression == expression)
+            .map(|w| w.result.clone());
+        match existing {
+            Some(Some(result)) => return result.map_err(anyhow::Error::msg),
+            Some(None) => {}
+            None => {
+                let mut expressions = watches
+                    .into_iter()
+                    .map(|w| w.expression)
+                    .collect::<Vec<_>>();
+                expressions.push(expression.to_owned());
+                self.adapter.set_watches(expressions)?;
+            }
+        }
+        self.adapter.evaluate(expression)
+    }
 }
 
 impl DebugServer for Backend {
@@ -141,6 +165,9 @@ impl DebugServer for Backend {
     }
 
     fn launch(&self, _: LaunchRequestArguments, args: Map<String, Value>) -> anyhow::Result<()> {
+        if let Some(Value::Bool(enabled)) = args.get("showNativeFrames") {
+            self.adapter.set_native_frames(*enabled);
+        }
         // Expecting program of type string
         match args.get("program") {
             Some(Value::String(path)) => {
@@ -204,7 +231,10 @@ impl DebugServer for Backend {
     }
 
     fn evaluate(&self, x: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
-        let expr_result = self.adapter.evaluate(&x.expression)?;
+        let expr_result = match x.context.as_deref() {
+            Some("watch") => self.watch(&x.expression)?,
+            _ => self.adapter.evaluate(&x.expression)?,
+        };
 
         Ok(EvaluateResponseBody {
             indexed_variables: None,
@@ -0,0 +1,270 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The transitive `load()` graph of a set of files.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use dupe::Dupe;
+use starlark::StarlarkResultExt;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+use crate::eval::resolve_load;
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum DepsFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct DepsArgs {
+    #[arg(long = "format", default_value = "dot", help = "Output format.")]
+    format: DepsFormat,
+
+    #[arg(value_name = "FILE", required = true, help = "Files to start from.")]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Dupe, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+/// Files in the order they were first reached, each with the files it loads.
+#[derive(Default)]
+pub(crate) struct LoadGraph {
+    pub(crate) files: Vec<(PathBuf, Vec<PathBuf>)>,
+    /// Each cycle as the path of loads from a file back to itself.
+    pub(crate) cycles: Vec<Vec<PathBuf>>,
+    /// Files which could not be read or parsed.
+    pub(crate) errors: Vec<anyhow::Error>,
+}
+
+impl LoadGraph {
+    pub(crate) fn new(roots: &[PathBuf], dialect: &Dialect) -> LoadGraph {
+        let mut graph = LoadGraph::default();
+        let mut visits = HashMap::new();
+        let mut stack = Vec::new();
+        for root in roots {
+            graph.visit(root.clone(), dialect, &mut visits, &mut stack);
+        }
+        graph
+    }
+
+    fn visit(
+        &mut self,
+        file: PathBuf,
+        dialect: &Dialect,
+        visits: &mut HashMap<PathBuf, Visit>,
+        stack: &mut Vec<PathBuf>,
+    ) {
+        match visits.get(&file) {
+            Some(Visit::Done) => return,
+            Some(Visit::InProgress) => {
+                let start = stack.iter().position(|x| x == &file).unwrap();
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(file);
+                self.cycles.push(cycle);
+                return;
+            }
+            None => {}
+        }
+        visits.insert(file.clone(), Visit::InProgress);
+
+        let loads: Vec<PathBuf> = match AstModule::parse_file(&file, dialect).into_anyhow_result() {
+            Ok(ast) => ast
+                .loads()
+                .iter()
+                .map(|x| resolve_load(x.module_id))
+                .collect(),
+            Err(e) => {
+                self.errors.push(e);
+                Vec::new()
+            }
+        };
+        self.files.push((file.clone(), loads.clone()));
+
+        stack.push(file.clone());
+        for load in loads {
+            self.visit(load, dialect, visits, stack);
+        }
+        stack.pop();
+        visits.insert(file, Visit::Done);
+    }
+
+    fn to_dot(&self) -> String {
+        let mut res = String::from("digraph loads {\n");
+        for (file, loads) in &self.files {
+            res.push_str(&format!("  {:?};\n", file.to_string_lossy()));
+            for load in loads {
+                res.push_str(&format!(
+                    "  {:?} -> {:?};\n",
+                    file.to_string_lossy(),
+                    load.to_string_lossy()
+                ));
+            }
+        }
+        res.push_str("}\n");
+        res
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let path = |x: &PathBuf| x.to_string_lossy().into_owned();
+        serde_json::json!({
+            "files": self
+                .files
+                .iter()
+                .map(|(file, loads)| serde_json::json!({
+                    "path": path(file),
+                    "loads": loads.iter().map(path).collect::<Vec<_>>(),
+                }))
+                .collect::<Vec<_>>(),
+            "cycles": self
+                .cycles
+                .iter()
+                .map(|cycle| cycle.iter().map(path).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+pub(crate) fn deps(args: DepsArgs, dialect: &Dialect) -> anyhow::Result<()> {
+    let graph = LoadGraph::new(&args.files, dialect);
+    match args.format {
+        DepsFormat::Dot => print!("{}", graph.to_dot()),
+        DepsFormat::Json => println!("{}", serde_json::to_string_pretty(&graph.to_json())?),
+    }
+    for e in &graph.errors {
+        eprintln!("{e:#}");
+    }
+    for cycle in &graph.cycles {
+        let cycle: Vec<_> = cycle.iter().map(|x| x.to_string_lossy()).collect();
+        eprintln!("Load cycle: {}", cycle.join(" -> "));
+    }
+    if !graph.errors.is_empty() || !graph.cycles.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Found {} errors and {} load cycles",
+            graph.errors.len(),
+            graph.cycles.len()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::load_path;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    /// `a` loads `b` and `c`, `b` loads `c`, and `c` loads `a`.
+    fn write_cycle(dir: &Path) -> [PathBuf; 3] {
+        let [a, b, c] = ["a.star", "b.star", "c.star"].map(|x| load_path(&dir.join(x)));
+        write_files(
+            dir,
+            &[
+                (
+                    "a.star",
+                    &format!("load('{b}', 'b')\nload('{c}', 'c')\na = b + c\n"),
+                ),
+                ("b.star", &format!("load('{c}', 'c')\nb = c\n")),
+                ("c.star", &format!("load('{a}', 'a')\nc = 1\n")),
+            ],
+        );
+        [a, b, c].map(PathBuf::from)
+    }
+
+    #[test]
+    fn test_load_graph() {
+        let dir = temp_dir("load_graph");
+        let [a, b, c] = write_cycle(&dir);
+        let graph = LoadGraph::new(std::slice::from_ref(&a), &Dialect::Standard);
+        assert_eq!(
+            vec![
+                (a.clone(), vec![b.clone(), c.clone()]),
+                (b.clone(), vec![c.clone()]),
+                (c.clone(), vec![a.clone()]),
+            ],
+            graph.files
+        );
+        assert_eq!(vec![vec![a.clone(), b, c, a]], graph.cycles);
+        assert!(graph.errors.is_empty());
+    }
+
+    #[test]
+    fn test_load_graph_errors() {
+        let dir = temp_dir("load_graph_errors");
+        let missing = dir.join("missing.star");
+        let bad = dir.join("bad.star");
+        write_files(
+            &dir,
+            &[
+                ("a.star", &format!("load('{}', 'x')\n", load_path(&missing))),
+                ("bad.star", "def f(:\n"),
+            ],
+        );
+        let a = dir.join("a.star");
+        let graph = LoadGraph::new(&[a.clone(), bad.clone()], &Dialect::Standard);
+        let missing = PathBuf::from(load_path(&missing));
+        assert_eq!(
+            vec![
+                (a, vec![missing.clone()]),
+                (missing, Vec::new()),
+                (bad, Vec::new()),
+            ],
+            graph.files
+        );
+        assert_eq!(2, graph.errors.len());
+        assert!(graph.cycles.is_empty());
+    }
+
+    #[test]
+    fn test_load_graph_output() {
+        let dir = temp_dir("load_graph_output");
+        let [a, b, c] = write_cycle(&dir);
+        let graph = LoadGraph::new(std::slice::from_ref(&b), &Dialect::Standard);
+        let [a, b, c] = [a, b, c].map(|x| x.to_string_lossy().into_owned());
+
+        assert_eq!(
+            format!(
+                "digraph loads {{\n  {b:?};\n  {b:?} -> {c:?};\n  {c:?};\n  {c:?} -> {a:?};\n  \
+{a:?};\n  {a:?} -> {b:?};\n  {a:?} -> {c:?};\n}}\n"
+            ),
+            graph.to_dot()
+        );
+        assert_eq!(
+            serde_json::json!({
+                "files": [
+                    {"path": b, "loads": [c]},
+                    {"path": c, "loads": [a]},
+                    {"path": a, "loads": [b, c]},
+                ],
+                // `a` loads `c` too, which is still being visited.
+                "cycles": [[b, c, a, b], [c, a, c]],
+            }),
+            graph.to_json()
+        );
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use dupe::Dupe;
+use starlark::StarlarkResultExt;
+use starlark::docs::DocItem;
+use starlark::docs::DocModule;
+use starlark::docs::json::render_docs_json;
+use starlark::docs::markdown::render_doc_item_no_link;
+use starlark::environment::Globals;
+use starlark::syntax::Dialect;
+
+use crate::eval::Context;
+use crate::eval::ContextMode;
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum DocFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct DocArgs {
+    #[arg(long = "format", default_value = "markdown", help = "Output format.")]
+    format: DocFormat,
+
+    #[arg(
+        long = "no-globals",
+        help = "Only document the files, not the built-in globals."
+    )]
+    no_globals: bool,
+
+    #[arg(
+        long = "output",
+        short = 'o',
+        value_name = "FILE",
+        help = "Write the documentation to a file instead of stdout."
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(value_name = "FILE", help = "Starlark files to document.")]
+    files: Vec<PathBuf>,
+}
+
+fn escape_html(x: &str) -> String {
+    let mut res = String::with_capacity(x.len());
+    for c in x.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+/// One section per module, with an anchor per member holding its prototype.
+fn render_html(sections: &[(String, DocItem)]) -> String {
+    let mut res = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Starlark documentation</title>\n</head>\n<body>\n",
+    );
+    for (name, item) in sections {
+        let name = escape_html(name);
+        res.push_str(&format!("<h1 id=\"{name}\">{name}</h1>\n"));
+        if let Some(summary) = item.get_doc_summary() {
+            res.push_str(&format!("<p>{}</p>\n", escape_html(summary)));
+        }
+        let DocItem::Module(module) = item else {
+            continue;
+        };
+        for (member, doc) in &module.members {
+            let code = escape_html(&doc.render_as_code(member));
+            let member = escape_html(member);
+            res.push_str(&format!(
+                "<h2 id=\"{name}.{member}\">{member}</h2>\n<pre><code>{code}</code></pre>\n"
+            ));
+        }
+    }
+    res.push_str("</body>\n</html>\n");
+    res
+}
+
+pub(crate) fn doc(args: DocArgs, dialect: Dialect, globals: Globals) -> anyhow::Result<()> {
+    let mut sections = Vec::new();
+    if !args.no_globals {
+        sections.push((
+            "globals".to_owned(),
+            DocItem::Module(globals.documentation()),
+        ));
+    }
+
+    let ctx = Context::new(
+        ContextMode::Run,
+        false,
+        &[],
+        None,
+        dialect,
+        globals,
+        Vec::new(),
+    )?;
+    for file in &args.files {
+        let module = ctx.load_path(file).into_anyhow_result()?;
+        sections.push((
+            file.to_string_lossy().into_owned(),
+            DocItem::Module(module.documentation()),
+        ));
+    }
+
+    let output = match args.format {
+        DocFormat::Markdown => sections
+            .iter()
+            .map(|(name, item)| render_doc_item_no_link(name, item))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        DocFormat::Html => render_html(&sections),
+        DocFormat::Json => render_docs_json(&DocModule {
+            docs: None,
+            members: sections.into_iter().collect(),
+        }),
+    };
+    match args.output {
+        Some(path) => fs::write(path, output)?,
+        None => println!("{output}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;",
+            escape_html("<a href=\"x\">&</a>")
+        );
+        assert_eq!("it's fine", escape_html("it's fine"));
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::PathBuf;
+
+use starlark::StarlarkResultExt;
+use starlark::environment::Globals;
+use starlark::syntax::Dialect;
+
+use crate::eval::Context;
+use crate::eval::ContextMode;
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct DumpBcArgs {
+    #[arg(value_name = "FILE", help = "File whose functions to disassemble.")]
+    file: PathBuf,
+}
+
+pub(crate) fn dump_bc(args: DumpBcArgs, dialect: Dialect, globals: Globals) -> anyhow::Result<()> {
+    let ctx = Context::new(
+        ContextMode::Run,
+        false,
+        &[],
+        None,
+        dialect,
+        globals,
+        Vec::new(),
+    )?;
+    let module = ctx.load_path(&args.file).into_anyhow_result()?;
+    print!("{}", module.dump_bytecode());
+    Ok(())
+}
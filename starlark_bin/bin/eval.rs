@@ -15,23 +15,35 @@
  * limitations under the License.
  */
 
+use std::cell::Cell;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
 use std::io;
+use std::io::Read;
 use std::iter;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
+use dupe::Dupe;
 use itertools::Either;
 use lsp_types::Url;
+use starlark::ErrorKind;
 use starlark::StarlarkResultExt;
 use starlark::analysis::AstModuleLint;
+use starlark::analysis::Lint;
 use starlark::docs::DocModule;
 use starlark::environment::FrozenModule;
 use starlark::environment::Globals;
 use starlark::environment::Module;
+use starlark::errors::DiagnosticPaths;
+use starlark::errors::DiagnosticRenderOptions;
 use starlark::errors::EvalMessage;
+use starlark::eval::Coverage;
 use starlark::eval::Evaluator;
 use starlark::eval::FileLoader;
 use starlark::syntax::AstModule;
@@ -41,7 +53,9 @@ use starlark_lsp::server::LspContext;
 use starlark_lsp::server::LspEvalResult;
 use starlark_lsp::server::LspUrl;
 use starlark_lsp::server::StringLiteralResult;
+use starlark_map::small_map::SmallMap;
 
+use crate::deps::LoadGraph;
 use crate::suppression::GlobLintSuppression;
 
 #[derive(Debug)]
@@ -50,6 +64,114 @@ pub(crate) enum ContextMode {
     Run,
 }
 
+/// The kind of the first error raised while evaluating, which decides the exit code.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Parse,
+    /// A call to `fail()`, which is what assertion helpers use.
+    Assertion,
+    Eval,
+}
+
+impl Failure {
+    fn new(e: &starlark::Error) -> Self {
+        match e.kind() {
+            ErrorKind::Parser(_) => Failure::Parse,
+            ErrorKind::Fail(_) => Failure::Assertion,
+            _ => Failure::Eval,
+        }
+    }
+}
+
+/// Limits on every evaluation, for running untrusted code.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub(crate) struct Limits {
+    #[arg(
+        long = "max-memory",
+        value_name = "BYTES",
+        help = "Fail evaluations whose heap grows beyond this many bytes (checked periodically)."
+    )]
+    max_memory: Option<usize>,
+
+    #[arg(
+        long = "max-steps",
+        value_name = "STEPS",
+        help = "Fail evaluations after this many function calls and loop iterations."
+    )]
+    max_steps: Option<u64>,
+
+    #[arg(
+        long = "timeout",
+        value_name = "SECONDS",
+        value_parser = parse_timeout,
+        help = "Cancel the evaluation of a file, including what it loads, after this long."
+    )]
+    timeout: Option<Duration>,
+}
+
+/// How errors with a span are printed.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub(crate) struct Rendering {
+    #[arg(long = "color", help = "Color the printed errors.")]
+    color: bool,
+
+    #[arg(
+        long = "context-lines",
+        value_name = "LINES",
+        default_value_t = 0,
+        help = "Print this many lines of source before and after each error."
+    )]
+    context_lines: usize,
+
+    #[arg(
+        long = "unicode",
+        help = "Draw the margins and underlines of errors with unicode box characters."
+    )]
+    unicode: bool,
+
+    #[arg(
+        long = "absolute-paths",
+        conflicts_with = "relative_to",
+        help = "Print the paths in errors as absolute paths."
+    )]
+    absolute_paths: bool,
+
+    #[arg(
+        long = "relative-to",
+        value_name = "DIR",
+        help = "Print the paths in errors relative to this directory."
+    )]
+    relative_to: Option<PathBuf>,
+
+    #[arg(
+        long = "max-stack-frames",
+        value_name = "FRAMES",
+        help = "Print at most this many of the innermost frames of a call stack."
+    )]
+    max_stack_frames: Option<usize>,
+}
+
+impl Rendering {
+    pub(crate) fn options(&self) -> DiagnosticRenderOptions {
+        DiagnosticRenderOptions {
+            color: self.color,
+            context_lines: self.context_lines,
+            unicode: self.unicode,
+            paths: match &self.relative_to {
+                Some(dir) => DiagnosticPaths::RelativeTo(dir.clone()),
+                None if self.absolute_paths => DiagnosticPaths::Absolute,
+                None => DiagnosticPaths::AsGiven,
+            },
+            max_stack_frames: self.max_stack_frames,
+            catalog: None,
+        }
+    }
+}
+
+fn parse_timeout(s: &str) -> anyhow::Result<Duration> {
+    Ok(Duration::try_from_secs_f64(s.parse()?)?)
+}
+
 #[derive(Debug, thiserror::Error)]
 enum ContextError {
     /// The provided Url was not absolute and it needs to be.
@@ -71,14 +193,41 @@ pub(crate) struct Context<'v> {
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
     pub(crate) suppression_rules: Vec<GlobLintSuppression>,
+    /// Bound to the `argv` list in modules being run.
+    pub(crate) argv: Vec<String>,
+    /// The first error of the current run, which decides the exit code.
+    pub(crate) failure: Cell<Option<Failure>>,
+    /// Files `load()`ed directly from the REPL, with when they were loaded.
+    loaded: RefCell<SmallMap<PathBuf, SystemTime>>,
+    load_depth: Cell<usize>,
+    /// When set, every evaluation adds the coverage of the statements it runs here.
+    pub(crate) coverage: Option<RefCell<Coverage>>,
+    pub(crate) limits: Limits,
+    pub(crate) render_options: DiagnosticRenderOptions,
+    /// When the evaluation of the current top-level file times out.
+    deadline: Cell<Option<Instant>>,
 }
 
 impl<'v> FileLoader for Context<'v> {
     fn load(&self, path: &str) -> starlark::Result<FrozenModule> {
-        self.load_path(Path::new(path))
+        let path = resolve_load(path);
+        let top_level = self.load_depth.get() == 0;
+        let loaded_at = SystemTime::now();
+        self.load_depth.set(self.load_depth.get() + 1);
+        let res = self.load_path(&path);
+        self.load_depth.set(self.load_depth.get() - 1);
+        if top_level && self.module.is_some() && res.is_ok() {
+            self.loaded.borrow_mut().insert(path, loaded_at);
+        }
+        res
     }
 }
 
+/// The file a `load()` of `path` refers to. Paths are relative to the working directory.
+pub(crate) fn resolve_load(path: &str) -> PathBuf {
+    PathBuf::from(path)
+}
+
 /// The outcome of evaluating (checking, parsing or running) given starlark code.
 pub(crate) struct EvalResult<T: Iterator<Item = EvalMessage>> {
     /// The diagnostic and error messages from evaluating a given piece of starlark code.
@@ -100,6 +249,17 @@ enum ResolveLoadError {
     WrongScheme(String, LspUrl),
 }
 
+/// Read a file, with `-` meaning stdin.
+fn read_source(file: &Path) -> io::Result<String> {
+    if file == Path::new("-") {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        fs::read_to_string(file)
+    }
+}
+
 impl<'v> Context<'v> {
     pub(crate) fn new(
         mode: ContextMode,
@@ -129,6 +289,14 @@ impl<'v> Context<'v> {
             builtin_docs,
             builtin_symbols,
             suppression_rules,
+            argv: Vec::new(),
+            failure: Cell::new(None),
+            loaded: RefCell::new(SmallMap::new()),
+            load_depth: Cell::new(0),
+            coverage: None,
+            limits: Limits::default(),
+            render_options: DiagnosticRenderOptions::default(),
+            deadline: Cell::new(None),
         };
 
         ctx.prelude = prelude
@@ -146,18 +314,93 @@ impl<'v> Context<'v> {
         Ok(ctx)
     }
 
-    fn load_path(&self, path: &Path) -> starlark::Result<FrozenModule> {
+    pub(crate) fn load_path(&self, path: &Path) -> starlark::Result<FrozenModule> {
         Module::with_temp_heap(|env| {
             {
                 let mut eval = Evaluator::new(&env);
                 eval.set_loader(self);
+                self.prepare_evaluator(&mut eval)?;
                 let module = AstModule::parse_file(path, &self.dialect).into_anyhow_result()?;
-                eval.eval_module(module, &self.globals)?;
+                let res = eval.eval_module(module, &self.globals);
+                self.collect_coverage(&eval);
+                res?;
             }
             Ok::<_, starlark::Error>(env.freeze()?)
         })
     }
 
+    /// Apply the coverage and resource limit settings to a new evaluator.
+    pub(crate) fn prepare_evaluator(&self, eval: &mut Evaluator) -> anyhow::Result<()> {
+        if self.coverage.is_some() {
+            eval.enable_coverage();
+        }
+        if let Some(max_memory) = self.limits.max_memory {
+            eval.set_max_heap_size(max_memory)?;
+        }
+        if let Some(max_steps) = self.limits.max_steps {
+            eval.set_max_tick_count(max_steps)?;
+        }
+        if let Some(timeout) = self.limits.timeout {
+            // Loaded files share the deadline of the file loading them.
+            if self.load_depth.get() == 0 || self.deadline.get().is_none() {
+                self.deadline.set(Some(Instant::now() + timeout));
+            }
+            let deadline = self.deadline.get().unwrap();
+            eval.set_check_cancelled(Box::new(move || Instant::now() > deadline));
+        }
+        Ok(())
+    }
+
+    /// Forget the failure and the coverage of the previous run, before running again,
+    /// e.g. under `--watch`.
+    pub(crate) fn start_run(&self) {
+        self.failure.set(None);
+        if let Some(coverage) = &self.coverage {
+            *coverage.borrow_mut() = Default::default();
+        }
+    }
+
+    pub(crate) fn collect_coverage(&self, eval: &Evaluator) {
+        if let Some(coverage) = &self.coverage {
+            if let Ok(covered) = eval.gen_coverage() {
+                coverage.borrow_mut().merge(&covered);
+            }
+        }
+    }
+
+    /// Load again the files `load()`ed from the REPL which changed, or anything they load
+    /// changed, and bind their exports in the REPL module. Returns the reloaded files.
+    pub(crate) fn reload(&self) -> starlark::Result<Vec<PathBuf>> {
+        let Some(module) = &self.module else {
+            return Ok(Vec::new());
+        };
+        let loaded: Vec<(PathBuf, SystemTime)> = self
+            .loaded
+            .borrow()
+            .iter()
+            .map(|(path, loaded_at)| (path.clone(), *loaded_at))
+            .collect();
+        let mut reloaded = Vec::new();
+        for (path, loaded_at) in loaded {
+            let changed = LoadGraph::new(std::slice::from_ref(&path), &self.dialect)
+                .files
+                .iter()
+                .any(|(file, _)| {
+                    fs::metadata(file)
+                        .and_then(|m| m.modified())
+                        .map_or(true, |modified| modified > loaded_at)
+                });
+            if changed {
+                let now = SystemTime::now();
+                let frozen = self.load_path(&path)?;
+                module.import_public_symbols(&frozen);
+                self.loaded.borrow_mut().insert(path.clone(), now);
+                reloaded.push(path);
+            }
+        }
+        Ok(reloaded)
+    }
+
     fn go(
         &self,
         file: &str,
@@ -181,14 +424,55 @@ impl<'v> Context<'v> {
         }
     }
 
+    fn record_failure(&self, e: &starlark::Error) {
+        if self.failure.get().is_none() {
+            self.failure.set(Some(Failure::new(e)));
+        }
+    }
+
+    /// Import the prelude and bind `argv`, before running a file in `module`.
+    fn prepare_module(&self, module: &Module) {
+        for p in &self.prelude {
+            module.import_public_symbols(p);
+        }
+        module.set("argv", module.heap().alloc(self.argv.clone()));
+    }
+
     // Convert a result over iterator of EvalMessage, into an iterator of EvalMessage
     fn err<T: Iterator<Item = EvalMessage>>(
+        &self,
         file: &str,
         result: starlark::Result<EvalResult<T>>,
     ) -> EvalResult<impl Iterator<Item = EvalMessage> + use<T>> {
+        self.errs(file, result.map_err(|e| vec![e]))
+    }
+
+    // Like `err`, but for all the errors found in a file.
+    fn errs<T: Iterator<Item = EvalMessage>>(
+        &self,
+        file: &str,
+        result: Result<EvalResult<T>, Vec<starlark::Error>>,
+    ) -> EvalResult<impl Iterator<Item = EvalMessage> + use<T>> {
+        if let Err(errors) = &result {
+            if let Some(e) = errors.first() {
+                self.record_failure(e);
+            }
+        }
         match result {
-            Err(e) => EvalResult {
-                messages: Either::Left(iter::once(EvalMessage::from_error(Path::new(file), &e))),
+            Err(errors) => EvalResult {
+                messages: Either::Left(
+                    errors
+                        .iter()
+                        .map(|e| {
+                            EvalMessage::from_error_with_options(
+                                Path::new(file),
+                                e,
+                                &self.render_options,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ),
                 ast: None,
             },
             Ok(res) => EvalResult {
@@ -203,7 +487,7 @@ impl<'v> Context<'v> {
         content: String,
     ) -> EvalResult<impl Iterator<Item = EvalMessage> + use<>> {
         let file = "expression";
-        Self::err(
+        self.err(
             file,
             AstModule::parse(file, content, &self.dialect)
                 .map(|module| self.go(file, module))
@@ -216,9 +500,9 @@ impl<'v> Context<'v> {
         file: &Path,
     ) -> EvalResult<impl Iterator<Item = EvalMessage> + use<>> {
         let filename = &file.to_string_lossy();
-        Self::err(
+        self.err(
             filename,
-            fs::read_to_string(file)
+            read_source(file)
                 .map(|content| self.file_with_contents(filename, content))
                 .map_err(|e| anyhow::Error::from(e).into()),
         )
@@ -229,14 +513,65 @@ impl<'v> Context<'v> {
         filename: &str,
         content: String,
     ) -> EvalResult<impl Iterator<Item = EvalMessage> + use<>> {
-        Self::err(
+        // Report every syntax error, so editors don't need a fix-one-rerun cycle.
+        self.errs(
             filename,
-            AstModule::parse(filename, content, &self.dialect)
-                .map(|module| self.go(filename, module))
-                .map_err(Into::into),
+            AstModule::parse_all(filename, content, &self.dialect)
+                .map(|module| self.go(filename, module)),
         )
     }
 
+    /// Like [`Context::file`], but also returns the public values the file defines which
+    /// can be represented as JSON. Only run mode produces any values.
+    pub(crate) fn file_with_exports(
+        &self,
+        file: &Path,
+    ) -> (Vec<EvalMessage>, serde_json::Map<String, serde_json::Value>) {
+        let filename = &file.to_string_lossy();
+        let ast = match read_source(file)
+            .map_err(|e| anyhow::Error::from(e).into())
+            .and_then(|content| AstModule::parse(filename, content, &self.dialect))
+        {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.record_failure(&e);
+                return (
+                    vec![EvalMessage::from_error_with_options(
+                        file,
+                        &e,
+                        &self.render_options,
+                    )],
+                    serde_json::Map::new(),
+                );
+            }
+        };
+        match self.mode {
+            ContextMode::Check => (self.check(filename, &ast).collect(), serde_json::Map::new()),
+            ContextMode::Run => Module::with_temp_heap(|module| {
+                self.prepare_module(&module);
+                let messages = self
+                    .run_with_module(filename, ast, &module)
+                    .messages
+                    .collect();
+                let values = module
+                    .names()
+                    .filter(|name| {
+                        name.as_str() != "argv"
+                            && !self
+                                .prelude
+                                .iter()
+                                .any(|p| p.names().any(|x| x.as_str() == name.as_str()))
+                    })
+                    .filter_map(|name| {
+                        let value = module.get(name.as_str())?.to_json_value().ok()?;
+                        Some((name.as_str().to_owned(), value))
+                    })
+                    .collect();
+                (messages, values)
+            }),
+        }
+    }
+
     fn run(
         &self,
         file: &str,
@@ -245,9 +580,7 @@ impl<'v> Context<'v> {
         match self.module.as_ref() {
             Some(module) => self.run_with_module(file, ast, module),
             None => Module::with_temp_heap(|module| {
-                for p in &self.prelude {
-                    module.import_public_symbols(p);
-                }
+                self.prepare_module(&module);
                 self.run_with_module(file, ast, &module)
             }),
         }
@@ -262,19 +595,23 @@ impl<'v> Context<'v> {
         let mut eval = Evaluator::new(module);
         eval.set_loader(self);
         eval.enable_terminal_breakpoint_console();
-        Self::err(
+        let res = self
+            .prepare_evaluator(&mut eval)
+            .map_err(starlark::Error::from)
+            .and_then(|()| eval.eval_module(ast, &self.globals));
+        self.collect_coverage(&eval);
+        self.err(
             file,
-            eval.eval_module(ast, &self.globals)
-                .map(|v| {
-                    if self.print_non_none && !v.is_none() {
-                        println!("{v}");
-                    }
-                    EvalResult {
-                        messages: iter::empty(),
-                        ast: None,
-                    }
-                })
-                .map_err(Into::into),
+            res.map(|v| {
+                if self.print_non_none && !v.is_none() {
+                    println!("{v}");
+                }
+                EvalResult {
+                    messages: iter::empty(),
+                    ast: None,
+                }
+            })
+            .map_err(Into::into),
         )
     }
 
@@ -284,7 +621,8 @@ impl<'v> Context<'v> {
             .any(|rule| rule.is_suppressed(file, issue))
     }
 
-    fn check(&self, file: &str, module: &AstModule) -> impl Iterator<Item = EvalMessage> + use<> {
+    /// The lints of `module`, leaving out the suppressed ones.
+    pub(crate) fn lint(&self, file: &str, module: &AstModule) -> Vec<Lint> {
         let globals = if self.prelude.is_empty() {
             None
         } else {
@@ -298,13 +636,18 @@ impl<'v> Context<'v> {
             for global_symbol in self.builtin_symbols.keys() {
                 globals.insert(global_symbol.to_owned());
             }
+            globals.insert("argv".to_owned());
 
             Some(globals)
         };
 
         let mut lints = module.lint(globals.as_ref());
         lints.retain(|issue| !self.is_suppressed(file, &issue.short_name));
-        lints.into_iter().map(EvalMessage::from)
+        lints
+    }
+
+    fn check(&self, file: &str, module: &AstModule) -> impl Iterator<Item = EvalMessage> + use<> {
+        self.lint(file, module).into_iter().map(EvalMessage::from)
     }
 }
 
@@ -400,4 +743,54 @@ impl<'v> LspContext for Context<'v> {
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
         DocModule::default()
     }
+
+    fn get_typecheck_globals(&self, _uri: &LspUrl) -> Option<Globals> {
+        Some(self.globals.dupe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    #[test]
+    fn test_failure() {
+        let dir = temp_dir("failure");
+        write_files(
+            &dir,
+            &[
+                ("parse.star", "def f(:\n"),
+                ("fail.star", "fail('x')\n"),
+                ("eval.star", "x = 1 + 'a'\n"),
+                ("ok.star", "x = 1\n"),
+            ],
+        );
+        let ctx = Context::new(
+            ContextMode::Run,
+            false,
+            &[],
+            None,
+            Dialect::Extended,
+            Globals::standard(),
+            Vec::new(),
+        )
+        .unwrap();
+        let run = |file: &str| {
+            ctx.start_run();
+            ctx.file(&dir.join(file)).messages.count();
+            ctx.failure.get()
+        };
+        assert_eq!(Some(Failure::Parse), run("parse.star"));
+        assert_eq!(None, run("ok.star"));
+        assert_eq!(Some(Failure::Assertion), run("fail.star"));
+        assert_eq!(Some(Failure::Eval), run("eval.star"));
+
+        // The first failure of a run decides the exit code.
+        ctx.start_run();
+        ctx.file(&dir.join("fail.star")).messages.count();
+        ctx.file(&dir.join("parse.star")).messages.count();
+        assert_eq!(Some(Failure::Assertion), ctx.failure.get());
+    }
 }
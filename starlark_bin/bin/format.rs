@@ -0,0 +1,221 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::PathBuf;
+
+use globset::Glob;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use starlark::StarlarkResultExt;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use walkdir::DirEntry;
+use walkdir::WalkDir;
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct FmtArgs {
+    #[arg(
+        long = "check",
+        help = "Do not write anything, fail if any file is not formatted."
+    )]
+    pub(crate) check: bool,
+
+    #[arg(
+        long = "extension",
+        help = "File extensions to format when searching directories [default: bzl, star]."
+    )]
+    pub(crate) extension: Vec<String>,
+
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        help = "Skip files matching the glob pattern."
+    )]
+    pub(crate) exclude: Vec<String>,
+
+    #[arg(
+        value_name = "FILE",
+        help = "Files or directories to format in place. Use `-` or nothing to format stdin to stdout."
+    )]
+    pub(crate) files: Vec<PathBuf>,
+}
+
+fn format_source(filename: &str, content: String, dialect: &Dialect) -> anyhow::Result<String> {
+    AstModule::parse(filename, content, dialect)
+        .and_then(|ast| ast.format())
+        .into_anyhow_result()
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Expand directories into the files with one of the extensions, skipping hidden
+/// directories and anything matching `exclude`.
+fn expand_files(paths: &[PathBuf], extensions: &[String], exclude: &GlobSet) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let entries = WalkDir::new(path)
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !is_hidden(e))
+                .filter_map(|e| e.ok());
+            for entry in entries {
+                let matches_extension = entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| extensions.iter().any(|x| OsStr::new(x) == ext));
+                if entry.file_type().is_file()
+                    && matches_extension
+                    && !exclude.is_match(entry.path())
+                {
+                    files.push(entry.into_path());
+                }
+            }
+        } else if !exclude.is_match(path) {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+pub(crate) fn fmt(args: FmtArgs, dialect: &Dialect) -> anyhow::Result<()> {
+    if args.files.is_empty() || args.files == [PathBuf::from("-")] {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        let formatted = format_source("<stdin>", content.clone(), dialect)?;
+        if !args.check {
+            print!("{formatted}");
+        } else if formatted != content {
+            return Err(anyhow::anyhow!("<stdin> is not formatted"));
+        }
+        return Ok(());
+    }
+
+    let extensions = if args.extension.is_empty() {
+        vec!["bzl".to_owned(), "star".to_owned()]
+    } else {
+        args.extension
+            .iter()
+            .map(|x| x.strip_prefix('.').unwrap_or(x).to_owned())
+            .collect()
+    };
+    let mut exclude = GlobSetBuilder::new();
+    for pattern in &args.exclude {
+        exclude.add(Glob::new(pattern)?);
+    }
+    let exclude = exclude.build()?;
+
+    let mut unformatted = 0;
+    let mut errors = 0;
+    for file in expand_files(&args.files, &extensions, &exclude) {
+        let result = fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                let formatted = format_source(&file.to_string_lossy(), content.clone(), dialect)?;
+                Ok((formatted != content).then_some(formatted))
+            });
+        match result {
+            Ok(None) => {}
+            Ok(Some(formatted)) => {
+                unformatted += 1;
+                if args.check {
+                    println!("{}", file.display());
+                } else {
+                    fs::write(&file, formatted)?;
+                }
+            }
+            Err(e) => {
+                errors += 1;
+                eprintln!("{e:#}");
+            }
+        }
+    }
+
+    if errors > 0 {
+        return Err(anyhow::anyhow!("Failed to format {errors} files"));
+    }
+    if args.check && unformatted > 0 {
+        return Err(anyhow::anyhow!("{unformatted} files are not formatted"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    fn fmt_args(check: bool, exclude: &[&str], files: Vec<PathBuf>) -> FmtArgs {
+        FmtArgs {
+            check,
+            extension: Vec::new(),
+            exclude: exclude.iter().map(|x| (*x).to_owned()).collect(),
+            files,
+        }
+    }
+
+    #[test]
+    fn test_fmt_files() {
+        let dir = temp_dir("fmt_files");
+        write_files(
+            &dir,
+            &[
+                ("a.star", "x=1\n"),
+                ("b.bzl", "y = 2\n"),
+                (".hidden/c.star", "z=3\n"),
+                ("d.txt", "w=4\n"),
+            ],
+        );
+        let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+
+        let err = fmt(fmt_args(true, &[], vec![dir.clone()]), &Dialect::Standard).unwrap_err();
+        assert_eq!("1 files are not formatted", err.to_string());
+        assert_eq!("x=1\n", read("a.star"));
+
+        fmt(fmt_args(false, &[], vec![dir.clone()]), &Dialect::Standard).unwrap();
+        assert_eq!("x = 1\n", read("a.star"));
+        assert_eq!("y = 2\n", read("b.bzl"));
+        assert_eq!("z=3\n", read(".hidden/c.star"));
+        assert_eq!("w=4\n", read("d.txt"));
+
+        fmt(fmt_args(true, &[], vec![dir.clone()]), &Dialect::Standard).unwrap();
+    }
+
+    #[test]
+    fn test_fmt_exclude_and_errors() {
+        let dir = temp_dir("fmt_exclude_and_errors");
+        write_files(&dir, &[("a.star", "x=1\n"), ("bad.star", "def f(:\n")]);
+
+        let err = fmt(fmt_args(false, &[], vec![dir.clone()]), &Dialect::Standard).unwrap_err();
+        assert_eq!("Failed to format 1 files", err.to_string());
+
+        fmt(
+            fmt_args(true, &["**/a.star"], vec![dir.join("a.star")]),
+            &Dialect::Standard,
+        )
+        .unwrap();
+    }
+}
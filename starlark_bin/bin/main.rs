@@ -22,8 +22,13 @@
 use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use clap::Parser;
 use clap::ValueEnum;
@@ -32,7 +37,11 @@ use clap::builder::TypedValueParser;
 use dupe::Dupe;
 use eval::Context;
 use itertools::Either;
+use starlark::StarlarkResultExt;
+use starlark::analysis::Lint;
 use starlark::analysis::LintMessage;
+use starlark::analysis::apply_lint_fixes;
+use starlark::analysis::remove_unused_loads;
 use starlark::docs::DocItem;
 use starlark::docs::markdown::render_doc_item_no_link;
 use starlark::environment::Globals;
@@ -40,20 +49,45 @@ use starlark::environment::Module;
 use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
 use starlark::read_line::ReadLine;
+use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
 use suppression::GlobLintSuppression;
 use walkdir::WalkDir;
 
 use crate::eval::ContextMode;
+use crate::eval::Failure;
+use crate::eval::Limits;
+use crate::eval::Rendering;
 
 mod bazel;
+mod bench;
+mod coverage;
 mod dap;
+mod deps;
+mod doc;
+mod dump_bc;
 mod eval;
+mod format;
+mod profile;
+mod sarif;
 mod suppression;
+mod test_runner;
+#[cfg(test)]
+mod testing;
+mod watch;
 
 #[derive(Debug, Parser)]
-#[command(name = "starlark", about = "Evaluate Starlark code", version)]
+#[command(
+    name = "starlark",
+    about = "Evaluate Starlark code",
+    version,
+    after_help = "Exit codes: 0 on success, 1 on evaluation errors or lints, 2 on invalid \
+arguments, 3 on parse errors, 4 when `fail()` is called, e.g. by a failed assertion."
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(
         long = "lsp",
         help = "Start an LSP server.",
@@ -92,13 +126,58 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "fmt",
+        help = "Format the files in place, or stdin to stdout, like `starlark fmt`.",
+        conflicts_with_all = &["lsp", "dap", "check", "check_fmt", "docs", "evaluate", "watch"],
+    )]
+    fmt: bool,
+
+    #[arg(
+        long = "check-fmt",
+        help = "Fail if any of the files is not formatted, like `starlark fmt --check`.",
+        conflicts_with_all = &["lsp", "dap", "check", "docs", "evaluate", "watch"],
+    )]
+    check_fmt: bool,
+
+    #[arg(
+        long = "fix",
+        help = "Apply machine-applicable lint fixes to the files before checking them.",
+        requires = "check"
+    )]
+    fix: bool,
+
     #[arg(
         long = "json",
-        help = "Show output as JSON lines.",
-        conflicts_with_all = &["lsp", "dap"],
+        help = "Show output as JSON lines, same as `--format=json`.",
+        conflicts_with_all = &["lsp", "dap", "format"],
     )]
     json: bool,
 
+    #[arg(
+        long = "format",
+        help = "How to show the output [default: text].",
+        conflicts_with_all = &["lsp", "dap"],
+    )]
+    format: Option<ArgsFormat>,
+
+    #[arg(
+        long = "fail-on",
+        value_name = "SEVERITY",
+        help = "Exit with failure if there are messages of this severity or above \
+[default: error for text output, never otherwise].",
+        conflicts_with_all = &["lsp", "dap"],
+    )]
+    fail_on: Option<ArgsFailOn>,
+
+    #[arg(
+        long = "output",
+        help = "Show a single document with the diagnostics, exported values and timing of \
+every file, instead of messages as they are found.",
+        conflicts_with_all = &["lsp", "dap", "json", "format"],
+    )]
+    output: Option<ArgsOutput>,
+
     #[arg(
         long = "docs",
         help = "Generate documentation output.",
@@ -106,6 +185,23 @@ struct Args {
     )]
     docs: Option<ArgsDoc>,
 
+    #[arg(
+        long = "watch",
+        help = "Re-run whenever the files, or anything they load, change.",
+        conflicts_with_all = &["lsp", "dap"],
+        requires = "files",
+    )]
+    watch: bool,
+
+    #[command(flatten)]
+    coverage: coverage::CoverageArgs,
+
+    #[command(flatten)]
+    limits: Limits,
+
+    #[command(flatten)]
+    rendering: Rendering,
+
     #[arg(
         long = "extension",
         help = "File extension when searching directories."
@@ -118,6 +214,7 @@ struct Args {
     #[arg(
         long = "expression",
         short = 'e',
+        visible_short_alias = 'c',
         id = "evaluate",
         value_name = "EXPRESSION",
         help = "Expressions to evaluate.",
@@ -129,14 +226,15 @@ struct Args {
     #[arg(
         long = "dialect",
         help = "Dialect to use for features and globals.",
-        default_value = "extended"
+        default_value = "extended",
+        global = true
     )]
     dialect: ArgsDialect,
 
     #[arg(
         id = "files",
         value_name = "FILE",
-        help = "Files to evaluate.",
+        help = "Files to evaluate, `-` for stdin.",
         conflicts_with_all = &["lsp", "dap"],
     )]
     files: Vec<PathBuf>,
@@ -155,6 +253,44 @@ suppress rules for files matching the pattern, in the format of `[<glob>:]<rule>
         value_parser = StringValueParser::new().try_map(GlobLintSuppression::try_parse)
     )]
     suppression: Vec<GlobLintSuppression>,
+
+    #[arg(
+        last = true,
+        value_name = "ARG",
+        help = "Arguments available to the program as `argv`."
+    )]
+    argv: Vec<String>,
+}
+
+/// Process exit codes, as documented in `--help`.
+const EXIT_FAILURE: u8 = 1;
+const EXIT_PARSE_ERROR: u8 = 3;
+const EXIT_ASSERTION_FAILURE: u8 = 4;
+
+/// An error which exits the process with a specific code.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+struct ExitError {
+    code: u8,
+    message: String,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Format files in place, or stdin to stdout.
+    Fmt(format::FmtArgs),
+    /// Render documentation for the built-in globals and the given files.
+    Doc(doc::DocArgs),
+    /// Run the `test_*` functions in `*_test.star` files.
+    Test(test_runner::TestArgs),
+    /// Run a file under a profiler and write the profile to a file.
+    Profile(profile::ProfileArgs),
+    /// Print the bytecode the functions in a file compile to.
+    DumpBc(dump_bc::DumpBcArgs),
+    /// Print the transitive load graph of files.
+    Deps(deps::DepsArgs),
+    /// Evaluate a file repeatedly and report how long it takes.
+    Bench(bench::BenchArgs),
 }
 
 #[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
@@ -164,6 +300,40 @@ enum ArgsDoc {
     Code,
 }
 
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsFormat {
+    Text,
+    /// JSON lines, one message per line.
+    Json,
+    /// A single SARIF log once all files are checked.
+    Sarif,
+}
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsOutput {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsFailOn {
+    Error,
+    Warning,
+    Advice,
+    Never,
+}
+
+impl ArgsFailOn {
+    fn severity(self) -> Option<EvalSeverity> {
+        match self {
+            ArgsFailOn::Error => Some(EvalSeverity::Error),
+            ArgsFailOn::Warning => Some(EvalSeverity::Warning),
+            ArgsFailOn::Advice => Some(EvalSeverity::Advice),
+            ArgsFailOn::Never => None,
+        }
+    }
+}
+
 #[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
 enum ArgsDialect {
     Standard,
@@ -222,16 +392,30 @@ impl Stats {
             EvalSeverity::Disabled => self.disabled += 1,
         }
     }
+
+    /// Number of messages at least as severe as `x`.
+    fn at_least(&self, x: EvalSeverity) -> usize {
+        match x {
+            EvalSeverity::Error => self.error,
+            EvalSeverity::Warning => self.error + self.warning,
+            EvalSeverity::Advice => self.error + self.warning + self.advice,
+            EvalSeverity::Disabled => self.error + self.warning + self.advice + self.disabled,
+        }
+    }
 }
 
+/// Print the messages, or for SARIF, hold them in `sarif` until everything is checked.
 fn drain(
     xs: impl Iterator<Item = EvalMessage>,
-    json: bool,
+    format: ArgsFormat,
     stats: &mut Stats,
+    sarif: &mut Vec<EvalMessage>,
 ) -> anyhow::Result<()> {
     for x in xs {
         stats.increment(x.severity);
-        if json {
+        if format == ArgsFormat::Sarif {
+            sarif.push(x);
+        } else if format == ArgsFormat::Json {
             println!(
                 "{}",
                 serde_json::to_string(&LintMessage::new(x))
@@ -250,13 +434,106 @@ fn drain(
     Ok(())
 }
 
+fn diagnostic_json(x: &EvalMessage) -> serde_json::Value {
+    let span = x.span.map(|span| {
+        serde_json::json!({
+            "begin": { "line": span.begin.line + 1, "column": span.begin.column + 1 },
+            "end": { "line": span.end.line + 1, "column": span.end.column + 1 },
+        })
+    });
+    serde_json::json!({
+        "path": x.path,
+        "span": span,
+        "severity": x.severity,
+        "name": x.name,
+        "description": x.description,
+    })
+}
+
+/// Evaluate or check everything, collecting a JSON document describing each file:
+/// its diagnostics, the data values it exports, and how long it took.
+fn report_json(
+    ctx: &Context,
+    evaluate: Vec<String>,
+    files: impl Iterator<Item = PathBuf>,
+    fix: bool,
+    stats: &mut Stats,
+) -> anyhow::Result<serde_json::Value> {
+    let start = Instant::now();
+    let mut results = Vec::new();
+    let mut add = |path: String,
+                   messages: Vec<EvalMessage>,
+                   values: serde_json::Map<String, serde_json::Value>,
+                   duration: Duration| {
+        stats.increment_file();
+        for x in &messages {
+            stats.increment(x.severity);
+        }
+        results.push(serde_json::json!({
+            "path": path,
+            "diagnostics": messages.iter().map(diagnostic_json).collect::<Vec<_>>(),
+            "values": values,
+            "duration_secs": duration.as_secs_f64(),
+        }));
+    };
+    for e in evaluate {
+        let start = Instant::now();
+        let messages = ctx.expression(e).messages.collect();
+        add(
+            "expression".to_owned(),
+            messages,
+            serde_json::Map::new(),
+            start.elapsed(),
+        );
+    }
+    for file in files {
+        if fix {
+            fix_file(&file, &ctx.dialect, |m| {
+                ctx.lint(&file.to_string_lossy(), m)
+            })?;
+        }
+        let start = Instant::now();
+        let (messages, values) = ctx.file_with_exports(&file);
+        add(
+            file.to_string_lossy().into_owned(),
+            messages,
+            values,
+            start.elapsed(),
+        );
+    }
+    Ok(serde_json::json!({
+        "results": results,
+        "stats": {
+            "files": stats.file,
+            "errors": stats.error,
+            "warnings": stats.warning,
+            "advices": stats.advice,
+            "disabled": stats.disabled,
+        },
+        "duration_secs": start.elapsed().as_secs_f64(),
+    }))
+}
+
 fn interactive(ctx: &Context) -> anyhow::Result<()> {
     let mut rl = ReadLine::new("STARLARK_RUST_HISTFILE")?;
     loop {
         match rl.read_line("$> ")? {
+            Some(line) if line.trim() == ":reload" => match ctx.reload().into_anyhow_result() {
+                Ok(reloaded) => {
+                    for file in reloaded {
+                        println!("Reloaded {}", file.display());
+                    }
+                }
+                Err(e) => eprintln!("{e:#}"),
+            },
             Some(line) => {
                 let mut stats = Stats::default();
-                drain(ctx.expression(line).messages, false, &mut stats)?;
+                drain(
+                    ctx.expression(line).messages,
+                    ArgsFormat::Text,
+                    &mut stats,
+                    &mut Vec::new(),
+                )?;
             }
             // User pressed EOF - disconnected terminal, or similar
             None => return Ok(()),
@@ -264,6 +541,29 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
     }
 }
 
+/// Rewrite the file with the fixes of its `lint`s applied, and then unused loads removed.
+/// Files which fail to parse are left alone, the check reports the error.
+fn fix_file(
+    file: &Path,
+    dialect: &Dialect,
+    lint: impl Fn(&AstModule) -> Vec<Lint>,
+) -> anyhow::Result<()> {
+    let content = fs::read_to_string(file)?;
+    let name = file.to_string_lossy();
+    let Ok(module) = AstModule::parse(&name, content.clone(), dialect).into_anyhow_result() else {
+        return Ok(());
+    };
+    let fixed = apply_lint_fixes(&module, &lint(&module)).into_anyhow_result()?;
+    let fixed = match remove_unused_loads(&name, &fixed).into_anyhow_result() {
+        Ok(Some(x)) => x,
+        _ => fixed,
+    };
+    if fixed != content {
+        fs::write(file, fixed)?;
+    }
+    Ok(())
+}
+
 /// starlark-rust does not support panic.
 /// Terminate on panic even if compiled without `-Cpanic=abort`.
 fn terminate_on_panic() {
@@ -274,7 +574,20 @@ fn terminate_on_panic() {
     }));
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
+    match main_impl() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(
+                e.downcast_ref::<ExitError>()
+                    .map_or(EXIT_FAILURE, |e| e.code),
+            )
+        }
+    }
+}
+
+fn main_impl() -> anyhow::Result<()> {
     terminate_on_panic();
 
     let args = argfile::expand_args(argfile::parse_fromfile, argfile::PREFIX)?;
@@ -285,6 +598,28 @@ fn main() -> anyhow::Result<()> {
         ArgsDialect::Extended => (Dialect::Extended, Globals::extended_internal()),
     };
 
+    if let Some(command) = args.command {
+        return match command {
+            Command::Fmt(fmt_args) => format::fmt(fmt_args, &dialect),
+            Command::Doc(doc_args) => doc::doc(doc_args, dialect, globals),
+            Command::Test(test_args) => test_runner::test(test_args, dialect, globals),
+            Command::Profile(profile_args) => profile::profile(profile_args, dialect, globals),
+            Command::DumpBc(dump_bc_args) => dump_bc::dump_bc(dump_bc_args, dialect, globals),
+            Command::Deps(deps_args) => deps::deps(deps_args, &dialect),
+            Command::Bench(bench_args) => bench::bench(bench_args, dialect, globals),
+        };
+    }
+
+    if args.fmt || args.check_fmt {
+        let fmt_args = format::FmtArgs {
+            check: args.check_fmt,
+            extension: args.extension.into_iter().collect(),
+            exclude: Vec::new(),
+            files: args.files,
+        };
+        return format::fmt(fmt_args, &dialect);
+    }
+
     if args.dap {
         dap::server(dialect, globals);
     } else {
@@ -322,6 +657,12 @@ fn main() -> anyhow::Result<()> {
                 globals,
                 args.suppression,
             )?;
+            ctx.argv = args.argv;
+            ctx.limits = args.limits;
+            ctx.render_options = args.rendering.options();
+            if args.coverage.coverage.is_some() {
+                ctx.coverage = Some(Default::default());
+            }
 
             if args.lsp {
                 ctx.mode = ContextMode::Check;
@@ -345,26 +686,161 @@ fn main() -> anyhow::Result<()> {
             } else if is_interactive {
                 interactive(&ctx)?;
             } else {
-                let mut stats = Stats::default();
-                for e in args.evaluate.clone() {
-                    stats.increment_file();
-                    drain(ctx.expression(e).messages, args.json, &mut stats)?;
+                let run = || -> anyhow::Result<()> {
+                    ctx.start_run();
+                    let format = if args.json {
+                        ArgsFormat::Json
+                    } else {
+                        args.format.unwrap_or(ArgsFormat::Text)
+                    };
+                    let mut stats = Stats::default();
+                    if args.output == Some(ArgsOutput::Json) {
+                        let report = report_json(
+                            &ctx,
+                            args.evaluate.clone(),
+                            expand_dirs(ext, args.files.clone()),
+                            args.fix,
+                            &mut stats,
+                        )?;
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        let mut sarif = Vec::new();
+                        for e in args.evaluate.clone() {
+                            stats.increment_file();
+                            drain(ctx.expression(e).messages, format, &mut stats, &mut sarif)?;
+                        }
+
+                        for file in expand_dirs(ext, args.files.clone()) {
+                            stats.increment_file();
+                            if args.fix {
+                                fix_file(&file, &ctx.dialect, |m| {
+                                    ctx.lint(&file.to_string_lossy(), m)
+                                })?;
+                            }
+                            drain(ctx.file(&file).messages, format, &mut stats, &mut sarif)?;
+                        }
+
+                        match format {
+                            ArgsFormat::Text => println!("{stats}"),
+                            ArgsFormat::Json => {}
+                            ArgsFormat::Sarif => println!(
+                                "{}",
+                                serde_json::to_string_pretty(&sarif::sarif_log(&sarif))?
+                            ),
+                        }
+                    }
+                    if let Some(coverage) = &ctx.coverage {
+                        let files = expand_dirs(ext, args.files.clone()).collect::<Vec<_>>();
+                        args.coverage
+                            .write_report(&coverage.borrow(), &files, &ctx.dialect)?;
+                    }
+                    let text = format == ArgsFormat::Text && args.output != Some(ArgsOutput::Json);
+                    let fail_on = args.fail_on.unwrap_or(if text {
+                        ArgsFailOn::Error
+                    } else {
+                        ArgsFailOn::Never
+                    });
+                    match fail_on.severity() {
+                        Some(EvalSeverity::Error) if stats.error > 0 => {
+                            let code = match ctx.failure.get() {
+                                Some(Failure::Parse) => EXIT_PARSE_ERROR,
+                                Some(Failure::Assertion) => EXIT_ASSERTION_FAILURE,
+                                Some(Failure::Eval) | None => EXIT_FAILURE,
+                            };
+                            return Err(ExitError {
+                                code,
+                                message: format!("Failed with {} errors", stats.error),
+                            }
+                            .into());
+                        }
+                        Some(severity) if stats.at_least(severity) > 0 => {
+                            return Err(anyhow::anyhow!(
+                                "Failed with {} messages of severity {} or above",
+                                stats.at_least(severity),
+                                severity
+                            ));
+                        }
+                        _ => {}
+                    }
+                    Ok(())
+                };
+                if args.watch {
+                    let files = expand_dirs(ext, args.files.clone()).collect::<Vec<_>>();
+                    watch::watch(&args.files, &files, &ctx.dialect, run);
                 }
+                run()?;
+            }
+            Ok::<(), anyhow::Error>(())
+        })?;
+    }
+    Ok(())
+}
 
-                for file in expand_dirs(ext, args.files.clone()) {
-                    stats.increment_file();
-                    drain(ctx.file(&file).messages, args.json, &mut stats)?;
-                }
+#[cfg(test)]
+mod tests {
+    use starlark::analysis::LintContext;
+    use starlark::analysis::LintRule;
+    use starlark::analysis::Linter;
+    use starlark::syntax::ast::Expr;
+    use starlark::syntax::edit::Edit;
+
+    use super::*;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    #[test]
+    fn test_fmt_flags() {
+        let args = Args::try_parse_from(["starlark", "--fmt", "a.star"]).unwrap();
+        assert!(args.fmt);
+        assert!(!args.check_fmt);
+        assert_eq!(vec![PathBuf::from("a.star")], args.files);
+
+        let args = Args::try_parse_from(["starlark", "--check-fmt", "a.star"]).unwrap();
+        assert!(args.check_fmt);
+
+        assert!(Args::try_parse_from(["starlark", "--fmt", "--check-fmt", "a.star"]).is_err());
+        assert!(Args::try_parse_from(["starlark", "--fmt", "--check", "a.star"]).is_err());
+        assert!(Args::try_parse_from(["starlark", "--check-fmt", "--lsp"]).is_err());
+    }
 
-                if !args.json {
-                    println!("{stats}");
-                    if stats.error > 0 {
-                        return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
+    #[test]
+    fn test_fix_file() {
+        struct NoPrint;
+
+        impl LintRule for NoPrint {
+            fn check(&self, ctx: &mut LintContext) {
+                let mut spans = Vec::new();
+                ctx.module().visit_exprs(|x| {
+                    if let Expr::Identifier(name) = &x.node
+                        && name.node.ident == "print"
+                    {
+                        spans.push(x.span);
                     }
+                });
+                for span in spans {
+                    ctx.report_with_fix(
+                        "banned-print",
+                        EvalSeverity::Warning,
+                        span,
+                        "Use `log`",
+                        vec![Edit::replace(span, "log")],
+                    );
                 }
             }
-            Ok(())
-        })?;
+        }
+
+        let dir = temp_dir("fix_file");
+        write_files(
+            &dir,
+            &[("a.star", "load(\"b.star\", \"unused\", \"x\")\nprint(x)\n")],
+        );
+        let mut linter = Linter::new();
+        linter.add_rule(NoPrint);
+        let file = dir.join("a.star");
+        fix_file(&file, &Dialect::Extended, |m| linter.lint(m, None, None)).unwrap();
+        assert_eq!(
+            "load(\"b.star\",  \"x\")\nlog(x)\n",
+            fs::read_to_string(&file).unwrap()
+        );
     }
-    Ok(())
 }
@@ -0,0 +1,101 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs;
+use std::path::PathBuf;
+
+use starlark::StarlarkResultExt;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::eval::ProfileData;
+use starlark::eval::ProfileMode;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+use crate::eval::Context;
+use crate::eval::ContextMode;
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ProfileArgs {
+    #[arg(
+        long = "mode",
+        default_value = "heap-summary-allocated",
+        help = "Profile mode, e.g. `heap-summary-allocated`, `heap-flame-retained`, `statement`, \
+`bytecode`, `time-flame`."
+    )]
+    mode: ProfileMode,
+
+    #[arg(
+        long = "output",
+        short = 'o',
+        value_name = "FILE",
+        help = "Where to write the profile. Flame modes write flamegraph.pl input, the rest CSV."
+    )]
+    output: PathBuf,
+
+    #[arg(value_name = "FILE", help = "File to run.")]
+    file: PathBuf,
+}
+
+fn is_flame(mode: ProfileMode) -> bool {
+    matches!(
+        mode,
+        ProfileMode::TimeFlame | ProfileMode::HeapFlameAllocated | ProfileMode::HeapFlameRetained
+    )
+}
+
+pub(crate) fn profile(args: ProfileArgs, dialect: Dialect, globals: Globals) -> anyhow::Result<()> {
+    let ctx = Context::new(
+        ContextMode::Run,
+        false,
+        &[],
+        None,
+        dialect,
+        globals,
+        Vec::new(),
+    )?;
+    let ast = AstModule::parse_file(&args.file, &ctx.dialect).into_anyhow_result()?;
+
+    let data = Module::with_temp_heap(|module| -> anyhow::Result<ProfileData> {
+        let data = {
+            let mut eval = Evaluator::new(&module);
+            eval.set_loader(&ctx);
+            eval.enable_profile(&args.mode)?;
+            eval.eval_module(ast, &ctx.globals).into_anyhow_result()?;
+            if args.mode.requires_frozen_module() {
+                None
+            } else {
+                Some(eval.gen_profile().into_anyhow_result()?)
+            }
+        };
+        match data {
+            Some(data) => Ok(data),
+            None => module.freeze()?.heap_profile(),
+        }
+    })?;
+
+    let output = if is_flame(args.mode) {
+        data.gen_flame_data()
+            .into_anyhow_result()?
+            .unwrap_or_default()
+    } else {
+        data.gen_csv().into_anyhow_result()?
+    };
+    fs::write(&args.output, output)?;
+    Ok(())
+}
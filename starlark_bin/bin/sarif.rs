@@ -0,0 +1,75 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Render lint messages as a [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! log, which is what most CI systems expect from static analysis tools.
+
+use serde_json::Value;
+use serde_json::json;
+use starlark::errors::EvalMessage;
+use starlark::errors::EvalSeverity;
+use starlark_map::small_set::SmallSet;
+
+fn level(severity: EvalSeverity) -> &'static str {
+    match severity {
+        EvalSeverity::Error => "error",
+        EvalSeverity::Warning => "warning",
+        EvalSeverity::Advice => "note",
+        EvalSeverity::Disabled => "none",
+    }
+}
+
+fn result(message: &EvalMessage) -> Value {
+    let mut physical_location = json!({
+        "artifactLocation": { "uri": message.path },
+    });
+    if let Some(span) = message.span {
+        // SARIF lines and columns are 1-based, ours are 0-based.
+        physical_location["region"] = json!({
+            "startLine": span.begin.line + 1,
+            "startColumn": span.begin.column + 1,
+            "endLine": span.end.line + 1,
+            "endColumn": span.end.column + 1,
+        });
+    }
+    json!({
+        "ruleId": message.name,
+        "level": level(message.severity),
+        "message": { "text": message.description },
+        "locations": [{ "physicalLocation": physical_location }],
+    })
+}
+
+/// A single-run SARIF log containing all the messages.
+pub(crate) fn sarif_log(messages: &[EvalMessage]) -> Value {
+    let rules: SmallSet<&str> = messages.iter().map(|x| x.name.as_str()).collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "starlark",
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                },
+            },
+            "results": messages.iter().map(result).collect::<Vec<_>>(),
+        }],
+    })
+}
@@ -0,0 +1,484 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Run `test_*` functions from `*_test.star` files.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use dupe::Dupe;
+use starlark::assert::asserts_module;
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::eval::FileLoader;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use walkdir::WalkDir;
+
+use crate::coverage::CoverageArgs;
+use crate::eval::Context;
+use crate::eval::ContextMode;
+
+const TEST_FILE_SUFFIX: &str = "_test.star";
+const TEST_FUNCTION_PREFIX: &str = "test_";
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct TestArgs {
+    #[arg(
+        long = "filter",
+        value_name = "SUBSTRING",
+        help = "Only run tests whose name contains the substring."
+    )]
+    filter: Option<String>,
+
+    #[arg(
+        long = "junit",
+        value_name = "FILE",
+        help = "Write the results as JUnit XML."
+    )]
+    junit: Option<PathBuf>,
+
+    #[arg(
+        value_name = "PATH",
+        help = "Test files, or directories to search for `*_test.star` files [default: .]."
+    )]
+    paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    coverage: CoverageArgs,
+}
+
+/// Resolves `asserts.star` to the built-in asserts module, everything else as a path.
+struct TestLoader<'a, 'v> {
+    ctx: &'a Context<'v>,
+    asserts: FrozenModule,
+}
+
+impl FileLoader for TestLoader<'_, '_> {
+    fn load(&self, path: &str) -> starlark::Result<FrozenModule> {
+        if path == "asserts.star" {
+            Ok(self.asserts.dupe())
+        } else {
+            self.ctx.load(path)
+        }
+    }
+}
+
+struct TestResult {
+    name: String,
+    duration: Duration,
+    /// The error if the test failed.
+    failure: Option<String>,
+}
+
+struct FileResult {
+    file: String,
+    /// The error if the file itself failed to load.
+    error: Option<String>,
+    tests: Vec<TestResult>,
+}
+
+impl FileResult {
+    fn failures(&self) -> usize {
+        self.tests.iter().filter(|t| t.failure.is_some()).count()
+            + usize::from(self.error.is_some())
+    }
+}
+
+fn find_test_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = WalkDir::new(path)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.file_type().is_file()
+                        && e.file_name().to_string_lossy().ends_with(TEST_FILE_SUFFIX)
+                })
+                .map(|e| e.into_path())
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn run_file(
+    loader: &TestLoader,
+    globals: &Globals,
+    dialect: &Dialect,
+    file: &Path,
+    filter: Option<&str>,
+) -> FileResult {
+    let mut result = FileResult {
+        file: file.to_string_lossy().into_owned(),
+        error: None,
+        tests: Vec::new(),
+    };
+    Module::with_temp_heap(|module| {
+        module.import_public_symbols(&loader.asserts);
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(loader);
+        let res = loader
+            .ctx
+            .prepare_evaluator(&mut eval)
+            .map_err(starlark::Error::from)
+            .and_then(|()| AstModule::parse_file(file, dialect))
+            .and_then(|ast| eval.eval_module(ast, globals));
+        if let Err(e) = res {
+            loader.ctx.collect_coverage(&eval);
+            result.error = Some(e.to_string());
+            return;
+        }
+
+        let names: Vec<String> = module
+            .names()
+            .map(|name| name.as_str().to_owned())
+            .filter(|name| name.starts_with(TEST_FUNCTION_PREFIX))
+            .filter(|name| filter.is_none_or(|filter| name.contains(filter)))
+            .collect();
+        for name in names {
+            let Some(function) = module.get(&name) else {
+                continue;
+            };
+            if function.get_type() != "function" {
+                continue;
+            }
+            let start = Instant::now();
+            let failure = eval
+                .eval_function(function, &[], &[])
+                .err()
+                .map(|e| e.to_string());
+            result.tests.push(TestResult {
+                name,
+                duration: start.elapsed(),
+                failure,
+            });
+        }
+        loader.ctx.collect_coverage(&eval);
+    });
+    result
+}
+
+fn escape_xml(x: &str) -> String {
+    let mut res = String::with_capacity(x.len());
+    for c in x.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+fn junit_xml(results: &[FileResult]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for file in results {
+        let classname = escape_xml(&file.file);
+        let time = file.tests.iter().map(|t| t.duration).sum::<Duration>();
+        writeln!(
+            xml,
+            "  <testsuite name=\"{classname}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            file.tests.len() + usize::from(file.error.is_some()),
+            file.failures(),
+            time.as_secs_f64(),
+        )
+        .unwrap();
+        if let Some(error) = &file.error {
+            writeln!(
+                xml,
+                "    <testcase name=\"(load)\" classname=\"{classname}\">\n      <error message=\"Failed to load\">{}</error>\n    </testcase>",
+                escape_xml(error)
+            )
+            .unwrap();
+        }
+        for test in &file.tests {
+            write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{classname}\" time=\"{:.3}\"",
+                escape_xml(&test.name),
+                test.duration.as_secs_f64()
+            )
+            .unwrap();
+            match &test.failure {
+                None => xml.push_str("/>\n"),
+                Some(failure) => writeln!(
+                    xml,
+                    ">\n      <failure message=\"Test failed\">{}</failure>\n    </testcase>",
+                    escape_xml(failure)
+                )
+                .unwrap(),
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+pub(crate) fn test(args: TestArgs, dialect: Dialect, globals: Globals) -> anyhow::Result<()> {
+    let paths = if args.paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        args.paths
+    };
+    let mut ctx = Context::new(
+        ContextMode::Run,
+        false,
+        &[],
+        None,
+        dialect,
+        globals,
+        Vec::new(),
+    )?;
+    if args.coverage.coverage.is_some() {
+        ctx.coverage = Some(Default::default());
+    }
+    let loader = TestLoader {
+        ctx: &ctx,
+        asserts: asserts_module(),
+    };
+
+    let files = find_test_files(&paths);
+    let mut results = Vec::new();
+    for file in &files {
+        let result = run_file(
+            &loader,
+            &ctx.globals,
+            &ctx.dialect,
+            file,
+            args.filter.as_deref(),
+        );
+        if let Some(error) = &result.error {
+            println!("ERROR {}\n{error}", result.file);
+        }
+        for test in &result.tests {
+            match &test.failure {
+                None => println!("PASS {}::{}", result.file, test.name),
+                Some(failure) => println!("FAIL {}::{}\n{failure}", result.file, test.name),
+            }
+        }
+        results.push(result);
+    }
+
+    if let Some(coverage) = &ctx.coverage {
+        args.coverage
+            .write_report(&coverage.borrow(), &files, &ctx.dialect)?;
+    }
+    if let Some(junit) = &args.junit {
+        fs::write(junit, junit_xml(&results))?;
+    }
+
+    let total: usize = results
+        .iter()
+        .map(|r| r.tests.len() + usize::from(r.error.is_some()))
+        .sum();
+    let failed: usize = results.iter().map(FileResult::failures).sum();
+    println!("{} passed, {failed} failed", total - failed);
+    if failed > 0 {
+        return Err(anyhow::anyhow!("Failed with {failed} failing tests"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use starlark::environment::Globals;
+    use starlark::syntax::Dialect;
+
+    use super::*;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: TestArgs,
+    }
+
+    const TESTS: &str = r#"
+def helper():
+    return 1
+
+def test_pass():
+    asserts.eq(helper(), 1)
+
+def test_fail():
+    asserts.eq(helper(), 2)
+
+test_not_a_function = 1
+"#;
+
+    fn run(file: &Path, filter: Option<&str>) -> FileResult {
+        let ctx = Context::new(
+            ContextMode::Run,
+            false,
+            &[],
+            None,
+            Dialect::Standard,
+            Globals::standard(),
+            Vec::new(),
+        )
+        .unwrap();
+        let loader = TestLoader {
+            ctx: &ctx,
+            asserts: asserts_module(),
+        };
+        run_file(&loader, &ctx.globals, &ctx.dialect, file, filter)
+    }
+
+    fn names(result: &FileResult) -> Vec<&str> {
+        result.tests.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_find_test_files() {
+        let dir = temp_dir("find_test_files");
+        write_files(
+            &dir,
+            &[
+                ("b_test.star", ""),
+                ("a_test.star", ""),
+                ("sub/c_test.star", ""),
+                ("helper.star", ""),
+                ("sub/test.star", ""),
+            ],
+        );
+        assert_eq!(
+            vec![
+                dir.join("a_test.star"),
+                dir.join("b_test.star"),
+                dir.join("sub").join("c_test.star"),
+                dir.join("helper.star"),
+            ],
+            find_test_files(&[dir.clone(), dir.join("helper.star")])
+        );
+    }
+
+    #[test]
+    fn test_run_file() {
+        let dir = temp_dir("run_file");
+        write_files(&dir, &[("x_test.star", TESTS)]);
+        let result = run(&dir.join("x_test.star"), None);
+        assert_eq!(None, result.error);
+        assert_eq!(vec!["test_pass", "test_fail"], names(&result));
+        assert_eq!(None, result.tests[0].failure);
+        assert!(result.tests[1].failure.is_some());
+        assert_eq!(1, result.failures());
+    }
+
+    #[test]
+    fn test_run_file_filter() {
+        let dir = temp_dir("run_file_filter");
+        write_files(&dir, &[("x_test.star", TESTS)]);
+        let result = run(&dir.join("x_test.star"), Some("pass"));
+        assert_eq!(vec!["test_pass"], names(&result));
+        assert_eq!(0, result.failures());
+    }
+
+    #[test]
+    fn test_run_file_load_error() {
+        let dir = temp_dir("run_file_load_error");
+        write_files(&dir, &[("x_test.star", "def test_x(:\n    pass\n")]);
+        let result = run(&dir.join("x_test.star"), None);
+        assert!(result.error.is_some());
+        assert!(result.tests.is_empty());
+        assert_eq!(1, result.failures());
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let results = [
+            FileResult {
+                file: "a_test.star".to_owned(),
+                error: None,
+                tests: vec![
+                    TestResult {
+                        name: "test_ok".to_owned(),
+                        duration: Duration::ZERO,
+                        failure: None,
+                    },
+                    TestResult {
+                        name: "test_bad".to_owned(),
+                        duration: Duration::from_millis(1500),
+                        failure: Some("1 < 2 & \"x\"".to_owned()),
+                    },
+                ],
+            },
+            FileResult {
+                file: "b_test.star".to_owned(),
+                error: Some("parse error".to_owned()),
+                tests: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="a_test.star" tests="2" failures="1" time="1.500">
+    <testcase name="test_ok" classname="a_test.star" time="0.000"/>
+    <testcase name="test_bad" classname="a_test.star" time="1.500">
+      <failure message="Test failed">1 &lt; 2 &amp; &quot;x&quot;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="b_test.star" tests="1" failures="1" time="0.000">
+    <testcase name="(load)" classname="b_test.star">
+      <error message="Failed to load">parse error</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+            junit_xml(&results)
+        );
+    }
+
+    #[test]
+    fn test_test_command() {
+        let dir = temp_dir("test_command");
+        write_files(&dir, &[("x_test.star", TESTS)]);
+        let junit = dir.join("junit.xml");
+        let run = |args: &[&str]| {
+            let mut argv = vec!["test".to_owned()];
+            argv.extend(args.iter().map(|x| (*x).to_owned()));
+            argv.push(dir.to_str().unwrap().to_owned());
+            let cli = Cli::try_parse_from(argv).unwrap();
+            test(cli.args, Dialect::Standard, Globals::standard())
+        };
+
+        let err = run(&["--junit", junit.to_str().unwrap()]).unwrap_err();
+        assert_eq!("Failed with 1 failing tests", err.to_string());
+        let xml = fs::read_to_string(&junit).unwrap();
+        assert!(xml.contains("name=\"test_pass\""), "{xml}");
+        assert!(xml.contains("name=\"test_fail\""), "{xml}");
+
+        run(&["--filter", "pass"]).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Re-run when any of the files, or anything they load, changes.
+//!
+//! We poll modification times rather than using OS notifications, which keeps us
+//! portable and is cheap for the handful of files a Starlark program loads.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use starlark::syntax::Dialect;
+
+use crate::deps::LoadGraph;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The `paths` and everything `files` load, to check for changes.
+fn watched(paths: &[PathBuf], files: &[PathBuf], dialect: &Dialect) -> Vec<PathBuf> {
+    // Directories are watched too, so adding or removing files triggers a re-run.
+    let mut watched = paths.to_vec();
+    // Files which fail to parse are still watched, so fixing them triggers a re-run.
+    watched.extend(
+        LoadGraph::new(files, dialect)
+            .files
+            .into_iter()
+            .map(|(file, _)| file),
+    );
+    watched
+}
+
+/// Call `run`, then call it again every time one of the watched files changes.
+pub(crate) fn watch(
+    paths: &[PathBuf],
+    files: &[PathBuf],
+    dialect: &Dialect,
+    mut run: impl FnMut() -> anyhow::Result<()>,
+) -> ! {
+    loop {
+        if let Err(e) = run() {
+            eprintln!("{e:#}");
+        }
+
+        let watched = watched(paths, files, dialect);
+        let snapshot: Vec<_> = watched.iter().map(|x| modified(x)).collect();
+        eprintln!("Watching {} files for changes...", watched.len());
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if watched
+                .iter()
+                .map(|x| modified(x))
+                .ne(snapshot.iter().copied())
+            {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::load_path;
+    use crate::testing::temp_dir;
+    use crate::testing::write_files;
+
+    #[test]
+    fn test_watched() {
+        let dir = temp_dir("watched");
+        let b = PathBuf::from(load_path(&dir.join("b.star")));
+        write_files(
+            &dir,
+            &[
+                ("a.star", &format!("load('{}', 'b')\n", load_path(&b))),
+                ("b.star", "b = 1\n"),
+                ("bad.star", "def f(:\n"),
+            ],
+        );
+        let a = dir.join("a.star");
+        let bad = dir.join("bad.star");
+        assert_eq!(
+            vec![dir.clone(), a.clone(), b, bad.clone()],
+            watched(&[dir], &[a, bad], &Dialect::Standard)
+        );
+    }
+}
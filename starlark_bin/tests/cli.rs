@@ -0,0 +1,529 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests of the `starlark` binary, run as a separate process.
+
+use std::env;
+use std::fs;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// A fresh directory for the test `name`, with the given files in it.
+fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("starlark_cli_{name}_{}", std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+struct Output {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run `starlark` with `args` in `dir`, with `stdin` as its standard input.
+fn starlark(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_starlark"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    Output {
+        code: output.status.code().unwrap(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+/// Compare `output` with the golden file `tests/golden/{name}.golden`, or overwrite the
+/// file if `STARLARK_RUST_REGENERATE_GOLDEN_TESTS` is set.
+fn golden(name: &str, output: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.golden"));
+    let output = format!(
+        "# {at}generated\n\
+# To regenerate, run:\n\
+# ```\n\
+# STARLARK_RUST_REGENERATE_GOLDEN_TESTS=1 cargo test -p starlark_bin --test cli\n\
+# ```\n\
+\n\
+{}\n",
+        output.trim_end(),
+        at = "@",
+    );
+    if env::var("STARLARK_RUST_REGENERATE_GOLDEN_TESTS").is_ok() {
+        fs::write(&path, output).unwrap();
+    } else {
+        let expected = fs::read_to_string(&path).unwrap().replace("\r\n", "\n");
+        assert_eq!(expected, output);
+    }
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = temp_dir(
+        "exit_codes",
+        &[
+            ("ok.star", "x = 1\n"),
+            ("eval.star", "x = 1 + 'a'\n"),
+            ("parse.star", "def f(:\n"),
+            ("fail.star", "fail('not equal')\n"),
+        ],
+    );
+    let code = |file| starlark(&dir, &[file], "").code;
+    assert_eq!(0, code("ok.star"));
+    assert_eq!(1, code("eval.star"));
+    assert_eq!(3, code("parse.star"));
+    assert_eq!(4, code("fail.star"));
+    assert_eq!(2, code("--no-such-flag"));
+
+    // The first failure decides the exit code.
+    assert_eq!(3, starlark(&dir, &["parse.star", "fail.star"], "").code);
+    assert_eq!(4, starlark(&dir, &["fail.star", "parse.star"], "").code);
+}
+
+#[test]
+fn test_stdin() {
+    let dir = temp_dir("stdin", &[]);
+    // `print` writes to stderr.
+    let output = starlark(&dir, &["-"], "print(1 + 2)\n");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    assert!(output.stderr.starts_with("3\n"), "{}", output.stderr);
+
+    assert_eq!(3, starlark(&dir, &["-"], "def f(:\n").code);
+}
+
+#[test]
+fn test_expression() {
+    let dir = temp_dir("expression", &[]);
+    let output = starlark(&dir, &["-c", "1 + 2"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    assert!(output.stdout.starts_with("3\n"), "{}", output.stdout);
+
+    assert_eq!(4, starlark(&dir, &["-c", "fail('x')"], "").code);
+}
+
+#[test]
+fn test_argv() {
+    let dir = temp_dir("argv", &[("args.star", "print(argv)\n")]);
+    let output = starlark(&dir, &["args.star", "--", "a", "--b"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    assert!(
+        output.stderr.starts_with("[\"a\", \"--b\"]\n"),
+        "{}",
+        output.stderr
+    );
+
+    let output = starlark(&dir, &["args.star"], "");
+    assert!(output.stderr.starts_with("[]\n"), "{}", output.stderr);
+}
+
+const LINT: &str = "\
+def f():
+    return 1
+    print(2)
+
+def g(x):
+    if x:
+        return 1
+";
+
+#[test]
+fn test_sarif() {
+    let dir = temp_dir("sarif", &[("lint.star", LINT), ("ok.star", "x = 1\n")]);
+    let output = starlark(
+        &dir,
+        &["--check", "--format=sarif", "lint.star", "ok.star"],
+        "",
+    );
+    assert_eq!(0, output.code, "{}", output.stderr);
+    golden(
+        "sarif",
+        &output
+            .stdout
+            .replace(env!("CARGO_PKG_VERSION"), "<version>"),
+    );
+}
+
+#[test]
+fn test_fail_on() {
+    let dir = temp_dir(
+        "fail_on",
+        &[("lint.star", LINT), ("parse.star", "def f(:\n")],
+    );
+    let code = |args: &[&str]| starlark(&dir, args, "").code;
+    // Warnings only fail when asked to.
+    assert_eq!(0, code(&["--check", "lint.star"]));
+    assert_eq!(0, code(&["--check", "--fail-on=error", "lint.star"]));
+    assert_eq!(1, code(&["--check", "--fail-on=warning", "lint.star"]));
+    assert_eq!(1, code(&["--check", "--fail-on=advice", "lint.star"]));
+
+    // Errors fail text output by default, and machine-readable output only when asked to.
+    assert_eq!(3, code(&["--check", "parse.star"]));
+    assert_eq!(0, code(&["--check", "--fail-on=never", "parse.star"]));
+    assert_eq!(0, code(&["--check", "--format=sarif", "parse.star"]));
+    assert_eq!(
+        3,
+        code(&["--check", "--format=sarif", "--fail-on=error", "parse.star"])
+    );
+    assert_eq!(0, code(&["--check", "--json", "parse.star"]));
+}
+
+#[test]
+fn test_fix() {
+    let dir = temp_dir(
+        "fix",
+        &[
+            ("lib.star", "x = 1\ny = 2\n"),
+            ("a.star", "load(\"lib.star\", \"x\", \"y\")\nprint(x)\n"),
+        ],
+    );
+    let output = starlark(&dir, &["--check", "--fix", "a.star"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    assert_eq!(
+        "load(\"lib.star\", \"x\", )\nprint(x)\n",
+        fs::read_to_string(dir.join("a.star")).unwrap()
+    );
+}
+
+#[test]
+fn test_doc() {
+    let dir = temp_dir(
+        "doc",
+        &[(
+            "lib.star",
+            r#""""Helpers for <b> & "quotes"."""
+
+def add(a, b = 1):
+    """Add `a` and `b`, if a < b & "x".
+
+    Args:
+        a: The first.
+        b: The second.
+    """
+    return a + b
+
+LIMIT = 3
+"#,
+        )],
+    );
+    for format in ["markdown", "html", "json"] {
+        let output = starlark(
+            &dir,
+            &["doc", "--no-globals", "--format", format, "lib.star"],
+            "",
+        );
+        assert_eq!(0, output.code, "{}", output.stderr);
+        golden(&format!("doc_{format}"), &output.stdout);
+    }
+}
+
+#[test]
+fn test_profile() {
+    let dir = temp_dir(
+        "profile",
+        &[(
+            "work.star",
+            "\
+def work(n):
+    x = []
+    for i in range(n):
+        x.append(str(i))
+    return x
+
+work(1000)
+",
+        )],
+    );
+    let profile = |mode: &str| {
+        let output = starlark(
+            &dir,
+            &["profile", "--mode", mode, "-o", "out.txt", "work.star"],
+            "",
+        );
+        assert_eq!(0, output.code, "{}", output.stderr);
+        fs::read_to_string(dir.join("out.txt")).unwrap()
+    };
+
+    let csv = profile("heap-summary-allocated");
+    assert!(csv.starts_with("Function,"), "{csv}");
+    assert!(csv.contains("\"work.star.work\""), "{csv}");
+
+    let csv = profile("statement");
+    assert!(csv.starts_with("File,Span,"), "{csv}");
+    assert!(csv.contains("\"work.star\",\"4:9-25\""), "{csv}");
+
+    let csv = profile("bytecode");
+    assert!(csv.starts_with("Opcode,Count,"), "{csv}");
+
+    // Flame modes write `frame;frame value` lines for flamegraph.pl.
+    let flame = profile("heap-flame-allocated");
+    assert!(flame.lines().any(|x| x.starts_with("module;")), "{flame}");
+
+    assert_eq!(
+        2,
+        starlark(
+            &dir,
+            &["profile", "--mode", "nope", "-o", "out.txt", "work.star"],
+            ""
+        )
+        .code
+    );
+}
+
+#[test]
+fn test_output_json() {
+    let dir = temp_dir(
+        "output_json",
+        &[
+            (
+                "values.star",
+                "x = 1\ns = \"a\"\nl = [1, {\"k\": None}]\n_private = 2\ndef f():\n    pass\n",
+            ),
+            ("eval.star", "x = 1 + 'a'\n"),
+        ],
+    );
+    let output = starlark(&dir, &["--output=json", "values.star", "eval.star"], "");
+    // Errors are in the document, not the exit code.
+    assert_eq!(0, output.code, "{}", output.stderr);
+    let mut report: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    assert!(report["duration_secs"].is_f64());
+    report.as_object_mut().unwrap().remove("duration_secs");
+    for result in report["results"].as_array_mut().unwrap() {
+        assert!(result["duration_secs"].is_f64());
+        result.as_object_mut().unwrap().remove("duration_secs");
+    }
+    assert_eq!(
+        serde_json::json!({
+            "results": [
+                {
+                    "path": "values.star",
+                    "diagnostics": [],
+                    // Only exported data values.
+                    "values": { "x": 1, "s": "a", "l": [1, { "k": null }] },
+                },
+                {
+                    "path": "eval.star",
+                    "diagnostics": [{
+                        "path": "eval.star",
+                        "span": {
+                            "begin": { "line": 1, "column": 5 },
+                            "end": { "line": 1, "column": 12 },
+                        },
+                        "severity": "error",
+                        "name": "E0402",
+                        "description":
+                            "Operation `+` not supported for types `int` and `string`",
+                    }],
+                    "values": {},
+                },
+            ],
+            "stats": { "files": 2, "errors": 1, "warnings": 0, "advices": 0, "disabled": 0 },
+        }),
+        report
+    );
+
+    let output = starlark(&dir, &["--check", "--output=json", "values.star"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    let report: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    // Checking doesn't run the file, so there are no values.
+    assert_eq!(serde_json::json!({}), report["results"][0]["values"]);
+
+    assert_eq!(
+        2,
+        starlark(&dir, &["--output=json", "--json", "values.star"], "").code
+    );
+}
+
+#[test]
+fn test_repl_load() {
+    let dir = temp_dir(
+        "repl_load",
+        &[("lib/hello.star", "def hello():\n    return 'v1'\n")],
+    );
+    // Loads in the REPL are relative to the working directory.
+    let output = starlark(&dir, &[], "load('lib/hello.star', 'hello')\nhello()\n");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    assert_eq!("\"v1\"\n", output.stdout);
+}
+
+#[test]
+fn test_repl_reload() {
+    let dir = temp_dir(
+        "repl_reload",
+        &[("hello.star", "def hello():\n    return 'v1'\n")],
+    );
+    let mut child = Command::new(env!("CARGO_BIN_EXE_starlark"))
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = |stdin: &mut dyn Write, input: &str| {
+        stdin.write_all(input.as_bytes()).unwrap();
+        stdin.flush().unwrap();
+        let mut res = String::new();
+        stdout.read_line(&mut res).unwrap();
+        res
+    };
+
+    assert_eq!(
+        "\"v1\"\n",
+        line(&mut stdin, "load('hello.star', 'hello')\nhello()\n")
+    );
+    // Only files changed since they were loaded are reloaded, and the session sees
+    // their new exports.
+    let file = dir.join("hello.star");
+    fs::write(&file, "def hello():\n    return 'v2'\n").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    assert_eq!("Reloaded hello.star\n", line(&mut stdin, ":reload\n"));
+    assert_eq!("\"v2\"\n", line(&mut stdin, "hello()\n"));
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_coverage() {
+    let dir = temp_dir(
+        "coverage",
+        &[
+            (
+                "lib.star",
+                "\
+def used():
+    \"\"\"Docstring.\"\"\"
+    return 1
+
+def unused():
+    pass
+    return 2
+",
+            ),
+            ("main.star", "load('lib.star', 'used')\nused()\n"),
+            (
+                "lib_test.star",
+                "load('lib.star', 'used')\ndef test_used():\n    used()\n",
+            ),
+        ],
+    );
+
+    // Files which are loaded are covered too, including statements which never ran.
+    let output = starlark(&dir, &["--coverage=out.lcov", "main.star"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    golden(
+        "coverage_lcov",
+        &fs::read_to_string(dir.join("out.lcov")).unwrap(),
+    );
+
+    let output = starlark(
+        &dir,
+        &["--coverage=out.html", "--coverage-format=html", "main.star"],
+        "",
+    );
+    assert_eq!(0, output.code, "{}", output.stderr);
+    golden(
+        "coverage_html",
+        &fs::read_to_string(dir.join("out.html")).unwrap(),
+    );
+
+    let output = starlark(&dir, &["test", "--coverage=test.lcov", "lib_test.star"], "");
+    assert_eq!(0, output.code, "{}", output.stderr);
+    let lcov = fs::read_to_string(dir.join("test.lcov")).unwrap();
+    assert!(
+        lcov.contains("SF:lib.star\nDA:1,1\nDA:3,1\nDA:5,1\nDA:7,0\n"),
+        "{lcov}"
+    );
+}
+
+#[test]
+fn test_limits() {
+    let dir = temp_dir(
+        "limits",
+        &[
+            (
+                "small.star",
+                "def f():\n    for i in range(10):\n        pass\nf()\n",
+            ),
+            (
+                "loop.star",
+                "def f():\n    for i in range(100000000):\n        pass\nf()\n",
+            ),
+            (
+                "memory.star",
+                "x = []\nfor i in range(10000000):\n    x.append(str(i))\n",
+            ),
+            ("load.star", "load('loop.star', 'f')\n"),
+        ],
+    );
+    let run = |args: &[&str]| {
+        let output = starlark(&dir, args, "");
+        (output.code, output.stdout + &output.stderr)
+    };
+
+    assert_eq!(0, run(&["--max-steps=1000", "small.star"]).0);
+    let (code, output) = run(&["--max-steps=1000", "loop.star"]);
+    assert_eq!(1, code, "{output}");
+    assert!(output.contains("limit of 1000 ticks"), "{output}");
+
+    let (code, output) = run(&["--max-memory=1000000", "memory.star"]);
+    assert_eq!(1, code, "{output}");
+    assert!(output.contains("memory limit of 1000000 bytes"), "{output}");
+
+    // Loaded files count towards the timeout of the file loading them.
+    for file in ["loop.star", "load.star"] {
+        let (code, output) = run(&["--timeout=0.2", file]);
+        assert_eq!(1, code, "{output}");
+        assert!(output.contains("cancelled"), "{output}");
+    }
+
+    assert_eq!(2, run(&["--timeout=soon", "small.star"]).0);
+}
@@ -0,0 +1,59 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * C ABI for the starlark-rust interpreter.
+ *
+ * Functions taking `char **error` set it to NULL on success, and to a message on
+ * failure. Every returned string, including errors, must be released with
+ * `starlark_string_free`, and every module with `starlark_module_free`.
+ * All strings are NUL-terminated UTF-8.
+ */
+
+#ifndef STARLARK_H
+#define STARLARK_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An evaluated, frozen, module. */
+typedef struct StarlarkModule StarlarkModule;
+
+/* Parse `source`, returning 0 if it is valid, and otherwise 1 and the syntax error. */
+int starlark_parse(const char *filename, const char *source, char **error);
+
+/* Evaluate `source` with the standard globals. Returns NULL on failure. */
+StarlarkModule *starlark_eval(const char *filename, const char *source, char **error);
+
+/* The global `name` of the module as JSON. Returns NULL on failure. */
+char *starlark_module_get_json(const StarlarkModule *module, const char *name, char **error);
+
+/* The names of the public globals of the module, as a JSON array of strings. */
+char *starlark_module_names_json(const StarlarkModule *module);
+
+/* Release a module. NULL is ignored. */
+void starlark_module_free(StarlarkModule *module);
+
+/* Release a string. NULL is ignored. */
+void starlark_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STARLARK_H */
@@ -0,0 +1,277 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A C ABI for parsing and evaluating Starlark, declared in `include/starlark.h`.
+//!
+//! Evaluated modules are returned as opaque handles, and values cross the boundary as
+//! JSON strings. Functions which can fail take a `char **error` out-parameter, set to
+//! a message on failure. Every string returned must be released with
+//! `starlark_string_free`, and every module with `starlark_module_free`.
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+/// An evaluated, frozen, module.
+pub struct StarlarkModule(FrozenModule);
+
+fn into_c_string(s: String) -> *mut c_char {
+    // Interior NULs can't be represented, so truncate at the first one.
+    let s = match CString::new(s) {
+        Ok(s) => s,
+        Err(e) => {
+            let nul = e.nul_position();
+            let mut bytes = e.into_vec();
+            bytes.truncate(nul);
+            CString::new(bytes).unwrap()
+        }
+    };
+    s.into_raw()
+}
+
+/// Read a string argument, which must be non-null and UTF-8.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("`{what}` is null"));
+    }
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| format!("`{what}` is not valid UTF-8"))
+}
+
+/// Run `f`, storing any error or panic in `error`, and returning `default` in that case.
+///
+/// # Safety
+///
+/// `error` must be null or valid for writes.
+unsafe fn guard<T>(
+    error: *mut *mut c_char,
+    default: T,
+    f: impl FnOnce() -> Result<T, String>,
+) -> T {
+    let res = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(_) => Err("Panic in starlark".to_owned()),
+    };
+    match res {
+        Ok(x) => {
+            if !error.is_null() {
+                unsafe { *error = ptr::null_mut() };
+            }
+            x
+        }
+        Err(e) => {
+            if !error.is_null() {
+                unsafe { *error = into_c_string(e) };
+            }
+            default
+        }
+    }
+}
+
+fn parse(filename: &str, source: &str) -> Result<AstModule, String> {
+    AstModule::parse(filename, source.to_owned(), &Dialect::Standard).map_err(|e| e.to_string())
+}
+
+fn eval(filename: &str, source: &str) -> Result<FrozenModule, String> {
+    let ast = parse(filename, source)?;
+    let globals = Globals::standard();
+    Module::with_temp_heap(|module| {
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.eval_module(ast, &globals).map_err(|e| e.to_string())?;
+        }
+        module
+            .freeze()
+            .map_err(|e| starlark::Error::from(e).to_string())
+    })
+}
+
+/// Parse `source`, returning 0 if it is valid, and otherwise 1 and the syntax error.
+///
+/// # Safety
+///
+/// `filename` and `source` must be NUL-terminated strings, and `error` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_parse(
+    filename: *const c_char,
+    source: *const c_char,
+    error: *mut *mut c_char,
+) -> c_int {
+    unsafe {
+        guard(error, 1, || {
+            parse(read_str(filename, "filename")?, read_str(source, "source")?)?;
+            Ok(0)
+        })
+    }
+}
+
+/// Evaluate `source` with the standard globals, returning the frozen module, or null and
+/// the error. `load()` is not supported.
+///
+/// # Safety
+///
+/// `filename` and `source` must be NUL-terminated strings, and `error` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_eval(
+    filename: *const c_char,
+    source: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut StarlarkModule {
+    unsafe {
+        guard(error, ptr::null_mut(), || {
+            let module = eval(read_str(filename, "filename")?, read_str(source, "source")?)?;
+            Ok(Box::into_raw(Box::new(StarlarkModule(module))))
+        })
+    }
+}
+
+/// The global `name` of the module as JSON, or null and the error if it does not exist
+/// or can't be converted to JSON.
+///
+/// # Safety
+///
+/// `module` must come from `starlark_eval`, `name` must be a NUL-terminated string,
+/// and `error` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_module_get_json(
+    module: *const StarlarkModule,
+    name: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    unsafe {
+        guard(error, ptr::null_mut(), || {
+            let module = module.as_ref().ok_or("`module` is null")?;
+            let name = read_str(name, "name")?;
+            let value = module.0.get(name).map_err(|e| format!("{e:#}"))?;
+            let json = value.value().to_json().map_err(|e| format!("{e:#}"))?;
+            Ok(into_c_string(json))
+        })
+    }
+}
+
+/// The names of the public globals of the module, as a JSON array of strings.
+///
+/// # Safety
+///
+/// `module` must come from `starlark_eval`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_module_names_json(module: *const StarlarkModule) -> *mut c_char {
+    let Some(module) = (unsafe { module.as_ref() }) else {
+        return ptr::null_mut();
+    };
+    let names: Vec<&str> = module.0.names().map(|x| x.as_str()).collect();
+    into_c_string(serde_json::to_string(&names).unwrap())
+}
+
+/// Release a module returned by `starlark_eval`. Null is ignored.
+///
+/// # Safety
+///
+/// `module` must come from `starlark_eval`, and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_module_free(module: *mut StarlarkModule) {
+    if !module.is_null() {
+        drop(unsafe { Box::from_raw(module) });
+    }
+}
+
+/// Release a string returned by any of these functions. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must come from this library, and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn starlark_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::ptr;
+
+    use super::*;
+
+    fn take_string(s: *mut std::ffi::c_char) -> String {
+        assert!(!s.is_null());
+        let res = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+        unsafe { starlark_string_free(s) };
+        res
+    }
+
+    #[test]
+    fn test_eval_and_get() {
+        let filename = CString::new("test.star").unwrap();
+        let source = CString::new("x = [1, {'a': True}]\n").unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let module = starlark_eval(filename.as_ptr(), source.as_ptr(), &mut error);
+            assert!(error.is_null());
+            assert!(!module.is_null());
+
+            let name = CString::new("x").unwrap();
+            let json = starlark_module_get_json(module, name.as_ptr(), &mut error);
+            assert_eq!(r#"[1,{"a":true}]"#, take_string(json));
+            assert_eq!(r#"["x"]"#, take_string(starlark_module_names_json(module)));
+
+            let name = CString::new("y").unwrap();
+            let json = starlark_module_get_json(module, name.as_ptr(), &mut error);
+            assert!(json.is_null());
+            assert!(take_string(error).contains('y'));
+
+            starlark_module_free(module);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let filename = CString::new("test.star").unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let source = CString::new("x = (").unwrap();
+            assert_eq!(
+                1,
+                starlark_parse(filename.as_ptr(), source.as_ptr(), &mut error)
+            );
+            take_string(error);
+
+            let source = CString::new("fail('oops')").unwrap();
+            let module = starlark_eval(filename.as_ptr(), source.as_ptr(), &mut error);
+            assert!(module.is_null());
+            assert!(take_string(error).contains("oops"));
+        }
+    }
+}
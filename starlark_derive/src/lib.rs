@@ -32,6 +32,7 @@ mod module;
 mod serde;
 mod starlark_type_repr;
 mod starlark_value;
+mod struct_fields;
 mod trace;
 mod type_matcher;
 mod unpack_value;
@@ -77,6 +78,9 @@ mod vtable;
 ///   is considered safe to execute speculatively: the function should have
 ///   no global side effects, should not panic, and should finish in reasonable time.
 ///   The evaluator may invoke such functions early to generate more efficient code.
+/// * `#[starlark(deterministic)]` - the function always gives the same result for the same
+///   arguments, so can be called by a hermetic evaluator. Functions which are
+///   `speculative_exec_safe` are deterministic too.
 /// * `#[starlark(attribute)]` to turn the name into
 ///   an attribute on the value. Such a function must take exactly one argument, namely a value
 ///   of the type you have attached it to.
@@ -159,7 +163,10 @@ pub fn derive_no_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenS
 }
 
 /// Derive the `StarlarkTypeRepr` trait.
-#[proc_macro_derive(StarlarkTypeRepr)]
+///
+/// For structs with named fields, the type is `struct(..)`, or `dict[str, typing.Any]` with
+/// `#[starlark(dict)]`, matching `ToStarlark` and `FromStarlark`.
+#[proc_macro_derive(StarlarkTypeRepr, attributes(starlark))]
 pub fn derive_starlark_type_repr(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     starlark_type_repr::derive_starlark_type_repr(input)
 }
@@ -182,6 +189,28 @@ pub fn derive_alloc_frozen_value(input: proc_macro::TokenStream) -> proc_macro::
     alloc_value::derive_alloc_frozen_value(input)
 }
 
+/// Derive `AllocValue` for a struct with named fields, allocating a Starlark `struct`
+/// with a field per Rust field, or a dict with `#[starlark(dict)]` on the struct.
+///
+/// Fields can be renamed with `#[starlark(rename = "name")]`, and left out with
+/// `#[starlark(skip)]`. `StarlarkTypeRepr` must also be derived.
+#[proc_macro_derive(ToStarlark, attributes(starlark))]
+pub fn derive_to_starlark(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    struct_fields::derive_to_starlark(input)
+}
+
+/// Derive `UnpackValue` for a struct with named fields, from a Starlark `struct`, or a
+/// dict with string keys with `#[starlark(dict)]` on the struct.
+///
+/// Missing and unknown fields are errors, except fields marked `#[starlark(default)]`,
+/// which are `Default::default()` when missing. Fields marked `#[starlark(skip)]` are
+/// always `Default::default()`. Fields can be renamed with `#[starlark(rename = "name")]`.
+/// `StarlarkTypeRepr` must also be derived.
+#[proc_macro_derive(FromStarlark, attributes(starlark))]
+pub fn derive_from_starlark(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    struct_fields::derive_from_starlark(input)
+}
+
 /// Derive accessor methods that are designed to be used from {has,get,dir}_attr in an `impl StarlarkValue` block.
 ///
 /// All fields in the struct that are not marked with #[starlark(skip)] are exported to Starlark code as
@@ -201,6 +230,10 @@ pub fn starlark_attrs(_: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
 /// Generate missing elements of `StarlarkValue` trait when this attribute
 /// is applied to an impl block of `StarlarkValue`.
+///
+/// Values allocated with `alloc_complex_no_freeze` should be marked
+/// `#[starlark_value(type = "...", NoFreeze)]`, so that they are not registered
+/// as frozen values for deserialization.
 #[proc_macro_attribute]
 pub fn starlark_value(
     attr: proc_macro::TokenStream,
@@ -59,6 +59,7 @@ struct FnAttrs {
     starlark_ty_custom_function: Option<Expr>,
     special_builtin_function: Option<Expr>,
     speculative_exec_safe: bool,
+    deterministic: bool,
     docstring: Option<String>,
     /// Rest attributes
     attrs: Vec<Attribute>,
@@ -200,6 +201,9 @@ fn parse_starlark_fn_attr(tokens: &Attribute, attrs: &mut FnAttrs) -> syn::Resul
             } else if ident == "speculative_exec_safe" {
                 attrs.speculative_exec_safe = true;
                 continue;
+            } else if ident == "deterministic" {
+                attrs.deterministic = true;
+                continue;
             } else if ident == "ty_custom_function" {
                 parser.parse::<Token![=]>()?;
                 attrs.starlark_ty_custom_function = Some(parser.parse::<Expr>()?);
@@ -215,7 +219,8 @@ fn parse_starlark_fn_attr(tokens: &Attribute, attrs: &mut FnAttrs) -> syn::Resul
                     `#[starlark(as_type = ImplStarlarkValue)]`, \
                     `#[starlark(ty_custom_function = MyTy)]`, \
                     `#[starlark(attribute)]`, \
-                    `#[starlark(speculative_exec_safe)]` attribute",
+                    `#[starlark(speculative_exec_safe)]`, \
+                    `#[starlark(deterministic)]` attribute",
             ));
         }
 
@@ -296,6 +301,7 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
         is_attribute,
         as_type,
         speculative_exec_safe,
+        deterministic,
         docstring,
         starlark_ty_custom_function,
         special_builtin_function,
@@ -407,6 +413,12 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
                 "Attribute function cannot types are not implemented",
             ));
         }
+        if deterministic {
+            return Err(syn::Error::new(
+                sig_span,
+                "Attributes are always allowed in hermetic mode, so can't be `deterministic`",
+            ));
+        }
         Ok(StarStmt::Attr(StarAttr {
             name: func.sig.ident,
             this,
@@ -451,6 +463,7 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
             starlark_ty_custom_function,
             special_builtin_function,
             speculative_exec_safe,
+            deterministic,
             body: *func.block,
             source,
             docstring,
@@ -68,7 +68,7 @@ impl StarFun {
             Some(x) => syn::parse_quote! {
                     std::option::Option::Some((
                         <#x as starlark::values::StarlarkValue>::get_type_starlark_repr(),
-                        starlark::docs::DocType::from_starlark_value::<#x>(),
+                        starlark::docs::DocType::from_starlark_value::<#x>,
                     ))
             },
             None => syn::parse_quote! {
@@ -625,11 +625,13 @@ fn render_native_callable_components(
     let turbofish = generics.turbofish();
 
     let speculative_exec_safe = x.speculative_exec_safe;
+    let deterministic = x.deterministic;
     Ok(quote!(
         {
             let param_spec = #param_spec;
             starlark::__derive_refs::components::NativeCallableComponents {
                 speculative_exec_safe: #speculative_exec_safe,
+                deterministic: #deterministic,
                 rust_docstring: #docs,
                 param_spec,
                 return_type: __starlark_return_type_starlark_type_repr #turbofish(),
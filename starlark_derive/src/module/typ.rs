@@ -116,6 +116,8 @@ pub(crate) struct StarFun {
     pub starlark_ty_custom_function: Option<Expr>,
     pub special_builtin_function: Option<Expr>,
     pub speculative_exec_safe: bool,
+    /// Marked `#[starlark(deterministic)]`.
+    pub deterministic: bool,
     pub body: Block,
     pub source: StarFunSource,
     pub docstring: Option<String>,
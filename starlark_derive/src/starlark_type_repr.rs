@@ -17,6 +17,9 @@
 
 use syn::spanned::Spanned;
 
+use crate::struct_fields::StructFieldsInput;
+use crate::struct_fields::type_repr_impl;
+
 pub(crate) fn derive_starlark_type_repr(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     match derive_starlark_type_repr_impl(input) {
@@ -111,6 +114,16 @@ fn derive_starlark_type_repr_impl(
 ) -> syn::Result<proc_macro2::TokenStream> {
     let span = input.ident.span();
 
+    if let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(_),
+        ..
+    }) = &input.data
+    {
+        let input = StructFieldsInput::parse(input, "StarlarkTypeRepr")?;
+        let trait_impl = type_repr_impl(&input)?;
+        return Ok(quote::quote_spanned! { span => #trait_impl });
+    }
+
     let input = StarlarkTypeReprInput::parse(input, "StarlarkTypeRepr")?;
 
     let ident = &input.ident;
@@ -41,6 +41,9 @@ struct StarlarkValueAttrs {
     unpack_value: bool,
     /// Implement `StarlarkTypeRepr` for `&T`.
     starlark_type_repr: bool,
+    /// The value is allocated with `alloc_complex_no_freeze`, so is never frozen,
+    /// and its frozen type is not registered for deserialization.
+    no_freeze: bool,
 }
 
 impl syn::parse::Parse for StarlarkValueAttrs {
@@ -52,6 +55,7 @@ impl syn::parse::Parse for StarlarkValueAttrs {
             typ,
             unpack_value: false,
             starlark_type_repr: false,
+            no_freeze: false,
         };
 
         loop {
@@ -68,10 +72,13 @@ impl syn::parse::Parse for StarlarkValueAttrs {
                 attrs.unpack_value = true;
             } else if name == "StarlarkTypeRepr" {
                 attrs.starlark_type_repr = true;
+            } else if name == "NoFreeze" {
+                attrs.no_freeze = true;
             } else {
                 return Err(syn::Error::new_spanned(
                     name,
-                    "unknown attribute, allowed attributes are `UnpackValue`, `StarlarkTypeRepr`",
+                    "unknown attribute, allowed attributes are `UnpackValue`, `StarlarkTypeRepr`, \
+                    `NoFreeze`",
                 ));
             }
         }
@@ -568,7 +575,12 @@ impl ImplStarlarkValue {
     /// - Types with unsupported generic parameters
     /// - Types that override `is_special` (they have custom AValue implementations
     ///   and should use `#[register_avalue_vtable]` on their AValue impl instead)
+    /// - Types marked `NoFreeze`, which are never frozen
     fn vtable_registration(&self) -> syn::Result<Option<proc_macro2::TokenStream>> {
+        if self.attrs.no_freeze {
+            return Ok(None);
+        }
+
         // Check if is_special is overridden in this impl block.
         if self.has_fn("is_special") {
             return Ok(None);
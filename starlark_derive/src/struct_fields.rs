@@ -0,0 +1,245 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! `#[derive(ToStarlark, FromStarlark)]`, converting structs with named fields to and
+//! from Starlark structs, or dicts with `#[starlark(dict)]`.
+
+use proc_macro2::Span;
+
+use crate::v_lifetime::find_v_lifetime;
+
+struct Field {
+    ident: syn::Ident,
+    /// The name in Starlark, `#[starlark(rename = "...")]`, or the Rust name.
+    name: String,
+    /// `#[starlark(default)]`: use `Default::default()` when the field is missing.
+    default: bool,
+    /// `#[starlark(skip)]`: not converted, and `Default::default()` when unpacking.
+    skip: bool,
+}
+
+pub(crate) struct StructFieldsInput {
+    ident: syn::Ident,
+    generics: syn::Generics,
+    /// `#[starlark(dict)]`: convert to and from a dict with string keys, not a struct.
+    dict: bool,
+    fields: Vec<Field>,
+    span: Span,
+}
+
+impl StructFieldsInput {
+    pub(crate) fn parse(
+        input: syn::DeriveInput,
+        trait_name: &str,
+    ) -> syn::Result<StructFieldsInput> {
+        let span = input.ident.span();
+        let syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(named),
+            ..
+        }) = input.data
+        else {
+            return Err(syn::Error::new(
+                span,
+                format!("`{trait_name}` can only be derived for structs with named fields"),
+            ));
+        };
+
+        let mut dict = false;
+        for attr in &input.attrs {
+            if attr.path().is_ident("starlark") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("dict") {
+                        dict = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("unknown `starlark` attribute, expected `dict`"))
+                    }
+                })?;
+            }
+        }
+
+        let fields = named
+            .named
+            .into_iter()
+            .map(|field| {
+                let ident = field.ident.unwrap();
+                let rust_name = ident.to_string();
+                let mut res = Field {
+                    name: rust_name.strip_prefix("r#").unwrap_or(&rust_name).to_owned(),
+                    ident,
+                    default: false,
+                    skip: false,
+                };
+                for attr in &field.attrs {
+                    if !attr.path().is_ident("starlark") {
+                        continue;
+                    }
+                    attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("rename") {
+                            res.name = meta.value()?.parse::<syn::LitStr>()?.value();
+                        } else if meta.path.is_ident("default") {
+                            res.default = true;
+                        } else if meta.path.is_ident("skip") {
+                            res.skip = true;
+                        } else {
+                            return Err(meta.error(
+                                "unknown `starlark` attribute, expected `rename`, `default` or `skip`",
+                            ));
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(res)
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(StructFieldsInput {
+            ident: input.ident,
+            generics: input.generics,
+            dict,
+            fields,
+            span,
+        })
+    }
+
+    /// The generics with a `'v` lifetime, added if the struct doesn't have one.
+    fn generics_with_v(&self) -> syn::Result<syn::Generics> {
+        let mut generics = self.generics.clone();
+        if find_v_lifetime(&self.generics)?.is_none() {
+            generics
+                .params
+                .push(syn::parse_quote_spanned! { self.span => 'v });
+        }
+        Ok(generics)
+    }
+
+    fn converted_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|f| !f.skip)
+    }
+}
+
+fn derive(
+    input: proc_macro::TokenStream,
+    trait_name: &str,
+    f: fn(&StructFieldsInput) -> syn::Result<syn::ItemImpl>,
+) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    match StructFieldsInput::parse(input, trait_name).and_then(|input| f(&input)) {
+        Ok(item) => quote::quote! { #item }.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+pub(crate) fn derive_to_starlark(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive(input, "ToStarlark", to_starlark_impl)
+}
+
+pub(crate) fn derive_from_starlark(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive(input, "FromStarlark", from_starlark_impl)
+}
+
+/// `StarlarkTypeRepr` for a struct with named fields: `struct(..)`, or `dict[str, typing.Any]`.
+pub(crate) fn type_repr_impl(input: &StructFieldsInput) -> syn::Result<syn::ItemImpl> {
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let repr: syn::Type = if input.dict {
+        syn::parse_quote_spanned! { input.span =>
+            starlark::values::dict::DictType<std::string::String, starlark::values::Value<'static>>
+        }
+    } else {
+        syn::parse_quote_spanned! { input.span =>
+            starlark::values::structs::StructRef<'static>
+        }
+    };
+    Ok(syn::parse_quote_spanned! { input.span =>
+        impl #impl_generics starlark::values::type_repr::StarlarkTypeRepr for #ident #type_generics #where_clause {
+            type Canonical = <#repr as starlark::values::type_repr::StarlarkTypeRepr>::Canonical;
+
+            fn starlark_type_repr() -> starlark::typing::Ty {
+                <#repr as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+            }
+        }
+    })
+}
+
+fn to_starlark_impl(input: &StructFieldsInput) -> syn::Result<syn::ItemImpl> {
+    let ident = &input.ident;
+    let generics = input.generics_with_v()?;
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, type_generics, where_clause) = input.generics.split_for_impl();
+    let entries = input.converted_fields().map(|f| {
+        let name = &f.name;
+        let field = &f.ident;
+        quote::quote_spanned! { field.span() => (#name, heap.alloc(self.#field)) }
+    });
+    let alloc: syn::Path = if input.dict {
+        syn::parse_quote! { starlark::values::dict::AllocDict }
+    } else {
+        syn::parse_quote! { starlark::values::structs::AllocStruct }
+    };
+    Ok(syn::parse_quote_spanned! { input.span =>
+        #[allow(clippy::all)]
+        impl #impl_generics starlark::values::AllocValue<'v> for #ident #type_generics #where_clause {
+            fn alloc_value(self, heap: starlark::values::Heap<'v>) -> starlark::values::Value<'v> {
+                let fields: std::vec::Vec<(&str, starlark::values::Value<'v>)> = std::vec![#(#entries),*];
+                heap.alloc(#alloc(fields))
+            }
+        }
+    })
+}
+
+fn from_starlark_impl(input: &StructFieldsInput) -> syn::Result<syn::ItemImpl> {
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let generics = input.generics_with_v()?;
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, type_generics, where_clause) = input.generics.split_for_impl();
+    let unpack_fields = quote::quote! { starlark::__derive_refs::unpack_fields::UnpackFields };
+    let fields = if input.dict {
+        quote::quote! { #unpack_fields::from_dict(#type_name, value)? }
+    } else {
+        quote::quote! { #unpack_fields::from_struct(#type_name, value) }
+    };
+    let inits = input.fields.iter().map(|f| {
+        let name = &f.name;
+        let field = &f.ident;
+        if f.skip {
+            quote::quote_spanned! { field.span() => #field: std::default::Default::default() }
+        } else if f.default {
+            quote::quote_spanned! { field.span() => #field: fields.take(#name)?.unwrap_or_default() }
+        } else {
+            quote::quote_spanned! { field.span() => #field: fields.take_required(#name)? }
+        }
+    });
+    Ok(syn::parse_quote_spanned! { input.span =>
+        #[allow(clippy::all, unused_mut)]
+        impl #impl_generics starlark::values::UnpackValue<'v> for #ident #type_generics #where_clause {
+            type Error = starlark::Error;
+
+            fn unpack_value_impl(
+                value: starlark::values::Value<'v>,
+            ) -> std::result::Result<std::option::Option<Self>, starlark::Error> {
+                let std::option::Option::Some(mut fields) = #fields else {
+                    return std::result::Result::Ok(std::option::Option::None);
+                };
+                let res = #ident { #(#inits),* };
+                fields.finish()?;
+                std::result::Result::Ok(std::option::Option::Some(res))
+            }
+        }
+    })
+}
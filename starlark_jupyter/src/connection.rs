@@ -0,0 +1,260 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The connection file Jupyter starts the kernel with, and the signed multipart messages
+//! of the Jupyter messaging protocol.
+
+use std::fs;
+use std::path::Path;
+
+use hmac::Hmac;
+use hmac::Mac;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+
+/// The protocol version implemented.
+const PROTOCOL_VERSION: &str = "5.3";
+
+/// Separates the routing identities from the rest of a message.
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+#[derive(Debug, thiserror::Error)]
+enum ConnectionError {
+    #[error("Unsupported signature scheme `{0}`, only `hmac-sha256` is supported")]
+    SignatureScheme(String),
+    #[error("Message has no `<IDS|MSG>` delimiter")]
+    NoDelimiter,
+    #[error("Message has {0} parts after the delimiter, expected at least 5")]
+    TooFewParts(usize),
+    #[error("Message signature does not match")]
+    BadSignature,
+}
+
+/// The contents of the connection file, see
+/// <https://jupyter-client.readthedocs.io/en/stable/kernels.html#connection-files>.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ConnectionInfo {
+    pub(crate) transport: String,
+    pub(crate) ip: String,
+    pub(crate) shell_port: u16,
+    pub(crate) iopub_port: u16,
+    pub(crate) stdin_port: u16,
+    pub(crate) control_port: u16,
+    pub(crate) hb_port: u16,
+    #[serde(default)]
+    pub(crate) key: String,
+    #[serde(default)]
+    pub(crate) signature_scheme: String,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn read(path: &Path) -> anyhow::Result<ConnectionInfo> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// The address to bind the socket on `port` to.
+    pub(crate) fn endpoint(&self, port: u16) -> String {
+        format!("{}://{}:{}", self.transport, self.ip, port)
+    }
+
+    pub(crate) fn signer(&self) -> anyhow::Result<Signer> {
+        match self.signature_scheme.as_str() {
+            "hmac-sha256" | "" => Ok(Signer {
+                key: self.key.as_bytes().to_vec(),
+            }),
+            scheme => Err(ConnectionError::SignatureScheme(scheme.to_owned()).into()),
+        }
+    }
+}
+
+/// Signs and checks messages with the key from the connection file. An empty key
+/// disables signing.
+#[derive(Debug, Clone)]
+pub(crate) struct Signer {
+    key: Vec<u8>,
+}
+
+impl Signer {
+    fn mac(&self, parts: &[&[u8]]) -> Option<Hmac<Sha256>> {
+        if self.key.is_empty() {
+            return None;
+        }
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key size");
+        for part in parts {
+            mac.update(part);
+        }
+        Some(mac)
+    }
+
+    fn sign(&self, parts: &[&[u8]]) -> String {
+        match self.mac(parts) {
+            None => String::new(),
+            Some(mac) => hex::encode(mac.finalize().into_bytes()),
+        }
+    }
+
+    fn verify(&self, signature: &[u8], parts: &[&[u8]]) -> bool {
+        match self.mac(parts) {
+            None => true,
+            Some(mac) => match hex::decode(signature) {
+                Ok(signature) => mac.verify_slice(&signature).is_ok(),
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+/// A message, either received from the frontend or to be sent to it.
+#[derive(Debug, Clone)]
+pub(crate) struct Message {
+    /// Routing identities on the ROUTER sockets, sent back with replies.
+    pub(crate) identities: Vec<Vec<u8>>,
+    pub(crate) header: serde_json::Value,
+    pub(crate) parent_header: serde_json::Value,
+    pub(crate) metadata: serde_json::Value,
+    pub(crate) content: serde_json::Value,
+}
+
+impl Message {
+    pub(crate) fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+
+    /// A message of `msg_type` in response to this one, going to the same frontend.
+    pub(crate) fn reply(&self, msg_type: &str, content: serde_json::Value) -> Message {
+        Message {
+            identities: self.identities.clone(),
+            header: header(msg_type, &self.header["session"], &self.header["username"]),
+            parent_header: self.header.clone(),
+            metadata: json!({}),
+            content,
+        }
+    }
+
+    /// Parse the parts of a multipart message, checking the signature.
+    pub(crate) fn decode(parts: Vec<Vec<u8>>, signer: &Signer) -> anyhow::Result<Message> {
+        let delimiter = parts
+            .iter()
+            .position(|part| part == DELIMITER)
+            .ok_or(ConnectionError::NoDelimiter)?;
+        let mut parts = parts;
+        let rest = parts.split_off(delimiter + 1);
+        parts.pop();
+        if rest.len() < 5 {
+            return Err(ConnectionError::TooFewParts(rest.len()).into());
+        }
+        let signed: Vec<&[u8]> = rest[1..5].iter().map(|x| x.as_slice()).collect();
+        if !signer.verify(&rest[0], &signed) {
+            return Err(ConnectionError::BadSignature.into());
+        }
+        Ok(Message {
+            identities: parts,
+            header: serde_json::from_slice(&rest[1])?,
+            parent_header: serde_json::from_slice(&rest[2])?,
+            metadata: serde_json::from_slice(&rest[3])?,
+            content: serde_json::from_slice(&rest[4])?,
+        })
+    }
+
+    /// The parts of the multipart message, signed.
+    pub(crate) fn encode(&self, signer: &Signer) -> Vec<Vec<u8>> {
+        let signed = [
+            self.header.to_string().into_bytes(),
+            self.parent_header.to_string().into_bytes(),
+            self.metadata.to_string().into_bytes(),
+            self.content.to_string().into_bytes(),
+        ];
+        let signature = signer.sign(&signed.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+        let mut parts = self.identities.clone();
+        parts.push(DELIMITER.to_vec());
+        parts.push(signature.into_bytes());
+        parts.extend(signed);
+        parts
+    }
+
+    pub(crate) fn send(&self, socket: &zmq::Socket, signer: &Signer) -> anyhow::Result<()> {
+        socket.send_multipart(self.encode(signer), 0)?;
+        Ok(())
+    }
+
+    pub(crate) fn recv(socket: &zmq::Socket, signer: &Signer) -> anyhow::Result<Message> {
+        Message::decode(socket.recv_multipart(0)?, signer)
+    }
+}
+
+/// A new message header, with a fresh id.
+fn header(
+    msg_type: &str,
+    session: &serde_json::Value,
+    username: &serde_json::Value,
+) -> serde_json::Value {
+    json!({
+        "msg_id": uuid::Uuid::new_v4().to_string(),
+        "session": session,
+        "username": username,
+        "date": chrono::Utc::now().to_rfc3339(),
+        "msg_type": msg_type,
+        "version": PROTOCOL_VERSION,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> Message {
+        Message {
+            identities: vec![b"frontend".to_vec()],
+            header: json!({"msg_id": "1", "session": "s", "username": "u", "msg_type": "kernel_info_request"}),
+            parent_header: json!({}),
+            metadata: json!({}),
+            content: json!({}),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let signer = Signer {
+            key: b"secret".to_vec(),
+        };
+        let message = request();
+        let decoded = Message::decode(message.encode(&signer), &signer).unwrap();
+        assert_eq!(vec![b"frontend".to_vec()], decoded.identities);
+        assert_eq!("kernel_info_request", decoded.msg_type());
+
+        let reply = decoded.reply("kernel_info_reply", json!({"status": "ok"}));
+        assert_eq!(message.header, reply.parent_header);
+        assert_eq!("s", reply.header["session"]);
+        assert_eq!(message.identities, reply.identities);
+    }
+
+    #[test]
+    fn test_bad_signature() {
+        let signer = Signer {
+            key: b"secret".to_vec(),
+        };
+        let mut parts = request().encode(&signer);
+        // Tamper with the content.
+        *parts.last_mut().unwrap() = b"{\"x\": 1}".to_vec();
+        assert!(Message::decode(parts.clone(), &signer).is_err());
+        // Without a key, nothing is checked.
+        assert!(Message::decode(parts, &Signer { key: Vec::new() }).is_ok());
+    }
+}
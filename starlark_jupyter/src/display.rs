@@ -0,0 +1,111 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rich display of cell results: dicts and structs are also rendered as HTML tables.
+
+use std::fmt::Write;
+
+use serde_json::json;
+use starlark::values::Value;
+use starlark::values::dict::DictRef;
+use starlark::values::structs::StructRef;
+
+/// The `data` of an `execute_result`, keyed by MIME type.
+pub(crate) fn display_data(value: Value) -> serde_json::Value {
+    let mut data = serde_json::Map::new();
+    data.insert("text/plain".to_owned(), json!(value.to_repr()));
+    if is_table(value) {
+        let mut html = String::new();
+        write_html(value, &mut html);
+        data.insert("text/html".to_owned(), json!(html));
+    }
+    serde_json::Value::Object(data)
+}
+
+fn is_table(value: Value) -> bool {
+    DictRef::from_value(value).is_some() || StructRef::from_value(value).is_some()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A table row per entry, with nested dicts and structs as nested tables.
+fn write_table<'v>(rows: impl Iterator<Item = (String, Value<'v>)>, out: &mut String) {
+    out.push_str("<table>");
+    for (key, value) in rows {
+        write!(out, "<tr><th>{}</th><td>", escape(&key)).unwrap();
+        write_html(value, out);
+        out.push_str("</td></tr>");
+    }
+    out.push_str("</table>");
+}
+
+fn write_html(value: Value, out: &mut String) {
+    if let Some(dict) = DictRef::from_value(value) {
+        write_table(dict.iter().map(|(k, v)| (k.to_repr(), v)), out);
+    } else if let Some(s) = StructRef::from_value(value) {
+        write_table(s.iter().map(|(k, v)| (k.as_str().to_owned(), v)), out);
+    } else {
+        out.push_str("<code>");
+        out.push_str(&escape(&value.to_repr()));
+        out.push_str("</code>");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::environment::Globals;
+    use starlark::environment::LibraryExtension;
+    use starlark::environment::Module;
+    use starlark::eval::Evaluator;
+    use starlark::syntax::AstModule;
+    use starlark::syntax::Dialect;
+
+    use super::*;
+
+    fn display(code: &str) -> serde_json::Value {
+        Module::with_temp_heap(|module| {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("cell", code.to_owned(), &Dialect::Extended).unwrap();
+            let value = eval
+                .eval_module(ast, &Globals::extended_by(&[LibraryExtension::StructType]))
+                .unwrap();
+            display_data(value)
+        })
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(json!({"text/plain": "[1, \"<a>\"]"}), display("[1, '<a>']"));
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(
+            "<table><tr><th>\"a\"</th><td><code>1</code></td></tr>\
+             <tr><th>\"b\"</th><td><table><tr><th>x</th><td><code>"&lt;"</code></td></tr></table></td></tr></table>",
+            display("{'a': 1, 'b': struct(x = '<')}")["text/html"]
+                .as_str()
+                .unwrap()
+                .replace("&quot;", "\""),
+        );
+    }
+}
@@ -0,0 +1,324 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The kernel: the sockets from the connection file, and the handlers for the requests
+//! on them.
+//!
+//! Evaluation happens on the main thread, which serves the shell socket. The heartbeat
+//! and control sockets have their own threads, so the kernel stays responsive, and can
+//! be interrupted, while a cell runs.
+
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use serde_json::json;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+
+use crate::connection::ConnectionInfo;
+use crate::connection::Message;
+use crate::connection::Signer;
+use crate::session::Session;
+use crate::session::is_complete;
+
+/// The `kernel_info_reply` content.
+fn kernel_info() -> serde_json::Value {
+    json!({
+        "status": "ok",
+        "protocol_version": "5.3",
+        "implementation": "starlark",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "starlark",
+            "version": env!("CARGO_PKG_VERSION"),
+            "mimetype": "text/x-starlark",
+            "file_extension": ".star",
+            "pygments_lexer": "python",
+            "codemirror_mode": "python",
+        },
+        "banner": "Starlark",
+        "help_links": [],
+    })
+}
+
+fn bind(
+    context: &zmq::Context,
+    kind: zmq::SocketType,
+    connection: &ConnectionInfo,
+    port: u16,
+) -> anyhow::Result<zmq::Socket> {
+    let socket = context.socket(kind)?;
+    socket.bind(&connection.endpoint(port))?;
+    Ok(socket)
+}
+
+/// Echo heartbeats, so the frontend knows the kernel is alive.
+fn heartbeat(socket: zmq::Socket) -> anyhow::Result<()> {
+    loop {
+        let ping = socket.recv_bytes(0)?;
+        socket.send(ping, 0)?;
+    }
+}
+
+/// Handle interrupts and shutdowns, which are sent on the control socket.
+fn control(
+    socket: zmq::Socket,
+    signer: Signer,
+    interrupted: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    loop {
+        let message = Message::recv(&socket, &signer)?;
+        match message.msg_type() {
+            "interrupt_request" => {
+                interrupted.store(true, Ordering::SeqCst);
+                message
+                    .reply("interrupt_reply", json!({"status": "ok"}))
+                    .send(&socket, &signer)?;
+            }
+            "shutdown_request" => {
+                let restart = message.content["restart"].as_bool().unwrap_or(false);
+                message
+                    .reply(
+                        "shutdown_reply",
+                        json!({"status": "ok", "restart": restart}),
+                    )
+                    .send(&socket, &signer)?;
+                // The frontend starts a new process to restart.
+                process::exit(0);
+            }
+            "kernel_info_request" => {
+                message
+                    .reply("kernel_info_reply", kernel_info())
+                    .send(&socket, &signer)?;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The shell and IOPub sockets, and the Starlark state, all used from the main thread.
+struct Kernel<'v> {
+    shell: zmq::Socket,
+    iopub: zmq::Socket,
+    signer: Signer,
+    session: Session<'v>,
+}
+
+impl<'v> Kernel<'v> {
+    fn publish(
+        &self,
+        parent: &Message,
+        msg_type: &str,
+        content: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let mut message = parent.reply(msg_type, content);
+        message.identities = vec![msg_type.as_bytes().to_vec()];
+        message.send(&self.iopub, &self.signer)
+    }
+
+    fn status(&self, parent: &Message, state: &str) -> anyhow::Result<()> {
+        self.publish(parent, "status", json!({"execution_state": state}))
+    }
+
+    fn reply(
+        &self,
+        request: &Message,
+        msg_type: &str,
+        content: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        request
+            .reply(msg_type, content)
+            .send(&self.shell, &self.signer)
+    }
+
+    fn execute(&mut self, request: &Message) -> anyhow::Result<()> {
+        let code = request.content["code"].as_str().unwrap_or_default();
+        let silent = request.content["silent"].as_bool().unwrap_or(false);
+        let store_history = !silent && request.content["store_history"].as_bool().unwrap_or(true);
+        if !silent {
+            self.publish(
+                request,
+                "execute_input",
+                json!({
+                    "code": code,
+                    "execution_count": self.session.execution_count() + u64::from(store_history),
+                }),
+            )?;
+        }
+        let outcome = self.session.execute(code, store_history);
+        let execution_count = self.session.execution_count();
+        if !silent {
+            if !outcome.stdout.is_empty() {
+                self.publish(
+                    request,
+                    "stream",
+                    json!({"name": "stdout", "text": outcome.stdout}),
+                )?;
+            }
+            if let Some(data) = outcome.result {
+                self.publish(
+                    request,
+                    "execute_result",
+                    json!({"execution_count": execution_count, "data": data, "metadata": {}}),
+                )?;
+            }
+        }
+        let content = match outcome.error {
+            None => json!({
+                "status": "ok",
+                "execution_count": execution_count,
+                "user_expressions": {},
+                "payload": [],
+            }),
+            Some(error) => {
+                let error = json!({
+                    "ename": error.ename,
+                    "evalue": error.evalue,
+                    "traceback": error.traceback,
+                });
+                if !silent {
+                    self.publish(request, "error", error.clone())?;
+                }
+                let mut content = error;
+                content["status"] = json!("error");
+                content["execution_count"] = json!(execution_count);
+                content
+            }
+        };
+        self.reply(request, "execute_reply", content)
+    }
+
+    /// Handle a shell request, returning `false` to shut down.
+    fn handle(&mut self, request: &Message) -> anyhow::Result<bool> {
+        match request.msg_type() {
+            "kernel_info_request" => self.reply(request, "kernel_info_reply", kernel_info())?,
+            "execute_request" => self.execute(request)?,
+            "complete_request" => {
+                let code = request.content["code"].as_str().unwrap_or_default();
+                let cursor = request.content["cursor_pos"].as_u64().unwrap_or(0) as usize;
+                let completion = self.session.complete(code, cursor);
+                self.reply(
+                    request,
+                    "complete_reply",
+                    json!({
+                        "status": "ok",
+                        "matches": completion.matches,
+                        "cursor_start": completion.cursor_start,
+                        "cursor_end": completion.cursor_end,
+                        "metadata": {},
+                    }),
+                )?;
+            }
+            "inspect_request" => {
+                let code = request.content["code"].as_str().unwrap_or_default();
+                let cursor = request.content["cursor_pos"].as_u64().unwrap_or(0) as usize;
+                let content = match self.session.inspect(code, cursor) {
+                    Some(docs) => json!({
+                        "status": "ok",
+                        "found": true,
+                        "data": {"text/markdown": docs, "text/plain": docs},
+                        "metadata": {},
+                    }),
+                    None => json!({"status": "ok", "found": false, "data": {}, "metadata": {}}),
+                };
+                self.reply(request, "inspect_reply", content)?;
+            }
+            "is_complete_request" => {
+                let code = request.content["code"].as_str().unwrap_or_default();
+                let status = is_complete(code);
+                let content = if status == "incomplete" {
+                    json!({"status": status, "indent": "    "})
+                } else {
+                    json!({"status": status})
+                };
+                self.reply(request, "is_complete_reply", content)?;
+            }
+            "history_request" => self.reply(
+                request,
+                "history_reply",
+                json!({"status": "ok", "history": []}),
+            )?,
+            "comm_info_request" => self.reply(
+                request,
+                "comm_info_reply",
+                json!({"status": "ok", "comms": {}}),
+            )?,
+            "shutdown_request" => {
+                let restart = request.content["restart"].as_bool().unwrap_or(false);
+                self.reply(
+                    request,
+                    "shutdown_reply",
+                    json!({"status": "ok", "restart": restart}),
+                )?;
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn serve(&mut self) -> anyhow::Result<()> {
+        loop {
+            let request = match Message::recv(&self.shell, &self.signer) {
+                Ok(request) => request,
+                Err(e) => {
+                    eprintln!("Ignoring bad message: {e:#}");
+                    continue;
+                }
+            };
+            self.status(&request, "busy")?;
+            let running = self.handle(&request)?;
+            self.status(&request, "idle")?;
+            if !running {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Run the kernel until the frontend shuts it down.
+pub(crate) fn run(connection: &ConnectionInfo) -> anyhow::Result<()> {
+    let signer = connection.signer()?;
+    let context = zmq::Context::new();
+    let shell = bind(&context, zmq::ROUTER, connection, connection.shell_port)?;
+    let iopub = bind(&context, zmq::PUB, connection, connection.iopub_port)?;
+    // Nothing reads from stdin, but the frontend expects the socket.
+    let _stdin = bind(&context, zmq::ROUTER, connection, connection.stdin_port)?;
+    let control_socket = bind(&context, zmq::ROUTER, connection, connection.control_port)?;
+    let hb = bind(&context, zmq::REP, connection, connection.hb_port)?;
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    thread::spawn(move || heartbeat(hb));
+    {
+        let signer = signer.clone();
+        let interrupted = interrupted.clone();
+        thread::spawn(move || control(control_socket, signer, interrupted));
+    }
+
+    Module::with_temp_heap(|module| {
+        let mut kernel = Kernel {
+            shell,
+            iopub,
+            signer,
+            session: Session::new(module, Globals::extended_internal(), interrupted),
+        };
+        kernel.serve()
+    })
+}
@@ -0,0 +1,44 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A Jupyter kernel for Starlark. Jupyter starts it with the connection file, as set up
+//! by `kernel/kernel.json`.
+
+mod connection;
+mod display;
+mod kernel;
+mod session;
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::connection::ConnectionInfo;
+
+#[derive(Debug, Parser)]
+#[command(name = "starlark-jupyter", about = "Jupyter kernel for Starlark")]
+struct Args {
+    /// The connection file written by Jupyter.
+    #[arg(long)]
+    connection_file: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let connection = ConnectionInfo::read(&args.connection_file)?;
+    kernel::run(&connection)
+}
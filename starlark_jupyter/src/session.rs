@@ -0,0 +1,328 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The Starlark state of a notebook: one module which every cell is evaluated in, so
+//! later cells see the globals defined by earlier ones, as in the REPL.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use starlark::PrintHandler;
+use starlark::docs::markdown::render_doc_item_no_link;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+use starlark::errors::EvalMessage;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::values::Value;
+
+use crate::display::display_data;
+
+/// Keywords offered as completions alongside the globals.
+const KEYWORDS: &[&str] = &[
+    "and", "break", "continue", "def", "elif", "else", "for", "if", "in", "lambda", "load", "not",
+    "or", "pass", "return",
+];
+
+#[derive(Default)]
+struct CollectPrints(RefCell<String>);
+
+impl PrintHandler for CollectPrints {
+    fn println(&self, text: &str) -> starlark::Result<()> {
+        let mut out = self.0.borrow_mut();
+        out.push_str(text);
+        out.push('\n');
+        Ok(())
+    }
+}
+
+/// A failed cell, as the fields of an `error` message.
+#[derive(Debug)]
+pub(crate) struct CellError {
+    pub(crate) ename: String,
+    pub(crate) evalue: String,
+    pub(crate) traceback: Vec<String>,
+}
+
+/// What running a cell produced.
+#[derive(Debug)]
+pub(crate) struct Outcome {
+    /// Everything `print`ed.
+    pub(crate) stdout: String,
+    /// The display data of the value of the last expression, unless it was `None`.
+    pub(crate) result: Option<serde_json::Value>,
+    pub(crate) error: Option<CellError>,
+}
+
+/// Completions of the name before the cursor. Offsets are in characters, as in the protocol.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Completion {
+    pub(crate) matches: Vec<String>,
+    pub(crate) cursor_start: usize,
+    pub(crate) cursor_end: usize,
+}
+
+pub(crate) struct Session<'v> {
+    module: Module<'v>,
+    globals: Globals,
+    execution_count: u64,
+    /// Set by an `interrupt_request` to cancel the running cell.
+    interrupted: Arc<AtomicBool>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The byte offset of the character offset `cursor`.
+fn byte_offset(code: &str, cursor: usize) -> usize {
+    code.char_indices()
+        .nth(cursor)
+        .map_or(code.len(), |(i, _)| i)
+}
+
+/// The start of the dotted name ending at byte offset `end`.
+fn dotted_name_start(code: &str, end: usize) -> usize {
+    code[..end]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_name_char(*c) || *c == '.')
+        .last()
+        .map_or(end, |(i, _)| i)
+}
+
+impl<'v> Session<'v> {
+    pub(crate) fn new(module: Module<'v>, globals: Globals, interrupted: Arc<AtomicBool>) -> Self {
+        Session {
+            module,
+            globals,
+            execution_count: 0,
+            interrupted,
+        }
+    }
+
+    pub(crate) fn execution_count(&self) -> u64 {
+        self.execution_count
+    }
+
+    /// Run a cell. Cells stored in the history are numbered, and their filename is
+    /// `cell[N]`, so errors point at the cell.
+    pub(crate) fn execute(&mut self, code: &str, store_history: bool) -> Outcome {
+        if store_history {
+            self.execution_count += 1;
+        }
+        let filename = format!("cell[{}]", self.execution_count);
+        let prints = CollectPrints::default();
+        self.interrupted.store(false, Ordering::SeqCst);
+        let res =
+            AstModule::parse(&filename, code.to_owned(), &Dialect::Extended).and_then(|ast| {
+                let mut eval = Evaluator::new(&self.module);
+                eval.set_print_handler(&prints);
+                let interrupted = self.interrupted.clone();
+                eval.set_check_cancelled(Box::new(move || interrupted.load(Ordering::SeqCst)));
+                eval.eval_module(ast, &self.globals)
+            });
+        let (result, error) = match res {
+            Ok(v) if v.is_none() => (None, None),
+            Ok(v) => (Some(display_data(v)), None),
+            Err(e) => {
+                let message = EvalMessage::from_error(Path::new(&filename), &e);
+                let error = CellError {
+                    ename: message.name,
+                    evalue: message.description,
+                    traceback: format!("{e}").lines().map(str::to_owned).collect(),
+                };
+                (None, Some(error))
+            }
+        };
+        Outcome {
+            stdout: prints.0.into_inner(),
+            result,
+            error,
+        }
+    }
+
+    /// The value of a global, of the module or the builtins.
+    fn global(&self, name: &str) -> Option<Value<'v>> {
+        self.module.get(name).or_else(|| {
+            self.globals
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_value())
+        })
+    }
+
+    /// Follow a dotted name like `json.encode`.
+    fn resolve(&self, dotted: &str) -> Option<Value<'v>> {
+        let mut parts = dotted.split('.');
+        let mut value = self.global(parts.next()?)?;
+        for attr in parts {
+            value = value.get_attr(attr, self.module.heap()).ok()??;
+        }
+        Some(value)
+    }
+
+    /// Complete the name before `cursor`: globals and keywords, or after a `.`, the
+    /// attributes of the value before it.
+    pub(crate) fn complete(&self, code: &str, cursor: usize) -> Completion {
+        let end = byte_offset(code, cursor);
+        let start = dotted_name_start(code, end);
+        let dotted = &code[start..end];
+        let (prefix, mut names, start) = match dotted.rsplit_once('.') {
+            Some((object, prefix)) => {
+                let names = self
+                    .resolve(object)
+                    .map(|v| v.dir_attr())
+                    .unwrap_or_default();
+                (prefix, names, end - prefix.len())
+            }
+            None => {
+                let mut names: Vec<String> =
+                    self.module.names().map(|x| x.as_str().to_owned()).collect();
+                names.extend(self.globals.names().map(|x| x.as_str().to_owned()));
+                names.extend(KEYWORDS.iter().map(|x| (*x).to_owned()));
+                (dotted, names, start)
+            }
+        };
+        names.retain(|x| x.starts_with(prefix));
+        names.sort();
+        names.dedup();
+        Completion {
+            matches: names,
+            cursor_start: code[..start].chars().count(),
+            cursor_end: cursor,
+        }
+    }
+
+    /// The documentation, as Markdown, of the dotted name around `cursor`.
+    pub(crate) fn inspect(&self, code: &str, cursor: usize) -> Option<String> {
+        let at = byte_offset(code, cursor);
+        let end = at
+            + code[at..]
+                .find(|c| !is_name_char(c))
+                .unwrap_or(code.len() - at);
+        let dotted = &code[dotted_name_start(code, end)..end];
+        let value = self.resolve(dotted)?;
+        let name = dotted.rsplit('.').next().unwrap_or(dotted);
+        Some(render_doc_item_no_link(name, &value.documentation()))
+    }
+}
+
+/// Whether a cell is ready to run, for `is_complete_request`: `complete`, `incomplete`
+/// when more lines are needed, or `invalid`.
+pub(crate) fn is_complete(code: &str) -> &'static str {
+    if AstModule::parse("cell", code.to_owned(), &Dialect::Extended).is_ok() {
+        // A block is only finished by a blank line.
+        let last = code.lines().last().unwrap_or_default();
+        if last.starts_with(char::is_whitespace) && !code.ends_with("\n\n") {
+            return "incomplete";
+        }
+        return "complete";
+    }
+    let trimmed = code.trim_end();
+    let open = trimmed
+        .chars()
+        .map(|c| match c {
+            '(' | '[' | '{' => 1,
+            ')' | ']' | '}' => -1,
+            _ => 0,
+        })
+        .sum::<i32>();
+    if open > 0 || trimmed.ends_with(':') || trimmed.ends_with('\\') {
+        "incomplete"
+    } else {
+        "invalid"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_session(f: impl FnOnce(&mut Session)) {
+        Module::with_temp_heap(|module| {
+            let mut session = Session::new(
+                module,
+                Globals::extended_internal(),
+                Arc::new(AtomicBool::new(false)),
+            );
+            f(&mut session)
+        })
+    }
+
+    #[test]
+    fn test_persistent_module() {
+        with_session(|session| {
+            let outcome = session.execute("x = 1\nprint('hello')", true);
+            assert_eq!("hello\n", outcome.stdout);
+            assert!(outcome.result.is_none());
+            let outcome = session.execute("x + 1", true);
+            assert_eq!("2", outcome.result.unwrap()["text/plain"]);
+            assert_eq!(2, session.execution_count());
+        });
+    }
+
+    #[test]
+    fn test_error() {
+        with_session(|session| {
+            let outcome = session.execute("fail('oops')", true);
+            let error = outcome.error.unwrap();
+            assert!(error.evalue.contains("oops"));
+            assert!(error.traceback.iter().any(|x| x.contains("cell[1]")));
+        });
+    }
+
+    #[test]
+    fn test_complete() {
+        with_session(|session| {
+            session.execute("my_var = struct(field = 1)", true);
+            let completion = session.complete("y = my_v", 8);
+            assert_eq!(vec!["my_var"], completion.matches);
+            assert_eq!((4, 8), (completion.cursor_start, completion.cursor_end));
+            let completion = session.complete("my_var.fi", 9);
+            assert_eq!(vec!["field"], completion.matches);
+            assert_eq!(7, completion.cursor_start);
+            assert!(
+                session
+                    .complete("js", 2)
+                    .matches
+                    .contains(&"json".to_owned())
+            );
+        });
+    }
+
+    #[test]
+    fn test_inspect() {
+        with_session(|session| {
+            assert!(session.inspect("len(x)", 1).unwrap().contains("len"));
+            assert!(session.inspect("undefined", 1).is_none());
+        });
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert_eq!("complete", is_complete("x = 1"));
+        assert_eq!("incomplete", is_complete("x = [1,"));
+        assert_eq!("incomplete", is_complete("def f():"));
+        assert_eq!("incomplete", is_complete("def f():\n  return 1"));
+        assert_eq!("invalid", is_complete("x = = 1"));
+    }
+}
@@ -124,9 +124,8 @@ impl<T: LspContext> Backend<T> {
         })
         .collect();
 
-        // Discover exported symbols from other documents
-        let docs = self.last_valid_parse.read().unwrap();
-        if docs.len() > 1 {
+        // Discover exported symbols from other documents, open or indexed.
+        if self.index.read().unwrap().len() > 1 {
             // Find the position of the last load in the current file.
             let mut last_load = None;
             let mut loads = HashMap::new();
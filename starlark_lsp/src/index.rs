@@ -0,0 +1,144 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! An index of the symbols exported by every file in the workspace, including those
+//! which are not open, used by `workspace/symbol` and to complete symbols from other files.
+//!
+//! The files are listed by [`LspContext::workspace_files`] at startup, and parsed one at a
+//! time while the server has no messages to handle. Open files are re-indexed whenever
+//! they change.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use lsp_types::InitializeParams;
+use lsp_types::Location;
+use lsp_types::SymbolInformation;
+use lsp_types::SymbolKind as LspSymbolKind;
+use lsp_types::Url;
+use lsp_types::WorkspaceSymbolParams;
+
+use crate::definition::LspModule;
+use crate::exported::Symbol;
+use crate::exported::SymbolKind;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspOpError;
+use crate::server::LspUrl;
+
+#[derive(Default)]
+pub(crate) struct WorkspaceIndex {
+    /// Files still to index.
+    pending: VecDeque<LspUrl>,
+    /// The exported symbols of each indexed file.
+    symbols: HashMap<LspUrl, Vec<Symbol>>,
+}
+
+impl WorkspaceIndex {
+    /// Record the exported symbols of `module`, replacing those found previously.
+    pub(crate) fn update(&mut self, uri: LspUrl, module: &LspModule) {
+        self.symbols.insert(uri, module.get_exported_symbols());
+    }
+
+    /// The indexed files, with the symbols they export.
+    pub(crate) fn files(&self) -> impl Iterator<Item = (&LspUrl, &[Symbol])> {
+        self.symbols.iter().map(|(uri, x)| (uri, x.as_slice()))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.symbols.len()
+    }
+}
+
+impl<T: LspContext> Backend<T> {
+    /// Queue the files of the workspace folders for indexing.
+    pub(crate) fn start_indexing(&self, initialize_params: &InitializeParams) {
+        let roots: Vec<PathBuf> = initialize_params
+            .workspace_folders
+            .iter()
+            .flatten()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect();
+        match self.context.workspace_files(&roots) {
+            Ok(files) => self.index.write().unwrap().pending.extend(files),
+            Err(e) => self.maybe_log_error(Err(LspOpError::FromContext(e))),
+        }
+    }
+
+    /// Index the next pending file, returning `false` if there were none left.
+    pub(crate) fn index_next(&self) -> bool {
+        let Some(uri) = self.index.write().unwrap().pending.pop_front() else {
+            return false;
+        };
+        // Open files are indexed as they are parsed.
+        if self.get_ast(&uri).is_none() {
+            match self.context.parse_file(&uri) {
+                Ok(Some(result)) => {
+                    if let Some(ast) = result.ast {
+                        self.index
+                            .write()
+                            .unwrap()
+                            .update(uri, &LspModule::new(ast));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => self.maybe_log_error(Err(LspOpError::FromContext(e))),
+            }
+        }
+        true
+    }
+
+    /// The exported symbols of the workspace whose name contains the query, ignoring case.
+    pub(crate) fn workspace_symbols(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Vec<SymbolInformation>, LspOpError> {
+        // Answer with the whole workspace, rather than whatever has been indexed so far.
+        while self.index_next() {}
+
+        let query = params.query.to_lowercase();
+        let index = self.index.read().unwrap();
+        let mut res = Vec::new();
+        for (uri, symbols) in index.files() {
+            let url = Url::try_from(uri)?;
+            for symbol in symbols
+                .iter()
+                .filter(|x| x.name.to_lowercase().contains(&query))
+            {
+                #[allow(deprecated)]
+                res.push(SymbolInformation {
+                    name: symbol.name.clone(),
+                    kind: match symbol.kind {
+                        SymbolKind::Any => LspSymbolKind::CONSTANT,
+                        SymbolKind::Function { .. } => LspSymbolKind::FUNCTION,
+                    },
+                    tags: None,
+                    deprecated: None,
+                    location: Location::new(url.clone(), symbol.span.resolve_span().into()),
+                    container_name: None,
+                });
+            }
+        }
+        res.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| a.location.uri.cmp(&b.location.uri))
+        });
+        Ok(res)
+    }
+}
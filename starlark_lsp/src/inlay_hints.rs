@@ -0,0 +1,318 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Inlay hints: the types inferred by the typechecker for variables, and the names of
+//! the parameters given positionally at calls of known functions.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use lsp_types::InitializeParams;
+use lsp_types::InlayHint;
+use lsp_types::InlayHintKind;
+use lsp_types::InlayHintLabel;
+use lsp_types::InlayHintParams;
+use lsp_types::Range;
+use starlark::environment::Globals;
+use starlark::syntax::AstModule;
+use starlark::typing::AstModuleTypecheck;
+use starlark::typing::Ty;
+use starlark_syntax::syntax::ast::ArgumentP;
+use starlark_syntax::syntax::ast::AssignP;
+use starlark_syntax::syntax::ast::AstAssignIdent;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::ForP;
+use starlark_syntax::syntax::ast::ParameterP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+use starlark_syntax::syntax::uniplate::Visit;
+
+use crate::definition::LspModule;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspOpError;
+use crate::server::LspUrl;
+
+/// The names of the parameters of the top-level `def` called `name` which can be given
+/// positionally.
+fn positional_parameters(ast: &AstModule, name: &str) -> Option<Vec<String>> {
+    top_level_stmts(ast.statement())
+        .into_iter()
+        .find_map(|x| match &x.node {
+            StmtP::Def(def) if def.name.ident == name => Some(
+                def.params
+                    .iter()
+                    .map_while(|p| match &p.node {
+                        ParameterP::Normal(name, _, _) => Some(Some(name.ident.clone())),
+                        ParameterP::Slash => Some(None),
+                        _ => None,
+                    })
+                    .flatten()
+                    .collect(),
+            ),
+            _ => None,
+        })
+}
+
+/// The variables assigned without a type annotation, or by a `for`, and whether they are
+/// at the top level of the module.
+fn assigned_idents<'a>(
+    x: Visit<'a, AstNoPayload>,
+    top_level: bool,
+    res: &mut Vec<(&'a AstAssignIdent, bool)>,
+) {
+    let mut inner_top_level = top_level;
+    if let Visit::Stmt(stmt) = &x {
+        let stmt = *stmt;
+        match &stmt.node {
+            StmtP::Assign(AssignP { lhs, ty: None, .. }) => {
+                lhs.visit_lvalue(|x| res.push((x, top_level)))
+            }
+            StmtP::For(ForP { var, .. }) => var.visit_lvalue(|x| res.push((x, top_level))),
+            StmtP::Def(_) => inner_top_level = false,
+            _ => {}
+        }
+    }
+    x.visit_children(|x| assigned_idents(x, inner_top_level, res));
+}
+
+impl LspModule {
+    /// Hints of the types inferred by typechecking against `globals`, after the first
+    /// assignment to each variable. Loaded symbols are not typechecked, so are `Any`.
+    pub(crate) fn type_hints(&self, globals: &Globals) -> Vec<InlayHint> {
+        let (_errors, typemap, interface, _approximations) =
+            self.ast.clone().typecheck(globals, &HashMap::new());
+        // Variables in functions, keyed by the span where they are first bound.
+        let local_types: HashMap<_, _> = typemap
+            .bindings()
+            .into_iter()
+            .map(|(_, span, ty)| (span, ty))
+            .collect();
+
+        let mut targets = Vec::new();
+        assigned_idents(Visit::Stmt(self.ast.statement()), true, &mut targets);
+        let mut seen = HashSet::new();
+        let mut res = Vec::new();
+        for (ident, top_level) in targets {
+            // Only the first assignment is hinted, which for the variables of functions is
+            // the only one `typemap` has.
+            if top_level && !seen.insert(&ident.ident) {
+                continue;
+            }
+            let ty: Option<&Ty> = if top_level {
+                interface.get(&ident.ident)
+            } else {
+                local_types.get(&ident.span).copied()
+            };
+            let Some(ty) = ty.filter(|ty| **ty != Ty::any()) else {
+                continue;
+            };
+            res.push(InlayHint {
+                position: Range::from(self.ast.codemap().resolve_span(ident.span)).end,
+                label: InlayHintLabel::String(format!(": {ty}")),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                padding_right: None,
+                data: None,
+            });
+        }
+        res
+    }
+
+    /// Hints of the parameter names before the positional arguments of calls. `parameters`
+    /// gives the names of the positional parameters of the function called, if known.
+    pub(crate) fn parameter_hints(
+        &self,
+        parameters: &mut dyn FnMut(&str) -> Option<Vec<String>>,
+    ) -> Vec<InlayHint> {
+        let mut res = Vec::new();
+        self.ast.visit_exprs(|x| {
+            let ExprP::Call(callee, args) = &x.node else {
+                return;
+            };
+            let ExprP::Identifier(function) = &callee.node else {
+                return;
+            };
+            let Some(names) = parameters(&function.ident) else {
+                return;
+            };
+            for (arg, name) in args.args.iter().zip(names) {
+                let ArgumentP::Positional(arg) = &arg.node else {
+                    break;
+                };
+                // `f(x)` where the parameter is also called `x` needs no hint.
+                if matches!(&arg.node, ExprP::Identifier(x) if x.ident == name) {
+                    continue;
+                }
+                res.push(InlayHint {
+                    position: Range::from(self.ast.codemap().resolve_span(arg.span)).start,
+                    label: InlayHintLabel::String(format!("{name}:")),
+                    kind: Some(InlayHintKind::PARAMETER),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: None,
+                    padding_right: Some(true),
+                    data: None,
+                });
+            }
+        });
+        res
+    }
+}
+
+impl<T: LspContext> Backend<T> {
+    /// Hints of inferred types, if the context gives globals to typecheck against, and of
+    /// parameter names at calls of the top-level functions of the module or loaded ones.
+    pub(crate) fn inlay_hints(
+        &self,
+        params: InlayHintParams,
+        initialize_params: &InitializeParams,
+    ) -> Result<Option<Vec<InlayHint>>, LspOpError> {
+        let uri: LspUrl = params.text_document.uri.try_into()?;
+        let Some(module) = self.get_ast(&uri) else {
+            return Ok(None);
+        };
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        // Local names of the loaded symbols, with the paths and names they are loaded by.
+        let loads: HashMap<&str, (&str, &str)> = top_level_stmts(module.ast.statement())
+            .into_iter()
+            .filter_map(|x| match &x.node {
+                StmtP::Load(load) => Some(load),
+                _ => None,
+            })
+            .flat_map(|load| {
+                load.args.iter().map(|arg| {
+                    (
+                        arg.local.ident.as_str(),
+                        (load.module.node.as_str(), arg.their.node.as_str()),
+                    )
+                })
+            })
+            .collect();
+        // The loaded modules, parsed at most once.
+        let mut loaded: HashMap<&str, Option<Arc<LspModule>>> = HashMap::new();
+        let mut parameters = |name: &str| {
+            if let Some(x) = positional_parameters(&module.ast, name) {
+                return Some(x);
+            }
+            let (path, their) = *loads.get(name)?;
+            let from = loaded.entry(path).or_insert_with(|| {
+                let from = self
+                    .resolve_load_path(path, &uri, workspace_root.as_deref())
+                    .ok()?;
+                self.get_ast_or_load_from_disk(&from).ok().flatten()
+            });
+            positional_parameters(&from.as_ref()?.ast, their)
+        };
+
+        let mut res = module.parameter_hints(&mut parameters);
+        if let Some(globals) = self.context.get_typecheck_globals(&uri) {
+            res.extend(module.type_hints(&globals));
+        }
+        res.retain(|x| {
+            x.position.line >= params.range.start.line && x.position.line <= params.range.end.line
+        });
+        res.sort_by_key(|x| (x.position.line, x.position.character));
+        Ok(Some(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn labels(hints: &[InlayHint]) -> Vec<(u32, u32, String)> {
+        hints
+            .iter()
+            .map(|x| match &x.label {
+                InlayHintLabel::String(label) => {
+                    (x.position.line, x.position.character, label.clone())
+                }
+                InlayHintLabel::LabelParts(_) => unreachable!("Only string labels are used"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hints_inferred_types() -> anyhow::Result<()> {
+        let fixture = FixtureWithRanges::from_fixture(
+            "foo.star",
+            dedent(
+                r#"
+                <x>x</x> = [1]
+                y: int = 2
+                def f(a: str):
+                    <n>n</n> = len(a)
+                    for <c>c</c> in a.elems():
+                        pass
+                    n = 3
+                "#,
+            )
+            .trim(),
+        )?;
+        let module = fixture.module().map_err(|e| e.into_anyhow())?;
+        let end = |id: &str| {
+            let span = fixture.resolved_span(id);
+            (span.end.line as u32, span.end.column as u32)
+        };
+        let expected: Vec<_> = [("x", ": list[int]"), ("n", ": int"), ("c", ": str")]
+            .into_iter()
+            .map(|(id, label)| (end(id).0, end(id).1, label.to_owned()))
+            .collect();
+        assert_eq!(expected, labels(&module.type_hints(&Globals::standard())));
+        Ok(())
+    }
+
+    #[test]
+    fn hints_parameter_names() -> anyhow::Result<()> {
+        let fixture = FixtureWithRanges::from_fixture(
+            "foo.star",
+            dedent(
+                r#"
+                def f(a, b, *args, c = 1):
+                    pass
+                b = 1
+                f(<one>1</one>, b, 3, 4)
+                f(<a>b</a>, c = 2)
+                g(1)
+                "#,
+            )
+            .trim(),
+        )?;
+        let module = fixture.module().map_err(|e| e.into_anyhow())?;
+        let begin = |id: &str| (fixture.begin_line(id), fixture.begin_column(id));
+        let mut parameters = |name: &str| positional_parameters(&module.ast, name);
+        assert_eq!(
+            vec![
+                (begin("one").0, begin("one").1, "a:".to_owned()),
+                (begin("a").0, begin("a").1, "a:".to_owned()),
+            ],
+            labels(&module.parameter_hints(&mut parameters))
+        );
+        Ok(())
+    }
+}
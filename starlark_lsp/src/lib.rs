@@ -27,8 +27,13 @@ mod definition;
 pub(crate) mod docs;
 pub mod error;
 mod exported;
+mod index;
+mod inlay_hints;
 pub(crate) mod inspect;
 pub(crate) mod loaded;
+mod references;
+mod rename;
+mod semantic_tokens;
 pub mod server;
 mod symbols;
 #[cfg(test)]
@@ -0,0 +1,539 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! All the references to a variable within a module, used for rename.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use dupe::Dupe;
+use lsp_types::DocumentHighlight;
+use lsp_types::DocumentHighlightKind;
+use lsp_types::DocumentHighlightParams;
+use lsp_types::InitializeParams;
+use lsp_types::Location;
+use lsp_types::ReferenceParams;
+use lsp_types::Url;
+use starlark::codemap::Pos;
+use starlark::codemap::Span;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+
+use crate::bind::Assigner;
+use crate::bind::Bind;
+use crate::bind::Scope;
+use crate::bind::scope;
+use crate::definition::LspModule;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspOpError;
+use crate::server::LspUrl;
+
+/// How a [`Reference`] uses the variable.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ReferenceKind {
+    /// The value is read.
+    Read,
+    /// The variable is bound, by an assignment, a parameter, a `def` or a `for`.
+    Write,
+    /// The local name in a `load`. Without an alias, as in `load("m", "x")`, the span is
+    /// the string `"x"`, which is also the name of the symbol in the loaded module.
+    Load { aliased: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reference {
+    pub(crate) span: Span,
+    pub(crate) kind: ReferenceKind,
+}
+
+/// Where the variable referred to is bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BindingKind {
+    /// In a function, lambda or comprehension.
+    Local,
+    /// At the top level of the module.
+    TopLevel,
+    /// By a `load` of the symbol `name` from `path`.
+    Loaded { path: String, name: String },
+    /// Not in this module, so a builtin or a global provided by the environment.
+    Global,
+}
+
+#[derive(Debug)]
+pub(crate) struct References {
+    pub(crate) name: String,
+    pub(crate) binding: BindingKind,
+    /// In source order, with one reference per span.
+    pub(crate) references: Vec<Reference>,
+    /// Names bound or used in the scopes of the references, which the variable can't be
+    /// renamed to without changing what some identifier refers to.
+    taken: HashSet<String>,
+}
+
+impl References {
+    /// Whether renaming the variable to `name` would clash with another variable.
+    pub(crate) fn conflicts_with(&self, name: &str) -> bool {
+        name != self.name && self.taken.contains(name)
+    }
+
+    /// Is the variable bound by a `load` with an alias, like `y` in `load("m", y = "x")`.
+    pub(crate) fn is_aliased_load(&self) -> bool {
+        self.references
+            .iter()
+            .any(|r| r.kind == ReferenceKind::Load { aliased: true })
+    }
+
+    fn in_scope(scope: &Scope, name: &str, top_level: bool) -> References {
+        let binding = match scope.bound.get(name) {
+            Some((Assigner::Load { path, name }, _)) => BindingKind::Loaded {
+                path: path.node.clone(),
+                name: name.node.clone(),
+            },
+            Some(_) if top_level => BindingKind::TopLevel,
+            Some(_) => BindingKind::Local,
+            None => BindingKind::Global,
+        };
+        let mut references = Vec::new();
+        let mut taken = HashSet::new();
+        collect(scope, name, &mut references, &mut taken);
+        taken.extend(scope.bound.keys().cloned());
+        taken.extend(scope.free.keys().cloned());
+        // `x += 1` both reads and writes `x`, report it as a write.
+        references.sort_by_key(|r| (r.span.begin(), Reverse(r.kind)));
+        references.dedup_by_key(|r| r.span);
+        References {
+            name: name.to_owned(),
+            binding,
+            references,
+            taken,
+        }
+    }
+}
+
+/// Find the variable at `pos`, pushing the scopes enclosing it to `path`, outermost first.
+fn locate<'a>(scope: &'a Scope, pos: Pos, path: &mut Vec<&'a Scope>) -> Option<&'a str> {
+    path.push(scope);
+    for bind in &scope.inner {
+        match bind {
+            Bind::Set(_, x) if x.span.contains(pos) => return Some(&x.ident),
+            Bind::Get(x) if x.span.contains(pos) => return Some(&x.ident),
+            Bind::GetDotted(x) if x.variable.span.contains(pos) => {
+                return Some(&x.variable.ident);
+            }
+            Bind::Scope(inner) => {
+                if let Some(name) = locate(inner, pos, path) {
+                    return Some(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    path.pop();
+    None
+}
+
+/// Collect the references to `name` in `scope`, and in the inner scopes which don't bind
+/// `name` again. Returns whether any were found.
+fn collect(
+    scope: &Scope,
+    name: &str,
+    references: &mut Vec<Reference>,
+    taken: &mut HashSet<String>,
+) -> bool {
+    let mut found = false;
+    for bind in &scope.inner {
+        let reference = match bind {
+            Bind::Set(assigner, x) if x.ident == name => Reference {
+                span: x.span,
+                kind: match assigner {
+                    Assigner::Load { name: their, .. } => ReferenceKind::Load {
+                        aliased: x.span != their.span,
+                    },
+                    Assigner::Argument | Assigner::Assign => ReferenceKind::Write,
+                },
+            },
+            Bind::Get(x) if x.ident == name => Reference {
+                span: x.span,
+                kind: ReferenceKind::Read,
+            },
+            Bind::GetDotted(x) if x.variable.ident == name => Reference {
+                span: x.variable.span,
+                kind: ReferenceKind::Read,
+            },
+            Bind::Scope(inner) if !inner.bound.contains_key(name) => {
+                found |= collect(inner, name, references, taken);
+                continue;
+            }
+            _ => continue,
+        };
+        references.push(reference);
+        found = true;
+    }
+    if found {
+        taken.extend(scope.bound.keys().cloned());
+    }
+    found
+}
+
+impl LspModule {
+    /// The references to the variable at `line` and `col`, which are zero based, if there is one.
+    pub(crate) fn find_references_at_location(&self, line: u32, col: u32) -> Option<References> {
+        let scope = scope(&self.ast);
+        let line_span = self.ast.codemap().line_span_opt(line as usize)?;
+        let pos = std::cmp::min(line_span.begin() + col, line_span.end());
+
+        let mut path = Vec::new();
+        let name = match locate(&scope, pos, &mut path) {
+            Some(name) => name.to_owned(),
+            None => {
+                // The symbol names of aliased loads, like `"x"` in `load("m", y = "x")`,
+                // are not binds, so look for them separately.
+                path = vec![&scope];
+                self.find_aliased_load_at(pos)?
+            }
+        };
+        // The variable is bound in the innermost scope which binds its name,
+        // and if none does, it is a global.
+        match path.iter().rposition(|s| s.bound.contains_key(&name)) {
+            Some(i) => Some(References::in_scope(path[i], &name, i == 0)),
+            None => Some(References::in_scope(path[0], &name, true)),
+        }
+    }
+
+    /// The references to the top-level variable `name`, or if the module does not bind it,
+    /// to the global `name`.
+    pub(crate) fn find_top_level_references(&self, name: &str) -> References {
+        References::in_scope(&scope(&self.ast), name, true)
+    }
+
+    fn find_aliased_load_at(&self, pos: Pos) -> Option<String> {
+        top_level_stmts(self.ast.statement())
+            .into_iter()
+            .find_map(|x| match &x.node {
+                StmtP::Load(load) => load
+                    .args
+                    .iter()
+                    .find(|arg| arg.their.span.contains(pos))
+                    .map(|arg| arg.local.ident.clone()),
+                _ => None,
+            })
+    }
+}
+
+/// How a module uses an exported variable, see [`Backend::find_exported_references`].
+pub(crate) enum ExportedUse {
+    /// The variable itself, in the module defining it or loading it without an alias.
+    Direct(References),
+    /// A `load` with an alias, like `load("m", y = "x")`: the span of `"x"`, and the
+    /// references to `y`.
+    Alias(Span, References),
+}
+
+pub(crate) struct ModuleReferences {
+    pub(crate) uri: LspUrl,
+    pub(crate) module: Arc<LspModule>,
+    pub(crate) uses: Vec<ExportedUse>,
+}
+
+impl<T: LspContext> Backend<T> {
+    /// The uses of the top-level variable `name` of the module at `uri`, in that module and
+    /// in the open modules loading it. Re-exports, `load("m", "x")` at the top level, are
+    /// followed to the module defining the variable, which comes first.
+    pub(crate) fn find_exported_references(
+        &self,
+        mut uri: LspUrl,
+        mut name: String,
+        initialize_params: &InitializeParams,
+    ) -> Result<Vec<ModuleReferences>, LspOpError> {
+        let workspace_root = |uri: &LspUrl| {
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), uri)
+        };
+        let parse = |uri: &LspUrl| {
+            self.get_ast_or_load_from_disk(uri)?
+                .ok_or_else(|| LspOpError::Other(format!("`{uri}` could not be parsed")))
+        };
+
+        // Modules which might load the variable, starting with the open ones.
+        let mut modules: HashMap<LspUrl, Arc<LspModule>> = self
+            .last_valid_parse
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(uri, module)| (uri.clone(), module.dupe()))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let (module, references) = loop {
+            let module = parse(&uri)?;
+            let references = module.find_top_level_references(&name);
+            match references.binding.clone() {
+                BindingKind::Loaded { path, name: their }
+                    if !references.is_aliased_load() && seen.insert(uri.clone()) =>
+                {
+                    let from =
+                        self.resolve_load_path(&path, &uri, workspace_root(&uri).as_deref())?;
+                    modules.insert(uri, module);
+                    uri = from;
+                    name = their;
+                }
+                BindingKind::TopLevel | BindingKind::Loaded { .. } => break (module, references),
+                BindingKind::Local | BindingKind::Global => {
+                    return Err(LspOpError::Other(format!(
+                        "`{name}` is not defined in `{uri}`"
+                    )));
+                }
+            }
+        };
+
+        let mut res = vec![ModuleReferences {
+            uri: uri.clone(),
+            module,
+            uses: vec![ExportedUse::Direct(references)],
+        }];
+        for (module_uri, module) in modules {
+            if module_uri == uri {
+                continue;
+            }
+            let mut uses = Vec::new();
+            for stmt in top_level_stmts(module.ast.statement()) {
+                let StmtP::Load(load) = &stmt.node else {
+                    continue;
+                };
+                if self
+                    .context
+                    .resolve_load(
+                        &load.module.node,
+                        &module_uri,
+                        workspace_root(&module_uri).as_deref(),
+                    )
+                    .ok()
+                    .as_ref()
+                    != Some(&uri)
+                {
+                    continue;
+                }
+                for arg in load.args.iter().filter(|arg| arg.their.node == name) {
+                    let references = module.find_top_level_references(&arg.local.ident);
+                    uses.push(if arg.local.span == arg.their.span {
+                        ExportedUse::Direct(references)
+                    } else {
+                        ExportedUse::Alias(arg.their.span, references)
+                    });
+                }
+            }
+            if !uses.is_empty() {
+                res.push(ModuleReferences {
+                    uri: module_uri,
+                    module,
+                    uses,
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    /// The locations of the references to the variable at the cursor. Exported variables
+    /// are also looked for in the open modules loading them.
+    pub(crate) fn find_references(
+        &self,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) -> Result<Vec<Location>, LspOpError> {
+        let uri: LspUrl = params.text_document_position.text_document.uri.try_into()?;
+        let position = params.text_document_position.position;
+        let include_declaration = params.context.include_declaration;
+        let Some(module) = self.get_ast(&uri) else {
+            return Ok(Vec::new());
+        };
+        let Some(references) =
+            module.find_references_at_location(position.line, position.character)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let exported = match &references.binding {
+            BindingKind::TopLevel => Some((uri.clone(), references.name.clone())),
+            BindingKind::Loaded { path, name } if !references.is_aliased_load() => {
+                let workspace_root =
+                    Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+                self.resolve_load_path(path, &uri, workspace_root.as_deref())
+                    .ok()
+                    .map(|from| (from, name.clone()))
+            }
+            _ => None,
+        };
+        // If the defining module can't be found, the local references are still useful.
+        let modules = exported
+            .and_then(|(from, name)| {
+                self.find_exported_references(from, name, initialize_params)
+                    .ok()
+            })
+            .unwrap_or_else(|| {
+                vec![ModuleReferences {
+                    uri,
+                    module,
+                    uses: vec![ExportedUse::Direct(references)],
+                }]
+            });
+
+        let mut res = Vec::new();
+        for ModuleReferences { uri, module, uses } in modules {
+            let url = Url::try_from(uri)?;
+            let mut spans = Vec::new();
+            for x in &uses {
+                let references = match x {
+                    ExportedUse::Direct(references) => references,
+                    ExportedUse::Alias(span, references) => {
+                        if include_declaration {
+                            spans.push(*span);
+                        }
+                        references
+                    }
+                };
+                spans.extend(
+                    references
+                        .references
+                        .iter()
+                        .filter(|r| include_declaration || r.kind == ReferenceKind::Read)
+                        .map(|r| r.span),
+                );
+            }
+            spans.sort_by_key(|x| x.begin());
+            spans.dedup();
+            let codemap = module.ast.codemap();
+            res.extend(
+                spans
+                    .into_iter()
+                    .map(|x| Location::new(url.clone(), codemap.resolve_span(x).into())),
+            );
+        }
+        Ok(res)
+    }
+
+    /// The references to the variable at the cursor in the current module.
+    pub(crate) fn document_highlights(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Vec<DocumentHighlight>, LspOpError> {
+        let uri: LspUrl = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .try_into()?;
+        let position = params.text_document_position_params.position;
+        let Some(module) = self.get_ast(&uri) else {
+            return Ok(Vec::new());
+        };
+        let Some(references) =
+            module.find_references_at_location(position.line, position.character)
+        else {
+            return Ok(Vec::new());
+        };
+        Ok(references
+            .references
+            .iter()
+            .map(|r| DocumentHighlight {
+                range: module.ast.codemap().resolve_span(r.span).into(),
+                kind: Some(match r.kind {
+                    ReferenceKind::Read => DocumentHighlightKind::READ,
+                    ReferenceKind::Write | ReferenceKind::Load { .. } => {
+                        DocumentHighlightKind::WRITE
+                    }
+                }),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn check(fixture: &str, click: &str, expected: &[&str]) -> anyhow::Result<References> {
+        let fixture = FixtureWithRanges::from_fixture("foo.star", dedent(fixture).trim())?;
+        let module = fixture.module().map_err(|e| e.into_anyhow())?;
+        let references = module
+            .find_references_at_location(fixture.begin_line(click), fixture.begin_column(click))
+            .unwrap();
+        let found: Vec<_> = references
+            .references
+            .iter()
+            .map(|r| module.ast.codemap().resolve_span(r.span))
+            .collect();
+        let expected: Vec<_> = expected.iter().map(|x| fixture.resolved_span(x)).collect();
+        assert_eq!(expected, found);
+        Ok(references)
+    }
+
+    #[test]
+    fn finds_references_respecting_scopes() -> anyhow::Result<()> {
+        let fixture = r#"
+            <x0>x</x0> = 1
+            def f(x):
+                return x
+            def g():
+                <x1>x</x1>.bit_length()
+                return [x for x in [<x2>x</x2>]]
+            <x3>x</x3> += 1
+            "#;
+        let references = check(fixture, "x1", &["x0", "x1", "x2", "x3"])?;
+        assert_eq!(BindingKind::TopLevel, references.binding);
+        assert_eq!(ReferenceKind::Write, references.references[3].kind);
+        assert!(references.conflicts_with("f"));
+        assert!(!references.conflicts_with("y"));
+
+        let fixture = r#"
+            def f(<p>p</p>, q = p):
+                def g():
+                    return <p1>p</p1>
+                return g
+            "#;
+        let references = check(fixture, "p1", &["p", "p1"])?;
+        assert_eq!(BindingKind::Local, references.binding);
+        assert!(references.conflicts_with("q"));
+        assert!(references.conflicts_with("g"));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_references_to_loads() -> anyhow::Result<()> {
+        let fixture = r#"
+            load("bar.star", <baz>"baz"</baz>, <y>y</y> = <x>"x"</x>)
+            <baz1>baz</baz1>(<y1>y</y1>)
+            "#;
+        let references = check(fixture, "baz1", &["baz", "baz1"])?;
+        assert_eq!(
+            BindingKind::Loaded {
+                path: "bar.star".to_owned(),
+                name: "baz".to_owned()
+            },
+            references.binding
+        );
+        assert!(!references.is_aliased_load());
+
+        let references = check(fixture, "x", &["y", "y1"])?;
+        assert!(references.is_aliased_load());
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rename of a variable, in its module and, for top-level variables, in the open modules
+//! loading it.
+
+use std::collections::HashMap;
+
+use lsp_types::InitializeParams;
+use lsp_types::RenameParams;
+use lsp_types::TextEdit;
+use lsp_types::Url;
+use lsp_types::WorkspaceEdit;
+use starlark_syntax::syntax::module::AstModuleFields;
+
+use crate::definition::LspModule;
+use crate::references::BindingKind;
+use crate::references::ExportedUse;
+use crate::references::ModuleReferences;
+use crate::references::ReferenceKind;
+use crate::references::References;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspOpError;
+use crate::server::LspUrl;
+
+/// Keywords and reserved words, which can't be used as identifiers.
+const KEYWORDS: &[&str] = &[
+    "and", "as", "break", "class", "continue", "def", "del", "elif", "else", "except", "finally",
+    "for", "from", "global", "if", "import", "in", "is", "lambda", "load", "nonlocal", "not", "or",
+    "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Edit all the references in `module`, failing if the new name is already taken.
+fn rename_references(
+    module: &LspModule,
+    references: &References,
+    new_name: &str,
+    edits: &mut Vec<TextEdit>,
+) -> Result<(), LspOpError> {
+    if references.conflicts_with(new_name) {
+        return Err(LspOpError::Other(format!(
+            "Can't rename `{}` to `{new_name}` in `{}`, the name is already used",
+            references.name,
+            module.ast.codemap().filename()
+        )));
+    }
+    for reference in &references.references {
+        let new_text = match reference.kind {
+            // Both the local name, and the name in the loaded module.
+            ReferenceKind::Load { aliased: false } => format!("\"{new_name}\""),
+            _ => new_name.to_owned(),
+        };
+        edits.push(TextEdit::new(
+            module.ast.codemap().resolve_span(reference.span).into(),
+            new_text,
+        ));
+    }
+    Ok(())
+}
+
+impl<T: LspContext> Backend<T> {
+    /// The edits to rename the variable at the cursor to `new_name`.
+    pub(crate) fn rename_edits(
+        &self,
+        params: RenameParams,
+        initialize_params: &InitializeParams,
+    ) -> Result<WorkspaceEdit, LspOpError> {
+        let uri: LspUrl = params.text_document_position.text_document.uri.try_into()?;
+        let position = params.text_document_position.position;
+        let new_name = params.new_name;
+        if !is_identifier(&new_name) {
+            return Err(LspOpError::Other(format!(
+                "`{new_name}` is not a valid identifier"
+            )));
+        }
+        let references = self.get_ast(&uri).and_then(|module| {
+            let references =
+                module.find_references_at_location(position.line, position.character)?;
+            Some((module, references))
+        });
+        let Some((module, references)) = references else {
+            return Err(LspOpError::Other(
+                "There is no variable to rename at the cursor".to_owned(),
+            ));
+        };
+
+        let mut edits: HashMap<LspUrl, Vec<TextEdit>> = HashMap::new();
+        match &references.binding {
+            BindingKind::Global => {
+                return Err(LspOpError::Other(format!(
+                    "`{}` is not defined in this workspace, so can't be renamed",
+                    references.name
+                )));
+            }
+            BindingKind::TopLevel => {
+                self.rename_exported(
+                    uri,
+                    references.name.clone(),
+                    &new_name,
+                    initialize_params,
+                    &mut edits,
+                )?;
+            }
+            // An alias only has to change locally.
+            BindingKind::Loaded { path, name } if !references.is_aliased_load() => {
+                let workspace_root =
+                    Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+                let defined_in = self.resolve_load_path(path, &uri, workspace_root.as_deref())?;
+                self.rename_exported(
+                    defined_in,
+                    name.clone(),
+                    &new_name,
+                    initialize_params,
+                    &mut edits,
+                )?;
+            }
+            BindingKind::Local | BindingKind::Loaded { .. } => {
+                rename_references(
+                    &module,
+                    &references,
+                    &new_name,
+                    edits.entry(uri).or_default(),
+                )?;
+            }
+        }
+
+        let mut changes = HashMap::new();
+        for (uri, edits) in edits {
+            changes.insert(Url::try_from(&uri)?, edits);
+        }
+        Ok(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        })
+    }
+
+    /// Rename the top-level variable `name` of the module at `uri`, along with the loads of
+    /// it in the open modules.
+    fn rename_exported(
+        &self,
+        uri: LspUrl,
+        name: String,
+        new_name: &str,
+        initialize_params: &InitializeParams,
+        edits: &mut HashMap<LspUrl, Vec<TextEdit>>,
+    ) -> Result<(), LspOpError> {
+        for ModuleReferences { uri, module, uses } in
+            self.find_exported_references(uri, name, initialize_params)?
+        {
+            let module_edits = edits.entry(uri).or_default();
+            for x in &uses {
+                match x {
+                    ExportedUse::Direct(references) => {
+                        rename_references(&module, references, new_name, module_edits)?
+                    }
+                    // The alias stays, only the name of the symbol it loads changes.
+                    ExportedUse::Alias(span, _) => module_edits.push(TextEdit::new(
+                        module.ast.codemap().resolve_span(*span).into(),
+                        format!("\"{new_name}\""),
+                    )),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_identifiers() {
+        assert!(is_identifier("foo_1"));
+        assert!(is_identifier("_Foo"));
+        assert!(!is_identifier("1foo"));
+        assert!(!is_identifier("foo-bar"));
+        assert!(!is_identifier("lambda"));
+        assert!(!is_identifier(""));
+    }
+}
@@ -0,0 +1,354 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Semantic tokens, classifying the identifiers of a module with the scopes found by
+//! [`crate::bind`], so editors can highlight parameters, locals, globals, builtins and
+//! types differently.
+
+use std::collections::HashSet;
+
+use lsp_types::Range;
+use lsp_types::SemanticToken;
+use lsp_types::SemanticTokenModifier;
+use lsp_types::SemanticTokenType;
+use lsp_types::SemanticTokens;
+use lsp_types::SemanticTokensLegend;
+use lsp_types::SemanticTokensParams;
+use lsp_types::SemanticTokensRangeParams;
+use lsp_types::SemanticTokensRangeResult;
+use lsp_types::SemanticTokensResult;
+use starlark::codemap::Span;
+use starlark::docs::DocItem;
+use starlark::docs::DocMember;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::uniplate::Visit;
+
+use crate::bind::Assigner;
+use crate::bind::Bind;
+use crate::bind::Scope;
+use crate::bind::scope;
+use crate::definition::LspModule;
+use crate::server::Backend;
+use crate::server::LspContext;
+use crate::server::LspOpError;
+use crate::server::LspUrl;
+
+/// The kind of an identifier, the index of its type in the [`legend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenType {
+    Parameter,
+    Variable,
+    Function,
+    Type,
+}
+
+/// Modifiers of a token, as bits in the order of the [`legend`].
+pub(crate) mod modifiers {
+    /// Where the variable is bound.
+    pub(crate) const DECLARATION: u32 = 1 << 0;
+    /// A top-level variable of the module.
+    pub(crate) const GLOBAL: u32 = 1 << 1;
+    /// A builtin, or another global provided by the environment.
+    pub(crate) const DEFAULT_LIBRARY: u32 = 1 << 2;
+}
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::TYPE,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::new("global"),
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+        ],
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub(crate) span: Span,
+    pub(crate) token_type: TokenType,
+    pub(crate) modifiers: u32,
+}
+
+/// The spans of the type annotations, and of the names of the `def`s.
+#[derive(Default)]
+struct Annotations {
+    types: Vec<Span>,
+    defs: HashSet<Span>,
+}
+
+impl Annotations {
+    fn collect(&mut self, x: Visit<AstNoPayload>) {
+        match &x {
+            Visit::Stmt(stmt) => match &stmt.node {
+                StmtP::Def(def) => {
+                    self.defs.insert(def.name.span);
+                    self.types.extend(
+                        def.params
+                            .iter()
+                            .filter_map(|p| p.split().1)
+                            .map(|t| t.span),
+                    );
+                    self.types.extend(def.return_type.iter().map(|t| t.span));
+                }
+                StmtP::Assign(assign) => self.types.extend(assign.ty.iter().map(|t| t.span)),
+                _ => {}
+            },
+            Visit::Expr(expr) => {
+                if let ExprP::Lambda(lambda) = &expr.node {
+                    self.types.extend(
+                        lambda
+                            .params
+                            .iter()
+                            .filter_map(|p| p.split().1)
+                            .map(|t| t.span),
+                    );
+                }
+            }
+        }
+        x.visit_children(|x| self.collect(x));
+    }
+
+    fn is_type(&self, span: Span) -> bool {
+        self.types.iter().any(|t| t.contains(span.begin()))
+    }
+}
+
+/// Classify the identifiers bound or used in `scope`, whose enclosing scopes are `path`.
+fn classify<'a>(
+    scope: &'a Scope,
+    path: &mut Vec<&'a Scope>,
+    annotations: &Annotations,
+    is_builtin_function: &dyn Fn(&str) -> bool,
+    res: &mut Vec<Token>,
+) {
+    path.push(scope);
+    for bind in &scope.inner {
+        let (name, span, declaration) = match bind {
+            // The name of an unaliased load is the string literal, which is not an identifier.
+            Bind::Set(Assigner::Load { name, .. }, x) if name.span == x.span => continue,
+            Bind::Set(_, x) => (&x.ident, x.span, true),
+            Bind::Get(x) => (&x.ident, x.span, false),
+            Bind::GetDotted(x) => (&x.variable.ident, x.variable.span, false),
+            Bind::Scope(inner) => {
+                classify(inner, path, annotations, is_builtin_function, res);
+                continue;
+            }
+            Bind::Flow => continue,
+        };
+        let mut modifiers = if declaration {
+            modifiers::DECLARATION
+        } else {
+            0
+        };
+        // The variable is bound in the innermost scope which binds its name.
+        let binding = path.iter().rposition(|s| s.bound.contains_key(name));
+        match binding {
+            Some(0) => modifiers |= modifiers::GLOBAL,
+            None => modifiers |= modifiers::DEFAULT_LIBRARY,
+            Some(_) => {}
+        }
+        let token_type = match binding {
+            _ if annotations.is_type(span) => TokenType::Type,
+            Some(i) => match &path[i].bound[name] {
+                (Assigner::Argument, _) => TokenType::Parameter,
+                (_, first) if annotations.defs.contains(first) => TokenType::Function,
+                _ => TokenType::Variable,
+            },
+            None if is_builtin_function(name) => TokenType::Function,
+            None => TokenType::Variable,
+        };
+        res.push(Token {
+            span,
+            token_type,
+            modifiers,
+        });
+    }
+    path.pop();
+}
+
+impl LspModule {
+    /// The semantic tokens of the identifiers in the module, in source order.
+    /// `is_builtin_function` says which of the globals not bound by the module are functions.
+    pub(crate) fn semantic_tokens(&self, is_builtin_function: &dyn Fn(&str) -> bool) -> Vec<Token> {
+        let mut annotations = Annotations::default();
+        annotations.collect(Visit::Stmt(self.ast.statement()));
+        let mut res = Vec::new();
+        classify(
+            &scope(&self.ast),
+            &mut Vec::new(),
+            &annotations,
+            is_builtin_function,
+            &mut res,
+        );
+        // `x += 1` both reads and binds `x`, keep the read.
+        res.sort_by_key(|x| x.span.begin());
+        res.dedup_by_key(|x| x.span);
+        res
+    }
+
+    /// Encode tokens, relative to each other as LSP wants them, keeping those in `range`.
+    fn encode_semantic_tokens(&self, tokens: &[Token], range: Option<Range>) -> SemanticTokens {
+        let mut data = Vec::new();
+        let (mut line, mut column) = (0, 0);
+        for token in tokens {
+            let span = self.ast.codemap().resolve_span(token.span);
+            let (token_line, token_column) = (span.begin.line as u32, span.begin.column as u32);
+            if range.is_some_and(|r| token_line < r.start.line || token_line > r.end.line) {
+                continue;
+            }
+            data.push(SemanticToken {
+                delta_line: token_line - line,
+                delta_start: if token_line == line {
+                    token_column - column
+                } else {
+                    token_column
+                },
+                length: (span.end.column - span.begin.column) as u32,
+                token_type: token.token_type as u32,
+                token_modifiers_bitset: token.modifiers,
+            });
+            (line, column) = (token_line, token_column);
+        }
+        SemanticTokens {
+            result_id: None,
+            data,
+        }
+    }
+}
+
+impl<T: LspContext> Backend<T> {
+    fn encoded_semantic_tokens(
+        &self,
+        uri: &LspUrl,
+        range: Option<Range>,
+    ) -> Option<SemanticTokens> {
+        let module = self.get_ast(uri)?;
+        let environment = self.context.get_environment(uri);
+        let is_builtin_function = |name: &str| {
+            matches!(
+                environment.members.get(name),
+                Some(DocItem::Member(DocMember::Function(_)))
+            )
+        };
+        let tokens = module.semantic_tokens(&is_builtin_function);
+        Some(module.encode_semantic_tokens(&tokens, range))
+    }
+
+    pub(crate) fn find_semantic_tokens(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>, LspOpError> {
+        let uri = params.text_document.uri.try_into()?;
+        Ok(self
+            .encoded_semantic_tokens(&uri, None)
+            .map(SemanticTokensResult::Tokens))
+    }
+
+    pub(crate) fn find_semantic_tokens_in_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>, LspOpError> {
+        let uri = params.text_document.uri.try_into()?;
+        Ok(self
+            .encoded_semantic_tokens(&uri, Some(params.range))
+            .map(SemanticTokensRangeResult::Tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    #[test]
+    fn classifies_identifiers() -> anyhow::Result<()> {
+        let fixture = FixtureWithRanges::from_fixture(
+            "foo.star",
+            dedent(
+                r#"
+                load("bar.star", "baz", <alias>alias</alias> = "x")
+                <g>g</g> = 1
+                def <f>f</f>(<p>p</p>: <int>int</int>) -> <list>list</list>:
+                    <l>l</l> = <p1>p</p1> + <g1>g</g1> + <len>len</len>(<other>other</other>)
+                    <l1>l</l1> += 1
+                    return [<f1>f</f1>(<x>x</x>) for <x1>x</x1> in <l2>l</l2>]
+                "#,
+            )
+            .trim(),
+        )?;
+        let module = fixture.module().map_err(|e| e.into_anyhow())?;
+        let found: Vec<_> = module
+            .semantic_tokens(&|name| name == "len")
+            .into_iter()
+            .map(|t| {
+                (
+                    module.ast.codemap().resolve_span(t.span),
+                    t.token_type,
+                    t.modifiers,
+                )
+            })
+            .collect();
+
+        use modifiers::*;
+        let expected: Vec<_> = [
+            ("alias", TokenType::Variable, DECLARATION | GLOBAL),
+            ("g", TokenType::Variable, DECLARATION | GLOBAL),
+            ("f", TokenType::Function, DECLARATION | GLOBAL),
+            ("p", TokenType::Parameter, DECLARATION),
+            ("int", TokenType::Type, DEFAULT_LIBRARY),
+            ("list", TokenType::Type, DEFAULT_LIBRARY),
+            ("l", TokenType::Variable, DECLARATION),
+            ("p1", TokenType::Parameter, 0),
+            ("g1", TokenType::Variable, GLOBAL),
+            ("len", TokenType::Function, DEFAULT_LIBRARY),
+            ("other", TokenType::Variable, DEFAULT_LIBRARY),
+            ("l1", TokenType::Variable, 0),
+            ("f1", TokenType::Function, GLOBAL),
+            ("x", TokenType::Variable, 0),
+            ("x1", TokenType::Variable, DECLARATION),
+            ("l2", TokenType::Variable, 0),
+        ]
+        .into_iter()
+        .map(|(id, token_type, modifiers)| (fixture.resolved_span(id), token_type, modifiers))
+        .collect();
+        assert_eq!(expected, found);
+
+        // `g = 1` and `def f(...)`, relative to the previous token.
+        let encoded = module.encode_semantic_tokens(
+            &module.semantic_tokens(&|_| false),
+            Some(Range::new(Position::new(1, 0), Position::new(2, 0))),
+        );
+        let position = |x: &SemanticToken| (x.delta_line, x.delta_start, x.length);
+        assert_eq!(
+            vec![(1, 0, 1), (1, 4, 1), (0, 2, 1), (0, 3, 3), (0, 8, 4)],
+            encoded.data.iter().map(position).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
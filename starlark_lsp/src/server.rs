@@ -48,6 +48,7 @@ use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DocumentHighlightParams;
 use lsp_types::Documentation;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
@@ -56,6 +57,7 @@ use lsp_types::HoverContents;
 use lsp_types::HoverParams;
 use lsp_types::HoverProviderCapability;
 use lsp_types::InitializeParams;
+use lsp_types::InlayHintParams;
 use lsp_types::LanguageString;
 use lsp_types::LocationLink;
 use lsp_types::LogMessageParams;
@@ -67,6 +69,13 @@ use lsp_types::OneOf;
 use lsp_types::Position;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
+use lsp_types::ReferenceParams;
+use lsp_types::RenameParams;
+use lsp_types::SemanticTokensFullOptions;
+use lsp_types::SemanticTokensOptions;
+use lsp_types::SemanticTokensParams;
+use lsp_types::SemanticTokensRangeParams;
+use lsp_types::SemanticTokensServerCapabilities;
 use lsp_types::ServerCapabilities;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
@@ -74,14 +83,22 @@ use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkDoneProgressOptions;
 use lsp_types::WorkspaceFolder;
+use lsp_types::WorkspaceSymbolParams;
 use lsp_types::notification::DidChangeTextDocument;
 use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
 use lsp_types::request::Completion;
+use lsp_types::request::DocumentHighlightRequest;
 use lsp_types::request::GotoDefinition;
 use lsp_types::request::HoverRequest;
+use lsp_types::request::InlayHintRequest;
+use lsp_types::request::References;
+use lsp_types::request::Rename;
+use lsp_types::request::SemanticTokensFullRequest;
+use lsp_types::request::SemanticTokensRangeRequest;
+use lsp_types::request::WorkspaceSymbolRequest;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
@@ -94,6 +111,7 @@ use starlark::docs::DocMember;
 use starlark::docs::DocModule;
 use starlark::docs::markdown::render_doc_item_no_link;
 use starlark::docs::markdown::render_doc_param;
+use starlark::environment::Globals;
 use starlark::syntax::AstModule;
 use starlark_syntax::codemap::ResolvedPos;
 use starlark_syntax::syntax::ast::AstPayload;
@@ -106,8 +124,10 @@ use crate::definition::Definition;
 use crate::definition::DottedDefinition;
 use crate::definition::IdentifierDefinition;
 use crate::definition::LspModule;
+use crate::index::WorkspaceIndex;
 use crate::inspect::AstModuleInspect;
 use crate::inspect::AutocompleteType;
+use crate::semantic_tokens;
 use crate::symbols::find_symbols_at_location;
 
 /// The request to get the file contents for a starlark: URI
@@ -372,9 +392,25 @@ pub trait LspContext {
         Ok(result)
     }
 
+    /// List the Starlark files of the workspace, which are indexed so their symbols can be
+    /// found and completed without the files being open.
+    ///
+    /// `workspace_roots` are the folders of the workspace given by the client, if any.
+    fn workspace_files(&self, workspace_roots: &[PathBuf]) -> Result<Vec<LspUrl>, String> {
+        let _unused = workspace_roots;
+        Ok(Vec::new())
+    }
+
     /// Get the preloaded environment for a particular file.
     fn get_environment(&self, uri: &LspUrl) -> DocModule;
 
+    /// Get the globals to typecheck a file against, to show the inferred types of its
+    /// variables as inlay hints. If `None`, types are not shown.
+    fn get_typecheck_globals(&self, uri: &LspUrl) -> Option<Globals> {
+        let _unused = uri;
+        None
+    }
+
     /// Get the LSPUrl for a global symbol if possible.
     ///
     /// The current file is provided in case different files have different global symbols
@@ -406,6 +442,8 @@ pub(crate) struct Backend<T: LspContext> {
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     pub(crate) last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// The symbols exported by the files of the workspace, open or not.
+    pub(crate) index: RwLock<WorkspaceIndex>,
 }
 
 /// The logic implementations of stuff
@@ -423,11 +461,24 @@ impl<T: LspContext> Backend<T> {
             definition_provider,
             completion_provider: Some(CompletionOptions::default()),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            rename_provider: Some(OneOf::Left(true)),
+            references_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            semantic_tokens_provider: Some(
+                SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                    legend: semantic_tokens::legend(),
+                    range: Some(true),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                    ..SemanticTokensOptions::default()
+                }),
+            ),
             ..ServerCapabilities::default()
         }
     }
 
-    fn get_ast(&self, uri: &LspUrl) -> Option<Arc<LspModule>> {
+    pub(crate) fn get_ast(&self, uri: &LspUrl) -> Option<Arc<LspModule>> {
         let last_valid_parse = self.last_valid_parse.read().unwrap();
         last_valid_parse.get(uri).duped()
     }
@@ -452,6 +503,7 @@ impl<T: LspContext> Backend<T> {
         let eval_result = self.context.parse_file_with_contents(&lsp_url, text);
         if let Some(ast) = eval_result.ast {
             let module = Arc::new(LspModule::new(ast));
+            self.index.write().unwrap().update(lsp_url.clone(), &module);
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(lsp_url, module);
         }
@@ -522,6 +574,62 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.hover_info(params, initialize_params)));
     }
 
+    /// Renames the variable at the cursor everywhere it is used, including in the open
+    /// files loading it.
+    fn rename(&self, id: RequestId, params: RenameParams, initialize_params: &InitializeParams) {
+        self.send_response(new_response(
+            id,
+            self.rename_edits(params, initialize_params),
+        ));
+    }
+
+    /// Finds the references to the variable at the cursor, including in the open files
+    /// loading it.
+    fn references(
+        &self,
+        id: RequestId,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.find_references(params, initialize_params),
+        ));
+    }
+
+    /// Highlights the references to the variable at the cursor in the current file.
+    fn document_highlight(&self, id: RequestId, params: DocumentHighlightParams) {
+        self.send_response(new_response(id, self.document_highlights(params)));
+    }
+
+    /// Shows the inferred types of variables, and the names of positional parameters.
+    fn inlay_hint(
+        &self,
+        id: RequestId,
+        params: InlayHintParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.inlay_hints(params, initialize_params),
+        ));
+    }
+
+    /// Classifies the identifiers of the file, for highlighting.
+    fn semantic_tokens_full(&self, id: RequestId, params: SemanticTokensParams) {
+        self.send_response(new_response(id, self.find_semantic_tokens(params)));
+    }
+
+    /// Classifies the identifiers of part of the file, for highlighting.
+    fn semantic_tokens_range(&self, id: RequestId, params: SemanticTokensRangeParams) {
+        self.send_response(new_response(id, self.find_semantic_tokens_in_range(params)));
+    }
+
+    /// Finds the symbols exported by the files of the workspace matching the query.
+    fn workspace_symbol(&self, id: RequestId, params: WorkspaceSymbolParams) {
+        self.send_response(new_response(id, self.workspace_symbols(params)));
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response = match params.uri {
@@ -860,12 +968,16 @@ impl<T: LspContext> Backend<T> {
 
         let all_documents = self.last_valid_parse.read().unwrap();
 
-        for (doc_uri, doc) in all_documents
-            .iter()
-            .filter(|&(doc_uri, _)| match except_from {
-                Some(uri) => doc_uri != uri,
-                None => true,
-            })
+        // The index covers the open documents, and any other files of the workspace.
+        for (doc_uri, doc_symbols) in
+            self.index
+                .read()
+                .unwrap()
+                .files()
+                .filter(|&(doc_uri, _)| match except_from {
+                    Some(uri) => doc_uri != uri,
+                    None => true,
+                })
         {
             let Ok(load_path) =
                 self.context
@@ -874,15 +986,14 @@ impl<T: LspContext> Backend<T> {
                 continue;
             };
 
-            for symbol in doc
-                .get_exported_symbols()
-                .into_iter()
+            for symbol in doc_symbols
+                .iter()
                 .filter(|symbol| !symbols.contains_key(&symbol.name))
             {
                 seen.insert(format!("{load_path}:{}", &symbol.name));
 
                 let text_edits = Some(vec![format_text_edit(&load_path, &symbol.name)]);
-                let mut completion_item: CompletionItem = symbol.into();
+                let mut completion_item: CompletionItem = symbol.clone().into();
                 completion_item.detail = Some(format!("Load from {load_path}"));
                 completion_item.additional_text_edits = text_edits;
 
@@ -1200,7 +1311,7 @@ impl<T: LspContext> Backend<T> {
         })
     }
 
-    fn get_workspace_root(
+    pub(crate) fn get_workspace_root(
         workspace_roots: Option<&Vec<WorkspaceFolder>>,
         target: &LspUrl,
     ) -> Option<PathBuf> {
@@ -1229,7 +1340,7 @@ impl<T: LspContext> Backend<T> {
         self.connection.sender.send(Message::Response(x)).unwrap()
     }
 
-    fn maybe_log_error(&self, res: Result<(), LspOpError>) {
+    pub(crate) fn maybe_log_error(&self, res: Result<(), LspOpError>) {
         if let Err(e) = res {
             self.log_message(MessageType::ERROR, &e.format());
         }
@@ -1250,7 +1361,13 @@ impl<T: LspContext> Backend<T> {
 
     fn main_loop(&self, initialize_params: InitializeParams) -> Result<(), ProtocolError> {
         self.log_message(MessageType::INFO, "Starlark server initialised");
-        for msg in &self.connection.receiver {
+        self.start_indexing(&initialize_params);
+        loop {
+            // Index the workspace while there are no messages to handle.
+            while self.connection.receiver.is_empty() && self.index_next() {}
+            let Ok(msg) = self.connection.receiver.recv() else {
+                break;
+            };
             match msg {
                 Message::Request(req) => {
                     // TODO(nmj): Also implement DocumentSymbols so that some logic can
@@ -1263,6 +1380,20 @@ impl<T: LspContext> Backend<T> {
                         self.completion(req.id, params, &initialize_params);
                     } else if let Some(params) = as_request::<HoverRequest>(&req) {
                         self.hover(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<Rename>(&req) {
+                        self.rename(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<References>(&req) {
+                        self.references(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<DocumentHighlightRequest>(&req) {
+                        self.document_highlight(req.id, params);
+                    } else if let Some(params) = as_request::<WorkspaceSymbolRequest>(&req) {
+                        self.workspace_symbol(req.id, params);
+                    } else if let Some(params) = as_request::<InlayHintRequest>(&req) {
+                        self.inlay_hint(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<SemanticTokensFullRequest>(&req) {
+                        self.semantic_tokens_full(req.id, params);
+                    } else if let Some(params) = as_request::<SemanticTokensRangeRequest>(&req) {
+                        self.semantic_tokens_range(req.id, params);
                     } else if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
@@ -1337,6 +1468,7 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        index: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -1414,21 +1546,41 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::path::Path;
     use std::path::PathBuf;
 
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::CompletionParams;
+    use lsp_types::CompletionResponse;
+    use lsp_types::DocumentHighlight;
+    use lsp_types::DocumentHighlightKind;
+    use lsp_types::DocumentHighlightParams;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
+    use lsp_types::Location;
     use lsp_types::LocationLink;
     use lsp_types::Position;
     use lsp_types::Range;
+    use lsp_types::ReferenceContext;
+    use lsp_types::ReferenceParams;
+    use lsp_types::RenameParams;
+    use lsp_types::SymbolInformation;
+    use lsp_types::SymbolKind;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
+    use lsp_types::TextEdit;
     use lsp_types::Url;
+    use lsp_types::WorkspaceEdit;
+    use lsp_types::WorkspaceSymbolParams;
+    use lsp_types::request::Completion;
+    use lsp_types::request::DocumentHighlightRequest;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::request::References;
+    use lsp_types::request::Rename;
+    use lsp_types::request::WorkspaceSymbolRequest;
     use starlark::codemap::ResolvedSpan;
     use starlark::wasm::is_wasm;
     use textwrap::dedent;
@@ -2554,4 +2706,266 @@ mod tests {
         }
         Ok(())
     }
+
+    fn rename_request(
+        server: &mut TestServer,
+        uri: Url,
+        line: u32,
+        character: u32,
+        new_name: &str,
+    ) -> Request {
+        server.new_request::<Rename>(RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: Position { line, character },
+            },
+            new_name: new_name.to_owned(),
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    #[test]
+    fn renames_across_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <baz>"baz"</baz>)
+            <baz_click>baz</baz_click>()
+            other = 1
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = "def <def>baz</def>():\n    pass\n<use>baz</use>()\n";
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), bar_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+
+        let request = rename_request(
+            &mut server,
+            foo_uri.clone(),
+            foo.begin_line("baz_click"),
+            foo.begin_column("baz_click"),
+            "qux",
+        );
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<WorkspaceEdit>(request_id)?;
+
+        let edit = |fixture: &FixtureWithRanges, id: &str, text: &str| {
+            TextEdit::new(fixture.resolved_span(id).into(), text.to_owned())
+        };
+        let expected = HashMap::from([
+            (
+                foo_uri.clone(),
+                vec![edit(&foo, "baz", "\"qux\""), edit(&foo, "baz_click", "qux")],
+            ),
+            (
+                bar_uri,
+                vec![edit(&bar, "def", "qux"), edit(&bar, "use", "qux")],
+            ),
+        ]);
+        assert_eq!(Some(expected), response.changes);
+
+        for new_name in ["other", "not an identifier"] {
+            let request = rename_request(
+                &mut server,
+                foo_uri.clone(),
+                foo.begin_line("baz_click"),
+                foo.begin_column("baz_click"),
+                new_name,
+            );
+            let request_id = server.send_request(request)?;
+            assert!(server.get_response::<WorkspaceEdit>(request_id).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn finds_references_across_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <baz>"baz"</baz>)
+            <baz_click>baz</baz_click>()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = "def <def>baz</def>():\n    pass\n<use>baz</use>()\n";
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), bar_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+
+        let location = |uri: &Url, fixture: &FixtureWithRanges, id: &str| {
+            Location::new(uri.clone(), fixture.resolved_span(id).into())
+        };
+        for include_declaration in [true, false] {
+            let request = server.new_request::<References>(ReferenceParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: foo_uri.clone(),
+                    },
+                    position: Position {
+                        line: foo.begin_line("baz_click"),
+                        character: foo.begin_column("baz_click"),
+                    },
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: ReferenceContext {
+                    include_declaration,
+                },
+            });
+            let request_id = server.send_request(request)?;
+            let mut response = server.get_response::<Vec<Location>>(request_id)?;
+            response.sort_by_key(|x| x.uri.to_string());
+
+            let mut expected = vec![
+                location(&foo_uri, &foo, "baz"),
+                location(&foo_uri, &foo, "baz_click"),
+                location(&bar_uri, &bar, "def"),
+                location(&bar_uri, &bar, "use"),
+            ];
+            if !include_declaration {
+                expected.retain(|x| {
+                    *x != location(&foo_uri, &foo, "baz") && *x != location(&bar_uri, &bar, "def")
+                });
+            }
+            expected.sort_by_key(|x| x.uri.to_string());
+            assert_eq!(expected, response);
+        }
+
+        let request = server.new_request::<DocumentHighlightRequest>(DocumentHighlightParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: bar_uri.clone(),
+                },
+                position: Position {
+                    line: bar.begin_line("use"),
+                    character: bar.begin_column("use"),
+                },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<DocumentHighlight>>(request_id)?;
+        let expected = vec![
+            DocumentHighlight {
+                range: bar.resolved_span("def").into(),
+                kind: Some(DocumentHighlightKind::WRITE),
+            },
+            DocumentHighlight {
+                range: bar.resolved_span("use").into(),
+                kind: Some(DocumentHighlightKind::READ),
+            },
+        ];
+        assert_eq!(expected, response);
+        Ok(())
+    }
+
+    fn workspace_symbols(server: &mut TestServer, query: &str) -> anyhow::Result<Vec<String>> {
+        let request = server.new_request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            query: query.to_owned(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<SymbolInformation>>(request_id)?;
+        Ok(response.into_iter().map(|x| x.name).collect())
+    }
+
+    #[test]
+    fn indexes_workspace_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+        let bar = FixtureWithRanges::from_fixture(
+            bar_uri.path(),
+            "def <baz>baz</baz>():\n    pass\n_private = 1\n",
+        )?;
+
+        // `bar.star` is never opened, so is only known from the index.
+        let mut server =
+            TestServer::new_with_workspace_files(&[(&bar_uri, bar.program().as_str())])?;
+        server.open_file(foo_uri.clone(), "qux = 1\n".to_owned())?;
+
+        let request = server.new_request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            query: "BA".to_owned(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<SymbolInformation>>(request_id)?;
+        #[allow(deprecated)]
+        let expected = vec![SymbolInformation {
+            name: "baz".to_owned(),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            location: Location::new(bar_uri.clone(), bar.resolved_span("baz").into()),
+            container_name: None,
+        }];
+        assert_eq!(expected, response);
+        assert_eq!(vec!["baz", "qux"], workspace_symbols(&mut server, "")?);
+
+        // Changes to open files are indexed.
+        server.change_file(foo_uri.clone(), "bazooka = 1\nb\n".to_owned())?;
+        assert_eq!(
+            vec!["baz", "bazooka"],
+            workspace_symbols(&mut server, "baz")?
+        );
+
+        let request = server.new_request::<Completion>(CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: foo_uri.clone(),
+                },
+                position: Position {
+                    line: 1,
+                    character: 1,
+                },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        });
+        let request_id = server.send_request(request)?;
+        let CompletionResponse::Array(items) =
+            server.get_response::<CompletionResponse>(request_id)?
+        else {
+            anyhow::bail!("Expected an array of completions");
+        };
+        let baz = items
+            .iter()
+            .find(|x| x.label == "baz")
+            .context("`baz` should be completed")?;
+        assert_eq!(Some("Load from :bar.star"), baz.detail.as_deref());
+        assert!(baz.additional_text_edits.is_some());
+        assert!(!items.iter().any(|x| x.label == "_private"));
+        Ok(())
+    }
 }
@@ -250,7 +250,8 @@ my_var = True
                             ret: DocReturn {
                                 docs: None,
                                 typ: Ty::any()
-                            }
+                            },
+                            issues: vec![]
                         }))),
                         param: None,
                     },
@@ -335,7 +336,8 @@ my_var = True
                             ret: DocReturn {
                                 docs: None,
                                 typ: Ty::any()
-                            }
+                            },
+                            issues: vec![]
                         }))),
                         param: None,
                     },
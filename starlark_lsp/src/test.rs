@@ -61,6 +61,7 @@ use starlark::docs::DocFunction;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
@@ -286,6 +287,22 @@ impl LspContext for TestServerContext {
         Ok(self.builtin_symbols.get(symbol).cloned())
     }
 
+    fn workspace_files(&self, workspace_roots: &[PathBuf]) -> Result<Vec<LspUrl>, String> {
+        // Only the `.star` files, so the prelude is not indexed.
+        self.file_contents
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|path| path.extension().is_some_and(|e| e == "star"))
+            .filter(|path| {
+                workspace_roots.is_empty() || workspace_roots.iter().any(|r| path.starts_with(r))
+            })
+            .map(|path| {
+                LspUrl::try_from(Url::from_file_path(path).unwrap()).map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
         DocModule {
             docs: None,
@@ -301,6 +318,10 @@ impl LspContext for TestServerContext {
                 .collect(),
         }
     }
+
+    fn get_typecheck_globals(&self, _uri: &LspUrl) -> Option<Globals> {
+        Some(Globals::standard())
+    }
 }
 
 /// A server for use in testing that provides helpers for sending requests, correlating
@@ -411,6 +432,21 @@ impl TestServer {
     /// initialization payload and makes sure that when the server is dropped, the threads
     /// are attempted to be stopped.
     pub(crate) fn new_with_settings(settings: Option<LspServerSettings>) -> anyhow::Result<Self> {
+        Self::start()?.initialize(settings)
+    }
+
+    /// Create and start a new LSP server, with files which exist before it is initialized,
+    /// and so are in the workspace it indexes.
+    pub(crate) fn new_with_workspace_files(files: &[(&Url, &str)]) -> anyhow::Result<Self> {
+        let server = Self::start()?;
+        for (uri, contents) in files {
+            server.set_file_contents(uri, (*contents).to_owned())?;
+        }
+        server.initialize(None)
+    }
+
+    /// Start the server thread, without initializing it.
+    fn start() -> anyhow::Result<Self> {
         let (server_connection, client_connection) = Connection::memory();
 
         let builtin = Self::testing_builtins(&std::env::current_dir()?)?;
@@ -449,7 +485,7 @@ impl TestServer {
             }
         });
 
-        let ret = Self {
+        Ok(Self {
             server_thread: Some(server_thread),
             client_connection,
             req_counter: 0,
@@ -461,8 +497,7 @@ impl TestServer {
             dirs,
             initialize_response: None,
             builtin_docs,
-        };
-        ret.initialize(settings)
+        })
     }
 
     /// Create and start a new LSP server. This sends the initialization messages, and makes
@@ -0,0 +1,99 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Storage for elements of collections without allocation.
+
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Storage for elements `(A, B)` of [`Vec2`](crate::vec2::Vec2)
+/// and maps built on it, stored inline, without allocation.
+///
+/// Implemented by `()`, which is the default and stores nothing, and [`Inline`].
+pub trait InlineStorage<A, B>: private::Sealed + Sized {
+    /// Number of elements stored inline.
+    const CAPACITY: usize;
+
+    /// Pointers to the arrays of `A` and `B` for reading.
+    #[doc(hidden)]
+    fn ptrs(&self) -> (NonNull<A>, NonNull<B>);
+
+    /// Pointers to the arrays of `A` and `B` for writing.
+    #[doc(hidden)]
+    fn ptrs_mut(&mut self) -> (NonNull<A>, NonNull<B>);
+}
+
+impl private::Sealed for () {}
+
+impl<A, B> InlineStorage<A, B> for () {
+    const CAPACITY: usize = 0;
+
+    #[inline]
+    fn ptrs(&self) -> (NonNull<A>, NonNull<B>) {
+        (NonNull::dangling(), NonNull::dangling())
+    }
+
+    #[inline]
+    fn ptrs_mut(&mut self) -> (NonNull<A>, NonNull<B>) {
+        (NonNull::dangling(), NonNull::dangling())
+    }
+}
+
+/// Storage for up to `N` elements `(A, B)` inline.
+///
+/// This avoids allocation for small collections,
+/// but makes the collection larger, even when empty or allocated.
+pub struct Inline<A, B, const N: usize> {
+    aaa: [MaybeUninit<A>; N],
+    bbb: [MaybeUninit<B>; N],
+}
+
+impl<A, B, const N: usize> private::Sealed for Inline<A, B, N> {}
+
+impl<A, B, const N: usize> InlineStorage<A, B> for Inline<A, B, N> {
+    const CAPACITY: usize = N;
+
+    #[inline]
+    fn ptrs(&self) -> (NonNull<A>, NonNull<B>) {
+        (
+            NonNull::from(&self.aaa).cast(),
+            NonNull::from(&self.bbb).cast(),
+        )
+    }
+
+    #[inline]
+    fn ptrs_mut(&mut self) -> (NonNull<A>, NonNull<B>) {
+        (
+            NonNull::from(&mut self.aaa).cast(),
+            NonNull::from(&mut self.bbb).cast(),
+        )
+    }
+}
+
+/// Uninitialized storage.
+#[inline]
+pub(crate) const fn uninit<A, B, I: InlineStorage<A, B>>() -> I {
+    // SAFETY: storage types only contain `MaybeUninit`, and the trait is sealed.
+    #[allow(clippy::uninit_assumed_init)]
+    unsafe {
+        MaybeUninit::uninit().assume_init()
+    }
+}
@@ -29,6 +29,7 @@
 mod hash_value;
 mod hashed;
 mod hasher;
+mod inline;
 mod iter;
 mod mix_u32;
 pub mod ordered_map;
@@ -49,3 +50,5 @@ pub use hash_value::StarlarkHashValue;
 pub use hashed::Hashed;
 pub use hasher::StarlarkHasher;
 pub use hasher::StarlarkHasherBuilder;
+pub use inline::Inline;
+pub use inline::InlineStorage;
@@ -28,6 +28,8 @@ use std::marker::PhantomData;
 use std::mem;
 
 use allocative::Allocative;
+use allocative::Key;
+use allocative::Visitor;
 use equivalent::Equivalent;
 use hashbrown::HashTable;
 #[cfg(feature = "pagable")]
@@ -41,6 +43,8 @@ use serde::Serialize;
 
 use crate::StarlarkHashValue;
 use crate::hashed::Hashed;
+use crate::inline::Inline;
+use crate::inline::InlineStorage;
 pub use crate::small_map::iter::IntoIter;
 pub use crate::small_map::iter::IntoIterHashed;
 pub use crate::small_map::iter::IntoKeys;
@@ -65,21 +69,46 @@ const NO_INDEX_THRESHOLD: usize = 32;
 #[cfg(not(rust_nightly))]
 const NO_INDEX_THRESHOLD: usize = 16;
 
+/// Storage for up to `N` entries of [`SmallMap`] inline, without allocation.
+pub type InlineEntries<K, V, const N: usize> = Inline<(K, V), StarlarkHashValue, N>;
+
 /// A map with deterministic iteration order.
 ///
 /// This map is similar to [`indexmap::IndexMap`](https://docs.rs/indexmap)
 /// with the following differences:
 /// - [Small hashes](StarlarkHashValue) are stored next to keys
 /// - Index is not created for small maps
+///
+/// With [`InlineEntries`] storage, small maps are stored inline, without allocation.
 #[repr(C)]
-#[derive(Clone, Allocative)]
-pub struct SmallMap<K, V> {
-    entries: VecMap<K, V>,
+pub struct SmallMap<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    entries: VecMap<K, V, I>,
     /// Map a key to the index in `entries`.
     /// This field is initialized when the size of the map exceeds `NO_INDEX_THRESHOLD`.
     index: Option<Box<HashTable<usize>>>,
 }
 
+impl<K: Allocative, V: Allocative, I: InlineStorage<(K, V), StarlarkHashValue>> Allocative
+    for SmallMap<K, V, I>
+{
+    fn visit<'a, 'b: 'a>(&self, visitor: &'a mut Visitor<'b>) {
+        let mut visitor = visitor.enter_self_sized::<Self>();
+        visitor.visit_field(Key::new("entries"), &self.entries);
+        visitor.visit_field(Key::new("index"), &self.index);
+        visitor.exit();
+    }
+}
+
+impl<K: Clone, V: Clone, I: InlineStorage<(K, V), StarlarkHashValue>> Clone for SmallMap<K, V, I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        SmallMap {
+            entries: self.entries.clone(),
+            index: self.index.clone(),
+        }
+    }
+}
+
 impl<K, V> Default for SmallMap<K, V> {
     #[inline]
     fn default() -> Self {
@@ -87,7 +116,7 @@ impl<K, V> Default for SmallMap<K, V> {
     }
 }
 
-impl<K: Debug, V: Debug> Debug for SmallMap<K, V> {
+impl<K: Debug, V: Debug, I: InlineStorage<(K, V), StarlarkHashValue>> Debug for SmallMap<K, V, I> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_map().entries(self.iter()).finish()
     }
@@ -97,15 +126,29 @@ impl<K, V> SmallMap<K, V> {
     /// Empty map.
     #[inline]
     pub const fn new() -> Self {
+        Self::new_inline()
+    }
+
+    /// Create an empty map with specified capacity.
+    #[inline]
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_inline(n)
+    }
+}
+
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> SmallMap<K, V, I> {
+    /// Empty map, with inline storage.
+    #[inline]
+    pub const fn new_inline() -> Self {
         Self {
-            entries: VecMap::new(),
+            entries: VecMap::new_inline(),
             index: None,
         }
     }
 
-    /// Create an empty map with specified capacity.
+    /// Create an empty map with specified capacity, with inline storage.
     #[inline]
-    pub fn with_capacity(n: usize) -> Self {
+    pub fn with_capacity_inline(n: usize) -> Self {
         if n <= NO_INDEX_THRESHOLD {
             SmallMap {
                 entries: VecMap::with_capacity(n),
@@ -140,6 +183,17 @@ impl<K, V> SmallMap<K, V> {
         }
     }
 
+    /// Move the entries to a map with different inline capacity.
+    ///
+    /// The allocation is reused if it is larger than the inline capacity.
+    #[inline]
+    pub fn into_inline<T: InlineStorage<(K, V), StarlarkHashValue>>(self) -> SmallMap<K, V, T> {
+        SmallMap {
+            entries: self.entries.into_inline(),
+            index: self.index,
+        }
+    }
+
     /// Drop the index if the map is too small, and the index is not really needed.
     ///
     /// We don't allocate index prematurely when we add entries the map,
@@ -170,7 +224,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Key owned iterator.
     #[inline]
-    pub fn into_keys(self) -> IntoKeys<K, V> {
+    pub fn into_keys(self) -> IntoKeys<K, V, I> {
         IntoKeys {
             iter: self.entries.into_iter(),
         }
@@ -178,7 +232,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Value owned iterator.
     #[inline]
-    pub fn into_values(self) -> IntoValues<K, V> {
+    pub fn into_values(self) -> IntoValues<K, V, I> {
         IntoValues {
             iter: self.entries.into_iter(),
         }
@@ -210,7 +264,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Entries with hashes iterator.
     #[inline]
-    pub fn into_iter_hashed(self) -> IntoIterHashed<K, V> {
+    pub fn into_iter_hashed(self) -> IntoIterHashed<K, V, I> {
         IntoIterHashed {
             iter: self.entries.into_iter_hashed(),
         }
@@ -237,7 +291,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Entries iterator.
     #[inline]
-    fn into_iter(self) -> IntoIter<K, V> {
+    fn into_iter(self) -> IntoIter<K, V, I> {
         IntoIter {
             iter: self.entries.into_iter(),
         }
@@ -456,7 +510,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Hasher for index resize.
     #[inline(always)]
-    fn hasher(entries: &VecMap<K, V>) -> impl Fn(&usize) -> u64 + '_ {
+    fn hasher(entries: &VecMap<K, V, I>) -> impl Fn(&usize) -> u64 + '_ {
         move |&index| {
             debug_assert!(index < entries.len());
             unsafe { entries.get_unchecked(index).0.hash().promote() }
@@ -613,7 +667,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Get the entry (occupied or not) for the key.
     #[inline]
-    pub fn entry_hashed(&mut self, key: Hashed<K>) -> Entry<'_, K, V>
+    pub fn entry_hashed(&mut self, key: Hashed<K>) -> Entry<'_, K, V, I>
     where
         K: Eq,
     {
@@ -654,7 +708,7 @@ impl<K, V> SmallMap<K, V> {
 
     /// Get the entry (occupied or not) for the key.
     #[inline]
-    pub fn entry(&mut self, key: K) -> Entry<'_, K, V>
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, I>
     where
         K: Eq + Hash,
     {
@@ -723,11 +777,11 @@ impl<K, V> SmallMap<K, V> {
         }
 
         // Rebuild index on drop to make this code panic-safe.
-        struct RebuildIndexOnDrop<'a, K, V> {
-            map: &'a mut SmallMap<K, V>,
+        struct RebuildIndexOnDrop<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> {
+            map: &'a mut SmallMap<K, V, I>,
         }
 
-        impl<K, V> Drop for RebuildIndexOnDrop<'_, K, V> {
+        impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Drop for RebuildIndexOnDrop<'_, K, V, I> {
             fn drop(&mut self) {
                 self.map.rebuild_index();
             }
@@ -773,12 +827,12 @@ impl<K, V> SmallMap<K, V> {
     where
         F: FnMut(&K, &mut V) -> bool,
     {
-        struct RebuildIndexOnDrop<'a, K, V> {
+        struct RebuildIndexOnDrop<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> {
             original_len: usize,
-            map: &'a mut SmallMap<K, V>,
+            map: &'a mut SmallMap<K, V, I>,
         }
 
-        impl<K, V> Drop for RebuildIndexOnDrop<'_, K, V> {
+        impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Drop for RebuildIndexOnDrop<'_, K, V, I> {
             fn drop(&mut self) {
                 debug_assert!(self.map.entries.len() <= self.original_len);
                 if self.map.len() < self.original_len {
@@ -807,17 +861,17 @@ pub struct OccupiedEntry<'a, K, V> {
 /// Reference to a vacant entry in the map.
 ///
 /// This can be used to insert an entry into the map.
-pub struct VacantEntry<'a, K, V> {
+pub struct VacantEntry<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
     key: Hashed<K>,
-    map: &'a mut SmallMap<K, V>,
+    map: &'a mut SmallMap<K, V, I>,
 }
 
 /// Occupied or vacant entry.
-pub enum Entry<'a, K, V> {
+pub enum Entry<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
     /// Occupied entry.
     Occupied(OccupiedEntry<'a, K, V>),
     /// No entry for given key.
-    Vacant(VacantEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V, I>),
 }
 
 impl<'a, K, V> OccupiedEntry<'a, K, V> {
@@ -857,7 +911,7 @@ impl<'a, K, V> OccupiedEntry<'a, K, V> {
     }
 }
 
-impl<'a, K, V> VacantEntry<'a, K, V>
+impl<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> VacantEntry<'a, K, V, I>
 where
     K: Eq,
 {
@@ -879,7 +933,7 @@ where
     }
 }
 
-impl<'a, K, V> Entry<'a, K, V>
+impl<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Entry<'a, K, V, I>
 where
     K: Eq,
 {
@@ -966,9 +1020,9 @@ where
     }
 }
 
-impl<K, V> IntoIterator for SmallMap<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIterator for SmallMap<K, V, I> {
     type Item = (K, V);
-    type IntoIter = IntoIter<K, V>;
+    type IntoIter = IntoIter<K, V, I>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -976,7 +1030,7 @@ impl<K, V> IntoIterator for SmallMap<K, V> {
     }
 }
 
-impl<'a, K, V> IntoIterator for &'a SmallMap<K, V> {
+impl<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIterator for &'a SmallMap<K, V, I> {
     type Item = (&'a K, &'a V);
     type IntoIter = Iter<'a, K, V>;
 
@@ -986,7 +1040,9 @@ impl<'a, K, V> IntoIterator for &'a SmallMap<K, V> {
     }
 }
 
-impl<'a, K, V> IntoIterator for &'a mut SmallMap<K, V> {
+impl<'a, K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIterator
+    for &'a mut SmallMap<K, V, I>
+{
     type Item = (&'a K, &'a mut V);
     type IntoIter = IterMut<'a, K, V>;
 
@@ -996,7 +1052,9 @@ impl<'a, K, V> IntoIterator for &'a mut SmallMap<K, V> {
     }
 }
 
-impl<K: Eq, V: PartialEq> PartialEq for SmallMap<K, V> {
+impl<K: Eq, V: PartialEq, I: InlineStorage<(K, V), StarlarkHashValue>> PartialEq
+    for SmallMap<K, V, I>
+{
     fn eq(&self, other: &Self) -> bool {
         self.len() == other.len()
             && self
@@ -1005,9 +1063,9 @@ impl<K: Eq, V: PartialEq> PartialEq for SmallMap<K, V> {
     }
 }
 
-impl<K: Eq, V: Eq> Eq for SmallMap<K, V> {}
+impl<K: Eq, V: Eq, I: InlineStorage<(K, V), StarlarkHashValue>> Eq for SmallMap<K, V, I> {}
 
-impl<K, V> Extend<(K, V)> for SmallMap<K, V>
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Extend<(K, V)> for SmallMap<K, V, I>
 where
     K: Hash + Eq,
 {
@@ -1078,7 +1136,9 @@ impl<'de, K: Pagable, V: Pagable> PagableDeserialize<'de> for SmallMap<K, V> {
     }
 }
 
-impl<K: Serialize, V: Serialize> Serialize for SmallMap<K, V> {
+impl<K: Serialize, V: Serialize, I: InlineStorage<(K, V), StarlarkHashValue>> Serialize
+    for SmallMap<K, V, I>
+{
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -1552,4 +1612,44 @@ mod tests {
         assert_eq!(*value3, 210);
         assert_eq!(map.get("key3"), Some(&210));
     }
+
+    #[test]
+    fn test_inline() {
+        let mut map = SmallMap::<String, u32, InlineEntries<String, u32, 4>>::new_inline();
+        for i in 0..4 {
+            map.insert(i.to_string(), i);
+        }
+        assert_eq!(4, map.capacity());
+        map.entry("1".to_owned()).and_modify(|v| *v = 10);
+        assert_eq!(Some(&10), map.get("1"));
+        assert_eq!(Some(3), map.shift_remove("3"));
+
+        for i in 4..100 {
+            map.insert(i.to_string(), i);
+        }
+        map.assert_invariants();
+        assert_eq!(99, map.len());
+        assert_eq!(Some(&50), map.get("50"));
+
+        map.retain(|k, _| k.len() == 1);
+        assert_eq!(
+            vec![("0", 0), ("1", 10), ("2", 2), ("4", 4)],
+            map.iter()
+                .take(4)
+                .map(|(k, v)| (k.as_str(), *v))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&9), map.get("9"),);
+        assert_eq!(map, map.clone());
+    }
+
+    #[test]
+    fn test_into_inline() {
+        let map = smallmap! { 1 => 2, 3 => 4 };
+        let map: SmallMap<_, _, InlineEntries<_, _, 4>> = map.into_inline();
+        assert_eq!(Some(&4), map.get(&3));
+        let mut map: SmallMap<_, _> = map.into_inline();
+        map.insert(5, 6);
+        assert_eq!(smallmap! { 1 => 2, 3 => 4, 5 => 6 }, map);
+    }
 }
@@ -18,6 +18,8 @@
 use dupe::Clone_;
 
 use crate::Hashed;
+use crate::InlineStorage;
+use crate::StarlarkHashValue;
 use crate::iter::def_double_ended_iter;
 use crate::iter::def_iter;
 use crate::vec_map;
@@ -143,60 +145,64 @@ impl<K, V> DoubleEndedIterator for IterMutUnchecked<'_, K, V> {
 }
 
 /// Iterator that moves hashed entries out of a [`SmallMap`](crate::small_map::SmallMap).
-pub struct IntoIterHashed<K, V> {
-    pub(crate) iter: vec_map::IntoIterHashed<K, V>,
+pub struct IntoIterHashed<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: vec_map::IntoIterHashed<K, V, I>,
 }
 
-impl<K, V> IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIterHashed<K, V, I> {
     #[inline]
     fn map((k, v): (Hashed<K>, V)) -> <Self as Iterator>::Item {
         (k, v)
     }
 }
 
-impl<K, V> Iterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoIterHashed<K, V, I> {
     type Item = (Hashed<K>, V);
 
     def_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator
+    for IntoIterHashed<K, V, I>
+{
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
     }
 }
 
-impl<K, V> DoubleEndedIterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator
+    for IntoIterHashed<K, V, I>
+{
     def_double_ended_iter!();
 }
 
 /// Iterator that moves entries out of a [`SmallMap`](crate::small_map::SmallMap).
-pub struct IntoIter<K, V> {
-    pub(crate) iter: vec_map::IntoIter<K, V>,
+pub struct IntoIter<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: vec_map::IntoIter<K, V, I>,
 }
 
-impl<K, V> IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIter<K, V, I> {
     #[inline]
     fn map((k, v): (K, V)) -> <Self as Iterator>::Item {
         (k, v)
     }
 }
 
-impl<K, V> Iterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoIter<K, V, I> {
     type Item = (K, V);
 
     def_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator for IntoIter<K, V, I> {
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
     }
 }
 
-impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator for IntoIter<K, V, I> {
     def_double_ended_iter!();
 }
 
@@ -261,60 +267,62 @@ impl<K, V> DoubleEndedIterator for Values<'_, K, V> {
 }
 
 /// Iterator that moves keys out of [`SmallMap`](crate::small_map::SmallMap).
-pub struct IntoKeys<K, V> {
-    pub(crate) iter: vec_map::IntoIter<K, V>,
+pub struct IntoKeys<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: vec_map::IntoIter<K, V, I>,
 }
 
-impl<K, V> IntoKeys<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoKeys<K, V, I> {
     #[inline]
     fn map((k, _): (K, V)) -> <Self as Iterator>::Item {
         k
     }
 }
 
-impl<K, V> Iterator for IntoKeys<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoKeys<K, V, I> {
     type Item = K;
 
     def_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoKeys<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator for IntoKeys<K, V, I> {
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
     }
 }
 
-impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator for IntoKeys<K, V, I> {
     def_double_ended_iter!();
 }
 
 /// Iterator that moves values out of [`SmallMap`](crate::small_map::SmallMap).
-pub struct IntoValues<K, V> {
-    pub(crate) iter: vec_map::IntoIter<K, V>,
+pub struct IntoValues<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: vec_map::IntoIter<K, V, I>,
 }
 
-impl<K, V> IntoValues<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoValues<K, V, I> {
     #[inline]
     fn map((_, v): (K, V)) -> <Self as Iterator>::Item {
         v
     }
 }
 
-impl<K, V> Iterator for IntoValues<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoValues<K, V, I> {
     type Item = V;
 
     def_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoValues<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator for IntoValues<K, V, I> {
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
     }
 }
 
-impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator
+    for IntoValues<K, V, I>
+{
     def_double_ended_iter!();
 }
 
@@ -46,10 +46,12 @@ pub(crate) fn slice_swap_shift<T>(slice: &mut [T], a: usize, b: usize) {
     assert!(a < b);
     assert!(b < slice.len());
 
+    // A single pointer for all accesses: `as_mut_ptr` invalidates pointers from `as_ptr`.
+    let ptr = slice.as_mut_ptr();
     unsafe {
-        let tmp = ptr::read(slice.as_mut_ptr().add(b));
-        ptr::copy(slice.as_ptr().add(a), slice.as_mut_ptr().add(a + 1), b - a);
-        ptr::write(slice.as_mut_ptr().add(a), tmp);
+        let tmp = ptr::read(ptr.add(b));
+        ptr::copy(ptr.add(a), ptr.add(a + 1), b - a);
+        ptr::write(ptr.add(a), tmp);
     }
 }
 
@@ -44,6 +44,8 @@ use serde::Serialize;
 use serde::de::SeqAccess;
 use serde::ser::SerializeSeq;
 
+use crate::inline;
+use crate::inline::InlineStorage;
 use crate::sorting::insertion::insertion_sort;
 use crate::sorting::insertion::slice_swap_shift;
 pub use crate::vec2::iter::IntoIter;
@@ -101,19 +103,27 @@ impl<A, B> Vec2Layout<A, B> {
 
 /// Array of pairs `(A, B)`, where `A` and `B` are stored separately.
 /// This reduces memory consumption when `A` and `B` have different alignments.
-pub struct Vec2<A, B> {
+///
+/// Elements are stored inline, without allocation, up to the capacity of the `I` storage,
+/// which is [`Inline`](crate::Inline) or `()`.
+pub struct Vec2<A, B, I: InlineStorage<A, B> = ()> {
     // Layout is `[padding, A, A, ..., A, B, B, ..., B]`
+    // Unused when elements are stored inline.
     bbb_ptr: NonNull<B>,
     len: usize,
+    // `I::CAPACITY` when elements are stored inline, greater when allocated.
     cap: usize,
+    inline: I,
     _marker: PhantomData<(A, B)>,
 }
 
-unsafe impl<A: Send, B: Send> Send for Vec2<A, B> {}
-unsafe impl<A: Sync, B: Sync> Sync for Vec2<A, B> {}
+unsafe impl<A: Send, B: Send, I: InlineStorage<A, B>> Send for Vec2<A, B, I> {}
+unsafe impl<A: Sync, B: Sync, I: InlineStorage<A, B>> Sync for Vec2<A, B, I> {}
 
 #[cfg(feature = "pagable")]
-impl<A: PagableSerialize, B: PagableSerialize> PagableSerialize for Vec2<A, B> {
+impl<A: PagableSerialize, B: PagableSerialize, I: InlineStorage<A, B>> PagableSerialize
+    for Vec2<A, B, I>
+{
     fn pagable_serialize(
         &self,
         serializer: &mut dyn pagable::PagableSerializer,
@@ -127,14 +137,14 @@ impl<A: PagableSerialize, B: PagableSerialize> PagableSerialize for Vec2<A, B> {
 }
 
 #[cfg(feature = "pagable")]
-impl<'de, A: PagableDeserialize<'de>, B: PagableDeserialize<'de>> PagableDeserialize<'de>
-    for Vec2<A, B>
+impl<'de, A: PagableDeserialize<'de>, B: PagableDeserialize<'de>, I: InlineStorage<A, B>>
+    PagableDeserialize<'de> for Vec2<A, B, I>
 {
     fn pagable_deserialize<D: pagable::PagableDeserializer<'de> + ?Sized>(
         deserializer: &mut D,
     ) -> pagable::Result<Self> {
         let len = usize::deserialize(deserializer.serde())?;
-        let mut vec = Vec2::with_capacity(len);
+        let mut vec = Vec2::with_capacity_inline(len);
         for _ in 0..len {
             let (a, b) = <(A, B)>::pagable_deserialize(deserializer)?;
             vec.push(a, b);
@@ -143,7 +153,7 @@ impl<'de, A: PagableDeserialize<'de>, B: PagableDeserialize<'de>> PagableDeseria
     }
 }
 
-impl<A: Serialize, B: Serialize> Serialize for Vec2<A, B> {
+impl<A: Serialize, B: Serialize, I: InlineStorage<A, B>> Serialize for Vec2<A, B, I> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -156,21 +166,23 @@ impl<A: Serialize, B: Serialize> Serialize for Vec2<A, B> {
     }
 }
 
-impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Deserialize<'de> for Vec2<A, B> {
+impl<'de, A: Deserialize<'de>, B: Deserialize<'de>, I: InlineStorage<A, B>> Deserialize<'de>
+    for Vec2<A, B, I>
+{
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct Vec2Visitor<A, B> {
-            marker: PhantomData<(A, B)>,
+        struct Vec2Visitor<A, B, I: InlineStorage<A, B>> {
+            marker: PhantomData<(A, B, I)>,
         }
 
-        impl<'de, A, B> serde::de::Visitor<'de> for Vec2Visitor<A, B>
+        impl<'de, A, B, I: InlineStorage<A, B>> serde::de::Visitor<'de> for Vec2Visitor<A, B, I>
         where
             A: Deserialize<'de>,
             B: Deserialize<'de>,
         {
-            type Value = Vec2<A, B>;
+            type Value = Vec2<A, B, I>;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 formatter.write_str("a sequence")
@@ -183,7 +195,7 @@ impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Deserialize<'de> for Vec2<A,
                 let capacity = seq
                     .size_hint()
                     .ok_or_else(|| serde::de::Error::custom("size hint missing"))?;
-                let mut values = Vec2::<A, B>::with_capacity(capacity);
+                let mut values = Vec2::<A, B, I>::with_capacity_inline(capacity);
 
                 while let Some((a, b)) = seq.next_element()? {
                     values.push(a, b);
@@ -207,15 +219,15 @@ impl<A, B> Default for Vec2<A, B> {
     }
 }
 
-impl<A: Debug, B: Debug> Debug for Vec2<A, B> {
+impl<A: Debug, B: Debug, I: InlineStorage<A, B>> Debug for Vec2<A, B, I> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_list().entries(self.iter()).finish()
     }
 }
 
-impl<A: Clone, B: Clone> Clone for Vec2<A, B> {
-    fn clone(&self) -> Vec2<A, B> {
-        let mut r = Vec2::with_capacity(self.len());
+impl<A: Clone, B: Clone, I: InlineStorage<A, B>> Clone for Vec2<A, B, I> {
+    fn clone(&self) -> Vec2<A, B, I> {
+        let mut r = Vec2::with_capacity_inline(self.len());
         for (a, b) in self.iter() {
             r.push(a.clone(), b.clone());
         }
@@ -227,32 +239,54 @@ impl<A, B> Vec2<A, B> {
     /// Empty vec.
     #[inline]
     pub const fn new() -> Vec2<A, B> {
+        Vec2::new_inline()
+    }
+
+    /// New instance with given capacity.
+    #[inline]
+    pub fn with_capacity(cap: usize) -> Vec2<A, B> {
+        Vec2::with_capacity_inline(cap)
+    }
+}
+
+impl<A, B, I: InlineStorage<A, B>> Vec2<A, B, I> {
+    /// Empty vec, with inline capacity.
+    #[inline]
+    pub const fn new_inline() -> Vec2<A, B, I> {
         Vec2 {
             // Provide a dangling pointer aligned to both A and B, so that aaa_ptr()
             // returns a properly aligned pointer
             bbb_ptr: NonNull::<(A, B)>::dangling().cast(),
             len: 0,
-            cap: 0,
+            cap: I::CAPACITY,
+            inline: inline::uninit(),
             _marker: PhantomData,
         }
     }
 
-    /// New instance with given capacity.
+    /// New instance with given capacity, allocating if it exceeds inline capacity.
     #[inline]
-    pub fn with_capacity(cap: usize) -> Vec2<A, B> {
-        if cap == 0 {
-            Vec2::new()
+    pub fn with_capacity_inline(cap: usize) -> Vec2<A, B, I> {
+        if cap <= I::CAPACITY {
+            Vec2::new_inline()
         } else {
             let bbb_ptr = unsafe { Vec2Layout::<A, B>::new(cap).alloc() };
             Vec2 {
                 bbb_ptr,
                 len: 0,
                 cap,
+                inline: inline::uninit(),
                 _marker: PhantomData,
             }
         }
     }
 
+    /// Elements are stored in a separate allocation.
+    #[inline]
+    fn is_allocated(&self) -> bool {
+        self.cap > I::CAPACITY
+    }
+
     /// Number of elements.
     #[inline]
     pub fn len(&self) -> usize {
@@ -271,14 +305,54 @@ impl<A, B> Vec2<A, B> {
         self.len == 0
     }
 
+    #[inline]
+    fn allocated_ptrs(&self) -> (NonNull<A>, NonNull<B>) {
+        let aaa_ptr =
+            unsafe { NonNull::new_unchecked(self.bbb_ptr.cast::<A>().as_ptr().sub(self.cap)) };
+        (aaa_ptr, self.bbb_ptr)
+    }
+
+    /// Pointers to `A` and `B` arrays for reading.
+    #[inline]
+    fn ptrs(&self) -> (NonNull<A>, NonNull<B>) {
+        if I::CAPACITY != 0 && !self.is_allocated() {
+            self.inline.ptrs()
+        } else {
+            self.allocated_ptrs()
+        }
+    }
+
+    /// Pointers to `A` and `B` arrays for writing.
+    ///
+    /// When elements are inline, a call invalidates pointers from the previous call,
+    /// so get both pointers with one call when both are needed.
+    #[inline]
+    fn ptrs_mut(&mut self) -> (NonNull<A>, NonNull<B>) {
+        if I::CAPACITY != 0 && !self.is_allocated() {
+            self.inline.ptrs_mut()
+        } else {
+            self.allocated_ptrs()
+        }
+    }
+
     #[inline]
     fn aaa_ptr(&self) -> NonNull<A> {
-        unsafe { NonNull::new_unchecked(self.bbb_ptr.cast::<A>().as_ptr().sub(self.cap)) }
+        self.ptrs().0
     }
 
     #[inline]
     fn bbb_ptr(&self) -> NonNull<B> {
-        self.bbb_ptr
+        self.ptrs().1
+    }
+
+    #[inline]
+    fn aaa_ptr_mut(&mut self) -> NonNull<A> {
+        self.ptrs_mut().0
+    }
+
+    #[inline]
+    fn bbb_ptr_mut(&mut self) -> NonNull<B> {
+        self.ptrs_mut().1
     }
 
     #[inline]
@@ -288,12 +362,12 @@ impl<A, B> Vec2<A, B> {
 
     #[inline]
     pub(crate) fn aaa_mut(&mut self) -> &mut [A] {
-        unsafe { slice::from_raw_parts_mut(self.aaa_ptr().as_ptr(), self.len) }
+        unsafe { slice::from_raw_parts_mut(self.aaa_ptr_mut().as_ptr(), self.len) }
     }
 
     #[inline]
     fn aaa_uninit(&mut self) -> &mut [MaybeUninit<A>] {
-        unsafe { slice::from_raw_parts_mut(self.aaa_ptr().as_ptr() as *mut _, self.cap) }
+        unsafe { slice::from_raw_parts_mut(self.aaa_ptr_mut().as_ptr() as *mut _, self.cap) }
     }
 
     #[inline]
@@ -303,12 +377,12 @@ impl<A, B> Vec2<A, B> {
 
     #[inline]
     pub(crate) fn bbb_mut(&mut self) -> &mut [B] {
-        unsafe { slice::from_raw_parts_mut(self.bbb_ptr().as_ptr(), self.len) }
+        unsafe { slice::from_raw_parts_mut(self.bbb_ptr_mut().as_ptr(), self.len) }
     }
 
     #[inline]
     fn bbb_uninit(&mut self) -> &mut [MaybeUninit<B>] {
-        unsafe { slice::from_raw_parts_mut(self.bbb_ptr().as_ptr() as *mut _, self.cap) }
+        unsafe { slice::from_raw_parts_mut(self.bbb_ptr_mut().as_ptr() as *mut _, self.cap) }
     }
 
     // This is what `Vec` does.
@@ -327,7 +401,8 @@ impl<A, B> Vec2<A, B> {
         let required_cap = self.len.checked_add(additional).expect("capacity overflow");
         let new_cap = cmp::max(required_cap, Self::MIN_NON_ZERO_CAP);
         let new_cap = cmp::max(new_cap, self.cap * 2);
-        let new = Self::with_capacity(new_cap);
+        // Always allocated, because `new_cap > self.cap >= I::CAPACITY`.
+        let new = Self::with_capacity_inline(new_cap);
         unsafe {
             ptr::copy_nonoverlapping(self.aaa_ptr().as_ptr(), new.aaa_ptr().as_ptr(), self.len);
             ptr::copy_nonoverlapping(self.bbb_ptr().as_ptr(), new.bbb_ptr().as_ptr(), self.len);
@@ -346,20 +421,13 @@ impl<A, B> Vec2<A, B> {
         }
     }
 
-    #[inline]
-    unsafe fn dealloc_impl(data: NonNull<B>, cap: usize) {
-        unsafe {
-            if cap != 0 {
-                Vec2Layout::<A, B>::new(cap).dealloc(data);
-            }
-        }
-    }
-
     /// Deallocate, but do not call destructors.
     #[inline]
     unsafe fn dealloc(&mut self) {
         unsafe {
-            Self::dealloc_impl(self.bbb_ptr, self.cap);
+            if self.is_allocated() {
+                Vec2Layout::<A, B>::new(self.cap).dealloc(self.bbb_ptr);
+            }
         }
     }
 
@@ -419,8 +487,8 @@ impl<A, B> Vec2<A, B> {
     pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> (&mut A, &mut B) {
         unsafe {
             debug_assert!(index < self.len);
-            let k_ptr = self.aaa_ptr().as_ptr();
-            let v_ptr = self.bbb_ptr().as_ptr();
+            let (k_ptr, v_ptr) = self.ptrs_mut();
+            let (k_ptr, v_ptr) = (k_ptr.as_ptr(), v_ptr.as_ptr());
             (&mut *k_ptr.add(index), &mut *v_ptr.add(index))
         }
     }
@@ -441,16 +509,10 @@ impl<A, B> Vec2<A, B> {
         assert!(index < self.len);
         unsafe {
             let (a, b) = self.read(index);
-            ptr::copy(
-                self.aaa_ptr().as_ptr().add(index + 1),
-                self.aaa_ptr().as_ptr().add(index),
-                self.len - index - 1,
-            );
-            ptr::copy(
-                self.bbb_ptr().as_ptr().add(index + 1),
-                self.bbb_ptr().as_ptr().add(index),
-                self.len - index - 1,
-            );
+            let (aaa, bbb) = self.ptrs_mut();
+            let (aaa, bbb) = (aaa.as_ptr(), bbb.as_ptr());
+            ptr::copy(aaa.add(index + 1), aaa.add(index), self.len - index - 1);
+            ptr::copy(bbb.add(index + 1), bbb.add(index), self.len - index - 1);
             self.len -= 1;
             (a, b)
         }
@@ -488,14 +550,14 @@ impl<A, B> Vec2<A, B> {
 
     /// If capacity exceeds length, shrink capacity to length.
     pub fn shrink_to_fit(&mut self) {
-        if self.len() < self.capacity() {
-            let mut new_vec = Vec2::with_capacity(self.len());
-            for (a, b) in mem::take(self).into_iter() {
+        if self.len() < self.capacity() && self.is_allocated() {
+            let mut new_vec = Vec2::with_capacity_inline(self.len());
+            for (a, b) in mem::replace(self, Vec2::new_inline()).into_iter() {
                 new_vec.push(a, b);
             }
             *self = new_vec;
         } else {
-            debug_assert!(self.len() == self.capacity());
+            debug_assert!(self.len() == self.capacity() || !self.is_allocated());
         }
     }
 
@@ -507,8 +569,9 @@ impl<A, B> Vec2<A, B> {
             return;
         };
         unsafe {
-            let drop_a = ptr::slice_from_raw_parts_mut(self.aaa_ptr().as_ptr().add(len), drop_len);
-            let drop_b = ptr::slice_from_raw_parts_mut(self.bbb_ptr().as_ptr().add(len), drop_len);
+            let (aaa, bbb) = self.ptrs_mut();
+            let drop_a = ptr::slice_from_raw_parts_mut(aaa.as_ptr().add(len), drop_len);
+            let drop_b = ptr::slice_from_raw_parts_mut(bbb.as_ptr().add(len), drop_len);
             self.len = len;
 
             struct DropInPlace<X>(*mut [X]);
@@ -532,32 +595,27 @@ impl<A, B> Vec2<A, B> {
     where
         F: FnMut(&mut A, &mut B) -> bool,
     {
-        struct Retain<'a, A, B> {
+        struct Retain<'a, A, B, I: InlineStorage<A, B>> {
             /// Data in `vec` is valid in ranges `[0, written)` and `[next, vec.len)`.
-            vec: &'a mut Vec2<A, B>,
+            vec: &'a mut Vec2<A, B, I>,
             /// Processed and retained element count.
             written: usize,
             /// Next element to check.
             next: usize,
         }
 
-        impl<A, B> Drop for Retain<'_, A, B> {
+        impl<A, B, I: InlineStorage<A, B>> Drop for Retain<'_, A, B, I> {
             fn drop(&mut self) {
                 debug_assert!(self.written <= self.next);
                 debug_assert!(self.next <= self.vec.len);
                 unsafe {
                     // Copy remaining elements to the beginning.
                     // Copy occurs only if `f` or `{A,B}::drop` panics.
-                    ptr::copy(
-                        self.vec.aaa_ptr().as_ptr().add(self.next),
-                        self.vec.aaa_ptr().as_ptr().add(self.written),
-                        self.vec.len - self.next,
-                    );
-                    ptr::copy(
-                        self.vec.bbb_ptr().as_ptr().add(self.next),
-                        self.vec.bbb_ptr().as_ptr().add(self.written),
-                        self.vec.len - self.next,
-                    );
+                    let (aaa, bbb) = self.vec.ptrs_mut();
+                    let (aaa, bbb) = (aaa.as_ptr(), bbb.as_ptr());
+                    let count = self.vec.len - self.next;
+                    ptr::copy(aaa.add(self.next), aaa.add(self.written), count);
+                    ptr::copy(bbb.add(self.next), bbb.add(self.written), count);
 
                     // Set correct length.
                     self.vec.len = self.written + self.vec.len - self.next;
@@ -579,8 +637,8 @@ impl<A, B> Vec2<A, B> {
                 let b = ptr::read(b);
                 retain.next += 1;
                 if retain_elem {
-                    ptr::write(retain.vec.aaa_ptr().as_ptr().add(retain.written), a);
-                    ptr::write(retain.vec.bbb_ptr().as_ptr().add(retain.written), b);
+                    ptr::write(retain.vec.aaa_ptr_mut().as_ptr().add(retain.written), a);
+                    ptr::write(retain.vec.bbb_ptr_mut().as_ptr().add(retain.written), b);
                     retain.written += 1;
                 } else {
                     drop((a, b));
@@ -616,6 +674,31 @@ impl<A, B> Vec2<A, B> {
         );
     }
 
+    /// Move the elements to a vec with different inline capacity.
+    ///
+    /// The allocation is reused if it is larger than the inline capacity.
+    pub fn into_inline<T: InlineStorage<A, B>>(mut self) -> Vec2<A, B, T> {
+        if self.is_allocated() && self.cap > T::CAPACITY {
+            let vec = Vec2 {
+                bbb_ptr: self.bbb_ptr,
+                len: self.len,
+                cap: self.cap,
+                inline: inline::uninit(),
+                _marker: PhantomData,
+            };
+            // Now owned by `vec`.
+            self.len = 0;
+            self.cap = I::CAPACITY;
+            vec
+        } else {
+            let mut vec = Vec2::with_capacity_inline(self.len);
+            for (a, b) in self {
+                vec.push(a, b);
+            }
+            vec
+        }
+    }
+
     /// Sort the elements using given comparator.
     pub fn sort_by<F>(&mut self, mut compare: F)
     where
@@ -630,7 +713,7 @@ impl<A, B> Vec2<A, B> {
 
         // TODO: sort without allocation.
         // TODO: drain.
-        let mut entries: Vec<(A, B)> = mem::take(self).into_iter().collect();
+        let mut entries: Vec<(A, B)> = mem::replace(self, Vec2::new_inline()).into_iter().collect();
         entries.sort_by(|(xa, xb), (ya, yb)| compare((xa, xb), (ya, yb)));
         for (a, b) in entries {
             self.push(a, b);
@@ -638,7 +721,7 @@ impl<A, B> Vec2<A, B> {
     }
 }
 
-impl<A, B> Drop for Vec2<A, B> {
+impl<A, B, I: InlineStorage<A, B>> Drop for Vec2<A, B, I> {
     #[inline]
     fn drop(&mut self) {
         unsafe {
@@ -650,7 +733,7 @@ impl<A, B> Drop for Vec2<A, B> {
     }
 }
 
-impl<'s, A, B> IntoIterator for &'s Vec2<A, B> {
+impl<'s, A, B, I: InlineStorage<A, B>> IntoIterator for &'s Vec2<A, B, I> {
     type Item = (&'s A, &'s B);
     type IntoIter = Iter<'s, A, B>;
 
@@ -660,34 +743,31 @@ impl<'s, A, B> IntoIterator for &'s Vec2<A, B> {
     }
 }
 
-impl<A, B> IntoIterator for Vec2<A, B> {
+impl<A, B, I: InlineStorage<A, B>> IntoIterator for Vec2<A, B, I> {
     type Item = (A, B);
-    type IntoIter = IntoIter<A, B>;
+    type IntoIter = IntoIter<A, B, I>;
 
     #[inline]
-    fn into_iter(self) -> IntoIter<A, B> {
-        let iter = IntoIter {
-            aaa_begin: self.aaa_ptr(),
-            bbb_begin: self.bbb_ptr(),
-            bbb_end: unsafe { NonNull::new_unchecked(self.bbb_ptr().as_ptr().add(self.len)) },
-            bbb_ptr: self.bbb_ptr,
-            cap: self.cap,
-        };
-        mem::forget(self);
-        iter
+    fn into_iter(mut self) -> IntoIter<A, B, I> {
+        let end = mem::replace(&mut self.len, 0);
+        IntoIter {
+            vec: self,
+            begin: 0,
+            end,
+        }
     }
 }
 
-impl<A: PartialEq, B: PartialEq> PartialEq for Vec2<A, B> {
+impl<A: PartialEq, B: PartialEq, I: InlineStorage<A, B>> PartialEq for Vec2<A, B, I> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         self.len == other.len && self.iter().eq(other.iter())
     }
 }
 
-impl<A: Eq, B: Eq> Eq for Vec2<A, B> {}
+impl<A: Eq, B: Eq, I: InlineStorage<A, B>> Eq for Vec2<A, B, I> {}
 
-impl<A: Hash, B: Hash> Hash for Vec2<A, B> {
+impl<A: Hash, B: Hash, I: InlineStorage<A, B>> Hash for Vec2<A, B, I> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.len.hash(state);
         for (a, b) in self.iter() {
@@ -697,10 +777,15 @@ impl<A: Hash, B: Hash> Hash for Vec2<A, B> {
     }
 }
 
-impl<A: Allocative, B: Allocative> Allocative for Vec2<A, B> {
+impl<A: Allocative, B: Allocative, I: InlineStorage<A, B>> Allocative for Vec2<A, B, I> {
     fn visit<'a, 'b: 'a>(&self, visitor: &'a mut Visitor<'b>) {
         let mut visitor = visitor.enter_self_sized::<Self>();
-        if self.cap != 0 {
+        if !self.is_allocated() {
+            for (a, b) in self {
+                a.visit(&mut visitor);
+                b.visit(&mut visitor);
+            }
+        } else {
             let mut visitor =
                 visitor.enter_unique(allocative::Key::new("ptr"), mem::size_of::<*const ()>());
             {
@@ -728,6 +813,7 @@ mod tests {
 
     use dupe::Dupe;
 
+    use crate::Inline;
     use crate::vec2::Vec2;
     use crate::vec2::Vec2Layout;
 
@@ -871,6 +957,75 @@ mod tests {
         assert_eq!(Some((&3, &4)), v.last());
     }
 
+    #[test]
+    fn test_inline() {
+        let mut v: Vec2<String, u32, Inline<String, u32, 2>> = Vec2::new_inline();
+        v.push("a".to_owned(), 1);
+        v.push("b".to_owned(), 2);
+        assert!(!v.is_allocated());
+        assert_eq!(2, v.capacity());
+        v.push("c".to_owned(), 3);
+        assert!(v.is_allocated());
+        assert_eq!(("a".to_owned(), 1), v.remove(0));
+        v.shrink_to_fit();
+        assert!(!v.is_allocated());
+        *v.get_mut(1).unwrap().1 = 4;
+        assert_eq!(
+            vec![(&"b".to_owned(), &2), (&"c".to_owned(), &4)],
+            v.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(v, v.clone());
+    }
+
+    #[test]
+    fn test_inline_moved() {
+        // Moving a vec moves inline elements, so they must not be referenced by pointers.
+        let mut vs = Vec::new();
+        for i in 0..20 {
+            let mut v: Vec2<String, u32, Inline<String, u32, 4>> = Vec2::new_inline();
+            for j in 0..i % 6 {
+                v.push(j.to_string(), j);
+            }
+            vs.push(v);
+        }
+        for (i, v) in vs.into_iter().enumerate() {
+            let expected = (0..i as u32 % 6).map(|j| (j.to_string(), j));
+            assert!(v.into_iter().eq(expected));
+        }
+    }
+
+    #[test]
+    fn test_inline_into_iter_drop() {
+        let r = Rc::new(1);
+        type V = Vec2<Rc<i32>, Rc<i32>, Inline<Rc<i32>, Rc<i32>, 4>>;
+        let mut v = V::new_inline();
+        for _ in 0..3 {
+            v.push(r.dupe(), r.dupe());
+        }
+        let mut iter = v.into_iter();
+        assert_eq!(Some(1), iter.next_back().map(|(a, _)| *a));
+        assert_eq!(2, iter.len());
+        iter.next();
+        assert_eq!(3, Rc::strong_count(&r));
+        drop(iter);
+        assert_eq!(1, Rc::strong_count(&r));
+    }
+
+    #[test]
+    fn test_into_inline() {
+        let mut v = Vec2::new();
+        for i in 0..3 {
+            v.push(i, i * 2);
+        }
+        let v: Vec2<_, _, Inline<_, _, 4>> = v.into_inline();
+        assert!(!v.is_allocated());
+        let mut v: Vec2<_, _, Inline<_, _, 2>> = v.into_inline();
+        assert!(v.is_allocated());
+        v.retain(|a, _| *a != 1);
+        let v: Vec2<_, _> = v.into_inline();
+        assert_eq!(vec![(&0, &0), (&2, &4)], v.iter().collect::<Vec<_>>());
+    }
+
     #[repr(align(16))]
     struct Align16;
 
@@ -22,6 +22,7 @@ use std::slice;
 
 use dupe::Clone_;
 
+use crate::inline::InlineStorage;
 use crate::vec2::Vec2;
 
 /// Iterator over [`Vec2`] elements.
@@ -69,36 +70,27 @@ impl<A, B> DoubleEndedIterator for Iter<'_, A, B> {
 }
 
 /// Iterator which consumes the [`Vec2`].
-pub struct IntoIter<A, B> {
-    /// Pointer to the next `A`. Updated as we iterate.
-    pub(crate) aaa_begin: NonNull<A>,
-    /// Pointer to the next `B`. Updated as we iterate.
-    pub(crate) bbb_begin: NonNull<B>,
-    /// Pointer to the end of the `bbb`. Updated as we iterate.
-    pub(crate) bbb_end: NonNull<B>,
-    /// The layout of `Vec2` is `[padding, aaa, bbb]`.
-    /// This field is a pointer to `bbb`. Used for `Drop`.
-    pub(crate) bbb_ptr: NonNull<B>,
-    /// `Vec2` capacity. Used for `Drop`.
-    pub(crate) cap: usize,
+pub struct IntoIter<A, B, I: InlineStorage<A, B> = ()> {
+    /// Elements are owned by the iterator, so length of `vec` is zero.
+    pub(crate) vec: Vec2<A, B, I>,
+    /// Index of the next element. Updated as we iterate.
+    pub(crate) begin: usize,
+    /// Index past the last element. Updated as we iterate.
+    pub(crate) end: usize,
 }
 
-unsafe impl<A: Send, B: Send> Send for IntoIter<A, B> {}
-unsafe impl<A: Sync, B: Sync> Sync for IntoIter<A, B> {}
-
-impl<A, B> Iterator for IntoIter<A, B> {
+impl<A, B, I: InlineStorage<A, B>> Iterator for IntoIter<A, B, I> {
     type Item = (A, B);
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.bbb_begin == self.bbb_end {
+        if self.begin == self.end {
             None
         } else {
             unsafe {
-                let a = ptr::read(self.aaa_begin.as_ref());
-                let b = ptr::read(self.bbb_begin.as_ref());
-                self.aaa_begin = NonNull::new_unchecked(self.aaa_begin.as_ptr().add(1));
-                self.bbb_begin = NonNull::new_unchecked(self.bbb_begin.as_ptr().add(1));
+                let a = ptr::read(self.vec.aaa_ptr().as_ptr().add(self.begin));
+                let b = ptr::read(self.vec.bbb_ptr().as_ptr().add(self.begin));
+                self.begin += 1;
                 Some((a, b))
             }
         }
@@ -111,47 +103,40 @@ impl<A, B> Iterator for IntoIter<A, B> {
     }
 }
 
-impl<A, B> Drop for IntoIter<A, B> {
+impl<A, B, I: InlineStorage<A, B>> Drop for IntoIter<A, B, I> {
     fn drop(&mut self) {
         unsafe {
             let rem = self.len();
-            ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(
-                self.aaa_begin.as_ptr(),
+            let (aaa, bbb) = self.vec.ptrs_mut();
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                aaa.as_ptr().add(self.begin),
                 rem,
             ));
-            ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(
-                self.bbb_begin.as_ptr(),
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                bbb.as_ptr().add(self.begin),
                 rem,
             ));
-            Vec2::<A, B>::dealloc_impl(self.bbb_ptr, self.cap);
+            // `vec` is deallocated when dropped.
         }
     }
 }
 
-impl<A, B> ExactSizeIterator for IntoIter<A, B> {
+impl<A, B, I: InlineStorage<A, B>> ExactSizeIterator for IntoIter<A, B, I> {
     #[inline]
     fn len(&self) -> usize {
-        unsafe { self.bbb_end.as_ptr().offset_from(self.bbb_begin.as_ptr()) as usize }
+        self.end - self.begin
     }
 }
 
-impl<A, B> DoubleEndedIterator for IntoIter<A, B> {
+impl<A, B, I: InlineStorage<A, B>> DoubleEndedIterator for IntoIter<A, B, I> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        if self.bbb_begin == self.bbb_end {
+        if self.begin == self.end {
             None
         } else {
             unsafe {
-                self.bbb_end = NonNull::new_unchecked(self.bbb_end.as_ptr().sub(1));
-                let new_len = self.len();
-
-                debug_assert!(ptr::eq(
-                    self.bbb_begin.as_ptr().add(new_len),
-                    self.bbb_end.as_ptr()
-                ));
-
-                let a = ptr::read(self.aaa_begin.as_ptr().add(new_len));
-                let b = ptr::read(self.bbb_end.as_ptr());
-
+                self.end -= 1;
+                let a = ptr::read(self.vec.aaa_ptr().as_ptr().add(self.end));
+                let b = ptr::read(self.vec.bbb_ptr().as_ptr().add(self.end));
                 Some((a, b))
             }
         }
@@ -19,17 +19,26 @@ mod hint;
 mod iter;
 mod simd;
 
+use std::fmt;
+use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::mem;
 
 use allocative::Allocative;
+use allocative::Key;
+use allocative::Visitor;
 use equivalent::Equivalent;
 #[cfg(feature = "pagable")]
 use pagable::Pagable;
+#[cfg(feature = "pagable")]
+use pagable::PagableDeserialize;
+#[cfg(feature = "pagable")]
+use pagable::PagableSerialize;
 
 use crate::hash_value::StarlarkHashValue;
 use crate::hashed::Hashed;
+use crate::inline::InlineStorage;
 use crate::vec_map::hint::likely;
 pub(crate) use crate::vec_map::iter::IntoIter;
 pub(crate) use crate::vec_map::iter::IntoIterHashed;
@@ -43,31 +52,90 @@ pub(crate) use crate::vec_map::iter::ValuesMut;
 use crate::vec_map::simd::find_hash_in_array;
 pub(crate) use crate::vec2::Vec2;
 
-#[derive(Debug, Clone, Allocative)]
-#[cfg_attr(feature = "pagable", derive(Pagable))]
-pub(crate) struct VecMap<K, V> {
-    buckets: Vec2<(K, V), StarlarkHashValue>,
+pub(crate) struct VecMap<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    buckets: Vec2<(K, V), StarlarkHashValue, I>,
+}
+
+impl<K: Debug, V: Debug, I: InlineStorage<(K, V), StarlarkHashValue>> Debug for VecMap<K, V, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VecMap")
+            .field("buckets", &self.buckets)
+            .finish()
+    }
+}
+
+impl<K: Clone, V: Clone, I: InlineStorage<(K, V), StarlarkHashValue>> Clone for VecMap<K, V, I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        VecMap {
+            buckets: self.buckets.clone(),
+        }
+    }
+}
+
+impl<K: Allocative, V: Allocative, I: InlineStorage<(K, V), StarlarkHashValue>> Allocative
+    for VecMap<K, V, I>
+{
+    fn visit<'a, 'b: 'a>(&self, visitor: &'a mut Visitor<'b>) {
+        let mut visitor = visitor.enter_self_sized::<Self>();
+        visitor.visit_field(Key::new("buckets"), &self.buckets);
+        visitor.exit();
+    }
+}
+
+#[cfg(feature = "pagable")]
+impl<K: Pagable, V: Pagable, I: InlineStorage<(K, V), StarlarkHashValue>> PagableSerialize
+    for VecMap<K, V, I>
+{
+    fn pagable_serialize(
+        &self,
+        serializer: &mut dyn pagable::PagableSerializer,
+    ) -> pagable::__internal::anyhow::Result<()> {
+        self.buckets.pagable_serialize(serializer)
+    }
+}
+
+#[cfg(feature = "pagable")]
+impl<'de, K: Pagable, V: Pagable, I: InlineStorage<(K, V), StarlarkHashValue>>
+    PagableDeserialize<'de> for VecMap<K, V, I>
+{
+    fn pagable_deserialize<D: pagable::PagableDeserializer<'de> + ?Sized>(
+        deserializer: &mut D,
+    ) -> pagable::Result<Self> {
+        Ok(VecMap {
+            buckets: Vec2::pagable_deserialize(deserializer)?,
+        })
+    }
 }
 
-impl<K, V> Default for VecMap<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Default for VecMap<K, V, I> {
     #[inline]
     fn default() -> Self {
-        Self::new()
+        Self::new_inline()
     }
 }
 
-impl<K, V> VecMap<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> VecMap<K, V, I> {
     #[inline]
-    pub(crate) const fn new() -> Self {
+    pub(crate) const fn new_inline() -> Self {
         VecMap {
-            buckets: Vec2::new(),
+            buckets: Vec2::new_inline(),
         }
     }
 
     #[inline]
     pub(crate) fn with_capacity(n: usize) -> Self {
         VecMap {
-            buckets: Vec2::with_capacity(n),
+            buckets: Vec2::with_capacity_inline(n),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_inline<T: InlineStorage<(K, V), StarlarkHashValue>>(
+        self,
+    ) -> VecMap<K, V, T> {
+        VecMap {
+            buckets: self.buckets.into_inline(),
         }
     }
 
@@ -196,7 +264,7 @@ impl<K, V> VecMap<K, V> {
     }
 
     #[inline]
-    pub(crate) fn into_iter(self) -> IntoIter<K, V> {
+    pub(crate) fn into_iter(self) -> IntoIter<K, V, I> {
         IntoIter {
             iter: self.into_iter_hashed(),
         }
@@ -218,7 +286,7 @@ impl<K, V> VecMap<K, V> {
     }
 
     #[inline]
-    pub(crate) fn into_iter_hashed(self) -> IntoIterHashed<K, V> {
+    pub(crate) fn into_iter_hashed(self) -> IntoIterHashed<K, V, I> {
         // See the comments on VMIntoIterHash for why this one looks different
         IntoIterHashed {
             iter: self.buckets.into_iter(),
@@ -20,6 +20,7 @@ use std::slice;
 use dupe::Clone_;
 
 use crate::Hashed;
+use crate::InlineStorage;
 use crate::StarlarkHashValue;
 use crate::iter::def_double_ended_iter;
 use crate::iter::def_iter;
@@ -220,56 +221,60 @@ impl<'a, K: 'a, V: 'a> ExactSizeIterator for IterMutUnchecked<'a, K, V> {
     }
 }
 
-pub(crate) struct IntoIterHashed<K, V> {
-    pub(crate) iter: vec2::IntoIter<(K, V), StarlarkHashValue>,
+pub(crate) struct IntoIterHashed<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: vec2::IntoIter<(K, V), StarlarkHashValue, I>,
 }
 
-impl<K, V> IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIterHashed<K, V, I> {
     #[inline]
     fn map(((k, v), hash): ((K, V), StarlarkHashValue)) -> (Hashed<K>, V) {
         (Hashed::new_unchecked(hash, k), v)
     }
 }
 
-impl<K, V> Iterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoIterHashed<K, V, I> {
     type Item = (Hashed<K>, V);
 
     def_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator
+    for IntoIterHashed<K, V, I>
+{
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
     }
 }
 
-impl<K, V> DoubleEndedIterator for IntoIterHashed<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator
+    for IntoIterHashed<K, V, I>
+{
     def_double_ended_iter!();
 }
 
-pub(crate) struct IntoIter<K, V> {
-    pub(crate) iter: IntoIterHashed<K, V>,
+pub(crate) struct IntoIter<K, V, I: InlineStorage<(K, V), StarlarkHashValue> = ()> {
+    pub(crate) iter: IntoIterHashed<K, V, I>,
 }
 
-impl<K, V> IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> IntoIter<K, V, I> {
     #[inline]
     fn map((k, v): (Hashed<K>, V)) -> (K, V) {
         (k.into_key(), v)
     }
 }
 
-impl<K, V> Iterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> Iterator for IntoIter<K, V, I> {
     type Item = (K, V);
 
     def_iter!();
 }
 
-impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> DoubleEndedIterator for IntoIter<K, V, I> {
     def_double_ended_iter!();
 }
 
-impl<K, V> ExactSizeIterator for IntoIter<K, V> {
+impl<K, V, I: InlineStorage<(K, V), StarlarkHashValue>> ExactSizeIterator for IntoIter<K, V, I> {
     #[inline]
     fn len(&self) -> usize {
         self.iter.len()
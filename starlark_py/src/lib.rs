@@ -0,0 +1,266 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Python bindings, as the `starlark_py` module:
+//!
+//! ```python
+//! import starlark_py
+//!
+//! starlark_py.parse("BUILD", source)  # Raises StarlarkError on syntax errors.
+//! module = starlark_py.eval("config.star", source, {"env": "prod"})
+//! module["targets"]  # Starlark data converted to Python.
+//! ```
+//!
+//! Data values (`None`, bools, ints, floats, strings, lists, tuples and dicts) convert
+//! both ways. Other Starlark values, like functions, can't be read from Python.
+
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+use pyo3::types::PyBool;
+use pyo3::types::PyDict;
+use pyo3::types::PyFloat;
+use pyo3::types::PyInt;
+use pyo3::types::PyList;
+use pyo3::types::PyString;
+use pyo3::types::PyTuple;
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::values::Heap;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use starlark::values::dict::AllocDict;
+use starlark::values::dict::DictRef;
+use starlark::values::float::StarlarkFloat;
+use starlark::values::list::AllocList;
+use starlark::values::list::ListRef;
+use starlark::values::tuple::AllocTuple;
+use starlark::values::tuple::TupleRef;
+
+create_exception!(starlark_py, StarlarkError, PyException);
+
+fn starlark_error(e: impl std::fmt::Display) -> PyErr {
+    StarlarkError::new_err(e.to_string())
+}
+
+/// Data-only extensions, suitable for configuration.
+const EXTENSIONS: &[LibraryExtension] = &[
+    LibraryExtension::StructType,
+    LibraryExtension::RecordType,
+    LibraryExtension::EnumType,
+    LibraryExtension::Map,
+    LibraryExtension::Filter,
+    LibraryExtension::Partial,
+    LibraryExtension::Json,
+    LibraryExtension::Typing,
+];
+
+fn to_python(py: Python<'_>, value: Value) -> PyResult<PyObject> {
+    if value.is_none() {
+        return Ok(py.None());
+    }
+    if let Some(x) = value.unpack_bool() {
+        return Ok(x.into_py(py));
+    }
+    if value.get_type() == "int" {
+        return match i64::unpack_value(value) {
+            Ok(Some(x)) => Ok(x.into_py(py)),
+            // Too big for `i64`, so go through the decimal representation.
+            _ => Ok(py
+                .get_type_bound::<PyInt>()
+                .call1((value.to_str(),))?
+                .unbind()),
+        };
+    }
+    if let Some(x) = value.downcast_ref::<StarlarkFloat>() {
+        return Ok(x.0.into_py(py));
+    }
+    if let Some(x) = value.unpack_str() {
+        return Ok(x.into_py(py));
+    }
+    if let Some(xs) = ListRef::from_value(value) {
+        let xs = xs
+            .iter()
+            .map(|x| to_python(py, x))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new_bound(py, xs).into_py(py));
+    }
+    if let Some(xs) = TupleRef::from_value(value) {
+        let xs = xs
+            .iter()
+            .map(|x| to_python(py, x))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyTuple::new_bound(py, xs).into_py(py));
+    }
+    if let Some(xs) = DictRef::from_value(value) {
+        let res = PyDict::new_bound(py);
+        for (k, v) in xs.iter() {
+            res.set_item(to_python(py, k)?, to_python(py, v)?)?;
+        }
+        return Ok(res.into_py(py));
+    }
+    Err(starlark_error(format_args!(
+        "Can't convert a value of type `{}` to Python",
+        value.get_type()
+    )))
+}
+
+fn from_python<'v>(heap: Heap<'v>, obj: &Bound<'_, PyAny>) -> PyResult<Value<'v>> {
+    if obj.is_none() {
+        return Ok(Value::new_none());
+    }
+    // Before ints, since `bool` is a subclass of `int`.
+    if let Ok(x) = obj.downcast::<PyBool>() {
+        return Ok(Value::new_bool(x.is_true()));
+    }
+    if let Ok(x) = obj.downcast::<PyInt>() {
+        return Ok(match x.extract::<i64>() {
+            Ok(x) => heap.alloc(x),
+            Err(_) => heap.alloc(BigInt::from_str(&x.str()?.to_cow()?).map_err(starlark_error)?),
+        });
+    }
+    if let Ok(x) = obj.downcast::<PyFloat>() {
+        return Ok(heap.alloc(x.value()));
+    }
+    if let Ok(x) = obj.downcast::<PyString>() {
+        return Ok(heap.alloc(x.to_cow()?.as_ref()));
+    }
+    if let Ok(xs) = obj.downcast::<PyList>() {
+        let xs = xs
+            .iter()
+            .map(|x| from_python(heap, &x))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(heap.alloc(AllocList(xs)));
+    }
+    if let Ok(xs) = obj.downcast::<PyTuple>() {
+        let xs = xs
+            .iter()
+            .map(|x| from_python(heap, &x))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(heap.alloc(AllocTuple(xs)));
+    }
+    if let Ok(xs) = obj.downcast::<PyDict>() {
+        let xs = xs
+            .iter()
+            .map(|(k, v)| Ok((from_python(heap, &k)?, from_python(heap, &v)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        // Python dict keys are hashable, so are their Starlark conversions.
+        return Ok(heap.alloc(AllocDict(xs)));
+    }
+    Err(starlark_error(format_args!(
+        "Can't convert a value of type `{}` to Starlark",
+        obj.get_type().name()?
+    )))
+}
+
+/// An evaluated, frozen, Starlark module. Index it by global name to read values.
+#[pyclass(frozen, name = "Module", module = "starlark_py")]
+struct PyStarlarkModule {
+    module: FrozenModule,
+}
+
+#[pymethods]
+impl PyStarlarkModule {
+    fn __getitem__(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        match self.module.get(name) {
+            Ok(value) => to_python(py, value.value()),
+            Err(_) => Err(PyKeyError::new_err(name.to_owned())),
+        }
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.module.get(name).is_ok()
+    }
+
+    /// The names of the public globals.
+    fn keys(&self) -> Vec<String> {
+        self.module.names().map(|x| x.as_str().to_owned()).collect()
+    }
+
+    /// All public globals converted to Python, skipping those which can't be.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let res = PyDict::new_bound(py);
+        for name in self.module.names() {
+            if let Ok(value) = self.module.get(name.as_str()) {
+                if let Ok(value) = to_python(py, value.value()) {
+                    res.set_item(name.as_str(), value)?;
+                }
+            }
+        }
+        Ok(res.into_py(py))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<starlark_py.Module with {} globals>", self.keys().len())
+    }
+}
+
+/// Parse `source`, raising `StarlarkError` if it is not valid Starlark.
+#[pyfunction]
+fn parse(filename: &str, source: &str) -> PyResult<()> {
+    AstModule::parse(filename, source.to_owned(), &Dialect::Extended).map_err(starlark_error)?;
+    Ok(())
+}
+
+/// Evaluate `source`, with `inputs` available as globals, returning the module.
+#[pyfunction]
+#[pyo3(signature = (filename, source, inputs = None))]
+fn eval(
+    filename: &str,
+    source: &str,
+    inputs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyStarlarkModule> {
+    let ast = AstModule::parse(filename, source.to_owned(), &Dialect::Extended)
+        .map_err(starlark_error)?;
+    let globals = Globals::extended_by(EXTENSIONS);
+    let module = Module::with_temp_heap(|module| -> PyResult<FrozenModule> {
+        if let Some(inputs) = inputs {
+            for (k, v) in inputs.iter() {
+                let k = k.downcast::<PyString>()?;
+                let v = from_python(module.heap(), &v)?;
+                module.set(&k.to_cow()?, v);
+            }
+        }
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.eval_module(ast, &globals).map_err(starlark_error)?;
+        }
+        module
+            .freeze()
+            .map_err(|e| starlark_error(starlark::Error::from(e)))
+    })?;
+    Ok(PyStarlarkModule { module })
+}
+
+#[pymodule]
+fn starlark_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("StarlarkError", m.py().get_type_bound::<StarlarkError>())?;
+    m.add_class::<PyStarlarkModule>()?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(eval, m)?)?;
+    Ok(())
+}
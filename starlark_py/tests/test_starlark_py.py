@@ -0,0 +1,52 @@
+# Copyright 2018 The Starlark in Rust Authors.
+# Copyright (c) Facebook, Inc. and its affiliates.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     https://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+import unittest
+
+import starlark_py
+
+
+class StarlarkPyTest(unittest.TestCase):
+    def test_eval_round_trip(self):
+        inputs = {
+            "names": ["a", "b"],
+            "big": 2**80,
+            "conf": {"debug": True, "ratio": 0.5, "tags": ("x", None)},
+        }
+        module = starlark_py.eval(
+            "config.star",
+            "targets = [name + '_bin' for name in names]\n"
+            "bigger = big + 1\n"
+            "same = conf\n",
+            inputs,
+        )
+        self.assertEqual(["a_bin", "b_bin"], module["targets"])
+        self.assertEqual(2**80 + 1, module["bigger"])
+        self.assertEqual(inputs["conf"], module["same"])
+        self.assertIn("targets", module)
+        self.assertEqual(
+            {"targets": ["a_bin", "b_bin"], "bigger": 2**80 + 1, "same": inputs["conf"]},
+            {k: v for k, v in module.to_dict().items() if k not in inputs},
+        )
+
+    def test_errors(self):
+        with self.assertRaises(starlark_py.StarlarkError):
+            starlark_py.parse("BUILD", "x = )")
+        with self.assertRaises(starlark_py.StarlarkError):
+            starlark_py.eval("config.star", "x = 1 // 0")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,467 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A gRPC service for parsing, typechecking, evaluating and documenting Starlark, as
+//! described in `proto/starlark.proto`.
+//!
+//! Every request is handled on its own blocking thread, with a fresh module, and with
+//! heap, step and time limits clamped to the server maximums.
+
+// `tonic::Status` is large, but it is what tonic services return.
+#![allow(clippy::result_large_err)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+use dupe::Dupe;
+use starlark::PrintHandler;
+use starlark::analysis::AstModuleLint;
+use starlark::docs::DocItem;
+use starlark::docs::markdown::render_doc_item_no_link;
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
+use starlark::environment::Module;
+use starlark::errors::EvalMessage;
+use starlark::errors::EvalSeverity;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::typing::AstModuleTypecheck;
+use starlark::values::Value;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+/// Code generated from `proto/starlark.proto`.
+pub mod proto {
+    tonic::include_proto!("starlark.v1");
+}
+
+use crate::proto::starlark_server::Starlark;
+
+/// Extensions which don't touch the host, so are safe for untrusted code.
+const EXTENSIONS: &[LibraryExtension] = &[
+    LibraryExtension::StructType,
+    LibraryExtension::RecordType,
+    LibraryExtension::EnumType,
+    LibraryExtension::NamespaceType,
+    LibraryExtension::SetType,
+    LibraryExtension::Map,
+    LibraryExtension::Filter,
+    LibraryExtension::Partial,
+    LibraryExtension::Json,
+    LibraryExtension::Typing,
+    LibraryExtension::Print,
+];
+
+/// The most any request may use. Requests asking for zero, or for more, get these.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerLimits {
+    /// Heap memory, in bytes.
+    pub max_heap_bytes: u64,
+    /// Evaluation steps, see `Evaluator::set_max_tick_count`.
+    pub max_steps: u64,
+    /// Wall-clock time for the whole request.
+    pub timeout: Duration,
+}
+
+impl ServerLimits {
+    fn clamp(&self, limits: Option<proto::Limits>) -> ServerLimits {
+        fn clamp(requested: u64, max: u64) -> u64 {
+            if requested == 0 {
+                max
+            } else {
+                requested.min(max)
+            }
+        }
+        let limits = limits.unwrap_or_default();
+        ServerLimits {
+            max_heap_bytes: clamp(limits.max_heap_bytes, self.max_heap_bytes),
+            max_steps: clamp(limits.max_steps, self.max_steps),
+            timeout: Duration::from_millis(clamp(
+                limits.timeout_ms,
+                self.timeout.as_millis().try_into().unwrap_or(u64::MAX),
+            )),
+        }
+    }
+}
+
+/// The service, to be served with `proto::starlark_server::StarlarkServer`.
+pub struct StarlarkService {
+    globals: Globals,
+    limits: ServerLimits,
+}
+
+impl StarlarkService {
+    pub fn new(limits: ServerLimits) -> StarlarkService {
+        StarlarkService {
+            globals: Globals::extended_by(EXTENSIONS),
+            limits,
+        }
+    }
+}
+
+fn diagnostic(message: EvalMessage) -> proto::Diagnostic {
+    let severity = match message.severity {
+        EvalSeverity::Error => proto::Severity::Error,
+        EvalSeverity::Warning => proto::Severity::Warning,
+        EvalSeverity::Advice | EvalSeverity::Disabled => proto::Severity::Advice,
+    };
+    proto::Diagnostic {
+        severity: severity as i32,
+        code: message.name,
+        message: message.description,
+        filename: message.path,
+        span: message.span.map(|span| proto::Span {
+            begin_line: span.begin.line as u32,
+            begin_column: span.begin.column as u32,
+            end_line: span.end.line as u32,
+            end_column: span.end.column as u32,
+        }),
+    }
+}
+
+fn error_diagnostic(filename: &str, err: &starlark::Error) -> proto::Diagnostic {
+    diagnostic(EvalMessage::from_error(Path::new(filename), err))
+}
+
+fn parse(file: &proto::SourceFile) -> Result<AstModule, proto::Diagnostic> {
+    AstModule::parse(&file.filename, file.content.clone(), &Dialect::Extended)
+        .map_err(|e| error_diagnostic(&file.filename, &e))
+}
+
+#[derive(Default)]
+struct CollectPrints(RefCell<Vec<String>>);
+
+impl PrintHandler for CollectPrints {
+    fn println(&self, text: &str) -> starlark::Result<()> {
+        self.0.borrow_mut().push(text.to_owned());
+        Ok(())
+    }
+}
+
+/// Evaluate a file within the limits, returning the frozen module, or the diagnostic.
+fn eval_module(
+    file: &proto::SourceFile,
+    inputs_json: &HashMap<String, String>,
+    globals: &Globals,
+    limits: &ServerLimits,
+    prints: &CollectPrints,
+) -> Result<Result<FrozenModule, proto::Diagnostic>, Status> {
+    let ast = match parse(file) {
+        Ok(ast) => ast,
+        Err(d) => return Ok(Err(d)),
+    };
+    let inputs = inputs_json
+        .iter()
+        .map(|(name, json)| match serde_json::from_str(json) {
+            Ok(json) => Ok((name.as_str(), json)),
+            Err(e) => Err(Status::invalid_argument(format!(
+                "Input `{name}` is not valid JSON: {e}"
+            ))),
+        })
+        .collect::<Result<Vec<(&str, serde_json::Value)>, Status>>()?;
+    let err = |e: starlark::Error| error_diagnostic(&file.filename, &e);
+    let internal = |e: anyhow::Error| Status::internal(e.to_string());
+    Module::with_temp_heap(|module| {
+        for (name, json) in &inputs {
+            module.set(name, Value::from_json_value(module.heap(), json));
+        }
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.set_print_handler(prints);
+            eval.set_max_heap_size(limits.max_heap_bytes.try_into().unwrap_or(usize::MAX))
+                .map_err(internal)?;
+            eval.set_max_tick_count(limits.max_steps)
+                .map_err(internal)?;
+            let deadline = Instant::now() + limits.timeout;
+            eval.set_check_cancelled(Box::new(move || Instant::now() >= deadline));
+            if let Err(e) = eval.eval_module(ast, globals) {
+                return Ok(Err(err(e)));
+            }
+        }
+        Ok(module.freeze().map_err(|e| err(e.into())))
+    })
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, Status> + Send + 'static,
+) -> Result<Response<T>, Status> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res.map(Response::new),
+        Err(e) => Err(Status::internal(e.to_string())),
+    }
+}
+
+fn required_file(file: Option<proto::SourceFile>) -> Result<proto::SourceFile, Status> {
+    file.ok_or_else(|| Status::invalid_argument("`file` is required"))
+}
+
+#[tonic::async_trait]
+impl Starlark for StarlarkService {
+    async fn parse(
+        &self,
+        request: Request<proto::ParseRequest>,
+    ) -> Result<Response<proto::ParseResponse>, Status> {
+        let request = request.into_inner();
+        let file = required_file(request.file)?;
+        blocking(move || {
+            Ok(match parse(&file) {
+                Ok(ast) => proto::ParseResponse {
+                    loads: ast
+                        .loads()
+                        .into_iter()
+                        .map(|x| x.module_id.to_owned())
+                        .collect(),
+                    diagnostics: if request.lint {
+                        ast.lint(None)
+                            .into_iter()
+                            .map(|lint| diagnostic(lint.into()))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    },
+                },
+                Err(d) => proto::ParseResponse {
+                    diagnostics: vec![d],
+                    loads: Vec::new(),
+                },
+            })
+        })
+        .await
+    }
+
+    async fn typecheck(
+        &self,
+        request: Request<proto::TypecheckRequest>,
+    ) -> Result<Response<proto::TypecheckResponse>, Status> {
+        let file = required_file(request.into_inner().file)?;
+        let globals = self.globals.dupe();
+        blocking(move || {
+            let diagnostics = match parse(&file) {
+                Ok(ast) => {
+                    let (errors, ..) = ast.typecheck(&globals, &HashMap::new());
+                    errors
+                        .iter()
+                        .map(|e| error_diagnostic(&file.filename, e))
+                        .collect()
+                }
+                Err(d) => vec![d],
+            };
+            Ok(proto::TypecheckResponse { diagnostics })
+        })
+        .await
+    }
+
+    async fn eval(
+        &self,
+        request: Request<proto::EvalRequest>,
+    ) -> Result<Response<proto::EvalResponse>, Status> {
+        let request = request.into_inner();
+        let file = required_file(request.file)?;
+        let limits = self.limits.clamp(request.limits);
+        let globals = self.globals.dupe();
+        blocking(move || {
+            let prints = CollectPrints::default();
+            let res = eval_module(&file, &request.inputs_json, &globals, &limits, &prints)?;
+            let output = prints.0.into_inner();
+            let module = match res {
+                Ok(module) => module,
+                Err(d) => {
+                    return Ok(proto::EvalResponse {
+                        diagnostics: vec![d],
+                        globals_json: HashMap::new(),
+                        output,
+                    });
+                }
+            };
+            let mut globals_json = HashMap::new();
+            if request.exports.is_empty() {
+                for name in module.names() {
+                    if let Ok(value) = module.get(name.as_str()) {
+                        if let Ok(json) = value.value().to_json() {
+                            globals_json.insert(name.as_str().to_owned(), json);
+                        }
+                    }
+                }
+            } else {
+                for name in &request.exports {
+                    let value = module
+                        .get(name)
+                        .map_err(|e| Status::not_found(format!("{e:#}")))?;
+                    let json = value.value().to_json().map_err(|e| {
+                        Status::failed_precondition(format!("Global `{name}`: {e:#}"))
+                    })?;
+                    globals_json.insert(name.clone(), json);
+                }
+            }
+            Ok(proto::EvalResponse {
+                diagnostics: Vec::new(),
+                globals_json,
+                output,
+            })
+        })
+        .await
+    }
+
+    async fn docs(
+        &self,
+        request: Request<proto::DocsRequest>,
+    ) -> Result<Response<proto::DocsResponse>, Status> {
+        let request = request.into_inner();
+        let limits = self.limits.clamp(request.limits);
+        let globals = self.globals.dupe();
+        blocking(move || {
+            let Some(file) = request.file else {
+                return Ok(proto::DocsResponse {
+                    diagnostics: Vec::new(),
+                    markdown: render_doc_item_no_link(
+                        "builtins",
+                        &DocItem::Module(globals.documentation()),
+                    ),
+                });
+            };
+            let prints = CollectPrints::default();
+            Ok(
+                match eval_module(&file, &HashMap::new(), &globals, &limits, &prints)? {
+                    Ok(module) => proto::DocsResponse {
+                        diagnostics: Vec::new(),
+                        markdown: render_doc_item_no_link(
+                            &file.filename,
+                            &DocItem::Module(module.documentation()),
+                        ),
+                    },
+                    Err(d) => proto::DocsResponse {
+                        diagnostics: vec![d],
+                        markdown: String::new(),
+                    },
+                },
+            )
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use tonic::Request;
+
+    use crate::ServerLimits;
+    use crate::StarlarkService;
+    use crate::proto;
+    use crate::proto::starlark_server::Starlark;
+
+    fn service() -> StarlarkService {
+        StarlarkService::new(ServerLimits {
+            max_heap_bytes: 100_000_000,
+            max_steps: 1_000_000,
+            timeout: Duration::from_secs(10),
+        })
+    }
+
+    fn file(content: &str) -> Option<proto::SourceFile> {
+        Some(proto::SourceFile {
+            filename: "test.star".to_owned(),
+            content: content.to_owned(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_parse() {
+        let res = service()
+            .parse(Request::new(proto::ParseRequest {
+                file: file("load('lib.star', 'x')\ny = x"),
+                lint: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.diagnostics.is_empty());
+        assert_eq!(vec!["lib.star"], res.loads);
+
+        let res = service()
+            .parse(Request::new(proto::ParseRequest {
+                file: file("x = ("),
+                lint: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(1, res.diagnostics.len());
+        assert!(res.diagnostics[0].span.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_eval() {
+        let res = service()
+            .eval(Request::new(proto::EvalRequest {
+                file: file("print('hi')\ny = [x * 2 for x in xs]"),
+                inputs_json: HashMap::from([("xs".to_owned(), "[1, 2]".to_owned())]),
+                exports: vec!["y".to_owned()],
+                limits: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.diagnostics.is_empty(), "{:?}", res.diagnostics);
+        assert_eq!("[2,4]", res.globals_json["y"]);
+        assert_eq!(vec!["hi"], res.output);
+    }
+
+    #[tokio::test]
+    async fn test_eval_limits() {
+        let res = service()
+            .eval(Request::new(proto::EvalRequest {
+                file: file("def f():\n  for _ in range(100000000): pass\nf()"),
+                inputs_json: HashMap::new(),
+                exports: Vec::new(),
+                limits: Some(proto::Limits {
+                    max_steps: 10000,
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(1, res.diagnostics.len());
+        assert!(
+            res.diagnostics[0].message.contains("ticks"),
+            "{:?}",
+            res.diagnostics
+        );
+    }
+
+    #[tokio::test]
+    async fn test_docs() {
+        let res = service()
+            .docs(Request::new(proto::DocsRequest {
+                file: file("def f():\n  \"\"\"Does nothing.\"\"\"\n  pass"),
+                limits: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.markdown.contains("Does nothing."), "{}", res.markdown);
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Serve the Starlark gRPC service.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use clap::Parser;
+use starlark_server::ServerLimits;
+use starlark_server::StarlarkService;
+use starlark_server::proto::starlark_server::StarlarkServer;
+use tonic::transport::Server;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "starlark-server",
+    about = "Serve Starlark evaluation over gRPC"
+)]
+struct Args {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+
+    /// Most heap memory, in bytes, a request may use.
+    #[arg(long, default_value_t = 256 * 1024 * 1024)]
+    max_heap_bytes: u64,
+
+    /// Most evaluation steps a request may take.
+    #[arg(long, default_value_t = 100_000_000)]
+    max_steps: u64,
+
+    /// Most time, in milliseconds, a request may take.
+    #[arg(long, default_value_t = 10_000)]
+    timeout_ms: u64,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let service = StarlarkService::new(ServerLimits {
+        max_heap_bytes: args.max_heap_bytes,
+        max_steps: args.max_steps,
+        timeout: Duration::from_millis(args.timeout_ms),
+    });
+    eprintln!("starlark-server listening on {}", args.listen);
+    Server::builder()
+        .add_service(StarlarkServer::new(service))
+        .serve(args.listen)
+        .await?;
+    Ok(())
+}
@@ -15,6 +15,12 @@
  * limitations under the License.
  */
 
+use std::env;
+use std::fs;
+use std::hash::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+
 fn lalrpop() {
     let source = "src/syntax/grammar.lalrpop";
     println!("cargo:rerun-if-changed={source}");
@@ -25,6 +31,32 @@ fn lalrpop() {
         .unwrap();
 }
 
+/// Hash of the sources of the types serialized by `AstModule::to_bytes`,
+/// so that modules serialized by a build with a different AST are rejected,
+/// even if the crate version is the same.
+fn ast_schema() {
+    let sources = [
+        "src/codemap.rs",
+        "src/dialect.rs",
+        "src/lexer.rs",
+        "src/syntax/ast.rs",
+        "src/syntax/cache.rs",
+        "src/syntax/lint_suppressions.rs",
+    ];
+    let mut hasher = DefaultHasher::new();
+    for source in sources {
+        println!("cargo:rerun-if-changed={source}");
+        fs::read(source).unwrap().hash(&mut hasher);
+    }
+    println!(
+        "cargo:rustc-env=STARLARK_SYNTAX_AST_SCHEMA={:016x}",
+        hasher.finish()
+    );
+}
+
 fn main() {
     lalrpop();
+    if env::var_os("CARGO_FEATURE_AST_CACHE").is_some() {
+        ast_schema();
+    }
 }
@@ -29,6 +29,7 @@ use std::fmt::Debug;
 use std::fmt::Display;
 
 use crate::frame::Frame;
+use crate::span_display::DiagnosticRenderOptions;
 
 pub const CALL_STACK_TRACEBACK_PREFIX: &str = "Traceback (most recent call last):";
 
@@ -49,20 +50,68 @@ impl CallStack {
     pub fn into_frames(self) -> Vec<Frame> {
         self.frames
     }
-}
 
-impl Display for CallStack {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Write the call stack as a traceback, with the paths and frame limit of `options`.
+    pub fn write_with_options(
+        &self,
+        options: &DiagnosticRenderOptions,
+        f: &mut dyn fmt::Write,
+    ) -> fmt::Result {
         if !self.frames.is_empty() {
             // Match Python output.
             writeln!(f, "{CALL_STACK_TRACEBACK_PREFIX}")?;
+            let skip = match options.max_stack_frames {
+                Some(max) if max < self.frames.len() => self.frames.len() - max,
+                _ => 0,
+            };
             // TODO(nga): use real module name.
             let mut prev = "<module>";
-            for x in &self.frames {
-                x.write_two_lines("  ", prev, f)?;
+            if skip != 0 {
+                writeln!(f, "  ... {skip} earlier frame(s) omitted")?;
+                prev = &self.frames[skip - 1].name;
+            }
+            let path = |file: &str| options.paths.render(file).into_owned();
+            for x in &self.frames[skip..] {
+                x.write_two_lines_with_path("  ", prev, &path, f)?;
                 prev = &x.name;
             }
         }
         Ok(())
     }
 }
+
+impl Display for CallStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with_options(&DiagnosticRenderOptions::default(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::call_stack::CallStack;
+    use crate::frame::Frame;
+    use crate::span_display::DiagnosticRenderOptions;
+
+    #[test]
+    fn test_max_stack_frames() {
+        let call_stack = CallStack {
+            frames: ["a", "b", "c"]
+                .iter()
+                .map(|name| Frame {
+                    name: (*name).to_owned(),
+                    location: None,
+                })
+                .collect(),
+        };
+        let options = DiagnosticRenderOptions {
+            max_stack_frames: Some(1),
+            ..DiagnosticRenderOptions::default()
+        };
+        let mut res = String::new();
+        call_stack.write_with_options(&options, &mut res).unwrap();
+        assert_eq!(
+            "Traceback (most recent call last):\n  ... 2 earlier frame(s) omitted\n  File <builtin>, in b\n",
+            res
+        );
+    }
+}
@@ -48,6 +48,7 @@ use crate::fast_string;
 #[derive(
     Copy, Clone, Dupe, Hash, Eq, PartialEq, PartialOrd, Ord, Debug, Default, Allocative
 )]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos(u32);
 
 impl Pos {
@@ -86,6 +87,7 @@ impl AddAssign<u32> for Pos {
 #[derive(
     Copy, Dupe, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Allocative
 )]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     /// The position in the codemap representing the first byte of the span.
     begin: Pos,
@@ -151,6 +153,7 @@ impl Span {
 
 /// Associate a Span with a value of arbitrary type (e.g. an AST node).
 #[derive(Clone, Copy, Dupe, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Spanned<T> {
     /// Data in the node.
     pub node: T,
@@ -22,7 +22,10 @@ use crate::call_stack::CallStack;
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::Span;
-use crate::span_display::span_display;
+use crate::error::ErrorCode;
+use crate::span_display::DiagnosticRenderOptions;
+use crate::span_display::SpanLabel;
+use crate::span_display::span_display_with_options;
 
 /// A value of type `T`, together with some diagnostic information.
 ///
@@ -49,6 +52,9 @@ impl<T> WithDiagnostic<T> {
             diagnostic: Diagnostic {
                 span: Some(codemap.file_span(span)),
                 call_stack: CallStack::default(),
+                code: None,
+                message_args: Vec::new(),
+                labels: Vec::new(),
             },
         }))
     }
@@ -86,6 +92,43 @@ impl<T> WithDiagnostic<T> {
         &self.0.diagnostic.call_stack
     }
 
+    /// The code set with [`set_code`](WithDiagnostic::set_code), if any.
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.0.diagnostic.code
+    }
+
+    /// Set the code identifying the error.
+    pub fn set_code(&mut self, code: ErrorCode) {
+        self.0.diagnostic.code = Some(code);
+    }
+
+    /// Clear the code, and the message arguments which go with it.
+    pub(crate) fn clear_code(&mut self) {
+        self.0.diagnostic.code = None;
+        self.0.diagnostic.message_args.clear();
+    }
+
+    /// Named arguments for the message template of the code, see
+    /// [`MessageCatalog`](crate::message_catalog::MessageCatalog).
+    pub fn message_args(&self) -> &[(&'static str, String)] {
+        &self.0.diagnostic.message_args
+    }
+
+    /// Set the named arguments for the message template of the code.
+    pub fn set_message_args(&mut self, args: Vec<(&'static str, String)>) {
+        self.0.diagnostic.message_args = args;
+    }
+
+    /// Secondary locations referenced by the error.
+    pub fn labels(&self) -> &[SpanLabel] {
+        &self.0.diagnostic.labels
+    }
+
+    /// Add a secondary location, shown with the primary span.
+    pub fn add_label(&mut self, label: SpanLabel) {
+        self.0.diagnostic.labels.push(label);
+    }
+
     /// Set the span, unless it's already been set.
     pub fn set_span(&mut self, span: Span, codemap: &CodeMap) {
         if self.0.diagnostic.span.is_none() {
@@ -105,20 +148,39 @@ impl<T: StdError> fmt::Display for WithDiagnostic<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Not showing the context trace without `{:#}` or `{:?}` is the same thing that anyhow does
         let with_context = f.alternate() && self.0.t.source().is_some();
-        diagnostic_display(self, false, f, with_context)
+        diagnostic_display(
+            self,
+            self.inner(),
+            &DiagnosticRenderOptions::default(),
+            None,
+            f,
+            with_context,
+        )
     }
 }
 
 impl<T: StdError> fmt::Debug for WithDiagnostic<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        diagnostic_display(self, false, f, /* with_context */ true)
+        diagnostic_display(
+            self,
+            self.inner(),
+            &DiagnosticRenderOptions::default(),
+            None,
+            f,
+            /* with_context */ true,
+        )
     }
 }
 
 impl<T: Into<crate::Error>> From<WithDiagnostic<T>> for crate::Error {
     fn from(e: WithDiagnostic<T>) -> Self {
-        let diagnostic = e.0.diagnostic;
+        let mut diagnostic = e.0.diagnostic;
         let mut e: crate::Error = e.0.t.into();
+        // Keep the code, and its message arguments, assigned by the conversion.
+        if diagnostic.code.is_none() {
+            diagnostic.code = e.0.code();
+            diagnostic.message_args = e.0.message_args().to_vec();
+        }
         e.0.0.diagnostic = diagnostic;
         e
     }
@@ -132,6 +194,15 @@ struct Diagnostic {
 
     /// Call stack where the error originated.
     call_stack: CallStack,
+
+    /// Code identifying the error, if more specific than the code of its kind.
+    code: Option<ErrorCode>,
+
+    /// Named arguments of the error, for message templates keyed by `code`.
+    message_args: Vec<(&'static str, String)>,
+
+    /// Secondary locations, like where a duplicated name was first declared.
+    labels: Vec<SpanLabel>,
 }
 
 impl Diagnostic {
@@ -139,12 +210,15 @@ impl Diagnostic {
     fn get_display_list<'a>(
         &'a self,
         annotation_label: &'a str,
-        color: bool,
+        annotation_id: Option<&'a str>,
+        options: &'a DiagnosticRenderOptions,
     ) -> impl fmt::Display + 'a {
-        span_display(
+        span_display_with_options(
             self.span.as_ref().map(|s| s.as_ref()),
             annotation_label,
-            color,
+            annotation_id,
+            &self.labels,
+            options,
         )
     }
 }
@@ -155,17 +229,22 @@ impl Diagnostic {
 // variants by doing a conversion using annotate-snippets
 // (https://github.com/rust-lang/annotate-snippets-rs)
 
+/// Display the diagnostic with `message`, usually the value itself, as its title.
 pub(crate) fn diagnostic_display<T: fmt::Debug + fmt::Display>(
     d: &WithDiagnostic<T>,
-    color: bool,
+    message: &dyn fmt::Display,
+    options: &DiagnosticRenderOptions,
+    code: Option<&str>,
     f: &mut dyn fmt::Write,
     with_context: bool,
 ) -> fmt::Result {
-    write!(f, "{}", d.call_stack())?;
-    let annotation_label = format!("{}", d.inner());
-    // I set color to false here to make the comparison easier with tests (coloring
-    // adds in pretty strange unicode chars).
-    let display_list = d.0.diagnostic.get_display_list(&annotation_label, color);
+    d.call_stack().write_with_options(options, f)?;
+    let annotation_label = message.to_string();
+    // Callers set color to false by default, to make the comparison easier with tests
+    // (coloring adds in pretty strange unicode chars).
+    let display_list =
+        d.0.diagnostic
+            .get_display_list(&annotation_label, code, options);
     writeln!(f, "{display_list}")?;
     // Print out the `Caused by:` trace (if exists) and rust backtrace (if enabled).
     // The trace printed comes from an [`anyhow::Error`] that is not a [`Diagnostic`].
@@ -22,6 +22,7 @@ use dupe::Dupe;
 /// If you are enabling types, you will often want to use
 /// `LibraryExtension::Typing` when constructing a `Globals` environment.
 #[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub enum DialectTypes {
     /// Prohibit types at parse time.
     Disable,
@@ -33,6 +34,7 @@ pub enum DialectTypes {
 
 /// Starlark language features to enable, e.g. [`Standard`](Dialect::Standard) to follow the Starlark standard.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dialect {
     /// Are `def` statements permitted.
     /// Enabled by default.
@@ -64,6 +66,13 @@ pub struct Dialect {
     ///
     /// [Starlark spec proposal](https://github.com/bazelbuild/starlark/issues/91).
     pub enable_f_strings: bool,
+    /// Does a top-level `__all__`, a list of names, restrict which symbols other modules
+    /// can `load()`. Names starting with `_` are private either way.
+    /// Disabled by default.
+    pub enable_export_list: bool,
+    /// Are set literals, like `{1, 2}`, supported? `{}` is always an empty dict.
+    /// Disabled by default.
+    pub enable_set_literals: bool,
     /// Like `#[non_exhaustive]`, but allows struct expression.
     ///
     /// [Explanation](https://github.com/rust-lang/rust-clippy/issues/6559).
@@ -93,6 +102,8 @@ impl Dialect {
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
         enable_f_strings: false,
+        enable_export_list: false,
+        enable_set_literals: false,
         _non_exhaustive: (),
     };
 
@@ -108,6 +119,8 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: false,
+        enable_export_list: false,
+        enable_set_literals: false,
         _non_exhaustive: (),
     };
 
@@ -123,6 +136,8 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: true,
+        enable_export_list: true,
+        enable_set_literals: true,
         _non_exhaustive: (),
     };
 }
@@ -17,6 +17,7 @@
 
 use std::fmt;
 use std::mem;
+use std::str::FromStr;
 
 use crate::call_stack::CallStack;
 use crate::codemap::CodeMap;
@@ -24,6 +25,10 @@ use crate::codemap::FileSpan;
 use crate::codemap::Span;
 use crate::diagnostic::WithDiagnostic;
 use crate::diagnostic::diagnostic_display;
+use crate::message_catalog::MessageCatalog;
+use crate::message_catalog::interpolate;
+use crate::span_display::DiagnosticRenderOptions;
+use crate::span_display::SpanLabel;
 
 /// An error produced by starlark.
 ///
@@ -36,6 +41,108 @@ pub struct Error(pub(crate) WithDiagnostic<ErrorKind>);
 
 const _: () = assert!(mem::size_of::<Error>() == mem::size_of::<usize>());
 
+/// A stable, machine-readable, identifier for a kind of error, displayed like `E0301`.
+///
+/// Codes are never reused or renumbered, so tooling can match on them rather than on
+/// messages, which may be reworded. The hundreds digit is the category, matching
+/// [`ErrorKind`], and `docs/errors.md` explains each code.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Debug)]
+pub struct ErrorCode(u16);
+
+impl ErrorCode {
+    /// An error without a more specific code.
+    pub const OTHER: ErrorCode = ErrorCode(0);
+    /// Syntax error.
+    pub const PARSER: ErrorCode = ErrorCode(200);
+    /// Scope error.
+    pub const SCOPE: ErrorCode = ErrorCode(300);
+    /// Value error.
+    pub const VALUE: ErrorCode = ErrorCode(400);
+    /// Function call error.
+    pub const FUNCTION: ErrorCode = ErrorCode(500);
+    /// An explicit `fail` invocation.
+    pub const FAIL: ErrorCode = ErrorCode(600);
+    /// Starlark call stack overflow.
+    pub const STACK_OVERFLOW: ErrorCode = ErrorCode(601);
+    /// The heap memory limit of the evaluator was exceeded.
+    pub const HEAP_LIMIT: ErrorCode = ErrorCode(602);
+    /// The tick limit of the evaluator was exceeded.
+    pub const TICK_LIMIT: ErrorCode = ErrorCode(603);
+    /// The evaluation was cancelled.
+    pub const CANCELLED: ErrorCode = ErrorCode(604);
+    /// A string larger than the string size limit of the evaluator.
+    pub const STRING_LIMIT: ErrorCode = ErrorCode(605);
+    /// The deadline of the evaluation passed.
+    pub const DEADLINE_EXCEEDED: ErrorCode = ErrorCode(606);
+    /// Any other error raised while evaluating, which has no more specific code.
+    pub const RUNTIME: ErrorCode = ErrorCode(607);
+    /// Freeze error.
+    pub const FREEZE: ErrorCode = ErrorCode(700);
+    /// Error from a user provided native function.
+    pub const NATIVE: ErrorCode = ErrorCode(800);
+    /// Logic bug in starlark.
+    pub const INTERNAL: ErrorCode = ErrorCode(900);
+
+    /// Create a code from its number, so `ErrorCode::new(301)` is `E0301`.
+    pub const fn new(number: u16) -> ErrorCode {
+        assert!(number <= 9999);
+        ErrorCode(number)
+    }
+
+    /// The number of the code, without the `E` prefix.
+    pub const fn number(self) -> u16 {
+        self.0
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{:04}", self.0)
+    }
+}
+
+/// A coarse classification of errors, for hosts deciding whether to retry an evaluation
+/// or raise an alert, without matching on messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ErrorClass {
+    /// An explicit `fail` invocation.
+    Fail,
+    /// The program is invalid: a syntax or scope error.
+    Syntax,
+    /// An operation on values which doesn't support it, like a type error, a missing
+    /// key or the wrong arguments to a function.
+    Type,
+    /// The evaluation ran out of a resource: the call stack, the heap memory limit,
+    /// the tick limit or the string size limit.
+    ResourceExhausted,
+    /// The evaluation was cancelled, including by a timeout implemented as cancellation.
+    Cancelled,
+    /// A logic bug in starlark.
+    Internal,
+    /// An error from a user provided native function.
+    Native,
+    /// Any other error, like a freeze error.
+    Other,
+}
+
+/// Error parsing an [`ErrorCode`] from a string.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid error code `{0}`, expected a code like `E0301`")]
+pub struct ErrorCodeParseError(String);
+
+impl FromStr for ErrorCode {
+    type Err = ErrorCodeParseError;
+
+    fn from_str(s: &str) -> std::result::Result<ErrorCode, ErrorCodeParseError> {
+        match s.strip_prefix('E') {
+            Some(n) if n.len() == 4 && n.bytes().all(|b| b.is_ascii_digit()) => {
+                Ok(ErrorCode(n.parse().unwrap()))
+            }
+            _ => Err(ErrorCodeParseError(s.to_owned())),
+        }
+    }
+}
+
 impl Error {
     /// Create a new error
     #[cold]
@@ -50,15 +157,22 @@ impl Error {
     }
 
     /// Create a new error with no diagnostic and of kind [`ErrorKind::Other`]
+    ///
+    /// If `e` is a starlark error converted with [`into_anyhow`](Error::into_anyhow), the
+    /// original error is returned instead.
     #[cold]
     pub fn new_other(e: impl Into<anyhow::Error>) -> Self {
-        Self(WithDiagnostic::new_empty(ErrorKind::Other(e.into())))
+        Self::from_anyhow(e.into(), ErrorKind::Other)
     }
 
     /// Create a new error with no diagnostic and of kind [`ErrorKind::Native`]
+    ///
+    /// If `e` is a starlark error converted with [`into_anyhow`](Error::into_anyhow), for
+    /// example from Starlark code called by the native function, the original error is
+    /// returned instead, keeping its call stack and span.
     #[cold]
     pub fn new_native(e: impl Into<anyhow::Error>) -> Self {
-        Self(WithDiagnostic::new_empty(ErrorKind::Native(e.into())))
+        Self::from_anyhow(e.into(), ErrorKind::Native)
     }
 
     /// Create a new error with no diagnostic and of kind [`ErrorKind::Value`]
@@ -67,6 +181,71 @@ impl Error {
         Self(WithDiagnostic::new_empty(ErrorKind::Value(e.into())))
     }
 
+    /// Set the code of this error, replacing the default code of its kind.
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.0.set_code(code);
+        self
+    }
+
+    /// The stable code identifying this error, or if it hasn't been given one, the code of
+    /// its kind.
+    pub fn code(&self) -> ErrorCode {
+        self.0.code().unwrap_or_else(|| self.kind().code())
+    }
+
+    /// Set the named arguments for the message template of the code of this error, see
+    /// [`MessageCatalog`].
+    pub fn with_message_args(mut self, args: Vec<(&'static str, String)>) -> Self {
+        self.0.set_message_args(args);
+        self
+    }
+
+    /// The named arguments for the message template of the code of this error.
+    pub fn message_args(&self) -> &[(&'static str, String)] {
+        self.0.message_args()
+    }
+
+    /// The message of this error, without diagnostic information, from the template for its
+    /// code in `catalog`, or the default message if there is none.
+    pub fn message_from_catalog(&self, catalog: &dyn MessageCatalog) -> String {
+        let message = self.kind().to_string();
+        match catalog.template(self.code()) {
+            Some(template) => {
+                let mut args: Vec<(&str, &str)> = self
+                    .message_args()
+                    .iter()
+                    .map(|(k, v)| (*k, v.as_str()))
+                    .collect();
+                args.push(("message", &message));
+                interpolate(template, &args)
+            }
+            None => message,
+        }
+    }
+
+    /// The classification of this error, from its code and kind.
+    pub fn class(&self) -> ErrorClass {
+        match self.code() {
+            ErrorCode::STACK_OVERFLOW
+            | ErrorCode::HEAP_LIMIT
+            | ErrorCode::TICK_LIMIT
+            | ErrorCode::STRING_LIMIT => {
+                return ErrorClass::ResourceExhausted;
+            }
+            ErrorCode::CANCELLED | ErrorCode::DEADLINE_EXCEEDED => return ErrorClass::Cancelled,
+            _ => {}
+        }
+        match self.kind() {
+            ErrorKind::Fail(_) => ErrorClass::Fail,
+            ErrorKind::StackOverflow(_) => ErrorClass::ResourceExhausted,
+            ErrorKind::Value(_) | ErrorKind::Function(_) => ErrorClass::Type,
+            ErrorKind::Scope(_) | ErrorKind::Parser(_) => ErrorClass::Syntax,
+            ErrorKind::Internal(_) => ErrorClass::Internal,
+            ErrorKind::Native(_) => ErrorClass::Native,
+            ErrorKind::Freeze(_) | ErrorKind::Other(_) => ErrorClass::Other,
+        }
+    }
+
     /// The kind of this error
     pub fn kind(&self) -> &ErrorKind {
         self.0.inner()
@@ -82,29 +261,25 @@ impl Error {
     }
 
     /// Convert this error into an `anyhow::Error`
+    ///
+    /// Converting the result back into a starlark `Error`, for example by returning it from a
+    /// native function, gives back this error, with its span and call stack.
     #[cold]
     pub fn into_anyhow(self) -> anyhow::Error {
-        struct Wrapped(Error);
-
-        impl fmt::Display for Wrapped {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                fmt::Display::fmt(&self.0, f)
-            }
-        }
+        anyhow::Error::new(Wrapped(self))
+    }
 
-        impl fmt::Debug for Wrapped {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                fmt::Debug::fmt(&self.0, f)
-            }
+    /// If `e` came from [`into_anyhow`](Error::into_anyhow), without context added since,
+    /// get back the original error, so that its diagnostic isn't only part of the message.
+    fn from_anyhow(e: anyhow::Error, kind: impl FnOnce(anyhow::Error) -> ErrorKind) -> Self {
+        // With context, downcasting would lose the context message.
+        if !e.chain().next().is_some_and(|x| x.is::<Wrapped>()) {
+            return Self(WithDiagnostic::new_empty(kind(e)));
         }
-
-        impl std::error::Error for Wrapped {
-            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-                self.0.kind().source()
-            }
+        match e.downcast::<Wrapped>() {
+            Ok(Wrapped(e)) => e,
+            Err(e) => Self(WithDiagnostic::new_empty(kind(e))),
         }
-
-        anyhow::Error::new(Wrapped(self))
     }
 
     /// Returns a value that can be used to format this error without including the diagnostic
@@ -128,6 +303,20 @@ impl Error {
         self.0.set_span(span, codemap);
     }
 
+    /// Secondary locations referenced by the error, shown with its span.
+    pub fn labels(&self) -> &[SpanLabel] {
+        self.0.labels()
+    }
+
+    /// Add a secondary location, like where a duplicated name was first declared.
+    pub fn with_label(mut self, span: FileSpan, label: impl Into<String>) -> Self {
+        self.0.add_label(SpanLabel {
+            span,
+            label: label.into(),
+        });
+        self
+    }
+
     /// Set the `call_stack` field, unless it's already been set.
     pub fn set_call_stack(&mut self, call_stack: impl FnOnce() -> CallStack) {
         self.0.set_call_stack(call_stack);
@@ -142,19 +331,66 @@ impl Error {
     pub fn eprint(&self) {
         if self.has_diagnostic() {
             let mut stderr = String::new();
-            diagnostic_display(&self.0, true, &mut stderr, true).unwrap();
+            let code = self.code().to_string();
+            let options = DiagnosticRenderOptions {
+                color: true,
+                ..DiagnosticRenderOptions::default()
+            };
+            diagnostic_display(
+                &self.0,
+                self.kind(),
+                &options,
+                Some(&code),
+                &mut stderr,
+                true,
+            )
+            .unwrap();
             eprint!("{stderr}");
         } else {
             eprintln!("{self:#}")
         }
     }
 
+    /// Render the error with its error code and diagnostic information, as configured by
+    /// `options`. Like `Display`, the context trace is only shown with `{:#}`.
+    pub fn display_with_options<'a>(
+        &'a self,
+        options: &'a DiagnosticRenderOptions,
+    ) -> impl fmt::Display + 'a {
+        struct Rendered<'a>(&'a Error, &'a DiagnosticRenderOptions);
+
+        impl fmt::Display for Rendered<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let Rendered(e, options) = self;
+                let message = match &options.catalog {
+                    Some(catalog) => e.message_from_catalog(&**catalog),
+                    None if !e.has_diagnostic() => {
+                        return fmt::Display::fmt(&e.without_diagnostic(), f);
+                    }
+                    None => e.kind().to_string(),
+                };
+                if e.has_diagnostic() {
+                    let with_context = f.alternate() && e.kind().source().is_some();
+                    let code = e.code().to_string();
+                    diagnostic_display(&e.0, &message, options, Some(&code), f, with_context)
+                } else {
+                    f.write_str(&message)
+                }
+            }
+        }
+
+        Rendered(self, options)
+    }
+
     /// Change error kind to internal error.
     pub fn into_internal_error(self) -> Error {
         if let ErrorKind::Internal(_) = self.kind() {
             self
         } else {
-            Error(self.0.map(ErrorKind::into_internal_error))
+            let mut e = self.0.map(ErrorKind::into_internal_error);
+            // The specific code described the original error, not the internal one.
+            e.clear_code();
+            Error(e)
         }
     }
 }
@@ -163,7 +399,14 @@ fn fmt_impl(this: &Error, is_debug: bool, f: &mut fmt::Formatter<'_>) -> fmt::Re
     if this.has_diagnostic() {
         // Not showing the context trace without `{:#}` or `{:?}` is the same thing that anyhow does
         let with_context = (f.alternate() || is_debug) && this.kind().source().is_some();
-        diagnostic_display(&this.0, false, f, with_context)
+        diagnostic_display(
+            &this.0,
+            this.kind(),
+            &DiagnosticRenderOptions::default(),
+            None,
+            f,
+            with_context,
+        )
     } else {
         fmt::Display::fmt(&this.without_diagnostic(), f)
     }
@@ -230,6 +473,22 @@ impl ErrorKind {
         }
     }
 
+    /// The code of errors of this kind which haven't been given a more specific one.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Fail(_) => ErrorCode::FAIL,
+            Self::StackOverflow(_) => ErrorCode::STACK_OVERFLOW,
+            Self::Value(_) => ErrorCode::VALUE,
+            Self::Function(_) => ErrorCode::FUNCTION,
+            Self::Scope(_) => ErrorCode::SCOPE,
+            Self::Parser(_) => ErrorCode::PARSER,
+            Self::Freeze(_) => ErrorCode::FREEZE,
+            Self::Internal(_) => ErrorCode::INTERNAL,
+            Self::Native(_) => ErrorCode::NATIVE,
+            Self::Other(_) => ErrorCode::OTHER,
+        }
+    }
+
     /// Change type to `Internal`.
     pub(crate) fn into_internal_error(self) -> ErrorKind {
         match self {
@@ -284,7 +543,28 @@ impl fmt::Display for ErrorKind {
 impl From<anyhow::Error> for Error {
     #[cold]
     fn from(e: anyhow::Error) -> Self {
-        Self(WithDiagnostic::new_empty(ErrorKind::Other(e)))
+        Self::from_anyhow(e, ErrorKind::Other)
+    }
+}
+
+/// A starlark error as an `std::error::Error`, for [`Error::into_anyhow`].
+struct Wrapped(Error);
+
+impl fmt::Display for Wrapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for Wrapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for Wrapped {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.kind().source()
     }
 }
 
@@ -363,3 +643,43 @@ macro_rules! function_error {
         $crate::error::function_error_impl(format_args!($format, $($args)*))
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::ErrorClass;
+    use crate::ErrorCode;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_error_code_display_parse() {
+        assert_eq!("E0301", ErrorCode::new(301).to_string());
+        assert_eq!("E0000", ErrorCode::OTHER.to_string());
+        assert_eq!(ErrorCode::new(301), "E0301".parse().unwrap());
+        assert!("E301".parse::<ErrorCode>().is_err());
+        assert!("0301".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn test_error_code() {
+        let e = crate::Error::new_kind(ErrorKind::Fail(anyhow::anyhow!("oops")));
+        assert_eq!(ErrorCode::FAIL, e.code());
+        let e = e.with_code(ErrorCode::new(611));
+        assert_eq!(ErrorCode::new(611), e.code());
+        // Internal errors don't keep the code of the original error.
+        assert_eq!(ErrorCode::INTERNAL, e.into_internal_error().code());
+    }
+
+    #[test]
+    fn test_error_class() {
+        let e = crate::Error::new_kind(ErrorKind::Fail(anyhow::anyhow!("oops")));
+        assert_eq!(ErrorClass::Fail, e.class());
+        let e = crate::Error::new_kind(ErrorKind::Value(anyhow::anyhow!("bad")));
+        assert_eq!(ErrorClass::Type, e.class());
+        let e =
+            crate::Error::new_other(anyhow::anyhow!("cancelled")).with_code(ErrorCode::CANCELLED);
+        assert_eq!(ErrorClass::Cancelled, e.class());
+        let e = crate::Error::new_other(anyhow::anyhow!("ticks")).with_code(ErrorCode::TICK_LIMIT);
+        assert_eq!(ErrorClass::ResourceExhausted, e.class());
+        assert_eq!(ErrorClass::Internal, e.into_internal_error().class());
+    }
+}
@@ -67,6 +67,12 @@ impl EvalException {
         EvalException(error)
     }
 
+    /// Add a secondary location, in the same file, with a message explaining it.
+    #[cold]
+    pub fn with_label(self, span: Span, codemap: &CodeMap, label: impl Into<String>) -> Self {
+        EvalException(self.0.with_label(codemap.file_span(span), label))
+    }
+
     #[cold]
     pub fn new_anyhow(error: anyhow::Error, span: Span, codemap: &CodeMap) -> EvalException {
         EvalException(crate::Error::new_spanned(
@@ -20,6 +20,7 @@
 //! to make up some of the difference.
 
 use std::cmp::min;
+use std::iter;
 use std::ops::Add;
 use std::ops::Sub;
 use std::str;
@@ -128,21 +129,83 @@ pub fn len(x: &str) -> CharIndex {
 /// search for that character in the string.
 #[inline]
 pub fn count_matches_byte(x: &str, needle: u8) -> usize {
-    x.as_bytes().iter().filter(|x| **x == needle).count()
+    memchr::memchr_iter(needle, x.as_bytes()).count()
 }
 
 /// Find the number of times a `needle` occurs within a string, non-overlapping.
 #[inline]
 pub fn count_matches(x: &str, needle: &str) -> usize {
-    if needle.len() == 1 {
+    match needle.len() {
+        // Matches at every character boundary.
+        0 => x.matches(needle).count(),
         // If we are searching for a 1-byte string, we can provide a much faster path.
         // Since it is one byte, given how UTF8 works, all the resultant slices must be UTF8 too.
-        count_matches_byte(x, needle.as_bytes()[0])
-    } else {
-        x.matches(needle).count()
+        1 => count_matches_byte(x, needle.as_bytes()[0]),
+        _ => memchr::memmem::find_iter(x.as_bytes(), needle.as_bytes()).count(),
+    }
+}
+
+// The searches below use `memchr`, which selects SIMD code (e.g. AVX2) for the
+// running CPU at runtime. A match of valid UTF-8 in valid UTF-8 always starts and
+// ends at character boundaries, so byte offsets can be used to slice the haystack.
+
+/// Byte offset of the first occurrence of `needle`, like `str::find`.
+#[inline]
+pub fn find(haystack: &str, needle: &str) -> Option<usize> {
+    match needle.as_bytes() {
+        [b] => memchr::memchr(*b, haystack.as_bytes()),
+        needle => memchr::memmem::find(haystack.as_bytes(), needle),
     }
 }
 
+/// Byte offset of the last occurrence of `needle`, like `str::rfind`.
+#[inline]
+pub fn rfind(haystack: &str, needle: &str) -> Option<usize> {
+    match needle.as_bytes() {
+        [b] => memchr::memrchr(*b, haystack.as_bytes()),
+        needle => memchr::memmem::rfind(haystack.as_bytes(), needle),
+    }
+}
+
+/// Byte offsets of the non-overlapping occurrences of `needle`, which must not be empty.
+#[inline]
+pub fn match_indices<'a>(haystack: &'a str, needle: &'a str) -> impl Iterator<Item = usize> + 'a {
+    debug_assert!(!needle.is_empty());
+    memchr::memmem::find_iter(haystack.as_bytes(), needle.as_bytes())
+}
+
+/// Split by `sep`, which must not be empty, like `str::split`.
+pub fn split<'a>(x: &'a str, sep: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let mut start = 0;
+    match_indices(x, sep)
+        .map(Some)
+        .chain(iter::once(None))
+        .map(move |end| {
+            let end = end.unwrap_or(x.len());
+            let part = &x[start..end];
+            start = end + sep.len();
+            part
+        })
+}
+
+/// Split by `sep`, which must not be empty, like `str::rsplit`: the parts are
+/// returned last first.
+pub fn rsplit<'a>(x: &'a str, sep: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    debug_assert!(!sep.is_empty());
+    let mut end = x.len();
+    memchr::memmem::rfind_iter(x.as_bytes(), sep.as_bytes())
+        .map(Some)
+        .chain(iter::once(None))
+        .map(move |start| {
+            let part = match start {
+                Some(start) => &x[start + sep.len()..end],
+                None => &x[..end],
+            };
+            end = start.unwrap_or(0);
+            part
+        })
+}
+
 /// Result of applying `start` and `end` to a string.
 #[derive(PartialEq, Debug)]
 pub struct StrIndices<'a> {
@@ -261,10 +324,12 @@ pub fn contains(haystack: &str, needle: &str) -> bool {
         memchr::memchr(needle.as_bytes()[0], haystack.as_bytes()).is_some()
     } else if haystack.len() < needle.len() {
         false
+    } else if haystack.len() >= 64 {
+        memchr::memmem::find(haystack.as_bytes(), needle.as_bytes()).is_some()
     } else {
         assert!(haystack.len() >= needle.len());
-        // `str::contains` is very slow for short strings.
-        // So use basic quadratic algorithm instead.
+        // `str::contains` is very slow for short strings, and so is building a
+        // `memmem` searcher. So use basic quadratic algorithm instead.
         let needle_0 = needle.as_bytes()[0];
         for start in 0..=haystack.len() - needle.len() {
             if haystack.as_bytes()[start] != needle_0 {
@@ -303,6 +368,7 @@ impl Add for CharIndex {
 mod tests {
     use std::iter;
 
+    use crate::fast_string;
     use crate::fast_string::CharIndex;
     use crate::fast_string::StrIndices;
     use crate::fast_string::convert_str_indices;
@@ -388,4 +454,28 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_search_like_std() {
+        let long = "ab".repeat(100) + "Телемак, abab";
+        for x in ["", "a", "abab", "xababx", "Телемак", long.as_str()] {
+            for needle in ["a", "ab", "aba", "ле", "x", "Телемак"] {
+                assert_eq!(x.find(needle), fast_string::find(x, needle));
+                assert_eq!(x.rfind(needle), fast_string::rfind(x, needle));
+                assert_eq!(x.contains(needle), fast_string::contains(x, needle));
+                assert_eq!(
+                    x.matches(needle).count(),
+                    fast_string::count_matches(x, needle)
+                );
+                assert_eq!(
+                    x.split(needle).collect::<Vec<_>>(),
+                    fast_string::split(x, needle).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    x.rsplit(needle).collect::<Vec<_>>(),
+                    fast_string::rsplit(x, needle).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
 }
@@ -58,6 +58,17 @@ impl Frame {
         indent: &str,
         caller: &str,
         write: &mut dyn fmt::Write,
+    ) -> fmt::Result {
+        self.write_two_lines_with_path(indent, caller, &|file| file.to_owned(), write)
+    }
+
+    /// Like [`write_two_lines`](Frame::write_two_lines), showing file paths with `path`.
+    pub(crate) fn write_two_lines_with_path(
+        &self,
+        indent: &str,
+        caller: &str,
+        path: &dyn Fn(&str) -> String,
+        write: &mut dyn fmt::Write,
     ) -> fmt::Result {
         if let Some(location) = &self.location {
             let line = location
@@ -65,11 +76,13 @@ impl Frame {
                 .source_line_at_pos(location.span.begin())
                 .trim();
             let (line, ddd) = truncate_snippet(line, 80);
+            let mut file_line = location.resolve().begin_file_line();
+            file_line.file = path(&file_line.file);
             writeln!(
                 write,
                 "{}* {}, in {}",
                 indent,
-                location.resolve().begin_file_line(),
+                file_line,
                 // Note we print caller function here as in Python, not callee,
                 // so in the stack trace, top frame is printed without executed function name.
                 caller,
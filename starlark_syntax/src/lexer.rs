@@ -19,6 +19,8 @@ use std::char;
 use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::Chars;
 
 use logos::Logos;
 use num_bigint::BigInt;
@@ -31,6 +33,7 @@ use crate::codemap::Span;
 use crate::cursors::CursorBytes;
 use crate::cursors::CursorChars;
 use crate::dialect::Dialect;
+use crate::error::ErrorCode;
 use crate::eval_exception::EvalException;
 
 #[derive(Error, Debug)]
@@ -59,9 +62,29 @@ pub enum LexemeError {
     CannotParse(String, u32),
 }
 
+impl LexemeError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::new(match self {
+            LexemeError::Indentation => 101,
+            LexemeError::InvalidInput(_) => 102,
+            LexemeError::InvalidTab => 103,
+            LexemeError::UnfinishedStringLiteral => 104,
+            LexemeError::InvalidEscapeSequence(_) => 105,
+            LexemeError::EmptyEscapeSequence => 106,
+            LexemeError::ReservedKeyword(_) => 107,
+            LexemeError::StartsZero(_) => 108,
+            LexemeError::IntParse(_) => 109,
+            LexemeError::CommentSpanComputedIncorrectly => 110,
+            LexemeError::CannotParse(..) => 111,
+        })
+    }
+}
+
 impl From<LexemeError> for crate::error::Error {
     fn from(e: LexemeError) -> Self {
+        let code = e.code();
         crate::error::Error::new_kind(crate::error::ErrorKind::Parser(anyhow::Error::new(e)))
+            .with_code(code)
     }
 }
 
@@ -429,6 +452,105 @@ impl<'a> Lexer<'a> {
         )
     }
 
+    /// Turn the raw contents of a bytes literal into bytes, decoding escapes unless `raw`.
+    ///
+    /// Unlike in strings, `\x` and octal escapes denote a single byte, rather than a code point.
+    fn bytes(&self, lex: LexemeT<(String, usize)>, raw: bool) -> Lexeme {
+        let (start, (s, _offset), end) = lex?;
+        if raw {
+            return Ok((start, Token::Bytes(s.into_bytes()), end));
+        }
+        let mut res = Vec::with_capacity(s.len());
+        let mut it = s.chars().peekable();
+        // Take up to `max` digits in the given radix.
+        fn take(it: &mut Peekable<Chars>, max: usize, radix: u32) -> String {
+            let mut digits = String::new();
+            while digits.len() < max {
+                match it.peek() {
+                    Some(c) if c.is_digit(radix) => digits.push(it.next().unwrap()),
+                    _ => break,
+                }
+            }
+            digits
+        }
+        while let Some(c) = it.next() {
+            if c != '\\' {
+                let mut buf = [0; 4];
+                res.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+            let Some(e) = it.next() else {
+                return self.err_span(LexemeError::EmptyEscapeSequence, start, end);
+            };
+            let byte = match e {
+                'n' => b'\n',
+                'r' => b'\r',
+                't' => b'\t',
+                'a' => 0x07,
+                'b' => 0x08,
+                'f' => 0x0C,
+                'v' => 0x0B,
+                '\n' => continue,
+                '\\' | '\'' | '"' => e as u8,
+                'x' => {
+                    let digits = take(&mut it, 2, 16);
+                    match u8::from_str_radix(&digits, 16) {
+                        Ok(b) if digits.len() == 2 => b,
+                        _ => {
+                            return self.err_span(
+                                LexemeError::InvalidEscapeSequence(format!("x{digits}")),
+                                start,
+                                end,
+                            );
+                        }
+                    }
+                }
+                '0'..='7' => {
+                    let digits = format!("{e}{}", take(&mut it, 2, 8));
+                    match u8::from_str_radix(&digits, 8) {
+                        Ok(b) => b,
+                        Err(_) => {
+                            return self.err_span(
+                                LexemeError::InvalidEscapeSequence(digits),
+                                start,
+                                end,
+                            );
+                        }
+                    }
+                }
+                'u' | 'U' => {
+                    let len = if e == 'u' { 4 } else { 8 };
+                    let digits = take(&mut it, len, 16);
+                    match u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                    {
+                        Some(c) if digits.len() == len => {
+                            let mut buf = [0; 4];
+                            res.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            continue;
+                        }
+                        _ => {
+                            return self.err_span(
+                                LexemeError::InvalidEscapeSequence(format!("{e}{digits}")),
+                                start,
+                                end,
+                            );
+                        }
+                    }
+                }
+                _ => {
+                    res.push(b'\\');
+                    let mut buf = [0; 4];
+                    res.extend_from_slice(e.encode_utf8(&mut buf).as_bytes());
+                    continue;
+                }
+            };
+            res.push(byte);
+        }
+        Ok((start, Token::Bytes(res), end))
+    }
+
     fn int(&self, s: &str, radix: u32) -> Lexeme {
         let span = self.lexer.span();
         match TokenInt::from_str_radix(s, radix) {
@@ -546,6 +668,19 @@ impl<'a> Lexer<'a> {
                                 Token::FString(_) => {
                                     unreachable!("The lexer does not produce FString")
                                 }
+                                Token::RawBytesDoubleQuote => {
+                                    let raw = self.lexer.span().len() == 3;
+                                    self.parse_double_quoted_string(true)
+                                        .map(|lex| self.bytes(lex, raw))
+                                }
+                                Token::RawBytesSingleQuote => {
+                                    let raw = self.lexer.span().len() == 3;
+                                    self.parse_single_quoted_string(true)
+                                        .map(|lex| self.bytes(lex, raw))
+                                }
+                                Token::Bytes(_) => {
+                                    unreachable!("The lexer does not produce Bytes")
+                                }
                                 Token::OpeningCurly
                                 | Token::OpeningRound
                                 | Token::OpeningSquare => {
@@ -604,6 +739,7 @@ impl<'a> Lexer<'a> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, derive_more::Display)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenInt {
     I32(i32),
     /// Only if larger than `i32`.
@@ -668,6 +804,16 @@ pub enum Token {
     #[token("f\"")]
     #[token("fr\"")]
     RawFStringDoubleQuote,
+    /// The start of a single-quoted bytes literal.
+    #[token("b'")]
+    #[token("br'")]
+    #[token("rb'")]
+    RawBytesSingleQuote,
+    /// The start of a double-quoted bytes literal.
+    #[token("b\"")]
+    #[token("br\"")]
+    #[token("rb\"")]
+    RawBytesDoubleQuote,
 
     #[regex(
         "as|\
@@ -716,6 +862,7 @@ pub enum Token {
     String(String), // A string literal
     /// The raw text of a f-string
     FString(TokenFString),
+    Bytes(Vec<u8>), // A bytes literal
 
     // Keywords
     #[token("and")]
@@ -860,6 +1007,7 @@ impl Token {
                 serde_json::to_writer(&mut buff, &x.content).unwrap();
                 String::from_utf8(buff).unwrap()
             }
+            Token::Bytes(x) => format!("b{x:?}"),
             _ => {
                 let s = self.to_string();
                 // Out display is often: keyword 'lambda'
@@ -954,6 +1102,9 @@ impl Display for Token {
             Token::RawFStringDoubleQuote => write!(f, "starting f'"),
             Token::RawFStringSingleQuote => write!(f, "starting f\""),
             Token::FString(s) => write!(f, "f-string {:?}", &s.content),
+            Token::RawBytesSingleQuote => write!(f, "starting b'"),
+            Token::RawBytesDoubleQuote => write!(f, "starting b\""),
+            Token::Bytes(b) => write!(f, "bytes literal {:?}", String::from_utf8_lossy(b)),
             Token::Comment(c) => write!(f, "comment '{c}'"),
             Token::Tabs => Ok(()),
         }
@@ -271,6 +271,21 @@ fn test_string_escape() {
     );
 }
 
+#[test]
+fn test_bytes_lit() {
+    assert_eq!(
+        lex(r#"b'' b"a" b'\x00\xff' b'\377\1' b'\n\q' b'é' b'\u00e9'"#),
+        "b[] b[97] b[0, 255] b[255, 1] b[10, 92, 113] b[195, 169] b[195, 169] \n"
+    );
+    // Raw bytes keep their backslashes, except before quotes, like raw strings.
+    assert_eq!(
+        lex(r#"rb'\x00' br"\"" b'''a''' rb'\n'"#),
+        "b[92, 120, 48, 48] b[34] b[97] b[92, 110] \n"
+    );
+    // Identifiers starting with `b` are not bytes.
+    assert_eq!(lex("b br rb bx"), "b br rb bx \n");
+}
+
 #[test]
 fn test_simple_example() {
     lexer_golden_test(
@@ -25,6 +25,8 @@
 #![allow(clippy::should_implement_trait)]
 
 pub use crate::error::Error;
+pub use crate::error::ErrorClass;
+pub use crate::error::ErrorCode;
 pub use crate::error::ErrorKind;
 pub use crate::error::StarlarkResultExt;
 
@@ -45,6 +47,7 @@ pub mod golden_test_template;
 pub mod lexer;
 #[cfg(test)]
 mod lexer_tests;
+pub mod message_catalog;
 pub mod slice_vec_ext;
 pub mod span_display;
 pub mod syntax;
@@ -0,0 +1,101 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Override the wording of error messages, for example to localize them.
+//!
+//! A [`MessageCatalog`] maps an [`ErrorCode`] to a template like
+//! `"Variable `{name}` not found"`. Placeholders are replaced with the arguments the error
+//! was created with, which `docs/errors.md` lists for each code, and `{message}` with the
+//! default message. Write `{{` and `}}` for literal braces.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::ErrorCode;
+
+/// Templates for error messages, keyed by error code.
+///
+/// Errors whose code has no template keep their default message.
+pub trait MessageCatalog: Debug + Send + Sync {
+    /// The template for errors with this code, if any.
+    fn template(&self, code: ErrorCode) -> Option<&str>;
+}
+
+impl MessageCatalog for HashMap<ErrorCode, String> {
+    fn template(&self, code: ErrorCode) -> Option<&str> {
+        self.get(&code).map(String::as_str)
+    }
+}
+
+/// Replace the `{name}` placeholders of `template` with the values of `args`.
+///
+/// Unknown placeholders are kept as they are.
+pub fn interpolate(template: &str, args: &[(&str, &str)]) -> String {
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        res.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            res.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('{') {
+            if let Some(end) = rest.find('}') {
+                let name = &rest[1..end];
+                if let Some((_, value)) = args.iter().find(|(k, _)| *k == name) {
+                    res.push_str(value);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        res.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::ErrorCode;
+    use crate::ErrorKind;
+    use crate::message_catalog::interpolate;
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(
+            "Variable `x` introuvable",
+            interpolate("Variable `{name}` introuvable", &[("name", "x")])
+        );
+        assert_eq!("{name} {x}", interpolate("{{name}} {x}", &[("name", "a")]));
+        assert_eq!("a{", interpolate("{name}{", &[("name", "a")]));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let catalog = HashMap::from([(ErrorCode::FAIL, "Echec: {message}".to_owned())]);
+        let e = crate::Error::new_kind(ErrorKind::Fail(anyhow::anyhow!(" oops")));
+        assert_eq!("Echec: fail: oops", e.message_from_catalog(&catalog));
+        let e = crate::Error::new_kind(ErrorKind::Value(anyhow::anyhow!("bad")));
+        assert_eq!("bad", e.message_from_catalog(&catalog));
+    }
+}
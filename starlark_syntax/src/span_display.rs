@@ -15,7 +15,13 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
+use std::env;
+use std::fmt;
 use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use annotate_snippets::display_list::DisplayList;
 use annotate_snippets::display_list::FormatOptions;
@@ -25,60 +31,403 @@ use annotate_snippets::snippet::Slice;
 use annotate_snippets::snippet::Snippet;
 use annotate_snippets::snippet::SourceAnnotation;
 
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
+use crate::codemap::Span;
 use crate::fast_string;
+use crate::message_catalog::MessageCatalog;
+
+/// How file paths are shown in rendered diagnostics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DiagnosticPaths {
+    /// As given when parsing the file.
+    #[default]
+    AsGiven,
+    /// Relative paths are made absolute, against the current directory.
+    Absolute,
+    /// Paths under the directory are made relative to it, others are shown as given.
+    RelativeTo(PathBuf),
+}
+
+impl DiagnosticPaths {
+    /// The path to show for `path`.
+    pub fn render<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        match self {
+            DiagnosticPaths::AsGiven => Cow::Borrowed(path),
+            DiagnosticPaths::Absolute => {
+                if Path::new(path).is_absolute() {
+                    return Cow::Borrowed(path);
+                }
+                match env::current_dir() {
+                    Ok(dir) => Cow::Owned(dir.join(path).to_string_lossy().into_owned()),
+                    Err(_) => Cow::Borrowed(path),
+                }
+            }
+            DiagnosticPaths::RelativeTo(base) => match Path::new(path).strip_prefix(base) {
+                Ok(relative) => Cow::Owned(relative.to_string_lossy().into_owned()),
+                Err(_) => Cow::Borrowed(path),
+            },
+        }
+    }
+}
+
+/// How errors are rendered with their diagnostic information, so that hosts can match
+/// their own output conventions.
+///
+/// The default matches the `Display` implementation of errors: no color, no context lines,
+/// ASCII underlines, paths as given and the whole call stack.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticRenderOptions {
+    /// Use ANSI color codes.
+    pub color: bool,
+    /// Number of lines of source to show before and after the lines of the error.
+    pub context_lines: usize,
+    /// Draw the margin and underlines with unicode box-drawing characters.
+    pub unicode: bool,
+    /// How file paths are shown.
+    pub paths: DiagnosticPaths,
+    /// Show at most this many frames of the call stack, the most recent ones.
+    pub max_stack_frames: Option<usize>,
+    /// Templates overriding the messages of errors, by error code.
+    pub catalog: Option<Arc<dyn MessageCatalog>>,
+}
+
+/// A secondary location of a diagnostic, with a message explaining how it relates to the
+/// error, like "first declared here".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanLabel {
+    /// The location.
+    pub span: FileSpan,
+    /// The message shown next to the location.
+    pub label: String,
+}
 
 /// Gets annotated snippets.
+///
+/// The `annotation_id`, like an error code, is shown next to the severity, as in
+/// `error[E0301]: ...`.
 pub fn span_display<'a>(
     span: Option<FileSpanRef<'a>>,
     annotation_label: &'a str,
+    annotation_id: Option<&'a str>,
     color: bool,
 ) -> impl Display + 'a {
-    fn convert_span_to_slice<'a>(span: FileSpanRef<'a>) -> Slice<'a> {
+    SpanDisplay {
+        span,
+        annotation_label,
+        annotation_id,
+        labels: &[],
+        options: Cow::Owned(DiagnosticRenderOptions {
+            color,
+            ..DiagnosticRenderOptions::default()
+        }),
+    }
+}
+
+/// Like [`span_display`], with secondary `labels` and all the rendering options.
+///
+/// Labels close to the primary span are shown in the same snippet, others in snippets
+/// of their own.
+pub fn span_display_with_options<'a>(
+    span: Option<FileSpanRef<'a>>,
+    annotation_label: &'a str,
+    annotation_id: Option<&'a str>,
+    labels: &'a [SpanLabel],
+    options: &'a DiagnosticRenderOptions,
+) -> impl Display + 'a {
+    SpanDisplay {
+        span,
+        annotation_label,
+        annotation_id,
+        labels,
+        options: Cow::Borrowed(options),
+    }
+}
+
+struct SpanDisplay<'a> {
+    span: Option<FileSpanRef<'a>>,
+    annotation_label: &'a str,
+    annotation_id: Option<&'a str>,
+    labels: &'a [SpanLabel],
+    options: Cow<'a, DiagnosticRenderOptions>,
+}
+
+/// Labels at most this many lines away from a snippet are shown in it.
+///
+/// Labels are annotated as warnings, which are underlined with `-` and, unlike `info`,
+/// have no prefix before their message.
+const MAX_LABEL_GAP_LINES: usize = 5;
+
+/// The annotations to show from one part of a file.
+struct SliceAnnotations<'a> {
+    file: &'a CodeMap,
+    first_line: usize,
+    last_line: usize,
+    annotations: Vec<(Span, &'a str, AnnotationType)>,
+}
+
+impl<'a> SliceAnnotations<'a> {
+    fn new(span: FileSpanRef<'a>, label: &'a str, annotation_type: AnnotationType) -> Self {
         let region = span.resolve_span();
+        SliceAnnotations {
+            file: span.file,
+            first_line: region.begin.line,
+            last_line: region.end.line,
+            annotations: vec![(span.span, label, annotation_type)],
+        }
+    }
 
-        // we want the source_span to capture any whitespace ahead of the diagnostic span to
-        // get the column numbers correct in the DisplayList, and any trailing source code
-        // on the last line for context.
-        let first_line_span = span.file.line_span(region.begin.line);
-        let last_line_span = span.file.line_span(region.end.line);
-        let source_span = span.span.merge(first_line_span).merge(last_line_span);
-        let source = span.file.source_span(source_span);
-
-        // We want to highlight the span, which needs to be relative to source, and in
-        // characters.
-        // Our spans are in terms of bytes, but our resolved spans in terms of characters.
-        let range_start_chars = region.begin.column;
-        let range_len_chars = fast_string::len(span.source_span()).0;
-
-        Slice {
-            source,
-            line_start: 1 + region.begin.line,
-            origin: Some(span.file.filename()),
-            fold: false,
-            annotations: vec![SourceAnnotation {
-                label: "",
+    /// Add the annotation if it is close enough to the others.
+    fn try_add(&mut self, span: FileSpanRef<'a>, label: &'a str) -> bool {
+        if span.file != self.file {
+            return false;
+        }
+        let region = span.resolve_span();
+        if region.begin.line > self.last_line + MAX_LABEL_GAP_LINES
+            || region.end.line + MAX_LABEL_GAP_LINES < self.first_line
+        {
+            return false;
+        }
+        self.first_line = self.first_line.min(region.begin.line);
+        self.last_line = self.last_line.max(region.end.line);
+        self.annotations
+            .push((span.span, label, AnnotationType::Warning));
+        true
+    }
+}
+
+impl Display for SpanDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut slices: Vec<SliceAnnotations> = Vec::new();
+        if let Some(span) = self.span {
+            slices.push(SliceAnnotations::new(span, "", AnnotationType::Error));
+        }
+        for label in self.labels {
+            let span = label.span.as_ref();
+            if !slices.iter_mut().any(|s| s.try_add(span, &label.label)) {
+                slices.push(SliceAnnotations::new(
+                    span,
+                    &label.label,
+                    AnnotationType::Warning,
+                ));
+            }
+        }
+        let origins: Vec<Cow<str>> = slices
+            .iter()
+            .map(|s| self.options.paths.render(s.file.filename()))
+            .collect();
+        let slices = slices
+            .iter()
+            .zip(&origins)
+            .map(|(s, origin)| convert_to_slice(s, origin, &self.options))
+            .collect();
+
+        let snippet = Snippet {
+            title: Some(Annotation {
+                label: Some(self.annotation_label),
+                id: self.annotation_id,
                 annotation_type: AnnotationType::Error,
-                range: (range_start_chars, range_start_chars + range_len_chars),
-            }],
+            }),
+            footer: Vec::new(),
+            slices,
+            opt: FormatOptions {
+                color: self.options.color,
+                ..Default::default()
+            },
+        };
+
+        let display_list = DisplayList::from(snippet);
+        if !self.options.unicode {
+            return write!(f, "{display_list}");
+        }
+        let rendered = display_list.to_string();
+        for (i, line) in rendered.split('\n').enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            f.write_str(&to_unicode(line))?;
         }
+        Ok(())
     }
+}
 
-    let slice = span.map(convert_span_to_slice);
+fn convert_to_slice<'a>(
+    annotations: &SliceAnnotations<'a>,
+    origin: &'a str,
+    options: &DiagnosticRenderOptions,
+) -> Slice<'a> {
+    let file = annotations.file;
 
-    let snippet = Snippet {
-        title: Some(Annotation {
-            label: Some(annotation_label),
-            id: None,
-            annotation_type: AnnotationType::Error,
-        }),
-        footer: Vec::new(),
-        slices: slice.map(|s| vec![s]).unwrap_or_default(),
-        opt: FormatOptions {
-            color,
-            ..Default::default()
-        },
+    // we want the source_span to capture any whitespace ahead of the diagnostic span to
+    // get the column numbers correct in the DisplayList, and any trailing source code
+    // on the last line for context.
+    let first_line = annotations.first_line.saturating_sub(options.context_lines);
+    let first_line_span = file.line_span(first_line);
+    let mut last_line_span = file.line_span(annotations.last_line);
+    for line in annotations.last_line + 1..=annotations.last_line + options.context_lines {
+        match file.line_span_opt(line) {
+            Some(line_span) => last_line_span = line_span,
+            None => break,
+        }
+    }
+    let source_span = annotations
+        .annotations
+        .iter()
+        .fold(first_line_span.merge(last_line_span), |s, (span, _, _)| {
+            s.merge(*span)
+        });
+    let source = file.source_span(source_span);
+
+    // The primary annotation first, since the `-->` location is taken from the first one,
+    // then the labels in order of position.
+    let mut annotations: Vec<_> = annotations.annotations.iter().collect();
+    annotations.sort_by_key(|(span, _, annotation_type)| {
+        (
+            !matches!(annotation_type, AnnotationType::Error),
+            span.begin(),
+        )
+    });
+    // We want to highlight the spans, which need to be relative to source, and in
+    // characters.
+    // Our spans are in terms of bytes, but our resolved spans in terms of characters.
+    let annotations = annotations
+        .into_iter()
+        .map(|(span, label, annotation_type)| {
+            let context_before = file.source_span(Span::new(source_span.begin(), span.begin()));
+            let range_start_chars = fast_string::len(context_before).0;
+            let range_len_chars = fast_string::len(file.source_span(*span)).0;
+            SourceAnnotation {
+                label,
+                annotation_type: *annotation_type,
+                range: (range_start_chars, range_start_chars + range_len_chars),
+            }
+        })
+        .collect();
+
+    Slice {
+        source,
+        line_start: 1 + first_line,
+        origin: Some(origin),
+        fold: false,
+        annotations,
+    }
+}
+
+/// Replace the ASCII margin and underlines of a rendered line with box-drawing characters.
+fn to_unicode(line: &str) -> String {
+    // The visible characters, with their byte offsets, skipping ANSI escape sequences.
+    let mut visible = Vec::new();
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\x1b' {
+            // Sequences end with a letter.
+            for (_, c) in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            visible.push((i, c));
+        }
+    }
+    // The margin is the first `|`, after the line number if any.
+    let Some(margin) = visible.iter().position(|(_, c)| *c == '|') else {
+        return line.to_owned();
     };
+    if !visible[..margin]
+        .iter()
+        .all(|(_, c)| *c == ' ' || c.is_ascii_digit())
+    {
+        return line.to_owned();
+    }
+    let rest = &visible[margin + 1..];
+    let underline =
+        rest.iter().any(|(_, c)| *c == '^') && rest.iter().all(|(_, c)| matches!(c, ' ' | '^'));
 
-    DisplayList::from(snippet)
+    let mut res = String::with_capacity(line.len());
+    let mut copied = 0;
+    for (k, &(i, c)) in visible.iter().enumerate() {
+        let replacement = if k == margin {
+            '│'
+        } else if k > margin && underline && c == '^' {
+            '━'
+        } else {
+            continue;
+        };
+        res.push_str(&line[copied..i]);
+        res.push(replacement);
+        copied = i + c.len_utf8();
+    }
+    res.push_str(&line[copied..]);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codemap::CodeMap;
+    use crate::codemap::Pos;
+    use crate::codemap::Span;
+    use crate::span_display::DiagnosticPaths;
+    use crate::span_display::DiagnosticRenderOptions;
+    use crate::span_display::SpanLabel;
+    use crate::span_display::span_display_with_options;
+
+    fn render(options: &DiagnosticRenderOptions) -> String {
+        let codemap = CodeMap::new(
+            "dir/x.star".to_owned(),
+            "a = 1\nb = c\nd = 2\ne = 3\n".to_owned(),
+        );
+        let span = codemap.file_span(Span::new(Pos::new(10), Pos::new(11)));
+        span_display_with_options(Some(span.as_ref()), "Bad", None, &[], options).to_string()
+    }
+
+    #[test]
+    fn test_default() {
+        assert_eq!(
+            render(&DiagnosticRenderOptions::default()),
+            "error: Bad\n --> dir/x.star:2:5\n  |\n2 | b = c\n  |     ^\n  |"
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let codemap = CodeMap::new("x.star".to_owned(), "a = 1\nb = c\n".to_owned());
+        let span = codemap.file_span(Span::new(Pos::new(10), Pos::new(11)));
+        let labels = [SpanLabel {
+            span: codemap.file_span(Span::new(Pos::new(0), Pos::new(1))),
+            label: "first here".to_owned(),
+        }];
+        let options = DiagnosticRenderOptions::default();
+        assert_eq!(
+            span_display_with_options(Some(span.as_ref()), "Bad", None, &labels, &options)
+                .to_string(),
+            "error: Bad\n --> x.star:2:5\n  |\n1 | a = 1\n  | - first here\n2 | b = c\n  |     ^\n  |"
+        );
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let options = DiagnosticRenderOptions {
+            context_lines: 1,
+            ..DiagnosticRenderOptions::default()
+        };
+        assert_eq!(
+            render(&options),
+            "error: Bad\n --> dir/x.star:2:5\n  |\n1 | a = 1\n2 | b = c\n  |     ^\n3 | d = 2\n  |"
+        );
+    }
+
+    #[test]
+    fn test_unicode_and_paths() {
+        let options = DiagnosticRenderOptions {
+            unicode: true,
+            paths: DiagnosticPaths::RelativeTo("dir".into()),
+            ..DiagnosticRenderOptions::default()
+        };
+        assert_eq!(
+            render(&options),
+            "error: Bad\n --> x.star:2:5\n  │\n2 │ b = c\n  │     ━\n  │"
+        );
+    }
 }
@@ -24,8 +24,12 @@ pub use crate::dialect::Dialect;
 pub use crate::dialect::DialectTypes;
 
 pub mod ast;
+#[cfg(feature = "ast_cache")]
+mod cache;
 pub mod call;
 pub mod def;
+pub mod edit;
+mod format;
 #[cfg(test)]
 mod grammar_tests;
 pub mod grammar_util;
@@ -37,6 +41,7 @@ pub(crate) mod state;
 #[cfg(test)]
 mod testcases;
 pub mod top_level_stmts;
+pub mod trivia;
 pub mod type_expr;
 pub mod uniplate;
 pub mod validate;
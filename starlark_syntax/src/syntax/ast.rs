@@ -52,8 +52,25 @@ impl AstPayload for AstNoPayload {
     type TypeExprPayload = ();
 }
 
+/// Payload which can be serialized with the AST, see `AstModule::to_bytes`.
+#[cfg(feature = "ast_cache")]
+pub trait AstPayloadSerde:
+    AstPayload<
+        LoadPayload: serde::Serialize + serde::de::DeserializeOwned,
+        IdentPayload: serde::Serialize + serde::de::DeserializeOwned,
+        IdentAssignPayload: serde::Serialize + serde::de::DeserializeOwned,
+        DefPayload: serde::Serialize + serde::de::DeserializeOwned,
+        TypeExprPayload: serde::Serialize + serde::de::DeserializeOwned,
+    >
+{
+}
+
+#[cfg(feature = "ast_cache")]
+impl AstPayloadSerde for AstNoPayload {}
+
 /// `,` token.
 #[derive(Copy, Clone, Dupe, Debug)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Comma;
 
 pub type Expr = ExprP<AstNoPayload>;
@@ -87,6 +104,7 @@ pub type AstAssignIdent = AstAssignIdentP<AstNoPayload>;
 pub type AstIdent = AstIdentP<AstNoPayload>;
 pub type AstArgument = AstArgumentP<AstNoPayload>;
 pub type AstString = Spanned<String>;
+pub type AstBytes = Spanned<Vec<u8>>;
 pub type AstParameter = AstParameterP<AstNoPayload>;
 pub type AstInt = Spanned<TokenInt>;
 pub type AstFloat = Spanned<f64>;
@@ -106,6 +124,11 @@ pub trait ToAst: Sized {
 impl<T> ToAst for T {}
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum ArgumentP<P: AstPayload> {
     Positional(AstExprP<P>),
     Named(AstString, AstExprP<P>),
@@ -114,6 +137,11 @@ pub enum ArgumentP<P: AstPayload> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum ParameterP<P: AstPayload> {
     /// `/` marker.
     Slash,
@@ -143,14 +171,21 @@ impl<P: AstPayload> ParameterP<P> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
+    Bytes(AstBytes),
     Ellipsis,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct LambdaP<P: AstPayload> {
     pub params: Vec<AstParameterP<P>>,
     pub body: Box<AstExprP<P>>,
@@ -171,11 +206,21 @@ impl<P: AstPayload> LambdaP<P> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct CallArgsP<P: AstPayload> {
     pub args: Vec<AstArgumentP<P>>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum ExprP<P: AstPayload> {
     Tuple(Vec<AstExprP<P>>),
     Dot(Box<AstExprP<P>>, AstString),
@@ -199,6 +244,8 @@ pub enum ExprP<P: AstPayload> {
     If(Box<(AstExprP<P>, AstExprP<P>, AstExprP<P>)>), // Order: condition, v1, v2 <=> v1 if condition else v2
     List(Vec<AstExprP<P>>),
     Dict(Vec<(AstExprP<P>, AstExprP<P>)>),
+    /// Non-empty set literal, only parsed if the dialect enables it.
+    Set(Vec<AstExprP<P>>),
     ListComprehension(Box<AstExprP<P>>, Box<ForClauseP<P>>, Vec<ClauseP<P>>),
     DictComprehension(
         Box<(AstExprP<P>, AstExprP<P>)>,
@@ -210,6 +257,11 @@ pub enum ExprP<P: AstPayload> {
 
 /// Restricted expression at type position.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct TypeExprP<P: AstPayload> {
     /// Currently it is an expr.
     /// Planning to restrict it.
@@ -220,6 +272,11 @@ pub struct TypeExprP<P: AstPayload> {
 
 /// In some places e.g. AssignModify, the Tuple case is not allowed.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum AssignTargetP<P: AstPayload> {
     // We use Tuple for both Tuple and List,
     // as these have the same semantics in Starlark.
@@ -231,6 +288,11 @@ pub enum AssignTargetP<P: AstPayload> {
 
 /// `x: t = y`.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct AssignP<P: AstPayload> {
     pub lhs: AstAssignTargetP<P>,
     pub ty: Option<AstTypeExprP<P>>,
@@ -239,6 +301,11 @@ pub struct AssignP<P: AstPayload> {
 
 /// Identifier in assign position.
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct AssignIdentP<P: AstPayload> {
     pub ident: String,
     pub payload: P::IdentAssignPayload,
@@ -247,6 +314,11 @@ pub struct AssignIdentP<P: AstPayload> {
 /// Identifier in read position, e. g. `foo` in `[foo.bar]`.
 /// `foo` in `foo = 1` or `bar.foo` are **not** represented by this type.
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct IdentP<P: AstPayload> {
     pub ident: String,
     pub payload: P::IdentPayload,
@@ -254,6 +326,11 @@ pub struct IdentP<P: AstPayload> {
 
 /// Argument of `load` statement.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct LoadArgP<P: AstPayload> {
     /// `x in `x="y"`.
     pub local: AstAssignIdentP<P>,
@@ -279,6 +356,11 @@ impl<P: AstPayload> LoadArgP<P> {
 
 /// `load` statement.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct LoadP<P: AstPayload> {
     pub module: AstString,
     pub args: Vec<LoadArgP<P>>,
@@ -286,18 +368,29 @@ pub struct LoadP<P: AstPayload> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct ForClauseP<P: AstPayload> {
     pub var: AstAssignTargetP<P>,
     pub over: AstExprP<P>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum ClauseP<P: AstPayload> {
     For(ForClauseP<P>),
     If(AstExprP<P>),
 }
 
 #[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinOp {
     Or,
     And,
@@ -323,6 +416,7 @@ pub enum BinOp {
 }
 
 #[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub enum AssignOp {
     Add,         // +=
     Subtract,    // -=
@@ -344,6 +438,11 @@ pub enum Visibility {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct DefP<P: AstPayload> {
     pub name: AstAssignIdentP<P>,
     pub params: Vec<AstParameterP<P>>,
@@ -366,6 +465,11 @@ impl<P: AstPayload> DefP<P> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct ForP<P: AstPayload> {
     pub var: AstAssignTargetP<P>,
     pub over: AstExprP<P>,
@@ -373,6 +477,11 @@ pub struct ForP<P: AstPayload> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub struct FStringP<P: AstPayload> {
     /// A format string containing a `{}` marker for each expression to interpolate.
     pub format: AstString,
@@ -381,6 +490,11 @@ pub struct FStringP<P: AstPayload> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "ast_cache",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "P: AstPayloadSerde")
+)]
 pub enum StmtP<P: AstPayload> {
     Break,
     Continue,
@@ -506,12 +620,29 @@ fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
     f.write_str("\"")
 }
 
+fn fmt_bytes_literal(f: &mut Formatter<'_>, s: &[u8]) -> fmt::Result {
+    f.write_str("b\"")?;
+    for b in s {
+        match b {
+            b'\n' => f.write_str("\\n")?,
+            b'\t' => f.write_str("\\t")?,
+            b'\r' => f.write_str("\\r")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\\' => f.write_str("\\\\")?,
+            0x20..=0x7E => write!(f, "{}", *b as char)?,
+            x => write!(f, "\\x{x:02x}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
 impl Display for AstLiteral {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             AstLiteral::Int(i) => write!(f, "{}", &i.node),
             AstLiteral::Float(n) => write!(f, "{}", &n.node),
             AstLiteral::String(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::Bytes(b) => fmt_bytes_literal(f, &b.node),
             AstLiteral::Ellipsis => f.write_str("..."),
         }
     }
@@ -590,6 +721,11 @@ impl Display for Expr {
                 comma_separated_fmt(f, v, |x, f| write!(f, "{}: {}", x.0.node, x.1.node), false)?;
                 f.write_str("}")
             }
+            Expr::Set(v) => {
+                f.write_str("{")?;
+                comma_separated_fmt(f, v, |x, f| write!(f, "{}", x.node), false)?;
+                f.write_str("}")
+            }
             Expr::ListComprehension(e, for_, c) => {
                 write!(f, "[{}", e.node)?;
                 write!(f, "{for_}")?;
@@ -0,0 +1,199 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Serialized parsed modules, see [`AstModule::to_bytes`].
+
+use crate::codemap::CodeMap;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::lint_suppressions::LintSuppressions;
+
+/// Identifies the format: the crate version, and a hash of the sources of the serialized
+/// types computed by the build script, since the shape of the AST can change without
+/// a version bump, e.g. between builds from git.
+const FORMAT: &str = concat!(
+    "starlark_syntax ",
+    env!("CARGO_PKG_VERSION"),
+    " ",
+    env!("STARLARK_SYNTAX_AST_SCHEMA")
+);
+
+#[derive(Debug, thiserror::Error)]
+enum AstBytesError {
+    #[error("Not a serialized Starlark module")]
+    NotAModule,
+    #[error("Module was serialized by `{0}`, but this is `{FORMAT}`")]
+    Version(String),
+    #[error("Module was parsed with a different dialect")]
+    Dialect,
+    #[error("Serialized module is corrupt: {0}")]
+    Corrupt(postcard::Error),
+}
+
+impl AstModule {
+    /// Serialize the module, to be loaded back with [`from_bytes`](AstModule::from_bytes),
+    /// for example by a later process, without parsing the source again.
+    ///
+    /// The bytes contain the source, so errors and stack traces of the loaded module
+    /// are the same, and are checked for corruption when loaded.
+    ///
+    /// Requires the `ast_cache` feature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = postcard::to_allocvec(&(FORMAT, &self.dialect)).unwrap();
+        // Same layout as the tuple deserialized in `from_bytes`.
+        let body = (
+            self.codemap.filename(),
+            self.codemap.source(),
+            self.typecheck,
+            &self.lint_suppressions,
+            &self.statement,
+        );
+        res.extend(postcard::to_allocvec_crc32(&body, crc32()).unwrap());
+        res
+    }
+
+    /// Load a module serialized by [`to_bytes`](AstModule::to_bytes).
+    ///
+    /// Fails if the bytes were produced by a different version of this crate or by a build
+    /// of it with a different AST, if the module was parsed with a different [`Dialect`],
+    /// or if the bytes are corrupt, in which case the caller should parse the source instead.
+    ///
+    /// ```
+    /// use starlark_syntax::syntax::AstModule;
+    /// use starlark_syntax::syntax::Dialect;
+    ///
+    /// let ast = AstModule::parse("x.star", "x = 1 + 2".to_owned(), &Dialect::Standard).unwrap();
+    /// let bytes = ast.to_bytes();
+    ///
+    /// let ast = AstModule::from_bytes(&bytes, &Dialect::Standard).unwrap();
+    /// assert_eq!(ast.file_span(ast.statement().span).filename(), "x.star");
+    /// assert!(AstModule::from_bytes(&bytes, &Dialect::Extended).is_err());
+    /// ```
+    pub fn from_bytes(bytes: &[u8], dialect: &Dialect) -> crate::Result<AstModule> {
+        Self::from_bytes_impl(bytes, dialect).map_err(crate::Error::new_other)
+    }
+
+    fn from_bytes_impl(bytes: &[u8], dialect: &Dialect) -> Result<AstModule, AstBytesError> {
+        let (format, rest) =
+            postcard::take_from_bytes::<&str>(bytes).map_err(|_| AstBytesError::NotAModule)?;
+        if format != FORMAT {
+            return Err(match format.strip_prefix("starlark_syntax ") {
+                Some(_) => AstBytesError::Version(format.to_owned()),
+                None => AstBytesError::NotAModule,
+            });
+        }
+        let (module_dialect, rest) =
+            postcard::take_from_bytes::<Dialect>(rest).map_err(AstBytesError::Corrupt)?;
+        if &module_dialect != dialect {
+            return Err(AstBytesError::Dialect);
+        }
+        let (filename, source, typecheck, lint_suppressions, statement): (
+            String,
+            String,
+            bool,
+            LintSuppressions,
+            AstStmt,
+        ) = postcard::from_bytes_crc32(rest, crc32()).map_err(AstBytesError::Corrupt)?;
+        Ok(AstModule {
+            codemap: CodeMap::new(filename, source),
+            statement,
+            dialect: module_dialect,
+            typecheck,
+            lint_suppressions,
+        })
+    }
+}
+
+fn crc32() -> crc::Digest<'static, u32> {
+    static CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+    CRC.digest()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn roundtrip(program: &str) {
+        let dialect = Dialect::AllOptionsInternal;
+        let ast = AstModule::parse("x.star", program.to_owned(), &dialect).unwrap();
+        let loaded = AstModule::from_bytes(&ast.to_bytes(), &dialect).unwrap();
+        assert_eq!(
+            format!("{:?}", ast.statement()),
+            format!("{:?}", loaded.statement())
+        );
+        assert_eq!(ast.codemap.source(), loaded.codemap.source());
+        assert_eq!(ast.typecheck, loaded.typecheck);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip("x = 1");
+        roundtrip(
+            r#"
+# @starlark-rust: typecheck
+load("a.star", "b", c = "d")
+def f(x: int, /, y = 100000000000000000000, *args, z: str = "s", **kwargs) -> list[int]:
+    for i, j in [(1, 2.5)]:
+        if i < j: continue
+        elif not i: break
+        else: pass
+    return [x for x in args if x] + list(kwargs) # starlark-lint-disable x
+g = lambda a, *, b: {a: b, "c": {1, 2}}[a:b:1] if ~a else f"{a}"
+g.h[0] += b"bytes"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_lint_suppressions() {
+        let program = "def f():\n    # starlark-lint-disable unused\n    x = 1\n";
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+        let loaded = AstModule::from_bytes(&ast.to_bytes(), &Dialect::Standard).unwrap();
+        let span = loaded.statement().span;
+        assert!(ast.is_suppressed("unused", span));
+        assert!(loaded.is_suppressed("unused", span));
+        assert!(!loaded.is_suppressed("other", span));
+    }
+
+    #[test]
+    fn test_rejected() {
+        let ast = AstModule::parse("x.star", "x = 1".to_owned(), &Dialect::Standard).unwrap();
+        let bytes = ast.to_bytes();
+        let err = |bytes: &[u8], dialect| {
+            AstModule::from_bytes(bytes, dialect)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(err(&bytes, &Dialect::Extended).contains("different dialect"));
+        assert!(err(b"x = 1", &Dialect::Standard).contains("Not a serialized"));
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(err(&corrupt, &Dialect::Standard).contains("corrupt"));
+        assert!(err(&bytes[..bytes.len() - 1], &Dialect::Standard).contains("corrupt"));
+
+        let old = postcard::to_allocvec(&("starlark_syntax 0.0.1", &Dialect::Standard)).unwrap();
+        assert!(err(&old, &Dialect::Standard).contains("`starlark_syntax 0.0.1`"));
+
+        let other_ast = concat!("starlark_syntax ", env!("CARGO_PKG_VERSION"), " 0");
+        let other = postcard::to_allocvec(&(other_ast, &Dialect::Standard)).unwrap();
+        assert!(err(&other, &Dialect::Standard).contains(other_ast));
+    }
+}
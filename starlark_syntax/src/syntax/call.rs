@@ -15,7 +15,7 @@
  * limitations under the License.
  */
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use crate::codemap::CodeMap;
 use crate::codemap::Span;
@@ -48,7 +48,7 @@ impl<'a, P: AstPayload> CallArgsUnpack<'a, P> {
         let args = &args.args;
 
         let mut stage = ArgsStage::Positional;
-        let mut named_args = HashSet::new();
+        let mut named_args = HashMap::new();
         let mut num_pos = 0;
         let mut num_named = 0;
         let mut star = None;
@@ -65,9 +65,14 @@ impl<'a, P: AstPayload> CallArgsUnpack<'a, P> {
                 ArgumentP::Named(n, _) => {
                     if stage > ArgsStage::Named {
                         return err(arg.span, "named argument after *args or **kwargs");
-                    } else if !named_args.insert(&n.node) {
+                    } else if let Some(first) = named_args.insert(&n.node, n.span) {
                         // Check the names are distinct
-                        return err(n.span, "repeated named argument");
+                        return Err(EvalException::parser_error(
+                            "repeated named argument",
+                            n.span,
+                            codemap,
+                        )
+                        .with_label(first, codemap, "first given here"));
                     } else {
                         stage = ArgsStage::Named;
                         num_named += 1;
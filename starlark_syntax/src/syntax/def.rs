@@ -15,7 +15,7 @@
  * limitations under the License.
  */
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::ops::Range;
 
 use allocative::Allocative;
@@ -108,17 +108,19 @@ pub struct DefParams<'a, P: AstPayload> {
 }
 
 fn check_param_name<'a, P: AstPayload, T>(
-    argset: &mut HashSet<&'a str>,
+    argset: &mut HashMap<&'a str, Span>,
     n: &'a AstAssignIdentP<P>,
     arg: &Spanned<T>,
     codemap: &CodeMap,
 ) -> Result<(), EvalException> {
-    if !argset.insert(n.node.ident.as_str()) {
-        return Err(EvalException::parser_error(
-            "duplicated parameter name",
-            arg.span,
-            codemap,
-        ));
+    if let Some(first) = argset.insert(n.node.ident.as_str(), n.span) {
+        return Err(
+            EvalException::parser_error("duplicated parameter name", arg.span, codemap).with_label(
+                first,
+                codemap,
+                "first declared here",
+            ),
+        );
     }
     Ok(())
 }
@@ -140,7 +142,7 @@ impl<'a, P: AstPayload> DefParams<'a, P> {
         }
 
         // you can't repeat argument names
-        let mut argset = HashSet::new();
+        let mut argset = HashMap::new();
         // You can't have more than one *args/*, **kwargs
         // **kwargs must be last
         // You can't have a required `x` after an optional `y=1`
@@ -0,0 +1,190 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rewriting the source of a module, for automated migrations.
+//!
+//! Find the nodes to change with [`AstModule::visit_exprs`], describe each change as an
+//! [`Edit`] of the original source, and apply them all with [`AstModule::apply_edits`].
+//! Everything outside the edited spans is kept byte for byte.
+//!
+//! ```
+//! use starlark_syntax::syntax::AstModule;
+//! use starlark_syntax::syntax::Dialect;
+//! use starlark_syntax::syntax::ast::Expr;
+//! use starlark_syntax::syntax::edit::Edit;
+//!
+//! let program = "x = old_fn(1)  # Keep me.\ny = old_fn(old_fn(2))\n";
+//! let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+//! let mut edits = Vec::new();
+//! ast.visit_exprs(|x| {
+//!     if let Expr::Call(f, _) = &x.node {
+//!         if let Expr::Identifier(name) = &f.node {
+//!             if name.node.ident == "old_fn" {
+//!                 edits.push(Edit::replace(f.span, "new_fn"));
+//!             }
+//!         }
+//!     }
+//! });
+//! assert_eq!(
+//!     ast.apply_edits(edits).unwrap(),
+//!     "x = new_fn(1)  # Keep me.\ny = new_fn(new_fn(2))\n"
+//! );
+//! ```
+
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::error::ErrorKind;
+use crate::syntax::AstModule;
+use crate::syntax::ast::AstExpr;
+
+/// A replacement of the source text in a span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The span of the original source to replace.
+    pub span: Span,
+    /// The text to put in its place.
+    pub replacement: String,
+}
+
+impl Edit {
+    /// Replace the text in `span` with `replacement`.
+    pub fn replace(span: Span, replacement: impl Into<String>) -> Edit {
+        Edit {
+            span,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Delete the text in `span`.
+    pub fn delete(span: Span) -> Edit {
+        Edit::replace(span, "")
+    }
+
+    /// Insert `text` at `pos`.
+    pub fn insert(pos: Pos, text: impl Into<String>) -> Edit {
+        Edit::replace(Span::new(pos, pos), text)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum EditError {
+    #[error("Edits overlap, so they can't both be applied")]
+    Overlap,
+    #[error("Edit span is not within the source, or splits a character")]
+    OutOfBounds,
+}
+
+impl AstModule {
+    /// Call `f` on every expression in the module, including nested ones, parents before
+    /// their children.
+    pub fn visit_exprs<'a>(&'a self, mut f: impl FnMut(&'a AstExpr)) {
+        fn go<'a>(x: &'a AstExpr, f: &mut impl FnMut(&'a AstExpr)) {
+            f(x);
+            x.visit_expr(|x| go(x, f));
+        }
+        self.statement.visit_expr(|x| go(x, &mut f));
+    }
+
+    /// The original source text of a span, for example to build a replacement from.
+    pub fn source_span(&self, span: Span) -> &str {
+        self.codemap.source_span(span)
+    }
+
+    /// Apply the edits, in any order, to the original source of the module.
+    ///
+    /// Edits must not overlap, although several insertions at the same position are allowed,
+    /// and applied in the order given. The result is not parsed again, so the edits are
+    /// responsible for producing valid code.
+    pub fn apply_edits(&self, mut edits: Vec<Edit>) -> crate::Result<String> {
+        let source = self.codemap.source();
+        // Stable, so insertions at the same position keep their order.
+        edits.sort_by_key(|x| (x.span.begin(), x.span.end()));
+
+        let mut res = String::with_capacity(source.len());
+        let mut pos = 0;
+        for edit in &edits {
+            let begin = edit.span.begin().get() as usize;
+            let end = edit.span.end().get() as usize;
+            if end > source.len()
+                || !source.is_char_boundary(begin)
+                || !source.is_char_boundary(end)
+            {
+                return Err(crate::Error::new_kind(ErrorKind::Other(
+                    EditError::OutOfBounds.into(),
+                )));
+            }
+            if begin < pos {
+                return Err(crate::Error::new_spanned(
+                    ErrorKind::Other(EditError::Overlap.into()),
+                    edit.span,
+                    &self.codemap,
+                ));
+            }
+            res.push_str(&source[pos..begin]);
+            res.push_str(&edit.replacement);
+            pos = end;
+        }
+        res.push_str(&source[pos..]);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codemap::Pos;
+    use crate::codemap::Span;
+    use crate::syntax::ast::Expr;
+    use crate::syntax::edit::Edit;
+    use crate::syntax::grammar_tests;
+
+    #[test]
+    fn test_apply_edits() {
+        let ast = grammar_tests::parse_ast("load('a', 'b')\n\nx = [1, 2]  # List.\n");
+        let mut literals = Vec::new();
+        ast.visit_exprs(|x| {
+            if let Expr::Literal(_) = &x.node {
+                literals.push(x.span);
+            }
+        });
+        // Both load arguments are `AstString`s, not expressions.
+        assert_eq!(literals.len(), 2);
+        let edits = vec![
+            Edit::replace(literals[1], "20"),
+            Edit::delete(literals[0]),
+            Edit::insert(literals[0].begin(), "10"),
+            Edit::insert(Pos::new(0), "# Migrated.\n"),
+        ];
+        assert_eq!(
+            ast.apply_edits(edits).unwrap(),
+            "# Migrated.\nload('a', 'b')\n\nx = [10, 20]  # List.\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_edits_errors() {
+        let ast = grammar_tests::parse_ast("x = 'é'\n");
+        let overlap = vec![
+            Edit::replace(Span::new(Pos::new(0), Pos::new(3)), "y"),
+            Edit::replace(Span::new(Pos::new(2), Pos::new(4)), "z"),
+        ];
+        assert!(ast.apply_edits(overlap).is_err());
+        let split_char = vec![Edit::delete(Span::new(Pos::new(5), Pos::new(6)))];
+        assert!(ast.apply_edits(split_char).is_err());
+        let out_of_bounds = vec![Edit::delete(Span::new(Pos::new(0), Pos::new(100)))];
+        assert!(ast.apply_edits(out_of_bounds).is_err());
+    }
+}
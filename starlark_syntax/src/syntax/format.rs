@@ -0,0 +1,973 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Pretty-printer turning an [`AstModule`] back into source code.
+//!
+//! The layout is canonical (four space indentation, one element per line for
+//! collections which do not fit on a line or were written across several lines),
+//! but comments are preserved: full-line comments stay in front of the statement
+//! or collection element they precede, and end-of-line comments stay at the end
+//! of the line they were written on.
+
+use std::mem;
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::dialect::Dialect;
+use crate::internal_error;
+use crate::syntax::AstModule;
+use crate::syntax::ast::Argument;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AssignTarget;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssignTarget;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstNoPayload;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::AstString;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::DefP;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::ForP;
+use crate::syntax::ast::LambdaP;
+use crate::syntax::ast::LoadArgP;
+use crate::syntax::ast::Parameter;
+use crate::syntax::ast::Stmt;
+use crate::syntax::trivia::TriviaKind;
+use crate::syntax::trivia::collect_trivia;
+
+const INDENT: &str = "    ";
+
+/// Collections longer than this are split one element per line.
+const MAX_LINE_WIDTH: usize = 100;
+
+impl AstModule {
+    /// Render the module as source code in the canonical layout, preserving comments.
+    ///
+    /// Formatting never changes the meaning of the code: the result is parsed again
+    /// and compared with the original, and an internal error is returned on mismatch.
+    pub fn format(&self) -> crate::Result<String> {
+        let mut printer = Printer::new(&self.codemap, &self.dialect);
+        printer.module(&self.statement);
+        let formatted = printer.out;
+
+        let reparsed = AstModule::parse(self.codemap.filename(), formatted.clone(), &self.dialect)
+            .map_err(|e| internal_error!("Formatted code does not parse: {}", e))?;
+        if reparsed.statement.to_string() != self.statement.to_string() {
+            return Err(internal_error!(
+                "Formatting changed the meaning of `{}`",
+                self.codemap.filename()
+            ));
+        }
+        Ok(formatted)
+    }
+}
+
+#[derive(Clone)]
+struct Comment {
+    /// Position of the `#`.
+    pos: Pos,
+    /// Zero-based line number.
+    line: usize,
+    /// Byte offset of the `#` within its line.
+    column: usize,
+    /// Nothing but whitespace precedes the comment on its line.
+    own_line: bool,
+    /// Comment text, including the `#`, without trailing whitespace.
+    text: String,
+}
+
+fn collect_comments(codemap: &CodeMap, dialect: &Dialect) -> Vec<Comment> {
+    collect_trivia(codemap, dialect)
+        .into_iter()
+        .filter_map(|x| match x.kind {
+            TriviaKind::Comment { text, own_line } => {
+                let pos = x.span.begin();
+                let line = codemap.find_line(pos);
+                Some(Comment {
+                    pos,
+                    line,
+                    column: (pos.get() - codemap.line_span(line).begin().get()) as usize,
+                    own_line,
+                    text: format!("#{}", text.trim_end()),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Operator precedence, mirroring the structure of the grammar.
+/// An expression is parenthesized when placed in a position requiring higher precedence.
+#[derive(Copy, Clone, Dupe, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// `lambda`, `x if c else y`.
+    Test,
+    Or,
+    And,
+    Not,
+    Compare,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Arith,
+    Product,
+    /// Unary `-`, `+`, `~`.
+    Unary,
+    /// Calls, attributes, indexing and atoms.
+    Primary,
+}
+
+impl Prec {
+    /// Precedence of the operator, and the precedences required of its left and right operands.
+    fn bin_op(op: BinOp) -> (Prec, Prec, Prec) {
+        match op {
+            BinOp::Or => (Prec::Or, Prec::Or, Prec::And),
+            BinOp::And => (Prec::And, Prec::And, Prec::Not),
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Less
+            | BinOp::Greater
+            | BinOp::LessOrEqual
+            | BinOp::GreaterOrEqual
+            | BinOp::In
+            | BinOp::NotIn => (Prec::Compare, Prec::BitOr, Prec::BitOr),
+            BinOp::BitOr => (Prec::BitOr, Prec::BitOr, Prec::BitXor),
+            BinOp::BitXor => (Prec::BitXor, Prec::BitXor, Prec::BitAnd),
+            BinOp::BitAnd => (Prec::BitAnd, Prec::BitAnd, Prec::Shift),
+            BinOp::LeftShift | BinOp::RightShift => (Prec::Shift, Prec::Shift, Prec::Arith),
+            BinOp::Add | BinOp::Subtract => (Prec::Arith, Prec::Arith, Prec::Product),
+            BinOp::Multiply | BinOp::Percent | BinOp::Divide | BinOp::FloorDivide => {
+                (Prec::Product, Prec::Product, Prec::Unary)
+            }
+        }
+    }
+
+    fn of(x: &Expr) -> Prec {
+        match x {
+            Expr::Lambda(_) | Expr::If(_) => Prec::Test,
+            Expr::Op(_, op, _) => Prec::bin_op(*op).0,
+            Expr::Not(_) => Prec::Not,
+            Expr::Minus(_) | Expr::Plus(_) | Expr::BitNot(_) => Prec::Unary,
+            _ => Prec::Primary,
+        }
+    }
+}
+
+/// An element of a bracketed sequence.
+enum Item<'a> {
+    Expr(&'a AstExpr),
+    Arg(&'a AstArgument),
+    Param(&'a AstParameter),
+    Entry(&'a AstExpr, &'a AstExpr),
+    Str(&'a AstString),
+    LoadArg(&'a LoadArgP<AstNoPayload>),
+    For(&'a ForClause),
+    If(&'a AstExpr),
+}
+
+impl Item<'_> {
+    fn span(&self) -> Span {
+        match self {
+            Item::Expr(x) | Item::If(x) => x.span,
+            Item::Arg(x) => x.span,
+            Item::Param(x) => x.span,
+            Item::Entry(k, v) => k.span.merge(v.span),
+            Item::Str(x) => x.span,
+            Item::LoadArg(x) => x.span(),
+            Item::For(x) => x.var.span.merge(x.over.span),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Dupe, PartialEq, Eq)]
+enum Separator {
+    Comma,
+    /// Like `Comma`, but a single element is followed by a comma, as in `(x,)`.
+    TupleComma,
+    /// Comprehension clauses.
+    Space,
+}
+
+struct Printer<'a> {
+    codemap: &'a CodeMap,
+    comments: Vec<Comment>,
+    /// Index of the first comment not yet written.
+    next_comment: usize,
+    out: String,
+    /// Last source line written, used to preserve blank lines.
+    last_line: Option<usize>,
+    /// Just written a block header, so blank lines are dropped.
+    block_start: bool,
+    /// Rendering to measure the width, so nothing is split over lines and no comments are taken.
+    flat: bool,
+}
+
+fn push_indent(s: &mut String, indent: usize) {
+    for _ in 0..indent {
+        s.push_str(INDENT);
+    }
+}
+
+fn flatten<'b>(x: &'b AstStmt, res: &mut Vec<&'b AstStmt>) {
+    match &x.node {
+        Stmt::Statements(xs) => {
+            for x in xs {
+                flatten(x, res);
+            }
+        }
+        _ => res.push(x),
+    }
+}
+
+impl<'a> Printer<'a> {
+    fn new(codemap: &'a CodeMap, dialect: &Dialect) -> Self {
+        Printer {
+            codemap,
+            comments: collect_comments(codemap, dialect),
+            next_comment: 0,
+            out: String::new(),
+            last_line: None,
+            block_start: false,
+            flat: false,
+        }
+    }
+
+    fn module(&mut self, stmt: &AstStmt) {
+        let end = self.codemap.full_span().end();
+        self.block(stmt, 0, end, None);
+        // Whatever remains are the comments at the end of the file.
+        self.leading_comments(end, 0);
+    }
+
+    fn line_of(&self, pos: Pos) -> usize {
+        self.codemap.find_line(pos)
+    }
+
+    fn column_of(&self, pos: Pos) -> usize {
+        let line = self.line_of(pos);
+        (pos.get() - self.codemap.line_span(line).begin().get()) as usize
+    }
+
+    /// Skip whitespace, line continuations and comments.
+    fn skip_trivia(&self, pos: Pos) -> Pos {
+        let src = self.codemap.source().as_bytes();
+        let mut i = pos.get() as usize;
+        while i < src.len() {
+            match src[i] {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\\' => i += 1,
+                b'#' => {
+                    while i < src.len() && src[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        Pos::new(i as u32)
+    }
+
+    /// Find the closing bracket `c` after the last element of a sequence,
+    /// skipping the trailing comma and any comments.
+    fn find_close(&self, pos: Pos, c: u8) -> Pos {
+        let src = self.codemap.source().as_bytes();
+        let mut i = self.skip_trivia(pos).get() as usize;
+        while i < src.len() && src[i] != c {
+            i = self.skip_trivia(Pos::new(i as u32 + 1)).get() as usize;
+        }
+        Pos::new(i as u32)
+    }
+
+    fn has_newline(&self, begin: Pos, end: Pos) -> bool {
+        begin < end
+            && self
+                .codemap
+                .source_span(Span::new(begin, end))
+                .contains('\n')
+    }
+
+    /// Write a blank line if the source had one before `line`.
+    fn separate(&mut self, line: usize, top_level: bool) {
+        if let Some(last) = self.last_line {
+            if !self.block_start {
+                let blank = line.saturating_sub(last + 1);
+                let max = if top_level { 2 } else { 1 };
+                for _ in 0..blank.min(max) {
+                    self.out.push('\n');
+                }
+            }
+        }
+        self.block_start = false;
+    }
+
+    /// Write all comments before `before` on their own lines.
+    fn leading_comments(&mut self, before: Pos, indent: usize) {
+        while let Some(c) = self.take_comment(|c| c.pos < before) {
+            self.write_comment(c, indent);
+        }
+    }
+
+    fn take_comment(&mut self, pred: impl Fn(&Comment) -> bool) -> Option<Comment> {
+        let c = self
+            .comments
+            .get(self.next_comment)
+            .filter(|&c| pred(c))?
+            .clone();
+        self.next_comment += 1;
+        Some(c)
+    }
+
+    fn write_comment(&mut self, c: Comment, indent: usize) {
+        self.separate(c.line, indent == 0);
+        push_indent(&mut self.out, indent);
+        self.out.push_str(&c.text);
+        self.out.push('\n');
+        self.last_line = Some(c.line);
+    }
+
+    /// Append the end-of-line comments which occur before `before`.
+    fn trailing_comments(&mut self, res: &mut String, before: Pos) {
+        if self.flat {
+            return;
+        }
+        while let Some(c) = self.take_comment(|c| c.pos < before && !c.own_line) {
+            res.push_str("  ");
+            res.push_str(&c.text);
+        }
+    }
+
+    /// Like `leading_comments`, but for comments inside a bracketed sequence.
+    fn inner_comments(&mut self, res: &mut String, before: Pos, indent: usize) {
+        while let Some(c) = self.take_comment(|c| c.pos < before) {
+            push_indent(res, indent);
+            res.push_str(&c.text);
+            res.push('\n');
+        }
+    }
+
+    /// Write a sequence of statements.
+    ///
+    /// `limit` is where the next statement after this block starts, and `parent_column`
+    /// is the column of the statement owning a nested block: comments after the last
+    /// statement which are indented deeper than the parent belong to this block.
+    fn block(&mut self, body: &AstStmt, indent: usize, limit: Pos, parent_column: Option<usize>) {
+        let mut stmts = Vec::new();
+        flatten(body, &mut stmts);
+        for (i, stmt) in stmts.iter().enumerate() {
+            let next = stmts.get(i + 1).map_or(limit, |x| x.span.begin());
+            self.leading_comments(stmt.span.begin(), indent);
+            self.stmt(stmt, indent, next);
+        }
+        if let Some(parent_column) = parent_column {
+            while let Some(c) = self.take_comment(|c| c.pos < limit && c.column > parent_column) {
+                self.write_comment(c, indent);
+            }
+        }
+    }
+
+    fn begin_stmt(&mut self, stmt: &AstStmt, indent: usize) {
+        let line = self.line_of(stmt.span.begin());
+        self.separate(line, indent == 0);
+        push_indent(&mut self.out, indent);
+    }
+
+    /// Write a line ending with `:`, the body follows.
+    fn header(&mut self, mut header: String, body: &AstStmt) {
+        self.trailing_comments(&mut header, body.span.begin());
+        self.out.push_str(&header);
+        self.out.push('\n');
+        self.block_start = true;
+    }
+
+    fn stmt(&mut self, stmt: &AstStmt, indent: usize, limit: Pos) {
+        let column = self.column_of(stmt.span.begin());
+        match &stmt.node {
+            Stmt::Statements(xs) => {
+                for x in xs {
+                    self.stmt(x, indent, limit);
+                }
+            }
+            Stmt::If(cond, body) => {
+                let header = format!("if {}:", self.expr(cond, Prec::Test, indent));
+                self.begin_stmt(stmt, indent);
+                self.header(header, body);
+                self.block(body, indent + 1, limit, Some(column));
+            }
+            Stmt::IfElse(cond, bodies) => {
+                let header = format!("if {}:", self.expr(cond, Prec::Test, indent));
+                self.begin_stmt(stmt, indent);
+                self.header(header, &bodies.0);
+                self.else_branches(&bodies.0, &bodies.1, indent, limit, column);
+            }
+            Stmt::For(ForP { var, over, body }) => {
+                let header = format!(
+                    "for {} in {}:",
+                    self.target(var, true),
+                    self.expr(over, Prec::Test, indent)
+                );
+                self.begin_stmt(stmt, indent);
+                self.header(header, body);
+                self.block(body, indent + 1, limit, Some(column));
+            }
+            Stmt::Def(def) => {
+                let header = self.def_header(def, indent);
+                self.begin_stmt(stmt, indent);
+                self.header(header, &def.body);
+                self.block(&def.body, indent + 1, limit, Some(column));
+            }
+            _ => {
+                let mut text = self.simple_stmt(stmt, indent);
+                self.trailing_comments(&mut text, limit);
+                self.begin_stmt(stmt, indent);
+                self.out.push_str(&text);
+                self.out.push('\n');
+                self.last_line = Some(self.line_of(stmt.span.end()));
+            }
+        }
+    }
+
+    /// Write the `elif`/`else` part of an `if` statement whose `then` block has
+    /// already had its header written.
+    fn else_branches(
+        &mut self,
+        then: &AstStmt,
+        else_: &AstStmt,
+        indent: usize,
+        limit: Pos,
+        column: usize,
+    ) {
+        let else_pos = self.skip_trivia(then.span.end());
+        self.block(then, indent + 1, else_pos, Some(column));
+        self.leading_comments(else_pos, indent);
+
+        let is_elif = self.codemap.source()[else_pos.get() as usize..].starts_with("elif");
+        match &else_.node {
+            Stmt::If(cond, body) if is_elif => {
+                let header = format!("elif {}:", self.expr(cond, Prec::Test, indent));
+                self.separate(self.line_of(else_pos), indent == 0);
+                push_indent(&mut self.out, indent);
+                self.header(header, body);
+                self.block(body, indent + 1, limit, Some(column));
+            }
+            Stmt::IfElse(cond, bodies) if is_elif => {
+                let header = format!("elif {}:", self.expr(cond, Prec::Test, indent));
+                self.separate(self.line_of(else_pos), indent == 0);
+                push_indent(&mut self.out, indent);
+                self.header(header, &bodies.0);
+                self.else_branches(&bodies.0, &bodies.1, indent, limit, column);
+            }
+            _ => {
+                self.separate(self.line_of(else_pos), indent == 0);
+                push_indent(&mut self.out, indent);
+                self.header("else:".to_owned(), else_);
+                self.block(else_, indent + 1, limit, Some(column));
+            }
+        }
+    }
+
+    fn def_header(&mut self, def: &DefP<AstNoPayload>, indent: usize) -> String {
+        let open = self.skip_trivia(def.name.span.end());
+        let close = match def.params.last() {
+            Some(last) => self.find_close(last.span.end(), b')'),
+            None => self.find_close(open + 1, b')'),
+        };
+        let items: Vec<Item> = def.params.iter().map(Item::Param).collect();
+        let mut res = format!("def {}", def.name.node.ident);
+        res.push_str(&self.collection(
+            "(",
+            &items,
+            ")",
+            Span::new(open + 1, close),
+            Separator::Comma,
+            indent,
+        ));
+        if let Some(return_type) = &def.return_type {
+            res.push_str(" -> ");
+            res.push_str(&self.expr(&return_type.node.expr, Prec::Test, indent));
+        }
+        res.push(':');
+        res
+    }
+
+    fn simple_stmt(&mut self, stmt: &AstStmt, indent: usize) -> String {
+        match &stmt.node {
+            Stmt::Break => "break".to_owned(),
+            Stmt::Continue => "continue".to_owned(),
+            Stmt::Pass => "pass".to_owned(),
+            Stmt::Return(None) => "return".to_owned(),
+            Stmt::Return(Some(e)) => format!("return {}", self.expr_top(e, indent)),
+            Stmt::Expression(e) => self.expr_top(e, indent),
+            Stmt::Assign(AssignP { lhs, ty, rhs }) => {
+                let mut res = self.target(lhs, true);
+                if let Some(ty) = ty {
+                    res.push_str(": ");
+                    res.push_str(&self.expr(&ty.node.expr, Prec::Test, indent));
+                }
+                res.push_str(" = ");
+                res.push_str(&self.expr_top(rhs, indent));
+                res
+            }
+            Stmt::AssignModify(lhs, op, rhs) => {
+                format!(
+                    "{}{}{}",
+                    self.target(lhs, true),
+                    op,
+                    self.expr_top(rhs, indent)
+                )
+            }
+            Stmt::Load(load) => {
+                let open = self.skip_trivia(stmt.span.begin() + "load".len() as u32);
+                let mut items = vec![Item::Str(&load.module)];
+                items.extend(load.args.iter().map(Item::LoadArg));
+                let inner = Span::new(open + 1, stmt.span.end() - 1);
+                format!(
+                    "load{}",
+                    self.collection("(", &items, ")", inner, Separator::Comma, indent)
+                )
+            }
+            Stmt::Statements(_) | Stmt::If(..) | Stmt::IfElse(..) | Stmt::For(_) | Stmt::Def(_) => {
+                unreachable!("not a simple statement")
+            }
+        }
+    }
+
+    /// Render a bracketed sequence, either on one line or one element per line.
+    ///
+    /// `inner` is the source between the brackets, used to find where the original
+    /// code had line breaks and to pick up comments.
+    fn collection(
+        &mut self,
+        open: &str,
+        items: &[Item<'_>],
+        close: &str,
+        inner: Span,
+        sep: Separator,
+        indent: usize,
+    ) -> String {
+        if items.is_empty() {
+            let has_comments = self.comments[self.next_comment..]
+                .first()
+                .is_some_and(|c| c.pos >= inner.begin() && c.pos < inner.end());
+            if self.flat || !has_comments {
+                return format!("{open}{close}");
+            }
+        }
+
+        let multiline = !self.flat && {
+            let mut gap_begin = inner.begin();
+            let mut newline = false;
+            for item in items {
+                newline |= self.has_newline(gap_begin, item.span().begin());
+                gap_begin = item.span().end();
+            }
+            newline |= self.has_newline(gap_begin, inner.end());
+            newline || {
+                let flat = mem::replace(&mut self.flat, true);
+                let text = self.collection_flat(open, items, close, sep, indent);
+                self.flat = flat;
+                (indent * INDENT.len() + text.len()) > MAX_LINE_WIDTH
+            }
+        };
+
+        if !multiline {
+            return self.collection_flat(open, items, close, sep, indent);
+        }
+
+        let mut res = open.to_owned();
+        let first = items.first().map_or(inner.end(), |x| x.span().begin());
+        self.trailing_comments(&mut res, first);
+        res.push('\n');
+        for (i, item) in items.iter().enumerate() {
+            self.inner_comments(&mut res, item.span().begin(), indent + 1);
+            push_indent(&mut res, indent + 1);
+            res.push_str(&self.item(item, indent + 1));
+            if sep != Separator::Space {
+                res.push(',');
+            }
+            let next = items.get(i + 1).map_or(inner.end(), |x| x.span().begin());
+            self.trailing_comments(&mut res, next);
+            res.push('\n');
+        }
+        self.inner_comments(&mut res, inner.end(), indent + 1);
+        push_indent(&mut res, indent);
+        res.push_str(close);
+        res
+    }
+
+    fn collection_flat(
+        &mut self,
+        open: &str,
+        items: &[Item<'_>],
+        close: &str,
+        sep: Separator,
+        indent: usize,
+    ) -> String {
+        let mut res = open.to_owned();
+        for (i, item) in items.iter().enumerate() {
+            if i != 0 {
+                res.push_str(if sep == Separator::Space { " " } else { ", " });
+            }
+            res.push_str(&self.item(item, indent));
+        }
+        if sep == Separator::TupleComma && items.len() == 1 {
+            res.push(',');
+        }
+        res.push_str(close);
+        res
+    }
+
+    fn item(&mut self, item: &Item<'_>, indent: usize) -> String {
+        match item {
+            Item::Expr(x) => self.expr(x, Prec::Test, indent),
+            Item::Arg(x) => match &x.node {
+                Argument::Positional(e) => self.expr(e, Prec::Test, indent),
+                Argument::Named(name, e) => {
+                    format!("{} = {}", name.node, self.expr(e, Prec::Test, indent))
+                }
+                Argument::Args(e) => format!("*{}", self.expr(e, Prec::Test, indent)),
+                Argument::KwArgs(e) => format!("**{}", self.expr(e, Prec::Test, indent)),
+            },
+            Item::Param(x) => self.param(x, indent),
+            Item::Entry(k, v) => format!(
+                "{}: {}",
+                self.expr(k, Prec::Test, indent),
+                self.expr(v, Prec::Test, indent)
+            ),
+            Item::Str(x) => self.string_literal(x.span),
+            Item::LoadArg(x) => {
+                let their = self.string_literal(x.their.span);
+                if x.local.node.ident == x.their.node {
+                    their
+                } else {
+                    format!("{} = {}", x.local.node.ident, their)
+                }
+            }
+            Item::For(x) => format!(
+                "for {} in {}",
+                self.target(&x.var, true),
+                self.expr(&x.over, Prec::Or, indent)
+            ),
+            Item::If(x) => format!("if {}", self.expr(x, Prec::Or, indent)),
+        }
+    }
+
+    fn param(&mut self, x: &AstParameter, indent: usize) -> String {
+        let (prefix, name, ty, default) = match &x.node {
+            Parameter::Slash => return "/".to_owned(),
+            Parameter::NoArgs => return "*".to_owned(),
+            Parameter::Normal(name, ty, default) => ("", name, ty, default.as_deref()),
+            Parameter::Args(name, ty) => ("*", name, ty, None),
+            Parameter::KwArgs(name, ty) => ("**", name, ty, None),
+        };
+        let mut res = format!("{}{}", prefix, name.node.ident);
+        if let Some(ty) = ty {
+            res.push_str(": ");
+            res.push_str(&self.expr(&ty.node.expr, Prec::Test, indent));
+        }
+        if let Some(default) = default {
+            res.push_str(" = ");
+            res.push_str(&self.expr(default, Prec::Test, indent));
+        }
+        res
+    }
+
+    /// Original literal text, with single quotes replaced by double quotes where
+    /// that does not require any escaping.
+    fn string_literal(&self, span: Span) -> String {
+        let src = self.codemap.source_span(span);
+        if let Some(inner) = src.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            if !inner.starts_with('\'') && !inner.contains(['"', '\\']) {
+                return format!("\"{inner}\"");
+            }
+        }
+        src.to_owned()
+    }
+
+    fn target(&mut self, x: &AstAssignTarget, top: bool) -> String {
+        match &x.node {
+            AssignTarget::Tuple(xs) => {
+                let mut res = String::new();
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        res.push_str(", ");
+                    }
+                    res.push_str(&self.target(x, false));
+                }
+                if xs.len() == 1 {
+                    res.push(',');
+                }
+                if top && !xs.is_empty() {
+                    res
+                } else {
+                    format!("({res})")
+                }
+            }
+            AssignTarget::Index(e_i) => {
+                let (e, i) = &**e_i;
+                format!(
+                    "{}[{}]",
+                    self.expr(e, Prec::Primary, 0),
+                    self.expr(i, Prec::Test, 0)
+                )
+            }
+            AssignTarget::Dot(e, name) => {
+                format!("{}.{}", self.expr(e, Prec::Primary, 0), name.node)
+            }
+            AssignTarget::Identifier(name) => name.node.ident.clone(),
+        }
+    }
+
+    /// Expression in statement position, where a tuple does not need parentheses.
+    fn expr_top(&mut self, x: &AstExpr, indent: usize) -> String {
+        let res = self.expr(x, Prec::Test, indent);
+        match &x.node {
+            Expr::Tuple(xs) if xs.len() > 1 && !res.contains('\n') => {
+                res[1..res.len() - 1].to_owned()
+            }
+            _ => res,
+        }
+    }
+
+    fn expr(&mut self, x: &AstExpr, prec: Prec, indent: usize) -> String {
+        let res = self.expr_unparenthesized(x, indent);
+        if Prec::of(&x.node) < prec {
+            format!("({res})")
+        } else {
+            res
+        }
+    }
+
+    fn expr_unparenthesized(&mut self, x: &AstExpr, indent: usize) -> String {
+        match &x.node {
+            Expr::Tuple(xs) => {
+                let items: Vec<Item> = xs.iter().map(Item::Expr).collect();
+                self.collection("(", &items, ")", x.span, Separator::TupleComma, indent)
+            }
+            Expr::List(xs) => {
+                let items: Vec<Item> = xs.iter().map(Item::Expr).collect();
+                let inner = Span::new(x.span.begin() + 1, x.span.end() - 1);
+                self.collection("[", &items, "]", inner, Separator::Comma, indent)
+            }
+            Expr::Dict(xs) => {
+                let items: Vec<Item> = xs.iter().map(|(k, v)| Item::Entry(k, v)).collect();
+                let inner = Span::new(x.span.begin() + 1, x.span.end() - 1);
+                self.collection("{", &items, "}", inner, Separator::Comma, indent)
+            }
+            Expr::Set(xs) => {
+                let items: Vec<Item> = xs.iter().map(Item::Expr).collect();
+                let inner = Span::new(x.span.begin() + 1, x.span.end() - 1);
+                self.collection("{", &items, "}", inner, Separator::Comma, indent)
+            }
+            Expr::ListComprehension(body, for_, clauses) => {
+                let items = comprehension_items(Item::Expr(body), for_, clauses);
+                let inner = Span::new(x.span.begin() + 1, x.span.end() - 1);
+                self.collection("[", &items, "]", inner, Separator::Space, indent)
+            }
+            Expr::DictComprehension(k_v, for_, clauses) => {
+                let (k, v) = &**k_v;
+                let items = comprehension_items(Item::Entry(k, v), for_, clauses);
+                let inner = Span::new(x.span.begin() + 1, x.span.end() - 1);
+                self.collection("{", &items, "}", inner, Separator::Space, indent)
+            }
+            Expr::Call(f, args) => {
+                let open = self.skip_trivia(f.span.end());
+                let items: Vec<Item> = args.args.iter().map(Item::Arg).collect();
+                let inner = Span::new(open + 1, x.span.end() - 1);
+                format!(
+                    "{}{}",
+                    self.expr(f, Prec::Primary, indent),
+                    self.collection("(", &items, ")", inner, Separator::Comma, indent)
+                )
+            }
+            Expr::Dot(e, name) => format!("{}.{}", self.expr(e, Prec::Primary, indent), name.node),
+            Expr::Index(e_i) => {
+                let (e, i) = &**e_i;
+                format!(
+                    "{}[{}]",
+                    self.expr(e, Prec::Primary, indent),
+                    self.expr(i, Prec::Test, indent)
+                )
+            }
+            Expr::Index2(a_i0_i1) => {
+                let (a, i0, i1) = &**a_i0_i1;
+                format!(
+                    "{}[{}, {}]",
+                    self.expr(a, Prec::Primary, indent),
+                    self.expr(i0, Prec::Test, indent),
+                    self.expr(i1, Prec::Test, indent)
+                )
+            }
+            Expr::Slice(e, i1, i2, i3) => {
+                let mut res = self.expr(e, Prec::Primary, indent);
+                res.push('[');
+                if let Some(i1) = i1 {
+                    res.push_str(&self.expr(i1, Prec::Test, indent));
+                }
+                res.push(':');
+                if let Some(i2) = i2 {
+                    res.push_str(&self.expr(i2, Prec::Test, indent));
+                }
+                if let Some(i3) = i3 {
+                    res.push(':');
+                    res.push_str(&self.expr(i3, Prec::Test, indent));
+                }
+                res.push(']');
+                res
+            }
+            Expr::Identifier(ident) => ident.node.ident.clone(),
+            Expr::Lambda(LambdaP { params, body, .. }) => {
+                let flat = mem::replace(&mut self.flat, true);
+                let params: Vec<String> = params.iter().map(|p| self.param(p, indent)).collect();
+                self.flat = flat;
+                let body = self.expr(body, Prec::Test, indent);
+                if params.is_empty() {
+                    format!("lambda: {body}")
+                } else {
+                    format!("lambda {}: {}", params.join(", "), body)
+                }
+            }
+            Expr::Literal(AstLiteral::String(s)) => self.string_literal(s.span),
+            Expr::Literal(AstLiteral::Ellipsis) => "...".to_owned(),
+            Expr::Literal(AstLiteral::Int(_) | AstLiteral::Float(_) | AstLiteral::Bytes(_))
+            | Expr::FString(_) => self.codemap.source_span(x.span).to_owned(),
+            Expr::Not(e) => format!("not {}", self.expr(e, Prec::Not, indent)),
+            Expr::Minus(e) => format!("-{}", self.expr(e, Prec::Unary, indent)),
+            Expr::Plus(e) => format!("+{}", self.expr(e, Prec::Unary, indent)),
+            Expr::BitNot(e) => format!("~{}", self.expr(e, Prec::Unary, indent)),
+            Expr::Op(l, op, r) => {
+                let (_, lhs, rhs) = Prec::bin_op(*op);
+                format!(
+                    "{}{}{}",
+                    self.expr(l, lhs, indent),
+                    op,
+                    self.expr(r, rhs, indent)
+                )
+            }
+            Expr::If(cond_v1_v2) => {
+                let (cond, v1, v2) = &**cond_v1_v2;
+                format!(
+                    "{} if {} else {}",
+                    self.expr(v1, Prec::Or, indent),
+                    self.expr(cond, Prec::Or, indent),
+                    self.expr(v2, Prec::Test, indent)
+                )
+            }
+        }
+    }
+}
+
+fn comprehension_items<'b>(
+    body: Item<'b>,
+    for_: &'b ForClause,
+    clauses: &'b [Clause],
+) -> Vec<Item<'b>> {
+    let mut items = vec![body, Item::For(for_)];
+    items.extend(clauses.iter().map(|c| match c {
+        Clause::For(x) => Item::For(x),
+        Clause::If(x) => Item::If(x),
+    }));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn format(code: &str) -> String {
+        let module =
+            AstModule::parse("test.star", code.to_owned(), &Dialect::AllOptionsInternal).unwrap();
+        let formatted = module.format().unwrap();
+        // Formatting is idempotent.
+        let again = AstModule::parse("test.star", formatted.clone(), &Dialect::AllOptionsInternal)
+            .unwrap()
+            .format()
+            .unwrap();
+        assert_eq!(formatted, again);
+        formatted
+    }
+
+    #[test]
+    fn test_format_simple() {
+        assert_eq!(format("x=1+2*3\n"), "x = 1 + 2 * 3\n");
+        assert_eq!(format("x = (1 + 2) * 3"), "x = (1 + 2) * 3\n");
+        assert_eq!(format("a,b = 1,2\n"), "a, b = 1, 2\n");
+        assert_eq!(format("x = 'a'\ny = 'b\"'\n"), "x = \"a\"\ny = 'b\"'\n");
+        assert_eq!(format("f(a,b=1,*c,**d)"), "f(a, b = 1, *c, **d)\n");
+        assert_eq!(format("x = not (a or b)"), "x = not (a or b)\n");
+        assert_eq!(
+            format("x = (lambda: 1) if a else 0x10"),
+            "x = (lambda: 1) if a else 0x10\n"
+        );
+    }
+
+    #[test]
+    fn test_format_blocks() {
+        assert_eq!(
+            format(
+                "def f(x, y=1):\n  if x:\n    return y\n  elif y: pass\n  else:\n    return (1,)\n"
+            ),
+            "def f(x, y = 1):\n    if x:\n        return y\n    elif y:\n        pass\n    else:\n        return (1,)\n"
+        );
+        assert_eq!(
+            format("for k,v in d.items():\n\n\n  print(k)\n\n\n\n  print(v)\n"),
+            "for k, v in d.items():\n    print(k)\n\n    print(v)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_multiline() {
+        assert_eq!(
+            format("x = [1,\n  2]\ny = foo([\n  3,\n])\n"),
+            "x = [\n    1,\n    2,\n]\ny = foo([\n    3,\n])\n"
+        );
+        assert_eq!(
+            format("x = {'a': 1, 'b': 2}\n"),
+            "x = {\"a\": 1, \"b\": 2}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let code = r#"
+# Header.
+
+load("a.star", "b")  # trailing
+
+def f():  # on def
+    # leading
+    x = [
+        1,  # one
+        # before two
+        2,
+    ]
+    return x
+    # end of f
+
+# end of file
+"#;
+        assert_eq!(format(code), code.trim_start());
+    }
+}
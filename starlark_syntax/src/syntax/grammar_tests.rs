@@ -281,6 +281,25 @@ fn test_comprehension() {
     );
 }
 
+#[test]
+fn test_set_literal() {
+    assert_eq!(parse("x = {1, 2,}"), "x = {1, 2}\n");
+    assert_eq!(parse("x = {y}"), "x = {y}\n");
+    assert_eq!(parse("x = {}"), "x = {}\n");
+    let err = AstModule::parse("x", "x = {1}".to_owned(), &Dialect::Standard).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("set literals are not allowed in this dialect")
+    );
+}
+
+#[test]
+fn test_bytes_literal() {
+    assert_eq!(parse(r#"x = b"a\n\xff""#), "x = b\"a\\n\\xff\"\n");
+    assert_eq!(parse("x = rb'\\\\'"), "x = b\"\\\\\\\\\"\n");
+    assert_eq!(parse("x = b'ab' + b"), "x = (b\"ab\" + b)\n");
+}
+
 #[test]
 fn test_lambda() {
     assert_eq!(parse("x = lambda y: y + 1"), "x = (lambda y: (y + 1))\n");
@@ -373,6 +392,39 @@ fn test_test_list_in_index_expr() {
     parse_fail("list_in_index_expr", "x[1, 2] = 3");
 }
 
+#[test]
+fn test_error_codes() {
+    fn code(program: &str) -> String {
+        AstModule::parse("x", program.to_owned(), &Dialect::AllOptionsInternal)
+            .unwrap_err()
+            .code()
+            .to_string()
+    }
+    assert_eq!("E0101", code("if x:\n    y\n  z\n"));
+    assert_eq!("E0107", code("class = 1"));
+    assert_eq!("E0202", code("x = )"));
+    assert_eq!("E0203", code("if x:"));
+    assert_eq!("E0205", code("[x or y] = 1"));
+    assert_eq!("E0209", code("load('a.bzl')"));
+}
+
+#[test]
+fn test_parse_all_reports_all_errors() {
+    let program = "def f(x, x):\n    pass\ndef g():\n    load('a.bzl', 'b')\nf(a=1, a=2)\n";
+    let errors = AstModule::parse_all("x", program.to_owned(), &Dialect::Standard).unwrap_err();
+    let messages: Vec<String> = errors
+        .iter()
+        .map(|e| e.without_diagnostic().to_string())
+        .collect();
+    assert_eq!(3, messages.len(), "{messages:?}");
+    assert!(messages[0].contains("duplicated parameter name"));
+    assert!(messages[1].contains("`load`"));
+    assert!(messages[2].contains("repeated named argument"));
+    // `parse` stops at one of them.
+    let err = AstModule::parse("x", program.to_owned(), &Dialect::Standard).unwrap_err();
+    assert!(messages.contains(&err.without_diagnostic().to_string()));
+}
+
 pub fn parse(program: &str) -> String {
     parse_ast(program).statement.to_string()
 }
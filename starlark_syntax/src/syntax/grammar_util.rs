@@ -24,6 +24,7 @@ use crate::codemap::Spanned;
 use crate::dot_format_parser::FormatConv;
 use crate::dot_format_parser::FormatParser;
 use crate::dot_format_parser::FormatToken;
+use crate::error::ErrorCode;
 use crate::eval_exception::EvalException;
 use crate::lexer::TokenFString;
 use crate::lexer::lex_exactly_one_identifier;
@@ -70,6 +71,19 @@ enum GrammarUtilError {
     LoadRequiresAtLeastTwoArguments,
 }
 
+impl From<GrammarUtilError> for crate::Error {
+    fn from(e: GrammarUtilError) -> Self {
+        let code = ErrorCode::new(match e {
+            GrammarUtilError::InvalidLhs => 205,
+            GrammarUtilError::InvalidModifyLhs => 206,
+            GrammarUtilError::TypeAnnotationOnAssignOp => 207,
+            GrammarUtilError::TypeAnnotationOnTupleAssign => 208,
+            GrammarUtilError::LoadRequiresAtLeastTwoArguments => 209,
+        });
+        crate::Error::new_other(e).with_code(code)
+    }
+}
+
 /// Ensure we produce normalised Statements, rather than singleton Statements
 pub fn statements(mut xs: Vec<AstStmt>, begin: usize, end: usize) -> AstStmt {
     if xs.len() == 1 {
@@ -93,7 +107,7 @@ pub fn check_assign(codemap: &CodeMap, x: AstExpr) -> Result<AstAssignTarget, Ev
                 payload: (),
             })),
             _ => {
-                return Err(EvalException::new_anyhow(
+                return Err(EvalException::new(
                     GrammarUtilError::InvalidLhs.into(),
                     x.span,
                     codemap,
@@ -114,7 +128,7 @@ pub fn check_assignment(
         // for augmented assignment, Starlark doesn't allow tuple/list
         match &lhs.node {
             Expr::Tuple(_) | Expr::List(_) => {
-                return Err(EvalException::new_anyhow(
+                return Err(EvalException::new(
                     GrammarUtilError::InvalidModifyLhs.into(),
                     lhs.span,
                     codemap,
@@ -133,7 +147,7 @@ pub fn check_assignment(
             None
         };
         if let Some(err) = err {
-            return Err(EvalException::new_anyhow(err.into(), ty.span, codemap));
+            return Err(EvalException::new(err.into(), ty.span, codemap));
         }
     }
     Ok(match op {
@@ -147,7 +161,7 @@ pub fn check_assignment(
 }
 
 pub(crate) fn check_load_0(module: AstString, parser_state: &mut ParserState) -> Stmt {
-    parser_state.errors.push(EvalException::new_anyhow(
+    parser_state.errors.push(EvalException::new(
         GrammarUtilError::LoadRequiresAtLeastTwoArguments.into(),
         module.span,
         parser_state.codemap,
@@ -273,8 +287,17 @@ enum DialectError {
     Types,
 }
 
+impl From<DialectError> for crate::Error {
+    fn from(e: DialectError) -> Self {
+        let code = ErrorCode::new(match e {
+            DialectError::Types => 210,
+        });
+        crate::Error::new_other(e).with_code(code)
+    }
+}
+
 fn err<T>(codemap: &CodeMap, span: Span, err: DialectError) -> Result<T, EvalException> {
-    Err(EvalException::new_anyhow(err.into(), span, codemap))
+    Err(EvalException::new(err.into(), span, codemap))
 }
 
 pub(crate) fn dialect_check_type(
@@ -25,6 +25,7 @@ use crate::codemap::Span;
 static LINT_SUPPRESISON_PREFIX: &str = "starlark-lint-disable ";
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 struct SuppressionInfo {
     /// The original span of the comment token containing the suppression
     token_span: Span,
@@ -34,6 +35,7 @@ struct SuppressionInfo {
     suppress_next_line: bool,
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "ast_cache", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct LintSuppressions {
     /// A map from lint short names to spans where they are suppressed
     suppressions: HashMap<String, Vec<SuppressionInfo>>,
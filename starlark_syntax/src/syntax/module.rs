@@ -17,8 +17,10 @@
 
 use std::collections::HashMap;
 use std::fmt::Write;
+#[cfg(feature = "fs")]
 use std::fs;
 use std::mem;
+#[cfg(feature = "fs")]
 use std::path::Path;
 
 use derivative::Derivative;
@@ -30,6 +32,7 @@ use crate::codemap::FileSpan;
 use crate::codemap::Pos;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
+use crate::error::ErrorCode;
 use crate::eval_exception::EvalException;
 use crate::lexer::Lexer;
 use crate::lexer::Token;
@@ -72,8 +75,9 @@ fn parse_error_add_span(
     pos: usize,
     codemap: &CodeMap,
 ) -> crate::Error {
-    let (message, span) = match err {
+    let (code, message, span) = match err {
         lu::ParseError::InvalidToken { location } => (
+            201,
             "Parse error: invalid token".to_owned(),
             Span::new(Pos::new(location as u32), Pos::new(location as u32)),
         ),
@@ -81,6 +85,7 @@ fn parse_error_add_span(
             token: (x, t, y),
             expected,
         } => (
+            202,
             format!(
                 "Parse error: unexpected {} here, expected {}",
                 t,
@@ -89,10 +94,12 @@ fn parse_error_add_span(
             Span::new(Pos::new(x as u32), Pos::new(y as u32)),
         ),
         lu::ParseError::UnrecognizedEOF { .. } => (
+            203,
             "Parse error: unexpected end of file".to_owned(),
             Span::new(Pos::new(pos as u32), Pos::new(pos as u32)),
         ),
         lu::ParseError::ExtraToken { token: (x, t, y) } => (
+            204,
             format!("Parse error: extraneous token {t}"),
             Span::new(Pos::new(x as u32), Pos::new(y as u32)),
         ),
@@ -104,6 +111,7 @@ fn parse_error_add_span(
         span,
         codemap,
     )
+    .with_code(ErrorCode::new(code))
 }
 
 /// A representation of a Starlark module abstract syntax tree.
@@ -125,7 +133,7 @@ pub struct AstModule {
     pub(crate) typecheck: bool,
     /// Lint issues suppressed in this module using inline comments of shape
     /// # starlark-lint-disable <ISSUE_NAME>, <ISSUE_NAME>, ...
-    lint_suppressions: LintSuppressions,
+    pub(crate) lint_suppressions: LintSuppressions,
 }
 
 /// This trait is not exported as public API of starlark.
@@ -158,14 +166,15 @@ impl AstModuleFields for AstModule {
 }
 
 impl AstModule {
+    /// Validate the parsed statement, adding to the recoverable errors found by the parser.
     fn create(
         codemap: CodeMap,
         statement: AstStmt,
         dialect: &Dialect,
         typecheck: bool,
         lint_suppressions: LintSuppressions,
-    ) -> crate::Result<AstModule> {
-        let mut errors = Vec::new();
+        mut errors: Vec<EvalException>,
+    ) -> Result<AstModule, Vec<EvalException>> {
         validate_module(
             &statement,
             &mut ParserState {
@@ -174,9 +183,8 @@ impl AstModule {
                 errors: &mut errors,
             },
         );
-        // We need the first error, so we don't use `.pop()`.
-        if let Some(err) = errors.into_iter().next() {
-            return Err(err.into_error());
+        if !errors.is_empty() {
+            return Err(errors);
         }
         Ok(AstModule {
             codemap,
@@ -188,6 +196,9 @@ impl AstModule {
     }
 
     /// Parse a file stored on disk. For details see [`parse`](AstModule::parse).
+    ///
+    /// Requires the `fs` feature, which is enabled by default.
+    #[cfg(feature = "fs")]
     pub fn parse_file(path: &Path, dialect: &Dialect) -> crate::Result<Self> {
         let content = fs::read_to_string(path).map_err(anyhow::Error::new)?;
         Self::parse(&path.to_string_lossy(), content, dialect)
@@ -210,6 +221,53 @@ impl AstModule {
     /// assert_eq!(span.to_string(), "filename:2:11");
     /// ```
     pub fn parse(filename: &str, content: String, dialect: &Dialect) -> crate::Result<Self> {
+        // We need the first error, so we don't use `.pop()`.
+        Self::parse_impl(filename, content, dialect)
+            .map_err(|errors| errors.into_iter().next().unwrap().into_error())
+    }
+
+    /// Like [`parse`](AstModule::parse), but on failure returns every syntax error found,
+    /// in source order, rather than only the first one.
+    ///
+    /// Errors the parser can recover from, like duplicate parameter names or a misplaced
+    /// `load`, are all reported. After an error which stops the parser, like an unclosed
+    /// bracket, no more errors are found.
+    ///
+    /// ```
+    /// use starlark_syntax::syntax::AstModule;
+    /// use starlark_syntax::syntax::Dialect;
+    ///
+    /// let program = "def f(x, x): pass\ndef g(y, y): pass\n";
+    /// let errors =
+    ///     AstModule::parse_all("filename", program.to_owned(), &Dialect::Standard).unwrap_err();
+    /// assert_eq!(errors.len(), 2);
+    /// ```
+    pub fn parse_all(
+        filename: &str,
+        content: String,
+        dialect: &Dialect,
+    ) -> Result<Self, Vec<crate::Error>> {
+        Self::parse_impl(filename, content, dialect).map_err(|errors| {
+            let mut errors: Vec<crate::Error> =
+                errors.into_iter().map(EvalException::into_error).collect();
+            errors.sort_by_key(|e| e.span().map(|s| s.span.begin()));
+            errors
+        })
+    }
+
+    /// On error, the returned vector is never empty.
+    fn parse_impl(
+        filename: &str,
+        content: String,
+        dialect: &Dialect,
+    ) -> Result<Self, Vec<EvalException>> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "starlark::parse",
+            filename,
+            statements = tracing::field::Empty
+        )
+        .entered();
         let typecheck = content.contains("@starlark-rust: typecheck");
         let codemap = CodeMap::new(filename.to_owned(), content);
         let lexer = Lexer::new(codemap.source(), dialect, codemap.dupe());
@@ -218,7 +276,7 @@ impl AstModule {
         // Keep track of block of comments, used for accumulating lint suppressions
         let mut in_comment_block = false;
         let mut errors = Vec::new();
-        match StarlarkParser::new().parse(
+        let res = StarlarkParser::new().parse(
             &mut ParserState {
                 codemap: &codemap,
                 dialect,
@@ -239,20 +297,29 @@ impl AstModule {
                     true
                 }
             }),
-        ) {
+        );
+        match res {
             Ok(v) => {
-                if let Some(err) = errors.into_iter().next() {
-                    return Err(err.into_error());
-                }
-                Ok(AstModule::create(
+                #[cfg(feature = "tracing")]
+                span.record(
+                    "statements",
+                    crate::syntax::top_level_stmts::top_level_stmts(&v).len(),
+                );
+                AstModule::create(
                     codemap,
                     v,
                     dialect,
                     typecheck,
                     lint_suppressions_builder.build(),
-                )?)
+                    errors,
+                )
+            }
+            Err(p) => {
+                // The error which stopped the parser is the one `parse` reports.
+                let error = parse_error_add_span(p, codemap.source().len(), &codemap);
+                errors.insert(0, EvalException::new_unknown_span(error));
+                Err(errors)
             }
-            Err(p) => Err(parse_error_add_span(p, codemap.source().len(), &codemap)),
         }
     }
 
@@ -0,0 +1,298 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Source text which the AST does not represent: comments, blank lines and trailing commas.
+//!
+//! Tools which rewrite or document code, like formatters, codemods and doc extractors,
+//! can use [`AstModule::with_trivia`] rather than lexing the file again.
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::dialect::Dialect;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+use crate::syntax::AstModule;
+
+/// What a piece of [`Trivia`] is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// A comment. The span includes the `#`, the text does not.
+    Comment {
+        /// Text after the `#`, without the line terminator.
+        text: String,
+        /// Nothing but whitespace precedes the comment on its line.
+        own_line: bool,
+    },
+    /// Consecutive lines containing only whitespace, outside of string literals.
+    /// The span covers the lines, including their line terminators.
+    BlankLines {
+        /// Number of lines.
+        count: usize,
+    },
+    /// A comma directly followed by a closing bracket, like the last one in `[1, 2,]`.
+    TrailingComma,
+}
+
+/// Source text without a representation in the AST.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    /// Location in the module.
+    pub span: Span,
+    /// What the trivia is.
+    pub kind: TriviaKind,
+}
+
+/// A parsed module along with its trivia, obtained by [`AstModule::with_trivia`].
+pub struct AstModuleWithTrivia<'a> {
+    ast: &'a AstModule,
+    /// Sorted by position, and not overlapping.
+    trivia: Vec<Trivia>,
+}
+
+impl AstModule {
+    /// A lossless view of the module: the AST, along with the comments, blank lines and
+    /// trailing commas which the AST drops.
+    ///
+    /// ```
+    /// use starlark_syntax::syntax::AstModule;
+    /// use starlark_syntax::syntax::Dialect;
+    ///
+    /// let program = "# Greet someone.\n# Politely.\ndef greet(name):\n    pass\n";
+    /// let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+    /// let ast = ast.with_trivia();
+    /// let def = ast.ast().stmt_locations()[0].span;
+    /// assert_eq!(ast.comments_before(def), vec![" Greet someone.", " Politely."]);
+    /// ```
+    pub fn with_trivia(&self) -> AstModuleWithTrivia<'_> {
+        AstModuleWithTrivia {
+            ast: self,
+            trivia: collect_trivia(&self.codemap, &self.dialect),
+        }
+    }
+}
+
+impl<'a> AstModuleWithTrivia<'a> {
+    /// The module itself.
+    pub fn ast(&self) -> &'a AstModule {
+        self.ast
+    }
+
+    /// All the trivia, in source order.
+    pub fn trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
+    /// The trivia contained entirely within `span`, in source order.
+    pub fn trivia_in(&self, span: Span) -> &[Trivia] {
+        let begin = self
+            .trivia
+            .partition_point(|x| x.span.begin() < span.begin());
+        let end = self.trivia.partition_point(|x| x.span.end() <= span.end());
+        &self.trivia[begin..end.max(begin)]
+    }
+
+    /// The texts of the block of full-line comments on the lines directly above the line
+    /// `span` starts on, in source order. A blank line ends the block.
+    pub fn comments_before(&self, span: Span) -> Vec<&str> {
+        let codemap = &self.ast.codemap;
+        let mut line = codemap.find_line(span.begin());
+        let end = self
+            .trivia
+            .partition_point(|x| x.span.end() <= span.begin());
+        let mut res = Vec::new();
+        for x in self.trivia[..end].iter().rev() {
+            match &x.kind {
+                TriviaKind::Comment {
+                    text,
+                    own_line: true,
+                } if line > 0 && codemap.find_line(x.span.begin()) == line - 1 => {
+                    res.push(text.as_str());
+                    line -= 1;
+                }
+                _ => break,
+            }
+        }
+        res.reverse();
+        res
+    }
+}
+
+/// Lex the source again to find the trivia. The module has been parsed, so lexing succeeds.
+pub(crate) fn collect_trivia(codemap: &CodeMap, dialect: &Dialect) -> Vec<Trivia> {
+    let mut tokens = Vec::new();
+    // Tokens spanning several lines, i.e. multi-line strings, which can contain blank lines.
+    let mut multiline = Vec::new();
+    for token in Lexer::new(codemap.source(), dialect, codemap.dupe()) {
+        let Ok((start, token, end)) = token else {
+            break;
+        };
+        let span = Span::new(Pos::new(start as u32), Pos::new(end as u32));
+        if token != Token::Newline && codemap.source_span(span).contains('\n') {
+            multiline.push(span);
+        }
+        tokens.push((span, token));
+    }
+
+    let mut res = Vec::new();
+    let mut comma: Option<Span> = None;
+    for (span, token) in tokens {
+        match token {
+            Token::Comment(text) => {
+                let line_begin = codemap.line_span(codemap.find_line(span.begin())).begin();
+                let prefix = codemap.source_span(Span::new(line_begin, span.begin()));
+                res.push(Trivia {
+                    span,
+                    kind: TriviaKind::Comment {
+                        text,
+                        own_line: prefix.trim().is_empty(),
+                    },
+                });
+                // Comments don't separate a comma from the closing bracket.
+                continue;
+            }
+            Token::ClosingRound | Token::ClosingSquare | Token::ClosingCurly => {
+                if let Some(comma) = comma {
+                    res.push(Trivia {
+                        span: comma,
+                        kind: TriviaKind::TrailingComma,
+                    });
+                }
+            }
+            _ => {}
+        }
+        comma = (token == Token::Comma).then_some(span);
+    }
+
+    let mut line = 0;
+    let mut multiline = multiline.into_iter().peekable();
+    while let Some(line_span) = codemap.line_span_opt(line) {
+        line += 1;
+        while multiline
+            .peek()
+            .is_some_and(|x| x.end() <= line_span.begin())
+        {
+            multiline.next();
+        }
+        let in_string = multiline
+            .peek()
+            .is_some_and(|x| x.begin() < line_span.begin());
+        // The empty line after a final line terminator is not a line.
+        if line_span.begin() == line_span.end() || in_string || !codemap.source_span(line_span).trim().is_empty() {
+            continue;
+        }
+        match res.last_mut() {
+            Some(Trivia {
+                span,
+                kind: TriviaKind::BlankLines { count },
+            }) if span.end() == line_span.begin() => {
+                *span = span.merge(line_span);
+                *count += 1;
+            }
+            _ => res.push(Trivia {
+                span: line_span,
+                kind: TriviaKind::BlankLines { count: 1 },
+            }),
+        }
+    }
+
+    res.sort_by_key(|x| x.span.begin());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codemap::Pos;
+    use crate::codemap::Span;
+    use crate::syntax::grammar_tests;
+    use crate::syntax::trivia::TriviaKind;
+
+    fn trivia(program: &str) -> Vec<String> {
+        let ast = grammar_tests::parse_ast(program);
+        let ast = ast.with_trivia();
+        ast.trivia()
+            .iter()
+            .map(|x| {
+                let loc = ast.ast().codemap.resolve_span(x.span);
+                match &x.kind {
+                    TriviaKind::Comment { text, own_line } => {
+                        format!("{loc} comment {text:?} own_line={own_line}")
+                    }
+                    TriviaKind::BlankLines { count } => format!("{loc} blank {count}"),
+                    TriviaKind::TrailingComma => format!("{loc} trailing comma"),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trivia() {
+        let program = r#"
+# Top.
+x = [
+    1,  # One.
+    # Two.
+    2,
+]
+
+y = """
+
+"""
+f(1, 2)
+
+
+z = (1,)
+"#;
+        assert_eq!(
+            trivia(program),
+            vec![
+                "1:1-2:1 blank 1",
+                "2:1-7 comment \" Top.\" own_line=true",
+                "4:9-15 comment \" One.\" own_line=false",
+                "5:5-11 comment \" Two.\" own_line=true",
+                "6:6-7 trailing comma",
+                "8:1-9:1 blank 1",
+                "13:1-15:1 blank 2",
+                "15:7-8 trailing comma",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_before() {
+        let program = r#"
+# Not attached.
+
+# Attached.
+# Also attached.
+def f():  # Same line.
+    pass
+"#;
+        let ast = grammar_tests::parse_ast(program);
+        let ast = ast.with_trivia();
+        let def = ast.ast().stmt_locations()[0].span;
+        assert_eq!(
+            ast.comments_before(def),
+            vec![" Attached.", " Also attached."]
+        );
+        let start = Span::new(Pos::new(0), Pos::new(0));
+        assert!(ast.comments_before(start).is_empty());
+        assert_eq!(ast.trivia_in(def).len(), 1);
+    }
+}
@@ -18,6 +18,7 @@
 use crate::codemap::CodeMap;
 use crate::codemap::Spanned;
 use crate::diagnostic::WithDiagnostic;
+use crate::error::ErrorCode;
 use crate::slice_vec_ext::SliceExt;
 use crate::syntax::ast::AstExprP;
 use crate::syntax::ast::AstIdentP;
@@ -42,7 +43,14 @@ pub enum TypeExprUnpackError {
 
 impl From<TypeExprUnpackError> for crate::Error {
     fn from(e: TypeExprUnpackError) -> Self {
-        crate::Error::new_other(e)
+        let code = ErrorCode::new(match e {
+            TypeExprUnpackError::InvalidType(_) => 211,
+            TypeExprUnpackError::EmptyListInType => 212,
+            TypeExprUnpackError::DotInType => 213,
+            TypeExprUnpackError::ExpectingPath => 214,
+            TypeExprUnpackError::DotTypeBan(_) => 215,
+        });
+        crate::Error::new_other(e).with_code(code)
     }
 }
 
@@ -226,6 +234,7 @@ impl<'a, P: AstPayload> TypeExprUnpackP<'a, P> {
             }
             ExprP::Literal(AstLiteral::Int(_)) => err("int"),
             ExprP::Literal(AstLiteral::Float(_)) => err("float"),
+            ExprP::Literal(AstLiteral::Bytes(_)) => err("bytes"),
             ExprP::Literal(AstLiteral::Ellipsis) => Ok(Spanned {
                 span,
                 node: TypeExprUnpackP::Ellipsis,
@@ -262,6 +271,7 @@ impl<'a, P: AstPayload> TypeExprUnpackP<'a, P> {
                 }
             }
             ExprP::Dict(..) => err("dict"),
+            ExprP::Set(..) => err("set"),
             ExprP::ListComprehension(..) => err("list comprehension"),
             ExprP::DictComprehension(..) => err("dict comprehension"),
             ExprP::FString(..) => err("f-string"),
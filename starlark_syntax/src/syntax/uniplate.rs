@@ -434,6 +434,7 @@ impl<P: AstPayload> ExprP<P> {
                 f(x);
                 f(y);
             }),
+            ExprP::Set(x) => x.iter().for_each(|x| f(x)),
             ExprP::ListComprehension(x, for_, y) => {
                 for_.visit_expr(|x| f(x));
                 y.iter().for_each(|x| x.visit_expr(|x| f(x)));
@@ -534,6 +535,7 @@ impl<P: AstPayload> ExprP<P> {
                 f(x);
                 f(y);
             }),
+            ExprP::Set(x) => x.iter_mut().for_each(|x| f(x)),
             ExprP::ListComprehension(x, for_, y) => {
                 for_.visit_expr_mut(|x| f(x));
                 y.iter_mut().for_each(|x| x.visit_expr_mut(|x| f(x)));
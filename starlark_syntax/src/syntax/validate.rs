@@ -162,6 +162,11 @@ pub(crate) fn validate_module(stmt: &AstStmt, parser_state: &mut ParserState) {
                 }
                 validate_params(params, parser_state);
             }
+            Expr::Set(..) => {
+                if !parser_state.dialect.enable_set_literals {
+                    parser_state.error(x.span, "set literals are not allowed in this dialect");
+                }
+            }
             _ => {}
         }
         x.node.visit_expr(|x| expr(x, parser_state));